@@ -0,0 +1,106 @@
+import argparse
+import sys
+from dataclasses import dataclass
+from typing import List, Tuple
+
+from cache.cache import Cache
+from utils.files import read_lines
+
+MEMORY_ACCESS_TIME = 100  # ns, main memory's configured latency (the System Configuration table's figure)
+
+@dataclass
+class AmatLevel:
+    """One cache level's part in the AMAT formula: its hit time and local miss rate so far"""
+    name: str
+    hit_time: int
+    hits: int
+    misses: int
+
+    @property
+    def accesses(self) -> int:
+        return self.hits + self.misses
+
+    @property
+    def miss_rate(self) -> float:
+        """Misses over the accesses that reached this level; a level nothing reached yet counts as never missing"""
+        return self.misses / self.accesses if self.accesses else 0.0
+
+def amat_levels(cache) -> List[AmatLevel]:
+    """The cache levels from cache down, skipping anything between them (such as a write buffer)"""
+    levels: List[AmatLevel] = []
+    seen = []
+    while cache is not None and all(cache is not level for level in seen):
+        seen.append(cache)
+        if isinstance(cache, Cache):
+            stats = cache.get_performance_stats()
+            levels.append(AmatLevel(cache._name, cache._access_time, stats['hits'], stats['misses']))
+        cache = getattr(cache, '_next_level', None)
+    return levels
+
+def compute_amat(levels: List[AmatLevel], memory_time: int = MEMORY_ACCESS_TIME) -> float:
+    """AMAT = hit time + miss rate x (AMAT of the level below), with main memory at the bottom"""
+    amat = float(memory_time)
+    for level in reversed(levels):
+        amat = level.hit_time + level.miss_rate * amat
+    return amat
+
+def formula_terms(levels: List[AmatLevel], memory_time: int = MEMORY_ACCESS_TIME) -> Tuple[str, str]:
+    """The right-hand side of the formula in symbols and with this run's numbers substituted"""
+    def group(term: str) -> str:
+        return f"({term})" if " + " in term else term
+
+    symbols, numbers = "Tmem", str(memory_time)
+    for level in reversed(levels):
+        short = level.name.replace("Cache", "")
+        symbols = f"T{short} + M{short} x {group(symbols)}"
+        numbers = f"{level.hit_time} + {level.miss_rate:.3f} x {group(numbers)}"
+    return symbols, numbers
+
+def format_formula(levels: List[AmatLevel], memory_time: int = MEMORY_ACCESS_TIME) -> List[str]:
+    """The formula in symbols, then with this run's numbers substituted, then the result"""
+    symbols, numbers = formula_terms(levels, memory_time)
+    return [f"AMAT = {symbols}", f"     = {numbers}", f"     = {compute_amat(levels, memory_time):.2f} ns"]
+
+def format_amat(levels: List[AmatLevel], memory_time: int = MEMORY_ACCESS_TIME) -> List[str]:
+    """Each level's hit time and miss rate, then the formula worked through"""
+    lines = []
+    for level in levels:
+        short = level.name.replace("Cache", "")
+        lines.append(f"{level.name}: T{short} = {level.hit_time} ns, M{short} = {level.misses}/{level.accesses} "
+                     f"= {level.miss_rate:.3f}")
+    lines.append(f"Main memory: Tmem = {memory_time} ns")
+    return lines + format_formula(levels, memory_time)
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program on the default cache hierarchy and work out its "
+                                                 "average memory access time from each level's hit time and "
+                                                 "miss rate")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--memory-time', type=int, default=MEMORY_ACCESS_TIME,
+                        help=f"Main memory latency in ns (default {MEMORY_ACCESS_TIME})")
+    args = parser.parse_args()
+
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The AMAT, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    print('\n'.join(format_amat(amat_levels(l1), args.memory_time)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
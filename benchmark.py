@@ -0,0 +1,197 @@
+import argparse
+import csv
+import glob
+import io
+import os
+import sys
+from contextlib import redirect_stdout
+from itertools import product
+from time import perf_counter
+from typing import Dict, List
+
+from isa import SimpleISA
+from locality import build_hierarchy, run_program
+from utils.logger import Logger, LogLevel
+
+BENCHMARK_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), 'benchmarks')
+
+# Miss penalties at a 10ns clock: an L1 miss waits for the L2 (30ns), an L2 miss for memory (100ns)
+L2_HIT_CYCLES = 3
+MEMORY_CYCLES = 10
+
+CSV_FIELDS = ['kernel', 'l1_size', 'l1_assoc', 'l2_size', 'l2_assoc', 'instructions',
+              'l1_hit_rate', 'l2_hit_rate', 'cycles', 'cpi']
+
+def kernels() -> Dict[str, List[str]]:
+    """Benchmark kernel name -> program lines"""
+    programs = {}
+    for path in sorted(glob.glob(os.path.join(BENCHMARK_DIR, '*.txt'))):
+        with open(path) as f:
+            programs[os.path.splitext(os.path.basename(path))[0]] = f.read().splitlines()
+    return programs
+
+def run_kernel(name: str, lines: List[str], l1_size: int, l1_assoc: int, l2_size: int, l2_assoc: int) -> Dict:
+    """Run one kernel on one cache configuration and return a CSV row
+
+    Cycles are the pipeline model's cycles plus the miss penalties, which
+    stall the whole pipeline (stores are assumed not to stall).
+    """
+    stats = run_program(lines, l1_size=l1_size, l1_assoc=l1_assoc, l2_size=l2_size, l2_assoc=l2_assoc)
+    cycles = (stats['pipeline_cycles'] + stats['l1_misses'] * L2_HIT_CYCLES
+              + stats['l2_misses'] * MEMORY_CYCLES)
+    return {
+        'kernel': name,
+        'l1_size': l1_size,
+        'l1_assoc': l1_assoc,
+        'l2_size': l2_size,
+        'l2_assoc': l2_assoc,
+        'instructions': stats['instructions'],
+        'l1_hit_rate': round(stats['l1_hit_rate'], 2),
+        'l2_hit_rate': round(stats['l2_hit_rate'], 2),
+        'cycles': cycles,
+        'cpi': round(cycles / stats['instructions'], 3) if stats['instructions'] else 0.0
+    }
+
+def sweep(l1_sizes: List[int], l1_assocs: List[int], l2_sizes: List[int], l2_assocs: List[int]) -> List[Dict]:
+    """Run every kernel on every cache configuration whose sizes divide into whole sets"""
+    rows = []
+    for name, lines in kernels().items():
+        for l1_size, l1_assoc, l2_size, l2_assoc in product(l1_sizes, l1_assocs, l2_sizes, l2_assocs):
+            if l1_size % l1_assoc or l2_size % l2_assoc:
+                continue
+            rows.append(run_kernel(name, lines, l1_size, l1_assoc, l2_size, l2_assoc))
+    return rows
+
+def to_csv(rows: List[Dict]) -> str:
+    out = io.StringIO()
+    writer = csv.DictWriter(out, fieldnames=CSV_FIELDS, lineterminator='\n')
+    writer.writeheader()
+    writer.writerows(rows)
+    return out.getvalue()
+
+# Simulator speed, as opposed to the simulated machine's: wall-clock timings of the hot paths
+
+def _best_of(repeats: int, fn) -> float:
+    """Fastest of several timed calls, in seconds"""
+    best = float('inf')
+    for _ in range(repeats):
+        start = perf_counter()
+        fn()
+        best = min(best, perf_counter() - start)
+    return best
+
+def time_step(lines: List[str], steps: int = 2000, fast: bool = False, repeats: int = 3) -> float:
+    """Seconds per SimpleISA.execute_step over at most `steps` instructions of a program"""
+    logger = Logger()
+    executed = 0
+    def run():
+        nonlocal executed
+        memory, l1, _ = build_hierarchy()
+        isa = SimpleISA(memory=memory, cache=l1)
+        with redirect_stdout(io.StringIO()):
+            isa.load_program(lines)
+        isa.running = True
+        logger.fast_mode = fast
+        try:
+            with redirect_stdout(io.StringIO()):
+                for executed in range(1, steps + 1):
+                    if not isa.execute_step():
+                        break
+        finally:
+            logger.fast_mode = False
+    return _best_of(repeats, run) / executed
+
+def time_cache_access(accesses: int = 2000, fast: bool = False, repeats: int = 3) -> Dict[str, float]:
+    """Seconds per L1 read and per L1 write over a repeating 48-word address pattern"""
+    logger = Logger()
+    memory, l1, _ = build_hierarchy()
+    addresses = [(i * 7) % 48 for i in range(accesses)]
+    def reads():
+        for addr in addresses:
+            l1.read(addr)
+    def writes():
+        for addr in addresses:
+            l1.write(addr, addr)
+    logger.fast_mode = fast
+    try:
+        with redirect_stdout(io.StringIO()):
+            return {'read': _best_of(repeats, reads) / accesses,
+                    'write': _best_of(repeats, writes) / accesses}
+    finally:
+        logger.fast_mode = False
+
+def time_program(lines: List[str], fast: bool = False, repeats: int = 3) -> Dict[str, float]:
+    """Wall-clock seconds to load and run a whole program, and the instructions per second"""
+    count = 0
+    def run():
+        nonlocal count
+        memory, l1, _ = build_hierarchy()
+        isa = SimpleISA(memory=memory, cache=l1)
+        with redirect_stdout(io.StringIO()):
+            isa.load_program(lines)
+            isa.run(fast=fast)
+        count = isa.instruction_count
+    seconds = _best_of(repeats, run)
+    return {'seconds': seconds, 'instructions': count, 'ips': count / seconds if seconds else 0.0}
+
+def perf_report() -> str:
+    """Timings of step, cache access and every kernel, with and without fast mode"""
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        programs = kernels()
+        lines = [f"{'benchmark':<22} {'normal':>12} {'fast':>12} {'speedup':>8}"]
+        def row(name, normal, fast, unit):
+            lines.append(f"{name:<22} {normal * 1e6:10.1f}{unit} {fast * 1e6:10.1f}{unit} {normal / fast:7.1f}x")
+
+        step_lines = programs['array_sum']
+        row('step (array_sum)', time_step(step_lines), time_step(step_lines, fast=True), 'us')
+        normal, fast = time_cache_access(), time_cache_access(fast=True)
+        row('L1 read', normal['read'], fast['read'], 'us')
+        row('L1 write', normal['write'], fast['write'], 'us')
+        for name, program in programs.items():
+            normal, fast = time_program(program), time_program(program, fast=True)
+            lines.append(f"{'run ' + name:<22} {normal['seconds'] * 1e3:10.1f}ms {fast['seconds'] * 1e3:10.1f}ms "
+                         f"{normal['seconds'] / fast['seconds']:7.1f}x  ({fast['ips']:,.0f} instructions/s fast)")
+        return '\n'.join(lines)
+    finally:
+        logger.log_level = level
+
+def _int_list(text: str) -> List[int]:
+    return [int(value) for value in text.split(',')]
+
+def main():
+    parser = argparse.ArgumentParser(description="Run the benchmark kernels and report hit rates and CPI")
+    parser.add_argument('--sweep', action='store_true', help="Run every kernel across a range of cache configurations")
+    parser.add_argument('--l1-sizes', type=_int_list, default=[16, 32, 64], help="L1 sizes in words (comma-separated)")
+    parser.add_argument('--l1-assocs', type=_int_list, default=[1, 2, 4], help="L1 associativities")
+    parser.add_argument('--l2-sizes', type=_int_list, default=[64, 128], help="L2 sizes in words")
+    parser.add_argument('--l2-assocs', type=_int_list, default=[4], help="L2 associativities")
+    parser.add_argument('-o', '--output', help="CSV file to write (default: stdout)")
+    parser.add_argument('--perf', action='store_true', help="Time the simulator itself: step, cache access and "
+                                                            "whole kernels, with and without fast mode")
+    args = parser.parse_args()
+
+    if args.perf:
+        print(perf_report())
+        return
+
+    if not args.sweep:
+        # Default hierarchy only, as a table
+        print(f"{'kernel':<14} {'instructions':>12} {'L1 rate':>8} {'L2 rate':>8} {'cycles':>7} {'CPI':>6}")
+        for name, lines in kernels().items():
+            row = run_kernel(name, lines, 32, 2, 64, 4)
+            print(f"{name:<14} {row['instructions']:12d} {row['l1_hit_rate']:7.1f}% {row['l2_hit_rate']:7.1f}% "
+                  f"{row['cycles']:7d} {row['cpi']:6.2f}")
+        return
+
+    text = to_csv(sweep(args.l1_sizes, args.l1_assocs, args.l2_sizes, args.l2_assocs))
+    if args.output:
+        with open(args.output, 'w') as f:
+            f.write(text)
+    else:
+        print(text, end='')
+
+if __name__ == '__main__':
+    sys.exit(main())
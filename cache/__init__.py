@@ -1,4 +1,5 @@
 from .cache import Cache
 from .policies import ReplacementPolicy
+from .write_buffer import WriteBuffer
 
-__all__ = ['Cache', 'ReplacementPolicy']
+__all__ = ['Cache', 'ReplacementPolicy', 'WriteBuffer']
@@ -12,7 +12,7 @@ class DEBUG:
     def log(msg, verbose=False):
         """Log message if enabled and either verbose mode is on or message is marked as not verbose"""
         if DEBUG.ENABLED and (DEBUG.VERBOSE or not verbose):
-            print(f"[CACHE DEBUG] {msg}")
+            Logger().log(LogLevel.DEBUG, f"[CACHE DEBUG] {msg}", target='cache.access')
 
     @staticmethod
     def set_enabled(enabled):
@@ -24,37 +24,133 @@ class DEBUG:
         """Enable or disable verbose logging"""
         DEBUG.VERBOSE = verbose
 
+# Victim choice when a set is full: least recently used, oldest filled, or random
+REPLACEMENT_POLICIES = ("lru", "fifo", "random")
+WRITE_POLICIES = ("write-back", "write-through")
+# Who an access is for. Instruction fetches read the program listing, not memory, so nothing in the
+# simulator issues "ifetch" yet; write-backs of evicted lines reach the level below as stores.
+REQUESTERS = ("ifetch", "load", "store", "prefetch", "coherence")
+
+def format_requesters(by_requester):
+    """'load 1/3, store 0/2': hits out of accesses for each requester that made any"""
+    return ", ".join(f"{kind} {counts['hits']}/{counts['hits'] + counts['misses']}"
+                     for kind, counts in by_requester.items() if counts['hits'] + counts['misses'])
+
+def format_read_write(stats):
+    """'read hits 12, read misses 3, write hits 4, write misses 1' from get_performance_stats()"""
+    return ", ".join(f"{key.replace('_', ' ')} {stats[key]}"
+                     for key in ('read_hits', 'read_misses', 'write_hits', 'write_misses'))
+
 class Cache:
-    def __init__(self, name, size, line_size, associativity, access_time=10, write_policy="write-back", next_level=None, logger=None):
+    def __init__(self, name, size, line_size, associativity, access_time=10, write_policy="write-back", next_level=None, logger=None,
+                 replacement_policy="lru"):
         """Initialize cache with given parameters"""
+        if replacement_policy not in REPLACEMENT_POLICIES:
+            raise ValueError(f"Unknown replacement policy '{replacement_policy}' (use {', '.join(REPLACEMENT_POLICIES)})")
         self._name = name
         self._size = size
         self._line_size = line_size
         self._associativity = associativity
         self._access_time = access_time
         self._write_policy = write_policy
+        self._replacement_policy = replacement_policy
+        self._rng = random.Random(0)  # Seeded so random replacement runs are repeatable
         self._next_level = next_level
+        self.requester = None  # Set by the level above for the access it passes down next
+        self.on_evict = None  # Called as on_evict(cache, set_index, entry, reason, address, written_back)
         self._logger = logger if logger else Logger()
         self._sets = size // (line_size * associativity)
         self._entries = [[] for _ in range(self._sets)]
+        self.reset_stats()
+        self._data_flow = []
+        self._last_access_time = 0
+        self._object_size = 32  # Size of Python objects in bytes
+
+    def reset_stats(self):
+        """Zero the hit, miss and access counters; the cached lines are kept"""
         self._stats = {
             'hits': 0,
             'misses': 0,
             'reads': 0,
             'writes': 0,
+            'read_hits': 0,
+            'read_misses': 0,
+            'write_hits': 0,
+            'write_misses': 0,
             'total_access_time': 0,
             'min_access_time': float('inf'),
-            'max_access_time': 0
+            'max_access_time': 0,
+            'prefetches': 0,
+            'useful_prefetches': 0,
+            'flushes': 0,
+            'invalidations': 0,
+            'discarded_writes': 0,
+            'by_requester': {kind: {'hits': 0, 'misses': 0} for kind in REQUESTERS}
         }
         self._exec_time = 0
-        self._data_flow = []
-        self._last_access_time = 0
-        self._object_size = 32  # Size of Python objects in bytes
 
     def set_next_level(self, next_level):
         """Set the next level in the memory hierarchy"""
         self._next_level = next_level
 
+    def _take_requester(self, default):
+        """Who the current access is for: what the level above said, else default"""
+        kind, self.requester = self.requester or default, None
+        return kind
+
+    def _count_requester(self, kind, hit):
+        self._stats['by_requester'][kind]['hits' if hit else 'misses'] += 1
+
+    def _count_access(self, access, kind, hit):
+        """Count a 'read' or 'write' as a hit or miss, overall, by direction and by requester"""
+        outcome = 'hits' if hit else 'misses'
+        self._stats[outcome] += 1
+        self._stats[access + 's'] += 1
+        self._stats[f"{access}_{outcome}"] += 1
+        self._count_requester(kind, hit)
+
+    def _report_eviction(self, set_index, entry, reason, address, written_back):
+        """Tell the eviction observer a line is leaving: reason is the replacement policy, 'flush' or 'invalidate'
+
+        address is the access that needed the room, or None for a flush or invalidation.
+        """
+        if self.on_evict:
+            self.on_evict(self, set_index, entry, reason, address, written_back)
+
+    def _tell_next_level(self, kind):
+        """Tag the access about to go to the next level, if that is a cache"""
+        if isinstance(self._next_level, Cache):
+            self._next_level.requester = kind
+
+    def _log(self, level, message):
+        self._logger.log(level, message, target='cache.access')
+
+    def split_address(self, address):
+        """(offset, set index, tag) fields of an address, without logging"""
+        offset_bits = (self._line_size - 1).bit_length()
+        index_bits = (self._sets - 1).bit_length()
+        offset = address & ((1 << offset_bits) - 1)
+        set_index = (address >> offset_bits) & ((1 << index_bits) - 1)
+        tag = address >> (offset_bits + index_bits)
+        return offset, set_index, tag
+
+    def format_split(self, address):
+        """An address as its tag | set | offset fields, in decimal and binary"""
+        offset_bits = (self._line_size - 1).bit_length()
+        index_bits = (self._sets - 1).bit_length()
+        offset, set_index, tag = self.split_address(address)
+        fields = [(f"tag {tag}", f"{tag:b}")]
+        if index_bits:
+            fields.append((f"set {set_index}", f"{set_index:0{index_bits}b}"))
+        if offset_bits:
+            fields.append((f"offset {offset}", f"{offset:0{offset_bits}b}"))
+        return (f"{address} = {' | '.join(name for name, _ in fields)} "
+                f"({'|'.join(bits for _, bits in fields)})")
+
+    def block_address(self, set_index, tag):
+        """First address of the line with this tag in this set (the inverse of split_address)"""
+        return tag * (self._line_size * self._sets) + set_index * self._line_size
+
     def _calculate_cache_indices(self, address):
         """Calculate set index and tag for a given address
 
@@ -63,64 +159,61 @@ class Cache:
         - Index bits = log2(num_sets)
         - Tag bits = 32 - offset_bits - index_bits
         """
-        # Calculate required bits for each field
         offset_bits = (self._line_size - 1).bit_length()  # Number of bits needed for byte offset
         index_bits = (self._sets - 1).bit_length()        # Number of bits needed for set index
-
-        # Create masks for extracting fields
-        offset_mask = (1 << offset_bits) - 1
-        index_mask = ((1 << index_bits) - 1) << offset_bits
-
-        # Extract fields using masks
-        offset = address & offset_mask
-        set_index = (address & index_mask) >> offset_bits
-        tag = address >> (offset_bits + index_bits)
-
-        # Debug output
-        self._logger.log(LogLevel.DEBUG, f"\nAddress Breakdown for {self._name}:")
-        self._logger.log(LogLevel.DEBUG, f"Address: {address} (0x{address:x})")
-        self._logger.log(LogLevel.DEBUG, f"Line Size: {self._line_size} (offset bits: {offset_bits})")
-        self._logger.log(LogLevel.DEBUG, f"Sets: {self._sets} (index bits: {index_bits})")
-        self._logger.log(LogLevel.DEBUG, f"Offset: {offset} (0x{offset:x})")
-        self._logger.log(LogLevel.DEBUG, f"Set Index: {set_index} (0x{set_index:x})")
-        self._logger.log(LogLevel.DEBUG, f"Tag: {tag} (0x{tag:x})")
+        offset, set_index, tag = self.split_address(address)
+
+        # Debug output (skipped entirely in fast mode, formatting included)
+        if not self._logger.fast_mode:
+            self._log(LogLevel.DEBUG, f"\nAddress Breakdown for {self._name}:")
+            self._log(LogLevel.DEBUG, f"Address: {address} (0x{address:x})")
+            self._log(LogLevel.DEBUG, f"Line Size: {self._line_size} (offset bits: {offset_bits})")
+            self._log(LogLevel.DEBUG, f"Sets: {self._sets} (index bits: {index_bits})")
+            self._log(LogLevel.DEBUG, f"Offset: {offset} (0x{offset:x})")
+            self._log(LogLevel.DEBUG, f"Set Index: {set_index} (0x{set_index:x})")
+            self._log(LogLevel.DEBUG, f"Tag: {tag} (0x{tag:x})")
 
         return set_index, tag
 
     def read(self, address, output=True):
         """Read data from cache"""
         start_time = time()
-
-        # Debug log for every read attempt
-        self._logger.log(LogLevel.DEBUG, f"\n=== Cache Read Operation ===")
-        self._logger.log(LogLevel.DEBUG, f"Address: {address}")
-        self._logger.log(LogLevel.DEBUG, f"Current Stats - Hits: {self._stats['hits']}, Misses: {self._stats['misses']}")
-
-        # Track data flow
-        self._data_flow.append({
-            'operation': 'read',
-            'address': address,
-            'time': start_time
-        })
+        kind = self._take_requester("load")
+
+        tracing = not self._logger.fast_mode
+        if tracing:
+            # Debug log for every read attempt
+            self._log(LogLevel.DEBUG, f"\n=== Cache Read Operation ===")
+            self._log(LogLevel.DEBUG, f"Address: {address}")
+            self._log(LogLevel.DEBUG, f"Current Stats - Hits: {self._stats['hits']}, Misses: {self._stats['misses']}")
+
+            # Track data flow
+            self._data_flow.append({
+                'operation': 'read',
+                'address': address,
+                'time': start_time
+            })
 
         # Calculate set index and tag using bit masking
         set_index, tag = self._calculate_cache_indices(address)
 
-        self._logger.log(LogLevel.DEBUG, f"Set Index: {set_index}, Tag: {tag}")
-        self._logger.log(LogLevel.DEBUG, f"Current Set Contents: {self._entries[set_index]}")
+        if tracing:
+            self._log(LogLevel.DEBUG, f"Set Index: {set_index}, Tag: {tag}")
+            self._log(LogLevel.DEBUG, f"Current Set Contents: {self._entries[set_index]}")
 
         # Check for hit
         for entry in self._entries[set_index]:
             if entry["tag"] == tag and entry["valid"]:
                 # Cache hit
-                self._stats['hits'] += 1
-                self._stats['reads'] += 1
+                self._count_access('read', kind, True)
+                self._count_prefetch_use(entry)
                 value = int(entry["data"])
 
-                self._logger.log(LogLevel.DEBUG, f"Cache HIT - Value: {value}")
+                if tracing:
+                    self._log(LogLevel.DEBUG, f"Cache HIT - Value: {value}")
 
                 # Log the hit with enhanced visualization
-                if output:
+                if output and tracing:
                     self._logger.log_cache_operation(
                         self._name,
                         'read',
@@ -130,7 +223,9 @@ class Cache:
                             'value': value,
                             'set': set_index,
                             'tag': tag,
+                            'fields': self.format_split(address),
                             'associativity': self._associativity,
+                            'replacement_policy': self._replacement_policy,
                             'entries': len(self._entries[set_index]),
                             'dirty': entry["dirty"]
                         }
@@ -147,15 +242,15 @@ class Cache:
                 return value
 
         # Cache miss
-        self._stats['misses'] += 1
-        self._stats['reads'] += 1
+        self._count_access('read', kind, False)
 
         # Get value from next level
         if self._next_level:
+            self._tell_next_level(kind)
             value = self._next_level.read(address)
 
             # Log the miss with enhanced visualization
-            if output:
+            if output and tracing:
                 self._logger.log_cache_operation(
                     self._name,
                     'read',
@@ -165,7 +260,9 @@ class Cache:
                         'value': value,
                         'set': set_index,
                         'tag': tag,
+                        'fields': self.format_split(address),
                         'associativity': self._associativity,
+                        'replacement_policy': self._replacement_policy,
                         'entries': len(self._entries[set_index]),
                         'eviction_needed': len(self._entries[set_index]) >= self._associativity
                     }
@@ -182,13 +279,16 @@ class Cache:
 
             # Handle set full condition
             if len(self._entries[set_index]) >= self._associativity:
-                # Find LRU entry to replace
-                lru_entry = min(self._entries[set_index], key=lambda x: x["lru"])
-                if lru_entry["dirty"] and self._write_policy == "write-back":
+                # Choose the entry to replace
+                victim = self._choose_victim(set_index)
+                if victim["dirty"] and self._write_policy == "write-back":
                     # Write back dirty data
-                    old_address = lru_entry["tag"] * (self._line_size * self._sets) + (set_index * self._line_size)
-                    self._next_level.write(old_address, lru_entry["data"])
-                self._entries[set_index].remove(lru_entry)
+                    old_address = victim["tag"] * (self._line_size * self._sets) + (set_index * self._line_size)
+                    self._tell_next_level("store")
+                    self._next_level.write(old_address, victim["data"])
+                self._report_eviction(set_index, victim, self._replacement_policy, address,
+                                      victim["dirty"] and self._write_policy == "write-back")
+                self._entries[set_index].remove(victim)
 
             # Add new entry
             self._entries[set_index].append(new_entry)
@@ -212,29 +312,34 @@ class Cache:
             propagate: Whether to propagate writes to next level (used internally)
         """
         start_time = time()
+        kind = self._take_requester("store")
 
-        # Debug log for every write attempt
-        self._logger.log(LogLevel.DEBUG, f"\n=== Cache Write Operation ({self._name}) ===")
-        self._logger.log(LogLevel.DEBUG, f"Address: {address}, Data: {data}")
-        self._logger.log(LogLevel.DEBUG, f"Write Policy: {self._write_policy}")
-        self._logger.log(LogLevel.DEBUG, f"Current Stats - Hits: {self._stats['hits']}, Misses: {self._stats['misses']}")
+        tracing = not self._logger.fast_mode
+        if tracing:
+            # Debug log for every write attempt
+            self._log(LogLevel.DEBUG, f"\n=== Cache Write Operation ({self._name}) ===")
+            self._log(LogLevel.DEBUG, f"Address: {address}, Data: {data}")
+            self._log(LogLevel.DEBUG, f"Write Policy: {self._write_policy}")
+            self._log(LogLevel.DEBUG, f"Current Stats - Hits: {self._stats['hits']}, Misses: {self._stats['misses']}")
 
         # Ensure data is integer
         data = int(data)
 
-        # Track data flow
-        self._data_flow.append({
-            'operation': 'write',
-            'address': address,
-            'data': data,
-            'time': start_time
-        })
+        if tracing:
+            # Track data flow
+            self._data_flow.append({
+                'operation': 'write',
+                'address': address,
+                'data': data,
+                'time': start_time
+            })
 
         # Calculate set index and tag using bit masking
         set_index, tag = self._calculate_cache_indices(address)
 
-        self._logger.log(LogLevel.DEBUG, f"Set Index: {set_index}, Tag: {tag}")
-        self._logger.log(LogLevel.DEBUG, f"Current Set Contents: {self._entries[set_index]}")
+        if tracing:
+            self._log(LogLevel.DEBUG, f"Set Index: {set_index}, Tag: {tag}")
+            self._log(LogLevel.DEBUG, f"Current Set Contents: {self._entries[set_index]}")
 
         # Check for hit
         hit_entry = None
@@ -245,11 +350,11 @@ class Cache:
 
         if hit_entry:
             # Cache hit
-            self._stats['hits'] += 1
-            self._stats['writes'] += 1
+            self._count_access('write', kind, True)
+            self._count_prefetch_use(hit_entry)
 
             # Log the hit
-            if output:
+            if output and tracing:
                 self._logger.log_cache_operation(
                     self._name,
                     'write',
@@ -259,7 +364,9 @@ class Cache:
                         'value': data,
                         'set': set_index,
                         'tag': tag,
+                        'fields': self.format_split(address),
                         'associativity': self._associativity,
+                        'replacement_policy': self._replacement_policy,
                         'entries': len(self._entries[set_index]),
                         'dirty': hit_entry["dirty"]
                     }
@@ -271,6 +378,7 @@ class Cache:
             # Handle write policy
             if self._write_policy == "write-through" and self._next_level and propagate:
                 # Propagate to next level for write-through
+                self._tell_next_level(kind)
                 self._next_level.write(address, data, output, propagate=True)
             else:
                 # Mark as dirty for write-back
@@ -281,11 +389,10 @@ class Cache:
 
         else:
             # Cache miss
-            self._stats['misses'] += 1
-            self._stats['writes'] += 1
+            self._count_access('write', kind, False)
 
             # Log the miss
-            if output:
+            if output and tracing:
                 self._logger.log_cache_operation(
                     self._name,
                     'write',
@@ -295,7 +402,9 @@ class Cache:
                         'value': data,
                         'set': set_index,
                         'tag': tag,
+                        'fields': self.format_split(address),
                         'associativity': self._associativity,
+                        'replacement_policy': self._replacement_policy,
                         'entries': len(self._entries[set_index]),
                         'eviction_needed': len(self._entries[set_index]) >= self._associativity
                     }
@@ -312,23 +421,27 @@ class Cache:
 
             # Handle set full condition
             if len(self._entries[set_index]) >= self._associativity:
-                # Find LRU entry to replace
-                lru_entry = min(self._entries[set_index], key=lambda x: x["lru"])
-                if lru_entry["dirty"] and self._write_policy == "write-back" and self._next_level:
+                # Choose the entry to replace
+                victim = self._choose_victim(set_index)
+                if victim["dirty"] and self._write_policy == "write-back" and self._next_level:
                     # Calculate original address using bit fields
                     offset_bits = (self._line_size - 1).bit_length()
                     index_bits = (self._sets - 1).bit_length()
-                    old_address = (lru_entry["tag"] << (offset_bits + index_bits)) | (set_index << offset_bits)
+                    old_address = (victim["tag"] << (offset_bits + index_bits)) | (set_index << offset_bits)
 
                     # Debug log address reconstruction
-                    self._logger.log(LogLevel.DEBUG, f"\n=== Write-Back Address Reconstruction ===")
-                    self._logger.log(LogLevel.DEBUG, f"Tag: {lru_entry['tag']}, Set Index: {set_index}")
-                    self._logger.log(LogLevel.DEBUG, f"Offset bits: {offset_bits}, Index bits: {index_bits}")
-                    self._logger.log(LogLevel.DEBUG, f"Reconstructed address: {old_address}")
+                    self._log(LogLevel.DEBUG, f"\n=== Write-Back Address Reconstruction ===")
+                    self._log(LogLevel.DEBUG, f"Tag: {victim['tag']}, Set Index: {set_index}")
+                    self._log(LogLevel.DEBUG, f"Offset bits: {offset_bits}, Index bits: {index_bits}")
+                    self._log(LogLevel.DEBUG, f"Reconstructed address: {old_address}")
 
                     # Write back dirty data before eviction
-                    self._next_level.write(old_address, lru_entry["data"], output, propagate=True)
-                self._entries[set_index].remove(lru_entry)
+                    self._tell_next_level("store")
+                    self._next_level.write(old_address, victim["data"], output, propagate=True)
+                self._report_eviction(set_index, victim, self._replacement_policy, address,
+                                      victim["dirty"] and self._write_policy == "write-back"
+                                      and self._next_level is not None)
+                self._entries[set_index].remove(victim)
 
             # Add new entry
             self._entries[set_index].append(new_entry)
@@ -337,6 +450,7 @@ class Cache:
             # Handle write policy for new entries
             if self._write_policy == "write-through" and self._next_level and propagate:
                 # Propagate to next level for write-through
+                self._tell_next_level(kind)
                 self._next_level.write(address, data, output, propagate=True)
 
         # Calculate access time and update statistics
@@ -346,6 +460,34 @@ class Cache:
 
         return True
 
+    def _choose_victim(self, set_index):
+        """Entry to evict from a full set under the replacement policy"""
+        entries = self._entries[set_index]
+        if self._replacement_policy == "random":
+            return self._rng.choice(entries)
+        return entries[self.next_victim(set_index)]
+
+    def recency_order(self, set_index):
+        """Ways of a set from most to least recently used; of lines with equal LRU counters the later filled is newer"""
+        entries = self._entries[set_index]
+        return sorted(range(len(entries)), key=lambda way: (entries[way]["lru"], way), reverse=True)
+
+    def recency_ranks(self, set_index):
+        """Each way's place in recency_order: 0 for the most recently used line of the set"""
+        ranks = [0] * len(self._entries[set_index])
+        for rank, way in enumerate(self.recency_order(set_index)):
+            ranks[way] = rank
+        return ranks
+
+    def next_victim(self, set_index):
+        """Way the next fill of this set evicts, whether or not it is full yet; None under random replacement"""
+        entries = self._entries[set_index]
+        if not entries or self._replacement_policy == "random":
+            return None
+        if self._replacement_policy == "fifo":
+            return 0  # Lines are appended as they are filled and hits do not reorder them
+        return self.recency_order(set_index)[-1]
+
     def _update_lru(self, set_index, entry):
         """Update LRU counters for a set"""
         # Decrease all other entries' LRU values
@@ -365,7 +507,7 @@ class Cache:
 
     def _log_stats(self):
         """Log cache statistics"""
-        self._logger.log(LogLevel.DEBUG, f"Cache stats: hits={self._stats['hits']}, misses={self._stats['misses']}, "
+        self._log(LogLevel.DEBUG, f"Cache stats: hits={self._stats['hits']}, misses={self._stats['misses']}, "
                         f"hit_rate={self._stats['hits']/self._stats['reads'] if self._stats['reads'] > 0 else 0:.2%}")
 
     def get_cache_state(self):
@@ -384,9 +526,137 @@ class Cache:
         return {
             'hits': self._stats['hits'],
             'misses': self._stats['misses'],
-            'hit_rate': hit_rate
+            'hit_rate': hit_rate,
+            'read_hits': self._stats['read_hits'],
+            'read_misses': self._stats['read_misses'],
+            'write_hits': self._stats['write_hits'],
+            'write_misses': self._stats['write_misses'],
+            'prefetches': self._stats['prefetches'],
+            'useful_prefetches': self._stats['useful_prefetches'],
+            'flushes': self._stats['flushes'],
+            'invalidations': self._stats['invalidations'],
+            'discarded_writes': self._stats['discarded_writes'],
+            'by_requester': {kind: dict(counts) for kind, counts in self._stats['by_requester'].items()}
         }
 
+    def get_prefetched_blocks(self):
+        """(set_index, block_index) of lines brought in by a prefetch and not yet used"""
+        return {(set_idx, block_idx)
+                for set_idx, entries in enumerate(self._entries)
+                for block_idx, entry in enumerate(entries)
+                if entry["valid"] and entry.get("prefetched", False)}
+
+    def _count_prefetch_use(self, entry):
+        """A demand access hit a prefetched line: the prefetch paid off"""
+        if entry.get("prefetched", False):
+            entry["prefetched"] = False
+            self._stats['useful_prefetches'] += 1
+
+    def _find_entry(self, address):
+        set_index, tag = self._calculate_cache_indices(address)
+        for entry in self._entries[set_index]:
+            if entry["tag"] == tag and entry["valid"]:
+                return set_index, entry
+        return set_index, None
+
+    def contains(self, address):
+        """Whether address is in a valid line, without touching statistics or LRU order"""
+        return self._find_entry(address)[1] is not None
+
+    def prefetch(self, address):
+        """Software prefetch: bring a line in without counting a demand access
+
+        The next level sees a read from a prefetch requester. Returns False if
+        the line was already cached, so the prefetch did nothing; that counts
+        as a prefetch hit in the requester breakdown.
+        """
+        set_index, entry = self._find_entry(address)
+        if entry or not self._next_level:
+            if entry:
+                self._count_requester("prefetch", True)
+            return False
+
+        self._stats['prefetches'] += 1
+        self._count_requester("prefetch", False)
+        self._tell_next_level("prefetch")
+        value = self._next_level.read(address)
+        if len(self._entries[set_index]) >= self._associativity:
+            victim = self._choose_victim(set_index)
+            if victim["dirty"] and self._write_policy == "write-back":
+                old_address = victim["tag"] * (self._line_size * self._sets) + (set_index * self._line_size)
+                self._tell_next_level("store")
+                self._next_level.write(old_address, victim["data"])
+            self._report_eviction(set_index, victim, self._replacement_policy, address,
+                                  victim["dirty"] and self._write_policy == "write-back")
+            self._entries[set_index].remove(victim)
+
+        new_entry = {
+            "tag": self._calculate_cache_indices(address)[1],
+            "data": value,
+            "valid": True,
+            "dirty": False,
+            "lru": 0,
+            "prefetched": True
+        }
+        self._entries[set_index].append(new_entry)
+        self._update_lru(set_index, new_entry)
+        self._log(LogLevel.INFO, f"{self._name}: prefetched address {address}")
+        return True
+
+    def flush_line(self, address):
+        """Write a dirty line back and drop it, here and in every lower cache level"""
+        set_index, entry = self._find_entry(address)
+        self._count_requester("coherence", entry is not None)
+        if entry:
+            written_back = entry["dirty"] and self._write_policy == "write-back" and self._next_level is not None
+            if written_back:
+                self._tell_next_level("coherence")
+                self._next_level.write(address, entry["data"])
+            self._report_eviction(set_index, entry, "flush", None, written_back)
+            self._entries[set_index].remove(entry)
+            self._stats['flushes'] += 1
+            self._log(LogLevel.INFO, f"{self._name}: flushed address {address}")
+        if hasattr(self._next_level, 'flush_line'):
+            self._next_level.flush_line(address)
+
+    def invalidate_line(self, address):
+        """Drop a line without writing it back, here and in every lower cache level
+
+        A dirty line's value is not written back; these are counted as discarded writes.
+        """
+        set_index, entry = self._find_entry(address)
+        self._count_requester("coherence", entry is not None)
+        if entry:
+            if entry["dirty"]:
+                self._stats['discarded_writes'] += 1
+                self._log(LogLevel.WARNING, f"{self._name}: invalidate discarded the dirty value "
+                                 f"{entry['data']} at address {address}")
+            self._report_eviction(set_index, entry, "invalidate", None, False)
+            self._entries[set_index].remove(entry)
+            self._stats['invalidations'] += 1
+        if hasattr(self._next_level, 'invalidate_line'):
+            self._next_level.invalidate_line(address)
+
+    def cached_addresses(self):
+        """Addresses of every valid line"""
+        return [entry["tag"] * (self._line_size * self._sets) + (set_index * self._line_size)
+                for set_index, entries in enumerate(self._entries)
+                for entry in entries if entry["valid"]]
+
+    def flush_all(self):
+        """Flush every line in this cache and the levels below it"""
+        for address in self.cached_addresses():
+            self.flush_line(address)
+        if hasattr(self._next_level, 'flush_all'):
+            self._next_level.flush_all()
+
+    def invalidate_all(self):
+        """Invalidate every line in this cache and the levels below it"""
+        for address in self.cached_addresses():
+            self.invalidate_line(address)
+        if hasattr(self._next_level, 'invalidate_all'):
+            self._next_level.invalidate_all()
+
     def debug_info(self):
         """Get debug information about cache state"""
         return {
@@ -396,6 +666,7 @@ class Cache:
             "associativity": self._associativity,
             "sets": self._sets,
             "write_policy": self._write_policy,
+            "replacement_policy": self._replacement_policy,
             "performance_stats": self.get_performance_stats(),
             "entries": len([entry for entries in self._entries for entry in entries]),
             "dirty_entries": len([entry for entries in self._entries for entry in entries if entry["dirty"]])
@@ -404,20 +675,20 @@ class Cache:
     def print_debug_info(self):
         """Print formatted debug information"""
         info = self.debug_info()
-        self._logger.log(LogLevel.DEBUG, f"\n=== {self._name} Debug Info ===")
-        self._logger.log(LogLevel.DEBUG, f"Size: {info['size']} bytes")
-        self._logger.log(LogLevel.DEBUG, f"Line Size: {info['line_size']} bytes")
-        self._logger.log(LogLevel.DEBUG, f"Associativity: {info['associativity']}-way")
-        self._logger.log(LogLevel.DEBUG, f"Sets: {info['sets']}")
-        self._logger.log(LogLevel.DEBUG, f"Write Policy: {info['write_policy']}")
-        self._logger.log(LogLevel.DEBUG, f"Total Entries: {info['entries']}")
-        self._logger.log(LogLevel.DEBUG, f"Dirty Entries: {info['dirty_entries']}")
+        self._log(LogLevel.DEBUG, f"\n=== {self._name} Debug Info ===")
+        self._log(LogLevel.DEBUG, f"Size: {info['size']} bytes")
+        self._log(LogLevel.DEBUG, f"Line Size: {info['line_size']} bytes")
+        self._log(LogLevel.DEBUG, f"Associativity: {info['associativity']}-way")
+        self._log(LogLevel.DEBUG, f"Sets: {info['sets']}")
+        self._log(LogLevel.DEBUG, f"Write Policy: {info['write_policy']}")
+        self._log(LogLevel.DEBUG, f"Total Entries: {info['entries']}")
+        self._log(LogLevel.DEBUG, f"Dirty Entries: {info['dirty_entries']}")
 
         perf_stats = info['performance_stats']
-        self._logger.log(LogLevel.DEBUG, "\nPerformance Statistics:")
-        self._logger.log(LogLevel.DEBUG, f"  Access Count: {total_accesses}")
-        self._logger.log(LogLevel.DEBUG, f"  Hit Rate: {perf_stats['hit_rate']:.2%}")
-        self._logger.log(LogLevel.DEBUG, f"  Execution Time: {self._exec_time:.6f}s")
+        self._log(LogLevel.DEBUG, "\nPerformance Statistics:")
+        self._log(LogLevel.DEBUG, f"  Access Count: {total_accesses}")
+        self._log(LogLevel.DEBUG, f"  Hit Rate: {perf_stats['hit_rate']:.2%}")
+        self._log(LogLevel.DEBUG, f"  Execution Time: {self._exec_time:.6f}s")
 
     def get_exec_time(self):
         """Get total execution time"""
@@ -492,12 +763,12 @@ class Cache:
 
     def write_back_all(self):
         """Write back all dirty entries to the next level"""
-        self._logger.log(LogLevel.DEBUG, f"\n=== Write-back operation for {self._name} ===")
-        self._logger.log(LogLevel.DEBUG, "Cache state before write-back:")
-        self._logger.log(LogLevel.DEBUG, f"Total entries: {sum(len(entries) for entries in self._entries)}")
+        self._log(LogLevel.DEBUG, f"\n=== Write-back operation for {self._name} ===")
+        self._log(LogLevel.DEBUG, "Cache state before write-back:")
+        self._log(LogLevel.DEBUG, f"Total entries: {sum(len(entries) for entries in self._entries)}")
         dirty_count = sum(1 for entries in self._entries for entry in entries if entry.get("dirty", False))
-        self._logger.log(LogLevel.DEBUG, f"Dirty entries: {dirty_count}")
-        self._logger.log(LogLevel.DEBUG, f"Clean entries: {sum(len(entries) for entries in self._entries) - dirty_count}")
+        self._log(LogLevel.DEBUG, f"Dirty entries: {dirty_count}")
+        self._log(LogLevel.DEBUG, f"Clean entries: {sum(len(entries) for entries in self._entries) - dirty_count}")
 
         # Write back all dirty entries
         for set_index, entries in enumerate(self._entries):
@@ -506,43 +777,43 @@ class Cache:
                     # Calculate address from tag and set index
                     address = entry["tag"] * (self._line_size * self._sets) + (set_index * self._line_size)
 
-                    self._logger.log(LogLevel.DEBUG, f"\nWriting back dirty entry:")
-                    self._logger.log(LogLevel.DEBUG, f"  Address: {address}")
-                    self._logger.log(LogLevel.DEBUG, f"  Data: {entry['data']}")
-                    self._logger.log(LogLevel.DEBUG, f"  Dirty: {entry['dirty']}")
+                    self._log(LogLevel.DEBUG, f"\nWriting back dirty entry:")
+                    self._log(LogLevel.DEBUG, f"  Address: {address}")
+                    self._log(LogLevel.DEBUG, f"  Data: {entry['data']}")
+                    self._log(LogLevel.DEBUG, f"  Dirty: {entry['dirty']}")
 
                     try:
                         # Write to next level
                         if self._next_level:
-                            self._logger.log(LogLevel.DEBUG, f"  Writing to next level: {self._next_level._name}")
+                            self._log(LogLevel.DEBUG, f"  Writing to next level: {self._next_level._name}")
                             self._next_level.write(address, entry["data"])
-                            self._logger.log(LogLevel.DEBUG, "  Write successful")
+                            self._log(LogLevel.DEBUG, "  Write successful")
                         else:
                             # If no next level, write directly to main memory
-                            self._logger.log(LogLevel.DEBUG, "  No next level, writing to main memory")
+                            self._log(LogLevel.DEBUG, "  No next level, writing to main memory")
                             # Get main memory from the memory hierarchy
                             memory = self._get_main_memory()
                             if memory:
                                 memory.write(address, entry["data"])
-                                self._logger.log(LogLevel.DEBUG, "  Write successful")
+                                self._log(LogLevel.DEBUG, "  Write successful")
                             else:
-                                self._logger.log(LogLevel.ERROR, "  No main memory found in hierarchy")
+                                self._log(LogLevel.ERROR, "  No main memory found in hierarchy")
 
                         # Mark as clean
                         entry["dirty"] = False
-                        self._logger.log(LogLevel.DEBUG, "  Entry marked as clean")
+                        self._log(LogLevel.DEBUG, "  Entry marked as clean")
                     except Exception as e:
-                        self._logger.log(LogLevel.ERROR, f"  Error writing back entry: {str(e)}")
+                        self._log(LogLevel.ERROR, f"  Error writing back entry: {str(e)}")
                         import traceback
-                        self._logger.log(LogLevel.ERROR, f"  Stack trace: {traceback.format_exc()}")
+                        self._log(LogLevel.ERROR, f"  Stack trace: {traceback.format_exc()}")
 
         # Log final cache state
-        self._logger.log(LogLevel.DEBUG, "\nCache state after write-back:")
-        self._logger.log(LogLevel.DEBUG, f"Total entries: {sum(len(entries) for entries in self._entries)}")
+        self._log(LogLevel.DEBUG, "\nCache state after write-back:")
+        self._log(LogLevel.DEBUG, f"Total entries: {sum(len(entries) for entries in self._entries)}")
         dirty_count = sum(1 for entries in self._entries for entry in entries if entry.get("dirty", False))
-        self._logger.log(LogLevel.DEBUG, f"Dirty entries: {dirty_count}")
-        self._logger.log(LogLevel.DEBUG, f"Clean entries: {sum(len(entries) for entries in self._entries) - dirty_count}")
-        self._logger.log(LogLevel.DEBUG, "=== Write-back operation complete ===\n")
+        self._log(LogLevel.DEBUG, f"Dirty entries: {dirty_count}")
+        self._log(LogLevel.DEBUG, f"Clean entries: {sum(len(entries) for entries in self._entries) - dirty_count}")
+        self._log(LogLevel.DEBUG, "=== Write-back operation complete ===\n")
 
     def _get_main_memory(self):
         """Traverse the cache hierarchy to find the main memory"""
@@ -0,0 +1,119 @@
+from collections import OrderedDict
+
+from utils.logger import Logger, LogLevel
+
+class WriteBuffer:
+    """Store buffer between a write-through cache and the next level
+
+    Writes are queued instead of waiting for the slower level and drain one
+    entry every `drain_interval` cycles, oldest first. A write to an address
+    that is already queued merges into its entry. The processor only stalls
+    when a write arrives while the buffer is full, until the oldest entry
+    drains. Reads check the buffer first so they see pending writes.
+    """
+
+    def __init__(self, next_level, entries=4, drain_interval=2, name="WriteBuffer", logger=None):
+        if entries < 1 or drain_interval < 1:
+            raise ValueError("Write buffer needs at least one entry and a drain interval of at least 1")
+        self._name = name
+        self._next_level = next_level
+        self._logger = logger if logger else Logger()
+        self.capacity = entries
+        self.drain_interval = drain_interval
+        self.pending = OrderedDict()  # address -> data, oldest first
+        self._countdown = drain_interval
+        self.reset_stats()
+
+    def reset_stats(self):
+        """Zero the counters; pending writes stay queued (and count towards the peak)"""
+        self._stats = {
+            'writes': 0,
+            'merged': 0,
+            'drained': 0,
+            'full_stalls': 0,
+            'stall_cycles': 0,
+            'read_forwards': 0,
+            'peak_occupancy': len(self.pending)
+        }
+
+    def set_next_level(self, next_level):
+        self._next_level = next_level
+
+    def _log(self, level, message):
+        self._logger.log(level, message, target='cache.access')
+
+    def read(self, address, output=True):
+        """Return a pending write's data, otherwise read the next level"""
+        if address in self.pending:
+            self._stats['read_forwards'] += 1
+            return self.pending[address]
+        return self._next_level.read(address)
+
+    def write(self, address, data, output=True, propagate=True):
+        """Queue a write, stalling for the oldest entry to drain if the buffer is full"""
+        self._stats['writes'] += 1
+        if address in self.pending:
+            self.pending[address] = data
+            self._stats['merged'] += 1
+            return True
+
+        if len(self.pending) >= self.capacity:
+            self._stats['full_stalls'] += 1
+            self._stats['stall_cycles'] += self._countdown
+            self._log(LogLevel.DEBUG, f"{self._name} full: stalled {self._countdown} cycles")
+            self._drain_one()
+
+        self.pending[address] = data
+        self._stats['peak_occupancy'] = max(self._stats['peak_occupancy'], len(self.pending))
+        return True
+
+    def tick(self, cycles=1):
+        """Advance time, draining one entry every drain_interval cycles"""
+        for _ in range(cycles):
+            if not self.pending:
+                self._countdown = self.drain_interval
+                continue
+            self._countdown -= 1
+            if self._countdown == 0:
+                self._drain_one()
+
+    def _drain_one(self):
+        address, data = self.pending.popitem(last=False)
+        self._next_level.write(address, data)
+        self._stats['drained'] += 1
+        self._countdown = self.drain_interval
+
+    def flush(self):
+        """Drain every pending write immediately"""
+        while self.pending:
+            self._drain_one()
+
+    def flush_line(self, address):
+        """Drain a pending write to the address, then flush the line below"""
+        if address in self.pending:
+            self._next_level.write(address, self.pending.pop(address))
+            self._stats['drained'] += 1
+        if hasattr(self._next_level, 'flush_line'):
+            self._next_level.flush_line(address)
+
+    def invalidate_line(self, address):
+        if hasattr(self._next_level, 'invalidate_line'):
+            self._next_level.invalidate_line(address)
+
+    def flush_all(self):
+        self.flush()
+        if hasattr(self._next_level, 'flush_all'):
+            self._next_level.flush_all()
+
+    def invalidate_all(self):
+        if hasattr(self._next_level, 'invalidate_all'):
+            self._next_level.invalidate_all()
+
+    @property
+    def occupancy(self):
+        return len(self.pending)
+
+    def get_performance_stats(self):
+        stats = dict(self._stats)
+        stats.update({'occupancy': len(self.pending), 'capacity': self.capacity})
+        return stats
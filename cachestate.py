@@ -0,0 +1,147 @@
+import json
+from typing import Dict, List
+
+from cache.cache import Cache
+
+STATE_VERSION = 1  # Bumped if the file layout changes
+
+def export_cache(cache: Cache) -> dict:
+    """Every set of a cache: its lines in fill order (the order FIFO evicts them) and its LRU order
+
+    Lines are one word, so each is named by the address it holds as well
+    as its tag. The LRU order lists a set's addresses from most to least
+    recently used; the last one is the next LRU victim.
+    """
+    contents = []
+    for set_index, entries in enumerate(cache._entries):
+        lines = [{"address": cache.block_address(set_index, entry["tag"]), "tag": entry["tag"],
+                  "data": entry["data"], "valid": entry["valid"], "dirty": entry["dirty"]}
+                 for entry in entries]
+        ranked = cache.recency_order(set_index)
+        contents.append({"set": set_index, "lines": lines,
+                         "lru_order": [lines[i]["address"] for i in ranked if entries[i]["valid"]]})
+    return {"name": cache._name, "sets": cache._sets, "associativity": cache._associativity,
+            "line_size": cache._line_size, "write_policy": cache._write_policy,
+            "replacement_policy": cache._replacement_policy, "contents": contents}
+
+def _line_entry(cache: Cache, set_index: int, line: dict, memory) -> dict:
+    """A cache entry for one line of a state file; ValueError if it cannot be in this set"""
+    if "address" in line:
+        _, line_set, tag = cache.split_address(int(line["address"]))
+        if line_set != set_index:
+            raise ValueError(f"address {line['address']} belongs in set {line_set}, not set {set_index}")
+        if "tag" in line and int(line["tag"]) != tag:
+            raise ValueError(f"address {line['address']} has tag {tag}, not {line['tag']}")
+    elif "tag" in line:
+        tag = int(line["tag"])
+    else:
+        raise ValueError(f"a line in set {set_index} has neither an address nor a tag")
+    address = cache.block_address(set_index, tag)
+    if "data" in line:
+        data = line["data"]
+    else:
+        data = memory.data[address] if memory is not None and address < len(memory.data) else 0
+    dirty = bool(line.get("dirty", False))
+    if dirty and cache._write_policy != "write-back":
+        raise ValueError(f"address {address} is dirty, but {cache._name} is {cache._write_policy}")
+    return {"tag": tag, "data": data, "valid": bool(line.get("valid", True)), "dirty": dirty, "lru": 0}
+
+def import_cache(cache: Cache, state: dict, memory=None) -> None:
+    """Replace a cache's contents with an exported state (sets it leaves out become empty)
+
+    The state must be for the same geometry. A line may give an address or
+    a tag; one without data holds what memory has at its address. Lines a
+    set's lru_order leaves out count as less recent, later fills first.
+    Statistics are kept. ValueError, with the cache left as it was, if
+    the state does not fit.
+    """
+    for key in ("sets", "associativity", "line_size"):
+        if key in state and state[key] != getattr(cache, f"_{key}"):
+            raise ValueError(f"{cache._name}: the state is for {state[key]} {key.replace('_', ' ')}, "
+                             f"this cache has {getattr(cache, f'_{key}')}")
+    entries: List[List[dict]] = [[] for _ in range(cache._sets)]
+    try:
+        for contents in state.get("contents", []):
+            set_index = int(contents["set"])
+            if not 0 <= set_index < cache._sets:
+                raise ValueError(f"there is no set {set_index} (sets 0-{cache._sets - 1})")
+            lines = [_line_entry(cache, set_index, line, memory) for line in contents.get("lines", [])]
+            if len(lines) > cache._associativity:
+                raise ValueError(f"set {set_index} has {len(lines)} lines, more than its "
+                                 f"{cache._associativity} ways")
+            by_address: Dict[int, dict] = {}
+            for entry in lines:
+                address = cache.block_address(set_index, entry["tag"])
+                if address in by_address:
+                    raise ValueError(f"address {address} is in set {set_index} twice")
+                by_address[address] = entry
+            order = [int(address) for address in contents.get("lru_order", [])]
+            for address in order:
+                if address not in by_address:
+                    raise ValueError(f"lru_order of set {set_index} names address {address}, "
+                                     f"which is not one of its lines")
+            order += [cache.block_address(set_index, entry["tag"]) for entry in reversed(lines)
+                      if cache.block_address(set_index, entry["tag"]) not in order]
+            for rank, address in enumerate(order):
+                by_address[address]["lru"] = cache._associativity - 1 - rank
+            entries[set_index] = lines
+    except KeyError as e:
+        raise ValueError(f"{cache._name}: malformed cache state, missing {e}") from None
+    except TypeError as e:
+        raise ValueError(f"{cache._name}: malformed cache state ({e})") from None
+    except ValueError as e:
+        raise ValueError(f"{cache._name}: {e}") from None
+    cache._entries = entries
+
+def export_hierarchy(l1: Cache, l2: Cache) -> dict:
+    return {"version": STATE_VERSION, "L1": export_cache(l1), "L2": export_cache(l2)}
+
+def import_hierarchy(l1: Cache, l2: Cache, state: dict, memory=None) -> None:
+    """Load both levels from an exported state; a level the state leaves out is not changed
+
+    Both levels are checked before either is changed.
+    """
+    if not isinstance(state, dict) or not ("L1" in state or "L2" in state):
+        raise ValueError("not a cache state (expected L1 and/or L2 sections)")
+    if state.get("version", STATE_VERSION) > STATE_VERSION:
+        raise ValueError(f"cache state version {state['version']} is newer than this simulator's "
+                         f"({STATE_VERSION})")
+    levels = [(cache, state[name]) for name, cache in (("L1", l1), ("L2", l2)) if name in state]
+    saved = [cache._entries for cache, _ in levels]
+    try:
+        for cache, cache_state in levels:
+            import_cache(cache, cache_state, memory)
+    except ValueError:
+        for (cache, _), entries in zip(levels, saved):
+            cache._entries = entries
+        raise
+
+def save_cache_state(path: str, l1: Cache, l2: Cache) -> None:
+    with open(path, 'w') as f:
+        json.dump(export_hierarchy(l1, l2), f, indent=2)
+        f.write('\n')
+
+def load_cache_state(path: str, l1: Cache, l2: Cache, memory=None) -> None:
+    """Load a state file into both levels; OSError if it cannot be read, ValueError if it does not fit"""
+    with open(path) as f:
+        try:
+            state = json.load(f)
+        except json.JSONDecodeError as e:
+            raise ValueError(f"{path} is not JSON: {e}") from None
+    import_hierarchy(l1, l2, state, memory)
+
+def format_cache(cache: Cache) -> List[str]:
+    """One row per non-empty set: [address]=data per line in fill order (* marks dirty ones), then the LRU order"""
+    state = export_cache(cache)
+    rows = [f"{cache._name}: {cache._sets} sets, {cache._associativity}-way, {cache._write_policy}, "
+            f"{cache._replacement_policy}"]
+    for contents in state["contents"]:
+        if not contents["lines"]:
+            continue
+        lines = "  ".join(f"[{line['address']}]={line['data']}{'*' if line['dirty'] else ''}"
+                          f"{'' if line['valid'] else ' (invalid)'}" for line in contents["lines"])
+        order = ", ".join(str(address) for address in contents["lru_order"])
+        rows.append(f"  set {contents['set']:3d}: {lines}   most recent first: {order}")
+    if len(rows) == 1:
+        rows.append("  empty")
+    return rows
@@ -0,0 +1,208 @@
+import argparse
+import sys
+from dataclasses import dataclass, fields
+from typing import Dict, List, Optional, Tuple
+
+from benchmark import L2_HIT_CYCLES, MEMORY_CYCLES
+from isa import SimpleISA, MEMORY_ORGANIZATIONS
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+
+@dataclass
+class MachineConfig:
+    """One complete simulator configuration: cache geometry, pipeline forwarding and memory organization"""
+    l1_size: int = 32
+    l1_assoc: int = 2
+    l2_size: int = 64
+    l2_assoc: int = 4
+    forwarding: bool = True
+    memory_organization: str = 'von_neumann'
+
+    def describe(self) -> str:
+        return (f"L1 {self.l1_size}w {self.l1_assoc}-way, L2 {self.l2_size}w {self.l2_assoc}-way, "
+                f"{'forwarding' if self.forwarding else 'no forwarding'}, {self.memory_organization}")
+
+def parse_config(spec: str, base: Optional[MachineConfig] = None) -> MachineConfig:
+    """A configuration from 'key=value,...' (e.g. 'l1_assoc=1,forwarding=off'); unset keys come from base"""
+    config = MachineConfig(**{f.name: getattr(base, f.name) for f in fields(MachineConfig)}) if base else MachineConfig()
+    names = [f.name for f in fields(MachineConfig)]
+    for item in filter(None, (part.strip() for part in spec.split(','))):
+        key, _, value = item.partition('=')
+        key = key.strip().replace('-', '_')
+        value = value.strip()
+        if key not in names:
+            raise ValueError(f"unknown setting '{key}' (use {', '.join(names)})")
+        if key == 'forwarding':
+            if value.lower() not in ('on', 'off', 'true', 'false', '1', '0'):
+                raise ValueError(f"forwarding must be on or off, not '{value}'")
+            setattr(config, key, value.lower() in ('on', 'true', '1'))
+        elif key == 'memory_organization':
+            if value not in MEMORY_ORGANIZATIONS:
+                raise ValueError(f"unknown memory organization '{value}' (use {', '.join(MEMORY_ORGANIZATIONS)})")
+            config.memory_organization = value
+        else:
+            try:
+                setattr(config, key, int(value, 0))
+            except ValueError:
+                raise ValueError(f"{key} must be a number, not '{value}'") from None
+    for level in ('l1', 'l2'):
+        size, assoc = getattr(config, f'{level}_size'), getattr(config, f'{level}_assoc')
+        if size <= 0 or assoc <= 0 or size % assoc:
+            raise ValueError(f"{level.upper()}: size {size} is not a whole number of {assoc}-way sets")
+    return config
+
+class Machine:
+    """A fresh memory hierarchy, ISA and pipeline model built from a MachineConfig"""
+
+    def __init__(self, name: str, config: MachineConfig, lines: List[str]):
+        self.name = name
+        self.config = config
+        self.memory, self.l1, self.l2 = build_hierarchy(config.l1_size, config.l1_assoc,
+                                                        config.l2_size, config.l2_assoc)
+        self.isa = SimpleISA(memory=self.memory, cache=self.l1, memory_organization=config.memory_organization)
+        self.isa.pipeline = PipelineModel(self.isa, forwarding=config.forwarding)
+        self.isa.load_program(lines)
+
+    @property
+    def done(self) -> bool:
+        return not self.isa.running or self.isa.pc >= len(self.isa.instructions)
+
+    @property
+    def cycles(self) -> int:
+        """Pipeline cycles plus the miss penalties, counted as in benchmark.py"""
+        return (self.isa.pipeline.total_cycles + self.l1._stats['misses'] * L2_HIT_CYCLES
+                + self.l2._stats['misses'] * MEMORY_CYCLES)
+
+    def stats(self) -> Dict:
+        l1_stats = self.l1.get_performance_stats()
+        l2_stats = self.l2.get_performance_stats()
+        instructions = self.isa.instruction_count
+        return {
+            'name': self.name,
+            'config': self.config.describe(),
+            'instructions': instructions,
+            'cycles': self.cycles,
+            'cpi': self.cycles / instructions if instructions else 0.0,
+            'stall_cycles': self.isa.pipeline.get_stats()['stall_cycles'],
+            'l1_hit_rate': l1_stats['hit_rate'],
+            'l1_misses': l1_stats['misses'],
+            'l2_hit_rate': l2_stats['hit_rate'],
+            'l2_misses': l2_stats['misses']
+        }
+
+class LockstepComparison:
+    """Runs one program on two configurations side by side, one instruction each per step
+
+    Both machines execute the same instruction stream, so after every step
+    their cycle counts can be compared directly; samples holds (step, cycles
+    A, cycles B) for the divergence chart. A machine that has finished
+    keeps its final count while the other catches up. diverged_at is the
+    first step after which the two machines' PCs differ, which only happens
+    when a configuration changes what the program computes.
+    """
+
+    def __init__(self, lines: List[str], config_a: MachineConfig, config_b: MachineConfig):
+        self.machines = (Machine('A', config_a, lines), Machine('B', config_b, lines))
+        self.samples: List[Tuple[int, int, int]] = [(0, 0, 0)]
+        self.diverged_at: Optional[int] = None
+
+    @property
+    def done(self) -> bool:
+        return all(machine.done for machine in self.machines)
+
+    def step(self) -> bool:
+        """Execute the next instruction on both machines; False once both have finished"""
+        if self.done:
+            return False
+        logger = Logger()
+        level = logger.log_level
+        logger.log_level = LogLevel.ERROR  # Two machines' cache traces would interleave unreadably
+        try:
+            for machine in self.machines:
+                if not machine.done:
+                    machine.isa.execute_step()
+        finally:
+            logger.log_level = level
+        a, b = self.machines
+        step = len(self.samples)
+        self.samples.append((step, a.cycles, b.cycles))
+        if self.diverged_at is None and (a.isa.pc != b.isa.pc or a.done != b.done):
+            self.diverged_at = step
+        return True
+
+    def run(self, max_steps: int = 100000) -> None:
+        while len(self.samples) <= max_steps and self.step():
+            pass
+
+    def results(self) -> List[Dict]:
+        return [machine.stats() for machine in self.machines]
+
+def format_comparison(comparison: LockstepComparison) -> List[str]:
+    """The two machines' statistics side by side, with B's difference from A"""
+    a, b = comparison.results()
+    lines = [f"A: {a['config']}", f"B: {b['config']}", "",
+             f"{'':<14} {'A':>10} {'B':>10} {'B - A':>10}"]
+    for key, label, fmt in (('instructions', 'instructions', 'd'), ('cycles', 'cycles', 'd'),
+                            ('cpi', 'CPI', '.2f'), ('stall_cycles', 'stall cycles', 'd'),
+                            ('l1_hit_rate', 'L1 hit rate', '.1f'), ('l2_hit_rate', 'L2 hit rate', '.1f')):
+        lines.append(f"{label:<14} {a[key]:>10{fmt}} {b[key]:>10{fmt}} {b[key] - a[key]:>+10{fmt}}")
+    if comparison.diverged_at is not None:
+        lines.append(f"\nThe machines took different paths after step {comparison.diverged_at}.")
+    return lines
+
+def format_divergence(samples: List[Tuple[int, int, int]], width: int = 60, height: int = 10) -> List[str]:
+    """A text chart of B's cycle count minus A's after each step"""
+    if len(samples) < 2:
+        return []
+    count = min(width, len(samples) - 1)
+    points = [samples[1 + round(i * (len(samples) - 2) / max(count - 1, 1))] for i in range(count)]
+    gaps = [b - a for _, a, b in points]
+    top, bottom = max(max(gaps), 0), min(min(gaps), 0)
+    span = (top - bottom) or 1
+    rows = []
+    for row in range(height, -1, -1):
+        level = bottom + span * row / height
+        marks = ''.join('*' if round((gap - bottom) / span * height) == row else
+                        '-' if abs(level) < span / height / 2 else ' ' for gap in gaps)
+        label = f"{level:+.0f}" if row in (0, height) or marks.startswith('-') else ''
+        rows.append(f"{label:>8} |{marks}")
+    last = f"step {points[-1][0]}"
+    rows.append(f"{'':>8}  step 1{last:>{max(len(last) + 1, len(gaps) - 6)}}")
+    return ["Cycles B - A after each step:"] + rows
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program on two simulator configurations in lock-step and "
+                                                 "compare their cycles, CPI and hit rates")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--a', default='', metavar='SETTINGS',
+                        help="Configuration A as key=value,... (keys: l1_size, l1_assoc, l2_size, l2_assoc, "
+                             "forwarding, memory_organization; default: the simulator's defaults)")
+    parser.add_argument('--b', default='', metavar='SETTINGS', help="Configuration B, changed from A")
+    parser.add_argument('--max-steps', type=int, default=100000, help="Stop after this many steps")
+    parser.add_argument('--no-chart', action='store_true', help="Leave out the divergence chart")
+    args = parser.parse_args()
+
+    try:
+        config_a = parse_config(args.a)
+        config_b = parse_config(args.b, base=config_a)
+    except ValueError as e:
+        print(f"Bad configuration: {e}")
+        return 1
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    comparison = LockstepComparison(program, config_a, config_b)
+    comparison.run(args.max_steps)
+
+    print('\n'.join(format_comparison(comparison)))
+    if not args.no_chart:
+        print()
+        print('\n'.join(format_divergence(comparison.samples)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,5 @@
+from .codegen import compile_source
+from .lexer import CompileError
+from .parser import parse
+
+__all__ = ['compile_source', 'CompileError', 'parse']
@@ -0,0 +1,58 @@
+import argparse
+import os
+import sys
+
+from isa import SimpleISA
+from locality import build_hierarchy
+from utils.files import STDIN, display_name, read_text
+from utils.logger import Logger, LogLevel
+
+from . import CompileError, compile_source
+from .optimizer import optimize
+
+def main():
+    parser = argparse.ArgumentParser(prog='python -m compiler',
+                                     description="Compile a C-subset program (int scalars and arrays, "
+                                                 "if/while/for, functions) to simulator assembly")
+    parser.add_argument('source', help="C source file, or - for standard input")
+    parser.add_argument('-o', '--output', help="Assembly file to write "
+                                                 "(default: the source with .txt; required for standard input)")
+    parser.add_argument('-O', '--optimize', action='store_true', help="Run the peephole optimizer on the output")
+    parser.add_argument('--run', action='store_true', help="Also run the program and print main's return value")
+    args = parser.parse_args()
+    if args.source == STDIN and not args.output:
+        parser.error("give -o for a source read from standard input")
+
+    name = display_name(args.source)
+    try:
+        lines = compile_source(read_text(args.source), os.path.basename(name))
+    except OSError as e:
+        print(f"Error: {e}")
+        return 1
+    except CompileError as e:
+        print(f"{name}:{e}")
+        return 1
+    if args.optimize:
+        lines = optimize(lines).after
+    output = args.output or os.path.splitext(args.source)[0] + '.txt'
+    with open(output, 'w') as f:
+        f.write('\n'.join(lines) + '\n')
+    instructions = sum(1 for line in lines if line and line[0] not in ';.' and not line.endswith(':'))
+    print(f"{name} -> {output}: {instructions} instructions")
+
+    if args.run:
+        memory, l1, _ = build_hierarchy()
+        isa = SimpleISA(memory=memory, cache=l1)
+        logger = Logger()
+        level = logger.log_level
+        logger.log_level = LogLevel.ERROR
+        try:
+            isa.load_program(lines)
+            isa.run(fast=True)
+        finally:
+            logger.log_level = level
+        print(f"Ran {isa.instruction_count} instructions: main returned {isa.registers['eax']}")
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,479 @@
+from dataclasses import dataclass
+from typing import Dict, List, Optional, Tuple
+
+from .lexer import CompileError
+from .parser import (Assign, Binary, Block, Call, Expr, ExprStmt, For, Function, If, Index, Jump, Num, Postfix,
+                     Program, Return, Stmt, Unary, Var, VarDecl, While, parse)
+
+# Memory layout: globals from DATA_BASE up, the stack from the top of the 1024-word memory down
+DATA_BASE = 512
+STACK_TOP = 1024
+STACK_RESERVE = 128   # Words kept free for the stack above the last global
+
+# Branch taken when the comparison holds, and when it does not (signed, on the flags CMP sets)
+BRANCHES = {'==': 'BEQ', '!=': 'BNE', '<': 'BLT', '<=': 'BLE', '>': 'BGT', '>=': 'BGE'}
+INVERSE = {'==': '!=', '!=': '==', '<': '>=', '<=': '>', '>': '<=', '>=': '<'}
+ALU = {'+': 'ADD', '-': 'SUB', '&': 'AND', '|': 'OR', '^': 'XOR', '<<': 'SHL', '>>': 'SHR'}
+
+# Runtime routines for the operators the ISA has no instruction for.
+# Called with JAL, operands in eax and ebx; they use ecx and edx as scratch.
+RUNTIME = {
+    '__mul': [
+        "; eax = eax * ebx (shift and add)",
+        "__mul:",
+        "CMP ebx #0",
+        "BGE __mul.loop_start",
+        "NOT eax",                 # Negate both so the multiplier is positive
+        "INC eax",
+        "NOT ebx",
+        "INC ebx",
+        "__mul.loop_start:",
+        "MOV ecx #0",
+        "__mul.loop:",
+        "CMP ebx #0",
+        "BEQ __mul.done",
+        "MOV edx ebx",
+        "AND edx #1",
+        "CMP edx #0",
+        "BEQ __mul.skip",
+        "ADD ecx eax",
+        "__mul.skip:",
+        "SHL eax #1",
+        "SHR ebx #1",
+        "JMP __mul.loop",
+        "__mul.done:",
+        "MOV eax ecx",
+        "JR ebp",
+    ],
+    '__divmod': [
+        "; eax = eax / ebx, ebx = eax % ebx, truncating toward zero as in C",
+        "__divmod:",
+        "CMP ebx #0",
+        "BNE __divmod.nonzero",
+        "HALT",                    # Division by zero stops the program
+        "__divmod.nonzero:",
+        "MOV edx #0",              # edx bit 0: negate the quotient, bit 1: negate the remainder
+        "CMP eax #0",
+        "BGE __divmod.dividend",
+        "NOT eax",
+        "INC eax",
+        "MOV edx #3",
+        "__divmod.dividend:",
+        "CMP ebx #0",
+        "BGE __divmod.divisor",
+        "NOT ebx",
+        "INC ebx",
+        "XOR edx #1",
+        "__divmod.divisor:",
+        "MOV ecx #0",
+        "__divmod.loop:",
+        "CMP eax ebx",
+        "BLT __divmod.done",
+        "SUB eax ebx",
+        "INC ecx",
+        "JMP __divmod.loop",
+        "__divmod.done:",
+        "MOV ebx eax",
+        "MOV eax ecx",
+        "TEST edx #1",
+        "BEQ __divmod.quotient",
+        "NOT eax",
+        "INC eax",
+        "__divmod.quotient:",
+        "TEST edx #2",
+        "BEQ __divmod.remainder",
+        "NOT ebx",
+        "INC ebx",
+        "__divmod.remainder:",
+        "JR ebp",
+    ],
+}
+
+@dataclass
+class Variable:
+    """Where a name lives: a global's memory word, or a local's offset from the frame pointer (edi)"""
+    name: str
+    is_global: bool
+    location: int
+    size: Optional[int] = None   # Array length; None for an int
+    pointer: bool = False        # An int a[] parameter: the word holds the array's address
+
+    @property
+    def is_array(self) -> bool:
+        return self.size is not None or self.pointer
+
+class CodeGenerator:
+    """Translate a parsed program into assembly for the simulator (ARM flags mode)
+
+    eax holds every expression's value and ebx the right operand of a
+    binary operator; partial results wait on the stack, so no register is
+    live across a call. A frame, addressed from edi:
+
+        edi + 2 + k   arguments (the last at edi + 2)
+        edi + 1       saved return address (ebp, which JAL overwrites)
+        edi           saved caller edi
+        edi - 1 ...   locals
+    """
+
+    def __init__(self, program: Program, source_lines: List[str]):
+        self.program = program
+        self.source_lines = source_lines
+        self.lines: List[str] = []
+        self.globals: Dict[str, Variable] = {}
+        self.functions: Dict[str, Function] = {}
+        self.runtime: set = set()
+        self.scopes: List[Dict[str, Variable]] = []
+        self.frame_size = 0
+        self.function: Optional[Function] = None
+        self.loops: List[Tuple[str, str]] = []   # (break label, continue label)
+        self.label_count = 0
+        self.last_line = 0
+
+    def emit(self, text: str) -> None:
+        self.lines.append(text)
+
+    def new_label(self, kind: str) -> str:
+        self.label_count += 1
+        return f"{self.function.name}.{kind}{self.label_count}"
+
+    def push(self, register: str = 'eax') -> None:
+        self.emit("SUB esp #1")
+        self.emit(f"MOV [esp] {register}")
+
+    def pop(self, register: str) -> None:
+        self.emit(f"MOV {register} [esp]")
+        self.emit("ADD esp #1")
+
+    def generate(self) -> List[str]:
+        address = DATA_BASE
+        for decl in self.program.globals:
+            if decl.name in self.globals:
+                raise CompileError(decl.line, f"'{decl.name}' is already defined")
+            self.globals[decl.name] = Variable(decl.name, True, address, decl.size)
+            address += decl.size or 1
+        if address > STACK_TOP - STACK_RESERVE:
+            raise CompileError(self.program.globals[-1].line,
+                               f"globals need {address - DATA_BASE} words; at most "
+                               f"{STACK_TOP - STACK_RESERVE - DATA_BASE} fit below the stack")
+        for function in self.program.functions:
+            if function.name in self.functions or function.name in self.globals:
+                raise CompileError(function.line, f"'{function.name}' is already defined")
+            if function.name in RUNTIME:
+                raise CompileError(function.line, f"'{function.name}' is reserved for the runtime")
+            self.functions[function.name] = function
+        if 'main' not in self.functions:
+            raise CompileError(1, "no main function")
+        if self.functions['main'].params:
+            raise CompileError(self.functions['main'].line, "main takes no parameters")
+
+        self.emit(".mode arm")
+        self.emit(f".stack {address} {STACK_TOP - 1}")
+        self.emit(f"MOV esp #{STACK_TOP}")
+        self.emit("MOV edi esp")
+        for decl in self.program.globals:
+            variable = self.globals[decl.name]
+            values = decl.init if isinstance(decl.init, list) else [decl.init.value] if decl.init else []
+            if values:
+                self.emit(f"; {decl.name} = {', '.join(map(str, values))}")
+            for offset, value in enumerate(values):
+                self.emit(f"MOV eax #{value}")
+                self.emit(f"MOV [{variable.location + offset}] eax")
+        self.emit("JAL main")
+        self.emit("HALT")
+        for function in self.program.functions:
+            self.generate_function(function)
+        for name in sorted(self.runtime):
+            self.emit("")
+            self.lines.extend(RUNTIME[name])
+        return self.lines
+
+    # Functions and statements
+    def generate_function(self, function: Function) -> None:
+        self.function = function
+        self.frame_size = 0
+        self.last_line = 0
+        count = len(function.params)
+        params = {}
+        for i, param in enumerate(function.params):
+            if param.name in params:
+                raise CompileError(function.line, f"parameter '{param.name}' appears twice")
+            params[param.name] = Variable(param.name, False, 2 + count - 1 - i, pointer=param.is_array)
+        self.scopes = [params]
+
+        # Locals are allocated as they are declared; the prologue reserves the final frame size
+        body: List[str] = []
+        outer, self.lines = self.lines, body
+        self.generate_statement(function.body)
+        self.lines = outer
+
+        self.emit("")
+        self.emit(f"; {self.source_lines[function.line - 1].strip()}")
+        self.emit(f"{function.name}:")
+        self.push('ebp')
+        self.push('edi')
+        self.emit("MOV edi esp")
+        if self.frame_size:
+            self.emit(f"SUB esp #{self.frame_size}")
+        self.lines.extend(body)
+        self.emit(f"{function.name}.return:")
+        self.emit("MOV esp edi")
+        self.pop('edi')
+        self.pop('ebp')
+        self.emit("JR ebp")
+
+    def comment(self, line: int) -> None:
+        """The C source line as a comment above the code it compiles to"""
+        if line != self.last_line:
+            self.last_line = line
+            self.emit(f"; {line}: {self.source_lines[line - 1].strip()}")
+
+    def declare(self, decl: VarDecl) -> Variable:
+        if decl.name in self.scopes[-1]:
+            raise CompileError(decl.line, f"'{decl.name}' is already defined in this scope")
+        self.frame_size += decl.size or 1
+        variable = Variable(decl.name, False, -self.frame_size, decl.size)
+        self.scopes[-1][decl.name] = variable
+        return variable
+
+    def lookup(self, name: str, line: int) -> Variable:
+        for scope in reversed(self.scopes):
+            if name in scope:
+                return scope[name]
+        if name in self.globals:
+            return self.globals[name]
+        raise CompileError(line, f"'{name}' is not declared")
+
+    def generate_statement(self, stmt: Stmt) -> None:
+        if isinstance(stmt, Block):
+            self.scopes.append({})
+            for item in stmt.items:
+                self.generate_statement(item)
+            self.scopes.pop()
+            return
+        self.comment(stmt.line)
+        if isinstance(stmt, VarDecl):
+            variable = self.declare(stmt)
+            if stmt.init is not None:
+                self.generate_expression(stmt.init)
+                self.store(variable, 'eax')
+        elif isinstance(stmt, ExprStmt):
+            self.generate_expression(stmt.expr)
+        elif isinstance(stmt, If):
+            otherwise = self.new_label('else')
+            end = self.new_label('endif')
+            self.generate_condition(stmt.cond, otherwise, False)
+            self.generate_statement(stmt.then)
+            if stmt.otherwise:
+                self.emit(f"JMP {end}")
+            self.emit(f"{otherwise}:")
+            if stmt.otherwise:
+                self.generate_statement(stmt.otherwise)
+                self.emit(f"{end}:")
+        elif isinstance(stmt, While):
+            top = self.new_label('while')
+            end = self.new_label('endwhile')
+            self.emit(f"{top}:")
+            self.generate_condition(stmt.cond, end, False)
+            self.loops.append((end, top))
+            self.generate_statement(stmt.body)
+            self.loops.pop()
+            self.emit(f"JMP {top}")
+            self.emit(f"{end}:")
+        elif isinstance(stmt, For):
+            top = self.new_label('for')
+            step = self.new_label('step')
+            end = self.new_label('endfor')
+            self.scopes.append({})
+            if isinstance(stmt.init, VarDecl):
+                self.generate_statement(stmt.init)
+            elif stmt.init:
+                self.generate_expression(stmt.init)
+            self.emit(f"{top}:")
+            if stmt.cond:
+                self.generate_condition(stmt.cond, end, False)
+            self.loops.append((end, step))
+            self.generate_statement(stmt.body)
+            self.loops.pop()
+            self.emit(f"{step}:")
+            if stmt.step:
+                self.generate_expression(stmt.step)
+            self.emit(f"JMP {top}")
+            self.emit(f"{end}:")
+            self.scopes.pop()
+        elif isinstance(stmt, Return):
+            if stmt.value is not None:
+                if not self.function.returns_value:
+                    raise CompileError(stmt.line, f"void function '{self.function.name}' returns a value")
+                self.generate_expression(stmt.value)
+            elif self.function.returns_value:
+                raise CompileError(stmt.line, f"'{self.function.name}' must return a value")
+            self.emit(f"JMP {self.function.name}.return")
+        elif isinstance(stmt, Jump):
+            if not self.loops:
+                raise CompileError(stmt.line, f"'{stmt.kind}' outside a loop")
+            self.emit(f"JMP {self.loops[-1][0 if stmt.kind == 'break' else 1]}")
+
+    # Expressions: each leaves its value in eax
+    def address(self, variable: Variable) -> str:
+        """The memory operand of a variable's word, setting esi for a local"""
+        if variable.is_global:
+            return f"[{variable.location}]"
+        self.emit("MOV esi edi")
+        if variable.location > 0:
+            self.emit(f"ADD esi #{variable.location}")
+        elif variable.location < 0:
+            self.emit(f"SUB esi #{-variable.location}")
+        return "[esi]"
+
+    def store(self, variable: Variable, register: str) -> None:
+        self.emit(f"MOV {self.address(variable)} {register}")
+
+    def element_address(self, name: str, line: int) -> None:
+        """esi = address of array element eax"""
+        variable = self.lookup(name, line)
+        if not variable.is_array:
+            raise CompileError(line, f"'{name}' is not an array")
+        if variable.pointer:
+            self.emit(f"MOV esi {self.address(variable)}")
+        elif variable.is_global:
+            self.emit(f"MOV esi #{variable.location}")
+        else:
+            self.emit("MOV esi edi")
+            self.emit(f"SUB esi #{-variable.location}")
+        self.emit("ADD esi eax")
+
+    def generate_expression(self, expr: Expr) -> None:
+        if isinstance(expr, Num):
+            self.emit(f"MOV eax #{expr.value}")
+        elif isinstance(expr, Var):
+            variable = self.lookup(expr.name, expr.line)
+            if variable.pointer or not variable.is_array:
+                self.emit(f"MOV eax {self.address(variable)}")
+            elif variable.is_global:
+                self.emit(f"MOV eax #{variable.location}")   # An array's name is its address
+            else:
+                self.emit("MOV eax edi")
+                self.emit(f"SUB eax #{-variable.location}")
+        elif isinstance(expr, Index):
+            self.generate_expression(expr.index)
+            self.element_address(expr.name, expr.line)
+            self.emit("MOV eax [esi]")
+        elif isinstance(expr, Assign):
+            self.generate_expression(expr.value)
+            if isinstance(expr.target, Var):
+                variable = self.lookup(expr.target.name, expr.line)
+                if variable.is_array and not variable.pointer:
+                    raise CompileError(expr.line, f"cannot assign to array '{variable.name}'")
+                self.store(variable, 'eax')
+            else:
+                self.push()
+                self.generate_expression(expr.target.index)
+                self.element_address(expr.target.name, expr.line)
+                self.pop('eax')
+                self.emit("MOV [esi] eax")
+        elif isinstance(expr, Postfix):
+            variable = self.lookup(expr.target.name, expr.line)
+            if variable.is_array and not variable.pointer:
+                raise CompileError(expr.line, f"cannot apply {expr.op} to array '{variable.name}'")
+            self.emit(f"MOV eax {self.address(variable)}")
+            self.emit("MOV ebx eax")
+            self.emit("INC ebx" if expr.op == '++' else "DEC ebx")
+            self.store(variable, 'ebx')
+        elif isinstance(expr, Call):
+            self.generate_call(expr)
+        elif isinstance(expr, Unary):
+            if expr.op == '!':
+                self.generate_boolean(expr)
+                return
+            self.generate_expression(expr.operand)
+            self.emit("NOT eax")
+            if expr.op == '-':
+                self.emit("INC eax")
+        elif isinstance(expr, Binary):
+            if expr.op in BRANCHES or expr.op in ('&&', '||'):
+                self.generate_boolean(expr)
+            else:
+                self.generate_arithmetic(expr)
+
+    def generate_arithmetic(self, expr: Binary) -> None:
+        self.generate_expression(expr.left)
+        if expr.op in ALU and isinstance(expr.right, Num):
+            self.emit(f"{ALU[expr.op]} eax #{expr.right.value}")
+            return
+        self.push()
+        self.generate_expression(expr.right)
+        self.emit("MOV ebx eax")
+        self.pop('eax')
+        if expr.op in ALU:
+            self.emit(f"{ALU[expr.op]} eax ebx")
+        elif expr.op == '*':
+            self.runtime.add('__mul')
+            self.emit("JAL __mul")
+        else:
+            self.runtime.add('__divmod')
+            self.emit("JAL __divmod")
+            if expr.op == '%':
+                self.emit("MOV eax ebx")
+
+    def generate_call(self, call: Call) -> None:
+        function = self.functions.get(call.name)
+        if function is None:
+            raise CompileError(call.line, f"'{call.name}' is not a function")
+        if len(call.args) != len(function.params):
+            raise CompileError(call.line, f"'{call.name}' takes {len(function.params)} arguments, "
+                                          f"not {len(call.args)}")
+        for arg in call.args:
+            self.generate_expression(arg)
+            self.push()
+        self.emit(f"JAL {call.name}")
+        if call.args:
+            self.emit(f"ADD esp #{len(call.args)}")
+
+    def generate_boolean(self, expr: Expr) -> None:
+        """eax = 1 if a comparison or logical expression holds, 0 if not"""
+        false = self.new_label('false')
+        end = self.new_label('end')
+        self.generate_condition(expr, false, False)
+        self.emit("MOV eax #1")
+        self.emit(f"JMP {end}")
+        self.emit(f"{false}:")
+        self.emit("MOV eax #0")
+        self.emit(f"{end}:")
+
+    def generate_condition(self, expr: Expr, target: str, when: bool) -> None:
+        """Branch to target if expr's truth equals when, else fall through"""
+        if isinstance(expr, Unary) and expr.op == '!':
+            self.generate_condition(expr.operand, target, not when)
+        elif isinstance(expr, Binary) and expr.op in ('&&', '||'):
+            # a && b is false as soon as a is; a || b is true as soon as a is
+            if (expr.op == '&&') != when:
+                self.generate_condition(expr.left, target, when)
+                self.generate_condition(expr.right, target, when)
+            else:
+                skip = self.new_label('skip')
+                self.generate_condition(expr.left, skip, not when)
+                self.generate_condition(expr.right, target, when)
+                self.emit(f"{skip}:")
+        elif isinstance(expr, Binary) and expr.op in BRANCHES:
+            self.generate_expression(expr.left)
+            if isinstance(expr.right, Num):
+                self.emit(f"CMP eax #{expr.right.value}")
+            else:
+                self.push()
+                self.generate_expression(expr.right)
+                self.emit("MOV ebx eax")
+                self.pop('eax')
+                self.emit("CMP eax ebx")
+            self.emit(f"{BRANCHES[expr.op if when else INVERSE[expr.op]]} {target}")
+        else:
+            self.generate_expression(expr)
+            self.emit("CMP eax #0")
+            self.emit(f"{'BNE' if when else 'BEQ'} {target}")
+
+def compile_program(program: Program, source: str) -> List[str]:
+    return CodeGenerator(program, source.splitlines()).generate()
+
+def compile_source(source: str, name: str = '') -> List[str]:
+    """Compile C-subset source into assembly lines; CompileError on the first error"""
+    lines = [f"; Compiled from {name or 'C source'} - main's return value is left in eax"]
+    return lines + compile_program(parse(source), source)
@@ -0,0 +1,333 @@
+import re
+from typing import Callable, List, Optional, Set
+
+from .lexer import CompileError
+from .parser import Assign, Binary, Num, Unary, Var, parse_expression
+
+REGISTERS = ('eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'ebp', 'esp')
+# Registers a line may borrow as temporaries, in order of preference: ebp (the
+# link register) only as a last resort, esp (the stack pointer) never
+TEMPORARIES = ('esi', 'edi', 'edx', 'ecx', 'ebx', 'eax', 'ebp')
+# JNZ tests eax, so a register-by-register multiply counts its loop down in eax
+LOOP_COUNTER = 'eax'
+
+ALU = {'+': 'ADD', '-': 'SUB', '&': 'AND', '|': 'OR', '^': 'XOR', '<<': 'SHL', '>>': 'SHR'}
+FOLD = {'+': lambda a, b: a + b, '-': lambda a, b: a - b, '*': lambda a, b: a * b, '&': lambda a, b: a & b,
+        '|': lambda a, b: a | b, '^': lambda a, b: a ^ b, '<<': lambda a, b: a << b, '>>': lambda a, b: a >> b}
+COMMUTATIVE = {'+', '*', '&', '|', '^'}
+# Instructions that exist only as expression lines, for editors to offer
+PSEUDO_INSTRUCTIONS = ('LI',)
+# Comparisons for 'if a < b goto label': the flag branch taken in arm mode,
+# and the same comparison with its operands swapped
+FLAG_BRANCHES = {'<': 'BLT', '<=': 'BLE', '>': 'BGT', '>=': 'BGE', '==': 'BEQ', '!=': 'BNE'}
+MIRROR = {'<': '>', '<=': '>=', '>': '<', '>=': '<=', '==': '==', '!=': '!='}
+
+EXPRESSION_LINE = re.compile(r'^\s*(\w+)\s*=(?!=)')
+LI_LINE = re.compile(r'^\s*li\s+(\w+)\s*,?\s*(.+)$', re.IGNORECASE)
+GOTO_LINE = re.compile(r'^\s*goto\s+([\w.]+)\s*$', re.IGNORECASE)
+IF_LINE = re.compile(r'^\s*if\s+(.+?)\s+goto\s+([\w.]+)\s*$', re.IGNORECASE)
+
+def is_expression_line(line: str) -> bool:
+    match = EXPRESSION_LINE.match(line.split(';')[0])
+    return bool(match) and match.group(1).lower() in REGISTERS
+
+def _reads(expr, registers: Set[str]) -> Set[str]:
+    if isinstance(expr, Var):
+        registers.add(expr.name)
+    elif isinstance(expr, Unary):
+        _reads(expr.operand, registers)
+    elif isinstance(expr, Binary):
+        _reads(expr.left, registers)
+        _reads(expr.right, registers)
+    return registers
+
+def _fold(expr):
+    """Check the operators and operands, and replace constant subexpressions by their values"""
+    if isinstance(expr, Num):
+        return expr
+    if isinstance(expr, Var):
+        if expr.name.lower() not in REGISTERS:
+            raise ValueError(f"'{expr.name}' is not a register")
+        return Var(expr.name.lower(), expr.line)
+    if isinstance(expr, Unary):
+        if expr.op == '!':
+            raise ValueError("'!' is not supported in expression lines (use the C compiler)")
+        operand = _fold(expr.operand)
+        if isinstance(operand, Num):
+            return Num(-operand.value if expr.op == '-' else ~operand.value, expr.line)
+        return Unary(expr.op, operand, expr.line)
+    if isinstance(expr, Binary):
+        if expr.op not in FOLD:
+            raise ValueError(f"'{expr.op}' is not supported in expression lines (use the C compiler)")
+        left, right = _fold(expr.left), _fold(expr.right)
+        if expr.op in ('<<', '>>') and isinstance(right, Num) and right.value < 0:
+            raise ValueError(f"negative shift amount {right.value}")
+        if isinstance(left, Num) and isinstance(right, Num):
+            return Num(FOLD[expr.op](left.value, right.value), expr.line)
+        if expr.op in COMMUTATIVE and isinstance(left, Num):
+            left, right = right, left
+        return Binary(expr.op, left, right, expr.line)
+    raise ValueError("only registers, constants and operators can appear in expression lines")
+
+class _Translator:
+    def __init__(self, free: List[str], new_label: Callable[[], str]):
+        self.free = free
+        self.new_label = new_label
+        self.code: List[str] = []
+
+    def allocate(self) -> str:
+        if not self.free:
+            raise ValueError("the expression needs more temporary registers than the line leaves free")
+        return self.free.pop(0)
+
+    def release(self, register: str) -> None:
+        self.free.insert(0, register)
+
+    def emit(self, text: str) -> None:
+        self.code.append(text)
+
+    def operand(self, expr, immediate: bool) -> str:
+        """A register holding expr's value, or #value for a constant if immediate is allowed"""
+        if isinstance(expr, Var):
+            return expr.name
+        if isinstance(expr, Num) and immediate:
+            return f"#{expr.value}"
+        temp = self.allocate()
+        self.generate(expr, temp)
+        return temp
+
+    def generate(self, expr, target: str) -> None:
+        """Evaluate expr into target, a register the expression does not read (except as its leftmost operand)"""
+        if isinstance(expr, Num):
+            self.emit(f"MOV {target} #{expr.value}")
+        elif isinstance(expr, Var):
+            if expr.name != target:
+                self.emit(f"MOV {target} {expr.name}")
+        elif isinstance(expr, Unary):
+            self.generate(expr.operand, target)
+            self.emit(f"NOT {target}")
+            if expr.op == '-':
+                self.emit(f"INC {target}")
+        elif expr.op == '*':
+            self.generate(expr.left, target)
+            if isinstance(expr.right, Num):
+                self.multiply_constant(target, expr.right.value)
+            else:
+                self.multiply(target, expr.right)
+        else:
+            self.generate(expr.left, target)
+            if isinstance(expr.right, Num):
+                self.emit(f"{ALU[expr.op]} {target} #{expr.right.value}")
+            elif isinstance(expr.right, Var):
+                self.emit(f"{ALU[expr.op]} {target} {expr.right.name}")
+            else:
+                temp = self.allocate()
+                self.generate(expr.right, temp)
+                self.emit(f"{ALU[expr.op]} {target} {temp}")
+                self.release(temp)
+
+    def multiply_constant(self, target: str, factor: int) -> None:
+        """target *= factor by shifts and adds"""
+        magnitude = abs(factor)
+        if magnitude == 0:
+            self.emit(f"MOV {target} #0")
+            return
+        shifted = (magnitude & -magnitude).bit_length() - 1  # The lowest set bit
+        if shifted:
+            self.emit(f"SHL {target} #{shifted}")
+        if magnitude >> shifted > 1:
+            temp = self.allocate()
+            self.emit(f"MOV {temp} {target}")
+            for bit in range(shifted + 1, magnitude.bit_length()):
+                if magnitude >> bit & 1:
+                    self.emit(f"SHL {temp} #{bit - shifted}")
+                    self.emit(f"ADD {target} {temp}")
+                    shifted = bit
+            self.release(temp)
+        if factor < 0:
+            self.emit(f"NOT {target}")
+            self.emit(f"INC {target}")
+
+    def multiply(self, target: str, multiplier) -> None:
+        """target *= multiplier with a shift-add loop that counts the multiplier down in eax"""
+        if _has_register_multiply(multiplier):
+            temp = self.allocate()
+            self.generate(multiplier, temp)
+            self.emit(f"MOV {LOOP_COUNTER} {temp}")
+            self.release(temp)
+        else:
+            self.generate(multiplier, LOOP_COUNTER)
+        product = self.allocate()
+        mask = self.allocate()
+        label = self.new_label()
+        self.code += [
+            # A negative multiplier is negated along with the multiplicand (32-bit values)
+            f"MOV {mask} {LOOP_COUNTER}", f"SHR {mask} #31",
+            f"XOR {LOOP_COUNTER} {mask}", f"SUB {LOOP_COUNTER} {mask}",
+            f"XOR {target} {mask}", f"SUB {target} {mask}",
+            f"MOV {product} #0",
+            f"{label}:",
+            # Add the multiplicand when the low bit is set, without a branch: mask = -(bit)
+            f"MOV {mask} {LOOP_COUNTER}", f"AND {mask} #1", f"NOT {mask}", f"INC {mask}",
+            f"AND {mask} {target}", f"ADD {product} {mask}",
+            f"SHL {target} #1", f"SHR {LOOP_COUNTER} #1", f"JNZ {label}",
+            f"MOV {target} {product}"]
+        self.release(mask)
+        self.release(product)
+
+def _has_register_multiply(expr) -> bool:
+    if isinstance(expr, Unary):
+        return _has_register_multiply(expr.operand)
+    if isinstance(expr, Binary):
+        return ((expr.op == '*' and not isinstance(expr.right, Num))
+                or _has_register_multiply(expr.left) or _has_register_multiply(expr.right))
+    return False
+
+def _leftmost(expr):
+    while isinstance(expr, (Unary, Binary)):
+        expr = expr.operand if isinstance(expr, Unary) else expr.left
+    return expr
+
+def _count(expr, name: str) -> int:
+    if isinstance(expr, Var):
+        return expr.name == name
+    if isinstance(expr, Unary):
+        return _count(expr.operand, name)
+    if isinstance(expr, Binary):
+        return _count(expr.left, name) + _count(expr.right, name)
+    return 0
+
+def _substitute(expr, old: str, new: str):
+    if isinstance(expr, Var) and expr.name == old:
+        return Var(new, expr.line)
+    if isinstance(expr, Unary):
+        return Unary(expr.op, _substitute(expr.operand, old, new), expr.line)
+    if isinstance(expr, Binary):
+        return Binary(expr.op, _substitute(expr.left, old, new), _substitute(expr.right, old, new), expr.line)
+    return expr
+
+def _parse(text: str):
+    try:
+        return parse_expression(text.replace('#', ''))
+    except CompileError as e:
+        raise ValueError(str(e).split(': ', 1)[1]) from None
+
+def _comment(text: str, code: List[str], kept: Set[str], overwritten: Set[str] = frozenset()) -> str:
+    """The comment opening an expansion: the line, and the registers it overwrites other than kept"""
+    used = sorted(({word for instruction in code for word in instruction.split()[1:]} & set(REGISTERS) - kept)
+                  | overwritten)
+    return f"; {text}" + (f" (temporaries: {', '.join(used)})" if used else '')
+
+def _translate_assignment(text: str, new_label: Callable[[], str]) -> List[str]:
+    assign = _parse(text)
+    if not isinstance(assign, Assign) or not isinstance(assign.target, Var) or isinstance(assign.value, Assign):
+        raise ValueError("an expression line assigns one expression to one register")
+    dest = assign.target.name.lower()
+    expr = _fold(assign.value)
+    reads = _reads(expr, set())
+    loop = _has_register_multiply(expr)
+    free = [r for r in TEMPORARIES if r not in reads and r != dest and not (loop and r == LOOP_COUNTER)]
+    translator = _Translator(free, new_label)
+    saved = None
+    if loop and LOOP_COUNTER in reads:
+        # The loop counter is an operand: read a copy, and put it back afterwards
+        saved = translator.allocate()
+        translator.emit(f"MOV {saved} {LOOP_COUNTER}")
+        expr = _substitute(expr, LOOP_COUNTER, saved)
+    # eax = eax + 1 updates eax in place; eax = 1 - eax needs a temporary
+    in_place = _count(expr, dest) == 1 and isinstance(_leftmost(expr), Var) and _leftmost(expr).name == dest
+    if (dest in reads and not in_place) or (loop and dest == LOOP_COUNTER):
+        result = translator.allocate()
+        translator.generate(expr, result)
+        translator.emit(f"MOV {dest} {result}")
+    else:
+        translator.generate(expr, dest)
+    if saved and dest != LOOP_COUNTER:
+        translator.emit(f"MOV {LOOP_COUNTER} {saved}")
+    return [_comment(text, translator.code, reads | {dest})] + translator.code
+
+def _translate_branch(text: str, condition: str, label: str, isa_mode: str,
+                      new_label: Callable[[], str]) -> List[str]:
+    cond = _parse(condition)
+    if isinstance(cond, Binary) and cond.op in FLAG_BRANCHES:
+        op, left, right = cond.op, _fold(cond.left), _fold(cond.right)
+    else:
+        op, left, right = '!=', _fold(cond), Num(0, cond.line)
+    if isinstance(left, Num) and isinstance(right, Num):
+        taken = {'<': left.value < right.value, '<=': left.value <= right.value, '>': left.value > right.value,
+                 '>=': left.value >= right.value, '==': left.value == right.value,
+                 '!=': left.value != right.value}[op]
+        return [f"; {text} (always)" if taken else f"; {text} (never)"] + ([f"JMP {label}"] if taken else [])
+    if isinstance(left, Num):
+        op, left, right = MIRROR[op], right, left
+    if _has_register_multiply(left) or _has_register_multiply(right):
+        raise ValueError("multiply two registers on a line of its own before comparing the result")
+    reads = _reads(left, _reads(right, set()))
+    standard = isa_mode != 'arm'
+    # In standard mode CMP writes its result to eax, so eax cannot hold an operand
+    free = [r for r in TEMPORARIES if r not in reads and not (standard and r == 'eax')]
+    translator = _Translator(free, new_label)
+    if not standard:
+        a, b = translator.operand(left, False), translator.operand(right, True)
+        translator.code += [f"CMP {a} {b}", f"{FLAG_BRANCHES[op]} {label}"]
+        return [_comment(text, translator.code, reads)] + translator.code
+
+    # CMP a b sets eax to 1 if a < b, else 0; equality is tested on a - b in eax
+    if op in ('>', '<=') and isinstance(right, Num):
+        op, right = ('>=' if op == '>' else '<'), Num(right.value + 1, right.line)
+    a = translator.operand(left, False)
+    b = translator.operand(right, op in ('<', '>=', '==', '!='))
+    if op in ('==', '!=') and b == 'eax':
+        a, b = b, a
+    if op in ('==', '!='):
+        if a != 'eax':
+            translator.emit(f"MOV eax {a}")
+        if b != '#0':
+            translator.emit(f"SUB eax {b}")
+        translator.emit(f"{'JZ' if op == '==' else 'JNZ'} {label}")
+    elif op == '<':
+        translator.code += [f"CMP {a} {b}", f"JNZ {label}"]
+    elif op == '>=':
+        translator.code += [f"CMP {a} {b}", f"JZ {label}"]
+    elif op == '>':
+        translator.code += [f"CMP {b} {a}", f"JNZ {label}"]
+    else:
+        translator.code += [f"CMP {b} {a}", f"JZ {label}"]
+    return [_comment(text, translator.code, reads, {'eax'})] + translator.code
+
+def translate_line_to_instructions(line: str, new_label: Callable[[], str],
+                                   isa_mode: str = 'standard') -> Optional[List[str]]:
+    """The instructions for an expression line, or None for other lines
+
+    An expression line assigns to a register ('eax = (ebx + 5) * ecx - edx'),
+    jumps ('goto label'), compares and jumps ('if eax < ebx + 1 goto label')
+    or loads a constant without touching memory ('li eax, 10', a MOV eax #10).
+    Operands are registers and constants, with C's operators + - * & | ^ << >>
+    (and unary - and ~), precedence and parentheses; a condition is one
+    comparison (< <= > >= == !=) or an expression tested against zero.
+    Constant parts are folded; multiplying by a constant becomes shifts and
+    adds, multiplying two registers a loop. Registers the line does not
+    mention (other than esp, and ebp only if nothing else is free) may be used
+    as temporaries, eax to count a multiply loop, and in standard mode a
+    comparison leaves its result in eax as CMP does; the expansion starts
+    with a comment naming the registers it overwrites. new_label returns a
+    fresh label. ValueError if the line is not valid.
+    """
+    text = line.split(';')[0].strip()
+    match = LI_LINE.match(text)
+    if match:
+        if match.group(1).lower() not in REGISTERS:
+            raise ValueError(f"LI needs a register, not '{match.group(1)}'")
+        value = _fold(_parse(match.group(2)))
+        if not isinstance(value, Num):
+            raise ValueError("LI loads a constant; use an expression line (reg = ...) to copy a register")
+        return [f"MOV {match.group(1).lower()} #{value.value}"]
+    match = GOTO_LINE.match(text)
+    if match:
+        return [f"JMP {match.group(1)}"]
+    match = IF_LINE.match(text)
+    if match:
+        return _translate_branch(text, match.group(1), match.group(2), isa_mode, new_label)
+    if is_expression_line(text):
+        return _translate_assignment(text, new_label)
+    return None
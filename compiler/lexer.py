@@ -0,0 +1,43 @@
+import re
+from dataclasses import dataclass
+from typing import List
+
+KEYWORDS = {'int', 'void', 'if', 'else', 'while', 'for', 'return', 'break', 'continue'}
+
+# Longest operators first so '<=' is not read as '<' '='
+OPERATORS = ['<<=', '>>=', '==', '!=', '<=', '>=', '&&', '||', '<<', '>>', '+=', '-=', '*=', '/=', '%=', '++', '--',
+             '+', '-', '*', '/', '%', '&', '|', '^', '~', '!', '<', '>', '=', '(', ')', '{', '}', '[', ']', ';', ',']
+
+TOKEN_PATTERN = re.compile(r'\s+|//[^\n]*|/\*.*?\*/|(?P<number>0[xX][0-9a-fA-F]+|\d+)|(?P<name>[A-Za-z_]\w*)|'
+                           r'(?P<op>' + '|'.join(re.escape(op) for op in OPERATORS) + ')', re.DOTALL)
+
+class CompileError(ValueError):
+    def __init__(self, line: int, message: str):
+        super().__init__(f"line {line}: {message}")
+        self.line = line
+
+@dataclass
+class Token:
+    kind: str    # 'number', 'name', 'keyword', 'op' or 'end'
+    text: str
+    line: int
+
+def tokenize(source: str) -> List[Token]:
+    tokens = []
+    position = 0
+    line = 1
+    while position < len(source):
+        match = TOKEN_PATTERN.match(source, position)
+        if not match:
+            raise CompileError(line, f"unexpected character '{source[position]}'")
+        text = match.group(0)
+        if match.lastgroup == 'number':
+            tokens.append(Token('number', text, line))
+        elif match.lastgroup == 'name':
+            tokens.append(Token('keyword' if text in KEYWORDS else 'name', text, line))
+        elif match.lastgroup == 'op':
+            tokens.append(Token('op', text, line))
+        line += text.count('\n')
+        position = match.end()
+    tokens.append(Token('end', '', line))
+    return tokens
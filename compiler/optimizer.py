@@ -0,0 +1,405 @@
+import argparse
+import itertools
+import operator
+import os
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Tuple
+
+from isa import FLAG_CONDITIONS, expand_macro_lines
+from locality import run_program
+from utils.files import read_text
+
+# Registers whose values the optimizer follows; anything else (zero, FP registers) ends what it knows
+REGISTERS = ('eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'ebp', 'esp')
+WORD_BITS = 32
+
+ALU = {'ADD': operator.add, 'SUB': operator.sub, 'AND': operator.and_, 'OR': operator.or_,
+       'XOR': operator.xor, 'SHL': operator.lshift, 'SHR': operator.rshift}
+UNARY = {'INC': lambda x: x + 1, 'DEC': lambda x: x - 1, 'NOT': operator.invert}
+FLAG_WRITERS = set(ALU) | set(UNARY) | {'CMP', 'TEST'}
+FLAG_READERS = {condition.name for condition in FLAG_CONDITIONS}
+# Operands that leave a value unchanged: x + 0, x << 0, ...
+IDENTITIES = {'ADD': 0, 'SUB': 0, 'OR': 0, 'XOR': 0, 'SHL': 0, 'SHR': 0}
+
+# Rewrite kinds, in the order reports list them
+KINDS = ('constant folding', 'redundant load', 'redundant store', 'strength reduction', 'dead code')
+
+# A symbolic value: (base, offset) is the base value plus offset; base None means the constant offset.
+# Bases name a register's value on entry to the basic block or the result of an instruction the
+# optimizer cannot evaluate, so they never change meaning within the block.
+Value = Tuple[Optional[str], int]
+
+@dataclass
+class Rewrite:
+    before: str
+    after: Optional[str]    # None when the instruction was removed
+    kind: str
+
+@dataclass
+class Optimization:
+    """An optimized program and what changed"""
+    before: List[str]
+    after: List[str]
+    rewrites: List[Rewrite] = field(default_factory=list)
+
+    def counts(self) -> Dict[str, int]:
+        return {kind: sum(1 for r in self.rewrites if r.kind == kind) for kind in KINDS}
+
+def _split(line: str) -> Tuple[Optional[str], List[str], str]:
+    """(mnemonic, operands, comment) of an instruction line; mnemonic None for anything else"""
+    text, _, comment = line.partition(';')
+    text = text.strip()
+    if not text or text.startswith('.') or text.endswith(':'):
+        return None, [], comment
+    parts = text.split()
+    return parts[0].upper(), parts[1:], comment
+
+def count_instructions(lines: List[str]) -> int:
+    return sum(1 for line in expand_macro_lines(lines) if _split(line)[0])
+
+class Peephole:
+    """One pass of the optimizer over a program's lines
+
+    Within each basic block, a forward walk follows what every register
+    and remembered memory word holds, as constants or as a base value plus
+    an offset. That lets it fold constant arithmetic, propagate constants
+    into operands, drop loads and address computations whose result a
+    register already holds, drop stores of a value the word already holds,
+    and replace calls to the compiler's __mul routine with a constant
+    power-of-two multiplier by a shift. A backward walk then removes moves
+    into registers that are overwritten before they are read.
+
+    Flags are only given up (by folding ADD eax #1 into a MOV, say) where
+    the next flag-reading branch is preceded by an instruction that sets
+    them again. Memory is ordinary memory: a word holds what was last
+    stored to it.
+    """
+
+    def __init__(self, lines: List[str]):
+        self.lines = list(lines)
+        self.rewrites: List[Rewrite] = []
+        self.arm = any(line.split(';')[0].split()[:2] == ['.mode', 'arm'] for line in lines)
+        self.has_mul = any(line.split(';')[0].strip() == '__mul:' for line in lines)
+        self.symbols = itertools.count()
+
+    def rewrite(self, index: int, text: Optional[str], kind: str) -> None:
+        before = self.lines[index]
+        comment = before.partition(';')[2] if ';' in before else ''
+        if text is not None and comment:
+            text = f"{text}  ;{comment}"
+        self.rewrites.append(Rewrite(before, text, kind))
+        self.lines[index] = text
+
+    def run(self) -> List[str]:
+        self.forward()
+        self.lines = [line for line in self.lines if line is not None]
+        self.backward()
+        return [line for line in self.lines if line is not None]
+
+    # Forward: values
+    def reset(self) -> None:
+        self.values: Dict[str, Value] = {register: (register, 0) for register in REGISTERS}
+        self.memory: Dict[Value, Value] = {}
+
+    def fresh(self) -> Value:
+        return (f"v{next(self.symbols)}", 0)
+
+    def value(self, operand: str) -> Optional[Value]:
+        """What a register or immediate operand holds, None for other operands"""
+        if operand.startswith('#'):
+            try:
+                return (None, int(operand[1:]))
+            except ValueError:
+                return None
+        return self.values.get(operand)
+
+    def address(self, operand: str) -> Optional[Value]:
+        """The address a [..] operand names, None if it cannot be followed"""
+        inner = operand[1:-1]
+        if inner.isdigit():
+            return (None, int(inner))
+        return self.values.get(inner)
+
+    def holder(self, value: Value) -> Optional[str]:
+        """An operand that supplies value without a memory access"""
+        if value[0] is None:
+            return f"#{value[1]}"
+        for register in REGISTERS:
+            if self.values[register] == value:
+                return register
+        return None
+
+    def flags_dead(self, index: int) -> bool:
+        """Whether no branch reads the flags an instruction at index sets before something sets them again"""
+        if not self.arm:
+            return True  # Flag branches need ARM mode, so nothing reads the flags
+        for line in self.lines[index + 1:]:
+            if line is None:
+                continue
+            text = line.split(';')[0].strip()
+            if not text:
+                continue
+            op = _split(line)[0]
+            if op is None or op in FLAG_READERS:
+                return False  # A label may be reached with other flags; a directive may change modes
+            if op in FLAG_WRITERS:
+                return True
+            if op != 'MOV':
+                return False
+        return True
+
+    def store(self, key: Optional[Value], value: Value) -> None:
+        if key is None:
+            self.memory.clear()
+            return
+        # Words at other offsets from the same base cannot be this one; anything else might be
+        self.memory = {k: v for k, v in self.memory.items() if k[0] == key[0] and k[1] != key[1]}
+        self.memory[key] = value
+
+    def forward(self) -> None:
+        self.reset()
+        for index in range(len(self.lines)):
+            line = self.lines[index]
+            if line is None:
+                continue
+            op, operands, _ = _split(line)
+            if op is None:
+                if line.split(';')[0].strip():
+                    self.reset()  # Labels start a basic block; directives may change the machine
+                continue
+            if not self.step(index, op, operands):
+                self.reset()
+
+    def step(self, index: int, op: str, operands: List[str]) -> bool:
+        """Follow one instruction, rewriting it if possible; False ends the basic block"""
+        if op == 'MOV' and len(operands) == 2:
+            return self.move(index, *operands)
+        if op in ALU and len(operands) == 2 and operands[0] in REGISTERS:
+            dest, src = operands
+            source = self.value(src)
+            if source is None:
+                return False
+            current = self.values[dest]
+            result = self.evaluate(op, current, source)
+            if result is not None and result[0] is None and self.flags_dead(index):
+                self.rewrite(index, f"MOV {dest} #{result[1]}", 'constant folding')
+            elif source[0] is None and IDENTITIES.get(op) == source[1] and self.flags_dead(index):
+                self.rewrite(index, None, 'constant folding')
+            elif source[0] is None and not src.startswith('#'):
+                self.rewrite(index, f"{op} {dest} #{source[1]}", 'constant folding')
+            self.values[dest] = result if result is not None else self.fresh()
+            return True
+        if op in UNARY and len(operands) == 1 and operands[0] in REGISTERS:
+            dest = operands[0]
+            current = self.values[dest]
+            if current[0] is None:
+                result = (None, UNARY[op](current[1]))
+                if self.flags_dead(index):
+                    self.rewrite(index, f"MOV {dest} #{result[1]}", 'constant folding')
+            elif op in ('INC', 'DEC'):
+                result = (current[0], UNARY[op](current[1]))
+            else:
+                result = self.fresh()
+            self.values[dest] = result
+            return True
+        if op in ('CMP', 'TEST') and len(operands) == 2 and operands[0] in REGISTERS:
+            source = self.value(operands[1])
+            if source is None:
+                return False
+            if source[0] is None and not operands[1].startswith('#'):
+                self.rewrite(index, f"{op} {operands[0]} #{source[1]}", 'constant folding')
+            if not self.arm:
+                self.values['eax' if op == 'CMP' else operands[0]] = self.fresh()  # The comparison's result
+            return True
+        if op == 'JAL' and operands == ['__mul'] and self.has_mul:
+            multiplier = self.values['ebx']
+            if multiplier[0] is None and multiplier[1] >= 0 and not multiplier[1] & (multiplier[1] - 1):
+                if multiplier[1] == 0:
+                    self.rewrite(index, "MOV eax #0", 'strength reduction')
+                    self.values['eax'] = (None, 0)
+                else:
+                    shift = multiplier[1].bit_length() - 1
+                    self.rewrite(index, f"SHL eax #{shift}", 'strength reduction')
+                    self.values['eax'] = self.fresh()
+                # __mul leaves these changed; nothing the compiler emits reads them after the call
+                for register in ('ebx', 'ecx', 'edx', 'ebp'):
+                    self.values[register] = self.fresh()
+                return True
+        return False
+
+    def evaluate(self, op: str, current: Value, source: Value) -> Optional[Value]:
+        if current[0] is None and source[0] is None:
+            if op in ('SHL', 'SHR') and not 0 <= source[1] <= WORD_BITS:
+                return None  # The CPU rejects the shift; leave it to fail there
+            return (None, ALU[op](current[1], source[1]))
+        if source[0] is None and op in ('ADD', 'SUB'):
+            return (current[0], ALU[op](current[1], source[1]))
+        return None
+
+    def move(self, index: int, dest: str, src: str) -> bool:
+        if dest in REGISTERS:
+            if src.startswith('['):
+                key = self.address(src)
+                value = self.memory.get(key) if key is not None else None
+                if value is None:
+                    value = self.fresh()
+                    if key is not None:
+                        self.memory[key] = value  # The word now has a name: whatever dest holds
+                elif self.values[dest] == value:
+                    self.rewrite(index, None, 'redundant load')
+                elif self.holder(value):
+                    self.rewrite(index, f"MOV {dest} {self.holder(value)}", 'redundant load')
+            else:
+                value = self.value(src)
+                if value is None:
+                    return False
+                if self.values[dest] == value:
+                    self.rewrite(index, None, 'redundant load')
+                elif self.recomputes(index, dest, value):
+                    return True  # dest keeps the value it holds
+                elif value[0] is None and not src.startswith('#'):
+                    self.rewrite(index, f"MOV {dest} #{value[1]}", 'constant folding')
+            self.values[dest] = value
+            return True
+        if dest.startswith('['):
+            value = self.value(src)
+            if value is None:
+                return False
+            key = self.address(dest)
+            if key is not None and self.memory.get(key) == value:
+                self.rewrite(index, None, 'redundant store')
+                return True
+            if value[0] is None and not src.startswith('#'):
+                self.rewrite(index, f"MOV {dest} #{value[1]}", 'constant folding')
+            self.store(key, value)
+            return True
+        return False
+
+    def recomputes(self, index: int, dest: str, value: Value) -> bool:
+        """Drop MOV dest src; ADD/SUB dest #n when it rebuilds the value dest already holds"""
+        following = next((i for i in range(index + 1, len(self.lines))
+                          if self.lines[i] is not None and self.lines[i].split(';')[0].strip()), None)
+        if following is None:
+            return False
+        op, operands, _ = _split(self.lines[following])
+        if op not in ('ADD', 'SUB') or len(operands) != 2 or operands[0] != dest:
+            return False
+        offset = self.value(operands[1])
+        if offset is None or offset[0] is not None:
+            return False
+        result = (value[0], ALU[op](value[1], offset[1]))
+        if result != self.values[dest] or not self.flags_dead(following):
+            return False
+        self.rewrite(index, None, 'redundant load')
+        self.rewrite(following, None, 'redundant load')
+        return True
+
+    # Backward: liveness
+    def backward(self) -> None:
+        live = set(REGISTERS)
+        for index in range(len(self.lines) - 1, -1, -1):
+            op, operands, _ = _split(self.lines[index])
+            if op is None:
+                if self.lines[index].split(';')[0].strip():
+                    live = set(REGISTERS)
+                continue
+            reads, writes = self.registers_used(op, operands)
+            if op == 'MOV' and writes and not writes & live:
+                self.rewrite(index, None, 'dead code')
+                continue
+            live = (live - writes) | reads
+
+    def registers_used(self, op: str, operands: List[str]) -> Tuple[set, set]:
+        """Registers an instruction reads and writes; unfamiliar instructions read them all and end a block"""
+        def read(operand):
+            inner = operand[1:-1] if operand.startswith('[') else operand
+            return {inner} & set(REGISTERS)
+
+        if op == 'MOV' and len(operands) == 2:
+            dest, src = operands
+            if dest in REGISTERS:
+                return read(src), {dest}
+            return read(src) | read(dest), set()
+        if op in ALU and len(operands) == 2 and operands[0] in REGISTERS:
+            return read(operands[0]) | read(operands[1]), {operands[0]}
+        if op in UNARY and len(operands) == 1 and operands[0] in REGISTERS:
+            return {operands[0]}, {operands[0]}
+        if op in ('CMP', 'TEST') and len(operands) == 2:
+            writes = set() if self.arm else {'eax' if op == 'CMP' else operands[0]} & set(REGISTERS)
+            return read(operands[0]) | read(operands[1]), writes
+        return set(REGISTERS), set()
+
+def optimize(lines: List[str], max_passes: int = 10) -> Optimization:
+    """Apply peephole passes until nothing changes (or max_passes)
+
+    Macros are expanded first, so the optimized program has none. ValueError
+    for a macro error.
+    """
+    result = Optimization(list(lines), expand_macro_lines(lines))
+    for _ in range(max_passes):
+        peephole = Peephole(result.after)
+        after = peephole.run()
+        if not peephole.rewrites:
+            break
+        result.rewrites += peephole.rewrites
+        result.after = after
+    return result
+
+def compare(optimization: Optimization) -> Dict[str, Dict]:
+    """Static and executed instruction counts and pipeline cycles of both versions, each run on a fresh machine"""
+    rows = {}
+    for name, lines in (('before', optimization.before), ('after', optimization.after)):
+        stats = run_program(lines)
+        rows[name] = {'static': count_instructions(lines), 'executed': stats['instructions'],
+                      'cycles': stats['pipeline_cycles']}
+    return rows
+
+def format_comparison(optimization: Optimization, rows: Dict[str, Dict]) -> List[str]:
+    before, after = rows['before'], rows['after']
+    def change(key):
+        if not before[key]:
+            return ""
+        return f"{100 * (after[key] - before[key]) / before[key]:+.1f}%"
+
+    lines = [f"{'':<24} {'unoptimized':>12} {'optimized':>12} {'change':>8}"]
+    for key, title in (('static', 'Program instructions'), ('executed', 'Executed instructions'),
+                       ('cycles', 'Pipeline cycles')):
+        lines.append(f"{title:<24} {before[key]:12d} {after[key]:12d} {change(key):>8}")
+    lines += ["", "Rewrites:"]
+    lines += [f"  {kind:<20} {count}" for kind, count in optimization.counts().items() if count]
+    return lines
+
+def format_rewrites(optimization: Optimization) -> List[str]:
+    """Every rewrite, pass by pass, as before -> after"""
+    return [f"  {r.before.strip():<28} -> {(r.after or '(removed)').strip():<24} {r.kind}"
+            for r in optimization.rewrites]
+
+def main():
+    parser = argparse.ArgumentParser(prog='python -m compiler.optimizer',
+                                     description="Peephole-optimize a C or assembly program and compare "
+                                                 "instruction and cycle counts before and after")
+    parser.add_argument('program', help="C source (.c) or assembly program, or - for assembly on standard input")
+    parser.add_argument('-o', '--output', help="Write the optimized assembly here")
+    parser.add_argument('--rewrites', action='store_true', help="List every rewrite")
+    args = parser.parse_args()
+
+    from . import CompileError, compile_source
+    try:
+        text = read_text(args.program)
+        lines = (compile_source(text, os.path.basename(args.program)) if args.program.endswith('.c')
+                 else text.splitlines())
+    except (OSError, CompileError) as e:
+        print(f"Error: {e}")
+        return 1
+    optimization = optimize(lines)
+    if args.output:
+        with open(args.output, 'w') as f:
+            f.write('\n'.join(optimization.after) + '\n')
+    print('\n'.join(format_comparison(optimization, compare(optimization))))
+    if args.rewrites:
+        print('\n'.join(format_rewrites(optimization)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,346 @@
+from dataclasses import dataclass, field
+from typing import List, Optional, Union
+
+from .lexer import CompileError, Token, tokenize
+
+# Expressions
+@dataclass
+class Num:
+    value: int
+    line: int
+
+@dataclass
+class Var:
+    name: str
+    line: int
+
+@dataclass
+class Index:
+    name: str
+    index: 'Expr'
+    line: int
+
+@dataclass
+class Call:
+    name: str
+    args: List['Expr']
+    line: int
+
+@dataclass
+class Unary:
+    op: str      # '-', '!' or '~'
+    operand: 'Expr'
+    line: int
+
+@dataclass
+class Binary:
+    op: str
+    left: 'Expr'
+    right: 'Expr'
+    line: int
+
+@dataclass
+class Assign:
+    target: Union[Var, Index]
+    value: 'Expr'
+    line: int
+
+@dataclass
+class Postfix:
+    op: str      # '++' or '--'
+    target: Var
+    line: int
+
+Expr = Union[Num, Var, Index, Call, Unary, Binary, Assign, Postfix]
+
+# Declarations and statements
+@dataclass
+class VarDecl:
+    name: str
+    size: Optional[int]                      # Element count of an array, None for an int
+    init: Union[None, Expr, List[int]]       # An expression, or constants for a global array
+    line: int
+
+@dataclass
+class Block:
+    items: List['Stmt']
+    line: int
+
+@dataclass
+class If:
+    cond: Expr
+    then: 'Stmt'
+    otherwise: Optional['Stmt']
+    line: int
+
+@dataclass
+class While:
+    cond: Expr
+    body: 'Stmt'
+    line: int
+
+@dataclass
+class For:
+    init: Union[None, Expr, VarDecl]
+    cond: Optional[Expr]
+    step: Optional[Expr]
+    body: 'Stmt'
+    line: int
+
+@dataclass
+class Return:
+    value: Optional[Expr]
+    line: int
+
+@dataclass
+class Jump:
+    kind: str    # 'break' or 'continue'
+    line: int
+
+@dataclass
+class ExprStmt:
+    expr: Expr
+    line: int
+
+Stmt = Union[VarDecl, Block, If, While, For, Return, Jump, ExprStmt]
+
+@dataclass
+class Param:
+    name: str
+    is_array: bool   # int a[] - the caller passes the array's address
+
+@dataclass
+class Function:
+    name: str
+    returns_value: bool
+    params: List[Param]
+    body: Block
+    line: int
+
+@dataclass
+class Program:
+    globals: List[VarDecl] = field(default_factory=list)
+    functions: List[Function] = field(default_factory=list)
+
+# Binary operators from lowest to highest precedence; all are left associative
+PRECEDENCE = [['||'], ['&&'], ['|'], ['^'], ['&'], ['==', '!='], ['<', '<=', '>', '>='], ['<<', '>>'],
+              ['+', '-'], ['*', '/', '%']]
+ASSIGNMENT_OPERATORS = {'=', '+=', '-=', '*=', '/=', '%=', '<<=', '>>='}
+
+class Parser:
+    """Recursive-descent parser for the C subset: int scalars and arrays, functions, if/while/for"""
+
+    def __init__(self, source: str):
+        self.tokens = tokenize(source)
+        self.position = 0
+
+    @property
+    def token(self) -> Token:
+        return self.tokens[self.position]
+
+    def _advance(self) -> Token:
+        token = self.token
+        self.position += 1
+        return token
+
+    def _accept(self, text: str) -> bool:
+        if self.token.kind in ('op', 'keyword') and self.token.text == text:
+            self.position += 1
+            return True
+        return False
+
+    def _expect(self, text: str) -> Token:
+        if not (self.token.kind in ('op', 'keyword') and self.token.text == text):
+            raise CompileError(self.token.line, f"expected '{text}' but found '{self.token.text or 'end of file'}'")
+        return self._advance()
+
+    def _name(self) -> Token:
+        if self.token.kind != 'name':
+            raise CompileError(self.token.line, f"expected a name but found '{self.token.text or 'end of file'}'")
+        return self._advance()
+
+    def _number(self) -> int:
+        negative = self._accept('-')
+        if self.token.kind != 'number':
+            raise CompileError(self.token.line, f"expected a number but found '{self.token.text}'")
+        value = int(self._advance().text, 0)
+        return -value if negative else value
+
+    def parse(self) -> Program:
+        program = Program()
+        while self.token.kind != 'end':
+            line = self.token.line
+            if self._accept('void'):
+                program.functions.append(self._function(self._name().text, False, line))
+                continue
+            self._expect('int')
+            name = self._name().text
+            if self.token.text == '(':
+                program.functions.append(self._function(name, True, line))
+            else:
+                program.globals.append(self._declaration_rest(name, line, constant_init=True))
+        return program
+
+    def _function(self, name: str, returns_value: bool, line: int) -> Function:
+        self._expect('(')
+        params = []
+        if not self._accept(')'):
+            if self.token.text == 'void' and self.tokens[self.position + 1].text == ')':
+                self._advance()
+            else:
+                while True:
+                    self._expect('int')
+                    param = self._name().text
+                    is_array = self._accept('[')
+                    if is_array:
+                        self._expect(']')
+                    params.append(Param(param, is_array))
+                    if not self._accept(','):
+                        break
+            self._expect(')')
+        return Function(name, returns_value, params, self._block(), line)
+
+    def _declaration_rest(self, name: str, line: int, constant_init: bool) -> VarDecl:
+        """The rest of 'int name...;' after the name"""
+        size = None
+        init = None
+        if self._accept('['):
+            size = self._number()
+            if size <= 0:
+                raise CompileError(line, f"array '{name}' must have a positive size")
+            self._expect(']')
+        if self._accept('='):
+            if size is not None:
+                if not constant_init:
+                    raise CompileError(line, "local arrays cannot have initializers")
+                self._expect('{')
+                init = [self._number()]
+                while self._accept(','):
+                    init.append(self._number())
+                self._expect('}')
+                if len(init) > size:
+                    raise CompileError(line, f"{len(init)} initializers for '{name}[{size}]'")
+            elif constant_init:
+                init = Num(self._number(), line)
+            else:
+                init = self._expression()
+        self._expect(';')
+        return VarDecl(name, size, init, line)
+
+    def _block(self) -> Block:
+        line = self._expect('{').line
+        items = []
+        while not self._accept('}'):
+            if self.token.kind == 'end':
+                raise CompileError(self.token.line, "missing '}'")
+            items.append(self._statement())
+        return Block(items, line)
+
+    def _statement(self) -> Stmt:
+        line = self.token.line
+        if self.token.text == '{':
+            return self._block()
+        if self._accept('int'):
+            return self._declaration_rest(self._name().text, line, constant_init=False)
+        if self._accept('if'):
+            self._expect('(')
+            cond = self._expression()
+            self._expect(')')
+            then = self._statement()
+            return If(cond, then, self._statement() if self._accept('else') else None, line)
+        if self._accept('while'):
+            self._expect('(')
+            cond = self._expression()
+            self._expect(')')
+            return While(cond, self._statement(), line)
+        if self._accept('for'):
+            self._expect('(')
+            if self._accept('int'):
+                init = self._declaration_rest(self._name().text, line, constant_init=False)
+            else:
+                init = None if self.token.text == ';' else self._expression()
+                self._expect(';')
+            cond = None if self.token.text == ';' else self._expression()
+            self._expect(';')
+            step = None if self.token.text == ')' else self._expression()
+            self._expect(')')
+            return For(init, cond, step, self._statement(), line)
+        if self._accept('return'):
+            value = None if self.token.text == ';' else self._expression()
+            self._expect(';')
+            return Return(value, line)
+        if self.token.text in ('break', 'continue'):
+            kind = self._advance().text
+            self._expect(';')
+            return Jump(kind, line)
+        if self._accept(';'):
+            return Block([], line)
+        expr = self._expression()
+        self._expect(';')
+        return ExprStmt(expr, line)
+
+    def _expression(self) -> Expr:
+        left = self._binary(0)
+        if self.token.kind == 'op' and self.token.text in ASSIGNMENT_OPERATORS:
+            op = self._advance()
+            if not isinstance(left, (Var, Index)):
+                raise CompileError(op.line, f"cannot assign to this expression")
+            value = self._expression()
+            if op.text != '=':
+                value = Binary(op.text[:-1], left, value, op.line)
+            return Assign(left, value, op.line)
+        return left
+
+    def _binary(self, level: int) -> Expr:
+        if level == len(PRECEDENCE):
+            return self._unary()
+        left = self._binary(level + 1)
+        while self.token.kind == 'op' and self.token.text in PRECEDENCE[level]:
+            op = self._advance()
+            left = Binary(op.text, left, self._binary(level + 1), op.line)
+        return left
+
+    def _unary(self) -> Expr:
+        if self.token.kind == 'op' and self.token.text in ('-', '!', '~', '+'):
+            op = self._advance()
+            operand = self._unary()
+            return operand if op.text == '+' else Unary(op.text, operand, op.line)
+        return self._postfix()
+
+    def _postfix(self) -> Expr:
+        token = self.token
+        if token.kind == 'number':
+            self._advance()
+            return Num(int(token.text, 0), token.line)
+        if self._accept('('):
+            expr = self._expression()
+            self._expect(')')
+            return expr
+        name = self._name()
+        if self._accept('('):
+            args = []
+            if not self._accept(')'):
+                args.append(self._expression())
+                while self._accept(','):
+                    args.append(self._expression())
+                self._expect(')')
+            return Call(name.text, args, name.line)
+        if self._accept('['):
+            index = self._expression()
+            self._expect(']')
+            return Index(name.text, index, name.line)
+        var = Var(name.text, name.line)
+        if self.token.kind == 'op' and self.token.text in ('++', '--'):
+            return Postfix(self._advance().text, var, name.line)
+        return var
+
+def parse(source: str) -> Program:
+    return Parser(source).parse()
+
+def parse_expression(source: str) -> Expr:
+    """One expression (an assignment is an expression), which must be all of source"""
+    parser = Parser(source)
+    expr = parser._expression()
+    if parser.token.kind != 'end':
+        raise CompileError(parser.token.line, f"unexpected '{parser.token.text}' after the expression")
+    return expr
@@ -0,0 +1,424 @@
+import argparse
+import cmd
+import sys
+from typing import Optional
+
+from cachestate import format_cache, load_cache_state, save_cache_state
+from evictions import AccessHistory, EvictionLog, explain_miss
+from explain import StepRecorder, explain_step
+from hexdump import dump_rows, format_dump, parse_range
+from isa import SimpleISA, call_depth_change, symbol_for
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from profiler import FunctionProfiler, format_profile
+from snapshots import CURRENT, SnapshotStore, format_diff
+from utils.logger import LogLevel, add_logging_arguments, configure_logging
+
+class Debugger(cmd.Cmd):
+    """Line-oriented debugger over a SimpleISA on the default cache hierarchy
+
+    Commands read from a terminal or, one per line, from a piped script.
+    """
+
+    intro = "CPU simulator debugger. Type help or ? to list commands."
+    prompt = "(sim) "
+
+    def __init__(self, program_path: Optional[str] = None, stdin=None, stdout=None):
+        super().__init__(stdin=stdin, stdout=stdout)
+        if stdin is not None:
+            self.use_rawinput = False
+        self.program_path = None
+        self.explaining = False  # Explain every step as it runs
+        self._new_machine()
+        if program_path:
+            self.do_load(program_path)
+
+    def _new_machine(self) -> None:
+        memory, l1, self.l2 = build_hierarchy()
+        self.isa = SimpleISA(memory=memory, cache=l1)
+        self.isa.pipeline = PipelineModel(self.isa)  # Clock for run-cycles
+        self.snapshots = SnapshotStore(self.isa)
+        self.recorder = StepRecorder(self.isa)
+        self.profiler = FunctionProfiler(self.isa)
+        self.eviction_log = EvictionLog(self.isa)
+        self.access_history = AccessHistory(self.isa)  # With the eviction log, explains misses for why-miss
+        self.last_record = None
+
+    def _print(self, text: str = "") -> None:
+        self.stdout.write(text + "\n")
+
+    def _pc_name(self, pc: int) -> str:
+        symbol = symbol_for(pc, self.isa.labels)
+        return f"pc {pc} <{symbol}>" if symbol else f"pc {pc}"
+
+    def _where(self) -> str:
+        isa = self.isa
+        where = self._pc_name(isa.pc)
+        if isa.pc < len(isa.instructions):
+            return f"{where}: {isa.instructions[isa.pc].source}"
+        return f"{where}: end of program"
+
+    def _execute_step(self) -> bool:
+        """One instruction, recorded for explain and explained at once when explaining is on"""
+        self.recorder.begin()
+        running = self.isa.execute_step()
+        self.last_record = self.recorder.finish() or self.last_record
+        if self.explaining and self.last_record:
+            self._explain(self.last_record)
+        return running
+
+    def _explain(self, record) -> None:
+        self._print(f"step {record.step}:")
+        for sentence in explain_step(record, self.isa.cache):
+            self._print(f"  {sentence}")
+
+    def emptyline(self):
+        return False  # Unlike cmd's default, an empty line does not repeat the last command
+
+    def precmd(self, line):
+        # Command names are typed with dashes (run-cycles) but cmd looks up do_ methods, which use underscores
+        command, space, rest = line.partition(' ')
+        return command.replace('-', '_') + space + rest
+
+    # Program control
+    def do_load(self, arg):
+        """load FILE - assemble a program and reset the machine (snapshots are kept)"""
+        path = arg.strip() or self.program_path
+        if not path:
+            self._print("usage: load FILE")
+            return
+        try:
+            with open(path) as f:
+                lines = f.read().splitlines()
+        except OSError as e:
+            self._print(f"Cannot open {path}: {e}")
+            return
+        snapshots = self.snapshots.snapshots
+        self._new_machine()
+        self.snapshots.snapshots = snapshots
+        try:
+            self.isa.load_program(lines)
+        except ValueError as e:
+            self._print(f"Cannot load {path}: {e}")
+            return
+        self.program_path = path
+        self._print(f"Loaded {len(self.isa.instructions)} instructions from {path}")
+
+    def do_step(self, arg):
+        """step [N] - execute N instructions (default 1)"""
+        try:
+            count = int(arg) if arg.strip() else 1
+        except ValueError:
+            count = None
+        if count is None or count < 0:
+            self._print("usage: step [N]")
+            return
+        for _ in range(count):
+            if not self._execute_step():
+                self._print(f"Program stopped after {self.isa.instruction_count} instructions")
+                break
+        self._print(self._where())
+
+    def do_next(self, arg):
+        """next - step over: execute one instruction, running the whole subroutine when it is a JAL"""
+        depth = 0
+        while True:
+            isa = self.isa
+            if isa.pc < len(isa.instructions):
+                depth += call_depth_change(isa.instructions[isa.pc])
+            if not self._execute_step():
+                self._print(f"Program stopped after {self.isa.instruction_count} instructions")
+                break
+            if depth <= 0:
+                break
+        self._print(self._where())
+
+    def do_until(self, arg):
+        """until LABEL|ADDR - run until the instruction at a label or instruction address is next"""
+        if not arg.strip():
+            self._print("usage: until LABEL|ADDR")
+            return
+        try:
+            target = self.isa.instruction_index(arg)
+        except ValueError as e:
+            self._print(str(e))
+            return
+        while True:
+            if not self._execute_step():
+                self._print(f"Program stopped after {self.isa.instruction_count} instructions "
+                            f"without reaching {arg.strip()}")
+                break
+            if self.isa.pc == target:
+                break
+        self._print(self._where())
+
+    def do_run(self, arg):
+        """run - execute until the program halts"""
+        while self._execute_step():
+            pass
+        self._print(f"Program stopped after {self.isa.instruction_count} instructions, {self._where()}")
+
+    def do_run_cycles(self, arg):
+        """run-cycles N - execute until N more pipeline cycles have passed, or the program halts"""
+        try:
+            cycles = int(arg)
+        except ValueError:
+            cycles = None
+        if cycles is None or cycles < 0:
+            self._print("usage: run-cycles N")
+            return
+        pipeline = self.isa.pipeline
+        start, count = pipeline.total_cycles, self.isa.instruction_count
+        while pipeline.total_cycles - start < cycles:
+            if not self._execute_step():
+                self._print(f"Program stopped after {self.isa.instruction_count} instructions")
+                break
+        self._print(f"Ran {self.isa.instruction_count - count} instructions in {pipeline.total_cycles - start} "
+                    f"cycles, {self._where()}")
+
+    def _memory_address(self, arg: str, usage: str) -> Optional[int]:
+        """A memory word's address (decimal or 0x hex, [ ] optional), or None after printing why it is not one"""
+        try:
+            addr = int(arg.strip().strip('[]').strip(), 0)
+        except ValueError:
+            self._print(usage)
+            return None
+        size = len(self.isa.memory.data)
+        if not 0 <= addr < size:
+            self._print(f"Address {addr} is outside memory (0-{size - 1})")
+            return None
+        return addr
+
+    def do_watch(self, arg):
+        """watch ADDR - run until memory word ADDR (decimal or 0x hex, [ ] optional) changes value, or the program halts"""
+        addr = self._memory_address(arg, "usage: watch ADDR")
+        if addr is None:
+            return
+        data = self.isa.memory.data
+        old = data[addr]
+        while True:
+            pc = self.isa.pc
+            if not self._execute_step():
+                self._print(f"Program stopped after {self.isa.instruction_count} instructions; "
+                            f"[{addr}] is still {old}")
+                return
+            if data[addr] != old:
+                break
+        self._print(f"[{addr}] changed {old} -> {data[addr]} at step {self.isa.instruction_count}, "
+                    f"by {self._pc_name(pc)}: {self.isa.instructions[pc].source}")
+        self._print(self._where())
+
+    def do_who_wrote(self, arg):
+        """who-wrote ADDR|REG - the instruction that last wrote a memory word (decimal or 0x hex) or register"""
+        name = arg.strip()
+        if not name:
+            self._print("usage: who-wrote ADDR|REG")
+            return
+        try:
+            writer = self.isa.last_writer(name)
+        except ValueError as e:
+            self._print(str(e))
+            return
+        if name not in self.isa.registers and name not in self.isa.fp_registers:
+            name = f"[{int(name.strip('[]').strip(), 0)}]"
+        if writer is None:
+            self._print(f"{name} has not been written by the program")
+            return
+        source = self.isa.instructions[writer.pc].source if writer.pc < len(self.isa.instructions) else '?'
+        self._print(f"{name} last written by {self._pc_name(writer.pc)}: {source}, "
+                    f"at step {writer.step}, cycle {writer.cycle}")
+
+    def do_why_miss(self, arg):
+        """why-miss ADDR - explain the latest L1 miss on a memory word (decimal or 0x hex) from its set's history"""
+        addr = self._memory_address(arg, "usage: why-miss ADDR")
+        if addr is None:
+            return
+        for line in explain_miss(self.access_history, self.eviction_log, addr):
+            self._print(line)
+
+    def do_explain(self, arg):
+        """explain [on|off] - explain what the last step did, or turn explaining every step on or off"""
+        arg = arg.strip().lower()
+        if arg in ('on', 'off'):
+            self.explaining = arg == 'on'
+            self._print(f"Explaining each step: {arg}")
+        elif arg:
+            self._print("usage: explain [on|off]")
+        elif self.last_record:
+            self._explain(self.last_record)
+        else:
+            self._print("No step to explain yet")
+
+    # State
+    def do_regs(self, arg):
+        """regs - show registers and flags"""
+        isa = self.isa
+        self._print("  ".join(f"{reg}={value}" for reg, value in isa.registers.items()))
+        self._print(f"flags {isa.format_flags()}  {self._where()}")
+
+    def do_symbols(self, arg):
+        """symbols - the program's labels and the instruction addresses they name"""
+        if not self.isa.labels:
+            self._print("No labels")
+        for name, index in sorted(self.isa.labels.items(), key=lambda item: item[1]):
+            self._print(f"{index:5d}  {name}")
+
+    def do_profile(self, arg):
+        """profile - calls, instructions and cycles (one per instruction) per function so far"""
+        for line in format_profile(self.profiler.profile()):
+            self._print(line)
+
+    def do_mem(self, arg):
+        """mem ADDR [COUNT] - show COUNT memory words from ADDR (default 8)"""
+        try:
+            parts = [int(part) for part in arg.split()]
+            addr, count = parts[0], parts[1] if len(parts) > 1 else 8
+        except (ValueError, IndexError):
+            self._print("usage: mem ADDR [COUNT]")
+            return
+        data = self.isa.memory.data
+        for a in range(max(addr, 0), min(addr + count, len(data))):
+            self._print(f"[{a}] {data[a]}")
+
+    def do_dump(self, arg):
+        """dump A..B [> FILE] | dump A [COUNT] - memory words in hex and decimal with their disassembly"""
+        arg, _, path = arg.partition('>')
+        try:
+            first, last = parse_range(arg, len(self.isa.memory.data))
+        except ValueError as e:
+            self._print(f"{e}\nusage: dump A..B [> FILE] | dump A [COUNT]  (decimal or 0x hex)")
+            return
+        lines = format_dump(dump_rows(self.isa, first, last))
+        if not path.strip():
+            for line in lines:
+                self._print(line)
+            return
+        try:
+            with open(path.strip(), 'w') as f:
+                f.write('\n'.join(lines) + '\n')
+        except OSError as e:
+            self._print(f"Cannot write {path.strip()}: {e}")
+            return
+        self._print(f"Wrote {last - first + 1} words to {path.strip()}")
+
+    def do_poke(self, arg):
+        """poke REG VALUE | poke [ADDR] VALUE - set a register or memory word"""
+        parts = arg.split()
+        if len(parts) != 2:
+            self._print("usage: poke REG VALUE | poke [ADDR] VALUE")
+            return
+        target, value = parts
+        try:
+            if target.startswith('[') and target.endswith(']'):
+                self.isa.poke_memory(int(target[1:-1]), int(value))
+            else:
+                self.isa.poke_register(target.lower(), float(value) if target.lower() in self.isa.fp_registers
+                                       else int(value))
+        except ValueError as e:
+            self._print(f"Poke failed: {e}")
+
+    def do_stats(self, arg):
+        """stats [reset] - cache hits and misses, memory cycles and DRAM rows since the start or the last reset"""
+        isa = self.isa
+        if arg.strip() == 'reset':
+            isa.reset_statistics()
+            self._print(f"Statistics reset after {isa.instruction_count} instructions")
+            return
+        if arg.strip():
+            self._print("usage: stats [reset]")
+            return
+        since = f" (since the reset after {isa.stats_reset_at})" if isa.stats_reset_at else ""
+        self._print(f"{isa.measured_instructions} instructions{since}")
+        for cache in (isa.cache, self.l2):
+            stats = cache.get_performance_stats()
+            self._print(f"{cache._name}: {stats['hits']} hits, {stats['misses']} misses "
+                        f"({stats['hit_rate']:.1f}% hit rate)")
+        ports = isa.get_memory_port_stats()
+        self._print(f"Memory: {ports['instruction_fetches']} fetches, {ports['data_accesses']} data accesses, "
+                    f"{ports['port_conflicts']} port conflicts, {ports['cycles']} cycles")
+        dram = isa.memory.dram.get_stats()
+        self._print(f"DRAM: {dram['hits']} row hits, {dram['misses']} misses, {dram['conflicts']} conflicts")
+
+    def do_cache(self, arg):
+        """cache [l1|l2] | cache save FILE | cache load FILE - show the caches' lines, or save or load their state"""
+        isa = self.isa
+        command, _, path = arg.strip().partition(' ')
+        if command in ('save', 'load') and path.strip():
+            path = path.strip()
+            try:
+                if command == 'save':
+                    save_cache_state(path, isa.cache, self.l2)
+                else:
+                    load_cache_state(path, isa.cache, self.l2, isa.memory)
+            except (OSError, ValueError) as e:
+                self._print(f"Cannot {command} {path}: {e}")
+                return
+            self._print(f"{'Saved cache state to' if command == 'save' else 'Loaded cache state from'} {path}")
+        elif command.lower() in ('', 'l1', 'l2'):
+            levels = {'l1': [isa.cache], 'l2': [self.l2]}.get(command.lower(), [isa.cache, self.l2])
+            for cache in levels:
+                for line in format_cache(cache):
+                    self._print(line)
+        else:
+            self._print("usage: cache [l1|l2] | cache save FILE | cache load FILE")
+
+    # Snapshots
+    def do_snapshot(self, arg):
+        """snapshot [NAME] - capture memory under NAME (default s1, s2, ...)"""
+        try:
+            snapshot = self.snapshots.take(arg.strip() or None)
+        except ValueError as e:
+            self._print(str(e))
+            return
+        self._print(f"Snapshot {self.snapshots.describe(snapshot.name)}")
+
+    def do_snapshots(self, arg):
+        """snapshots - list captured snapshots"""
+        for name in self.snapshots.snapshots:
+            self._print(self.snapshots.describe(name))
+        if not self.snapshots.snapshots:
+            self._print("No snapshots")
+
+    def do_diff(self, arg):
+        """diff OLD [NEW] - changed words between two snapshots, or a snapshot and current memory"""
+        names = arg.split()
+        if not 1 <= len(names) <= 2:
+            self._print("usage: diff OLD [NEW]")
+            return
+        old, new = names[0], names[1] if len(names) == 2 else CURRENT
+        try:
+            changes = self.snapshots.diff(old, new)
+        except ValueError as e:
+            self._print(str(e))
+            return
+        self._print(f"{self.snapshots.describe(old)} -> {self.snapshots.describe(new)}")
+        for row in format_diff(changes):
+            self._print(row)
+
+    def do_quit(self, arg):
+        """quit - leave the debugger"""
+        return True
+
+    do_EOF = do_quit
+
+def main():
+    parser = argparse.ArgumentParser(description="Step through a program from the terminal; commands can also "
+                                                 "be piped in, one per line")
+    parser.add_argument('program', nargs='?', help="Assembly program file to load")
+    add_logging_arguments(parser)
+    args = parser.parse_args()
+    logger = configure_logging(args)
+    if not args.verbose:
+        # Cache and register traces would bury the debugger's own output
+        logger.log_level = LogLevel.WARNING
+
+    interactive = sys.stdin.isatty()
+    debugger = Debugger(args.program, stdin=None if interactive else sys.stdin)
+    if not interactive:
+        debugger.intro = None
+        debugger.prompt = ""
+    debugger.cmdloop()
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,169 @@
+import argparse
+import sys
+from dataclasses import dataclass
+from typing import Dict, List, Set, Tuple
+
+from isa import (SimpleISA, Instruction, InstructionType, DEST_READ_INSTRUCTIONS, FLAG_CONDITIONS,
+                 SUBWORD_LOADS, SUBWORD_STORES, WORD_BYTES)
+from utils.files import read_lines
+from verifier import CONDITIONAL_BRANCHES, NO_FALLTHROUGH
+
+# Any register-indirect access may alias any memory word
+UNKNOWN_ADDRESS = 'mem[?]'
+
+# FP instructions that read / write their first (FP register) operand
+FP_DEST_READ_INSTRUCTIONS = {InstructionType.ADD_S, InstructionType.SUB_S, InstructionType.MUL_S,
+                             InstructionType.DIV_S, InstructionType.STORE_S, InstructionType.C_EQ_S,
+                             InstructionType.C_LT_S, InstructionType.C_LE_S}
+FP_DEST_WRITE_INSTRUCTIONS = {InstructionType.MOV_S, InstructionType.LOAD_S, InstructionType.ADD_S,
+                              InstructionType.SUB_S, InstructionType.MUL_S, InstructionType.DIV_S,
+                              InstructionType.CVT_S_W}
+
+def instruction_accesses(isa: SimpleISA, instruction: Instruction) -> Tuple[Set[str], Set[str]]:
+    """Resources (registers, flags, memory words) an instruction reads and writes"""
+    reads = set(isa.register_reads(instruction))
+    writes = set(isa.register_writes(instruction))
+
+    for i, operand in enumerate(instruction.operands):
+        if operand not in isa.fp_registers:
+            continue
+        if i > 0 or instruction.type in FP_DEST_READ_INSTRUCTIONS:
+            reads.add(operand)
+        if i == 0 and instruction.type in FP_DEST_WRITE_INSTRUCTIONS:
+            writes.add(operand)
+
+    for i, operand in enumerate(instruction.operands):
+        if not operand.startswith('['):
+            continue
+        inner = operand[1:-1]
+        if not inner.isdigit():
+            word = UNKNOWN_ADDRESS
+        elif instruction.type in SUBWORD_LOADS or instruction.type in SUBWORD_STORES:
+            word = f"mem[{int(inner) // WORD_BYTES}]"
+        else:
+            word = f"mem[{inner}]"
+        if i > 0 or instruction.type in DEST_READ_INSTRUCTIONS:
+            reads.add(word)
+        if i == 0 and instruction.type not in (InstructionType.CMP, InstructionType.PREF):
+            writes.add(word)
+
+    if instruction.type in DEST_READ_INSTRUCTIONS:
+        writes.add('flags')
+    if instruction.type in FLAG_CONDITIONS:
+        reads.add('flags')
+    if instruction.type in (InstructionType.BC1T, InstructionType.BC1F):
+        reads.add('fcc')
+    if instruction.type in (InstructionType.C_EQ_S, InstructionType.C_LT_S, InstructionType.C_LE_S):
+        writes.add('fcc')
+    return reads, writes
+
+@dataclass
+class Dependency:
+    """An edge from an earlier instruction to a later one that must follow it"""
+    source: int       # Index of the earlier instruction
+    target: int       # Index of the later instruction
+    kind: str         # RAW, WAR or WAW
+    resource: str     # Register, flags or memory word
+
+class DependencyGraph:
+    """RAW/WAR/WAW dependencies between the instructions of each basic block
+
+    Registers come from the ISA's read/write analysis, memory words from
+    [n] operands ([reg] operands conservatively alias every word) and the
+    flags register from ALU instructions and flag branches. Instructions on
+    the same level have no path between them and could execute in parallel.
+    """
+
+    def __init__(self, isa: SimpleISA, instructions: List[Instruction], labels: Dict[str, int]):
+        self.isa = isa
+        self.instructions = instructions
+        self.labels = labels
+        self.edges: List[Dependency] = []
+        for block in self.basic_blocks():
+            self._analyze_block(block)
+
+    def basic_blocks(self) -> List[range]:
+        """Split the program at labels and after branches"""
+        leaders = {0} | {index for index in self.labels.values() if index < len(self.instructions)}
+        for index, instruction in enumerate(self.instructions):
+            if instruction.type in CONDITIONAL_BRANCHES or instruction.type in NO_FALLTHROUGH:
+                leaders.add(index + 1)
+        starts = sorted(index for index in leaders if index < len(self.instructions))
+        return [range(start, end) for start, end in zip(starts, starts[1:] + [len(self.instructions)])]
+
+    @staticmethod
+    def _conflicts(a: str, b: str) -> bool:
+        if a == b:
+            return True
+        return a.startswith('mem[') and b.startswith('mem[') and UNKNOWN_ADDRESS in (a, b)
+
+    def _analyze_block(self, block: range) -> None:
+        history: List[Tuple[int, Set[str], Set[str]]] = []
+        for index in block:
+            reads, writes = instruction_accesses(self.isa, self.instructions[index])
+
+            # RAW: a read depends on the nearest earlier write
+            for resource in reads:
+                for earlier, _, earlier_writes in reversed(history):
+                    if any(self._conflicts(resource, w) for w in earlier_writes):
+                        self.edges.append(Dependency(earlier, index, 'RAW', resource))
+                        break
+
+            # WAW on the nearest earlier write, WAR on every read since then
+            for resource in writes:
+                for earlier, earlier_reads, earlier_writes in reversed(history):
+                    if any(self._conflicts(resource, r) for r in earlier_reads):
+                        self.edges.append(Dependency(earlier, index, 'WAR', resource))
+                    if any(self._conflicts(resource, w) for w in earlier_writes):
+                        self.edges.append(Dependency(earlier, index, 'WAW', resource))
+                        break
+
+            history.append((index, reads, writes))
+
+    def levels(self) -> List[int]:
+        """Earliest step each instruction could run at if only dependencies constrained it"""
+        level = [0] * len(self.instructions)
+        for edge in sorted(self.edges, key=lambda e: e.target):
+            level[edge.target] = max(level[edge.target], level[edge.source] + 1)
+        return level
+
+    def to_dot(self) -> str:
+        """Render the graph in Graphviz DOT format"""
+        colors = {'RAW': 'red', 'WAR': 'blue', 'WAW': 'orange'}
+        lines = ['digraph dependencies {', '    rankdir=TB;', '    node [shape=box, fontname="Courier"];']
+        levels = self.levels()
+        for level in sorted(set(levels)):
+            members = [i for i, l in enumerate(levels) if l == level]
+            lines.append('    { rank=same; ' + ' '.join(f'i{i};' for i in members) + ' }')
+        for index, instruction in enumerate(self.instructions):
+            label = instruction.source.replace('"', '\\"')
+            lines.append(f'    i{index} [label="{index}: {label}"];')
+        for edge in self.edges:
+            lines.append(f'    i{edge.source} -> i{edge.target} '
+                         f'[label="{edge.kind} {edge.resource}", color={colors[edge.kind]}];')
+        lines.append('}')
+        return '\n'.join(lines)
+
+def main():
+    parser = argparse.ArgumentParser(description="Export the data-dependency graph of a program as DOT")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('-o', '--output', help="DOT file to write (default: stdout)")
+    args = parser.parse_args()
+
+    isa = SimpleISA()
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+    dot = DependencyGraph(isa, isa.instructions, isa.labels).to_dot()
+
+    if args.output:
+        with open(args.output, 'w') as f:
+            f.write(dot + '\n')
+    else:
+        print(dot)
+
+if __name__ == '__main__':
+    sys.exit(main())
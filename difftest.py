@@ -0,0 +1,339 @@
+import argparse
+import io
+import json
+import sys
+from contextlib import redirect_stdout
+from dataclasses import dataclass
+from typing import Dict, List, Optional
+
+from isa import SimpleISA, Instruction, InstructionType, FLAG_CONDITIONS, LINK_REGISTER, ZERO_REGISTER
+from locality import build_hierarchy
+from utils.files import check_stdin_once, display_name, read_lines
+from utils.logger import Logger, LogLevel
+
+class ReferenceStop(Exception):
+    """The reference interpreter cannot continue: an unmodelled instruction or an address outside memory"""
+
+class ReferenceInterpreter:
+    """Golden model of the integer instruction set, written for clarity rather than speed
+
+    It shares only the assembler with the simulator: no caches, no traps,
+    no logging, just registers, flags and a flat word memory. Registers
+    and memory words are word_bits wide (the program's .word_size): as in
+    the simulator, every result is stored as the two's complement number
+    with the same low bits. Flags are computed on words of that width.
+    Floating-point, sub-word, privileged and cache-control instructions,
+    and addresses outside memory, raise ReferenceStop.
+    Branches take effect at once: there are no delay slots.
+    """
+
+    def __init__(self, instructions: List[Instruction], labels: Dict[str, int], isa_mode: str = 'standard',
+                 memory_size: int = 1024, entry: int = 0, data_base: int = 0, word_bits: int = 32):
+        self.instructions = instructions
+        self.labels = labels
+        self.isa_mode = isa_mode
+        self.memory_size = memory_size
+        self.data_base = data_base  # Added to every data address (.data)
+        self.word_bits = word_bits
+        self.mask = (1 << word_bits) - 1
+        self.pc = entry
+        self.registers = {reg: 0 for reg in ('eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'ebp', 'esp', ZERO_REGISTER)}
+        self.flags = {'Z': 0, 'N': 0, 'C': 0, 'V': 0}
+        self.memory: Dict[int, int] = {}
+        self.halted = False
+
+    # Operands
+    def _address(self, operand: str) -> int:
+        inner = operand[1:-1]
+        addr = self.data_base + (int(inner) if inner.isdigit() else self.registers[inner])
+        if not 0 <= addr < self.memory_size:
+            raise ReferenceStop(f"address {addr} is outside memory")
+        return addr
+
+    def _value(self, operand: str) -> int:
+        if operand.startswith('#'):
+            return int(operand[1:])
+        if operand.startswith('['):
+            return self.memory.get(self._address(operand), 0)
+        return self.registers[operand]
+
+    def _signed(self, value: int) -> int:
+        value &= self.mask
+        return value - (1 << self.word_bits) if value >> (self.word_bits - 1) else value
+
+    def _store(self, operand: str, value: int) -> None:
+        value = self._signed(value)
+        if operand.startswith('['):
+            self.memory[self._address(operand)] = value
+        elif operand != ZERO_REGISTER:
+            self.registers[operand] = value
+
+    # Flags
+    def _zn(self, result: int) -> None:
+        self.flags['Z'] = int(self._signed(result) == 0)
+        self.flags['N'] = int(self._signed(result) < 0)
+
+    def _add_flags(self, a: int, b: int, result: int) -> None:
+        self._zn(result)
+        sa, sb, sr = self._signed(a), self._signed(b), self._signed(result)
+        self.flags['C'] = int((a & self.mask) + (b & self.mask) > self.mask)
+        self.flags['V'] = int((sa < 0) == (sb < 0) and (sr < 0) != (sa < 0))
+
+    def _sub_flags(self, a: int, b: int, result: int) -> None:
+        self._zn(result)
+        sa, sb, sr = self._signed(a), self._signed(b), self._signed(result)
+        self.flags['C'] = int((a & self.mask) >= (b & self.mask))
+        self.flags['V'] = int((sa < 0) != (sb < 0) and (sr < 0) != (sa < 0))
+
+    def step(self) -> bool:
+        """Execute one instruction; returns False once the program has halted"""
+        if self.halted or self.pc >= len(self.instructions):
+            self.halted = True
+            return False
+
+        instruction = self.instructions[self.pc]
+        t, ops = instruction.type, instruction.operands
+        next_pc = self.pc + 1
+
+        if t in (InstructionType.MOV, InstructionType.LOAD, InstructionType.STORE):
+            self._store(ops[0], self._value(ops[1]))
+        elif t in (InstructionType.ADD, InstructionType.INC):
+            a, b = self._value(ops[0]), self._value(ops[1]) if t == InstructionType.ADD else 1
+            self._store(ops[0], a + b)
+            self._add_flags(a, b, a + b)
+        elif t in (InstructionType.SUB, InstructionType.DEC):
+            a, b = self._value(ops[0]), self._value(ops[1]) if t == InstructionType.SUB else 1
+            self._store(ops[0], a - b)
+            self._sub_flags(a, b, a - b)
+        elif t in (InstructionType.AND, InstructionType.OR, InstructionType.XOR):
+            a, b = self._value(ops[0]), self._value(ops[1])
+            result = a & b if t == InstructionType.AND else a | b if t == InstructionType.OR else a ^ b
+            self._store(ops[0], result)
+            self._zn(result)
+        elif t == InstructionType.NOT:
+            result = ~self._value(ops[0])
+            self._store(ops[0], result)
+            self._zn(result)
+        elif t in (InstructionType.SHL, InstructionType.SHR):
+            value, amount = self._value(ops[0]), self._value(ops[1])
+            result = value << amount if t == InstructionType.SHL else value >> amount
+            self._store(ops[0], result)
+            self._zn(result)
+            if amount > 0:
+                bit = self.word_bits - amount if t == InstructionType.SHL else amount - 1
+                self.flags['C'] = ((value & self.mask) >> bit) & 1 if 0 <= bit < self.word_bits else 0
+        elif t == InstructionType.CMP:
+            a, b = self._value(ops[0]), self._value(ops[1])
+            self._sub_flags(a, b, a - b)
+            if self.isa_mode == 'standard':
+                self.registers['eax'] = int(a < b)
+        elif t == InstructionType.TEST:
+            result = self._value(ops[0]) & self._value(ops[1])
+            self._zn(result)
+            if self.isa_mode == 'standard':
+                self._store(ops[0], int(result != 0))
+        elif t == InstructionType.JMP:
+            next_pc = self.labels[ops[0]]
+        elif t == InstructionType.JZ:
+            if self.registers['eax'] == 0:
+                next_pc = self.labels[ops[0]]
+        elif t == InstructionType.JNZ:
+            if self.registers['eax'] != 0:
+                next_pc = self.labels[ops[0]]
+        elif t in FLAG_CONDITIONS:
+            if FLAG_CONDITIONS[t](self.flags):
+                next_pc = self.labels[ops[0]]
+        elif t == InstructionType.JAL:
+            self.registers[LINK_REGISTER] = next_pc
+            next_pc = self.labels[ops[0]]
+        elif t == InstructionType.JR:
+            next_pc = self.registers[ops[0]]
+            if not 0 <= next_pc <= len(self.instructions):
+                raise ReferenceStop(f"JR target {next_pc} is outside the program")
+        elif t == InstructionType.HALT:
+            self.halted = True
+        elif t in (InstructionType.PRINT_CACHE, InstructionType.PRINT_REG, InstructionType.RESET_STATS,
+                   InstructionType.NOP):
+            pass
+        else:
+            raise ReferenceStop(f"{t.name} is not modelled")
+
+        self.pc = next_pc
+        return not self.halted
+
+@dataclass
+class Divergence:
+    """The first point where the simulator and the reference disagree"""
+    step: int
+    pc: int
+    source: str
+    location: str   # pc, a register, a flag or mem[n]
+    expected: object
+    actual: object
+
+    def __str__(self):
+        return (f"Step {self.step} (pc {self.pc}: {self.source}): {self.location} is {self.actual}, "
+                f"reference says {self.expected}")
+
+def architectural_state(registers: Dict[str, int], flags: Dict[str, int], pc: int,
+                        memory: Dict[int, int]) -> Dict:
+    """State compared after every step, in a JSON-friendly form"""
+    return {'pc': pc, 'registers': dict(registers), 'flags': dict(flags),
+            'memory': {str(addr): value for addr, value in sorted(memory.items())}}
+
+def _diff(expected: Dict, actual: Dict) -> Optional[tuple]:
+    if expected['pc'] != actual['pc']:
+        return 'pc', expected['pc'], actual['pc']
+    for group in ('registers', 'flags'):
+        for name, value in expected[group].items():
+            if actual[group].get(name) != value:
+                return name, value, actual[group].get(name)
+    for addr, value in expected['memory'].items():
+        if actual['memory'].get(addr, 0) != value:
+            return f"mem[{addr}]", value, actual['memory'].get(addr, 0)
+    return None
+
+class DifferentialTester:
+    """Steps the simulator and the reference in lockstep and reports the first divergence"""
+
+    def __init__(self, lines: List[str], max_steps: int = 100000):
+        memory, l1, _ = build_hierarchy()
+        self.isa = SimpleISA(memory=memory, cache=l1)
+        self.lines = lines
+        self.max_steps = max_steps
+        self.stopped: Optional[str] = None  # Why comparison ended before the program did
+        self.steps = 0
+        self._trap = None
+
+        # Traps depend on regions, privilege and handlers the reference does not model
+        take_trap = self.isa._take_trap
+        def record_trap(trap, resume_pc):
+            self._trap = trap
+            return take_trap(trap, resume_pc)
+        self.isa._take_trap = record_trap
+
+    def _reference(self) -> ReferenceInterpreter:
+        return ReferenceInterpreter(self.isa.instructions, self.isa.labels, self.isa.isa_mode,
+                                    len(self.isa.memory.data), self.isa.entry_point, self.isa.base_register,
+                                    self.isa.word_bits)
+
+    def _simulator_state(self, written: set) -> Dict:
+        data = self.isa.memory.data
+        memory = {addr: data[addr] for addr in written if 0 <= addr < len(data)}
+        return architectural_state(self.isa.registers, self.isa.flags, self.isa.pc, memory)
+
+    def run(self, reference_trace: Optional[List[Dict]] = None) -> Optional[Divergence]:
+        """Compare against the live reference, or against a recorded list of states"""
+        logger = Logger()
+        level = logger.log_level
+        logger.log_level = LogLevel.ERROR
+        try:
+            with redirect_stdout(io.StringIO()):
+                self.isa.load_program(self.lines)
+                self.isa.running = True
+                return self._lockstep(reference_trace)
+        finally:
+            logger.log_level = level
+
+    def _lockstep(self, reference_trace: Optional[List[Dict]]) -> Optional[Divergence]:
+        if self.isa.delay_slots:
+            self.stopped = "pc 0: branch delay slots are not modelled"
+            return None
+        reference = self._reference()
+        written = set()
+        while self.steps < self.max_steps:
+            pc = self.isa.pc
+            if pc >= len(self.isa.instructions):
+                break
+            instruction = self.isa.instructions[pc]
+
+            if reference_trace is None:
+                try:
+                    reference.step()
+                except ReferenceStop as e:
+                    self.stopped = f"pc {pc}: {e}"
+                    return None
+                expected = architectural_state(reference.registers, reference.flags, reference.pc,
+                                               reference.memory)
+            elif self.steps < len(reference_trace):
+                expected = reference_trace[self.steps]
+            else:
+                break
+
+            running = self.isa.execute_step()
+            if self._trap:
+                self.stopped = f"pc {pc}: the simulator took a {self._trap.cause.name} trap"
+                return None
+            written.update(addr for access, addr in self.isa.step_accesses if access == 'w')
+            written.update(int(addr) for addr in expected['memory'])
+            self.steps += 1
+            mismatch = _diff(expected, self._simulator_state(written))
+            if mismatch:
+                return Divergence(self.steps, pc, instruction.source, *mismatch)
+            if not running:
+                break
+        return None
+
+    def record(self) -> List[Dict]:
+        """Run the reference alone and return its state after every step"""
+        with redirect_stdout(io.StringIO()):
+            self.isa.load_program(self.lines)
+        reference = self._reference()
+        trace = []
+        while len(trace) < self.max_steps:
+            try:
+                running = reference.step()
+            except ReferenceStop:
+                break
+            trace.append(architectural_state(reference.registers, reference.flags, reference.pc, reference.memory))
+            if not running:
+                break
+        return trace
+
+def main():
+    parser = argparse.ArgumentParser(description="Run programs on the simulator and a reference interpreter "
+                                                 "and report the first difference in architectural state")
+    parser.add_argument('programs', nargs='*', help="Assembly program files; - reads one from standard input")
+    parser.add_argument('--random', type=int, metavar='N', help="Also check N generated programs (see randgen.py)")
+    parser.add_argument('--seed', type=int, default=0, help="First seed for --random")
+    parser.add_argument('--record', metavar='TRACE', help="Save the reference trace of the (single) program as JSON")
+    parser.add_argument('--against', metavar='TRACE', help="Compare with a recorded trace instead of the live reference")
+    args = parser.parse_args()
+    check_stdin_once(parser, args.programs)
+
+    try:
+        programs = [(display_name(path), read_lines(path)) for path in args.programs]
+    except OSError as e:
+        print(f"Cannot read {e.filename}: {e.strerror}")
+        return 1
+    if args.random:
+        from randgen import ProgramGenerator
+        programs.extend((f"random seed {seed}", ProgramGenerator(seed).generate())
+                        for seed in range(args.seed, args.seed + args.random))
+
+    if args.record:
+        with open(args.record, 'w') as f:
+            json.dump(DifferentialTester(programs[0][1]).record(), f)
+        print(f"Recorded reference trace of {programs[0][0]} to {args.record}")
+        return 0
+
+    trace = None
+    if args.against:
+        with open(args.against) as f:
+            trace = json.load(f)
+
+    failures = 0
+    for name, lines in programs:
+        tester = DifferentialTester(lines)
+        divergence = tester.run(trace)
+        if divergence:
+            failures += 1
+            print(f"{name}: DIVERGED  {divergence}")
+        elif tester.stopped:
+            print(f"{name}: matched for {tester.steps} steps, then stopped at {tester.stopped}")
+        else:
+            print(f"{name}: matched for {tester.steps} steps")
+    return 1 if failures else 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,222 @@
+import argparse
+import os
+import struct
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Tuple
+
+from encoding import build_listing, decode_instruction
+from isa import SimpleISA, format_instruction
+from locality import build_hierarchy
+from utils.files import STDIN, read_bytes, read_lines
+from utils.logger import Logger, LogLevel
+
+# e_machine values; EM_NONE marks code in this simulator's own encoding (encoding.py)
+EM_NONE, EM_386, EM_MIPS, EM_RISCV = 0, 3, 8, 243
+MACHINE_NAMES = {EM_NONE: "CPU simulator", EM_386: "x86", EM_MIPS: "MIPS", EM_RISCV: "RISC-V"}
+ET_EXEC = 2
+PT_LOAD = 1
+PF_X, PF_W, PF_R = 1, 2, 4
+HEADER_SIZE, PROGRAM_HEADER_SIZE = 52, 32
+
+class ElfError(ValueError):
+    pass
+
+@dataclass
+class Segment:
+    """A PT_LOAD segment: bytes to place at a byte address, zero-filled up to size"""
+    address: int
+    data: bytes
+    size: int
+    flags: int
+
+    @property
+    def executable(self) -> bool:
+        return bool(self.flags & PF_X)
+
+@dataclass
+class ElfImage:
+    machine: int
+    endianness: str   # 'little' or 'big'
+    entry: int        # Byte address
+    segments: List[Segment] = field(default_factory=list)
+
+    @property
+    def machine_name(self) -> str:
+        return MACHINE_NAMES.get(self.machine, f"machine {self.machine}")
+
+def parse_elf32(data: bytes) -> ElfImage:
+    """The header and PT_LOAD segments of a 32-bit ELF executable"""
+    if data[:4] != b'\x7fELF':
+        raise ElfError("not an ELF file")
+    if len(data) < HEADER_SIZE or data[4] != 1:
+        raise ElfError("not a 32-bit ELF file (ELF64 is not supported)")
+    if data[5] not in (1, 2):
+        raise ElfError(f"unknown ELF data encoding {data[5]}")
+    order = '<' if data[5] == 1 else '>'
+    (e_type, e_machine, _, e_entry, e_phoff, _, _, _, e_phentsize,
+     e_phnum) = struct.unpack_from(order + 'HHIIIIIHHH', data, 16)
+    if e_type != ET_EXEC:
+        raise ElfError(f"ELF type {e_type} is not an executable (link the program first)")
+    image = ElfImage(e_machine, 'little' if order == '<' else 'big', e_entry)
+    for i in range(e_phnum):
+        offset = e_phoff + i * e_phentsize
+        if offset + PROGRAM_HEADER_SIZE > len(data):
+            raise ElfError(f"program header {i} is past the end of the file")
+        p_type, p_offset, p_vaddr, _, p_filesz, p_memsz, p_flags, _ = struct.unpack_from(order + 'IIIIIIII',
+                                                                                      data, offset)
+        if p_type != PT_LOAD:
+            continue
+        if p_offset + p_filesz > len(data):
+            raise ElfError(f"segment at 0x{p_vaddr:x} is past the end of the file")
+        image.segments.append(Segment(p_vaddr, data[p_offset:p_offset + p_filesz], p_memsz, p_flags))
+    return image
+
+def build_elf32(text: List[int], text_address: int = 0, entry: int = 0, endianness: str = 'little') -> bytes:
+    """An ELF executable with one read/execute segment of instruction words in this simulator's encoding"""
+    order = '<' if endianness == 'little' else '>'
+    body = struct.pack(f"{order}{len(text)}I", *text)
+    ident = b'\x7fELF' + bytes([1, 1 if endianness == 'little' else 2, 1]) + bytes(9)
+    header = ident + struct.pack(order + 'HHIIIIIHHHHHH', ET_EXEC, EM_NONE, 1, entry, HEADER_SIZE, 0, 0,
+                                 HEADER_SIZE, PROGRAM_HEADER_SIZE, 1, 40, 0, 0)
+    offset = HEADER_SIZE + PROGRAM_HEADER_SIZE
+    program_header = struct.pack(order + 'IIIIIIII', PT_LOAD, offset, text_address, text_address,
+                                 len(body), len(body), PF_R | PF_X, 4)
+    return header + program_header + body
+
+def _words(segment: Segment, endianness: str) -> List[int]:
+    data = segment.data + bytes(-len(segment.data) % 4)
+    return list(struct.unpack(f"{'<' if endianness == 'little' else '>'}{len(data) // 4}I", data))
+
+def elf_program(image: ElfImage, memory_words: int) -> Tuple[List[str], Dict[int, int]]:
+    """The assembly lines of the executable segment and the words of the others, by word address
+
+    Only executables in this simulator's own encoding (EM_NONE) can run:
+    MIPS or RISC-V machine code is a different instruction set.
+    """
+    if image.machine != EM_NONE:
+        raise ElfError(f"{image.machine_name} machine code cannot run on this simulator, which executes its own "
+                       "instruction set (see encoding.py); build an executable with 'elfload.py pack'")
+    for segment in image.segments:
+        if segment.address % 4:
+            raise ElfError(f"segment at 0x{segment.address:x} is not word aligned")
+        if segment.address // 4 + (segment.size + 3) // 4 > memory_words:
+            raise ElfError(f"segment at 0x{segment.address:x} ({segment.size} bytes) does not fit in the "
+                           f"{memory_words * 4}-byte memory")
+    code = [segment for segment in image.segments if segment.executable]
+    if len(code) != 1:
+        raise ElfError(f"expected one executable segment, found {len(code)}")
+    text = code[0]
+    words = _words(text, image.endianness)
+
+    instructions = []
+    index_at = {}  # Word offset -> instruction index
+    offset = 0
+    while offset < len(words):
+        index_at[offset] = len(instructions)
+        try:
+            instruction, length = decode_instruction(words, offset)
+        except ValueError as e:
+            raise ElfError(f"text segment: {e}") from None
+        instructions.append(instruction)
+        offset += length
+    entry_offset, remainder = divmod(image.entry - text.address, 4)
+    if remainder or entry_offset not in index_at:
+        raise ElfError(f"entry point 0x{image.entry:x} is not the start of an instruction")
+
+    # Branch targets decode as L<index>; they and the entry point get label lines
+    targets = {int(op[1:]) for instruction in instructions for op in instruction.operands
+               if op.startswith('L') and op[1:].isdigit()}
+    entry = index_at[entry_offset]
+    lines = [f".endian {image.endianness}", f".text {text.address // 4}"]
+    if entry:
+        lines.append(f".entry L{entry}")
+        targets.add(entry)
+    for index, instruction in enumerate(instructions):
+        if index in targets:
+            lines.append(f"L{index}:")
+        lines.append(format_instruction(instruction))
+    if len(instructions) in targets:
+        lines.append(f"L{len(instructions)}:")
+
+    data = {}
+    for segment in image.segments:
+        if not segment.executable:
+            base = segment.address // 4
+            data.update({base + i: word for i, word in enumerate(_words(segment, image.endianness))})
+    return lines, data
+
+def load_elf(isa: SimpleISA, data: bytes) -> ElfImage:
+    """Load an ELF executable: its text as the program, other segments into memory"""
+    image = parse_elf32(data)
+    lines, words = elf_program(image, len(isa.memory.data))
+    isa.load_program(lines)
+    for address, value in words.items():
+        isa.memory.data[address] = value
+        isa.initialized_memory.add(address)
+    return image
+
+def format_image(image: ElfImage) -> List[str]:
+    lines = [f"ELF32 {image.endianness}-endian executable for {image.machine_name}, entry 0x{image.entry:x}",
+             "Loadable segments:"]
+    for segment in image.segments:
+        flags = ''.join(c if segment.flags & bit else '-' for c, bit in (('r', PF_R), ('w', PF_W), ('x', PF_X)))
+        lines.append(f"  0x{segment.address:08x}  {segment.size:6d} bytes  {flags}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Inspect, build and run 32-bit ELF executables")
+    commands = parser.add_subparsers(dest='command', required=True)
+    info = commands.add_parser('info', help="Show an ELF file's machine, entry point and loadable segments")
+    info.add_argument('file', help="ELF file, or - for standard input")
+    run = commands.add_parser('run', help="Load an executable's segments and run it")
+    run.add_argument('file', help="ELF file, or - for standard input")
+    pack = commands.add_parser('pack', help="Assemble a program into an ELF executable for this simulator "
+                                            "(directives other than .endian and .entry are not kept)")
+    pack.add_argument('program', help="Assembly program file, or - for standard input")
+    pack.add_argument('-o', '--output', help="Executable to write "
+                                                "(default: the program with .elf; required for standard input)")
+    pack.add_argument('--base', type=int, default=0, help="Memory word of the first instruction (default: 0)")
+    args = parser.parse_args()
+    if args.command == 'pack' and args.program == STDIN and not args.output:
+        parser.error("give -o for a program read from standard input")
+
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        if args.command == 'pack':
+            memory, l1, _ = build_hierarchy()
+            isa = SimpleISA(memory=memory, cache=l1)
+            isa.load_program(read_lines(args.program))
+            rows = build_listing(isa.instructions, isa.labels)
+            for row in rows:
+                if row.error:
+                    raise ElfError(f"line {row.line_number + 1}: {row.error}")
+            text = [word for row in rows for word in row.words]
+            entry = args.base * 4 + 4 * (rows[isa.entry_point].offset if isa.entry_point < len(rows) else 0)
+            output = args.output or os.path.splitext(args.program)[0] + '.elf'
+            with open(output, 'wb') as f:
+                f.write(build_elf32(text, args.base * 4, entry, isa.endianness))
+            print(f"{args.program} -> {output}: {len(rows)} instructions in {len(text)} words")
+            return 0
+
+        data = read_bytes(args.file)
+        image = parse_elf32(data)
+        print('\n'.join(format_image(image)))
+        if args.command == 'run':
+            memory, l1, _ = build_hierarchy()
+            isa = SimpleISA(memory=memory, cache=l1)
+            load_elf(isa, data)
+            isa.run(fast=True)
+            print(f"Ran {isa.instruction_count} instructions: "
+                  + "  ".join(f"{reg}={value}" for reg, value in isa.registers.items()))
+    except (OSError, ElfError) as e:
+        print(f"Error: {e}")
+        return 1
+    finally:
+        logger.log_level = level
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
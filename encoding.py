@@ -0,0 +1,312 @@
+import argparse
+import re
+import struct
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Tuple
+
+from isa import Instruction, InstructionType, BRANCH_INSTRUCTIONS, ZERO_REGISTER, SimpleISA, format_instruction
+from utils.files import read_lines
+from utils.logger import Logger
+
+# Instruction word layout (32 bits), followed by one extension word per immediate, address or branch target:
+#   [31:24] opcode (the InstructionType value)
+#   [23:21] operand 1 kind   [20:16] operand 1 register number
+#   [15:13] operand 2 kind   [12:8]  operand 2 register number
+#   [7:0]   bit n set when operand n+1 is an immediate written unsigned (above 2^31 - 1)
+WORD_MASK = 0xFFFFFFFF
+MAX_OPERANDS = 2
+FP_REGISTER_COUNT = 32
+
+# Register numbers used in the register fields
+REGISTERS = ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'ebp', 'esp', ZERO_REGISTER]
+CONTROL_REGISTERS = ['status', 'cause', 'epc', 'timer']
+
+# Operand kinds
+NONE, REG, FREG, CREG, IMM, MEM, MEM_REG, LABEL = range(8)
+KIND_NAMES = ['-', 'reg', 'freg', 'creg', 'imm', 'mem', '[reg]', 'label']
+EXTENSION_KINDS = {IMM, MEM, LABEL}
+
+# Instructions whose immediates are single-precision floats, encoded as their IEEE 754 bits
+FLOAT_IMMEDIATES = {t for t in InstructionType if t.name.endswith('_S')}
+
+def _float_bits(value: float) -> int:
+    return struct.unpack('>I', struct.pack('>f', value))[0]
+
+def _bits_float(bits: int) -> float:
+    return struct.unpack('>f', struct.pack('>I', bits))[0]
+
+def _format_float(value: float) -> str:
+    """Shortest text that reads back as the same single-precision value"""
+    for precision in range(1, 10):
+        text = f"{value:.{precision}g}"
+        if _float_bits(float(text)) == _float_bits(value):
+            return text if any(c in text for c in '.enia') else text + '.0'
+    return repr(value)
+
+def _encode_operand(operand: str, inst_type: InstructionType, branch: bool,
+                    labels: Dict[str, int]) -> Tuple[int, int, Optional[int], bool]:
+    """(kind, register number, extension word or None, written unsigned) of one operand"""
+    if branch:
+        if operand not in labels:
+            raise ValueError(f"branch target '{operand}' is undefined")
+        return LABEL, 0, labels[operand], False
+    if operand in REGISTERS:
+        return REG, REGISTERS.index(operand), None, False
+    if operand in CONTROL_REGISTERS:
+        return CREG, CONTROL_REGISTERS.index(operand), None, False
+    if operand[:1] == 'f' and operand[1:].isdigit() and int(operand[1:]) < FP_REGISTER_COUNT:
+        return FREG, int(operand[1:]), None, False
+    if operand in labels:
+        return LABEL, 0, labels[operand], False  # e.g. a handler address moved into epc
+    if operand.startswith('[') and operand.endswith(']'):
+        inner = operand[1:-1]
+        if inner.isdigit() and int(inner) <= WORD_MASK:
+            return MEM, 0, int(inner), False
+        if inner in REGISTERS:
+            return MEM_REG, REGISTERS.index(inner), None, False
+    elif operand.startswith('#'):
+        if inst_type in FLOAT_IMMEDIATES:
+            try:
+                return IMM, 0, _float_bits(float(operand[1:])), False
+            except (ValueError, OverflowError):
+                pass
+        else:
+            try:
+                value = int(operand[1:])
+            except ValueError:
+                value = None
+            if value is not None and -(1 << 31) <= value <= WORD_MASK:
+                return IMM, 0, value & WORD_MASK, value > (1 << 31) - 1
+    raise ValueError(f"operand '{operand}' cannot be encoded")
+
+def encode_instruction(instruction: Instruction, labels: Dict[str, int]) -> List[int]:
+    """The instruction word and its extension words; ValueError for operands the format cannot hold"""
+    operands = instruction.operands
+    if len(operands) > MAX_OPERANDS:
+        raise ValueError(f"{len(operands)} operands (at most {MAX_OPERANDS} can be encoded)")
+
+    word = instruction.type.value << 24
+    extensions = []
+    for i, operand in enumerate(operands):
+        branch = instruction.type in BRANCH_INSTRUCTIONS and i == 0
+        kind, number, extension, unsigned = _encode_operand(operand, instruction.type, branch, labels)
+        shift = 16 if i == 0 else 8
+        word |= (kind << (shift + 5)) | (number << shift) | (unsigned << i)
+        if extension is not None:
+            extensions.append(extension)
+    return [word] + extensions
+
+def decode_instruction(words: List[int], offset: int = 0,
+                       symbols: Optional[Dict[int, str]] = None) -> Tuple[Instruction, int]:
+    """The instruction at words[offset] and the number of words it takes
+
+    Branch targets are named from symbols (instruction index -> label) when
+    given, and written L<index> otherwise.
+    """
+    word = words[offset]
+    try:
+        inst_type = InstructionType(word >> 24)
+    except ValueError:
+        raise ValueError(f"word {offset}: unknown opcode {word >> 24}") from None
+
+    operands = []
+    length = 1
+    for i, shift in enumerate((16, 8)):
+        kind = (word >> (shift + 5)) & 0x7
+        number = (word >> shift) & 0x1F
+        if kind == NONE:
+            break
+        extension = None
+        if kind in EXTENSION_KINDS:
+            if offset + length >= len(words):
+                raise ValueError(f"word {offset}: missing extension word for operand {i + 1}")
+            extension = words[offset + length]
+            length += 1
+
+        if kind == REG or kind == MEM_REG:
+            if number >= len(REGISTERS):
+                raise ValueError(f"word {offset}: register number {number} out of range")
+            operands.append(REGISTERS[number] if kind == REG else f"[{REGISTERS[number]}]")
+        elif kind == CREG:
+            if number >= len(CONTROL_REGISTERS):
+                raise ValueError(f"word {offset}: control register number {number} out of range")
+            operands.append(CONTROL_REGISTERS[number])
+        elif kind == FREG:
+            operands.append(f"f{number}")
+        elif kind == MEM:
+            operands.append(f"[{extension}]")
+        elif kind == LABEL:
+            operands.append((symbols or {}).get(extension, f"L{extension}"))
+        elif inst_type in FLOAT_IMMEDIATES:
+            operands.append(f"#{_format_float(_bits_float(extension))}")
+        elif word & (1 << i):
+            operands.append(f"#{extension}")
+        else:
+            operands.append(f"#{extension - (1 << 32) if extension >> 31 else extension}")
+    return Instruction(inst_type, operands, 0), length
+
+@dataclass
+class ListingRow:
+    """One instruction of an assembled program: where it came from, its encoding and how it decodes"""
+    pc: int                  # Instruction index, which is what the PC counts
+    line_number: int         # 0-based source line
+    source: str
+    offset: int              # Word offset of the instruction in the binary image
+    words: List[int] = field(default_factory=list)
+    disassembly: str = ""
+    error: Optional[str] = None  # Why the instruction could not be encoded
+
+def build_listing(instructions: List[Instruction], labels: Dict[str, int]) -> List[ListingRow]:
+    """Encode every instruction and decode it back; unencodable ones get an error and no words"""
+    symbols = {}
+    for name, index in labels.items():
+        symbols.setdefault(index, name)
+
+    rows = []
+    offset = 0
+    for pc, instruction in enumerate(instructions):
+        row = ListingRow(pc, instruction.line_number, instruction.source, offset)
+        try:
+            row.words = encode_instruction(instruction, labels)
+        except ValueError as e:
+            row.error = str(e)
+        else:
+            decoded, _ = decode_instruction(row.words, 0, symbols)
+            row.disassembly = format_instruction(decoded)
+            offset += len(row.words)
+        rows.append(row)
+    return rows
+
+def format_fields(word: int) -> str:
+    """An instruction word in binary, split into its fields"""
+    bits = f"{word & WORD_MASK:032b}"
+    return ' '.join(bits[start:end] for start, end in ((0, 8), (8, 11), (11, 16), (16, 19), (19, 24), (24, 32)))
+
+def format_listing(rows: List[ListingRow]) -> List[str]:
+    lines = [f"{'line':>5} {'pc':>4} {'offset':>6}  {'encoding':<26}  disassembly"]
+    for row in rows:
+        encoding = ' '.join(f"{word:08x}" for word in row.words) if not row.error else f"error: {row.error}"
+        lines.append(f"{row.line_number + 1:5d} {row.pc:4d} {row.offset:6d}  {encoding:<26}  {row.disassembly}")
+    return lines
+
+def describe_word(word: int) -> str:
+    """What each field of an instruction word holds: ADD (4), op1 reg eax, op2 imm"""
+    try:
+        parts = [f"{InstructionType(word >> 24 & 0xFF).name.replace('_', '.')} ({word >> 24 & 0xFF})"]
+    except ValueError:
+        return f"unknown opcode {word >> 24 & 0xFF}"
+    for i, shift in enumerate((16, 8)):
+        kind = (word >> (shift + 5)) & 0x7
+        number = (word >> shift) & 0x1F
+        if kind == NONE:
+            break
+        text = f"op{i + 1} {KIND_NAMES[kind]}"
+        if kind in (REG, MEM_REG) and number < len(REGISTERS):
+            text += f" {REGISTERS[number]}"
+        elif kind == CREG and number < len(CONTROL_REGISTERS):
+            text += f" {CONTROL_REGISTERS[number]}"
+        elif kind == FREG:
+            text += f" f{number}"
+        parts.append(text)
+    return ', '.join(parts)
+
+def assemble_lines(lines: List[str]) -> Tuple[List[Instruction], Dict[str, int]]:
+    """Assemble lines without a machine; ValueError on the first line the assembler rejects
+
+    Branch targets written L<index>, as the disassembler writes them, need no label.
+    """
+    isa = SimpleISA()
+    with Logger().muted():
+        instructions, labels = isa.parse_program(lines)
+    for line, message in isa.diagnostics:
+        raise ValueError(f"line {line + 1}: {message}")
+    for instruction in instructions:
+        for operand in instruction.operands:
+            if re.fullmatch(r'L\d+', operand) and operand not in labels:
+                labels[operand] = int(operand[1:])
+    return instructions, labels
+
+def parse_words(texts: List[str]) -> List[int]:
+    """Words written in hex (0x optional), separated by spaces or commas; ';' starts a comment"""
+    words = []
+    for text in texts:
+        for token in re.split(r'[\s,]+', text.split(';')[0]):
+            if not token:
+                continue
+            try:
+                word = int(token, 16)
+            except ValueError:
+                raise ValueError(f"'{token}' is not a hex word") from None
+            if not 0 <= word <= WORD_MASK:
+                raise ValueError(f"'{token}' does not fit in 32 bits")
+            words.append(word)
+    return words
+
+def format_encoding(instruction: Instruction, words: List[int]) -> List[str]:
+    return [format_instruction(instruction), f"  words:  {' '.join(f'0x{word:08x}' for word in words)}",
+            f"  fields: {format_fields(words[0])}  ({describe_word(words[0])})"]
+
+def format_disassembly(words: List[int]) -> List[str]:
+    """Each instruction in a run of words: its pc (the index branch targets L<pc> refer to), word offset,
+    words and assembly text"""
+    lines = [f"{'pc':>4} {'offset':>6}  {'encoding':<26}  disassembly"]
+    offset = 0
+    while offset < len(words):
+        instruction, length = decode_instruction(words, offset)
+        encoding = ' '.join(f"{word:08x}" for word in words[offset:offset + length])
+        lines.append(f"{len(lines) - 1:4d} {offset:6d}  {encoding:<26}  {format_instruction(instruction)}")
+        offset += length
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Encode instructions to machine code words and disassemble words "
+                                                 "back to instructions, without running the simulator")
+    commands = parser.add_subparsers(dest='command', required=True)
+    encode = commands.add_parser('encode', help="Encode instructions, e.g. encode \"ADD eax #5\"")
+    encode.add_argument('instructions', nargs='*', help="Instructions; commas between operands are allowed")
+    encode.add_argument('--file', help="Encode a whole program and print its machine code listing "
+                                        "(- reads standard input)")
+    encode.add_argument('-o', '--output', help="Also write the words, one hex word per line, for disasm --file")
+    disasm = commands.add_parser('disasm', help="Disassemble hex words, e.g. disasm 0x04010500 0x00000005")
+    disasm.add_argument('words', nargs='*', help="Hex words (0x optional)")
+    disasm.add_argument('--file', help="Disassemble a file of hex words, e.g. written by encode -o "
+                                        "(- reads standard input)")
+    args = parser.parse_args()
+
+    try:
+        if args.command == 'encode':
+            if args.file:
+                lines = read_lines(args.file)
+            else:
+                lines = [text.replace(',', ' ') for text in args.instructions]
+            if not lines:
+                parser.error("give instructions to encode or --file")
+            instructions, labels = assemble_lines(lines)
+            rows = build_listing(instructions, labels)
+            for row in rows:
+                if row.error:
+                    raise ValueError(f"line {row.line_number + 1}: {row.source}: {row.error}")
+            if args.output:
+                with open(args.output, 'w') as f:
+                    f.writelines(f"{word:08x}\n" for row in rows for word in row.words)
+            if args.file:
+                print('\n'.join(format_listing(rows)))
+            else:
+                for instruction, row in zip(instructions, rows):
+                    print('\n'.join(format_encoding(instruction, row.words)))
+        else:
+            if args.file:
+                words = parse_words(read_lines(args.file))
+            else:
+                words = parse_words(args.words)
+            if not words:
+                parser.error("give words to disassemble or --file")
+            print('\n'.join(format_disassembly(words)))
+    except (OSError, ValueError) as e:
+        print(f"Error: {e}")
+        return 1
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
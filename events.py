@@ -0,0 +1,112 @@
+from collections import Counter
+from dataclasses import dataclass, field
+from enum import Enum, auto
+from typing import Any, Callable, Dict, Iterable, List, Optional
+
+class SimEvent(Enum):
+    """What the core announces while it executes"""
+    INSTRUCTION_RETIRED = auto()  # instruction, next_pc
+    CACHE_ACCESS = auto()         # cache, access ('r' or 'w'), address, hit (cache is None when uncached)
+    MEMORY_WRITE = auto()         # address, value
+    BRANCH_RESOLVED = auto()      # instruction, target, taken
+    EXCEPTION = auto()            # cause, message, fatal
+    STATS_RESET = auto()          # (no data) the core's statistics counters were zeroed
+
+@dataclass
+class Event:
+    """One published event; step is the instruction count when it happened"""
+    kind: SimEvent
+    step: int
+    pc: int
+    data: Dict[str, Any] = field(default_factory=dict)
+
+Subscriber = Callable[[Event], None]
+
+class EventBus:
+    """Delivers the core's events to front-ends, tracers and statistics modules
+
+    Subscribers register for some kinds of event, or all of them, and are
+    called synchronously in subscription order. The core asks wants()
+    before building an event, so kinds nobody listens to cost nothing.
+    """
+
+    def __init__(self):
+        self._subscribers: Dict[SimEvent, List[Subscriber]] = {kind: [] for kind in SimEvent}
+
+    def subscribe(self, callback: Subscriber, kinds: Optional[Iterable[SimEvent]] = None) -> Subscriber:
+        for kind in (kinds if kinds is not None else SimEvent):
+            if callback not in self._subscribers[kind]:
+                self._subscribers[kind].append(callback)
+        return callback
+
+    def unsubscribe(self, callback: Subscriber) -> None:
+        for subscribers in self._subscribers.values():
+            if callback in subscribers:
+                subscribers.remove(callback)
+
+    def wants(self, kind: SimEvent) -> bool:
+        return bool(self._subscribers[kind])
+
+    def publish(self, kind: SimEvent, step: int, pc: int, **data) -> None:
+        if not self._subscribers[kind]:
+            return
+        event = Event(kind, step, pc, data)
+        for callback in list(self._subscribers[kind]):
+            callback(event)
+
+class EventStats:
+    """Statistics built only from the event stream, independent of the core's own counters"""
+
+    def __init__(self, bus: Optional[EventBus] = None):
+        self.reset()
+        if bus:
+            self.attach(bus)
+
+    def reset(self) -> None:
+        self.counts = Counter()
+        self.branches_taken = 0
+        self.cache_hits = 0
+        self.cache_misses = 0
+        self.uncached = 0
+        self.written_addresses = set()
+        self.exceptions: List[str] = []
+
+    def attach(self, bus: EventBus) -> None:
+        bus.subscribe(self.on_event)
+
+    def detach(self, bus: EventBus) -> None:
+        bus.unsubscribe(self.on_event)
+
+    def on_event(self, event: Event) -> None:
+        self.counts[event.kind] += 1
+        if event.kind == SimEvent.BRANCH_RESOLVED and event.data['taken']:
+            self.branches_taken += 1
+        elif event.kind == SimEvent.CACHE_ACCESS:
+            if event.data['cache'] is None:
+                self.uncached += 1
+            elif event.data['hit']:
+                self.cache_hits += 1
+            else:
+                self.cache_misses += 1
+        elif event.kind == SimEvent.MEMORY_WRITE:
+            self.written_addresses.add(event.data['address'])
+        elif event.kind == SimEvent.EXCEPTION:
+            self.exceptions.append(event.data['cause'])
+        elif event.kind == SimEvent.STATS_RESET:
+            self.reset()  # Measure along with the core
+
+    def get_stats(self) -> Dict[str, Any]:
+        branches = self.counts[SimEvent.BRANCH_RESOLVED]
+        accesses = self.cache_hits + self.cache_misses
+        return {
+            'retired': self.counts[SimEvent.INSTRUCTION_RETIRED],
+            'branches': branches,
+            'branches_taken': self.branches_taken,
+            'taken_rate': self.branches_taken / branches if branches else 0.0,
+            'cache_accesses': accesses,
+            'cache_hit_rate': self.cache_hits / accesses if accesses else 0.0,
+            'uncached_accesses': self.uncached,
+            'memory_writes': self.counts[SimEvent.MEMORY_WRITE],
+            'words_written': len(self.written_addresses),
+            'exceptions': len(self.exceptions)
+        }
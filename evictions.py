@@ -0,0 +1,221 @@
+import argparse
+import sys
+from dataclasses import dataclass
+from typing import List, Optional
+
+from events import Event, SimEvent
+from utils.files import read_lines
+from utils.style import style
+
+MAX_EVICTIONS = 10000  # Evictions kept; later ones are counted but not logged
+MAX_ACCESSES = 50000  # L1 accesses kept for explaining misses; later ones are counted but not kept
+SET_HISTORY_SHOWN = 8  # Accesses to the set listed before a miss
+
+# Why a line left its cache: the replacement policy that chose it, or an explicit flush or invalidation
+EVICTION_REASONS = {'lru': "least recently used", 'fifo': "oldest in the set", 'random': "random pick",
+                    'flush': "flushed", 'invalidate': "invalidated"}
+
+@dataclass
+class Eviction:
+    """One line leaving a cache level"""
+    step: int                # Instruction count of the step that evicted it
+    cycle: int               # The ISA's cycle count then (one per instruction plus memory stalls)
+    pc: int
+    cache: str
+    set_index: int
+    tag: int
+    address: int             # First word of the evicted line
+    reason: str              # A key of EVICTION_REASONS
+    incoming: Optional[int]  # The access that needed the room; None for flushes and invalidations
+    written_back: bool       # Dirty data went to the level below first
+
+    @property
+    def reason_text(self) -> str:
+        return EVICTION_REASONS[self.reason]
+
+class EvictionLog:
+    """Every line evicted from every cache level below the ISA, in order, with why and whether it was written back
+
+    Each level reports its evictions through its on_evict hook, so a
+    level has one log at a time; a new log takes the levels over. Like
+    the core's own counters, the log starts over when the statistics are
+    reset.
+    """
+
+    def __init__(self, isa):
+        self.isa = isa
+        self.reset()
+        for cache in isa.cache_levels():
+            cache.on_evict = self.on_evict
+        isa.events.subscribe(self.on_event, [SimEvent.STATS_RESET])
+
+    def reset(self) -> None:
+        self.evictions: List[Eviction] = []
+        self.dropped = 0
+
+    def on_event(self, event: Event) -> None:
+        self.reset()
+
+    def on_evict(self, cache, set_index: int, entry: dict, reason: str, address: Optional[int],
+                 written_back: bool) -> None:
+        if len(self.evictions) >= MAX_EVICTIONS:
+            self.dropped += 1
+            return
+        self.evictions.append(Eviction(self.isa.instruction_count, self.isa.cycle, self.isa.pc - 1, cache._name,
+                                       set_index, entry['tag'], cache.block_address(set_index, entry['tag']),
+                                       reason, address, written_back))
+
+    def select(self, cache: Optional[str] = None, address: Optional[int] = None) -> List[Eviction]:
+        """The evictions from one level (by name, e.g. 'L1Cache') and of the line holding one word, or all"""
+        return [e for e in self.evictions if (cache is None or e.cache == cache)
+                and (address is None or self._held(e, address))]
+
+    def _held(self, eviction: Eviction, address: int) -> bool:
+        """Whether the evicted line held the word at address"""
+        level = next(level for level in self.isa.cache_levels() if level._name == eviction.cache)
+        _, set_index, tag = level.split_address(address)
+        return (set_index, tag) == (eviction.set_index, eviction.tag)
+
+@dataclass
+class L1Access:
+    """One load or store reaching the L1, and whether its line was there"""
+    step: int
+    pc: int
+    access: str  # 'r' or 'w'
+    address: int
+    hit: bool
+
+class AccessHistory:
+    """The L1's loads and stores in order with their steps and hits, from the ISA's cache-access events
+
+    Uncacheable accesses never reach the L1 and are left out. Like the
+    core's own counters, the history starts over when the statistics are
+    reset.
+    """
+
+    def __init__(self, isa):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.CACHE_ACCESS, SimEvent.STATS_RESET])
+
+    def reset(self) -> None:
+        self.accesses: List[L1Access] = []
+        self.dropped = 0
+
+    def on_event(self, event: Event) -> None:
+        if event.kind == SimEvent.STATS_RESET:
+            self.reset()
+        elif event.data['cache'] is not None:
+            if len(self.accesses) < MAX_ACCESSES:
+                self.accesses.append(L1Access(event.step, event.pc, event.data['access'], event.data['address'],
+                                              event.data['hit']))
+            else:
+                self.dropped += 1
+
+def explain_miss(history: AccessHistory, log: EvictionLog, address: int) -> List[str]:
+    """Why the latest L1 miss on a word happened, from the history of its set
+
+    Finds the miss, the last access that found its line resident before
+    it, the eviction in between and the other lines that went through the
+    set meanwhile, and counts the L1 accesses since each.
+    """
+    cache = history.isa.cache
+    _, set_index, tag = cache.split_address(address)
+    accesses = history.accesses
+    same_line = [i for i, a in enumerate(accesses) if cache.split_address(a.address)[1:] == (set_index, tag)]
+    misses = [i for i in same_line if not accesses[i].hit]
+    where = f"word {address} (L1 set {set_index}, tag {tag})"
+    if not misses:
+        return [f"No L1 access to {where} has missed" + ("" if same_line else "; it has not been accessed")]
+    miss = misses[-1]
+    lines = [f"Latest miss on {where}: {_describe(history, accesses[miss])}, "
+             f"{len(accesses) - 1 - miss} L1 accesses ago"]
+    earlier = [i for i in same_line if i < miss]
+    if not earlier:
+        lines.append("  The line had not been used before: a compulsory miss")
+        return lines
+    last = earlier[-1]
+    lines.append(f"  Last resident at {_describe(history, accesses[last])}, {miss - last} L1 accesses before the miss")
+
+    evictions = [e for e in log.select(cache._name, address) if accesses[last].step <= e.step <= accesses[miss].step]
+    if evictions:
+        e = evictions[-1]
+        cause = f" to make room for word {e.incoming}" if e.incoming is not None else ""
+        back = ", written back first" if e.written_back else ""
+        lines.append(f"  Evicted at step {e.step} by pc {e.pc}: {e.reason_text}{cause}{back}")
+    else:
+        lines.append("  Its eviction is not in the log")
+
+    in_set = [a for a in accesses[last + 1:miss] if cache.split_address(a.address)[1] == set_index]
+    others = {cache.split_address(a.address)[2] for a in in_set}
+    ways = cache._associativity
+    lines.append(f"  Set {set_index} saw {len(in_set)} accesses to {len(others)} other lines in between; it holds "
+                 f"{ways} line{'s' if ways != 1 else ''}")
+    for a in in_set[-SET_HISTORY_SHOWN:]:
+        result = style('hit', 'hit') if a.hit else style('miss', 'miss')
+        lines.append(f"    step {a.step}: {'load' if a.access == 'r' else 'store'} word {a.address} "
+                     f"(tag {cache.split_address(a.address)[2]}) {result}")
+    return lines
+
+def _describe(history: AccessHistory, access: L1Access) -> str:
+    """An access's step and instruction"""
+    instructions = history.isa.instructions
+    source = instructions[access.pc].source if 0 <= access.pc < len(instructions) else "?"
+    return f"step {access.step}, pc {access.pc}: {source}"
+
+EVICTION_COLUMNS = ("step", "cycle", "pc", "cache", "set", "tag", "word", "reason", "for word", "written back")
+
+def eviction_row(eviction: Eviction) -> tuple:
+    """An eviction's values in EVICTION_COLUMNS order, as text"""
+    e = eviction
+    return (str(e.step), str(e.cycle), str(e.pc), e.cache.replace('Cache', ''), str(e.set_index), str(e.tag),
+            str(e.address), e.reason_text, '' if e.incoming is None else str(e.incoming),
+            'yes' if e.written_back else 'no')
+
+def format_evictions(evictions: List[Eviction], dropped: int = 0) -> List[str]:
+    """A table with one row per eviction, oldest first"""
+    if not evictions:
+        return ["No evictions yet"]
+    widths = (6, 7, 4, 5, 4, 5, 6, 20, 8, 12)
+    lines = [" ".join(f"{column:<{width}}" for column, width in zip(EVICTION_COLUMNS, widths)).rstrip()]
+    for eviction in evictions:
+        lines.append(" ".join(f"{value:<{width}}" for value, width in zip(eviction_row(eviction), widths)).rstrip())
+    if dropped:
+        lines.append(f"... and {dropped} more evictions after the first {MAX_EVICTIONS}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program and list every line evicted from the caches: "
+                                                 "when, from which set, why, and whether it was written back")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--level', choices=('L1', 'L2'), help="Only evictions from this cache level")
+    parser.add_argument('--address', type=lambda text: int(text, 0),
+                        help="Only evictions of the line holding this word, to see why a later access to it missed")
+    args = parser.parse_args()
+
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    log = EvictionLog(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The log, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    evictions = log.select(f"{args.level}Cache" if args.level else None, args.address)
+    print('\n'.join(format_evictions(evictions, log.dropped)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,290 @@
+import argparse
+import sys
+from collections import OrderedDict
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Tuple
+
+from cache.cache import Cache
+from events import Event, SimEvent
+from isa import AluOperation, Instruction, SimpleISA
+from locality import build_hierarchy
+from utils.files import read_lines
+from utils.logger import LogLevel, add_logging_arguments, configure_logging
+
+# Why the replacement policy picked the line it evicted
+EVICTION_REASONS = {'lru': "it was the least recently used line in the set",
+                    'fifo': "it was the oldest line in the set",
+                    'random': "the random replacement policy picked it"}
+
+# The three Cs: first touch, more lines in use than the whole cache holds, or than one set holds
+MISS_REASONS = {'compulsory': "the first access to this word",
+                'capacity': "the program has touched more lines since than the whole cache holds",
+                'conflict': "a fully associative cache of the same size would still hold it, but other words "
+                            "mapping to the same set pushed it out"}
+
+@dataclass
+class AccessRecord:
+    """One data access of a step and what the L1 cache did with it"""
+    access: str                           # 'r' or 'w'
+    address: int
+    cached: bool                          # False for uncacheable ranges
+    hit: bool = False
+    set_index: int = 0
+    tag: int = 0
+    miss_kind: Optional[str] = None       # One of MISS_REASONS on a miss
+    next_level_hit: Optional[bool] = None  # Whether L2 held the word on an L1 miss (None without an L2)
+    evicted_way: Optional[int] = None     # Position in the set of the line the access replaced
+    evicted_address: Optional[int] = None
+    evicted_dirty: bool = False           # The evicted line was written back first
+
+@dataclass
+class StepRecord:
+    """What one executed instruction did, for the explanation generator"""
+    step: int
+    pc: int
+    instruction: Instruction
+    next_pc: Optional[int] = None
+    registers: Dict[str, Tuple] = field(default_factory=dict)  # Changed register -> (old, new), FP included
+    flags: Dict[str, Tuple[int, int]] = field(default_factory=dict)
+    accesses: List[AccessRecord] = field(default_factory=list)
+    branch_taken: Optional[bool] = None
+    alu: Optional[AluOperation] = None
+    delay_slot_of: Optional[int] = None  # pc of the taken branch whose delay slot this instruction filled
+    delayed_target: Optional[int] = None  # Where a taken branch goes once its delay slot has run
+    exceptions: List[Tuple[str, str, bool]] = field(default_factory=list)  # (cause, message, fatal)
+    stall_cycles: int = 0  # Cycles waited for memory on L1 misses (the ISA's miss_penalty each)
+    halted: bool = False
+
+def _cache_below(cache) -> Optional[Cache]:
+    """The next cache level under cache, looking past a write buffer"""
+    level = cache._next_level
+    while level is not None and not isinstance(level, Cache):
+        level = getattr(level, '_next_level', None)
+    return level
+
+class StepRecorder:
+    """Builds a StepRecord for each step from the ISA's events
+
+    Call begin() before a step and finish() after it; events outside a
+    begin/finish pair (e.g. micro-steps) are ignored. A fully associative
+    LRU shadow of the L1 cache with the same number of lines tells capacity
+    misses from conflict misses.
+    """
+
+    def __init__(self, isa: SimpleISA, previous: Optional['StepRecorder'] = None):
+        """previous is the recorder of an earlier ISA on the same caches, whose access history carries over"""
+        self.isa = isa
+        self.record: Optional[StepRecord] = None
+        self._before = None
+        self._pending = None  # (AccessRecord, the set's entries before the access)
+        self.reset()
+        if previous:
+            self.seen, self.shadow = previous.seen, previous.shadow
+        isa.events.subscribe(self.on_event)
+
+    def reset(self) -> None:
+        """Forget which lines were touched, e.g. when a new program starts on cleared caches"""
+        self.seen = set()
+        self.shadow: OrderedDict = OrderedDict()
+
+    def begin(self) -> None:
+        isa = self.isa
+        if isa.pc >= len(isa.instructions):
+            self.record = None
+            return
+        self.record = StepRecord(isa.instruction_count + 1, isa.pc, isa.instructions[isa.pc])
+        if isa.pending_branch:
+            self.record.delay_slot_of = isa.pending_branch[0]
+        self._before = (dict(isa.registers), dict(isa.fp_registers), dict(isa.flags))
+
+    def finish(self) -> Optional[StepRecord]:
+        self._settle()
+        record, self.record = self.record, None
+        if record is None:
+            return None
+        isa = self.isa
+        registers, fp_registers, flags = self._before
+        for before, after in ((registers, isa.registers), (fp_registers, isa.fp_registers)):
+            record.registers.update({reg: (before.get(reg, 0), value) for reg, value in after.items()
+                                     if before.get(reg, 0) != value})
+        record.flags = {flag: (flags[flag], value) for flag, value in isa.flags.items() if flags.get(flag) != value}
+        record.alu = isa.step_alu
+        record.stall_cycles = isa.step_stall
+        if isa.pending_branch and isa.pending_branch[0] == record.pc:
+            record.delayed_target = isa.pending_branch[1]
+        record.halted = not isa.running
+        return record
+
+    def on_event(self, event: Event) -> None:
+        if self.record is None:
+            return
+        self._settle()
+        if event.kind == SimEvent.CACHE_ACCESS:
+            self._start_access(event)
+        elif event.kind == SimEvent.BRANCH_RESOLVED:
+            self.record.branch_taken = event.data['taken']
+        elif event.kind == SimEvent.EXCEPTION:
+            self.record.exceptions.append((event.data['cause'], event.data['message'], event.data['fatal']))
+        elif event.kind == SimEvent.INSTRUCTION_RETIRED:
+            self.record.next_pc = event.data['next_pc']
+
+    def _start_access(self, event: Event) -> None:
+        """Classify an access as the core announces it, before the cache handles it"""
+        access = AccessRecord(event.data['access'], event.data['address'], event.data['cache'] is not None,
+                              event.data['hit'])
+        self.record.accesses.append(access)
+        if not access.cached:
+            return
+        cache = self.isa.cache
+        _, access.set_index, access.tag = cache.split_address(access.address)
+        line = cache.block_address(access.set_index, access.tag)
+        if not access.hit:
+            access.miss_kind = ('compulsory' if line not in self.seen else
+                                'conflict' if line in self.shadow else 'capacity')
+            below = _cache_below(cache)
+            if below is not None:
+                access.next_level_hit = below.contains(access.address)
+        self.seen.add(line)
+        self.shadow[line] = True
+        self.shadow.move_to_end(line)
+        if len(self.shadow) > cache._sets * cache._associativity:
+            self.shadow.popitem(last=False)
+        self._pending = (access, [dict(entry) for entry in cache._entries[access.set_index]])
+
+    def _settle(self) -> None:
+        """Find the line the pending access evicted, now that the cache has handled it"""
+        if self._pending is None:
+            return
+        access, before = self._pending
+        self._pending = None
+        cache = self.isa.cache
+        remaining = {entry['tag'] for entry in cache._entries[access.set_index]}
+        for way, entry in enumerate(before):
+            if entry['tag'] not in remaining:
+                access.evicted_way = way
+                access.evicted_address = cache.block_address(access.set_index, entry['tag'])
+                access.evicted_dirty = entry['dirty'] and cache._write_policy == 'write-back'
+                break
+
+def _describe_access(access: AccessRecord, cache: Cache) -> List[str]:
+    """Sentences for one data access: where the address falls in L1 and what the hit or miss meant"""
+    verb = "wrote" if access.access == 'w' else "read"
+    if not access.cached:
+        return [f"It {verb} word {access.address}, which is in an uncacheable range, so the access went straight "
+                "to main memory and left the caches alone."]
+
+    info = cache.debug_info()
+    sentences = [f"It {verb} word {access.address} (0x{access.address:x}). L1 has {info['sets']} sets of "
+                 f"{info['associativity']} ways, so the word maps to set {access.set_index} with tag {access.tag}."]
+    if access.hit:
+        sentences.append(f"A line in set {access.set_index} held tag {access.tag}: an L1 hit, so L2 and main "
+                         "memory were not involved.")
+    else:
+        sentences.append(f"No line in set {access.set_index} held tag {access.tag}: a {access.miss_kind} miss "
+                         f"({MISS_REASONS[access.miss_kind]}).")
+        if access.next_level_hit is None:
+            sentences.append("The word came from main memory.")
+        elif access.next_level_hit:
+            sentences.append("L2 held it, so the word came from L2.")
+        else:
+            sentences.append("L2 missed as well, so the word came from main memory.")
+        if access.evicted_way is not None:
+            sentence = (f"Way {access.evicted_way} of the set (word {access.evicted_address}) was evicted to make "
+                        f"room because {EVICTION_REASONS[info['replacement_policy']]}")
+            sentences.append(sentence + ("; it was dirty, so it was written back first." if access.evicted_dirty
+                                         else "."))
+    if access.access == 'w':
+        if info['write_policy'] == 'write-through':
+            sentences.append("L1 is write-through, so the new value was passed on to L2 immediately.")
+        else:
+            sentences.append("L1 is write-back, so the line is marked dirty and reaches the next level only when "
+                             "it is evicted.")
+    return sentences
+
+def format_alu(alu: AluOperation) -> str:
+    """An ALU operation as an equation, e.g. '3 + 4 = 7' or '~5 = -6'"""
+    if alu.b is None:
+        return f"{alu.op}{alu.a} = {alu.result}"
+    return f"{alu.a} {alu.op} {alu.b} = {alu.result}"
+
+def explain_step(record: StepRecord, cache: Cache) -> List[str]:
+    """What one executed instruction did, in plain sentences
+
+    cache is the L1 cache the record's accesses went through; its geometry,
+    policies and running statistics are quoted in the explanation.
+    """
+    sentences = [f"Executed {record.instruction.source.split(';')[0].strip()} at pc {record.pc}."]
+    if record.alu:
+        sentences.append(f"The ALU computed {format_alu(record.alu)}"
+                         f"{'' if record.alu.dest else ', keeping only the flags'}.")
+    for reg, (old, new) in record.registers.items():
+        sentences.append(f"{reg} changed from {old} to {new}.")
+    if record.flags:
+        sentences.append("Flags changed: " + ", ".join(f"{flag} {old}->{new}"
+                                                       for flag, (old, new) in record.flags.items()) + ".")
+    for access in record.accesses:
+        sentences += _describe_access(access, cache)
+    if not record.accesses:
+        sentences.append("It did not access data memory, so the caches are unchanged.")
+    if record.stall_cycles:
+        sentences.append(f"The processor stalled {record.stall_cycles} cycles waiting for memory, so this "
+                         f"instruction took {record.stall_cycles + 1} cycles instead of one.")
+    if record.branch_taken is not None and record.next_pc is not None:
+        if record.delayed_target is not None:
+            sentences.append(f"The branch was taken, but its delay slot, instruction {record.next_pc}, runs first; "
+                             f"then execution continues at instruction {record.delayed_target}.")
+        else:
+            sentences.append(f"The branch was taken: execution continues at instruction {record.next_pc}."
+                             if record.branch_taken else
+                             f"The branch was not taken: execution falls through to instruction {record.next_pc}.")
+    if record.delay_slot_of is not None and record.next_pc is not None:
+        sentences.append(f"It ran in the delay slot of the branch at pc {record.delay_slot_of}, which now takes "
+                         f"effect: execution continues at instruction {record.next_pc}.")
+    for cause, message, fatal in record.exceptions:
+        sentences.append(f"The program stopped with an error: {message}" if fatal else
+                         f"It trapped ({cause}{': ' + message if message else ''}), so the handler runs next.")
+    if record.halted and not any(fatal for _, _, fatal in record.exceptions):
+        sentences.append("The program has halted.")
+    if record.accesses:
+        stats = cache.get_performance_stats()
+        sentences.append(f"L1 so far: {stats['hits']} hits, {stats['misses']} misses "
+                         f"({stats['hit_rate']:.1f}% hit rate).")
+    return sentences
+
+def main():
+    parser = argparse.ArgumentParser(description="Step through a program, explaining what each instruction does "
+                                                 "to the registers and caches")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--steps', type=int, default=10, help="Instructions to explain (default: 10)")
+    parser.add_argument('--miss-penalty', type=int, default=0,
+                        help="Cycles each L1 miss stalls the processor (default: the program's .miss_penalty, or 0)")
+    add_logging_arguments(parser)
+    args = parser.parse_args()
+    logger = configure_logging(args)
+    if not args.verbose:
+        logger.log_level = LogLevel.WARNING  # Cache traces would bury the explanations
+
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    recorder = StepRecorder(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+    if args.miss_penalty:
+        isa.set_miss_penalty(args.miss_penalty)
+
+    for step in range(args.steps):
+        if not isa.running or isa.pc >= len(isa.instructions):
+            break
+        recorder.begin()
+        isa.execute_step()
+        print(f"Step {step + 1}:")
+        for sentence in explain_step(recorder.finish(), l1):
+            print(f"  {sentence}")
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
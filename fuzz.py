@@ -0,0 +1,184 @@
+import argparse
+import glob
+import io
+import os
+import random
+import signal
+import sys
+import traceback
+from contextlib import redirect_stdout
+from typing import List, Optional
+
+from encoding import build_listing
+from isa import SimpleISA, InstructionType, CPUTrap, mnemonic
+from locality import build_hierarchy
+from properties import OPERAND_SHAPES, random_operand
+from utils.logger import Logger, LogLevel
+from verifier import ProgramVerifier
+
+ROOT = os.path.dirname(os.path.abspath(__file__))
+
+# Raw material for generated lines: valid spellings next to near misses
+MNEMONICS = [t.name for t in InstructionType] + ['ADD.S', 'CVT.S.W', 'mov', 'Load', 'FOO', 'ADD.', '.S', '']
+OPERANDS = ['eax', 'ebx', 'ecx', 'esi', 'zero', 'f0', 'f31', 'f32', 'status', 'epc', 'timer', 'loop', 'done',
+            '#0', '#1', '#-1', '#4294967296', '#99999999999999999999', '#33', '#17179869184', '#1.5', '#-0.0', '#nan', '#inf',
+            '#', '#x', '##1', '[100]', '[1023]', '[1024]', '[-1]', '[eax]', '[esp]', '[]', '[', ']',
+            '[[5]]', '[100', 'eax]', '[f0]', ';', ':', 'label:', '0', '-5']
+DIRECTIVES = ['.mode arm', '.mode standard', '.mode x86', '.endian big', '.endian middle', '.memory harvard',
+              '.memory tape', '.zero_register writable', '.zero_register maybe', '.dram 4 8', '.dram 0 0',
+              '.dram -1 8', '.dram x y', '.write_buffer 4', '.write_buffer 0', '.write_buffer -3 1',
+              '.write_buffer 4 0', '.region 0 10 r-x', '.region 10 0 rw-', '.region 0 10 abc',
+              '.region a b rwx', '.uncacheable 300 301', '.uncacheable 301 300', '.uncacheable x',
+              '.heap 500 600', '.heap 600 500', '.heap -5 2000', '.kernel_region 0 10', '.kernel_region x y',
+              '.stack 900 1023', '.stack 10 5', '.mmio 1000 1023', '.mmio 0 5000', '.word_size 8', '.word_size 16',
+              '.word_size 12', '.delay_slots on', '.delay_slots fill', '.delay_slots maybe',
+              '.miss_penalty 10', '.miss_penalty -1', '.']
+
+class Hang(Exception):
+    """A single operation ran past the time limit"""
+
+def _alarm(signum, frame):
+    raise Hang()
+
+class Fuzzer:
+    """Feeds generated and mutated programs to the assembler, the verifier, the encoder and execute_step
+
+    The contract checked is that malformed input produces an error message,
+    not a crash: load_program may only raise ValueError, the verifier and
+    the machine code listing must never raise, and execute_step must never raise or take longer than the
+    time limit on a single instruction.
+    """
+
+    def __init__(self, seed: int, time_limit: int = 2, max_steps: int = 300):
+        self.rng = random.Random(seed)
+        self.time_limit = time_limit
+        self.max_steps = max_steps
+        self.corpus = []
+        for path in sorted(glob.glob(os.path.join(ROOT, 'tests', '*.txt')) +
+                           glob.glob(os.path.join(ROOT, 'benchmarks', '*.txt'))):
+            with open(path) as f:
+                self.corpus.append(f.read().splitlines())
+
+    def _noise(self) -> str:
+        """A line of random characters, including non-ASCII and control characters"""
+        alphabet = 'abcdefxyz0123456789#[];:.,- \t\x00\xffé→'
+        return ''.join(self.rng.choice(alphabet) for _ in range(self.rng.randint(0, 20)))
+
+    def well_formed_line(self) -> str:
+        """An instruction with the right operand shape, sometimes with one operand swapped for a near miss"""
+        inst_type = self.rng.choice(list(OPERAND_SHAPES))
+        operands = [random_operand(self.rng, kind) for kind in self.rng.choice(OPERAND_SHAPES[inst_type])]
+        if operands and self.rng.random() < 0.5:
+            operands[self.rng.randrange(len(operands))] = self.rng.choice(OPERANDS)
+        return ' '.join([mnemonic(inst_type)] + operands)
+
+    def random_line(self) -> str:
+        roll = self.rng.random()
+        if roll < 0.1:
+            return self._noise()
+        if roll < 0.25:
+            return self.rng.choice(DIRECTIVES)
+        if roll < 0.35:
+            return f"{self.rng.choice(OPERANDS)}:"
+        if roll < 0.7:
+            return self.well_formed_line()
+        words = [self.rng.choice(MNEMONICS)] + [self.rng.choice(OPERANDS) for _ in range(self.rng.randint(0, 3))]
+        return ' '.join(words)
+
+    def random_program(self) -> List[str]:
+        return [self.random_line() for _ in range(self.rng.randint(1, 25))]
+
+    def mutated_program(self) -> List[str]:
+        """A test program with a few lines or words replaced, dropped or duplicated"""
+        lines = list(self.rng.choice(self.corpus))
+        for _ in range(self.rng.randint(1, 4)):
+            if not lines:
+                break
+            i = self.rng.randrange(len(lines))
+            kind = self.rng.choice(['replace', 'drop', 'duplicate', 'word'])
+            if kind == 'replace':
+                lines[i] = self.random_line()
+            elif kind == 'drop':
+                del lines[i]
+            elif kind == 'duplicate':
+                lines.insert(self.rng.randrange(len(lines) + 1), lines[i])
+            else:
+                words = lines[i].split() or ['']
+                words[self.rng.randrange(len(words))] = self.rng.choice(OPERANDS + MNEMONICS)
+                lines[i] = ' '.join(words)
+        return lines
+
+    def check(self, lines: List[str]) -> Optional[str]:
+        """Run one program through every target; returns a description of the first contract violation"""
+        memory, l1, _ = build_hierarchy()
+        isa = SimpleISA(memory=memory, cache=l1)
+        stage = 'load_program'
+        signal.alarm(self.time_limit)
+        try:
+            with redirect_stdout(io.StringIO()):
+                try:
+                    isa.load_program(lines)
+                except ValueError:
+                    return None
+                stage = 'verify'
+                ProgramVerifier(len(memory.data), isa.delay_slots).verify(isa.instructions, isa.labels,
+                                                                          entry=isa.entry_point)
+                stage = 'build_listing'
+                build_listing(isa.instructions, isa.labels)
+                stage = 'execute_step'
+                isa.running = True
+                for _ in range(self.max_steps):
+                    signal.alarm(self.time_limit)
+                    pc = isa.pc
+                    stage = f"execute_step at pc {pc}"
+                    if not isa.execute_step():
+                        break
+                # Bad programs stop with a ValueError or an unhandled trap; anything else is a simulator bug
+                if isa.last_error is not None and type(isa.last_error) not in (ValueError, CPUTrap):
+                    frame = traceback.extract_tb(isa.last_error.__traceback__)[-1]
+                    return (f"{stage} failed with {isa.last_error!r} "
+                            f"({os.path.basename(frame.filename)}:{frame.lineno})")
+        except Hang:
+            return f"{stage} took more than {self.time_limit}s"
+        except Exception as e:
+            frame = traceback.extract_tb(e.__traceback__)[-1]
+            return f"{stage} raised {e!r} ({os.path.basename(frame.filename)}:{frame.lineno})"
+        finally:
+            signal.alarm(0)
+        return None
+
+def main():
+    parser = argparse.ArgumentParser(description="Fuzz the assembler, load-time verifier and executor with random "
+                                                 "and mutated programs, reporting crashes and hangs")
+    parser.add_argument('--seed', type=int, default=0, help="Seed of the first case")
+    parser.add_argument('--cases', type=int, default=500, help="Number of programs to try")
+    parser.add_argument('--time-limit', type=int, default=2, help="Seconds allowed for loading or one step")
+    parser.add_argument('-o', '--output', help="Directory to save failing programs to")
+    args = parser.parse_args()
+
+    signal.signal(signal.SIGALRM, _alarm)
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    failures = 0
+    try:
+        for seed in range(args.seed, args.seed + args.cases):
+            fuzzer = Fuzzer(seed, args.time_limit)
+            lines = fuzzer.mutated_program() if seed % 2 else fuzzer.random_program()
+            problem = fuzzer.check(lines)
+            if not problem:
+                continue
+            failures += 1
+            print(f"seed {seed}: {problem}")
+            if args.output:
+                os.makedirs(args.output, exist_ok=True)
+                with open(os.path.join(args.output, f"crash_{seed}.txt"), 'w') as f:
+                    f.write('\n'.join(lines) + '\n')
+    finally:
+        logger.log_level = level
+
+    print(f"{args.cases - failures}/{args.cases} programs handled cleanly")
+    return 1 if failures else 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,109 @@
+import argparse
+import glob
+import io
+import json
+import os
+import sys
+from contextlib import redirect_stdout
+from typing import Dict, List
+
+from isa import SimpleISA
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from utils.logger import Logger, LogLevel
+
+ROOT = os.path.dirname(os.path.abspath(__file__))
+GOLDEN_DIR = os.path.join(ROOT, 'tests', 'golden')
+
+def golden_path(program: str) -> str:
+    return os.path.join(GOLDEN_DIR, os.path.splitext(os.path.basename(program))[0] + '.json')
+
+def capture(lines: List[str]) -> Dict:
+    """Run a program to halt on the default hierarchy and return the results a golden records
+
+    Memory holds every word the program wrote, read back through the cache
+    so write-back lines that never reached main memory are included.
+    """
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    isa.pipeline = PipelineModel(isa)
+
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        with redirect_stdout(io.StringIO()):
+            isa.load_program(lines)
+            isa.run()
+            words = {str(addr): l1.read(addr, output=False) for addr in sorted(isa.initialized_memory)
+                     if 0 <= addr < len(memory.data)}
+    finally:
+        logger.log_level = level
+
+    return {
+        'pc': isa.pc,
+        'instructions': isa.instruction_count,
+        'cycles': isa.pipeline.get_stats()['cycles'],
+        'registers': dict(isa.registers),
+        'fp_registers': {reg: value for reg, value in isa.fp_registers.items() if value},
+        'flags': dict(isa.flags),
+        'memory': words
+    }
+
+def compare(expected: Dict, actual: Dict) -> List[str]:
+    """Differences between a golden and a fresh run, one line each"""
+    problems = []
+    for key in ('pc', 'instructions', 'cycles'):
+        if expected.get(key) != actual[key]:
+            problems.append(f"{key}: expected {expected.get(key)}, got {actual[key]}")
+    for group in ('registers', 'fp_registers', 'flags', 'memory'):
+        want, got = expected.get(group, {}), actual[group]
+        for name in sorted(set(want) | set(got), key=lambda n: (len(n), n)):
+            if want.get(name) != got.get(name):
+                label = f"mem[{name}]" if group == 'memory' else name
+                problems.append(f"{label}: expected {want.get(name)}, got {got.get(name)}")
+    return problems
+
+def main():
+    parser = argparse.ArgumentParser(description="Run programs to halt and compare registers, memory and cycle "
+                                                 "counts with the checked-in golden results")
+    parser.add_argument('programs', nargs='*', help="Program files (default: tests/*.txt)")
+    parser.add_argument('--update', action='store_true', help="Regenerate the goldens instead of checking them")
+    args = parser.parse_args()
+
+    programs = args.programs or sorted(glob.glob(os.path.join(ROOT, 'tests', '*.txt')))
+    failures = 0
+    for program in programs:
+        name = os.path.relpath(program, ROOT)
+        with open(program) as f:
+            actual = capture(f.read().splitlines())
+        path = golden_path(program)
+
+        if args.update:
+            os.makedirs(GOLDEN_DIR, exist_ok=True)
+            with open(path, 'w') as f:
+                json.dump(actual, f, indent=2)
+                f.write('\n')
+            print(f"{name}: golden written")
+            continue
+
+        if not os.path.exists(path):
+            failures += 1
+            print(f"{name}: no golden (run with --update to create one)")
+            continue
+        with open(path) as f:
+            problems = compare(json.load(f), actual)
+        if problems:
+            failures += 1
+            print(f"{name}: FAILED")
+            for problem in problems:
+                print(f"    {problem}")
+        else:
+            print(f"{name}: ok")
+
+    if not args.update:
+        print(f"{len(programs) - failures}/{len(programs)} programs match their goldens")
+    return 1 if failures else 0
+
+if __name__ == '__main__':
+    sys.exit(main())
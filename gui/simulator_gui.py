@@ -1,22 +1,142 @@
 from PyQt6.QtWidgets import (QApplication, QMainWindow, QWidget, QVBoxLayout,
                             QHBoxLayout, QLabel, QPushButton, QFrame, QSlider,
-                            QTextEdit, QScrollArea, QTabWidget, QGridLayout, QDialog)
-from PyQt6.QtCore import Qt, QTimer, QPoint, QPropertyAnimation, QEasingCurve
-from PyQt6.QtGui import QFont, QPalette, QColor, QPainter, QPen, QBrush
+                            QTextEdit, QScrollArea, QTabWidget, QGridLayout, QDialog, QMessageBox,
+                            QFileDialog, QComboBox, QInputDialog, QPlainTextEdit, QCompleter,
+                            QFormLayout, QSpinBox, QCheckBox, QDialogButtonBox, QDockWidget, QLineEdit, QTabBar,
+                            QTableWidget, QTableWidgetItem)
+from PyQt6.QtSvg import QSvgGenerator
+from PyQt6.QtCore import Qt, QTimer, QPoint, QPropertyAnimation, QEasingCurve, QStringListModel, QByteArray
+from PyQt6.QtGui import (QFont, QPalette, QColor, QPainter, QPen, QBrush, QKeySequence, QShortcut,
+                         QSyntaxHighlighter, QTextCharFormat, QTextCursor)
+import argparse
+import re
 import sys
 import os
-
-print("Starting simulator...")
+import math
+from string import Template
+
+# Border colors for the data regions of scheduled processes
+PROCESS_COLORS = ["#ff69b4", "#00ccff", "#ffaa00", "#9370db"]
+
+# Named colors of each theme, used by the control, register, cache and memory panels.
+# hit and miss mark the cache block the last step accessed, pc the current instruction.
+THEME_COLORS = {
+    'dark': {'background': "#1e1e1e", 'panel': "#2b2b2b", 'hover': "#3b3b3b", 'pressed': "#1b1b1b",
+             'separator': "#333333", 'active': "#2e2e2e", 'text': "#ffffff", 'muted': "#aaaaaa", 'label': "#888888",
+             'dim': "#666666", 'accent': "#00ff00", 'l1': "#ff69b4", 'l2': "#9370db", 'flags': "#ff69b4",
+             'register': "#ffaa00", 'status': "#ffaa00", 'pc_text': "#0099ff", 'info': "#00ccff",
+             'prefetch': "#ffd700", 'uncached': "#ff8c00", 'error': "#ff4444",
+             'hit': "#1e7a3a", 'miss': "#8b1e1e", 'pc': "#1e5c1e", 'cursor': "#444444"},
+    'light': {'background': "#ffffff", 'panel': "#f0f0f0", 'hover': "#e0e0e0", 'pressed': "#d0d0d0",
+              'separator': "#cccccc", 'active': "#e8e8e8", 'text': "#000000", 'muted': "#555555", 'label': "#666666",
+              'dim': "#999999", 'accent': "#006400", 'l1': "#c71585", 'l2': "#6a3d9a", 'flags': "#c71585",
+              'register': "#b35900", 'status': "#b35900", 'pc_text': "#0050b3", 'info': "#007acc",
+              'prefetch': "#a67c00", 'uncached': "#c85a00", 'error': "#cc0000",
+              'hit': "#b8e6c1", 'miss': "#f4b4b4", 'pc': "#c8e6c9", 'cursor': "#dddddd"},
+}
+
+# Okabe-Ito replacements for the colors that carry meaning, told apart with any color vision deficiency
+COLORBLIND_COLORS = {
+    'dark': {'l1': "#cc79a7", 'l2': "#56b4e9", 'flags': "#cc79a7", 'register': "#e69f00", 'status': "#e69f00",
+             'info': "#56b4e9", 'prefetch': "#f0e442", 'uncached': "#e69f00", 'error': "#d55e00",
+             'hit': "#0072b2", 'miss': "#d55e00", 'pc': "#5c5200"},
+    'light': {'l1': "#cc79a7", 'l2': "#0072b2", 'flags': "#cc79a7", 'register': "#d55e00", 'status': "#d55e00",
+              'info': "#0072b2", 'prefetch': "#e69f00", 'uncached': "#d55e00", 'error': "#d55e00",
+              'hit': "#56b4e9", 'miss': "#e69f00", 'pc': "#f0e442"},
+}
+
+def theme_colors(theme, palette='standard', highlights=None):
+    """Color name -> #rrggbb for a theme and palette, with any highlight colors the user chose"""
+    colors = dict(THEME_COLORS[theme])
+    if palette == 'colorblind':
+        colors.update(COLORBLIND_COLORS[theme])
+    colors.update({name: color for name, color in (highlights or {}).items() if color})
+    return colors
+
+# Window colors of the dark theme; the light theme is Qt's default palette
+DARK_PALETTE = {'Window': "#1e1e1e", 'WindowText': "#dddddd", 'Base': "#252525", 'AlternateBase': "#2b2b2b",
+                'Text': "#dddddd", 'Button': "#2b2b2b", 'ButtonText': "#dddddd", 'ToolTipBase': "#2b2b2b",
+                'ToolTipText': "#dddddd", 'Highlight': "#1e5c1e", 'HighlightedText': "#ffffff"}
+
+# Bumped when the panels change so layouts saved by older versions are not restored
+LAYOUT_VERSION = 1
+
+# Border colors for heap words managed by the malloc/free syscalls
+HEAP_COLORS = {'allocated': "#00ff7f", 'free': "#2e8b57", 'header': "#556b2f"}
+
+# Most-accessed end of the memory window's heatmap; blocks blend toward it from the theme background
+HEAT_COLOR = "#ff3030"
+
+# Colors of the memory kinds (SimpleISA.memory_kind) in the memory panel and hex view, readable on either theme
+MEMORY_KIND_COLORS = {'instruction': "#4f9fd8", 'data': "#d4a017", 'stack': "#b060d0", 'heap': "#3cb371",
+                      'mmio': "#ff8c00", 'untouched': "#808080"}
 
 # Add the parent directory to the Python path
 sys.path.append(os.path.dirname(os.path.dirname(os.path.abspath(__file__))))
 
-from isa import SimpleISA
-from cache.cache import Cache
-from memory import MainMemory
-from utils.logger import Logger, LogLevel
-
-print("Imports successful...")
+from isa import SimpleISA, InstructionType, mnemonic
+from scheduler import RoundRobinScheduler
+from workload import format_workload
+from verifier import ProgramVerifier, check_source
+from reference import format_isa_list, format_reference, instruction_reference
+from encoding import build_listing, format_fields
+from dependency import DependencyGraph
+from pipeline import PipelineModel, PipelineResources, STAGES, parse_resources
+from vcd import VCDRecorder
+from events import EventStats, SimEvent
+from history import MachineState, UndoStack
+from snapshots import CURRENT, SnapshotStore, format_diff
+from microcode import MicroSequencer, CONTROL_ROM
+from lsq import LoadStoreQueue
+from predictor import BranchPredictor, format_predictor
+from locality import MATRIX_PROGRAMS, GUIDE, compare_matrix_programs
+from report import write_report
+from linker import format_map, link as link_modules
+from objfile import read_modules
+from compare import MachineConfig, LockstepComparison, format_comparison, parse_config
+from explain import StepRecorder, explain_step
+from profiler import FunctionProfiler, format_profile
+from heatmap import AccessHeatmap
+from workingset import DEFAULT_WINDOW, cache_capacities, summarize, working_set
+from strides import MIN_RUN, StrideAnalyzer
+from evictions import EVICTION_COLUMNS, EvictionLog, eviction_row
+from whatif import CacheConfig, CacheTrace, replay
+from sweeps import SWEEPS, describe_knee, format_sweep, knee, peak_working_set
+from scoreboard import Scoreboard, format_scoreboard
+from amat import amat_levels, format_amat, format_formula
+from compiler import CompileError, compile_source
+from compiler.exprline import PSEUDO_INSTRUCTIONS
+from hexdump import dump_rows, format_dump, parse_range
+from cachestate import load_cache_state, save_cache_state
+from compiler.optimizer import compare as compare_optimization, format_comparison as format_optimization, optimize
+from tutorial import LESSONS, SAMPLE_PROGRAM
+from cache.cache import REPLACEMENT_POLICIES, WRITE_POLICIES, format_read_write, format_requesters
+from settings import (Settings, load_settings, save_settings, build_hierarchy, format_word,
+                      MEMORY_FORMATS, THEMES, PALETTES, HIGHLIGHTS, ENDIANNESSES, STEP_SPEED_RANGE, CONFIG_PATH)
+from isa import (DELAY_SLOT_MODES, ISA_MODES, MEMORY_KINDS, MEMORY_ORGANIZATIONS, WORD_SIZES, ZERO_REGISTER,
+                 LINK_REGISTER, call_depth_change, symbol_for)
+from utils.logger import Logger, LogLevel, LOG_TARGETS, add_logging_arguments, configure_logging
+
+Logger().log(LogLevel.DEBUG, "Imports successful...")
+
+# Cycles an L1 miss stalls the processor, stepped through by the "Miss penalty:" button
+MISS_PENALTIES = (0, 10, 50, 100)
+
+# File dialog filter for image exports; the extension picks the format
+IMAGE_FILTER = "PNG image (*.png);;SVG image (*.svg)"
+
+def save_widget_image(widget, filename):
+    """Render a widget at its full size, scrolled-away parts included, to a PNG or (for .svg) an SVG"""
+    if filename.lower().endswith('.svg'):
+        generator = QSvgGenerator()
+        generator.setFileName(filename)
+        generator.setSize(widget.size())
+        generator.setViewBox(widget.rect())
+        generator.setTitle(widget.windowTitle() or "CPU Simulator")
+        painter = QPainter(generator)
+        widget.render(painter)
+        return painter.end()
+    return widget.grab().save(filename)
 
 class FlowLine(QWidget):
     def __init__(self, parent=None):
@@ -77,22 +197,746 @@ class FlowLine(QWidget):
         painter.setBrush(QBrush(self.color))
         painter.drawPolygon(*points)
 
+class AnimatedView(QWidget):
+    """A painted diagram that plays a short animation: progress runs from 0 to 1 in FRAMES repaints"""
+    FRAMES = 20
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.progress = 1.0
+        self.timer = QTimer(self)
+        self.timer.timeout.connect(self._advance)
+
+    def _animate(self, duration):
+        """Play the animation over about duration ms, or jump to its end if duration is 0"""
+        self.timer.stop()
+        if duration <= 0:
+            self.progress = 1.0
+        else:
+            self.progress = 0.0
+            self.timer.start(max(duration // self.FRAMES, 1))
+        self.update()
+
+    def _advance(self):
+        self.progress = min(self.progress + 1 / self.FRAMES, 1.0)
+        if self.progress >= 1.0:
+            self.timer.stop()
+        self.update()
+
+class AluView(AnimatedView):
+    """The ALU: operands A and B flow in at the top, the operator acts on them and the result flows out below
+
+    show_operation() starts the animation: for the first half the operand
+    values slide down into the ALU, for the second the result slides out
+    to the destination. Idle, it shows the operations it can perform.
+    """
+    COLORS = {'a': "#00ccff", 'b': "#ffaa00", 'result': "#00ff00", 'body': "#555555", 'text': "#aaaaaa"}
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.alu = None  # isa.AluOperation being shown
+        self.setMinimumSize(220, 110)
+
+    def show_operation(self, alu, duration=400):
+        """Animate alu over about duration ms; None (no ALU work this step) clears the view"""
+        self.alu = alu
+        self._animate(duration if alu is not None else 0)
+
+    @staticmethod
+    def _value_text(value):
+        return "-" if value is None else f"{value:g}" if isinstance(value, float) else str(value)
+
+    def paintEvent(self, event):
+        painter = QPainter(self)
+        painter.setRenderHint(QPainter.RenderHint.Antialiasing)
+        painter.setFont(QFont("Courier", 8))
+        width, height = self.width(), self.height()
+        top, bottom = 28, height - 30  # The ALU body between the operand inputs and the result output
+        left, right = width // 4, width * 3 // 4
+        notch = width // 2
+
+        # The classic ALU outline: a trapezoid with a notch between the two inputs
+        painter.setPen(QPen(QColor(self.COLORS['body']), 2))
+        painter.setBrush(QBrush(QColor("#2b2b2b")))
+        painter.drawPolygon(QPoint(left - 30, top), QPoint(notch - 8, top), QPoint(notch, top + 10),
+                            QPoint(notch + 8, top), QPoint(right + 30, top), QPoint(right, bottom),
+                            QPoint(left, bottom))
+        painter.setPen(QColor(self.COLORS['text']))
+        if self.alu is None:
+            painter.drawText(left, (top + bottom) // 2 + 4, "ALU  + - & | ^ << >>")
+            return
+        painter.setFont(QFont("Courier", 12, QFont.Weight.Bold))
+        painter.drawText(notch - 8, (top + bottom) // 2 + 10, self.alu.op)
+        painter.setFont(QFont("Courier", 8))
+
+        # Operands travel from above the inputs to inside the body, then the result to below the output
+        inflow = min(self.progress * 2, 1.0)
+        outflow = max(self.progress * 2 - 1, 0.0)
+        for name, value, x in (('a', self.alu.a, left - 10), ('b', self.alu.b, right - 30)):
+            if value is None:
+                continue
+            # Once the result leaves, the operands rest dimmed at their inputs
+            moving = outflow == 0
+            painter.setPen(QColor(self.COLORS[name] if moving else self.COLORS['text']))
+            painter.drawText(x, 12 + int(inflow * (top + 6)) if moving else 12,
+                             f"{name.upper()}={self._value_text(value)}")
+        if outflow > 0:
+            y = bottom - 4 + int(outflow * (height - bottom))
+            painter.setPen(QColor(self.COLORS['result']))
+            destination = f" -> {self.alu.dest}" if self.alu.dest else " -> flags"
+            painter.drawText(left - 10, y, f"{self._value_text(self.alu.result)}{destination}")
+
+class FetchView(AnimatedView):
+    """The fetch: a PC box with an arrow to the memory word it fetched, the IR holding that word's instruction,
+    and how the next PC was computed (PC + 1, or a branch or jump target)
+
+    The arrow grows from the PC to the fetched word when a step is shown.
+    Memory lists a few words of program text around the fetched one.
+    """
+    COLORS = {'pc': "#0099ff", 'ir': "#00ff00", 'cell': "#555555", 'fetched': "#1e5c1e", 'text': "#aaaaaa",
+              'next': "#ffaa00"}
+    CELLS = 5  # Program words shown around the fetched one
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.pc = 0
+        self.address = 0            # Memory word of the instruction at pc
+        self.cells = []             # [(address, source)] of the words shown, in address order
+        self.instruction = ""       # What the IR holds; empty before the first fetch
+        self.next_pc = None
+        self.next_text = ""         # How the next PC was computed
+        self.setMinimumSize(300, 120)
+
+    def show_fetch(self, pc, address, cells, instruction, next_pc, next_text, duration=400):
+        self.pc, self.address, self.cells = pc, address, cells
+        self.instruction, self.next_pc, self.next_text = instruction, next_pc, next_text
+        self._animate(duration)
+
+    def paintEvent(self, event):
+        painter = QPainter(self)
+        painter.setRenderHint(QPainter.RenderHint.Antialiasing)
+        painter.setFont(QFont("Courier", 8))
+        box_width, box_height, row_height = 110, 26, 18
+        memory_left = box_width + 50
+
+        # PC and IR registers
+        for y, name, text, color in ((4, "PC", f"{self.pc}", self.COLORS['pc']),
+                                     (4 + box_height + 14, "IR", self.instruction or "-", self.COLORS['ir'])):
+            painter.setPen(QPen(QColor(color), 2))
+            painter.setBrush(Qt.BrushStyle.NoBrush)
+            painter.drawRect(4, y, box_width, box_height)
+            painter.drawText(8, y + 17, f"{name} {text}"[:17])
+
+        # Program text around the fetched word
+        fetched_y = None
+        for row, (address, source) in enumerate(self.cells):
+            y = 4 + row * row_height
+            fetched = address == self.address
+            painter.setPen(QPen(QColor(self.COLORS['cell']), 1))
+            painter.setBrush(QBrush(QColor(self.COLORS['fetched'])) if fetched else Qt.BrushStyle.NoBrush)
+            painter.drawRect(memory_left, y, self.width() - memory_left - 4, row_height - 2)
+            painter.setPen(QColor(self.COLORS['text']))
+            painter.drawText(memory_left + 4, y + 12, f"[{address}] {source}")
+            if fetched:
+                fetched_y = y + row_height // 2
+
+        # The fetch arrow grows from the PC box to the fetched word
+        if fetched_y is not None:
+            start = QPoint(4 + box_width, 4 + box_height // 2)
+            end = QPoint(start.x() + int((memory_left - start.x()) * self.progress),
+                         start.y() + int((fetched_y - start.y()) * self.progress))
+            painter.setPen(QPen(QColor(self.COLORS['pc']), 2))
+            painter.drawLine(start, end)
+            if self.progress >= 1.0:
+                painter.setBrush(QBrush(QColor(self.COLORS['pc'])))
+                painter.drawPolygon(end, QPoint(end.x() - 8, end.y() - 4), QPoint(end.x() - 8, end.y() + 4))
+
+        # Next PC computation
+        painter.setPen(QColor(self.COLORS['next']))
+        painter.drawText(4, 4 + 2 * box_height + 34, f"next {self.next_text}")
+
+class NextPcView(QWidget):
+    """The two next PCs of the branch about to execute: fall-through and target, each named by its instruction
+
+    The path the branch will take is filled; the one the fetch stage
+    predicts is marked, so a misprediction shows as the mark and the fill
+    on different boxes.
+    """
+    COLORS = {'taken': "#1e7a3a", 'border': "#555555", 'predicted': "#ffd700", 'text': "#aaaaaa",
+              'mispredicted': "#ff4444"}
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.preview = None  # isa.BranchPreview, None when the next instruction is not a branch
+        self.names = {}      # Instruction index -> text shown for it
+        self.setMinimumSize(300, 56)
+
+    def set_preview(self, preview, names):
+        self.preview, self.names = preview, names
+        self.update()
+
+    def paintEvent(self, event):
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        if self.preview is None:
+            painter.setPen(QColor(self.COLORS['text']))
+            painter.drawText(4, 14, "next PC: not a branch, PC + 1")
+            return
+        half = (self.width() - 12) // 2
+        boxes = (("fall-through", self.preview.fall_through, False), ("target", self.preview.target, True))
+        for column, (title, index, taken) in enumerate(boxes):
+            x = 4 + column * (half + 4)
+            actual = taken == self.preview.taken
+            predicted = taken == self.preview.predicted_taken
+            painter.setPen(QPen(QColor(self.COLORS['predicted'] if predicted else self.COLORS['border']),
+                                2 if predicted else 1))
+            painter.setBrush(QBrush(QColor(self.COLORS['taken'])) if actual else Qt.BrushStyle.NoBrush)
+            painter.drawRect(x, 4, half, 34)
+            painter.setPen(QColor(self.COLORS['text']))
+            painter.drawText(x + 4, 16, f"{title}{' (predicted)' if predicted else ''}")
+            painter.drawText(x + 4, 32, self.names.get(index, "?") if index is not None else "not known yet")
+        if self.preview.delay_slot:
+            painter.setPen(QColor(self.COLORS['text']))
+            painter.drawText(4, 52, "delay slot: the fall-through runs first either way")
+        elif self.preview.mispredicted:
+            painter.setPen(QColor(self.COLORS['mispredicted']))
+            wrong_path = 'target' if self.preview.predicted_taken else 'fall-through'
+            painter.drawText(4, 52, f"mispredicted: the fetched {wrong_path} is flushed")
+
+class BarChart(QWidget):
+    """Horizontal bar chart with one row per label and a bar per series, or one bar of stacked series"""
+    def __init__(self, series_colors, parent=None, stacked=False, label_width=60):
+        super().__init__(parent)
+        self.series_colors = series_colors  # [(series name, color)]
+        self.stacked = stacked
+        self.label_width = label_width
+        self.rows = []  # [(label, [value per series])]
+        self.setMinimumHeight(120)
+
+    def set_rows(self, rows):
+        self.rows = rows
+        self.setMinimumHeight(20 + 18 * len(rows))
+        self.update()
+
+    def paintEvent(self, event):
+        if not self.rows:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        label_width = self.label_width
+        bar_height = 14 // len(self.series_colors)
+        max_value = max(sum(values) if self.stacked else max(values) for _, values in self.rows) or 1
+        scale = (self.width() - label_width - 40) / max_value
+
+        for row, (label, values) in enumerate(self.rows):
+            y = 4 + row * 18
+            painter.setPen(QColor("#aaaaaa"))
+            painter.drawText(0, y + 12, label)
+            if self.stacked:
+                x = label_width
+                for i, value in enumerate(values):
+                    painter.fillRect(x, y, int(value * scale), 13, QColor(self.series_colors[i][1]))
+                    x += int(value * scale)
+                painter.drawText(x + 4, y + 12, "/".join(str(value) for value in values))
+                continue
+            for i, value in enumerate(values):
+                color = QColor(self.series_colors[i][1])
+                painter.fillRect(label_width, y + i * bar_height, int(value * scale), bar_height - 1, color)
+                painter.setPen(color)
+                painter.drawText(label_width + int(value * scale) + 4, y + (i + 1) * bar_height, str(value))
+
+        # Legend
+        x = label_width
+        for name, color in self.series_colors:
+            painter.setPen(QColor(color))
+            painter.drawText(x, self.height() - 2, name)
+            x += 8 * (len(name) + 2)
+
+class DependencyGraphView(QWidget):
+    """Draws a DependencyGraph with one row per level; instructions in a row could run in parallel"""
+    EDGE_COLORS = {'RAW': "#ff4444", 'WAR': "#4488ff", 'WAW': "#ffaa00"}
+    NODE_WIDTH = 150
+    NODE_HEIGHT = 22
+    ROW_SPACING = 50
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.graph = None
+        self.positions = {}
+
+    def set_graph(self, graph):
+        self.graph = graph
+        self.positions = {}
+        rows = {}
+        for index, level in enumerate(graph.levels()):
+            rows.setdefault(level, []).append(index)
+        for level, members in rows.items():
+            for column, index in enumerate(members):
+                self.positions[index] = (10 + column * (self.NODE_WIDTH + 20), 10 + level * self.ROW_SPACING)
+        widest = max((len(members) for members in rows.values()), default=1)
+        self.resize(20 + widest * (self.NODE_WIDTH + 20), 20 + len(rows) * self.ROW_SPACING)
+        self.update()
+
+    def paintEvent(self, event):
+        if not self.graph:
+            return
+
+        painter = QPainter(self)
+        painter.setRenderHint(QPainter.RenderHint.Antialiasing)
+        painter.setFont(QFont("Courier", 8))
+
+        for edge in self.graph.edges:
+            sx, sy = self.positions[edge.source]
+            tx, ty = self.positions[edge.target]
+            painter.setPen(QPen(QColor(self.EDGE_COLORS[edge.kind]), 1))
+            painter.drawLine(sx + self.NODE_WIDTH // 2, sy + self.NODE_HEIGHT,
+                             tx + self.NODE_WIDTH // 2, ty)
+
+        for index, (x, y) in self.positions.items():
+            painter.setPen(QPen(QColor("#00ff00"), 1))
+            painter.setBrush(QBrush(QColor("#1e1e1e")))
+            painter.drawRect(x, y, self.NODE_WIDTH, self.NODE_HEIGHT)
+            painter.setPen(QColor("#ffffff"))
+            painter.drawText(x + 4, y + 15, f"{index}: {self.graph.instructions[index].source}"[:22])
+
+class PipelineGanttChart(AnimatedView):
+    """One row per dynamic instruction and one column per cycle, showing the stage it occupies
+
+    Instructions fetched down the wrong path after a mispredicted branch
+    follow it in their own color, struck out with an X the cycle they were
+    squashed; show_squash() animates the X over the latest branch's.
+    """
+    STAGE_COLORS = {'IF': "#4488ff", 'ID': "#00cc66", 'EX': "#ffaa00", 'MEM': "#9370db", 'WB': "#888888"}
+    STALL_COLOR = "#ff4444"
+    WRONG_PATH_COLOR = "#ff66cc"
+    LABEL_WIDTH = 190
+    CELL_WIDTH = 30
+    ROW_HEIGHT = 18
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.records = []
+        self.rows = []  # Records, each mispredicted branch followed by its wrong-path records
+        self.cycles = 0
+        self.squashing = None  # seq of the branch whose wrong path is being squashed
+
+    def set_records(self, records, cycles, wrong_path=()):
+        self.records = records
+        self.cycles = cycles
+        squashed = {}
+        for record in wrong_path:
+            squashed.setdefault(record.seq, []).append(record)
+        self.rows = [row for record in records for row in [record] + squashed.get(record.seq, [])]
+        self.resize(self.LABEL_WIDTH + (cycles + 1) * self.CELL_WIDTH, (len(self.rows) + 1) * self.ROW_HEIGHT + 4)
+        self.update()
+
+    def show_squash(self, seq, duration=400):
+        """Strike out the wrong-path instructions of the branch seq as it resolves"""
+        self.squashing = seq
+        self._animate(duration)
+
+    def paintEvent(self, event):
+        if not self.records:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+
+        # Cycle numbers along the top
+        painter.setPen(QColor("#aaaaaa"))
+        for cycle in range(1, self.cycles + 1):
+            painter.drawText(self.LABEL_WIDTH + (cycle - 1) * self.CELL_WIDTH + 4, 12, str(cycle))
+
+        for row, record in enumerate(self.rows, start=1):
+            y = row * self.ROW_HEIGHT
+            if record.wrong_path:
+                self._paint_wrong_path(painter, record, y)
+                continue
+            painter.setPen(QColor("#ffffff"))
+            painter.drawText(2, y + 13, f"{record.seq}: {record.source}"[:26])
+
+            # A stage spans from its start to the cycle before the next stage; extra cycles are stalls
+            for i, stage in enumerate(STAGES):
+                start = record.stages[stage]
+                end = record.stages[STAGES[i + 1]] - 1 if i + 1 < len(STAGES) else start
+                for cycle in range(start, end + 1):
+                    x = self.LABEL_WIDTH + (cycle - 1) * self.CELL_WIDTH
+                    stalled = cycle < end
+                    painter.fillRect(x, y + 1, self.CELL_WIDTH - 1, self.ROW_HEIGHT - 2,
+                                     QColor(self.STALL_COLOR if stalled else self.STAGE_COLORS[stage]))
+                    painter.setPen(QColor("#000000"))
+                    painter.drawText(x + 2, y + 13, "--" if stalled else stage)
+
+    def _paint_wrong_path(self, painter, record, y):
+        """A squashed instruction: the stages it reached, fading, and an X drawn across the cycle it was squashed"""
+        progress = self.progress if record.seq == self.squashing else 1.0
+        color = QColor(self.WRONG_PATH_COLOR)
+        painter.setPen(color)
+        painter.drawText(2, y + 13, f"  x {record.source}"[:26])
+        color.setAlpha(int(255 - 155 * progress))
+        for stage, cycle in record.stages.items():
+            x = self.LABEL_WIDTH + (cycle - 1) * self.CELL_WIDTH
+            painter.fillRect(x, y + 1, self.CELL_WIDTH - 1, self.ROW_HEIGHT - 2, color)
+            painter.setPen(QColor("#000000"))
+            painter.drawText(x + 2, y + 13, stage)
+        x = self.LABEL_WIDTH + (record.squashed - 1) * self.CELL_WIDTH
+        reach_x, reach_y = int((self.CELL_WIDTH - 4) * progress), int((self.ROW_HEIGHT - 4) * progress)
+        painter.setPen(QPen(QColor(self.STALL_COLOR), 2))
+        painter.drawLine(x + 2, y + 2, x + 2 + reach_x, y + 2 + reach_y)
+        painter.drawLine(x + 2, y + self.ROW_HEIGHT - 2, x + 2 + reach_x, y + self.ROW_HEIGHT - 2 - reach_y)
+
+class DivergenceChart(QWidget):
+    """Cycle counts of two lock-stepped machines after each step, with the gap between them shaded"""
+    COLORS = {'A': "#00ccff", 'B': "#ffaa00", 'gap': "#ff6347"}
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.samples = []  # [(step, cycles A, cycles B)]
+        self.setMinimumHeight(180)
+
+    def set_samples(self, samples):
+        self.samples = samples
+        self.update()
+
+    def paintEvent(self, event):
+        if len(self.samples) < 2:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        left, bottom = 50, self.height() - 16
+        width, height = self.width() - left - 10, bottom - 16
+        last_step = self.samples[-1][0] or 1
+        top = max(max(a, b) for _, a, b in self.samples) or 1
+
+        def point(step, cycles):
+            return QPoint(left + int(step * width / last_step), bottom - int(cycles * height / top))
+
+        # One sample per pixel column is enough
+        stride = max(1, len(self.samples) // max(width, 1))
+        samples = self.samples[::stride] + ([self.samples[-1]] if (len(self.samples) - 1) % stride else [])
+        gap = QColor(self.COLORS['gap'])
+        gap.setAlpha(80)
+        for step, a, b in samples:
+            painter.setPen(gap)
+            painter.drawLine(point(step, a), point(step, b))
+        for index, name in ((1, 'A'), (2, 'B')):
+            painter.setPen(QPen(QColor(self.COLORS[name]), 2))
+            for before, after in zip(samples, samples[1:]):
+                painter.drawLine(point(before[0], before[index]), point(after[0], after[index]))
+
+        painter.setPen(QColor("#aaaaaa"))
+        painter.drawLine(left, bottom, left + width, bottom)
+        painter.drawLine(left, bottom, left, bottom - height)
+        painter.drawText(0, bottom - height + 8, str(top))
+        painter.drawText(0, bottom, "0")
+        painter.drawText(left, self.height() - 2, "step 0")
+        painter.drawText(left + width - 70, self.height() - 2, f"step {last_step}")
+        x = left + 80
+        for name, color in self.COLORS.items():
+            painter.setPen(QColor(color))
+            painter.drawText(x, 12, name if name == 'gap' else f"cycles {name}")
+            x += 80
+
+class AccessTimelineChart(QWidget):
+    """Each data access as a dot at its cycle (x) and address (y); strides show as lines, reuse as rows"""
+    COLORS = {'r': "#00ccff", 'w': "#ff6347"}
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.samples = []  # [heatmap.Access]
+        self.setMinimumSize(500, 300)
+
+    def set_samples(self, samples):
+        self.samples = samples
+        self.update()
+
+    def paintEvent(self, event):
+        if not self.samples:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        left, bottom = 50, self.height() - 16
+        width, height = self.width() - left - 10, bottom - 16
+        first = min(s.address for s in self.samples)
+        last = max(s.address for s in self.samples)
+        start, end = self.samples[0].cycle, self.samples[-1].cycle
+        for sample in self.samples:
+            x = left + (sample.cycle - start) * width // max(end - start, 1)
+            y = bottom - (sample.address - first) * height // max(last - first, 1)
+            painter.fillRect(x - 1, y - 1, 3, 3, QColor(self.COLORS[sample.access]))
+
+        painter.setPen(QColor("#aaaaaa"))
+        painter.drawLine(left, bottom, left + width, bottom)
+        painter.drawLine(left, bottom, left, bottom - height)
+        painter.drawText(0, bottom - height + 8, str(last))
+        painter.drawText(0, bottom, str(first))
+        painter.drawText(left, self.height() - 2, f"cycle {start}")
+        painter.drawText(left + width - 80, self.height() - 2, f"cycle {end}")
+        x = left + 100
+        for access, color in self.COLORS.items():
+            painter.setPen(QColor(color))
+            painter.drawText(x, 12, "reads" if access == 'r' else "writes")
+            x += 60
+
+class WorkingSetChart(QWidget):
+    """Working-set size (y) over cycles (x), with a dashed line at each cache level's capacity"""
+    LINE_COLOR = "#00ff00"
+    CAPACITY_COLORS = ["#00ccff", "#ffaa00", "#ff6347"]
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.points = []  # [workingset.WorkingSetPoint]
+        self.capacities = []  # [(cache name, words)]
+        self.setMinimumSize(500, 250)
+
+    def set_points(self, points, capacities):
+        self.points = points
+        self.capacities = capacities
+        self.update()
+
+    def paintEvent(self, event):
+        if not self.points:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        left, bottom = 40, self.height() - 16
+        width, height = self.width() - left - 90, bottom - 16
+        top = max([point.words for point in self.points] + [words for _, words in self.capacities]) or 1
+        start, end = self.points[0].cycle, self.points[-1].cycle
+
+        def position(point):
+            return (left + (point.cycle - start) * width // max(end - start, 1),
+                    bottom - point.words * height // top)
+
+        for i, (name, words) in enumerate(self.capacities):
+            color = QColor(self.CAPACITY_COLORS[i % len(self.CAPACITY_COLORS)])
+            y = bottom - words * height // top
+            painter.setPen(QPen(color, 1, Qt.PenStyle.DashLine))
+            painter.drawLine(left, y, left + width, y)
+            painter.drawText(left + width + 4, y + 4, f"{name.replace('Cache', '')} {words}")
+
+        painter.setPen(QPen(QColor(self.LINE_COLOR), 2))
+        for previous, point in zip(self.points, self.points[1:]):
+            painter.drawLine(*position(previous), *position(point))
+
+        painter.setPen(QColor("#aaaaaa"))
+        painter.drawLine(left, bottom, left + width, bottom)
+        painter.drawLine(left, bottom, left, bottom - height)
+        painter.drawText(0, bottom - height + 8, str(top))
+        painter.drawText(0, bottom, "0")
+        painter.drawText(left, self.height() - 2, f"cycle {start}")
+        painter.drawText(left + width - 80, self.height() - 2, f"cycle {end}")
+        painter.drawText(left + 8, 12, "distinct words in the window")
+
+class SweepChart(QWidget):
+    """Miss rate (left y) and traffic to the level below (right y) at each setting of a cache sweep (x, evenly
+    spaced), with each point's miss rate written above it and a dashed vertical line at each mark"""
+    LINE_COLOR = "#00ccff"
+    TRAFFIC_COLOR = "#ffaa00"
+    MARK_COLOR = "#ff6347"
+
+    def __init__(self, parent=None):
+        super().__init__(parent)
+        self.points = []  # [sweeps.SweepPoint]
+        self.heading = ""
+        self.marks = []  # [(label, setting)], placed between the points' settings on a log scale
+        self.setMinimumSize(500, 250)
+
+    def set_points(self, points, heading, marks=()):
+        self.points = points
+        self.heading = heading
+        self.marks = list(marks)
+        self.update()
+
+    def _mark_x(self, value, left, step):
+        """Where a setting falls on the x axis, interpolating in log2 between the two points around it"""
+        values = [point.value for point in self.points]
+        if value <= values[0]:
+            return left
+        for i, (low, high) in enumerate(zip(values, values[1:])):
+            if value <= high:
+                return left + int((i + math.log2(value / low) / math.log2(high / low)) * step)
+        return left + (len(values) - 1) * step
+
+    def paintEvent(self, event):
+        if not self.points:
+            return
+
+        painter = QPainter(self)
+        painter.setFont(QFont("Courier", 8))
+        left, bottom = 40, self.height() - 20
+        width, height = self.width() - left - 60, bottom - 30
+        top = max(point.miss_rate for point in self.points) or 1
+        top_traffic = max(point.traffic for point in self.points) or 1
+        step = width // max(len(self.points) - 1, 1)
+        positions = [(left + i * step, bottom - int(point.miss_rate * height / top))
+                     for i, point in enumerate(self.points)]
+        traffic = [(left + i * step, bottom - point.traffic * height // top_traffic)
+                   for i, point in enumerate(self.points)]
+
+        painter.setPen(QPen(QColor(self.TRAFFIC_COLOR), 1, Qt.PenStyle.DashLine))
+        for previous, position in zip(traffic, traffic[1:]):
+            painter.drawLine(*previous, *position)
+        painter.setPen(QColor(self.TRAFFIC_COLOR))
+        painter.drawText(left + width + 4, bottom - height + 8, str(top_traffic))
+        painter.drawText(left + width - 150, 12, "traffic (words)")
+
+        for label, value in self.marks:
+            x = self._mark_x(value, left, step)
+            painter.setPen(QPen(QColor(self.MARK_COLOR), 1, Qt.PenStyle.DashLine))
+            painter.drawLine(x, bottom, x, bottom - height)
+            painter.setPen(QColor(self.MARK_COLOR))
+            painter.drawText(x + 3, bottom - height + 20 + 12 * self.marks.index((label, value)), label)
+
+        painter.setPen(QPen(QColor(self.LINE_COLOR), 2))
+        for previous, position in zip(positions, positions[1:]):
+            painter.drawLine(*previous, *position)
+        painter.setBrush(QColor(self.LINE_COLOR))
+        for point, (x, y) in zip(self.points, positions):
+            painter.drawEllipse(x - 3, y - 3, 6, 6)
+            painter.drawText(x - 14, y - 8, f"{point.miss_rate:.1f}%")
+            painter.drawText(x - 4, self.height() - 4, str(point.value))
+
+        painter.setPen(QColor("#aaaaaa"))
+        painter.drawLine(left, bottom, left + width, bottom)
+        painter.drawLine(left, bottom, left, bottom - height)
+        painter.drawText(0, bottom - height + 8, f"{top:.0f}%")
+        painter.drawText(0, bottom, "0%")
+        painter.drawText(left + 8, 12, f"L1 miss rate by {self.heading}")
+
+class AssemblyHighlighter(QSyntaxHighlighter):
+    """Colors mnemonics, registers, operands, labels and comments; underlines lines with diagnostics"""
+    COLORS = {'opcode': "#569cd6", 'register': "#9cdcfe", 'immediate': "#b5cea8", 'memory': "#ce9178",
+              'label': "#dcdcaa", 'directive': "#c586c0", 'comment': "#6a9955"}
+    UNDERLINES = {'error': "#ff4444", 'warning': "#ffcc00"}
+
+    def __init__(self, document, registers):
+        super().__init__(document)
+        self.mnemonics = {mnemonic(t) for t in InstructionType} | set(PSEUDO_INSTRUCTIONS)
+        self.rules = [
+            ('register', re.compile(r'\b(?:' + '|'.join(sorted(registers, key=len, reverse=True)) + r')\b',
+                                    re.IGNORECASE)),
+            ('immediate', re.compile(r'#\S*')),
+            ('memory', re.compile(r'\[[^\]]*\]?')),
+            ('label', re.compile(r'^\s*[^\s;:]+:')),
+            ('directive', re.compile(r'^\s*\.\S*')),
+        ]
+        self.diagnostics = {}  # line index -> 'error' or 'warning'
+
+    def set_diagnostics(self, diagnostics):
+        self.diagnostics = diagnostics
+        self.rehighlight()
+
+    def _format(self, kind, underline):
+        fmt = QTextCharFormat()
+        if kind:
+            fmt.setForeground(QColor(self.COLORS[kind]))
+        if underline:
+            fmt.setUnderlineStyle(QTextCharFormat.UnderlineStyle.SpellCheckUnderline)
+            fmt.setUnderlineColor(QColor(self.UNDERLINES[underline]))
+        return fmt
+
+    def highlightBlock(self, text):
+        underline = self.diagnostics.get(self.currentBlock().blockNumber())
+        comment = text.find(';')
+        code = text if comment < 0 else text[:comment]
+        if underline:
+            self.setFormat(0, len(code), self._format(None, underline))
+
+        opcode = re.match(r'\s*([A-Za-z][\w.]*)', code)
+        if opcode and opcode.group(1).upper() in self.mnemonics:
+            self.setFormat(opcode.start(1), len(opcode.group(1)), self._format('opcode', underline))
+        for kind, pattern in self.rules:
+            for match in pattern.finditer(code):
+                self.setFormat(match.start(), match.end() - match.start(), self._format(kind, underline))
+        if comment >= 0:
+            self.setFormat(comment, len(text) - comment, self._format('comment', None))
+
+class AssemblyEditor(QPlainTextEdit):
+    """Plain-text editor that offers mnemonics at the start of a line and registers or labels after it"""
+    def __init__(self, mnemonics, registers, parent=None):
+        super().__init__(parent)
+        self.mnemonics = sorted(mnemonics)
+        self.registers = sorted(registers)
+        self.completer = QCompleter(self)
+        self.completer.setWidget(self)
+        self.completer.setCaseSensitivity(Qt.CaseSensitivity.CaseInsensitive)
+        self.completer.setCompletionMode(QCompleter.CompletionMode.PopupCompletion)
+        self.completer.activated.connect(self.insert_completion)
+        self.completion_model = QStringListModel()
+        self.completer.setModel(self.completion_model)
+        self._prefix = ""
+
+    def labels(self):
+        return sorted({line.strip()[:-1].strip() for line in self.toPlainText().split("\n")
+                       if line.strip().endswith(':') and not line.strip().startswith(';')})
+
+    def completions(self, before_cursor):
+        """(prefix being typed, candidate words) for the text of the line up to the cursor"""
+        if ';' in before_cursor:
+            return "", []
+        prefix = re.search(r'[\w.]*$', before_cursor).group()
+        if not before_cursor[:len(before_cursor) - len(prefix)].strip():
+            words = self.mnemonics  # First word of the line
+        else:
+            words = self.registers + self.labels()
+        return prefix, [w for w in words if w.lower().startswith(prefix.lower()) and w.lower() != prefix.lower()]
+
+    def insert_completion(self, word):
+        cursor = self.textCursor()
+        cursor.movePosition(QTextCursor.MoveOperation.Left, QTextCursor.MoveMode.KeepAnchor, len(self._prefix))
+        cursor.insertText(word)
+        self.setTextCursor(cursor)
+
+    def keyPressEvent(self, event):
+        popup = self.completer.popup()
+        if popup.isVisible() and event.key() in (Qt.Key.Key_Enter, Qt.Key.Key_Return, Qt.Key.Key_Tab,
+                                                 Qt.Key.Key_Escape):
+            event.ignore()  # The popup handles these
+            return
+        forced = (event.key() == Qt.Key.Key_Space and
+                  event.modifiers() & Qt.KeyboardModifier.ControlModifier)  # Ctrl+Space
+        if not forced:
+            super().keyPressEvent(event)
+        if not forced and not event.text().strip():
+            popup.hide()
+            return
+
+        cursor = self.textCursor()
+        self._prefix, words = self.completions(cursor.block().text()[:cursor.positionInBlock()])
+        if not words or (not forced and not self._prefix):
+            popup.hide()
+            return
+        self.completion_model.setStringList(words)
+        self.completer.setCompletionPrefix(self._prefix)
+        popup.setCurrentIndex(self.completer.completionModel().index(0, 0))
+        rect = self.cursorRect()
+        rect.setWidth(popup.sizeHintForColumn(0) + popup.verticalScrollBar().sizeHint().width())
+        self.completer.complete(rect)
+
 class SimulatorGUI(QMainWindow):
-    def __init__(self, main_memory=None, l1_cache=None, l2_cache=None):
-        print("Initializing GUI...")
+    def __init__(self, main_memory=None, l1_cache=None, l2_cache=None, settings=None):
+        Logger().log(LogLevel.DEBUG, "Initializing GUI...")
         super().__init__()
+        self.logger = Logger()
+        self.settings = settings or load_settings()
+        self._themed_widgets = []  # (widget, stylesheet template) restyled when the theme changes
+        self.theme = {}
+        self.logger.add_handler(self._collect_log_record)
+        self._log_pending = False
         self.setWindowTitle("CPU & Cache Simulator")
-        self.setMinimumSize(1200, 400)
-        print("Window created...")
+        self.resize(1400, 600)
+        self.logger.log(LogLevel.DEBUG, "Window created...")
 
         # Initialize dictionaries for UI elements
         self.register_labels = {}
+        self.fp_register_labels = {}
         self.memory_labels = {}
 
-        # Initialize ISA and components
-        self.logger = Logger()
-        self.logger.log_level = LogLevel.INFO
-
         # Use provided memory hierarchy or create new one
         if main_memory and l1_cache and l2_cache:
             self.main_memory = main_memory
@@ -100,8 +944,8 @@ class SimulatorGUI(QMainWindow):
             self.l2_cache = l2_cache
         else:
             # Create memory hierarchy with correct sizes
-            print("Setting up memory hierarchy...")
-            self.main_memory = MainMemory("MainMemory", 1024)  # 1KB memory
+            self.logger.log(LogLevel.DEBUG, "Setting up memory hierarchy...")
+            self.main_memory, self.l1_cache, self.l2_cache = build_hierarchy(self.settings, self.logger)
 
             # Initialize memory with test values
             self.main_memory.write(100, 168)  # 42 shifted left by 2
@@ -119,104 +963,281 @@ class SimulatorGUI(QMainWindow):
             self.main_memory.write(148, 800)
             self.main_memory.write(152, 900)
 
-            # Create L2 cache (slower, larger)
-            self.l2_cache = Cache(
-                name="L2Cache",
-                size=64,         # 64 bytes
-                line_size=1,     # 1 byte per line for simplicity
-                associativity=4, # 4-way set associative
-                access_time=30,  # 30ns access time
-                write_policy="write-back",
-                next_level=self.main_memory,
-                logger=self.logger
-            )
-
-            # Create L1 cache (faster, smaller)
-            self.l1_cache = Cache(
-                name="L1Cache",
-                size=32,         # 32 bytes
-                line_size=1,     # 1 byte per line for simplicity
-                associativity=2, # 2-way set associative
-                access_time=10,  # 10ns access time
-                write_policy="write-through",
-                next_level=self.l2_cache,
-                logger=self.logger
-            )
-
-            # Explicitly connect the cache hierarchy
-            self.l1_cache.set_next_level(self.l2_cache)
-            self.l2_cache.set_next_level(self.main_memory)
-
         # Create ISA with L1 cache as its memory interface
-        self.isa = SimpleISA(memory=self.main_memory, cache=self.l1_cache)
-
-        # Update the cache info display to reflect new sizes
-        cache_info = [
-            ("L1 Cache", "32 bytes", "1 byte", "2-way", "10ns", "Write-through"),
-            ("L2 Cache", "64 bytes", "1 byte", "4-way", "30ns", "Write-back"),
-            ("Main Memory", "1024 bytes", "N/A", "N/A", "100ns", "N/A")
-        ]
+        self.pipeline_forwarding = True
+        self.pipeline_resources = PipelineResources()  # ALUs, memory ports and write ports; unlimited by default
+        self.branch_prediction = False  # BTB and return-address stack instead of always predicting not taken
+        self.miss_penalty = 0  # Stall cycles per L1 miss; 0 makes every instruction one cycle
+        self._build_machine()
 
         # Setup UI
-        print("Setting up UI components...")
+        self.logger.log(LogLevel.DEBUG, "Setting up UI components...")
+        self.simulation_speed = self.settings.step_speed
+        self.address_probe = None  # Address typed into the breakdown panel; its sets are highlighted
+        self._apply_theme()
         self.setup_ui()
+        self._restore_layout()
 
         # Initialize simulation state
         self.is_running = False
-        self.simulation_speed = 1000
-        self.current_instruction = 0
-        self.instructions = []
+        self.scheduler_quantum = 8
+        self.back_to_back = False  # Run several programs one after another instead of round-robin
+        self._reset_program_state()
+        QShortcut(QKeySequence("Ctrl+Z"), self).activated.connect(self.undo)
+        QShortcut(QKeySequence("Ctrl+Y"), self).activated.connect(self.redo)
 
         # Setup timer for continuous execution
         self.timer = QTimer()
         self.timer.timeout.connect(self.step_execution)
-        print("GUI initialization complete...")
+        self.logger.log(LogLevel.DEBUG, "GUI initialization complete...")
 
         self.used_memory_blocks = set([100, 104, 108, 112, 116, 120, 124, 128, 132, 136, 140, 144, 148, 152])
+        # Each tab is a machine of its own; the inactive ones keep their SESSION_ATTRIBUTES here
+        self.sessions = [{}]
+        self.session_index = 0
         self.memory_window = None  # Store reference to memory window
+        self.memory_heatmap = False  # Shade memory blocks by how often they were accessed
+        self.timeline_window = None  # Address vs cycle plot of every data access
+        self.working_set_window = None  # Working-set size over time against the cache capacities
+        self.patterns_window = None  # Per-load stride and access-pattern table
+        self.stats_window = None  # Execution statistics window
+        self.dependency_window = None  # Data-dependency graph of the loaded program
+        self.pipeline_window = None  # Gantt chart of pipeline stage timings
+        self.microcode_window = None  # Control ROM of the microprogrammed control unit
+        self.lsq_window = None  # Load/store queue forwarding and ordering violations
+        self.predictor_window = None  # Branch target buffer and return-address stack contents
+        self.scoreboard_window = None  # Scoreboard tables, one cycle at a time
+        self.scoreboard_cycle = None  # Cycle the scoreboard window shows; None follows the latest
+        self.whatif_window = None  # The recorded L1 trace replayed on another L1 shape
+        self.sweep_window = None  # Miss rate of the recorded L1 trace across one swept L1 parameter
+        self.evictions_window = None  # Every line evicted from L1 and L2, newest last
+        self.log_window = None  # Recent log messages by level and target
+        self.snapshot_window = None  # Named memory snapshots and their differences
+        self.hexdump_window = None  # Raw memory words in hex and decimal with their disassembly
+        self.editor_window = None  # Program text with syntax highlighting and live diagnostics
+        self._listing = (None, [])  # (instruction list, listing rows) of the last machine code listing
+        self.locality_window = None  # Naive vs blocked matrix multiply comparison
+        self.compare_window = None  # The loaded program on two configurations in lock-step
+        self.optimizer_window = None  # The loaded program before and after the peephole optimizer
+        self.optimization = None  # Result of the last optimizer run, for loading the optimized program
+        self.settings_window = None  # Cache, speed, display and ISA settings saved to the config file
+        self.tutorial_window = None  # Guided walkthrough that explains each step
+        self.tutorial_lesson = 0
+
+    def _build_machine(self):
+        """A fresh ISA, with its timing models and event subscribers, on the current memory hierarchy"""
+        self.endianness = self.settings.endianness
+        self.hardwired_zero = self.settings.hardwired_zero
+        self.memory_organization = self.settings.memory_organization
+        self.word_size = self.settings.word_size
+        self.delay_slots = self.settings.delay_slots
+        self.isa = self._new_isa()
+        self.isa.pipeline = PipelineModel(self.isa, forwarding=self.pipeline_forwarding,
+                                          predictor=BranchPredictor() if self.branch_prediction else None,
+                                          resources=self.pipeline_resources)
+        self.isa.lsq = LoadStoreQueue(self.isa)
+        self.vcd_recorder = VCDRecorder(self.isa)  # Signals sampled on every step for waveform export
+        self.microcode = MicroSequencer(self.isa)  # Microprogrammed control unit for micro-stepping
+        self._subscribe_to_events()
+
+    def _reset_program_state(self):
+        """No program loaded, and nothing to undo"""
+        self.current_instruction = 0
+        self.instructions = []
+        self.programs = []  # (name, lines) for each loaded program
+        self.scheduler = None
+        self.snapshots = SnapshotStore()
+        # Steps, micro-steps, resets, loads and pokes can be undone (Ctrl+Z) and redone (Ctrl+Y)
+        self.undo_stack = UndoStack(self._capture_state, self._restore_state)
 
     def setup_ui(self):
-        central_widget = QWidget()
-        self.setCentralWidget(central_widget)
-        main_layout = QVBoxLayout(central_widget)
-        main_layout.setSpacing(4)
-        main_layout.setContentsMargins(4, 4, 4, 4)
-
         # Create all sections first
         self.system_info_section = self.create_system_info_section()
         self.cpu_section = self.create_cpu_section()
         self.register_section = self.create_register_section()
+        self.fpu_section = self.create_fpu_section()
+        self.address_section = self.create_address_section()
+        self.explanation_section = self.create_explanation_section()
         self.memory_section = self.create_memory_section()
         self.control_section = self.create_controls()
 
-        # Create left side layout for system info, CPU status, and registers
-        left_layout = QVBoxLayout()
-        left_layout.setSpacing(2)
-        left_layout.setAlignment(Qt.AlignmentFlag.AlignLeft | Qt.AlignmentFlag.AlignTop)
-
-        # Add sections to left layout
-        self.system_info_section.setFixedWidth(300)
-        left_layout.addWidget(self.system_info_section)
-
-        self.cpu_section.setFixedWidth(300)
-        left_layout.addWidget(self.cpu_section)
-
-        left_layout.addWidget(self.register_section)
-
-        # Create main horizontal layout
-        main_horizontal = QHBoxLayout()
-        main_horizontal.setSpacing(4)
-
-        # Add left side layout
-        left_widget = QWidget()
-        left_widget.setLayout(left_layout)
-        main_horizontal.addWidget(left_widget)
-
-        # Add memory section
-        main_horizontal.addWidget(self.memory_section, 1)
-
-        main_layout.addLayout(main_horizontal)
-        main_layout.addWidget(self.control_section)
+        # The cache panel fills the window and scrolls when it is squeezed
+        self.cache_scroll = QScrollArea()
+        self.cache_scroll.setWidgetResizable(True)
+        self.cache_scroll.setWidget(self.memory_section)
+
+        # One tab per open machine, above the cache panel of the selected one
+        self.session_tabs = QTabBar()
+        self.session_tabs.setTabsClosable(True)
+        self.session_tabs.addTab("untitled")
+        self.session_tabs.currentChanged.connect(self.switch_session)
+        self.session_tabs.tabCloseRequested.connect(self.close_session)
+        central = QWidget()
+        central_layout = QVBoxLayout(central)
+        central_layout.setContentsMargins(0, 0, 0, 0)
+        central_layout.setSpacing(0)
+        central_layout.addWidget(self.session_tabs)
+        central_layout.addWidget(self.cache_scroll)
+        self.setCentralWidget(central)
+
+        file_menu = self.menuBar().addMenu("&File")
+        file_menu.addAction("New Tab", self.new_session).setShortcut(QKeySequence("Ctrl+T"))
+        file_menu.addAction("Open in New Tab...", self.open_in_new_session).setShortcut(QKeySequence("Ctrl+O"))
+        file_menu.addAction("Close Tab", lambda: self.close_session(self.session_index)).setShortcut(
+            QKeySequence("Ctrl+W"))
+        file_menu.addAction("Link and Load...", self.link_and_load)
+        file_menu.addSeparator()
+        export_menu = file_menu.addMenu("Export Image")
+        # Looked up when chosen: the cache panel is rebuilt on tab switches, probes and geometry changes
+        for title, name, widget in (("Cache Panel...", "cache_panel", self.cache_scroll.widget),
+                                    ("CPU Panel...", "cpu_panel", lambda: self.cpu_section),
+                                    ("Registers...", "registers", lambda: self.register_section),
+                                    ("Window...", "window", lambda: self)):
+            export_menu.addAction(title, lambda w=widget, n=name: self.export_image(w(), n))
+
+        # Everything else is a dock that can be resized, closed, floated or moved to another edge
+        controls_scroll = QScrollArea()
+        controls_scroll.setWidgetResizable(True)
+        controls_scroll.setVerticalScrollBarPolicy(Qt.ScrollBarPolicy.ScrollBarAlwaysOff)
+        controls_scroll.setWidget(self.control_section)
+        panels = [("system_info", "System Information", self.system_info_section, Qt.DockWidgetArea.LeftDockWidgetArea),
+                  ("cpu", "CPU", self.cpu_section, Qt.DockWidgetArea.LeftDockWidgetArea),
+                  ("registers", "Registers", self.register_section, Qt.DockWidgetArea.LeftDockWidgetArea),
+                  ("fpu", "FPU", self.fpu_section, Qt.DockWidgetArea.LeftDockWidgetArea),
+                  ("address", "Address Breakdown", self.address_section, Qt.DockWidgetArea.RightDockWidgetArea),
+                  ("explanation", "What Happened", self.explanation_section, Qt.DockWidgetArea.RightDockWidgetArea),
+                  ("controls", "Controls", controls_scroll, Qt.DockWidgetArea.BottomDockWidgetArea)]
+        self.panel_docks = {}
+        view_menu = self.menuBar().addMenu("&View")
+        for name, title, widget, area in panels:
+            dock = QDockWidget(title, self)
+            dock.setObjectName(f"{name}_dock")  # saveState() identifies docks by object name
+            dock.setWidget(widget)
+            self.addDockWidget(area, dock)
+            view_menu.addAction(dock.toggleViewAction())
+            self.panel_docks[name] = dock
+        view_menu.addSeparator()
+        view_menu.addAction("Reset Layout", self.reset_layout)
+        self._default_layout = self.saveState(LAYOUT_VERSION)
+
+    def _restore_layout(self):
+        """Put the panels back where they were when the window was last closed"""
+        if self.settings.window_geometry:
+            self.restoreGeometry(QByteArray.fromBase64(self.settings.window_geometry.encode()))
+        if self.settings.window_state:
+            self.restoreState(QByteArray.fromBase64(self.settings.window_state.encode()), LAYOUT_VERSION)
+
+    def reset_layout(self):
+        """Show every panel again in its original place"""
+        self.restoreState(self._default_layout, LAYOUT_VERSION)
+        for dock in self.panel_docks.values():
+            dock.show()
+
+    def closeEvent(self, event):
+        self.settings.window_geometry = bytes(self.saveGeometry().toBase64()).decode()
+        self.settings.window_state = bytes(self.saveState(LAYOUT_VERSION).toBase64()).decode()
+        try:
+            save_settings(self.settings)
+        except OSError as e:
+            self.logger.log(LogLevel.WARNING, f"Could not save the window layout: {e}")
+        super().closeEvent(event)
+
+    # Attributes that make up one tab's machine; switching tabs swaps them
+    SESSION_ATTRIBUTES = ('main_memory', 'l1_cache', 'l2_cache', 'isa', 'vcd_recorder', 'microcode', 'event_stats',
+                          'step_exceptions', 'step_cache_accesses', 'step_recorder', 'profiler', 'heatmap',
+                          'strides', 'cache_trace', 'eviction_log', 'scoreboard', 'scheduler', 'programs',
+                          'instructions', 'current_instruction', 'endianness', 'hardwired_zero', 'word_size',
+                          'delay_slots', 'memory_organization', 'back_to_back', 'miss_penalty', 'used_memory_blocks',
+                          'snapshots', 'undo_stack')
+
+    def _session_name(self):
+        return ", ".join(name for name, _ in self.programs) or "untitled"
+
+    def _store_session(self):
+        """Keep the selected tab's machine, and its unsaved editor text, while another tab is shown"""
+        if self.is_running:
+            self.toggle_run()  # Only the tab on screen runs
+        state = {name: getattr(self, name) for name in self.SESSION_ATTRIBUTES}
+        state['editor_text'] = self.editor.toPlainText() if self.editor_window is not None else None
+        self.sessions[self.session_index] = state
+
+    def new_session(self, filenames=None):
+        """Open a tab with a memory, caches and CPU of its own, loading filenames into it if given"""
+        self._store_session()
+        self.main_memory, self.l1_cache, self.l2_cache = build_hierarchy(self.settings, self.logger)
+        self._build_machine()
+        self._reset_program_state()
+        self.used_memory_blocks = set()
+        self.sessions.append({})
+        self.session_index = len(self.sessions) - 1
+        self.session_tabs.blockSignals(True)
+        self.session_tabs.addTab("untitled")
+        self.session_tabs.setCurrentIndex(self.session_index)
+        self.session_tabs.blockSignals(False)
+        self._show_session(None)
+        if filenames:
+            self.load_programs(filenames)
+
+    def link_and_load(self):
+        filenames, _ = QFileDialog.getOpenFileNames(self, "Link Files into One Program", "tests",
+                                                    "Assembly and object files (*.asm *.txt *.obj);;All files (*)")
+        if filenames:
+            self.load_programs(filenames, link=True)
+
+    def open_in_new_session(self):
+        filenames, _ = QFileDialog.getOpenFileNames(self, "Open Program in New Tab", "tests",
+                                                    "Programs (*.txt *.c);;Assembly files (*.txt);;C files (*.c);;"
+                                                    "All files (*)")
+        if filenames:
+            self.new_session(filenames)
+
+    def switch_session(self, index):
+        """Show another tab's machine, exactly as it was left"""
+        if index == self.session_index or not 0 <= index < len(self.sessions):
+            return
+        self._store_session()
+        self.session_index = index
+        self.session_tabs.setCurrentIndex(index)
+        state = dict(self.sessions[index])
+        editor_text = state.pop('editor_text', None)
+        for name, value in state.items():
+            setattr(self, name, value)
+        self._show_session(editor_text)
+
+    def close_session(self, index):
+        """Close a tab and drop its machine; the last tab stays open"""
+        if len(self.sessions) == 1:
+            self.status_label.setText("The last tab cannot be closed")
+            return
+        if index == self.session_index:
+            self.switch_session(index - 1 if index else 1)
+        del self.sessions[index]
+        self.session_tabs.blockSignals(True)
+        self.session_tabs.removeTab(index)
+        if index < self.session_index:
+            self.session_index -= 1
+        self.session_tabs.setCurrentIndex(self.session_index)
+        self.session_tabs.blockSignals(False)
+
+    def _show_session(self, editor_text):
+        """Point the panels and open windows at the selected tab's machine"""
+        self.memory_section = self.create_memory_section()  # Its caches may predate a geometry change
+        self.cache_scroll.setWidget(self.memory_section)
+        self.session_tabs.setTabText(self.session_index, self._session_name())
+        self._update_setting_buttons()
+        self.instruction_label.setText("None")
+        self.pc_label.setText(f"0x{self.isa.pc:02x}")
+        self.status_label.setText(f"Tab {self.session_index + 1}: {self._session_name()}")
+        if self.editor_window is not None:
+            self._fill_editor_programs()
+            if editor_text is not None:
+                self.editor.setPlainText(editor_text)
+                self.check_editor_program()
+            else:
+                self._load_editor_text()
+        if self.snapshot_window is not None:
+            self._refresh_snapshot_names()
+        self.update_address_breakdown()  # Splits the probed address for this tab's caches and redraws
 
     def create_system_info_section(self):
         frame = QFrame()
@@ -227,21 +1248,21 @@ class SimulatorGUI(QMainWindow):
 
         # Create toggle button with left alignment
         toggle_button = QPushButton("Show System Information")
-        toggle_button.setStyleSheet("""
+        self._themed(toggle_button, """
             QPushButton {
-                background-color: #2b2b2b;
-                color: #00ff00;
-                border: 1px solid #00ff00;
+                background-color: $panel;
+                color: $accent;
+                border: 1px solid $accent;
                 border-radius: 4px;
                 padding: 4px 8px;
                 font-size: 10pt;
                 min-width: 150px;
             }
             QPushButton:hover {
-                background-color: #3b3b3b;
+                background-color: $hover;
             }
             QPushButton:pressed {
-                background-color: #1b1b1b;
+                background-color: $pressed;
             }
         """)
         layout.addWidget(toggle_button)
@@ -262,18 +1283,17 @@ class SimulatorGUI(QMainWindow):
         grid.setSpacing(8)
 
         # Cache Configuration
-        cache_info = [
-            ("L1 Cache", "32 bytes", "1 byte", "2-way", "10ns", "Write-through"),
-            ("L2 Cache", "64 bytes", "1 byte", "4-way", "30ns", "Write-back"),
-            ("Main Memory", "1024 bytes", "N/A", "N/A", "100ns", "N/A")
-        ]
+        cache_info = [(name, f"{cache.size} words", "1 word", f"{cache.associativity}-way", f"{cache.access_time}ns",
+                       f"{cache.write_policy.capitalize()}, {cache.replacement_policy.upper()}")
+                      for name, cache in (("L1 Cache", self.settings.l1), ("L2 Cache", self.settings.l2))]
+        cache_info.append(("Main Memory", f"{len(self.main_memory.data)} words", "N/A", "N/A", "100ns", "N/A"))
 
         # Headers
-        headers = ["Component", "Size", "Line Size", "Associativity", "Access Time", "Write Policy"]
+        headers = ["Component", "Size", "Line Size", "Associativity", "Access Time", "Policies"]
         for col, header in enumerate(headers):
             label = QLabel(header)
             label.setFont(QFont("Arial", 10, QFont.Weight.Bold))
-            label.setStyleSheet("color: #00ff00;")
+            self._themed(label, "color: $accent;")
             grid.addWidget(label, 0, col)
 
         # Cache data
@@ -311,7 +1331,7 @@ class SimulatorGUI(QMainWindow):
         for col, header in enumerate(reg_headers):
             label = QLabel(header)
             label.setFont(QFont("Arial", 10, QFont.Weight.Bold))
-            label.setStyleSheet("color: #00ff00;")
+            self._themed(label, "color: $accent;")
             reg_grid.addWidget(label, 0, col)
 
         # Register data
@@ -354,6 +1374,9 @@ class SimulatorGUI(QMainWindow):
         title = QLabel("CPU & Instruction Status")
         title.setFont(QFont("Arial", 9))
         grid.addWidget(title, 0, 0, 1, 2)
+        frame.setToolTip("The control unit fetches the instruction at the PC, decodes it and drives the ALU, "
+                         "registers and caches to execute it.\nShow Microcode steps through the control signals "
+                         "it raises for each instruction.")
 
         # Program Counter label and value
         pc_title = QLabel("PC:")  # Shortened
@@ -362,8 +1385,11 @@ class SimulatorGUI(QMainWindow):
 
         self.pc_label = QLabel("0x00")
         self.pc_label.setFont(QFont("Courier", 9))  # Smaller font
-        self.pc_label.setStyleSheet("QLabel { color: #0099ff; }")
+        self._themed(self.pc_label, "QLabel { color: $pc_text; }")
         grid.addWidget(self.pc_label, 1, 1)
+        for widget in (pc_title, self.pc_label):
+            widget.setToolTip("Program counter: index of the next instruction to fetch.\nIt moves on by one each "
+                              "step unless a jump or taken branch loads its target.")
 
         # Current Instruction label and value
         instr_title = QLabel("Instr:")  # Shortened
@@ -372,8 +1398,11 @@ class SimulatorGUI(QMainWindow):
 
         self.instruction_label = QLabel("None")
         self.instruction_label.setFont(QFont("Courier", 9))  # Smaller font
-        self.instruction_label.setStyleSheet("QLabel { color: #00ff00; }")
+        self._themed(self.instruction_label, "QLabel { color: $accent; }")
         grid.addWidget(self.instruction_label, 0, 3)
+        for widget in (instr_title, self.instruction_label):
+            widget.setToolTip("Instruction register: the instruction the control unit fetched and decoded last.\n"
+                              "Its opcode selects the ALU operation, its operands the registers and memory used.")
 
         # Status label and value
         status_title = QLabel("Status:")
@@ -382,21 +1411,68 @@ class SimulatorGUI(QMainWindow):
 
         self.status_label = QLabel("Ready")
         self.status_label.setFont(QFont("Courier", 9))  # Smaller font
-        self.status_label.setStyleSheet("QLabel { color: #ffaa00; }")
+        self._themed(self.status_label, "QLabel { color: $status; }")
         grid.addWidget(self.status_label, 1, 3)
+        for widget in (status_title, self.status_label):
+            widget.setToolTip("What the last step, run or command did")
+
+        # Flags register label and value
+        flags_title = QLabel("Flags:")
+        flags_title.setFont(QFont("Courier", 9))
+        grid.addWidget(flags_title, 2, 0)
+
+        self.flags_label = QLabel("Z=0 N=0 C=0 V=0")
+        self.flags_label.setFont(QFont("Courier", 9))
+        self._themed(self.flags_label, "QLabel { color: $flags; }")
+        grid.addWidget(self.flags_label, 2, 1)
+        for widget in (flags_title, self.flags_label):
+            widget.setToolTip("ALU condition flags, set by CMP, TEST and arithmetic:\n"
+                              "Z - the result was zero\nN - the result was negative\n"
+                              "C - unsigned carry or borrow out of bit 31\nV - signed overflow\n"
+                              "Conditional branches such as JZ and JNZ test them.")
+
+        # ISA mode label and value
+        mode_title = QLabel("Mode:")
+        mode_title.setFont(QFont("Courier", 9))
+        grid.addWidget(mode_title, 2, 2)
+
+        self.mode_label = QLabel("standard/kernel")
+        self.mode_label.setFont(QFont("Courier", 9))
+        self._themed(self.mode_label, "QLabel { color: $muted; }")
+        grid.addWidget(self.mode_label, 2, 3)
+        for widget in (mode_title, self.mode_label):
+            widget.setToolTip("ISA mode (how compares behave) and privilege level.\nThe CPU starts in kernel mode; "
+                              "privileged instructions trap in user mode.")
+
+        # ALU with the operands and result of the last operation
+        self.alu_view = AluView()
+        self.alu_view.setToolTip("The ALU: operands A and B flow in from the registers, memory or the instruction's "
+                                 "immediate,\nand the result flows out to the destination (or only sets the flags, "
+                                 "for CMP and TEST).")
+        grid.addWidget(self.alu_view, 3, 0, 1, 4)
+
+        # PC and IR with the fetch from memory and the next PC
+        self.fetch_view = FetchView()
+        self.fetch_view.setToolTip("Fetch: the PC addresses a word of program text, which is read into the "
+                                   "instruction register (IR).\nThe next PC is PC + 1, unless a taken branch, "
+                                   "jump or trap loads another address.")
+        grid.addWidget(self.fetch_view, 4, 0, 1, 4)
+
+        # Where a branch about to execute can go
+        self.next_pc_view = NextPcView()
+        self.next_pc_view.setToolTip("Before a branch executes: its fall-through (PC + 1) and target.\nThe filled "
+                                     "box is where it will go; the outlined one is what the fetch stage predicts "
+                                     "(not taken, or the BTB's target with the branch predictor on).")
+        grid.addWidget(self.next_pc_view, 5, 0, 1, 4)
 
         # Add grid to main layout
         layout.addLayout(grid)
 
-        frame.setFixedWidth(300)  # Reduced from 400
-        frame.setFixedHeight(45)  # Reduced from 50
         return frame
 
     def create_register_section(self):
         frame = QFrame()
         frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
-        frame.setFixedWidth(300)  # Reduced from 400
-        frame.setFixedHeight(90)  # Reduced from 100
         layout = QVBoxLayout(frame)
         layout.setContentsMargins(4, 2, 4, 2)  # Minimal margins
         layout.setSpacing(2)  # Minimal spacing
@@ -412,20 +1488,21 @@ class SimulatorGUI(QMainWindow):
         register_grid.setContentsMargins(2, 2, 2, 2)  # Minimal margins
 
         # Define registers
-        registers = ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi']
+        registers = ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'zero']
         self.register_labels = {}
+        self.register_frames = {}  # Carry the register tooltips
 
-        # Create registers in a 3x2 grid
+        # Create registers in a 4x2 grid
         for i, reg_name in enumerate(registers):
             row = i // 2
             col = i % 2
 
             reg_frame = QFrame()
             reg_frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
-            reg_frame.setStyleSheet("""
+            self._themed(reg_frame, """
                 QFrame {
-                    background-color: #1e1e1e;
-                    border: 1px solid #ffaa00;
+                    background-color: $background;
+                    border: 1px solid $register;
                     border-radius: 2px;
                 }
             """)
@@ -435,14 +1512,15 @@ class SimulatorGUI(QMainWindow):
 
             reg_label = QLabel(reg_name)
             reg_label.setFont(QFont("Courier", 9))  # Smaller font
-            reg_label.setStyleSheet("QLabel { color: #888888; }")
+            self._themed(reg_label, "QLabel { color: $label; }")
             reg_layout.addWidget(reg_label)
 
             value_label = QLabel("0")
             value_label.setFont(QFont("Courier", 9))  # Smaller font
-            value_label.setStyleSheet("QLabel { color: #ffaa00; }")
+            self._themed(value_label, "QLabel { color: $register; }")
             value_label.setAlignment(Qt.AlignmentFlag.AlignRight)
             self.register_labels[reg_name] = value_label
+            self.register_frames[reg_name] = reg_frame
             reg_layout.addWidget(value_label)
 
             reg_frame.setFixedHeight(24)  # Match cache block height
@@ -452,11 +1530,130 @@ class SimulatorGUI(QMainWindow):
         layout.addLayout(register_grid)
         return frame
 
+    def create_fpu_section(self):
+        frame = QFrame()
+        frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
+        layout = QVBoxLayout(frame)
+        layout.setContentsMargins(4, 2, 4, 2)
+        layout.setSpacing(2)
+
+        # Header with title and FP condition flag
+        header_layout = QHBoxLayout()
+        title = QLabel("FPU Registers")
+        title.setFont(QFont("Arial", 10))
+        header_layout.addWidget(title)
+
+        self.fp_condition_label = QLabel("fcc: 0")
+        self.fp_condition_label.setFont(QFont("Courier", 9))
+        self._themed(self.fp_condition_label, "QLabel { color: $info; }")
+        self.fp_condition_label.setAlignment(Qt.AlignmentFlag.AlignRight)
+        header_layout.addWidget(self.fp_condition_label)
+        layout.addLayout(header_layout)
+
+        # 32 FP registers in an 8x4 grid
+        fpu_grid = QGridLayout()
+        fpu_grid.setSpacing(1)
+        fpu_grid.setContentsMargins(2, 2, 2, 2)
+        self.fp_register_labels = {}
+
+        for i in range(32):
+            reg_name = f"f{i}"
+            value_label = QLabel(f"{reg_name}: 0")
+            value_label.setFont(QFont("Courier", 8))
+            value_label.setStyleSheet(f"QLabel {{ color: {self.theme['dim']}; }}")
+            self.fp_register_labels[reg_name] = value_label
+            fpu_grid.addWidget(value_label, i // 4, i % 4)
+
+        layout.addLayout(fpu_grid)
+        return frame
+
+    def create_address_section(self):
+        frame = QFrame()
+        frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
+        layout = QVBoxLayout(frame)
+        layout.setContentsMargins(4, 2, 4, 2)
+        layout.setSpacing(2)
+
+        title = QLabel("Address Breakdown")
+        title.setFont(QFont("Arial", 10))
+        layout.addWidget(title)
+
+        self.address_input = QLineEdit()
+        self.address_input.setPlaceholderText("Word address, e.g. 100 or 0x64")
+        self.address_input.textChanged.connect(self.update_address_breakdown)
+        layout.addWidget(self.address_input)
+
+        self.address_breakdown_label = QLabel("")
+        self.address_breakdown_label.setFont(QFont("Courier", 9))
+        self._themed(self.address_breakdown_label, "QLabel { color: $text; }")
+        layout.addWidget(self.address_breakdown_label)
+        layout.addStretch()
+        return frame
+
+    def create_explanation_section(self):
+        frame = QFrame()
+        frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
+        layout = QVBoxLayout(frame)
+        layout.setContentsMargins(4, 2, 4, 2)
+        layout.setSpacing(2)
+
+        title = QLabel("What Happened")
+        title.setFont(QFont("Arial", 10))
+        layout.addWidget(title)
+
+        # Plain-language account of the last step: registers, set/tag, hit or kind of miss, eviction
+        self.explanation_view = QTextEdit()
+        self.explanation_view.setReadOnly(True)
+        self.explanation_view.setFont(QFont("Arial", 9))
+        self.explanation_view.setPlaceholderText("Step the program to see what each instruction did.")
+        layout.addWidget(self.explanation_view)
+        return frame
+
+    def update_address_breakdown(self):
+        """Split the typed address into tag, set and offset for each cache and highlight the sets it maps to"""
+        try:
+            address = int(self.address_input.text().strip(), 0)
+        except ValueError:
+            address = None
+        if address is None or not 0 <= address < len(self.main_memory.data):
+            self.address_probe = None
+            self.address_breakdown_label.setText(
+                f"Enter a word address 0-{len(self.main_memory.data) - 1}" if self.address_input.text().strip() else "")
+            self.update_display()
+            return
+
+        self.address_probe = address
+        lines = []
+        for name, cache in (("L1", self.l1_cache), ("L2", self.l2_cache)):
+            info = cache.debug_info()
+            _, set_index, tag = cache.split_address(address)
+            lines.append(f"{name} ({info['sets']} sets, {info['associativity']}-way):")
+            lines.append(f"  {cache.format_split(address)}")
+            lines.append(f"  set {set_index} {'holds' if cache.contains(address) else 'does not hold'} tag {tag}: "
+                         f"an access now would {'hit' if cache.contains(address) else 'miss'}")
+        if self.isa.is_uncacheable(address):
+            lines.append("Uncacheable: accesses bypass both caches")
+        self.address_breakdown_label.setText("\n".join(lines))
+
+        shown = (self.l1_cache.split_address(address)[1] in self.l1_set_labels and
+                 self.l2_cache.split_address(address)[1] in self.l2_set_labels)
+        if not shown:
+            self.memory_section = self.create_memory_section()  # Swap the sets into the cache panel
+            self.cache_scroll.setWidget(self.memory_section)
+        self.update_display()
+
+    def _panel_sets(self, cache):
+        """The (at most four, evenly spaced) sets of a cache shown in the cache panel, plus the probed address's set"""
+        sets = list(range(0, cache._sets, max(1, cache._sets // 4)))[:4]
+        if self.address_probe is not None:
+            probe_set = cache.split_address(self.address_probe)[1]
+            if probe_set not in sets:
+                sets = sorted(sets[:-1] + [probe_set])
+        return sets
+
     def create_memory_section(self):
         frame = QFrame()
         frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
-        frame.setMinimumWidth(900)
-        frame.setMaximumHeight(180)
 
         main_layout = QVBoxLayout(frame)
         main_layout.setContentsMargins(4, 2, 4, 2)
@@ -475,16 +1672,28 @@ class SimulatorGUI(QMainWindow):
         # Stats in single compact line
         self.l1_stats_label = QLabel("L1: H:0 M:0 R:0%")  # Shortened stats
         self.l1_stats_label.setFont(QFont("Arial", 10))
-        self.l1_stats_label.setStyleSheet("color: #ff69b4;")
+        self._themed(self.l1_stats_label, "color: $l1;")
         header_layout.addWidget(self.l1_stats_label)
 
         self.l2_stats_label = QLabel("L2: H:0 M:0 R:0%")  # Shortened stats
         self.l2_stats_label.setFont(QFont("Arial", 10))
-        self.l2_stats_label.setStyleSheet("color: #9370db;")
+        self._themed(self.l2_stats_label, "color: $l2;")
         header_layout.addWidget(self.l2_stats_label)
 
+        # Write buffer occupancy (when a program enables one with .write_buffer)
+        self.write_buffer_label = QLabel("")
+        self.write_buffer_label.setFont(QFont("Arial", 10))
+        self._themed(self.write_buffer_label, "color: $info;")
+        header_layout.addWidget(self.write_buffer_label)
+
         main_layout.addWidget(header)
 
+        # Average memory access time, worked through with this run's miss rates
+        self.amat_label = QLabel("")
+        self.amat_label.setFont(QFont("Courier", 9))
+        self._themed(self.amat_label, "color: $info;")
+        main_layout.addWidget(self.amat_label)
+
         # Cache container
         cache_container = QWidget()
         cache_layout = QHBoxLayout(cache_container)
@@ -496,11 +1705,12 @@ class SimulatorGUI(QMainWindow):
         l1_layout = QVBoxLayout(l1_widget)
         l1_layout.setSpacing(1)
         l1_layout.setContentsMargins(0, 0, 0, 0)
-        l1_widget.setFixedWidth(240)  # Adjusted for 75px blocks
+        l1_ways = self.l1_cache._associativity  # Laid out for the live caches, which a tab may keep from older settings
+        l1_widget.setFixedWidth(20 + 110 * l1_ways)  # Adjusted for 75px blocks
 
-        l1_title = QLabel("L1 (2-way)")
+        l1_title = QLabel(f"L1 ({l1_ways}-way)")
         l1_title.setFont(QFont("Arial", 9))  # Smaller font
-        l1_title.setStyleSheet("color: #ff69b4;")
+        self._themed(l1_title, "color: $l1;")
         l1_layout.addWidget(l1_title)
 
         l1_grid = QGridLayout()
@@ -508,22 +1718,24 @@ class SimulatorGUI(QMainWindow):
         l1_grid.setHorizontalSpacing(1)
         l1_grid.setVerticalSpacing(1)
         self.l1_blocks = {}
+        self.l1_set_labels = {}
 
-        for row, set_idx in enumerate([0, 4, 8, 12]):
+        for row, set_idx in enumerate(self._panel_sets(self.l1_cache)):
             set_label = QLabel(f"S{set_idx}")
-            set_label.setStyleSheet("color: #aaaaaa; font-size: 9pt;")
+            self._themed(set_label, "color: $muted; font-size: 9pt;")
             set_label.setAlignment(Qt.AlignmentFlag.AlignRight | Qt.AlignmentFlag.AlignVCenter)
             set_label.setFixedWidth(20)
+            self.l1_set_labels[set_idx] = set_label
             l1_grid.addWidget(set_label, row, 0)
 
-            for way in range(2):
+            for way in range(l1_ways):
                 block = QFrame()
                 block.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
                 block.setFixedSize(75, 20)  # Set to 75px width
-                block.setStyleSheet("""
+                self._themed(block, """
                     QFrame {
-                        background-color: #1e1e1e;
-                        border: 1px solid #ff69b4;
+                        background-color: $background;
+                        border: 1px solid $l1;
                         border-radius: 2px;
                     }
                 """)
@@ -533,7 +1745,7 @@ class SimulatorGUI(QMainWindow):
                 layout.setSpacing(0)
 
                 value_label = QLabel("Empty")
-                value_label.setStyleSheet("color: #666666; font-size: 9pt;")
+                value_label.setStyleSheet(f"color: {self.theme['dim']}; font-size: 9pt;")
                 value_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
                 layout.addWidget(value_label)
 
@@ -546,7 +1758,7 @@ class SimulatorGUI(QMainWindow):
         # Thin separator
         separator = QFrame()
         separator.setFrameShape(QFrame.Shape.VLine)
-        separator.setStyleSheet("background-color: #333333;")
+        self._themed(separator, "background-color: $separator;")
         separator.setFixedWidth(1)
         cache_layout.addWidget(separator)
 
@@ -555,11 +1767,12 @@ class SimulatorGUI(QMainWindow):
         l2_layout = QVBoxLayout(l2_widget)
         l2_layout.setSpacing(1)
         l2_layout.setContentsMargins(0, 0, 0, 0)
-        l2_widget.setFixedWidth(460)  # Adjusted for 75px blocks
+        l2_ways = self.l2_cache._associativity
+        l2_widget.setFixedWidth(20 + 110 * l2_ways)  # Adjusted for 75px blocks
 
-        l2_title = QLabel("L2 (4-way)")
+        l2_title = QLabel(f"L2 ({l2_ways}-way)")
         l2_title.setFont(QFont("Arial", 9))  # Smaller font
-        l2_title.setStyleSheet("color: #9370db;")
+        self._themed(l2_title, "color: $l2;")
         l2_layout.addWidget(l2_title)
 
         l2_grid = QGridLayout()
@@ -567,22 +1780,24 @@ class SimulatorGUI(QMainWindow):
         l2_grid.setHorizontalSpacing(1)
         l2_grid.setVerticalSpacing(1)
         self.l2_blocks = {}
+        self.l2_set_labels = {}
 
-        for row, set_idx in enumerate([0, 4, 8, 12]):
+        for row, set_idx in enumerate(self._panel_sets(self.l2_cache)):
             set_label = QLabel(f"S{set_idx}")
-            set_label.setStyleSheet("color: #aaaaaa; font-size: 9pt;")
+            self._themed(set_label, "color: $muted; font-size: 9pt;")
             set_label.setAlignment(Qt.AlignmentFlag.AlignRight | Qt.AlignmentFlag.AlignVCenter)
             set_label.setFixedWidth(20)
+            self.l2_set_labels[set_idx] = set_label
             l2_grid.addWidget(set_label, row, 0)
 
-            for way in range(4):
+            for way in range(l2_ways):
                 block = QFrame()
                 block.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
                 block.setFixedSize(75, 20)  # Set to 75px width
-                block.setStyleSheet("""
+                self._themed(block, """
                     QFrame {
-                        background-color: #1e1e1e;
-                        border: 1px solid #9370db;
+                        background-color: $background;
+                        border: 1px solid $l2;
                         border-radius: 2px;
                     }
                 """)
@@ -592,7 +1807,7 @@ class SimulatorGUI(QMainWindow):
                 layout.setSpacing(0)
 
                 value_label = QLabel("Empty")
-                value_label.setStyleSheet("color: #666666; font-size: 9pt;")
+                value_label.setStyleSheet(f"color: {self.theme['dim']}; font-size: 9pt;")
                 value_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
                 layout.addWidget(value_label)
 
@@ -615,7 +1830,6 @@ class SimulatorGUI(QMainWindow):
     def create_controls(self):
         frame = QFrame()
         frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
-        frame.setFixedHeight(40)  # Set fixed compact height
 
         layout = QHBoxLayout(frame)
         layout.setContentsMargins(4, 2, 4, 2)  # Minimal margins
@@ -624,9 +1838,9 @@ class SimulatorGUI(QMainWindow):
         # Control buttons with compact styling
         button_style = """
             QPushButton {
-                background-color: #2b2b2b;
-                color: #00ff00;
-                border: 1px solid #00ff00;
+                background-color: $panel;
+                color: $accent;
+                border: 1px solid $accent;
                 border-radius: 2px;
                 padding: 4px 12px;
                 font-size: 10pt;
@@ -634,54 +1848,94 @@ class SimulatorGUI(QMainWindow):
                 max-height: 24px;
             }
             QPushButton:hover {
-                background-color: #3b3b3b;
+                background-color: $hover;
             }
             QPushButton:pressed {
-                background-color: #1b1b1b;
+                background-color: $pressed;
             }
         """
 
         self.step_button = QPushButton("Step")
-        self.step_button.clicked.connect(self.step_execution)
-        self.step_button.setStyleSheet(button_style)
+        self.step_button.clicked.connect(self.step_many)
+        self._themed(self.step_button, button_style)
         layout.addWidget(self.step_button)
 
+        # Instructions per click of Step; more than one run without animation
+        self.step_count_spin = QSpinBox()
+        self.step_count_spin.setRange(1, self.RUN_UNTIL_LIMIT)
+        self.step_count_spin.setPrefix("x ")
+        self.step_count_spin.setToolTip("Instructions Step executes; more than one run without animation "
+                                        "and undo as one step")
+        layout.addWidget(self.step_count_spin)
+
+        self.step_over_button = QPushButton("Step Over")
+        self.step_over_button.setToolTip("Step, but run a JAL's whole subroutine until it returns")
+        self.step_over_button.clicked.connect(self.step_over)
+        self._themed(self.step_over_button, button_style)
+        layout.addWidget(self.step_over_button)
+
+        self.run_to_cursor_button = QPushButton("Run to Cursor")
+        self.run_to_cursor_button.setToolTip("Run until the instruction on the editor's cursor line, or at an "
+                                             "address or label you enter, is next")
+        self.run_to_cursor_button.clicked.connect(self.run_to_cursor)
+        self._themed(self.run_to_cursor_button, button_style)
+        layout.addWidget(self.run_to_cursor_button)
+
+        self.run_until_change_button = QPushButton("Run Until Change")
+        self.run_until_change_button.setToolTip("Run until a memory word you enter changes value, and show the "
+                                                "instruction that wrote it")
+        self.run_until_change_button.clicked.connect(self.run_until_change)
+        self._themed(self.run_until_change_button, button_style)
+        layout.addWidget(self.run_until_change_button)
+
         self.run_button = QPushButton("Run")
         self.run_button.clicked.connect(self.toggle_run)
-        self.run_button.setStyleSheet(button_style)
+        self._themed(self.run_button, button_style)
         layout.addWidget(self.run_button)
 
         self.reset_button = QPushButton("Reset")
         self.reset_button.clicked.connect(self.reset_simulation)
-        self.reset_button.setStyleSheet(button_style)
+        self._themed(self.reset_button, button_style)
         layout.addWidget(self.reset_button)
 
+        self.undo_button = QPushButton("Undo")
+        self.undo_button.setToolTip("Undo the last step, reset, load or poke (Ctrl+Z)")
+        self.undo_button.clicked.connect(self.undo)
+        self._themed(self.undo_button, button_style)
+        layout.addWidget(self.undo_button)
+
+        self.redo_button = QPushButton("Redo")
+        self.redo_button.setToolTip("Redo the last undone action (Ctrl+Y)")
+        self.redo_button.clicked.connect(self.redo)
+        self._themed(self.redo_button, button_style)
+        layout.addWidget(self.redo_button)
+
         # Add small spacer
         layout.addSpacing(8)
 
         # Speed control with compact layout
         speed_label = QLabel("Speed:")
-        speed_label.setStyleSheet("QLabel { color: #00ff00; font-size: 10pt; }")
+        self._themed(speed_label, "QLabel { color: $accent; font-size: 10pt; }")
         speed_label.setFixedWidth(45)
         layout.addWidget(speed_label)
 
         self.speed_slider = QSlider(Qt.Orientation.Horizontal)
-        self.speed_slider.setMinimum(100)
-        self.speed_slider.setMaximum(2000)
-        self.speed_slider.setValue(1000)
+        self.speed_slider.setMinimum(STEP_SPEED_RANGE[0])
+        self.speed_slider.setMaximum(STEP_SPEED_RANGE[1])
+        self.speed_slider.setValue(self.simulation_speed)
         self.speed_slider.valueChanged.connect(self.update_speed)
         self.speed_slider.setFixedWidth(200)  # Limit slider width
-        self.speed_slider.setStyleSheet("""
+        self._themed(self.speed_slider, """
             QSlider::groove:horizontal {
-                border: 1px solid #00ff00;
+                border: 1px solid $accent;
                 height: 4px;
-                background: #2b2b2b;
+                background: $panel;
                 margin: 1px 0;
                 border-radius: 2px;
             }
             QSlider::handle:horizontal {
-                background: #00ff00;
-                border: 1px solid #00ff00;
+                background: $accent;
+                border: 1px solid $accent;
                 width: 12px;
                 margin: -4px 0;
                 border-radius: 6px;
@@ -692,61 +1946,358 @@ class SimulatorGUI(QMainWindow):
         # Add stretch to push everything to the left
         layout.addStretch()
 
+        # Whether writes to the zero register are discarded
+        self.zero_button = QPushButton("Zero reg: hardwired")
+        self.zero_button.clicked.connect(self.toggle_zero_register)
+        layout.addWidget(self.zero_button)
+
+        # Datapath width: registers, ALU results and memory words wrap to it
+        self.word_size_button = QPushButton(f"Word: {self.word_size}-bit")
+        self.word_size_button.setToolTip("Make this an 8-, 16- or 32-bit machine; restarts the program")
+        self.word_size_button.clicked.connect(self.toggle_word_size)
+        layout.addWidget(self.word_size_button)
+
+        # MIPS-style branch delay slots, optionally filled with NOPs by the assembler
+        self.delay_slots_button = QPushButton(f"Delay slots: {self.delay_slots}")
+        self.delay_slots_button.setToolTip("off, on (the instruction after a branch always runs) or fill (on, with "
+                                           "a NOP assembled after every branch); restarts the program")
+        self.delay_slots_button.clicked.connect(self.toggle_delay_slots)
+        layout.addWidget(self.delay_slots_button)
+
+        # Byte order used by sub-word loads/stores and the memory byte view
+        self.endian_button = QPushButton(f"Endian: {self.endianness}")
+        self.endian_button.clicked.connect(self.toggle_endianness)
+        layout.addWidget(self.endian_button)
+
+        # Unified (von Neumann) or split (Harvard) instruction and data memory
+        self.memory_organization_button = QPushButton(f"Memory: {self._memory_organization_name()}")
+        self.memory_organization_button.clicked.connect(self.toggle_memory_organization)
+        layout.addWidget(self.memory_organization_button)
+
+        # Whether several loaded programs share the CPU round-robin or run one after another
+        self.workload_button = QPushButton(f"Workload: {self._workload_name()}")
+        self.workload_button.setToolTip("Interleave several loaded programs every few instructions or run each to "
+                                        "completion in turn, against the same caches; restarts the programs")
+        self.workload_button.clicked.connect(self.toggle_workload)
+        layout.addWidget(self.workload_button)
+
+        # Stall cycles charged for each data access that L1 cannot serve
+        self.miss_penalty_button = QPushButton("Miss penalty: 0 cycles")
+        self.miss_penalty_button.setToolTip("Cycles the processor waits for memory on each L1 miss, added to the "
+                                            "cycle count and the pipeline's MEM stage; restarts the program")
+        self.miss_penalty_button.clicked.connect(self.toggle_miss_penalty)
+        layout.addWidget(self.miss_penalty_button)
+
+        # Guided walkthrough of loading, stepping and reading the cache panel
+        tutorial_button = QPushButton("Tutorial")
+        tutorial_button.clicked.connect(self.show_tutorial)
+        layout.addWidget(tutorial_button)
+
+        # Edit the loaded program with highlighting and assembler diagnostics
+        edit_button = QPushButton("Edit Program")
+        edit_button.clicked.connect(self.show_editor)
+        layout.addWidget(edit_button)
+
+        # Set a register or memory word by hand
+        poke_button = QPushButton("Poke...")
+        poke_button.clicked.connect(self.poke)
+        layout.addWidget(poke_button)
+
         # Add Show Used Memory button
         show_memory_button = QPushButton("Show Used Memory")
         show_memory_button.clicked.connect(self.show_used_memory)
         layout.addWidget(show_memory_button)
 
+        # Add Hex View button
+        hexdump_button = QPushButton("Hex View")
+        hexdump_button.clicked.connect(self.show_hexdump)
+        layout.addWidget(hexdump_button)
+
+        # Add Memory Snapshots button
+        snapshots_button = QPushButton("Memory Snapshots")
+        snapshots_button.clicked.connect(self.show_snapshots)
+        layout.addWidget(snapshots_button)
+
+        # Add Save/Load Cache State buttons
+        save_cache_button = QPushButton("Save Cache State...")
+        save_cache_button.clicked.connect(self.save_cache_state_dialog)
+        layout.addWidget(save_cache_button)
+        load_cache_button = QPushButton("Load Cache State...")
+        load_cache_button.clicked.connect(self.load_cache_state_dialog)
+        layout.addWidget(load_cache_button)
+
+        # Add Show Dependencies button
+        show_dependencies_button = QPushButton("Show Dependencies")
+        show_dependencies_button.clicked.connect(self.show_dependencies)
+        layout.addWidget(show_dependencies_button)
+
+        # Add Show Pipeline button
+        show_pipeline_button = QPushButton("Show Pipeline")
+        show_pipeline_button.clicked.connect(self.show_pipeline)
+        layout.addWidget(show_pipeline_button)
+
+        # Add Show LSQ button
+        show_lsq_button = QPushButton("Show LSQ")
+        show_lsq_button.clicked.connect(self.show_lsq)
+        layout.addWidget(show_lsq_button)
+
+        # Add Show Predictor button
+        show_predictor_button = QPushButton("Show Predictor")
+        show_predictor_button.clicked.connect(self.show_predictor)
+        layout.addWidget(show_predictor_button)
+
+        # Add Show Scoreboard button
+        show_scoreboard_button = QPushButton("Show Scoreboard")
+        show_scoreboard_button.clicked.connect(self.show_scoreboard)
+        layout.addWidget(show_scoreboard_button)
+
+        # Add What-If Cache button
+        whatif_button = QPushButton("What-If Cache")
+        whatif_button.setToolTip("Replay the loads and stores so far on an L1 with other sets, ways, block size "
+                                 "or policies")
+        whatif_button.clicked.connect(self.show_whatif)
+        layout.addWidget(whatif_button)
+
+        # Add Cache Sweep button
+        sweep_button = QPushButton("Cache Sweep")
+        sweep_button.setToolTip("Replay the loads and stores so far on L1s with 1, 2, 4 and 8 ways or words per "
+                                "block at the same capacity, or with other capacities, and plot the miss rate")
+        sweep_button.clicked.connect(self.show_sweep)
+        layout.addWidget(sweep_button)
+
+        # Add Eviction Log button
+        evictions_button = QPushButton("Eviction Log")
+        evictions_button.setToolTip("Every line evicted from L1 and L2: when, from which set, why and whether it "
+                                    "was written back")
+        evictions_button.clicked.connect(self.show_evictions)
+        layout.addWidget(evictions_button)
+
+        # Add Show Microcode button
+        show_microcode_button = QPushButton("Show Microcode")
+        show_microcode_button.clicked.connect(self.show_microcode)
+        layout.addWidget(show_microcode_button)
+
+        # Add Locality Lab button
+        locality_button = QPushButton("Locality Lab")
+        locality_button.clicked.connect(self.show_locality_lab)
+        layout.addWidget(locality_button)
+
+        # Add A/B Compare button
+        compare_button = QPushButton("A/B Compare")
+        compare_button.clicked.connect(self.show_compare)
+        layout.addWidget(compare_button)
+
+        # Add Optimizer button
+        optimizer_button = QPushButton("Optimizer")
+        optimizer_button.clicked.connect(self.show_optimizer)
+        layout.addWidget(optimizer_button)
+
+        # Add Save Report button
+        report_button = QPushButton("Save Report...")
+        report_button.clicked.connect(self.save_report)
+        layout.addWidget(report_button)
+
+        # Add Show Statistics button
+        show_stats_button = QPushButton("Show Statistics")
+        show_stats_button.clicked.connect(self.show_statistics)
+        layout.addWidget(show_stats_button)
+
+        # Add Reset Statistics button
+        reset_stats_button = QPushButton("Reset Statistics")
+        reset_stats_button.setToolTip("Zero hits, misses and cycle counts so they measure only what runs next "
+                                      "(the program can do this itself with RESET_STATS)")
+        reset_stats_button.clicked.connect(self.reset_statistics)
+        layout.addWidget(reset_stats_button)
+
+        # Add Show Log button
+        show_log_button = QPushButton("Show Log")
+        show_log_button.clicked.connect(self.show_log)
+        layout.addWidget(show_log_button)
+
+        # Add Export VCD button
+        export_vcd_button = QPushButton("Export VCD...")
+        export_vcd_button.clicked.connect(self.export_vcd)
+        layout.addWidget(export_vcd_button)
+
+        # Add Settings button
+        settings_button = QPushButton("Settings...")
+        settings_button.clicked.connect(self.show_settings)
+        layout.addWidget(settings_button)
+
         return frame
 
     def load_instructions(self, filename):
         """Load instructions from file"""
+        self.load_programs([filename])
+
+    def load_programs(self, filenames, link=False):
+        """Load one program, or several programs sharing the CPU round-robin
+
+        With link set, the files are instead linked into one program. C files
+        (.c) are compiled first; the editor and its machine code listing then
+        show the generated assembly.
+        """
+        if self.programs:
+            self.undo_stack.record(f"{'link' if link else 'load'} "
+                                   f"{', '.join(os.path.basename(f) for f in filenames)}")
         try:
-            with open(filename, 'r') as f:
-                # Filter out empty lines and comments
-                self.instructions = []
-                for line in f:
-                    line = line.strip()
-                    # Skip empty lines and comment-only lines
-                    if not line or line.startswith(';'):
-                        continue
-                    # For lines with inline comments, only keep the instruction part
-                    if ';' in line:
-                        line = line.split(';')[0].strip()
-                    if line:  # Only add non-empty lines
-                        self.instructions.append(line)
-
-            self.current_instruction = 0
+            self.programs = []
+            if link:
+                linked = link_modules(read_modules(filenames))
+                self.programs.append(("+".join(module.name for module in linked.modules), linked.lines))
+                for line in format_map(linked):
+                    self.logger.log(LogLevel.INFO, line)
+            else:
+                for filename in filenames:
+                    name = os.path.basename(filename)
+                    with open(filename, 'r') as f:
+                        text = f.read()
+                    if filename.endswith('.c'):
+                        try:
+                            lines = compile_source(text, name)
+                        except CompileError as e:
+                            raise ValueError(f"{name}: {e}") from None
+                        self.logger.log(LogLevel.INFO, f"Compiled {name} to {len(lines)} lines of assembly")
+                        self.programs.append((name, lines))
+                    else:
+                        self.programs.append((name, text.splitlines()))
+
+            # A new program starts from empty memory and caches, not the previous program's data
+            self.isa.clear_memory()
+            self.step_recorder.reset()
+            self.used_memory_blocks = set()
+            self._load_programs_into_isa()
+            self.session_tabs.setTabText(self.session_index, self._session_name())
             self.instruction_label.setText("None")
-            self.pc_label.setText("0x00")
+            self.pc_label.setText(f"0x{self.isa.pc:02x}")
             self.status_label.setText("Ready")
             self.update_display()
+            self._verify_programs()
         except Exception as e:
             self.status_label.setText(f"Error loading instructions - {str(e)}")
 
+    def _load_programs_into_isa(self):
+        """Assemble the loaded programs into the current ISA instance"""
+        if len(self.programs) > 1:
+            self.scheduler = RoundRobinScheduler(quantum=0 if self.back_to_back else self.scheduler_quantum)
+            self.scheduler.load(self.isa, self.programs)
+        else:
+            self.scheduler = None
+            self.isa.load_program(self.programs[0][1] if self.programs else [])
+
+        self.instructions = [instruction.source for instruction in self.isa.instructions]
+        self.current_instruction = self.isa.pc
+        self.vcd_recorder.reset()
+        self.microcode.reset()
+        self.event_stats.reset()
+        self.profiler.reset()
+        self.heatmap.reset()
+        self.strides.reset()
+        self.cache_trace.reset()
+        self.eviction_log.reset()
+        self.scoreboard.reset()
+        # .endian, .zero_register, .word_size, .delay_slots and .miss_penalty directives override the current settings
+        # until the next load (see SimpleISA.load_program)
+        self.endianness = self.isa.endianness
+        self.hardwired_zero = self.isa.hardwired_zero
+        self.memory_organization = self.isa.memory_organization
+        self.word_size = self.isa.word_bits
+        self.delay_slots = self.isa.delay_slot_mode
+        self.miss_penalty = self.isa.miss_penalty
+        self._update_setting_buttons()
+
+    def _update_setting_buttons(self):
+        self.word_size_button.setText(f"Word: {self.word_size}-bit")
+        self.delay_slots_button.setText(f"Delay slots: {self.delay_slots}")
+        self.endian_button.setText(f"Endian: {self.endianness}")
+        self.zero_button.setText(f"Zero reg: {'hardwired' if self.hardwired_zero else 'writable'}")
+        self.memory_organization_button.setText(f"Memory: {self._memory_organization_name()}")
+        self.workload_button.setText(f"Workload: {self._workload_name()}")
+        self.miss_penalty_button.setText(f"Miss penalty: {self.miss_penalty} cycles")
+
+    def _subscribe_to_events(self):
+        """Follow the current ISA's event bus: statistics from every event, status from exceptions"""
+        self.event_stats = EventStats(self.isa.events)
+        self.step_exceptions = []
+        self.isa.events.subscribe(self.step_exceptions.append, [SimEvent.EXCEPTION])
+        self.step_cache_accesses = []  # Highlighted as hits or misses in the L1 panel
+        self.isa.events.subscribe(self.step_cache_accesses.append, [SimEvent.CACHE_ACCESS])
+        # What each step did, for the "What Happened" panel; a reset keeps the caches, so their history carries over
+        previous = getattr(self, 'step_recorder', None)
+        self.step_recorder = StepRecorder(self.isa, previous if previous and previous.isa.cache is self.isa.cache
+                                          else None)
+        self.profiler = FunctionProfiler(self.isa)
+        self.heatmap = AccessHeatmap(self.isa)  # Accesses per word for the memory window's heatmap and timeline
+        self.strides = StrideAnalyzer(self.isa)  # Each load's strides, sequential runs and pointer chasing
+        self.cache_trace = CacheTrace(self.isa)  # L1 accesses for the what-if window to replay
+        self.eviction_log = EvictionLog(self.isa)  # Lines evicted from every level, for the eviction log window
+        self.scoreboard = Scoreboard(self.isa)  # CDC 6600-style schedule of the same instructions
+
+    def _verify_programs(self):
+        """Run static checks on the loaded programs and show any warnings before execution"""
+        warnings = []
+        errors = []
+        if self.scheduler:
+            verifier = ProgramVerifier(self.scheduler.region_size, self.isa.delay_slots)
+            for process in self.scheduler.processes:
+                code = self.isa.instructions[process.code_base:process.code_base + process.code_size]
+                warnings += [f"{process.name}: {w}" for w in verifier.verify(code, process.labels, process.code_base)]
+        else:
+            verifier = ProgramVerifier(self.main_memory._size, self.isa.delay_slots)
+            warnings = verifier.verify(self.isa.instructions, self.isa.labels, entry=self.isa.entry_point)
+            # Lines the assembler skipped; they are already in the log as errors
+            errors = [f"Line {line + 1}: {message}" for line, message in self.isa.diagnostics]
+
+        for warning in warnings:
+            self.logger.log(LogLevel.WARNING, warning)
+        warnings = errors + warnings
+        if warnings:
+            self.status_label.setText(f"Ready - {len(warnings)} load warning{'s' if len(warnings) > 1 else ''}")
+            QMessageBox.warning(self, "Program Warnings", "\n".join(warnings))
+
     def step_execution(self):
         """Execute one instruction and update display"""
-        if self.current_instruction < len(self.instructions):
-            instruction = self.instructions[self.current_instruction]
+        if self.isa.running and self.isa.pc < len(self.isa.instructions):
+            self.undo_stack.record(f"step at pc {self.isa.pc}")
+            instruction = self.isa.instructions[self.isa.pc]
+            self.step_recorder.begin()
             # Show a cleaner instruction display (without any trailing comments)
-            self.instruction_label.setText(instruction)
-            self.pc_label.setText(f"0x{self.current_instruction:02x}")
+            self.instruction_label.setText(instruction.source)
+            self.pc_label.setText(f"0x{self.isa.pc:02x}")
             self.status_label.setText("Executing...")
 
             # Force GUI update
             QApplication.processEvents()
 
             try:
-                # First load the program if we haven't already
-                if self.current_instruction == 0:
-                    self.isa.load_program(self.instructions)
-
-                # Execute one step
-                result = self.isa.execute_step()
-                if result:
-                    self.status_label.setText("Instruction Complete")
+                # Execute one step, sampling signals for VCD export
+                self.step_exceptions.clear()
+                self.step_cache_accesses.clear()
+                result = self.vcd_recorder.step()
+                fatal = [event for event in self.step_exceptions if event.data['fatal']]
+                if fatal:
+                    self.status_label.setText(f"Error - {fatal[-1].data['message']}")
+                    self.status_label.setStyleSheet(f"QLabel {{ color: {self.theme['error']}; }}")
+                    self.timer.stop()
+                    self.is_running = False
+                    self.run_button.setText("Run")
+                elif result:
+                    status = "Instruction Complete"
+                    if self.scheduler and self.scheduler.current:
+                        status += f" [{self.scheduler.current.name}]"
+                    for event in self.step_exceptions:
+                        status += f" - trap: {event.data['cause']}"
+                    if self.isa.step_stall:
+                        status += f" - stalled {self.isa.step_stall} cycles waiting for memory"
+                    if self.isa.step_warnings:
+                        # Highlight reads of never-written registers or memory
+                        status += " - " + "; ".join(self.isa.step_warnings)
+                        self.status_label.setStyleSheet(f"QLabel {{ color: {self.theme['error']}; }}")
+                    else:
+                        self.status_label.setStyleSheet(f"QLabel {{ color: {self.theme['status']}; }}")
+                    self.status_label.setText(status)
                 else:
-                    self.status_label.setText("Program Halted")
+                    self.status_label.setText(self._halted_status())
                     self.timer.stop()
                     self.is_running = False
                     self.run_button.setText("Run")
@@ -757,8 +2308,23 @@ class SimulatorGUI(QMainWindow):
                 self.is_running = False
                 self.run_button.setText("Run")
 
-            self.current_instruction += 1
+            self.current_instruction = self.isa.pc
+            self.microcode.reset()  # A whole instruction ran, so the control unit is back at fetch
             self.update_display()
+            record = self.step_recorder.finish()
+            # Animate within one step's time while running, so the next step does not cut it short
+            duration = min(400, self.simulation_speed) if self.is_running else 400
+            if record and record.alu:
+                self.alu_view.show_operation(record.alu, duration)
+            if record:
+                self.update_fetch_view(record, duration)
+            last = self.isa.pipeline.records[-1] if self.isa.pipeline.records else None
+            if last and last.mispredicted and self.pipeline_window is not None and self.pipeline_window.isVisible():
+                self.pipeline_chart.show_squash(last.seq, duration)
+            explanation = "\n".join(explain_step(record, self.l1_cache)) if record else ""
+            self.explanation_view.setPlainText(explanation)
+            if self.tutorial_window is not None and self.tutorial_window.isVisible():
+                self.tutorial_explanation.setPlainText(explanation)
 
             # Force another GUI update after state changes
             QApplication.processEvents()
@@ -769,6 +2335,193 @@ class SimulatorGUI(QMainWindow):
             self.status_label.setText("Program Complete")
             QApplication.processEvents()
 
+    # Attributes that say which simulator objects are live; a reset or load replaces them
+    UNDO_ATTRIBUTES = ('isa', 'vcd_recorder', 'microcode', 'event_stats', 'step_exceptions', 'step_cache_accesses',
+                       'step_recorder', 'profiler', 'heatmap', 'strides', 'cache_trace', 'eviction_log', 'scoreboard',
+                       'scheduler', 'programs', 'instructions', 'current_instruction', 'endianness', 'hardwired_zero',
+                       'word_size', 'delay_slots', 'memory_organization', 'back_to_back', 'miss_penalty',
+                       'used_memory_blocks')
+
+    def _capture_state(self):
+        objects = [self.isa, self.main_memory, self.l1_cache, self.l2_cache, self.isa.write_buffer,
+                   self.isa.pipeline, self.isa.lsq, self.scheduler, self.vcd_recorder, self.microcode, self.event_stats,
+                   self.step_recorder, self.profiler, self.heatmap, self.strides, self.cache_trace,
+                   self.eviction_log, self.scoreboard]
+        return {name: getattr(self, name) for name in self.UNDO_ATTRIBUTES}, MachineState(objects)
+
+    def _restore_state(self, state):
+        attributes, machine = state
+        machine.restore()
+        for name, value in attributes.items():
+            setattr(self, name, value)
+        self._update_setting_buttons()
+
+    def _after_history_change(self, verb, description):
+        if description is None:
+            self.status_label.setText(f"Nothing to {verb.lower()}")
+            return
+        self.timer.stop()
+        self.is_running = False
+        self.run_button.setText("Run")
+        self.status_label.setText(f"{verb}: {description}")
+        self.status_label.setStyleSheet(f"QLabel {{ color: {self.theme['info']}; }}")
+        pc = self.isa.pc
+        if pc < len(self.isa.instructions):
+            self.instruction_label.setText(self.isa.instructions[pc].source)
+        self.pc_label.setText(f"0x{pc:02x}")
+        self.update_display()
+        self.update_microcode_display()
+
+    def undo(self):
+        """Go back to the state before the last step, reset, load or poke"""
+        self.timer.stop()
+        self._after_history_change("Undo", self.undo_stack.undo())
+
+    def redo(self):
+        self.timer.stop()
+        self._after_history_change("Redo", self.undo_stack.redo())
+
+    def poke(self):
+        """Set a register (eax=5, f2=1.5) or memory word ([100]=7) by hand"""
+        text, ok = QInputDialog.getText(self, "Poke", "Register or [address] = value:")
+        if not ok or '=' not in text:
+            return
+        target, value = (part.strip() for part in text.split('=', 1))
+        self.undo_stack.record(f"poke {target}")
+        try:
+            if target.startswith('[') and target.endswith(']'):
+                self.isa.poke_memory(int(target[1:-1]), int(value))
+            else:
+                self.isa.poke_register(target.lower(), float(value) if target.lower() in self.isa.fp_registers
+                                       else int(value))
+        except ValueError as e:
+            self.undo_stack.discard_last()
+            self.status_label.setText(f"Poke failed - {e}")
+            return
+        self.status_label.setText(f"Poked {target} = {value}")
+        self.update_display()
+
+    def _halted_status(self):
+        return f"Program Halted - {self.isa.halt_description()}" if self.isa.halt_reason else "Program Halted"
+
+    # Instructions Step Over and Run to Cursor execute before giving up, in case the target is never reached
+    RUN_UNTIL_LIMIT = 100000
+
+    def _run_until(self, done, description):
+        """Execute instructions without animating them until done(instruction just executed) holds
+
+        The whole run is undone as a single step.
+        """
+        if not self.isa.running or self.isa.pc >= len(self.isa.instructions):
+            self.status_label.setText("Program Complete")
+            return
+        self.timer.stop()
+        self.is_running = False
+        self.run_button.setText("Run")
+        self.undo_stack.record(description)
+
+        record = None
+        status = None
+        for _ in range(self.RUN_UNTIL_LIMIT):
+            self.step_exceptions.clear()
+            self.step_cache_accesses.clear()
+            self.step_recorder.begin()
+            instruction = self.isa.instructions[self.isa.pc]
+            result = self.vcd_recorder.step()
+            record = self.step_recorder.finish() or record
+            fatal = [event for event in self.step_exceptions if event.data['fatal']]
+            if fatal:
+                status = f"Error - {fatal[-1].data['message']}"
+                break
+            if not result or self.isa.pc >= len(self.isa.instructions):
+                status = self._halted_status()
+                break
+            if done(instruction):
+                break
+        else:
+            status = f"Stopped after {self.RUN_UNTIL_LIMIT} instructions"
+
+        self.status_label.setText(status or f"Stopped at pc {self.isa.pc}")
+        self.current_instruction = self.isa.pc
+        self.microcode.reset()
+        self.update_display()
+        explanation = "\n".join(explain_step(record, self.l1_cache)) if record else ""
+        self.explanation_view.setPlainText(explanation)
+
+    def step_many(self):
+        """Step as many instructions as the step-count field says"""
+        count = self.step_count_spin.value()
+        if count <= 1:
+            self.step_execution()
+            return
+        executed = 0
+
+        def counted(_):
+            nonlocal executed
+            executed += 1
+            return executed >= count
+
+        self._run_until(counted, f"step {count} at pc {self.isa.pc}")
+
+    def step_over(self):
+        """Step one instruction; a JAL runs its subroutine, and any it calls, until it returns"""
+        if self.isa.pc >= len(self.isa.instructions) or call_depth_change(self.isa.instructions[self.isa.pc]) <= 0:
+            self.step_execution()
+            return
+        depth = 0
+
+        def returned(instruction):
+            nonlocal depth
+            depth += call_depth_change(instruction)
+            return depth <= 0
+
+        self._run_until(returned, f"step over at pc {self.isa.pc}")
+
+    def run_to_cursor(self):
+        """Run until the instruction on the editor's cursor line is next, or one at an entered address or label"""
+        target = None
+        if self.editor_window is not None and self.editor_window.isVisible():
+            line = self.editor.textCursor().block().blockNumber()
+            target = next((pc for pc, instruction in enumerate(self.isa.instructions)
+                           if instruction.line_number >= line), None)
+        if target is None:
+            location, ok = QInputDialog.getText(self, "Run to Cursor", "Instruction address or label:")
+            if not ok or not location.strip():
+                return
+            try:
+                target = self.isa.instruction_index(location)
+            except ValueError as e:
+                self.status_label.setText(str(e))
+                return
+        self._run_until(lambda _: self.isa.pc == target, f"run to pc {target}")
+
+    def run_until_change(self):
+        """Run until a memory word changes value, e.g. to find out when data[3] gets written"""
+        text, ok = QInputDialog.getText(self, "Run Until Change", "Memory address (decimal or 0x hex):")
+        if not ok or not text.strip():
+            return
+        try:
+            addr = int(text.strip().strip('[]'), 0)
+        except ValueError:
+            self.status_label.setText(f"'{text.strip()}' is not an address")
+            return
+        data = self.isa.memory.data
+        if not 0 <= addr < len(data):
+            self.status_label.setText(f"Address {addr} is outside memory (0-{len(data) - 1})")
+            return
+        old = data[addr]
+        writer = []
+
+        def changed(instruction):
+            if data[addr] != old:
+                writer.append(instruction)
+            return bool(writer)
+
+        self._run_until(changed, f"run until [{addr}] changes")
+        if writer:
+            self.status_label.setText(f"[{addr}] changed {old} -> {data[addr]} by {writer[0].source} "
+                                      f"(line {writer[0].line_number + 1})")
+
     def toggle_run(self):
         """Toggle between run and pause states"""
         self.is_running = not self.is_running
@@ -779,13 +2532,95 @@ class SimulatorGUI(QMainWindow):
             self.run_button.setText("Run")
             self.timer.stop()
 
+    def toggle_zero_register(self):
+        """Switch between a hardwired and an ordinary writable zero register"""
+        self.hardwired_zero = not self.hardwired_zero
+        self.isa.set_zero_register(self.hardwired_zero)
+        self.zero_button.setText(f"Zero reg: {'hardwired' if self.hardwired_zero else 'writable'}")
+
+    def toggle_word_size(self):
+        """Cycle through the datapath widths and restart, since values of the old width may not fit the new one"""
+        self.word_size = WORD_SIZES[(WORD_SIZES.index(self.word_size) + 1) % len(WORD_SIZES)]
+        self.reset_simulation()
+        self.status_label.setText(f"{self.word_size}-bit datapath: results that do not fit wrap around")
+
+    def toggle_delay_slots(self):
+        """Cycle off, on and fill and restart, since fill changes what the program assembles to"""
+        self.undo_stack.record("reset")  # Before the change, so undo brings the old mode back with the old ISA
+        self.delay_slots = DELAY_SLOT_MODES[(DELAY_SLOT_MODES.index(self.delay_slots) + 1) % len(DELAY_SLOT_MODES)]
+        self._restart()
+        self.status_label.setText(f"Delay slots {self.delay_slots}" +
+                                  (": the instruction after a branch always runs" if self.delay_slots != 'off' else ""))
+
+    def toggle_endianness(self):
+        """Switch byte order; memory words keep their value but their bytes are reinterpreted"""
+        self.endianness = 'big' if self.endianness == 'little' else 'little'
+        self.isa.set_endianness(self.endianness)
+        self.endian_button.setText(f"Endian: {self.endianness}")
+        self.update_memory_display()
+
+    def _memory_organization_name(self):
+        return "Harvard" if self.memory_organization == 'harvard' else "von Neumann"
+
+    def toggle_memory_organization(self):
+        """Switch between unified and split instruction/data memory"""
+        self.memory_organization = 'harvard' if self.memory_organization == 'von_neumann' else 'von_neumann'
+        self.isa.set_memory_organization(self.memory_organization)
+        self.memory_organization_button.setText(f"Memory: {self._memory_organization_name()}")
+        self.update_memory_display()
+
+    def _workload_name(self):
+        return "back to back" if self.back_to_back else "interleaved"
+
+    def toggle_workload(self):
+        """Switch several programs between round-robin and back-to-back and restart them"""
+        self.undo_stack.record("reset")
+        self.back_to_back = not self.back_to_back
+        self.workload_button.setText(f"Workload: {self._workload_name()}")
+        self._restart()
+        self.status_label.setText("Programs run back to back" if self.back_to_back else
+                                  f"Programs interleaved every {self.scheduler_quantum} instructions")
+
+    def toggle_miss_penalty(self):
+        """Step through the miss penalties and restart, so the cycle count covers the whole run at one penalty"""
+        self.undo_stack.record("reset")
+        penalties = sorted(set(MISS_PENALTIES) | {self.miss_penalty})  # A .miss_penalty value may not be listed
+        self.miss_penalty = penalties[(penalties.index(self.miss_penalty) + 1) % len(penalties)]
+        self._restart()
+        self.status_label.setText(f"Each L1 miss stalls {self.miss_penalty} cycles" if self.miss_penalty else
+                                  "No miss penalty: every instruction takes one cycle")
+
     def reset_simulation(self):
         """Reset the simulation to initial state"""
-        self.current_instruction = 0
-        self.isa = SimpleISA(memory=self.main_memory, cache=self.l1_cache)
+        self.undo_stack.record("reset")
+        self._restart()
+
+    def _new_isa(self):
+        isa = SimpleISA(memory=self.main_memory, cache=self.l1_cache, isa_mode=self.settings.isa_mode,
+                        endianness=self.endianness, memory_organization=self.memory_organization)
+        isa.set_zero_register(self.hardwired_zero)
+        isa.set_word_size(self.word_size)
+        isa.set_delay_slots(self.delay_slots)
+        isa.set_miss_penalty(self.miss_penalty)
+        if hasattr(self, 'isa'):
+            isa.inherit_directive_settings(self.isa)
+        return isa
+
+    def _restart(self):
+        """Build a fresh ISA on the same memory hierarchy and load the programs into it"""
+        self.isa.disable_write_buffer()  # Reconnect L1 to L2 before the old ISA is dropped
+        self.isa = self._new_isa()
+        self.isa.pipeline = PipelineModel(self.isa, forwarding=self.pipeline_forwarding,
+                                          predictor=BranchPredictor() if self.branch_prediction else None,
+                                          resources=self.pipeline_resources)
+        self.isa.lsq = LoadStoreQueue(self.isa)
+        self.vcd_recorder = VCDRecorder(self.isa)
+        self.microcode = MicroSequencer(self.isa)
+        self._subscribe_to_events()
+        self._load_programs_into_isa()
         self.status_label.setText("Ready")
         self.instruction_label.setText("None")
-        self.pc_label.setText("0x00")
+        self.pc_label.setText(f"0x{self.isa.pc:02x}")
         self.update_display()
         if self.is_running:
             self.toggle_run()
@@ -799,45 +2634,98 @@ class SimulatorGUI(QMainWindow):
     def update_display(self):
         """Update all visual elements based on current state"""
         # Update registers
-        for reg_name in ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi']:
+        for reg_name in ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'zero']:
             value = self.isa.registers.get(reg_name, 0)
             self.register_labels[reg_name].setText(f"{value}")
+            self.register_frames[reg_name].setToolTip(self._register_tooltip(reg_name))
+
+        # Update flags register and ISA mode
+        self.flags_label.setText(self.isa.format_flags())
+        self.mode_label.setText(f"{self.isa.isa_mode}/{self.isa.privilege}")
+        self.mode_label.setStyleSheet(
+            f"QLabel {{ color: {self.theme['error' if self.isa.privilege == 'kernel' else 'muted']}; }}")
+
+        # Update FPU registers, dimming those still at zero
+        for reg_name, value_label in self.fp_register_labels.items():
+            value = self.isa.fp_registers.get(reg_name, 0.0)
+            value_label.setText(f"{reg_name}: {value:g}")
+            writer = self.isa.register_writers.get(reg_name)
+            value_label.setToolTip(self._writer_description(writer) if writer else "Not written by the program")
+            color = self.theme['info'] if value != 0.0 else self.theme['dim']
+            value_label.setStyleSheet(f"QLabel {{ color: {color}; }}")
+        self.fp_condition_label.setText(f"fcc: {int(self.isa.fp_condition)}")
+
+        # The last ALU operation; a single step animates it once its record is finished
+        if self.alu_view.alu is not self.isa.step_alu:
+            self.alu_view.show_operation(self.isa.step_alu, 0)
+        if self.fetch_view.next_pc != self.isa.pc:
+            self.update_fetch_view()
+        preview = self.isa.branch_preview()
+        self.next_pc_view.set_preview(preview, {index: self._branch_path_name(index) for index in
+                                                ((preview.fall_through, preview.target) if preview else ())})
 
         # Get cache states
         l1_info = self.l1_cache.get_cache_state()
         l2_info = self.l2_cache.get_cache_state()
+        l1_prefetched = self.l1_cache.get_prefetched_blocks()
 
         # Update L1 Cache blocks
-        used_sets = [0, 4, 8, 12]
-        for set_idx in used_sets:
-            for block_idx in range(2):  # 2-way associative
-                block_key = f"{set_idx}_{block_idx}"
-                if block_key in self.l1_blocks:
-                    value_label = self.l1_blocks[block_key]
-                    if (set_idx, block_idx) in l1_info:
-                        tag, value = l1_info[(set_idx, block_idx)]
-                        display_text = f"T:{tag} V:{value}"
-                        value_label.setText(display_text)
-                        value_label.setStyleSheet("QLabel { color: #ff69b4; font-weight: bold; }")
-                    else:
-                        value_label.setText("Empty")
-                        value_label.setStyleSheet("QLabel { color: #666666; }")
+        l1_ranks, l1_victims = self._replacement_marks(self.l1_cache)
+        for block_key, value_label in self.l1_blocks.items():
+            set_idx, block_idx = map(int, block_key.split('_'))
+            if (set_idx, block_idx) in l1_info:
+                tag, value = l1_info[(set_idx, block_idx)]
+                rank = f" R{l1_ranks[(set_idx, block_idx)]}" if l1_ranks else ""
+                victim = self._victim_style() if (set_idx, block_idx) in l1_victims else ""
+                if (set_idx, block_idx) in l1_prefetched:
+                    # Prefetched but not yet used by a load or store
+                    value_label.setText(f"P T:{tag} V:{value}{rank}")
+                    value_label.setStyleSheet(f"QLabel {{ color: {self.theme['prefetch']}; font-style: italic; "
+                                              f"{victim}}}")
+                else:
+                    display_text = f"T:{tag} V:{value}{rank}"
+                    value_label.setText(display_text)
+                    value_label.setStyleSheet(f"QLabel {{ color: {self.theme['l1']}; font-weight: bold; {victim}}}")
+            else:
+                value_label.setText("Empty")
+                value_label.setStyleSheet(f"QLabel {{ color: {self.theme['dim']}; }}")
+            value_label.setToolTip(self._cache_block_tooltip(self.l1_cache, set_idx, block_idx))
+
+        # The L1 blocks the last step read or wrote, in the hit or miss color
+        for event in self.step_cache_accesses:
+            if event.data['cache'] is None:
+                continue  # Uncacheable access
+            set_idx, entry = self.l1_cache._find_entry(event.data['address'])
+            key = f"{set_idx}_{self.l1_cache._entries[set_idx].index(entry)}" if entry else None
+            if key in self.l1_blocks:
+                color = self.theme['hit' if event.data['hit'] else 'miss']
+                self.l1_blocks[key].setStyleSheet(
+                    f"QLabel {{ color: {self.theme['text']}; background-color: {color}; font-weight: bold; }}")
+
+        # The sets the address in the breakdown panel maps to
+        for cache, set_labels in ((self.l1_cache, self.l1_set_labels), (self.l2_cache, self.l2_set_labels)):
+            probe_set = cache.split_address(self.address_probe)[1] if self.address_probe is not None else None
+            for set_idx, set_label in set_labels.items():
+                if set_idx == probe_set:
+                    set_label.setStyleSheet(f"color: {self.theme['text']}; background-color: {self.theme['pc']}; "
+                                            f"font-size: 9pt; font-weight: bold;")
+                else:
+                    set_label.setStyleSheet(f"color: {self.theme['muted']}; font-size: 9pt;")
 
         # Update L2 Cache blocks
-        used_sets_l2 = [0, 4, 8, 12]
-        for set_idx in used_sets_l2:
-            for block_idx in range(4):  # 4-way associative
-                block_key = f"{set_idx}_{block_idx}"
-                if block_key in self.l2_blocks:
-                    value_label = self.l2_blocks[block_key]
-                    if (set_idx, block_idx) in l2_info:
-                        tag, value = l2_info[(set_idx, block_idx)]
-                        display_text = f"T:{tag} V:{value}"
-                        value_label.setText(display_text)
-                        value_label.setStyleSheet("QLabel { color: #9370db; font-weight: bold; }")
-                    else:
-                        value_label.setText("Empty")
-                        value_label.setStyleSheet("QLabel { color: #666666; }")
+        l2_ranks, l2_victims = self._replacement_marks(self.l2_cache)
+        for block_key, value_label in self.l2_blocks.items():
+            set_idx, block_idx = map(int, block_key.split('_'))
+            if (set_idx, block_idx) in l2_info:
+                tag, value = l2_info[(set_idx, block_idx)]
+                display_text = f"T:{tag} V:{value}" + (f" R{l2_ranks[(set_idx, block_idx)]}" if l2_ranks else "")
+                victim = self._victim_style() if (set_idx, block_idx) in l2_victims else ""
+                value_label.setText(display_text)
+                value_label.setStyleSheet(f"QLabel {{ color: {self.theme['l2']}; font-weight: bold; {victim}}}")
+            else:
+                value_label.setText("Empty")
+                value_label.setStyleSheet(f"QLabel {{ color: {self.theme['dim']}; }}")
+            value_label.setToolTip(self._cache_block_tooltip(self.l2_cache, set_idx, block_idx))
 
         # Update cache statistics
         l1_stats = self.l1_cache.get_performance_stats()
@@ -846,25 +2734,192 @@ class SimulatorGUI(QMainWindow):
         self.l1_stats_label.setText(
             f"L1 Cache: Hits: {l1_stats['hits']}, "
             f"Misses: {l1_stats['misses']}, "
-            f"Hit Rate: {l1_stats['hit_rate']:.2f}%"
+            f"Hit Rate: {l1_stats['hit_rate']:.2f}% ({format_read_write(l1_stats)})"
         )
+        if l1_stats['prefetches'] or l1_stats['flushes'] or l1_stats['invalidations']:
+            self.l1_stats_label.setText(
+                self.l1_stats_label.text() +
+                f", Prefetches: {l1_stats['prefetches']} ({l1_stats['useful_prefetches']} useful), "
+                f"Flushed: {l1_stats['flushes']}, Invalidated: {l1_stats['invalidations']}"
+            )
 
         self.l2_stats_label.setText(
             f"L2 Cache: Hits: {l2_stats['hits']}, "
             f"Misses: {l2_stats['misses']}, "
-            f"Hit Rate: {l2_stats['hit_rate']:.2f}%"
+            f"Hit Rate: {l2_stats['hit_rate']:.2f}% ({format_read_write(l2_stats)})"
         )
 
+        levels = amat_levels(self.l1_cache)
+        formula = format_formula(levels)
+        self.amat_label.setText(f"{formula[0]}  {formula[1].strip()}  {formula[2].strip()}")
+        self.amat_label.setToolTip("\n".join(format_amat(levels)))
+
+        if self.isa.write_buffer:
+            wb_stats = self.isa.write_buffer.get_performance_stats()
+            slots = "■" * wb_stats['occupancy'] + "□" * (wb_stats['capacity'] - wb_stats['occupancy'])
+            self.write_buffer_label.setText(f"Write Buffer: {slots} {wb_stats['occupancy']}/{wb_stats['capacity']}, "
+                                            f"Full Stalls: {wb_stats['full_stalls']}")
+        else:
+            self.write_buffer_label.setText("")
+
         # Update flow visualization
         self._update_flow_visualization()
 
-        # Update memory window if it exists
+        # Update memory and statistics windows if they exist
         self.update_memory_display()
+        self.update_statistics_display()
+        self.update_pipeline_display()
+        self.update_lsq_display()
+        self.update_predictor_display()
+        self.update_scoreboard_display()
+        self.update_log_display()
+        self.update_snapshot_display()
+        self.update_hexdump_display()
+        self.update_timeline_display()
+        self.update_working_set_display()
+        self.update_access_patterns_display()
+        self.update_evictions_display()
+        self.update_listing_display()
 
         # Force immediate update
         self.repaint()
         QApplication.processEvents()
 
+    def _register_tooltip(self, reg):
+        """Value, use counts and role of a register, for its hover tooltip"""
+        value = self.isa.registers.get(reg, 0)
+        usage = self.isa.register_usage.get(reg, {'reads': 0, 'writes': 0})
+        bits = self.isa.word_bits
+        lines = [f"{reg} = {value} (0x{value & ((1 << bits) - 1):0{bits // 4}x}, {bits}-bit)"]
+        if reg == ZERO_REGISTER:
+            lines.append("Zero register: always reads 0 and writes to it are discarded" if self.isa.hardwired_zero
+                         else "Zero register, with hardwiring off: it holds whatever was last written")
+        elif reg == LINK_REGISTER:
+            lines.append("General purpose register, and the link register: JAL saves the return address here")
+        else:
+            lines.append("General purpose register")
+        lines.append(f"Read {usage['reads']} times, written {usage['writes']} times by the program")
+        if reg not in self.isa.initialized_registers:
+            lines.append("Not written yet: reading it warns of an uninitialized read")
+        elif reg in self.isa.register_writers:
+            lines.append(self._writer_description(self.isa.register_writers[reg]))
+        return "\n".join(lines)
+
+    def _writer_description(self, writer):
+        """The instruction of a last-writer record, with the step and cycle it ran in"""
+        text = f"Last written by instruction {self._instruction_name(writer.pc)}"
+        if 0 <= writer.pc < len(self.isa.instructions):
+            text += f": {self.isa.instructions[writer.pc].source.strip()}"
+        return f"{text}\nat step {writer.step}, cycle {writer.cycle}"
+
+    @staticmethod
+    def _replacement_marks(cache):
+        """Each cached block's recency rank (R0 most recently used) and the blocks a fill would evict from full sets
+
+        A direct-mapped cache has one line per set, so there is nothing to rank or choose and both are left empty.
+        """
+        ranks, victims = {}, set()
+        if cache._associativity == 1:
+            return ranks, victims
+        for set_idx, entries in enumerate(cache._entries):
+            for way, rank in enumerate(cache.recency_ranks(set_idx)):
+                ranks[(set_idx, way)] = rank
+            victim = cache.next_victim(set_idx)
+            if victim is not None and len(entries) == cache._associativity:
+                victims.add((set_idx, victim))
+        return ranks, victims
+
+    def _victim_style(self):
+        return f"border: 1px dashed {self.theme['miss']};"
+
+    def _cache_block_tooltip(self, cache, set_idx, way):
+        """Address held by a cache block and how it splits into tag, set and offset"""
+        info = cache.debug_info()
+        entries = cache._entries[set_idx]
+        name = cache._name.replace("Cache", "")
+        if way >= len(entries):
+            return (f"{name} set {set_idx}, way {way}: empty\nAddresses whose set field is {set_idx} are cached in "
+                    f"one of this set's {info['associativity']} ways")
+        entry = entries[way]
+        address = cache.block_address(set_idx, entry['tag'])
+        lines = [f"{name} set {set_idx}, way {way}: word {address}, value {entry['data']}",
+                 f"Address {cache.format_split(address)}",
+                 f"{info['sets']} sets of {info['associativity']} ways, {info['write_policy']}, "
+                 f"{info['replacement_policy'].upper()} replacement"]
+        if info['associativity'] > 1:
+            rank = cache.recency_ranks(set_idx)[way]
+            lines.append(f"R{rank}: " + ("the most recently used line of its set" if rank == 0 else
+                                         f"{rank} other line{'s' if rank > 1 else ''} of its set used more recently"))
+        if entry['dirty']:
+            lines.append("Dirty: changed since it was filled, written back when evicted")
+        if entry.get('prefetched'):
+            lines.append("Prefetched, not yet used by a load or store")
+        if len(entries) == info['associativity'] and cache.next_victim(set_idx) == way:
+            lines.append("Evicted next when another address maps to this set (dashed border)")
+        return "\n".join(lines)
+
+    def _next_pc_text(self, record):
+        """How the PC after a step was computed, e.g. 'PC = 4 + 1 = 5' or 'taken: PC = 2 <loop>'"""
+        pc, next_pc = record.pc, record.next_pc
+        if any(not fatal for _, _, fatal in record.exceptions):
+            return f"trap: PC = {self._instruction_name(self.isa.pc)} (handler)"
+        if record.halted or next_pc is None:
+            return "halted: nothing more to fetch"
+        if record.delayed_target is not None:
+            return (f"taken: PC = {pc} + 1 = {next_pc} (delay slot), "
+                    f"then target {self._instruction_name(record.delayed_target)}")
+        if record.delay_slot_of is not None:
+            return f"delay slot done: PC = branch target {self._instruction_name(next_pc)}"
+        if record.branch_taken is True:
+            return f"taken: PC = target {self._instruction_name(next_pc)}"
+        if next_pc == pc + 1:
+            return f"{'not taken: ' if record.branch_taken is False else ''}PC = {pc} + 1 = {next_pc}"
+        return f"jump: PC = {self._instruction_name(next_pc)}"
+
+    def _branch_path_name(self, index):
+        """An instruction a branch may go to, e.g. '2 <loop>: ADD eax #1'"""
+        if index is None or not 0 <= index < len(self.isa.instructions):
+            return f"{index} (end of program)"
+        return f"{self._instruction_name(index)}: {self.isa.instructions[index].source.split(';')[0].strip()}"
+
+    def update_fetch_view(self, record=None, duration=0):
+        """Show the fetch of a step record's instruction, or with no record the fetch about to happen"""
+        isa = self.isa
+        text_base = 0 if self.memory_organization == 'harvard' else isa.text_base
+        if record:
+            pc, instruction = record.pc, record.instruction.source.split(';')[0].strip()
+            next_pc, next_text = isa.pc, self._next_pc_text(record)
+        else:
+            pc, instruction, next_pc = isa.pc, "", isa.pc
+            next_text = "fetch at PC" if isa.running and pc < len(isa.instructions) else "nothing to fetch"
+        first = max(0, min(pc - FetchView.CELLS // 2, len(isa.instructions) - FetchView.CELLS))
+        cells = [(text_base + index, isa.instructions[index].source.split(';')[0].strip())
+                 for index in range(first, min(first + FetchView.CELLS, len(isa.instructions)))]
+        self.fetch_view.show_fetch(pc, text_base + pc, cells, instruction, next_pc, next_text, duration)
+
+    def _instruction_name(self, index):
+        """An instruction index with the label it is at or after, e.g. '9 <add_twice+2>'"""
+        symbol = symbol_for(index, self.isa.labels)
+        return f"{index} <{symbol}>" if symbol else str(index)
+
+    def _memory_tooltip(self, addr):
+        """Kind, value and last writer of a memory word"""
+        value = self.main_memory.read(addr)
+        kind = self.isa.memory_kind(addr)
+        lines = [f"Memory word {addr}: {kind}", f"Value {value} (0x{value & 0xFFFFFFFF:08x})"]
+        if kind == 'instruction':
+            index = addr - self.isa.text_base
+            lines.append(f"Holds instruction {self._instruction_name(index)}: "
+                         f"{self.isa.instructions[index].source.strip()}")
+        if self.isa.is_uncacheable(addr):
+            lines.append("Uncacheable: accesses bypass the caches")
+        writer = self.isa.memory_writers.get(addr)
+        if writer is not None:
+            lines.append(self._writer_description(writer))
+        else:
+            lines.append("Not written by the program")
+        return "\n".join(lines)
+
     def _highlight_component(self, widget, color, duration=500):
         """Highlight a component with a glowing effect"""
         original_style = widget.styleSheet()
@@ -894,6 +2949,11 @@ class SimulatorGUI(QMainWindow):
         self.flow_lines.append(flow)
         fade.start()
 
+    def _operand_address(self, operand):
+        """Address of a [n] or [reg] memory operand"""
+        expr = operand.strip("[]")
+        return int(expr) if expr.isdigit() else self.isa.registers.get(expr, 0)
+
     def _update_flow_visualization(self):
         """Update the flow visualization based on current operation"""
         if not hasattr(self, 'current_instruction') or self.current_instruction >= len(self.instructions):
@@ -917,9 +2977,9 @@ class SimulatorGUI(QMainWindow):
         if source in self.register_labels:
             source_widget = self.register_labels[source]
         elif source.startswith("["):
-            addr = int(source.strip("[]"))
+            addr = self._operand_address(source)
             set_idx = (addr // 4) * 4
-            if set_idx in [0, 4, 8, 12]:
+            if f"{set_idx}_0" in self.l1_blocks and f"{set_idx}_0" in self.l2_blocks:
                 # Check L1 cache first
                 for way in range(2):
                     key = f"{set_idx}_{way}"
@@ -940,9 +3000,9 @@ class SimulatorGUI(QMainWindow):
             if dest in self.register_labels:
                 dest_widget = self.register_labels[dest]
             elif dest.startswith("["):
-                addr = int(dest.strip("[]"))
+                addr = self._operand_address(dest)
                 set_idx = (addr // 4) * 4
-                if set_idx in [0, 4, 8, 12]:
+                if f"{set_idx}_0" in self.l1_blocks and f"{set_idx}_0" in self.l2_blocks:
                     dest_widget = self.l1_blocks[f"{set_idx}_0"]
                     # For writes, we need to update L2 as well
                     intermediate_widgets.append(self.l2_blocks[f"{set_idx}_0"])
@@ -950,7 +3010,7 @@ class SimulatorGUI(QMainWindow):
         # Create flow visualizations
         if source_widget and dest_widget:
             # Highlight source
-            self._highlight_component(source_widget, "#00ff00")
+            self._highlight_component(source_widget, self.theme['accent'])
 
             # Create flow animations
             prev_widget = source_widget
@@ -960,7 +3020,7 @@ class SimulatorGUI(QMainWindow):
                 dest_pos = widget.mapTo(self.flow_layer,
                     QPoint(widget.width()//2, widget.height()//2))
                 self._create_flow_animation(source_pos, dest_pos)
-                self._highlight_component(widget, "#ff69b4")
+                self._highlight_component(widget, self.theme['l1'])
                 prev_widget = widget
 
     def show_used_memory(self):
@@ -969,19 +3029,78 @@ class SimulatorGUI(QMainWindow):
             self.memory_window.setWindowTitle("Memory Block Details")
             self.memory_window.setMinimumWidth(400)  # Increased width for more info
 
+            window_layout = QHBoxLayout()
             layout = QVBoxLayout()
+            window_layout.addLayout(layout)
 
             # Add description
-            description = QLabel("Memory blocks and their cache references:")
-            description.setFont(QFont("Courier", 10))
-            layout.addWidget(description)
+            self.memory_description = QLabel("Memory blocks and their cache references:")
+            self.memory_description.setFont(QFont("Courier", 10))
+            layout.addWidget(self.memory_description)
+
+            # Legend for the kind colors of the blocks
+            legend = QHBoxLayout()
+            for kind in MEMORY_KINDS:
+                swatch = QLabel(f"\u25a0 {kind}")
+                swatch.setFont(QFont("Courier", 9))
+                swatch.setStyleSheet(f"color: {MEMORY_KIND_COLORS[kind]};")
+                legend.addWidget(swatch)
+            legend.addStretch()
+            layout.addLayout(legend)
+
+            # Access heatmap overlay and the address vs cycle timeline
+            heat_controls = QHBoxLayout()
+            self.heatmap_button = QPushButton("Heatmap: off")
+            self.heatmap_button.setToolTip("Shade each word by how many times the program read and wrote it")
+            self.heatmap_button.clicked.connect(self.toggle_memory_heatmap)
+            heat_controls.addWidget(self.heatmap_button)
+            timeline_button = QPushButton("Access Timeline")
+            timeline_button.setToolTip("Plot every load and store's address against its cycle")
+            timeline_button.clicked.connect(self.show_access_timeline)
+            heat_controls.addWidget(timeline_button)
+            working_set_button = QPushButton("Working Set")
+            working_set_button.setToolTip("Plot how many distinct words each window of cycles touched against "
+                                           "the cache capacities")
+            working_set_button.clicked.connect(self.show_working_set)
+            heat_controls.addWidget(working_set_button)
+            patterns_button = QPushButton("Access Patterns")
+            patterns_button.setToolTip("Classify each load's addresses as sequential, strided, pointer chasing "
+                                       "or irregular")
+            patterns_button.clicked.connect(self.show_access_patterns)
+            heat_controls.addWidget(patterns_button)
+            heat_controls.addStretch()
+            layout.addLayout(heat_controls)
 
             # Create a grid for memory blocks
             self.memory_grid = QGridLayout()
             self.memory_grid.setSpacing(4)  # Add some spacing between blocks
             layout.addLayout(self.memory_grid)
 
-            self.memory_window.setLayout(layout)
+            # Program text: part of the same memory (von Neumann) or a separate memory (Harvard)
+            instruction_layout = QVBoxLayout()
+            self.instruction_memory_title = QLabel("")
+            self.instruction_memory_title.setFont(QFont("Courier", 10))
+            instruction_layout.addWidget(self.instruction_memory_title)
+            self.instruction_memory_label = QLabel("")
+            self.instruction_memory_label.setFont(QFont("Courier", 9))
+            self._themed(self.instruction_memory_label, "color: $info;")
+            self.instruction_memory_label.setAlignment(Qt.AlignmentFlag.AlignTop)
+            instruction_layout.addWidget(self.instruction_memory_label)
+            instruction_layout.addStretch()
+            window_layout.addLayout(instruction_layout)
+
+            # Instruction fetch and data access counts on the memory port(s)
+            self.memory_port_label = QLabel("")
+            self.memory_port_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.memory_port_label)
+
+            # Heap allocation statistics
+            self.heap_stats_label = QLabel("")
+            self.heap_stats_label.setFont(QFont("Courier", 9))
+            self.heap_stats_label.setStyleSheet(f"color: {HEAP_COLORS['allocated']};")
+            layout.addWidget(self.heap_stats_label)
+
+            self.memory_window.setLayout(window_layout)
 
             # Update the memory display initially
             self.update_memory_display()
@@ -993,6 +3112,168 @@ class SimulatorGUI(QMainWindow):
             self.memory_window.show()
             self.memory_window.raise_()
 
+    def _heat_color(self, heat):
+        """The theme's background blended toward red by heat, 0 (never accessed) to 1 (the most accessed word)"""
+        background = QColor(self.theme['background'])
+        red = QColor(HEAT_COLOR)
+        mix = 0.15 + 0.85 * heat if heat else 0.0
+        return QColor(int(background.red() + (red.red() - background.red()) * mix),
+                      int(background.green() + (red.green() - background.green()) * mix),
+                      int(background.blue() + (red.blue() - background.blue()) * mix)).name()
+
+    def toggle_memory_heatmap(self):
+        self.memory_heatmap = not self.memory_heatmap
+        self.heatmap_button.setText(f"Heatmap: {'on' if self.memory_heatmap else 'off'}")
+        self.update_memory_display()
+
+    def show_access_timeline(self):
+        """Open a plot of each data access's address against its cycle, to show strides and reuse"""
+        if self.timeline_window is None:
+            self.timeline_window = QWidget(None)
+            self.timeline_window.setWindowTitle("Access Timeline")
+            layout = QVBoxLayout()
+            self.timeline_chart = AccessTimelineChart()
+            layout.addWidget(self.timeline_chart)
+            self.timeline_label = QLabel("")
+            self.timeline_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.timeline_label)
+            self.timeline_window.setLayout(layout)
+        self.timeline_window.show()
+        self.timeline_window.raise_()
+        self.update_timeline_display()
+
+    def update_timeline_display(self):
+        if self.timeline_window is None or not self.timeline_window.isVisible():
+            return
+        heatmap = self.heatmap
+        self.timeline_chart.set_samples(heatmap.samples)
+        words = len(heatmap.reads.keys() | heatmap.writes.keys())
+        text = [f"{len(heatmap.samples) + heatmap.dropped} accesses to {words} words"]
+        hottest = heatmap.hottest(3)
+        if hottest:
+            text.append("hottest: " + ", ".join(f"[{address}] {reads}r/{writes}w"
+                                                for address, reads, writes in hottest))
+        if heatmap.dropped:
+            text.append(f"plot shows the first {len(heatmap.samples)}")
+        self.timeline_label.setText("; ".join(text))
+
+    def show_working_set(self):
+        """Open a plot of the working-set size over sliding windows of cycles, with the cache capacities marked"""
+        if self.working_set_window is None:
+            self.working_set_window = QWidget(None)
+            self.working_set_window.setWindowTitle("Working Set")
+            layout = QVBoxLayout()
+            self.working_set_chart = WorkingSetChart()
+            layout.addWidget(self.working_set_chart)
+            controls = QHBoxLayout()
+            controls.addWidget(QLabel("Window:"))
+            self.working_set_spin = QSpinBox()
+            self.working_set_spin.setRange(1, 100000)
+            self.working_set_spin.setValue(DEFAULT_WINDOW)
+            self.working_set_spin.setSuffix(" cycles")
+            self.working_set_spin.setToolTip("Cycles each point looks back over; a window longer than the reuse "
+                                             "distance counts a loop's whole footprint")
+            self.working_set_spin.valueChanged.connect(lambda _: self.update_working_set_display())
+            controls.addWidget(self.working_set_spin)
+            controls.addStretch()
+            layout.addLayout(controls)
+            self.working_set_label = QLabel("")
+            self.working_set_label.setFont(QFont("Courier", 9))
+            self.working_set_label.setWordWrap(True)
+            layout.addWidget(self.working_set_label)
+            self.working_set_window.setLayout(layout)
+        self.working_set_window.show()
+        self.working_set_window.raise_()
+        self.update_working_set_display()
+
+    def update_working_set_display(self):
+        if self.working_set_window is None or not self.working_set_window.isVisible():
+            return
+        points = working_set(self.heatmap.samples, self.working_set_spin.value())
+        capacities = cache_capacities(self.l1_cache)
+        self.working_set_chart.set_points(points, capacities)
+        self.working_set_label.setText(summarize(points, capacities))
+
+    # Columns of the access-pattern table, in the order format_patterns prints them
+    PATTERN_COLUMNS = ("pc", "line", "instruction", "loads", "pattern", "top stride", "runs", "longest", "chased")
+
+    def show_access_patterns(self):
+        """Open the per-load access-pattern table; double-clicking a row shows its line in the editor"""
+        if self.patterns_window is None:
+            self.patterns_window = QWidget(None)
+            self.patterns_window.setWindowTitle("Access Patterns")
+            self.patterns_window.setMinimumSize(800, 300)
+            layout = QVBoxLayout()
+            self.patterns_table = QTableWidget(0, len(self.PATTERN_COLUMNS))
+            self.patterns_table.setHorizontalHeaderLabels(self.PATTERN_COLUMNS)
+            self.patterns_table.setFont(QFont("Courier", 9))
+            self.patterns_table.setEditTriggers(QTableWidget.EditTrigger.NoEditTriggers)
+            self.patterns_table.cellDoubleClicked.connect(
+                lambda row, _: self._show_source_line(int(self.patterns_table.item(row, 1).text()) - 1))
+            layout.addWidget(self.patterns_table)
+            self.patterns_label = QLabel(f"A run is {MIN_RUN} or more loads of consecutive words; chased loads "
+                                         f"took their address from a value loaded from memory. Double-click a row "
+                                         f"to see it in the editor.")
+            self.patterns_label.setFont(QFont("Courier", 9))
+            self.patterns_label.setWordWrap(True)
+            layout.addWidget(self.patterns_label)
+            self.patterns_window.setLayout(layout)
+        self.patterns_window.show()
+        self.patterns_window.raise_()
+        self.update_access_patterns_display()
+
+    def update_access_patterns_display(self):
+        if self.patterns_window is None or not self.patterns_window.isVisible():
+            return
+        patterns = self.strides.patterns()
+        self.patterns_table.setRowCount(len(patterns))
+        for row, p in enumerate(patterns):
+            stride = f"{p.top_stride:+d} ({p.top_share:.0%})" if p.strides else ""
+            values = (p.pc, p.line_number + 1, p.source, p.accesses, p.pattern, stride, p.runs, p.longest_run,
+                      p.chased)
+            for column, value in enumerate(values):
+                self.patterns_table.setItem(row, column, QTableWidgetItem(str(value)))
+        self.patterns_table.resizeColumnsToContents()
+
+    def _show_source_line(self, line):
+        """Open the editor with the cursor on a program line (0-based)"""
+        self.show_editor()
+        cursor = QTextCursor(self.editor.document().findBlockByNumber(line))
+        self.editor.setTextCursor(cursor)
+        self.editor.centerCursor()
+
+    def _process_memory_blocks(self):
+        """Non-zero memory words inside the data regions of scheduled processes"""
+        if not self.scheduler:
+            return set()
+        blocks = set()
+        for process in self.scheduler.processes:
+            for addr in range(process.data_base, process.data_base + process.data_size):
+                if self.main_memory._data[addr] != 0:
+                    blocks.add(addr)
+        return blocks
+
+    def _program_memory_blocks(self):
+        """Words the program names as [address] (relocated by .data) and every word it has written"""
+        if self.scheduler:
+            return set()
+        blocks = {self.isa.base_register + addr for instruction in self.isa.instructions
+                  for addr in ProgramVerifier.literal_addresses(instruction)}
+        return {addr for addr in blocks | self.isa.initialized_memory if 0 <= addr < self.main_memory._size}
+
+    def _heap_memory_blocks(self):
+        """Every word between the heap start and the current break"""
+        heap = self.isa.heap
+        if not heap:
+            return set()
+        return set(range(heap.start, heap.brk))
+
+    def _uncacheable_memory_blocks(self):
+        """Non-zero words inside the uncacheable ranges"""
+        return {addr for start, end in self.isa.uncacheable_ranges
+                for addr in range(start, min(end + 1, self.main_memory._size))
+                if self.main_memory._data[addr] != 0}
+
     def update_memory_display(self):
         """Update the memory display window with just address and value"""
         if self.memory_window is None or not self.memory_window.isVisible():
@@ -1005,20 +3286,44 @@ class SimulatorGUI(QMainWindow):
                 item.widget().deleteLater()
 
         # Add memory blocks to grid
-        sorted_blocks = sorted(self.used_memory_blocks)
+        heat = self.heatmap.reads.keys() | self.heatmap.writes.keys() if self.memory_heatmap else set()
+        sorted_blocks = sorted(self.used_memory_blocks | self._program_memory_blocks() |
+                               self._process_memory_blocks() | self._heap_memory_blocks() |
+                               self._uncacheable_memory_blocks() | {addr for addr in heat if addr >= 0})
+        hottest = self.heatmap.max_count
         for i, addr in enumerate(sorted_blocks):
             row = i // 3  # 3 columns for wider blocks
             col = i % 3
 
+            # Color blocks by kind, or by owning process with the running one highlighted
+            kind = self.isa.memory_kind(addr)
+            border_color = MEMORY_KIND_COLORS[kind]
+            background = self.theme['background']
+            process = self.scheduler.process_for_address(addr) if self.scheduler else None
+            if process:
+                border_color = PROCESS_COLORS[process.pid % len(PROCESS_COLORS)]
+                if process is self.scheduler.current:
+                    background = self.theme['active']
+                else:
+                    border_color += "66"  # Dim inactive regions
+            heap_block = self.isa.heap.block_at(addr) if self.isa.heap else None
+            if heap_block:
+                border_color = HEAP_COLORS[heap_block[0]]
+            uncacheable = self.isa.is_uncacheable(addr)
+            if uncacheable:
+                border_color = self.theme['uncached']
+            if self.memory_heatmap and hottest:
+                background = self._heat_color(self.heatmap.count(addr) / hottest)
+
             # Create frame for each memory block
             block_frame = QFrame()
             block_frame.setFrameStyle(QFrame.Shape.Box | QFrame.Shadow.Raised)
-            block_frame.setStyleSheet("""
-                QFrame {
-                    background-color: #1e1e1e;
-                    border: 1px solid #666666;
+            block_frame.setStyleSheet(f"""
+                QFrame {{
+                    background-color: {background};
+                    border: 1px solid {border_color};
                     border-radius: 2px;
-                }
+                }}
             """)
 
             block_layout = QVBoxLayout()
@@ -1026,43 +3331,1749 @@ class SimulatorGUI(QMainWindow):
             block_layout.setContentsMargins(4, 4, 4, 4)
 
             # Add address header
-            addr_label = QLabel(f"Address [{addr}]")
+            addr_text = f"Address [{addr}]"
+            if process:
+                addr_text += f" {process.name}"
+            if heap_block:
+                addr_text += f" heap {heap_block[0]}"
+            if uncacheable:
+                addr_text += " uncached"
+            addr_label = QLabel(addr_text)
             addr_label.setFont(QFont("Courier", 9, QFont.Weight.Bold))
-            addr_label.setStyleSheet("color: #00ff00;")
+            addr_label.setStyleSheet(f"color: {self.theme['accent']};")
             addr_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
             block_layout.addWidget(addr_label)
 
+            kind_label = QLabel(kind)
+            kind_label.setFont(QFont("Courier", 8))
+            kind_label.setStyleSheet(f"color: {MEMORY_KIND_COLORS[kind]};")
+            kind_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
+            block_layout.addWidget(kind_label)
+
             # Add memory value
             value = self.main_memory.read(addr)
-            value_label = QLabel(f"Value: {value}")
+            value_label = QLabel(f"Value: {format_word(value, self.settings.memory_format)}")
             value_label.setFont(QFont("Courier", 9))
-            value_label.setStyleSheet("color: #ffffff;")
+            value_label.setStyleSheet(f"color: {self.theme['text']};")
             value_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
             block_layout.addWidget(value_label)
 
+            # Byte layout in address order, showing the effect of endianness
+            byte_values = " ".join(f"{b:02X}" for b in self.isa.word_to_bytes(value))
+            bytes_label = QLabel(f"[{addr * 4}..{addr * 4 + 3}] {byte_values}")
+            bytes_label.setFont(QFont("Courier", 8))
+            bytes_label.setStyleSheet(f"color: {self.theme['muted']};")
+            bytes_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
+            block_layout.addWidget(bytes_label)
+
+            if self.memory_heatmap:
+                heat_label = QLabel(f"r {self.heatmap.reads[addr]}  w {self.heatmap.writes[addr]}")
+                heat_label.setFont(QFont("Courier", 8))
+                heat_label.setStyleSheet(f"color: {self.theme['text']};")
+                heat_label.setAlignment(Qt.AlignmentFlag.AlignCenter)
+                block_layout.addWidget(heat_label)
+
             block_frame.setLayout(block_layout)
+            block_frame.setToolTip(self._memory_tooltip(addr))
             self.memory_grid.addWidget(block_frame, row, col)
 
+        # Program text, with the instruction about to execute marked
+        harvard = self.memory_organization == 'harvard'
+        self.memory_description.setText("Data memory (own port):" if harvard else
+                                        "Unified memory - code and data share one port:")
+        text_base = 0 if harvard else self.isa.text_base
+        self.instruction_memory_title.setText(
+            "Instruction memory (own port):" if harvard else
+            f"Text: words {text_base}-{text_base + max(len(self.isa.instructions) - 1, 0)} of memory"
+            + (f", data at {self.isa.base_register}" if self.isa.base_register and not self.scheduler else ""))
+        # Labels head the instructions they name, as in an assembler listing
+        label_lines = {}
+        for name, index in self.isa.labels.items():
+            label_lines.setdefault(index, []).append(f"  {name}:")
+        # The two ways a branch at the PC can go: the path it will take, and the fetch stage's prediction
+        preview = self.isa.branch_preview()
+        paths = {}
+        if preview:
+            for name, index, taken in (("fall-through", preview.fall_through, False),
+                                       ("target", preview.target, True)):
+                marks = [mark for mark, on in (("next", taken == preview.taken),
+                                               ("predicted", taken == preview.predicted_taken)) if on]
+                paths[index] = f"   <- {name}" + (f" ({', '.join(marks)})" if marks else "")
+        text = []
+        for index, instruction in enumerate(self.isa.instructions):
+            text += label_lines.get(index, [])
+            text.append(f"{'>' if index == self.isa.pc else ' '} [{text_base + index}] {instruction.source}"
+                        f"{paths.get(index, '')}")
+        self.instruction_memory_label.setText("\n".join(text))
+        ports = self.isa.get_memory_port_stats()
+        dram = self.main_memory.dram.get_stats()
+        self.memory_port_label.setText(
+            f"{ports['instruction_fetches']} fetches, {ports['data_accesses']} data accesses, "
+            f"{ports['port_conflicts']} port conflicts ({ports['cycles']} cycles)\n"
+            f"DRAM: {dram['hits']} row hits, {dram['misses']} misses, {dram['conflicts']} conflicts "
+            f"(avg {dram['avg_latency']:.0f}ns), open rows: "
+            + " ".join(f"b{bank}:{'-' if row is None else row}" for bank, row in enumerate(dram['open_rows'])))
+
         # Update window title and description
-        self.memory_window.setWindowTitle("Memory Values")
+        title = f"{'Harvard' if harvard else 'von Neumann'} Memory ({self.endianness}-endian)"
+        if self.scheduler and self.scheduler.current:
+            title += f" - running {self.scheduler.current.name}"
+        self.memory_window.setWindowTitle(title)
+
+        if self.isa.heap:
+            stats = self.isa.heap.get_stats()
+            self.heap_stats_label.setText(
+                f"Heap {stats['heap_start']}-{stats['heap_limit'] - 1}: "
+                f"{stats['live_blocks']} live blocks, {stats['words_in_use']} words in use "
+                f"(peak {stats['peak_words_in_use']})\n"
+                f"mallocs {stats['mallocs']}, frees {stats['frees']}, failed {stats['failed']}, "
+                f"fragmentation {stats['fragmentation']:.1f}%")
+        else:
+            self.heap_stats_label.setText("Heap: not in use")
 
         # Force layout update
         self.memory_window.adjustSize()
 
+    def show_dependencies(self):
+        """Open the data-dependency graph of the loaded program"""
+        if self.dependency_window is None:
+            self.dependency_window = QWidget(None)
+            self.dependency_window.setWindowTitle("Data Dependencies")
+            self.dependency_window.setMinimumSize(500, 400)
+
+            layout = QVBoxLayout()
+            legend = QLabel(" ".join(
+                f'<span style="color: {color};">{kind}</span>'
+                for kind, color in DependencyGraphView.EDGE_COLORS.items()) +
+                " - instructions on the same row could execute in parallel")
+            legend.setFont(QFont("Courier", 9))
+            layout.addWidget(legend)
+
+            self.dependency_view = DependencyGraphView()
+            scroll = QScrollArea()
+            scroll.setWidget(self.dependency_view)
+            layout.addWidget(scroll)
+
+            export_button = QPushButton("Export DOT...")
+            export_button.clicked.connect(self.export_dependency_dot)
+            layout.addWidget(export_button)
+
+            self.dependency_window.setLayout(layout)
+
+        self.dependency_graph = DependencyGraph(self.isa, self.isa.instructions, self.isa.labels)
+        self.dependency_view.set_graph(self.dependency_graph)
+        self.dependency_window.show()
+        self.dependency_window.raise_()
+
+    def export_dependency_dot(self):
+        """Save the dependency graph in Graphviz DOT format"""
+        filename, _ = QFileDialog.getSaveFileName(self.dependency_window, "Export Dependency Graph",
+                                                  "dependencies.dot", "DOT files (*.dot)")
+        if filename:
+            with open(filename, 'w') as f:
+                f.write(self.dependency_graph.to_dot() + '\n')
+            self.status_label.setText(f"Dependency graph saved to {os.path.basename(filename)}")
+
+    def show_statistics(self):
+        """Open the execution statistics window"""
+        if self.stats_window is None:
+            self.stats_window = QWidget(None)
+            self.stats_window.setWindowTitle("Execution Statistics")
+            self.stats_window.setMinimumWidth(400)
+
+            layout = QVBoxLayout()
+
+            # Register usage: reads and writes per register
+            title = QLabel("Register usage")
+            title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(title)
+            self.register_usage_chart = BarChart([("reads", "#00ff00"), ("writes", "#ffaa00")])
+            layout.addWidget(self.register_usage_chart)
+            self.register_usage_label = QLabel("")
+            self.register_usage_label.setFont(QFont("Courier", 9))
+            self.register_usage_label.setWordWrap(True)
+            layout.addWidget(self.register_usage_label)
+
+            # Loops detected from taken backward branches
+            loops_title = QLabel("Loops")
+            loops_title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(loops_title)
+            self.loop_chart = BarChart([("% of cycles", "#00ccff")])
+            layout.addWidget(self.loop_chart)
+            self.loop_label = QLabel("")
+            self.loop_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.loop_label)
+
+            # Calls and cycles per subroutine (JAL target)
+            functions_title = QLabel("Functions")
+            functions_title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(functions_title)
+            self.profile_label = QLabel("")
+            self.profile_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.profile_label)
+
+            # Hits and misses at each cache level split by what the access was for
+            requesters_title = QLabel("Cache accesses by requester")
+            requesters_title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(requesters_title)
+            self.requester_chart = BarChart([("hits", "#00ff00"), ("misses", "#ff4444")], stacked=True,
+                                            label_width=100)
+            layout.addWidget(self.requester_chart)
+            self.requester_label = QLabel("")
+            self.requester_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.requester_label)
+
+            # Each scheduled program's share of the run and of the shared L1
+            programs_title = QLabel("Programs")
+            programs_title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(programs_title)
+            self.programs_label = QLabel("")
+            self.programs_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.programs_label)
+
+            # Counts taken from the simulation event stream
+            events_title = QLabel("Events")
+            events_title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            layout.addWidget(events_title)
+            self.events_label = QLabel("")
+            self.events_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.events_label)
+
+            export_button = QPushButton("Export Image...")
+            export_button.clicked.connect(lambda: self.export_image(self.stats_window, "statistics"))
+            layout.addWidget(export_button)
+
+            self.stats_window.setLayout(layout)
+            self.stats_window.show()
+            self.update_statistics_display()
+        else:
+            self.stats_window.show()
+            self.stats_window.raise_()
+
+    def update_statistics_display(self):
+        """Refresh the statistics window from the ISA's counters"""
+        if self.stats_window is None or not self.stats_window.isVisible():
+            return
+
+        usage = self.isa.get_register_usage()
+        self.register_usage_chart.set_rows([
+            (reg, [counts['reads'], counts['writes']]) for reg, counts in usage['counts'].items()
+        ])
+        self.register_usage_label.setText(
+            f"Most used: {', '.join(usage['most_used']) or 'none'}\n"
+            f"Never used: {', '.join(usage['never_used']) or 'none'}\n"
+            f"Written but never read: {', '.join(usage['written_never_read']) or 'none'}")
+
+        loops = self.isa.get_loop_stats()
+        self.loop_chart.set_rows([(loop['label'][:7], [round(loop['share'])]) for loop in loops])
+        self.loop_label.setText("\n".join(
+            f"{loop['label']} ({loop['start']}-{loop['end']}): {loop['iterations']} iterations, "
+            f"{loop['instructions']} instructions ({loop['share']:.1f}%)"
+            for loop in loops) or "No loops detected yet")
+
+        self.profile_label.setText("\n".join(format_profile(self.profiler.profile())))
+
+        levels = self.isa.get_cache_requester_stats()
+        self.requester_chart.set_rows([
+            (f"{name.replace('Cache', '')} {kind}", [counts['hits'], counts['misses']])
+            for name, requesters in levels for kind, counts in requesters.items()
+            if counts['hits'] + counts['misses']])
+        self.requester_label.setText("\n".join(
+            f"{name}: {format_requesters(requesters)} hits" for name, requesters in levels
+            if format_requesters(requesters)) or "No cache accesses yet")
+
+        self.programs_label.setText("\n".join(format_workload(self.scheduler.get_stats())) if self.scheduler
+                                    else "One program")
+
+        events = self.event_stats.get_stats()
+        self.events_label.setText(
+            f"Retired: {events['retired']}\n"
+            f"Branches: {events['branches']} ({events['taken_rate']:.0%} taken)\n"
+            f"L1 accesses: {events['cache_accesses']} ({events['cache_hit_rate']:.0%} hits), "
+            f"uncached: {events['uncached_accesses']}\n"
+            f"Memory writes: {events['memory_writes']} to {events['words_written']} words\n"
+            f"Exceptions: {events['exceptions']}")
+
+    def reset_statistics(self):
+        """Start measuring here: zero the counters but keep the warmed-up caches and pipeline"""
+        self.undo_stack.record("reset statistics")
+        self.isa.reset_statistics()
+        self.status_label.setText(f"Statistics reset after {self.isa.instruction_count} instructions")
+        self.update_display()
+
+    def show_pipeline(self):
+        """Open the pipeline Gantt chart of the instructions executed so far"""
+        if self.pipeline_window is None:
+            self.pipeline_window = QWidget(None)
+            self.pipeline_window.setWindowTitle("Pipeline Timing")
+            self.pipeline_window.setMinimumSize(700, 400)
+
+            layout = QVBoxLayout()
+            legend = QLabel(" ".join(
+                f'<span style="color: {color};">{stage}</span>'
+                for stage, color in PipelineGanttChart.STAGE_COLORS.items()) +
+                f' <span style="color: {PipelineGanttChart.STALL_COLOR};">-- stall</span>'
+                f' <span style="color: {PipelineGanttChart.WRONG_PATH_COLOR};">x wrong path (squashed)</span>')
+            legend.setFont(QFont("Courier", 9))
+            layout.addWidget(legend)
+
+            self.pipeline_chart = PipelineGanttChart()
+            scroll = QScrollArea()
+            scroll.setWidget(self.pipeline_chart)
+            layout.addWidget(scroll)
+
+            self.pipeline_stats_label = QLabel("")
+            self.pipeline_stats_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.pipeline_stats_label)
+
+            buttons = QHBoxLayout()
+            self.forwarding_button = QPushButton()
+            self.forwarding_button.clicked.connect(self.toggle_forwarding)
+            buttons.addWidget(self.forwarding_button)
+            self.resources_button = QPushButton()
+            self.resources_button.setToolTip("How many ALUs, memory ports and register write ports the pipeline has")
+            self.resources_button.clicked.connect(self.configure_pipeline_resources)
+            buttons.addWidget(self.resources_button)
+            export_button = QPushButton("Export CSV...")
+            export_button.clicked.connect(self.export_pipeline_csv)
+            buttons.addWidget(export_button)
+            image_button = QPushButton("Export Image...")
+            image_button.clicked.connect(lambda: self.export_image(self.pipeline_chart, "pipeline"))
+            buttons.addWidget(image_button)
+            layout.addLayout(buttons)
+
+            self.pipeline_window.setLayout(layout)
+
+        self.pipeline_window.show()
+        self.pipeline_window.raise_()
+        self.update_pipeline_display()
+
+    def update_pipeline_display(self):
+        """Refresh the Gantt chart from the pipeline model"""
+        if self.pipeline_window is None or not self.pipeline_window.isVisible():
+            return
+
+        pipeline = self.isa.pipeline
+        stats = pipeline.get_stats()
+        self.pipeline_chart.set_records(pipeline.records, pipeline.total_cycles, pipeline.wrong_path)
+        self.pipeline_stats_label.setText(
+            f"{stats['instructions']} instructions in {stats['cycles']} cycles (CPI {stats['cpi']:.2f}), "
+            f"{stats['stall_cycles']} stall cycles, {stats['branch_bubbles']} branch bubbles, "
+            f"{stats['squashed']} wrong-path instructions squashed\nStall cycles by cause: " +
+            ", ".join(f"{cause} {cycles}" for cause, cycles in stats['stalls_by_cause'].items()))
+        self.forwarding_button.setText(f"Forwarding: {'on' if self.pipeline_forwarding else 'off'}")
+        self.resources_button.setText(f"Resources: {self.pipeline_resources.describe()}")
+
+    def toggle_forwarding(self):
+        """Switch operand forwarding and restart the program so the timings are consistent"""
+        self.pipeline_forwarding = not self.pipeline_forwarding
+        self.reset_simulation()
+
+    def configure_pipeline_resources(self):
+        """Ask for the ALU, memory port and write port counts and restart the program with them"""
+        current = ", ".join(f"{name}={count}" for name, count in vars(self.pipeline_resources).items()
+                            if count is not None)
+        text, ok = QInputDialog.getText(self.pipeline_window, "Pipeline Resources",
+                                        "alus, memory_ports and write_ports, e.g. alus=1, write_ports=1\n"
+                                        "(leave one out for as many as needed):", QLineEdit.EchoMode.Normal, current)
+        if not ok:
+            return
+        try:
+            self.pipeline_resources = parse_resources(text)
+        except ValueError as e:
+            self.status_label.setText(f"Resources: {e}")
+            return
+        self.reset_simulation()
+        self.status_label.setText(f"Pipeline resources: {self.pipeline_resources.describe()}")
+
+    def export_pipeline_csv(self):
+        """Save per-instruction stage timings as CSV"""
+        filename, _ = QFileDialog.getSaveFileName(self.pipeline_window, "Export Pipeline Timings",
+                                                  "pipeline.csv", "CSV files (*.csv)")
+        if filename:
+            with open(filename, 'w', newline='') as f:
+                f.write(self.isa.pipeline.to_csv())
+            self.status_label.setText(f"Pipeline timings saved to {os.path.basename(filename)}")
+
+    def show_lsq(self):
+        """Open the load/store queue panel"""
+        if self.lsq_window is None:
+            self.lsq_window = QWidget(None)
+            self.lsq_window.setWindowTitle("Load/Store Queue")
+            self.lsq_window.setMinimumSize(700, 350)
+
+            layout = QVBoxLayout()
+            legend = QLabel('<span style="color: #00ff7f;">forwarded from a pending store</span> '
+                            '<span style="color: #ff4444;">memory-ordering violation (replayed)</span>')
+            legend.setFont(QFont("Courier", 9))
+            layout.addWidget(legend)
+
+            self.lsq_view = QTextEdit()
+            self.lsq_view.setReadOnly(True)
+            self.lsq_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.lsq_view)
+
+            self.lsq_stats_label = QLabel("")
+            self.lsq_stats_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.lsq_stats_label)
+
+            self.lsq_window.setLayout(layout)
+
+        self.lsq_window.show()
+        self.lsq_window.raise_()
+        self.update_lsq_display()
+
+    def update_lsq_display(self):
+        """List the loads and stores seen so far with the cycles they dispatched, issued and committed"""
+        if self.lsq_window is None or not self.lsq_window.isVisible():
+            return
+
+        lsq = self.isa.lsq
+        colors = {'forwarded': "#00ff7f", 'violation': "#ff4444"}
+        rows = [f"{'#':>3} {'instruction':<22} {'addr':>5} {'disp':>4} {'addr@':>5} {'exec':>4} {'commit':>6}  outcome"]
+        for op in lsq.ops:
+            text = (f"{op.seq:3d} {op.source[:22]:<22} {op.address:5d} {op.dispatch:4d} {op.address_ready:5d} "
+                    f"{op.execute:4d} {op.commit:6d}  {lsq.describe(op)}")
+            text = text.replace('&', '&amp;').replace('<', '&lt;')
+            rows.append(f'<span style="color: {colors.get(op.outcome, "#ffffff")};">{text}</span>')
+        self.lsq_view.setHtml('<pre>' + '\n'.join(rows) + '</pre>')
+
+        stats = lsq.get_stats()
+        self.lsq_stats_label.setText(
+            f"{stats['loads']} loads: {stats['forwarded']} forwarded, {stats['violations']} ordering violations, "
+            f"{stats['from_memory']} from memory; {stats['stores']} stores")
+
+    def show_predictor(self):
+        """Open the branch predictor panel: the BTB's entries, the return-address stack and their hit rates"""
+        if self.predictor_window is None:
+            self.predictor_window = QWidget(None)
+            self.predictor_window.setWindowTitle("Branch Predictor")
+            self.predictor_window.setMinimumSize(600, 350)
+
+            layout = QVBoxLayout()
+            self.predictor_view = QTextEdit()
+            self.predictor_view.setReadOnly(True)
+            self.predictor_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.predictor_view)
+
+            self.predictor_stats_label = QLabel("")
+            self.predictor_stats_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.predictor_stats_label)
+
+            self.predictor_button = QPushButton()
+            self.predictor_button.setToolTip("Predict with a branch target buffer and return-address stack, or "
+                                             "always not taken (restarts the program)")
+            self.predictor_button.clicked.connect(self.toggle_branch_prediction)
+            layout.addWidget(self.predictor_button)
+
+            self.predictor_window.setLayout(layout)
+
+        self.predictor_window.show()
+        self.predictor_window.raise_()
+        self.update_predictor_display()
+
+    def update_predictor_display(self):
+        """List the BTB's entries and the return-address stack with their hit rates"""
+        if self.predictor_window is None or not self.predictor_window.isVisible():
+            return
+
+        pipeline = self.isa.pipeline
+        predictor = pipeline.predictor
+        self.predictor_button.setText(f"Prediction: {'BTB + RAS' if predictor else 'always not taken'}")
+        if predictor is None:
+            self.predictor_view.setPlainText("Fetch always goes on at PC + 1, so every taken branch and jump "
+                                             "costs the full penalty.\nTurn on the BTB and return-address stack "
+                                             "to see their contents and hit rates.")
+        else:
+            self.predictor_view.setPlainText('\n'.join(format_predictor(predictor, self.isa)))
+        stats = pipeline.get_stats()
+        self.predictor_stats_label.setText(f"{stats['instructions']} instructions in {stats['cycles']} cycles "
+                                           f"(CPI {stats['cpi']:.2f}), {stats['branch_bubbles']} branch bubbles, "
+                                           f"{stats['squashed']} wrong-path instructions squashed")
+
+    def toggle_branch_prediction(self):
+        """Switch between the BTB and RAS and static not-taken prediction and restart so the timings are consistent"""
+        self.branch_prediction = not self.branch_prediction
+        self.reset_simulation()
+
+    def show_scoreboard(self):
+        """Open the scoreboard: instruction, functional unit and register result status at a chosen cycle"""
+        if self.scoreboard_window is None:
+            self.scoreboard_window = QWidget(None)
+            self.scoreboard_window.setWindowTitle("Scoreboard")
+            self.scoreboard_window.setMinimumSize(750, 500)
+
+            layout = QVBoxLayout()
+            self.scoreboard_view = QTextEdit()
+            self.scoreboard_view.setReadOnly(True)
+            self.scoreboard_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.scoreboard_view)
+
+            self.scoreboard_stats_label = QLabel("")
+            self.scoreboard_stats_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.scoreboard_stats_label)
+
+            buttons = QHBoxLayout()
+            for text, step in (("<< Cycle", -1), ("Cycle >>", 1)):
+                button = QPushButton(text)
+                button.clicked.connect(lambda _, s=step: self.step_scoreboard_cycle(s))
+                buttons.addWidget(button)
+            latest_button = QPushButton("Latest")
+            latest_button.setToolTip("Follow the last cycle as the program runs")
+            latest_button.clicked.connect(lambda: self.step_scoreboard_cycle(None))
+            buttons.addWidget(latest_button)
+            layout.addLayout(buttons)
+
+            self.scoreboard_window.setLayout(layout)
+
+        self.scoreboard_window.show()
+        self.scoreboard_window.raise_()
+        self.update_scoreboard_display()
+
+    def step_scoreboard_cycle(self, step):
+        """Show the scoreboard one cycle earlier or later, or (step None) follow the latest cycle again"""
+        last = self.scoreboard.total_cycles
+        if step is None:
+            self.scoreboard_cycle = None
+        else:
+            cycle = last if self.scoreboard_cycle is None else self.scoreboard_cycle
+            self.scoreboard_cycle = min(max(cycle + step, 1), max(last, 1))
+        self.update_scoreboard_display()
+
+    def update_scoreboard_display(self):
+        """Render the scoreboard tables at the chosen cycle"""
+        if self.scoreboard_window is None or not self.scoreboard_window.isVisible():
+            return
+
+        scoreboard = self.scoreboard
+        cycle = self.scoreboard_cycle if self.scoreboard_cycle is not None else scoreboard.total_cycles
+        self.scoreboard_view.setPlainText('\n'.join(format_scoreboard(scoreboard, cycle)))
+        stats = scoreboard.get_stats()
+        self.scoreboard_stats_label.setText(
+            f"{stats['instructions']} instructions in {stats['cycles']} cycles (CPI {stats['cpi']:.2f}); stall "
+            "cycles: " + ", ".join(f"{cause} {count}" for cause, count in stats['stalls'].items()))
+
+    def show_whatif(self):
+        """Open the what-if cache: sliders reshape the L1 and the recorded trace is replayed on every change"""
+        if self.whatif_window is None:
+            self.whatif_window = QWidget(None)
+            self.whatif_window.setWindowTitle("What-If Cache")
+            self.whatif_window.setMinimumSize(600, 300)
+
+            layout = QVBoxLayout()
+            form = QFormLayout()
+            recorded = CacheConfig.of(self.l1_cache)
+            self.whatif_sliders = {}
+            # (field, label, slider range, slider value -> field value); sets and block size go in powers of two
+            for name, label, low, high, value in (("sets", "Sets", 0, 6, lambda v: 2 ** v),
+                                                  ("ways", "Ways", 1, 8, lambda v: v),
+                                                  ("line_size", "Block size", 0, 3, lambda v: 2 ** v)):
+                slider = QSlider(Qt.Orientation.Horizontal)
+                slider.setRange(low, high)
+                current = getattr(recorded, name)
+                slider.setValue(current.bit_length() - 1 if name != "ways" else current)
+                slider.valueChanged.connect(lambda _: self.update_whatif_display())
+                self.whatif_sliders[name] = (slider, value)
+                form.addRow(f"{label}:", slider)
+            self.whatif_policy_combo = QComboBox()
+            self.whatif_policy_combo.addItems(REPLACEMENT_POLICIES)
+            self.whatif_policy_combo.setCurrentText(recorded.replacement_policy)
+            self.whatif_policy_combo.currentIndexChanged.connect(lambda _: self.update_whatif_display())
+            form.addRow("Replacement:", self.whatif_policy_combo)
+            self.whatif_write_combo = QComboBox()
+            self.whatif_write_combo.addItems(WRITE_POLICIES)
+            self.whatif_write_combo.setCurrentText(recorded.write_policy)
+            self.whatif_write_combo.currentIndexChanged.connect(lambda _: self.update_whatif_display())
+            form.addRow("Write policy:", self.whatif_write_combo)
+            layout.addLayout(form)
+
+            self.whatif_label = QLabel("")
+            self.whatif_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.whatif_label)
+
+            replay_button = QPushButton("Replay")
+            replay_button.setToolTip("Replay again with the accesses the program has made since")
+            replay_button.clicked.connect(self.update_whatif_display)
+            layout.addWidget(replay_button)
+
+            self.whatif_window.setLayout(layout)
+
+        self.whatif_window.show()
+        self.whatif_window.raise_()
+        self.update_whatif_display()
+
+    def update_whatif_display(self):
+        """Replay the trace on the L1 the controls describe and on the L1 it ran on, and compare the two"""
+        if self.whatif_window is None or not self.whatif_window.isVisible():
+            return
+
+        recorded, l2 = CacheConfig.of(self.l1_cache), CacheConfig.of(self.l2_cache)
+        what_if = CacheConfig(**{name: value(slider.value()) for name, (slider, value) in self.whatif_sliders.items()},
+                              replacement_policy=self.whatif_policy_combo.currentText(),
+                              write_policy=self.whatif_write_combo.currentText(),
+                              access_time=recorded.access_time)
+        accesses = self.cache_trace.accesses
+        results = [(title, config, replay(accesses, config, l2))
+                   for title, config in (("This L1", recorded), ("What if", what_if))]
+        lines = [f"{len(accesses) + self.cache_trace.dropped} L1 accesses recorded" +
+                 (f" (replaying the first {len(accesses)})" if self.cache_trace.dropped else "") +
+                 ", replayed on empty caches"]
+        for title, config, result in results:
+            l1_stats, l2_stats = result['l1'], result['l2']
+            lines += ["", f"{title}: {config.describe()}",
+                      f"  L1 {l1_stats['hits']} hits, {l1_stats['misses']} misses ({l1_stats['hit_rate']:.1f}%)",
+                      f"  L2 {l2_stats['hits']} hits, {l2_stats['misses']} misses ({l2_stats['hit_rate']:.1f}%)",
+                      f"  AMAT {result['amat']:.2f} ns"]
+        change = results[1][2]['l1']['misses'] - results[0][2]['l1']['misses']
+        lines += ["", f"L1 misses {'+' if change > 0 else ''}{change}, AMAT "
+                      f"{results[1][2]['amat'] - results[0][2]['amat']:+.2f} ns"]
+        self.whatif_label.setText("\n".join(lines))
+
+    def show_sweep(self):
+        """Open the cache sweep: the recorded trace replayed at every setting of one L1 parameter, plotted"""
+        if self.sweep_window is None:
+            self.sweep_window = QWidget(None)
+            self.sweep_window.setWindowTitle("Cache Sweep")
+            self.sweep_window.setMinimumSize(600, 450)
+            layout = QVBoxLayout()
+            row = QHBoxLayout()
+            row.addWidget(QLabel("Vary:"))
+            self.sweep_combo = QComboBox()
+            self.sweep_combo.addItems(list(SWEEPS))
+            self.sweep_combo.currentIndexChanged.connect(lambda _: self.update_sweep_display())
+            row.addWidget(self.sweep_combo)
+            rerun_button = QPushButton("Replay")
+            rerun_button.setToolTip("Sweep again with the accesses the program has made since")
+            rerun_button.clicked.connect(self.update_sweep_display)
+            row.addWidget(rerun_button)
+            row.addStretch()
+            layout.addLayout(row)
+            self.sweep_chart = SweepChart()
+            layout.addWidget(self.sweep_chart)
+            self.sweep_label = QLabel("")
+            self.sweep_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.sweep_label)
+            self.sweep_window.setLayout(layout)
+
+        self.sweep_window.show()
+        self.sweep_window.raise_()
+        self.update_sweep_display()
+
+    def update_sweep_display(self):
+        """Replay the trace on each L1 of the chosen sweep and plot and tabulate their miss rates"""
+        if self.sweep_window is None or not self.sweep_window.isVisible():
+            return
+
+        parameter = self.sweep_combo.currentText() or next(iter(SWEEPS))
+        sweep, values, heading = SWEEPS[parameter]
+        accesses = self.cache_trace.accesses
+        points = sweep(accesses, CacheConfig.of(self.l1_cache), CacheConfig.of(self.l2_cache), values)
+        lines = [f"{len(accesses) + self.cache_trace.dropped} L1 accesses recorded" +
+                 (f" (replaying the first {len(accesses)})" if self.cache_trace.dropped else "") +
+                 ", replayed on empty L1s", ""] + format_sweep(points)
+        marks = []
+        if parameter == 'capacity':
+            # The knee against the working set, both marked where they fall on the curve
+            working_set_words = peak_working_set(accesses)
+            point = knee(points)
+            marks = ([("knee", point.value)] if point and point is not points[-1] else []) + \
+                [("working set", working_set_words)]
+            lines.append(describe_knee(points, working_set_words))
+        self.sweep_chart.set_points(points, heading, marks)
+        self.sweep_label.setText("\n".join(lines))
+
+    def show_evictions(self):
+        """Open the eviction log; an address filters it down to the line holding that word"""
+        if self.evictions_window is None:
+            self.evictions_window = QWidget(None)
+            self.evictions_window.setWindowTitle("Eviction Log")
+            self.evictions_window.setMinimumSize(800, 300)
+            layout = QVBoxLayout()
+            filter_row = QHBoxLayout()
+            filter_row.addWidget(QLabel("Word:"))
+            self.evictions_address = QLineEdit()
+            self.evictions_address.setPlaceholderText("all (or an address, to see why an access to it missed)")
+            self.evictions_address.textChanged.connect(lambda _: self.update_evictions_display())
+            filter_row.addWidget(self.evictions_address)
+            layout.addLayout(filter_row)
+            self.evictions_table = QTableWidget(0, len(EVICTION_COLUMNS))
+            self.evictions_table.setHorizontalHeaderLabels(EVICTION_COLUMNS)
+            self.evictions_table.setFont(QFont("Courier", 9))
+            self.evictions_table.setEditTriggers(QTableWidget.EditTrigger.NoEditTriggers)
+            layout.addWidget(self.evictions_table)
+            self.evictions_label = QLabel("")
+            self.evictions_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.evictions_label)
+            self.evictions_window.setLayout(layout)
+        self.evictions_window.show()
+        self.evictions_window.raise_()
+        self.update_evictions_display()
+
+    def update_evictions_display(self):
+        if self.evictions_window is None or not self.evictions_window.isVisible():
+            return
+        text = self.evictions_address.text().strip()
+        try:
+            address = int(text, 0) if text else None
+        except ValueError:
+            self.evictions_label.setText(f"'{text}' is not an address")
+            return
+        evictions = self.eviction_log.select(address=address)
+        self.evictions_table.setRowCount(len(evictions))
+        for row, eviction in enumerate(evictions):
+            for column, value in enumerate(eviction_row(eviction)):
+                self.evictions_table.setItem(row, column, QTableWidgetItem(value))
+        self.evictions_table.resizeColumnsToContents()
+        if evictions:
+            self.evictions_table.scrollToBottom()
+        total = len(self.eviction_log.evictions) + self.eviction_log.dropped
+        self.evictions_label.setText(
+            (f"{len(evictions)} evictions of the line holding word {address} out of {total}" if address is not None
+             else f"{total} evictions") +
+            (f" ({self.eviction_log.dropped} after the first {len(self.eviction_log.evictions)} not listed)"
+             if self.eviction_log.dropped else ""))
+
+    def _collect_log_record(self, record):
+        """Logger handler: note that the log panel has messages it has not shown yet"""
+        self._log_pending = True
+
+    def show_log(self):
+        """Open the panel of recent log messages"""
+        if self.log_window is None:
+            self.log_window = QWidget(None)
+            self.log_window.setWindowTitle("Log")
+            self.log_window.setMinimumSize(700, 350)
+
+            layout = QVBoxLayout()
+            controls = QHBoxLayout()
+            controls.addWidget(QLabel("Target:"))
+            self.log_target_combo = QComboBox()
+            self.log_target_combo.addItem("all")
+            for target, description in LOG_TARGETS.items():
+                self.log_target_combo.addItem(target)
+                self.log_target_combo.setItemData(self.log_target_combo.count() - 1, description,
+                                                  Qt.ItemDataRole.ToolTipRole)
+            self.log_target_combo.currentIndexChanged.connect(lambda _: self.update_log_display(force=True))
+            controls.addWidget(self.log_target_combo)
+            controls.addWidget(QLabel("Level:"))
+            self.log_level_combo = QComboBox()
+            self.log_level_combo.addItems([level.name for level in LogLevel])
+            self.log_level_combo.currentIndexChanged.connect(lambda _: self.update_log_display(force=True))
+            controls.addWidget(self.log_level_combo)
+            controls.addStretch()
+            layout.addLayout(controls)
+
+            self.log_view = QTextEdit()
+            self.log_view.setReadOnly(True)
+            self.log_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.log_view)
+
+            self.log_window.setLayout(layout)
+
+        self.log_window.show()
+        self.log_window.raise_()
+        self.update_log_display(force=True)
+
+    def update_log_display(self, force=False):
+        """List the recent messages that match the panel's target and minimum level"""
+        if self.log_window is None or not self.log_window.isVisible():
+            return
+        if not (force or self._log_pending):
+            return
+        self._log_pending = False
+
+        targets = [None] + list(LOG_TARGETS)
+        target = targets[self.log_target_combo.currentIndex()]
+        minimum = list(LogLevel)[self.log_level_combo.currentIndex()]
+        colors = {LogLevel.DEBUG: "#00ccff", LogLevel.INFO: "#ffffff",
+                  LogLevel.WARNING: "#ffd700", LogLevel.ERROR: "#ff4444"}
+        rows = []
+        for record in self.logger.recent:
+            if record.level.value < minimum.value:
+                continue
+            if target and record.target != target and not record.target.startswith(target + '.'):
+                continue
+            text = f"{record.level.name:<7} {record.target:<13} {record.message}"
+            text = text.replace('&', '&amp;').replace('<', '&lt;')
+            rows.append(f'<span style="color: {colors[record.level]};">{text}</span>')
+        self.log_view.setHtml('<pre>' + '\n'.join(rows) + '</pre>')
+        self.log_view.verticalScrollBar().setValue(self.log_view.verticalScrollBar().maximum())
+
+    def show_snapshots(self):
+        """Open the memory snapshot window: capture named snapshots and diff them"""
+        if self.snapshot_window is None:
+            self.snapshot_window = QWidget(None)
+            self.snapshot_window.setWindowTitle("Memory Snapshots")
+            self.snapshot_window.setMinimumSize(500, 400)
+
+            layout = QVBoxLayout()
+            controls = QHBoxLayout()
+            take_button = QPushButton("Take Snapshot...")
+            take_button.clicked.connect(self.take_snapshot)
+            controls.addWidget(take_button)
+            controls.addWidget(QLabel("Diff"))
+            self.snapshot_old_combo = QComboBox()
+            self.snapshot_old_combo.currentIndexChanged.connect(lambda _: self.update_snapshot_display())
+            controls.addWidget(self.snapshot_old_combo)
+            controls.addWidget(QLabel("against"))
+            self.snapshot_new_combo = QComboBox()
+            self.snapshot_new_combo.currentIndexChanged.connect(lambda _: self.update_snapshot_display())
+            controls.addWidget(self.snapshot_new_combo)
+            controls.addStretch()
+            layout.addLayout(controls)
+
+            self.snapshot_view = QTextEdit()
+            self.snapshot_view.setReadOnly(True)
+            self.snapshot_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.snapshot_view)
+
+            self.snapshot_window.setLayout(layout)
+            self._refresh_snapshot_names()
+
+        self.snapshot_window.show()
+        self.snapshot_window.raise_()
+        self.update_snapshot_display()
+
+    def take_snapshot(self):
+        name, ok = QInputDialog.getText(self, "Take Snapshot", "Snapshot name (blank for s1, s2, ...):")
+        if not ok:
+            return
+        self.snapshots.isa = self.isa
+        try:
+            snapshot = self.snapshots.take(name.strip() or None)
+        except ValueError as e:
+            self.status_label.setText(f"Snapshot failed - {e}")
+            return
+        self.status_label.setText(f"Snapshot {self.snapshots.describe(snapshot.name)}")
+        if self.snapshot_window is not None:
+            self._refresh_snapshot_names(select=snapshot.name)
+            self.update_snapshot_display()
+
+    def _refresh_snapshot_names(self, select=None):
+        """Fill the diff selectors: any snapshot as the old side, a later one or live memory as the new"""
+        names = list(self.snapshots.snapshots)
+        for combo, choices in ((self.snapshot_old_combo, names), (self.snapshot_new_combo, [CURRENT] + names)):
+            combo.blockSignals(True)
+            combo.clear()
+            combo.addItems(choices)
+            combo.blockSignals(False)
+        if select in names:
+            self.snapshot_old_combo.setCurrentIndex(names.index(select))
+
+    def update_snapshot_display(self):
+        """Show the words that differ between the two selected snapshots"""
+        if self.snapshot_window is None or not self.snapshot_window.isVisible():
+            return
+        names = list(self.snapshots.snapshots)
+        if not names:
+            self.snapshot_view.setPlainText("No snapshots yet. Take one, run the program, then take another "
+                                            "or compare with current memory.")
+            return
+        self.snapshots.isa = self.isa
+        old = names[min(max(self.snapshot_old_combo.currentIndex(), 0), len(names) - 1)]
+        new = ([CURRENT] + names)[min(max(self.snapshot_new_combo.currentIndex(), 0), len(names))]
+        rows = [f"{self.snapshots.describe(old)} -> {self.snapshots.describe(new)}", ""]
+        rows += format_diff(self.snapshots.diff(old, new))
+        self.snapshot_view.setPlainText("\n".join(rows))
+
+    def show_hexdump(self):
+        """Open the hex view: a range of memory words with their hex, decimal and disassembly"""
+        if self.hexdump_window is None:
+            self.hexdump_window = QWidget(None)
+            self.hexdump_window.setWindowTitle("Hex View")
+            self.hexdump_window.setMinimumSize(550, 450)
+
+            layout = QVBoxLayout()
+            controls = QHBoxLayout()
+            controls.addWidget(QLabel("Words"))
+            self.hexdump_range_edit = QLineEdit("0x0000..0x003f")
+            self.hexdump_range_edit.setToolTip("A..B (both included), A COUNT or A; decimal or 0x hex")
+            self.hexdump_range_edit.returnPressed.connect(self.update_hexdump_display)
+            controls.addWidget(self.hexdump_range_edit)
+            show_button = QPushButton("Show")
+            show_button.clicked.connect(self.update_hexdump_display)
+            controls.addWidget(show_button)
+            export_button = QPushButton("Export...")
+            export_button.clicked.connect(self.export_hexdump)
+            controls.addWidget(export_button)
+            controls.addStretch()
+            layout.addLayout(controls)
+
+            self.hexdump_view = QTextEdit()
+            self.hexdump_view.setReadOnly(True)
+            self.hexdump_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.hexdump_view)
+
+            self.hexdump_window.setLayout(layout)
+
+        self.hexdump_window.show()
+        self.hexdump_window.raise_()
+        self.update_hexdump_display()
+
+    def _hexdump_rows(self):
+        """The hex view's rows for the range typed in it; ValueError for a bad range"""
+        text = self.hexdump_range_edit.text().strip() or "0x0000..0x003f"
+        first, last = parse_range(text, len(self.isa.memory.data))
+        return dump_rows(self.isa, first, last)
+
+    def _hexdump_lines(self):
+        return format_dump(self._hexdump_rows())
+
+    def update_hexdump_display(self):
+        """Show the hex view's rows, each in the color of its memory kind"""
+        if self.hexdump_window is None or not self.hexdump_window.isVisible():
+            return
+        try:
+            rows = self._hexdump_rows()
+        except ValueError as e:
+            self.hexdump_view.setPlainText(str(e))
+            return
+        lines = format_dump(rows)
+        html = [lines[0]]
+        for row, line in zip(rows, lines[1:]):
+            line = line.replace('&', '&amp;').replace('<', '&lt;')
+            html.append(f'<span style="color: {MEMORY_KIND_COLORS[row.kind]};">{line}</span>')
+        self.hexdump_view.setHtml('<pre>' + '\n'.join(html) + '</pre>')
+
+    def export_hexdump(self):
+        """Save the hex view's rows as a text file"""
+        try:
+            lines = self._hexdump_lines()
+        except ValueError as e:
+            self.status_label.setText(f"Export failed - {e}")
+            return
+        filename, _ = QFileDialog.getSaveFileName(self.hexdump_window, "Export Hex View", "memory.txt",
+                                                  "Text files (*.txt)")
+        if filename:
+            with open(filename, 'w') as f:
+                f.write("\n".join(lines) + "\n")
+            self.status_label.setText(f"Hex view saved to {os.path.basename(filename)}")
+
+    def save_cache_state_dialog(self):
+        """Save every L1 and L2 line (tag, data, valid, dirty) and each set's LRU order as JSON"""
+        filename, _ = QFileDialog.getSaveFileName(self, "Save Cache State", "cache_state.json",
+                                                  "JSON files (*.json)")
+        if not filename:
+            return
+        try:
+            save_cache_state(filename, self.l1_cache, self.l2_cache)
+        except OSError as e:
+            QMessageBox.warning(self, "Save Cache State", f"Could not save {filename}: {e}")
+            return
+        self.status_label.setText(f"Cache state saved to {os.path.basename(filename)}")
+
+    def load_cache_state_dialog(self):
+        filename, _ = QFileDialog.getOpenFileName(self, "Load Cache State", "tests/cache_states", "JSON files (*.json)")
+        if filename:
+            self.load_cache_state(filename)
+
+    def load_cache_state(self, filename):
+        """Replace the cache contents with a saved state, e.g. to set up a conflict before running"""
+        self.undo_stack.record(f"load cache state {os.path.basename(filename)}")
+        try:
+            load_cache_state(filename, self.l1_cache, self.l2_cache, self.main_memory)
+        except (OSError, ValueError) as e:
+            self.undo_stack.discard_last()
+            self.status_label.setText(f"Cache state not loaded - {e}")
+            return False
+        self.status_label.setText(f"Cache state loaded from {os.path.basename(filename)}")
+        self.update_display()
+        return True
+
+    def show_editor(self):
+        """Open the program editor: edit a loaded program, see assembler errors and warnings as you type"""
+        if self.editor_window is None:
+            self.editor_window = QWidget(None)
+            self.editor_window.setWindowTitle("Program Editor")
+            self.editor_window.setMinimumSize(1200, 600)
+
+            layout = QVBoxLayout()
+            controls = QHBoxLayout()
+            controls.addWidget(QLabel("Program"))
+            self.editor_program_combo = QComboBox()
+            self.editor_program_combo.currentIndexChanged.connect(lambda _: self._load_editor_text())
+            controls.addWidget(self.editor_program_combo)
+            assemble_button = QPushButton("Assemble && Load")
+            assemble_button.clicked.connect(self.assemble_editor_program)
+            controls.addWidget(assemble_button)
+            save_button = QPushButton("Save As...")
+            save_button.clicked.connect(self.save_editor_program)
+            controls.addWidget(save_button)
+            controls.addStretch()
+            layout.addLayout(controls)
+
+            registers = list(self.isa.registers) + list(self.isa.fp_registers) + list(self.isa.control_registers)
+            self.editor = AssemblyEditor([mnemonic(t) for t in InstructionType] + list(PSEUDO_INSTRUCTIONS),
+                                         registers)
+            self.editor.setFont(QFont("Courier", 10))
+            self.editor.setLineWrapMode(QPlainTextEdit.LineWrapMode.NoWrap)
+            self.editor_highlighter = AssemblyHighlighter(self.editor.document(), registers)
+            self.editor.cursorPositionChanged.connect(self._follow_editor_cursor)
+
+            # Beside the text: the loaded program's machine code, and the instruction reference
+            side_tabs = QTabWidget()
+            self.listing_view = QTextEdit()
+            self.listing_view.setReadOnly(True)
+            self.listing_view.setFont(QFont("Courier", 9))
+            self.listing_view.setLineWrapMode(QTextEdit.LineWrapMode.NoWrap)
+            side_tabs.addTab(self.listing_view, "Machine Code")
+
+            # The reference follows the instruction under the cursor
+            self.instruction_reference = {info.mnemonic: info for info in instruction_reference()}
+            reference_tab = QWidget()
+            reference_panel = QVBoxLayout(reference_tab)
+            self.reference_combo = QComboBox()
+            self.reference_combo.addItem("All instructions")
+            self.reference_combo.addItems(list(self.instruction_reference))
+            self.reference_combo.currentIndexChanged.connect(lambda _: self.update_reference_display())
+            reference_panel.addWidget(self.reference_combo)
+            self.reference_view = QTextEdit()
+            self.reference_view.setReadOnly(True)
+            self.reference_view.setFont(QFont("Courier", 9))
+            reference_panel.addWidget(self.reference_view)
+            side_tabs.addTab(reference_tab, "Reference")
+            side_tabs.setMinimumWidth(560)
+
+            editor_row = QHBoxLayout()
+            editor_row.addWidget(self.editor, stretch=1)
+            editor_row.addWidget(side_tabs)
+            layout.addLayout(editor_row, stretch=1)
+
+            # Re-check once typing pauses rather than on every keystroke
+            self.editor_check_timer = QTimer()
+            self.editor_check_timer.setSingleShot(True)
+            self.editor_check_timer.setInterval(300)
+            self.editor_check_timer.timeout.connect(self.check_editor_program)
+            self.editor.textChanged.connect(self.editor_check_timer.start)
+
+            self.editor_diagnostics = QTextEdit()
+            self.editor_diagnostics.setReadOnly(True)
+            self.editor_diagnostics.setFont(QFont("Courier", 9))
+            self.editor_diagnostics.setMaximumHeight(120)
+            layout.addWidget(self.editor_diagnostics)
+
+            self.editor_window.setLayout(layout)
+
+        self._fill_editor_programs()
+        self._load_editor_text()
+        self.update_reference_display()
+        self.editor_window.show()
+        self.editor_window.raise_()
+        self.update_listing_display()
+
+    def _follow_editor_cursor(self):
+        """Show the reference entry of the instruction on the cursor's line"""
+        words = self.editor.textCursor().block().text().split(';')[0].split()
+        name = words[0].upper() if words else ""
+        names = list(self.instruction_reference)
+        if name in names and names.index(name) + 1 != self.reference_combo.currentIndex():
+            self.reference_combo.setCurrentIndex(names.index(name) + 1)
+        self.update_listing_display()
+
+    def update_reference_display(self):
+        """The chosen instruction's reference entry, or one line per instruction for the first choice"""
+        names = list(self.instruction_reference)
+        index = self.reference_combo.currentIndex()
+        if index <= 0:
+            self.reference_view.setPlainText("\n".join(format_isa_list(list(self.instruction_reference.values()))))
+            return
+        info = self.instruction_reference[names[min(index - 1, len(names) - 1)]]
+        self.reference_view.setPlainText("\n".join(format_reference(info)))
+
+    def _edited_code_range(self):
+        """Instruction indices that came from the program open in the editor"""
+        if self.scheduler:
+            process = self.scheduler.processes[self._editor_program_index()]
+            return range(process.code_base, process.code_base + process.code_size)
+        return range(len(self.isa.instructions))
+
+    def update_listing_display(self):
+        """Source line, pc, encoding and disassembly of every loaded instruction
+
+        The instruction at the PC is highlighted in green and the one from the
+        editor's cursor line in grey.
+        """
+        if self.editor_window is None or not self.editor_window.isVisible():
+            return
+        instructions = self.isa.instructions
+        if self._listing[0] is not instructions:
+            self._listing = (instructions, build_listing(instructions, self.isa.labels))
+        rows = self._listing[1]
+
+        cursor_line = self.editor.textCursor().block().blockNumber()
+        edited = self._edited_code_range()
+        text_base = 0 if self.memory_organization == 'harvard' else self.isa.text_base
+        html = [f"{'line':>5} {'pc':>4} {'addr':>5} {'offset':>6}  "
+                f"{'instruction word (op kind reg kind reg flags)':<46}  {'extension':<17}  disassembly"]
+        for row in rows:
+            if row.error:
+                cells = f"error: {row.error}"
+            else:
+                extension = ' '.join(f"{word:08x}" for word in row.words[1:])
+                cells = f"{row.words[0]:08x} {format_fields(row.words[0])}  {extension:<17}  {row.disassembly}"
+            text = f"{row.line_number + 1:5d} {row.pc:4d} {text_base + row.pc:5d} {row.offset:6d}  {cells}"
+            text = text.replace('&', '&amp;').replace('<', '&lt;')
+            if row.pc == self.isa.pc:
+                text = f'<span style="background-color: {self.theme["pc"]};">{text}</span>'
+            elif row.pc in edited and row.line_number == cursor_line:
+                text = f'<span style="background-color: {self.theme["cursor"]};">{text}</span>'
+            elif row.error:
+                text = f'<span style="color: {self.theme["error"]};">{text}</span>'
+            html.append(text)
+        scroll = self.listing_view.verticalScrollBar().value()
+        self.listing_view.setHtml('<pre>' + '\n'.join(html) + '</pre>')
+        self.listing_view.verticalScrollBar().setValue(scroll)
+
+    def _fill_editor_programs(self):
+        self.editor_program_combo.blockSignals(True)
+        self.editor_program_combo.clear()
+        self.editor_program_combo.addItems([name for name, _ in self.programs] or ["untitled"])
+        self.editor_program_combo.blockSignals(False)
+
+    def _editor_program_index(self):
+        return min(max(self.editor_program_combo.currentIndex(), 0), max(len(self.programs) - 1, 0))
+
+    def _load_editor_text(self):
+        lines = self.programs[self._editor_program_index()][1] if self.programs else []
+        self.editor.setPlainText("\n".join(lines))
+        self.check_editor_program()
+
+    def check_editor_program(self):
+        """Assemble the editor text on a scratch machine and mark the lines with problems"""
+        lines = self.editor.toPlainText().split("\n")
+        results = check_source(lines, self.main_memory._size)
+        marks = {}
+        for line, severity, _ in results:
+            if marks.get(line) != 'error':
+                marks[line] = severity
+        self.editor_highlighter.set_diagnostics(marks)
+
+        colors = {'error': "#ff4444", 'warning': "#ffcc00"}
+        rows = []
+        for line, severity, message in results:
+            text = f"Line {line + 1}: {severity}: {message}".replace('&', '&amp;').replace('<', '&lt;')
+            rows.append(f'<span style="color: {colors[severity]};">{text}</span>')
+        self.editor_diagnostics.setHtml('<pre>' + '\n'.join(rows or ["No problems found"]) + '</pre>')
+        return results
+
+    def assemble_editor_program(self):
+        """Replace the selected program with the editor text and restart the machine with it"""
+        lines = self.editor.toPlainText().split("\n")
+        errors = [f"Line {line + 1}: {message}" for line, severity, message in self.check_editor_program()
+                  if severity == 'error']
+        if errors:
+            self.status_label.setText(f"Not loaded - {len(errors)} error{'s' if len(errors) > 1 else ''}")
+            QMessageBox.warning(self.editor_window, "Assembly Errors", "\n".join(errors))
+            return
+
+        index = self._editor_program_index()
+        name = self.programs[index][0] if self.programs else "untitled"
+        self.undo_stack.record(f"edit {name}")
+        programs = list(self.programs) or [(name, [])]
+        programs[index] = (name, lines)
+        self.programs = programs  # A new list, so undo keeps the old one
+        self._restart()
+        self.status_label.setText(f"Loaded edited {name}")
+
+    def save_editor_program(self):
+        index = self._editor_program_index()
+        name = self.programs[index][0] if self.programs else "untitled.txt"
+        if name.endswith('.c'):
+            name = name[:-2] + '.txt'  # The editor holds the compiled assembly, not the C source
+        filename, _ = QFileDialog.getSaveFileName(self.editor_window, "Save Program", name,
+                                                  "Assembly (*.txt *.asm);;All files (*)")
+        if not filename:
+            return
+        with open(filename, 'w') as f:
+            f.write(self.editor.toPlainText().rstrip("\n") + "\n")
+        self.status_label.setText(f"Saved {os.path.basename(filename)}")
+
+    def show_locality_lab(self):
+        """Open the guided comparison of naive and blocked matrix multiplication"""
+        if self.locality_window is None:
+            self.locality_window = QWidget(None)
+            self.locality_window.setWindowTitle("Locality Lab: Matrix Multiply Cache Blocking")
+            self.locality_window.setMinimumSize(700, 450)
+
+            layout = QVBoxLayout()
+            self.locality_view = QTextEdit()
+            self.locality_view.setReadOnly(True)
+            layout.addWidget(self.locality_view)
+
+            buttons = QHBoxLayout()
+            rerun_button = QPushButton("Run Comparison")
+            rerun_button.clicked.connect(self.update_locality_display)
+            buttons.addWidget(rerun_button)
+            for name, path, _ in MATRIX_PROGRAMS:
+                load_button = QPushButton(f"Load {name}")
+                load_button.clicked.connect(lambda _, p=path: self.load_instructions(p))
+                buttons.addWidget(load_button)
+            layout.addLayout(buttons)
+
+            self.locality_window.setLayout(layout)
+
+        self.locality_window.show()
+        self.locality_window.raise_()
+        self.update_locality_display()
+
+    def update_locality_display(self):
+        """Run both matrix programs on fresh caches shaped like the current ones and tabulate them"""
+        results = compare_matrix_programs(l1_size=self.l1_cache._size, l1_assoc=self.l1_cache._associativity,
+                                          l2_size=self.l2_cache._size, l2_assoc=self.l2_cache._associativity)
+        best = max(results, key=lambda r: r['l1_hit_rate'])
+
+        rows = ["<tr><th>Program</th><th>Instructions</th><th>L1 hits</th><th>L1 misses</th>"
+                "<th>L1 hit rate</th><th>L2 misses</th><th>L2 hit rate</th></tr>"]
+        for r in results:
+            color = "#00ff7f" if r is best else "#ff6347"
+            rows.append(f"<tr><td>{r['name']}</td><td align='right'>{r['instructions']}</td>"
+                        f"<td align='right'>{r['l1_hits']}</td><td align='right'>{r['l1_misses']}</td>"
+                        f"<td align='right' style='color: {color};'>{r['l1_hit_rate']:.1f}%</td>"
+                        f"<td align='right'>{r['l2_misses']}</td>"
+                        f"<td align='right'>{r['l2_hit_rate']:.1f}%</td></tr>")
+
+        html = [f"<p>L1 {self.l1_cache._size} words {self.l1_cache._associativity}-way, "
+                f"L2 {self.l2_cache._size} words {self.l2_cache._associativity}-way</p>",
+                "<table cellpadding='4'>" + ''.join(rows) + "</table>"]
+        html.extend(f"<p><b>{r['name']}</b> ({r['program']}): {r['description']}</p>" for r in results)
+        html.append("<ol>" + ''.join(f"<li>{step}</li>" for step in GUIDE) + "</ol>")
+        self.locality_view.setHtml(''.join(html))
+
+    def _machine_config(self):
+        """The current machine as an A/B comparison configuration"""
+        return MachineConfig(self.l1_cache._size, self.l1_cache._associativity, self.l2_cache._size,
+                             self.l2_cache._associativity, self.pipeline_forwarding, self.memory_organization)
+
+    def show_compare(self):
+        """Open the A/B comparison of the loaded program on two configurations"""
+        if self.compare_window is None:
+            self.compare_window = QWidget(None)
+            self.compare_window.setWindowTitle("A/B Compare")
+            self.compare_window.setMinimumSize(640, 520)
+            layout = QVBoxLayout()
+
+            form = QFormLayout()
+            self.compare_inputs = {}
+            for name in ('A', 'B'):
+                self.compare_inputs[name] = QLineEdit()
+                self.compare_inputs[name].setToolTip(
+                    "key=value,... with keys l1_size, l1_assoc, l2_size, l2_assoc, forwarding (on/off) and "
+                    "memory_organization" + ("; empty keys keep A's values" if name == 'B' else
+                                             "; empty keys keep the current machine's values"))
+                form.addRow(f"Configuration {name}:", self.compare_inputs[name])
+            self.compare_inputs['B'].setText("l1_assoc=1")
+            layout.addLayout(form)
+
+            run_button = QPushButton("Run Both")
+            run_button.clicked.connect(self.update_compare_display)
+            layout.addWidget(run_button)
+
+            self.compare_view = QTextEdit()
+            self.compare_view.setReadOnly(True)
+            layout.addWidget(self.compare_view)
+            self.compare_chart = DivergenceChart()
+            layout.addWidget(self.compare_chart)
+            export_button = QPushButton("Export Chart Image...")
+            export_button.clicked.connect(lambda: self.export_image(self.compare_chart, "divergence"))
+            layout.addWidget(export_button)
+            self.compare_window.setLayout(layout)
+
+        self.compare_window.show()
+        self.compare_window.raise_()
+        self.update_compare_display()
+
+    def update_compare_display(self):
+        """Run the first loaded program to completion on both configurations and show where they differ"""
+        if not self.programs:
+            self.compare_view.setPlainText("Load a program to compare configurations.")
+            self.compare_chart.set_samples([])
+            return
+        try:
+            config_a = parse_config(self.compare_inputs['A'].text(), base=self._machine_config())
+            config_b = parse_config(self.compare_inputs['B'].text(), base=config_a)
+        except ValueError as e:
+            self.compare_view.setPlainText(f"Bad configuration: {e}")
+            return
+
+        name, lines = self.programs[0]
+        comparison = LockstepComparison(lines, config_a, config_b)
+        comparison.run()
+        text = '\n'.join(format_comparison(comparison)).replace('&', '&amp;').replace('<', '&lt;')
+        self.compare_view.setHtml(f"<p><b>{name}</b></p><pre>{text}</pre>")
+        self.compare_chart.set_samples(comparison.samples)
+
+    def show_optimizer(self):
+        """Open the peephole optimizer's before/after view of the selected program"""
+        if self.optimizer_window is None:
+            self.optimizer_window = QWidget(None)
+            self.optimizer_window.setWindowTitle("Peephole Optimizer")
+            self.optimizer_window.setMinimumSize(900, 600)
+            layout = QVBoxLayout()
+
+            self.optimizer_summary = QTextEdit()
+            self.optimizer_summary.setReadOnly(True)
+            self.optimizer_summary.setFont(QFont("Courier", 9))
+            self.optimizer_summary.setMaximumHeight(170)
+            layout.addWidget(self.optimizer_summary)
+
+            panes = QHBoxLayout()
+            self.optimizer_views = {}
+            for key, title in (('before', "Unoptimized"), ('after', "Optimized")):
+                column = QVBoxLayout()
+                column.addWidget(QLabel(title))
+                self.optimizer_views[key] = QPlainTextEdit()
+                self.optimizer_views[key].setReadOnly(True)
+                self.optimizer_views[key].setFont(QFont("Courier", 9))
+                self.optimizer_views[key].setLineWrapMode(QPlainTextEdit.LineWrapMode.NoWrap)
+                column.addWidget(self.optimizer_views[key])
+                panes.addLayout(column)
+            layout.addLayout(panes, stretch=1)
+
+            buttons = QHBoxLayout()
+            run_button = QPushButton("Optimize && Compare")
+            run_button.clicked.connect(self.update_optimizer_display)
+            buttons.addWidget(run_button)
+            load_button = QPushButton("Load Optimized")
+            load_button.clicked.connect(self.load_optimized_program)
+            buttons.addWidget(load_button)
+            buttons.addStretch()
+            layout.addLayout(buttons)
+            self.optimizer_window.setLayout(layout)
+
+        self.optimizer_window.show()
+        self.optimizer_window.raise_()
+        self.update_optimizer_display()
+
+    def update_optimizer_display(self):
+        """Optimize the first loaded program and run both versions on fresh machines"""
+        if not self.programs:
+            self.optimization = None
+            self.optimizer_summary.setPlainText("Load a program (or a .c file) to optimize it.")
+            return
+        name, lines = self.programs[0]
+        try:
+            self.optimization = optimize(lines)
+        except ValueError as e:
+            self.optimization = None
+            self.optimizer_summary.setPlainText(f"Cannot optimize {name}: {e}")
+            return
+        rows = compare_optimization(self.optimization)
+        self.optimizer_summary.setPlainText("\n".join([name, ""] + format_optimization(self.optimization, rows)))
+        self.optimizer_views['before'].setPlainText("\n".join(self.optimization.before))
+        self.optimizer_views['after'].setPlainText("\n".join(self.optimization.after))
+
+    def load_optimized_program(self):
+        """Replace the first program with its optimized version and restart"""
+        if not self.programs:
+            return
+        if not self.optimization or self.optimization.before != self.programs[0][1]:
+            self.update_optimizer_display()  # Optimized in another tab, or the program changed since
+            if not self.optimization:
+                return
+        name = self.programs[0][0]
+        self.undo_stack.record(f"optimize {name}")
+        self.programs = [(name, self.optimization.after)] + list(self.programs[1:])
+        self._restart()
+        self.status_label.setText(f"Loaded optimized {name} ({len(self.optimization.rewrites)} rewrites)")
+
+    def show_tutorial(self):
+        """Open the guided walkthrough at its first lesson"""
+        if self.tutorial_window is None:
+            self.tutorial_window = QWidget(self, Qt.WindowType.Tool)  # Stays above the main window
+            self.tutorial_window.setWindowTitle("Tutorial")
+            self.tutorial_window.setMinimumSize(420, 380)
+            layout = QVBoxLayout()
+
+            self.tutorial_title = QLabel("")
+            self.tutorial_title.setFont(QFont("Arial", 12, QFont.Weight.Bold))
+            layout.addWidget(self.tutorial_title)
+            self.tutorial_text = QLabel("")
+            self.tutorial_text.setWordWrap(True)
+            layout.addWidget(self.tutorial_text)
+            self.tutorial_action_button = QPushButton("")
+            self.tutorial_action_button.clicked.connect(self.tutorial_action)
+            layout.addWidget(self.tutorial_action_button)
+
+            layout.addWidget(QLabel("What just happened:"))
+            self.tutorial_explanation = QTextEdit()
+            self.tutorial_explanation.setReadOnly(True)
+            self.tutorial_explanation.setPlaceholderText("Step the program to see each instruction explained.")
+            layout.addWidget(self.tutorial_explanation)
+
+            buttons = QHBoxLayout()
+            self.tutorial_back_button = QPushButton("Back")
+            self.tutorial_back_button.clicked.connect(lambda: self.show_tutorial_lesson(self.tutorial_lesson - 1))
+            buttons.addWidget(self.tutorial_back_button)
+            self.tutorial_progress = QLabel("")
+            buttons.addWidget(self.tutorial_progress, 1, Qt.AlignmentFlag.AlignCenter)
+            self.tutorial_next_button = QPushButton("Next")
+            self.tutorial_next_button.clicked.connect(lambda: self.show_tutorial_lesson(self.tutorial_lesson + 1))
+            buttons.addWidget(self.tutorial_next_button)
+            close_button = QPushButton("Close")
+            close_button.clicked.connect(self.tutorial_window.close)
+            buttons.addWidget(close_button)
+            layout.addLayout(buttons)
+            self.tutorial_window.setLayout(layout)
+
+        self.show_tutorial_lesson(0)
+        self.tutorial_window.show()
+        self.tutorial_window.raise_()
+
+    def show_tutorial_lesson(self, index):
+        self.tutorial_lesson = max(0, min(index, len(LESSONS) - 1))
+        lesson = LESSONS[self.tutorial_lesson]
+        self.tutorial_title.setText(lesson.title)
+        self.tutorial_text.setText(lesson.text)
+        self.tutorial_action_button.setText({'load': "Load the sample program", 'step': "Step"}.get(lesson.action, ""))
+        self.tutorial_action_button.setVisible(lesson.action is not None)
+        self.tutorial_progress.setText(f"{self.tutorial_lesson + 1} of {len(LESSONS)}")
+        self.tutorial_back_button.setEnabled(self.tutorial_lesson > 0)
+        self.tutorial_next_button.setEnabled(self.tutorial_lesson < len(LESSONS) - 1)
+
+        # Point at the panel the lesson is about
+        panels = {'cpu': self.cpu_section, 'registers': self.register_section, 'cache': self.memory_section,
+                  'controls': self.control_section}
+        if lesson.panel in panels:
+            self._highlight_component(panels[lesson.panel], self.theme['accent'], 1500)
+
+    def tutorial_action(self):
+        lesson = LESSONS[self.tutorial_lesson]
+        if lesson.action == 'load':
+            root = os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+            self.load_programs([os.path.join(root, SAMPLE_PROGRAM)])
+            self.tutorial_explanation.setPlainText(
+                f"Loaded {SAMPLE_PROGRAM}: {len(self.isa.instructions)} instructions, PC at {self.isa.pc}. "
+                "Memory and the caches start empty. Press Next.")
+        elif lesson.action == 'step':
+            self.step_execution()
+
+    def show_microcode(self):
+        """Open the control ROM panel for stepping one microinstruction at a time"""
+        if self.microcode_window is None:
+            self.microcode_window = QWidget(None)
+            self.microcode_window.setWindowTitle("Microprogrammed Control Unit")
+            self.microcode_window.setMinimumSize(650, 500)
+
+            layout = QVBoxLayout()
+            self.microcode_instruction_label = QLabel("")
+            self.microcode_instruction_label.setFont(QFont("Courier", 10, QFont.Weight.Bold))
+            layout.addWidget(self.microcode_instruction_label)
+
+            self.control_rom_view = QTextEdit()
+            self.control_rom_view.setReadOnly(True)
+            self.control_rom_view.setFont(QFont("Courier", 9))
+            layout.addWidget(self.control_rom_view)
+
+            self.microcode_stats_label = QLabel("")
+            self.microcode_stats_label.setFont(QFont("Courier", 9))
+            layout.addWidget(self.microcode_stats_label)
+
+            micro_step_button = QPushButton("Micro Step")
+            micro_step_button.clicked.connect(self.micro_step)
+            layout.addWidget(micro_step_button)
+
+            self.microcode_window.setLayout(layout)
+
+        self.microcode_window.show()
+        self.microcode_window.raise_()
+        self.update_microcode_display()
+
+    def micro_step(self):
+        """Execute one microinstruction; the instruction takes effect on its routine's last word"""
+        self.undo_stack.record(f"micro-step at uPC {self.microcode.upc}")
+        completes = self.microcode.current.next == 'fetch'
+        if not self.microcode.step() and not completes:
+            self.status_label.setText("Program Complete")
+        if completes:
+            self.current_instruction = self.isa.pc
+            self.update_display()
+        self.update_microcode_display()
+
+    def update_microcode_display(self):
+        """Redraw the control ROM with the next microinstruction highlighted"""
+        if self.microcode_window is None or not self.microcode_window.isVisible():
+            return
+
+        sequencer = self.microcode
+        instruction = sequencer.instruction if sequencer.current.routine != 'fetch' else None
+        rows = []
+        for word in CONTROL_ROM:
+            # Fill in operands for the routine being executed
+            transfer = word.render(instruction) if word.routine == sequencer.current.routine else word.transfer
+            text = f"{word.address:3d} {word.routine:<12} {transfer:<38} {', '.join(sorted(word.signals))}"
+            text = text.replace('&', '&amp;').replace('<', '&lt;')
+            if word.address == sequencer.upc:
+                text = f'<span style="background-color: {self.theme["pc"]};">{text}</span>'
+            rows.append(text)
+        self.control_rom_view.setHtml('<pre>' + '\n'.join(rows) + '</pre>')
+
+        if instruction is not None:
+            self.microcode_instruction_label.setText(f"IR: {instruction.source}")
+        elif self.isa.pc < len(self.isa.instructions):
+            self.microcode_instruction_label.setText(f"Fetching: {self.isa.instructions[self.isa.pc].source}")
+        stats = sequencer.get_stats()
+        self.microcode_stats_label.setText(
+            f"{stats['micro_cycles']} micro-cycles, {stats['instructions']} instructions "
+            f"({stats['cycles_per_instruction']:.2f} per instruction)")
+
+    def export_vcd(self):
+        """Save the signals sampled so far as a VCD waveform for GTKWave"""
+        filename, _ = QFileDialog.getSaveFileName(self, "Export VCD Waveform", "trace.vcd", "VCD files (*.vcd)")
+        if filename:
+            self.vcd_recorder.save(filename)
+            self.status_label.setText(f"{len(self.vcd_recorder.samples)} cycles saved to {os.path.basename(filename)}")
+
+    def export_image(self, widget, name):
+        """Save a panel or chart as a PNG or SVG image, e.g. for slides"""
+        filename, selected = QFileDialog.getSaveFileName(widget.window(), "Export Image", f"{name}.png", IMAGE_FILTER)
+        if not filename:
+            return
+        if not os.path.splitext(filename)[1]:
+            filename += '.svg' if 'svg' in selected.lower() else '.png'
+        if save_widget_image(widget, filename):
+            self.status_label.setText(f"Image saved to {os.path.basename(filename)}")
+        else:
+            QMessageBox.warning(self, "Export Image", f"Could not save {filename}")
+
+    def save_report(self):
+        """Save an HTML or Markdown report of the run so far: listing, registers, memory, statistics and charts"""
+        filename, selected = QFileDialog.getSaveFileName(self, "Save Report", "report.html",
+                                                         "HTML report (*.html);;Markdown report (*.md)")
+        if not filename:
+            return
+        if not os.path.splitext(filename)[1]:
+            filename += '.md' if 'markdown' in selected.lower() else '.html'
+        name = ', '.join(name for name, _ in self.programs) or "untitled"
+        try:
+            write_report(self.isa, name, filename)
+        except OSError as e:
+            QMessageBox.warning(self, "Save Report", f"Could not save {filename}: {e}")
+            return
+        self.status_label.setText(f"Report saved to {os.path.basename(filename)}")
+
+    def _apply_theme(self):
+        """Switch the window palette and every themed widget to the theme, palette and highlights in the settings"""
+        settings = self.settings
+        self.theme = theme_colors(settings.theme, settings.palette,
+                                  {'hit': settings.hit_color, 'miss': settings.miss_color, 'pc': settings.pc_color})
+        palette = QPalette()
+        if settings.theme == 'dark':
+            for role, color in DARK_PALETTE.items():
+                palette.setColor(getattr(QPalette.ColorRole, role), QColor(color))
+        QApplication.instance().setPalette(palette)
+
+        live = []
+        for widget, template in self._themed_widgets:
+            try:
+                widget.setStyleSheet(Template(template).substitute(self.theme))
+            except RuntimeError:
+                continue  # Deleted along with its panel, e.g. the cache panel after a geometry change
+            live.append((widget, template))
+        self._themed_widgets = live
+
+    def _themed(self, widget, template):
+        """Style a widget from a stylesheet template naming theme colors ($accent, $l1, ...), now and on theme changes"""
+        self._themed_widgets.append((widget, template))
+        widget.setStyleSheet(Template(template).substitute(self.theme))
+
+    def show_settings(self):
+        """Open the settings dialog; saving applies the settings and writes them to the config file"""
+        if self.settings_window is None:
+            self.settings_window = QDialog(self)
+            self.settings_window.setWindowTitle("Settings")
+            layout = QVBoxLayout()
+            self.settings_fields = {}
+
+            def spin(key, low, high, suffix=""):
+                box = QSpinBox()
+                box.setRange(low, high)
+                box.setSuffix(suffix)
+                self.settings_fields[key] = box
+                return box
+
+            def choice(key, options):
+                combo = QComboBox()
+                combo.addItems(list(options))
+                self.settings_fields[key] = combo
+                return combo
+
+            # Cache geometry and policies, one group per level
+            for level in ('l1', 'l2'):
+                form = QFormLayout()
+                title = QLabel(f"{level.upper()} cache")
+                title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+                form.addRow(title)
+                form.addRow("Size (words):", spin(f"{level}.size", 1, 1024))
+                form.addRow("Associativity:", spin(f"{level}.associativity", 1, 64, "-way"))
+                form.addRow("Access time:", spin(f"{level}.access_time", 0, 1000, " ns"))
+                form.addRow("Write policy:", choice(f"{level}.write_policy", WRITE_POLICIES))
+                form.addRow("Replacement:", choice(f"{level}.replacement_policy", REPLACEMENT_POLICIES))
+                layout.addLayout(form)
+
+            form = QFormLayout()
+            title = QLabel("Simulator")
+            title.setFont(QFont("Arial", 10, QFont.Weight.Bold))
+            form.addRow(title)
+            form.addRow("Step speed:", spin("step_speed", *STEP_SPEED_RANGE, " ms"))
+            form.addRow("Memory values:", choice("memory_format", MEMORY_FORMATS))
+            form.addRow("Theme:", choice("theme", THEMES))
+            form.addRow("Palette:", choice("palette", PALETTES))
+            for key, label in zip(HIGHLIGHTS, ("Cache hit color:", "Cache miss color:", "Current PC color:")):
+                self.settings_fields[key] = QLineEdit()
+                self.settings_fields[key].setPlaceholderText("#rrggbb, blank for the palette's")
+                form.addRow(label, self.settings_fields[key])
+            form.addRow("ISA mode:", choice("isa_mode", ISA_MODES))
+            form.addRow("Memory organization:", choice("memory_organization", MEMORY_ORGANIZATIONS))
+            form.addRow("Byte order:", choice("endianness", ENDIANNESSES))
+            form.addRow("Word size:", choice("word_size", [str(bits) for bits in WORD_SIZES]))
+            form.addRow("Branch delay slots:", choice("delay_slots", DELAY_SLOT_MODES))
+            self.settings_fields["hardwired_zero"] = QCheckBox("Hardwired zero register")
+            form.addRow(self.settings_fields["hardwired_zero"])
+            layout.addLayout(form)
+
+            note = QLabel(f"Saved to {CONFIG_PATH}. Changing a cache, the ISA mode, the word size or the delay "
+                          f"slots restarts the program.")
+            note.setWordWrap(True)
+            self._themed(note, "color: $muted;")
+            layout.addWidget(note)
+
+            buttons = QDialogButtonBox(QDialogButtonBox.StandardButton.Save | QDialogButtonBox.StandardButton.Cancel |
+                                       QDialogButtonBox.StandardButton.RestoreDefaults)
+            buttons.accepted.connect(self.save_settings_dialog)
+            buttons.rejected.connect(self.settings_window.reject)
+            buttons.button(QDialogButtonBox.StandardButton.RestoreDefaults).clicked.connect(
+                lambda: self._fill_settings_form(Settings()))
+            layout.addWidget(buttons)
+            self.settings_window.setLayout(layout)
+
+        # Live toggles may have moved away from the saved settings; the dialog shows what is in effect
+        current = Settings.from_dict(self.settings.to_dict())
+        current.step_speed = self.simulation_speed
+        current.endianness = self.endianness
+        current.memory_organization = self.memory_organization
+        current.hardwired_zero = self.hardwired_zero
+        current.word_size = self.word_size
+        current.delay_slots = self.delay_slots
+        self._fill_settings_form(current)
+        self.settings_window.show()
+        self.settings_window.raise_()
+
+    def _fill_settings_form(self, settings):
+        for key, widget in self.settings_fields.items():
+            owner, _, name = key.rpartition('.')
+            value = getattr(getattr(settings, owner) if owner else settings, name)
+            if isinstance(widget, QSpinBox):
+                widget.setValue(value)
+            elif isinstance(widget, QComboBox):
+                widget.setCurrentText(str(value))
+            elif isinstance(widget, QLineEdit):
+                widget.setText(value)
+            else:
+                widget.setChecked(value)
+
+    def _settings_from_form(self):
+        values = {}
+        for key, widget in self.settings_fields.items():
+            if isinstance(widget, QSpinBox):
+                value = widget.value()
+            elif isinstance(widget, QComboBox):
+                value = widget.currentText()
+                value = int(value) if value.isdigit() else value  # The word size is a number
+            elif isinstance(widget, QLineEdit):
+                value = widget.text().strip()
+            else:
+                value = widget.isChecked()
+            owner, _, name = key.rpartition('.')
+            (values.setdefault(owner, {}) if owner else values)[name] = value
+        return Settings.from_dict(dict(self.settings.to_dict(), **values))  # Keeps the saved window layout
+
+    def save_settings_dialog(self):
+        settings = self._settings_from_form()
+        problems = settings.problems()
+        if problems:
+            QMessageBox.warning(self.settings_window, "Settings", "\n".join(problems))
+            return
+        self.apply_settings(settings)
+        try:
+            save_settings(settings)
+        except OSError as e:
+            QMessageBox.warning(self.settings_window, "Settings", f"Applied but not saved: {e}")
+        self.settings_window.accept()
+
+    def apply_settings(self, settings):
+        """Switch the simulator to new settings; a new cache geometry, ISA mode, word size or delay slot mode
+        restarts the program"""
+        old = self.settings
+        self.settings = settings
+        self.update_speed(settings.step_speed)
+        self.endianness = settings.endianness
+        self.memory_organization = settings.memory_organization
+        self.hardwired_zero = settings.hardwired_zero
+        self.word_size = settings.word_size
+        self.delay_slots = settings.delay_slots
+        if (settings.theme, settings.palette, [getattr(settings, name) for name in HIGHLIGHTS]) != \
+                (old.theme, old.palette, [getattr(old, name) for name in HIGHLIGHTS]):
+            self._apply_theme()
+
+        if settings.l1 != old.l1 or settings.l2 != old.l2:
+            # Undo states refer to the old caches and memory, so they cannot come along
+            self.undo_stack.clear()
+            self.isa.disable_write_buffer()
+            self.main_memory, self.l1_cache, self.l2_cache = build_hierarchy(settings, self.logger)
+            self.memory_section = self.create_memory_section()  # Laid out for the cache geometry
+            self.cache_scroll.setWidget(self.memory_section)
+            self._restart()
+            self.status_label.setText(f"Settings applied: L1 {settings.l1.size} words {settings.l1.associativity}-way, "
+                                      f"L2 {settings.l2.size} words {settings.l2.associativity}-way")
+        elif (settings.isa_mode != old.isa_mode or settings.word_size != self.isa.word_bits or
+              settings.delay_slots != self.isa.delay_slot_mode):
+            self.undo_stack.record("settings")
+            self._restart()
+            self.status_label.setText(f"Settings applied: {settings.isa_mode} ISA mode, "
+                                      f"{settings.word_size}-bit words, delay slots {settings.delay_slots}")
+        else:
+            self.isa.set_endianness(self.endianness)
+            self.isa.set_memory_organization(self.memory_organization)
+            self.isa.set_zero_register(self.hardwired_zero)
+            self.status_label.setText("Settings applied")
+            self.update_display()
+        self.speed_slider.setValue(settings.step_speed)
+        self._update_setting_buttons()
+
 def main():
-    print("Starting main application...")
-    app = QApplication(sys.argv)
-    print("Created QApplication...")
+    parser = argparse.ArgumentParser(description="Cache and CPU simulator")
+    parser.add_argument('programs', nargs='*', default=['tests/test_program.txt'],
+                        help="Program files; several run round-robin (default: tests/test_program.txt)")
+    parser.add_argument('--tab', action='append', default=[], metavar='PROGRAM',
+                        help="Also open PROGRAM in a tab of its own (repeatable)")
+    parser.add_argument('--cache-state', metavar='FILE',
+                        help="Load L1 and L2 contents saved with \"Save Cache State...\" before the first step")
+    parser.add_argument('--list-isa', action='store_true',
+                        help="Print every instruction with its opcode, operand forms and summary, and exit")
+    add_logging_arguments(parser)
+    args = parser.parse_args()
+    if args.list_isa:
+        print("\n".join(format_isa_list(instruction_reference())))
+        return
+    configure_logging(args)
+
+    logger = Logger()
+    logger.log(LogLevel.DEBUG, "Starting main application...")
+    app = QApplication(sys.argv[:1])
+    logger.log(LogLevel.DEBUG, "Created QApplication...")
     window = SimulatorGUI()
-    print("Created main window...")
+    logger.log(LogLevel.DEBUG, "Created main window...")
 
-    # Get test file from command line or use default
-    test_file = sys.argv[1] if len(sys.argv) > 1 else 'tests/test_program.txt'
-    window.load_instructions(test_file)
-    print(f"Loaded instructions from {test_file}...")
+    window.load_programs(args.programs)
+    logger.log(LogLevel.INFO, f"Loaded instructions from {', '.join(args.programs)}...", target='asm.load')
+    for program in args.tab:
+        window.new_session([program])
+    window.switch_session(0)
+    if args.cache_state and not window.load_cache_state(args.cache_state):
+        logger.log(LogLevel.ERROR, window.status_label.text())
 
     window.show()
-    print("Showing window...")
+    logger.log(LogLevel.DEBUG, "Showing window...")
     sys.exit(app.exec())
 
 if __name__ == '__main__':
@@ -0,0 +1,185 @@
+from typing import Dict, List, Tuple
+
+from utils.logger import Logger, LogLevel
+
+class HeapCorruptionError(Exception):
+    """The free list no longer describes the heap: the program wrote over a freed block's link"""
+
+class HeapAllocator:
+    """First-fit free-list allocator whose metadata lives in simulated memory
+
+    Every block starts with a one-word header holding its payload size in
+    words; the pointer handed to the program is the address just after it.
+    Free blocks store the address of the next free block in their first
+    payload word, keeping an address-ordered free list so neighbours can be
+    coalesced on free. The heap grows upwards from `start` with sbrk until
+    it reaches `limit`. The links live in memory the program can write, so
+    walks of the free list check that every link stays in the heap and never
+    comes back to a block already visited.
+    """
+
+    HEADER_WORDS = 1
+    SBRK_FAILED = -1  # sbrk's result when the heap cannot grow; never an address
+    MIN_SPLIT = 2  # Smallest remainder worth splitting off (header + one word)
+
+    def __init__(self, read_word, write_word, start: int = 512, limit: int = 768):
+        self._read = read_word
+        self._write = write_word
+        self.start = start
+        self.limit = limit
+        self.brk = start
+        self.free_head = 0  # 0 terminates the free list
+        self.allocated: Dict[int, int] = {}  # pointer -> payload size
+        self.logger = Logger()
+        self.stats = {
+            'mallocs': 0,
+            'frees': 0,
+            'failed': 0,
+            'sbrk_calls': 0,
+            'words_in_use': 0,
+            'peak_words_in_use': 0
+        }
+
+    def sbrk(self, words: int) -> int:
+        """Grow the heap by `words`; returns the old break or SBRK_FAILED if out of memory"""
+        self.stats['sbrk_calls'] += 1
+        if words < 0 or self.brk + words > self.limit:
+            return self.SBRK_FAILED
+        old_brk = self.brk
+        self.brk += words
+        return old_brk
+
+    def _free_blocks(self, check: bool = True) -> List[Tuple[int, int]]:
+        """Walk the free list, returning (pointer, size) pairs in address order
+
+        A link outside the heap or back to a visited block raises
+        HeapCorruptionError, or with check off ends the walk there.
+        """
+        blocks = []
+        visited = set()
+        ptr = self.free_head
+        while ptr:
+            problem = ("points outside the heap" if not self.start + self.HEADER_WORDS <= ptr < self.brk
+                       else "loops back" if ptr in visited else None)
+            if problem:
+                if check:
+                    raise HeapCorruptionError(f"free list {problem} at {ptr}")
+                break
+            visited.add(ptr)
+            blocks.append((ptr, self._read(ptr - self.HEADER_WORDS)))
+            ptr = self._read(ptr)
+        return blocks
+
+    def _set_next(self, prev: int, ptr: int) -> None:
+        if prev:
+            self._write(prev, ptr)
+        else:
+            self.free_head = ptr
+
+    def malloc(self, words: int) -> int:
+        """Allocate `words` words; returns a pointer or 0 on failure"""
+        if words <= 0:
+            self.stats['failed'] += 1
+            return 0
+
+        prev = 0
+        for ptr, size in self._free_blocks():
+            if size >= words:
+                next_ptr = self._read(ptr)
+                if size - words >= self.MIN_SPLIT:
+                    # Split: the tail stays on the free list
+                    rest = ptr + words + self.HEADER_WORDS
+                    self._write(rest - self.HEADER_WORDS, size - words - self.HEADER_WORDS)
+                    self._write(rest, next_ptr)
+                    self._write(ptr - self.HEADER_WORDS, words)
+                    self._set_next(prev, rest)
+                    size = words
+                else:
+                    self._set_next(prev, next_ptr)
+                return self._mark_allocated(ptr, size)
+            prev = ptr
+
+        # No free block fits: extend the heap
+        base = self.sbrk(words + self.HEADER_WORDS)
+        if base == self.SBRK_FAILED:
+            self.stats['failed'] += 1
+            self.logger.log(LogLevel.WARNING, f"malloc({words}) failed: heap exhausted")
+            return 0
+        self._write(base, words)
+        return self._mark_allocated(base + self.HEADER_WORDS, words)
+
+    def _mark_allocated(self, ptr: int, size: int) -> int:
+        self.allocated[ptr] = size
+        self.stats['mallocs'] += 1
+        self.stats['words_in_use'] += size
+        self.stats['peak_words_in_use'] = max(self.stats['peak_words_in_use'], self.stats['words_in_use'])
+        self.logger.log(LogLevel.INFO, f"malloc: {size} words at {ptr}")
+        return ptr
+
+    def free(self, ptr: int) -> bool:
+        """Return a block to the free list; returns False for invalid pointers"""
+        if ptr not in self.allocated:
+            self.logger.log(LogLevel.WARNING, f"free({ptr}): not an allocated block")
+            return False
+
+        # Insert in address order
+        prev = 0
+        for block, _ in self._free_blocks():
+            if block > ptr:
+                break
+            prev = block
+        next_ptr = self._read(prev) if prev else self.free_head
+
+        size = self.allocated.pop(ptr)
+        self.stats['frees'] += 1
+        self.stats['words_in_use'] -= size
+        self._write(ptr, next_ptr)
+        self._set_next(prev, ptr)
+
+        # Coalesce with the following block, then with the preceding one
+        if next_ptr and ptr + size + self.HEADER_WORDS == next_ptr:
+            size += self.HEADER_WORDS + self._read(next_ptr - self.HEADER_WORDS)
+            self._write(ptr - self.HEADER_WORDS, size)
+            self._write(ptr, self._read(next_ptr))
+        if prev:
+            prev_size = self._read(prev - self.HEADER_WORDS)
+            if prev + prev_size + self.HEADER_WORDS == ptr:
+                self._write(prev - self.HEADER_WORDS, prev_size + self.HEADER_WORDS + size)
+                self._write(prev, self._read(ptr))
+
+        self.logger.log(LogLevel.INFO, f"free: {ptr}")
+        return True
+
+    def block_at(self, address: int):
+        """Classify a heap address as ('allocated'|'free'|'header', pointer) or None"""
+        if not self.start <= address < self.brk:
+            return None
+        for ptr, size in self.allocated.items():
+            if address == ptr - self.HEADER_WORDS:
+                return ('header', ptr)
+            if ptr <= address < ptr + size:
+                return ('allocated', ptr)
+        for ptr, size in self._free_blocks(check=False):
+            if address == ptr - self.HEADER_WORDS:
+                return ('header', ptr)
+            if ptr <= address < ptr + size:
+                return ('free', ptr)
+        return None
+
+    def get_stats(self):
+        """Return allocation statistics including current fragmentation"""
+        free_blocks = self._free_blocks(check=False)
+        free_words = sum(size for _, size in free_blocks)
+        largest = max((size for _, size in free_blocks), default=0)
+        stats = dict(self.stats)
+        stats.update({
+            'heap_start': self.start,
+            'heap_break': self.brk,
+            'heap_limit': self.limit,
+            'live_blocks': len(self.allocated),
+            'free_blocks': len(free_blocks),
+            'free_words': free_words,
+            # Share of free memory unusable for a request of the full free size
+            'fragmentation': (1 - largest / free_words) * 100 if free_words else 0.0
+        })
+        return stats
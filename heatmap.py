@@ -0,0 +1,156 @@
+import argparse
+import csv
+import sys
+from collections import Counter
+from dataclasses import dataclass
+from typing import List, Tuple
+
+from events import Event, SimEvent
+from isa import SimpleISA, symbol_for
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+
+MAX_SAMPLES = 50000  # Accesses kept for the timeline; counting goes on after that
+
+@dataclass
+class Access:
+    """One data access: when it happened, the word and whether it was a read ('r') or a write ('w')"""
+    cycle: int
+    address: int
+    access: str
+    pc: int
+
+class AccessHeatmap:
+    """Reads and writes per memory word, and each access in order with its cycle, from the ISA's retired instructions
+
+    Cycles are the pipeline model's when the ISA has one and one per
+    instruction otherwise. Instruction fetches are not counted. Like the
+    core's own counters, the counts start over when its statistics are
+    reset.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED, SimEvent.STATS_RESET])
+
+    def reset(self) -> None:
+        self.reads: Counter = Counter()
+        self.writes: Counter = Counter()
+        self.samples: List[Access] = []
+        self.dropped = 0  # Accesses past MAX_SAMPLES, counted but not on the timeline
+
+    def on_event(self, event: Event) -> None:
+        if event.kind == SimEvent.STATS_RESET:
+            self.reset()
+            return
+        cycle = self.isa.pipeline.total_cycles if self.isa.pipeline else event.step
+        for access, address in self.isa.step_accesses:
+            (self.reads if access == 'r' else self.writes)[address] += 1
+            if len(self.samples) < MAX_SAMPLES:
+                self.samples.append(Access(cycle, address, access, event.pc))
+            else:
+                self.dropped += 1
+
+    def count(self, address: int) -> int:
+        return self.reads[address] + self.writes[address]
+
+    @property
+    def max_count(self) -> int:
+        """Accesses to the most used word, the top of the heat scale"""
+        return max((self.count(address) for address in self.reads.keys() | self.writes.keys()), default=0)
+
+    def hottest(self, limit: int = 10) -> List[Tuple[int, int, int]]:
+        """(address, reads, writes) of the most accessed words, most first (lower address on ties)"""
+        addresses = sorted(self.reads.keys() | self.writes.keys(), key=lambda address: (-self.count(address), address))
+        return [(address, self.reads[address], self.writes[address]) for address in addresses[:limit]]
+
+def format_heatmap(heatmap: AccessHeatmap, limit: int = 10) -> List[str]:
+    """The most accessed words as a table, with a bar for each one's share of the hottest word's count"""
+    rows = heatmap.hottest(limit)
+    if not rows:
+        return ["No data accesses yet"]
+    top = heatmap.max_count
+    lines = [f"{'address':>7} {'reads':>6} {'writes':>6} {'total':>6}  heat"]
+    for address, reads, writes in rows:
+        lines.append(f"{address:>7} {reads:>6} {writes:>6} {reads + writes:>6}  "
+                     f"{'#' * max(1, round(20 * (reads + writes) / top))}")
+    return lines
+
+def format_timeline(samples: List[Access], width: int = 64, height: int = 16) -> List[str]:
+    """An address (rows, lowest at the bottom) by cycle (columns) scatter plot in text
+
+    Each cell covers a range of addresses and cycles: 'r' if only reads
+    fell in it, 'w' if only writes, '*' if both. A stride shows as a
+    diagonal, a loop over one array as a repeated ramp.
+    """
+    if not samples:
+        return ["No data accesses yet"]
+    first, last = min(s.address for s in samples), max(s.address for s in samples)
+    start, end = samples[0].cycle, samples[-1].cycle
+    rows = min(height, last - first + 1)
+    columns = min(width, end - start + 1)
+    cells = [[set() for _ in range(columns)] for _ in range(rows)]
+    for sample in samples:
+        row = (sample.address - first) * rows // (last - first + 1)
+        column = (sample.cycle - start) * columns // (end - start + 1)
+        cells[row][column].add(sample.access)
+    label_width = len(str(last))
+    lines = []
+    for row in reversed(range(rows)):
+        low = first + row * (last - first + 1) // rows
+        marks = ''.join('*' if len(cell) == 2 else next(iter(cell)) if cell else '.' for cell in cells[row])
+        lines.append(f"{low:>{label_width}} |{marks}")
+    lines.append(f"{'':>{label_width}} +{'-' * columns}")
+    lines.append(f"{'':>{label_width}}  cycle {start}{f'cycle {end}':>{max(columns - 6 - len(str(start)), 0)}}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program to halt and show which memory words it read and "
+                                                 "wrote most, and when (an address vs cycle plot)")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--top', type=int, default=10, help="Most accessed words to list (default 10)")
+    parser.add_argument('--timeline', action='store_true', help="Also plot addresses against cycles")
+    parser.add_argument('--csv', metavar='FILE', help="Write every access as cycle,address,access,pc rows")
+    parser.add_argument('--no-pipeline', action='store_true',
+                        help="Count one cycle per instruction instead of the pipeline model's cycles")
+    args = parser.parse_args()
+
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    if not args.no_pipeline:
+        isa.pipeline = PipelineModel(isa)
+    heatmap = AccessHeatmap(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The heatmap, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    print('\n'.join(format_heatmap(heatmap, args.top)))
+    if args.timeline:
+        print()
+        print('\n'.join(format_timeline(heatmap.samples)))
+    if heatmap.dropped:
+        print(f"(the timeline keeps the first {MAX_SAMPLES} accesses; {heatmap.dropped} more were only counted)")
+    if args.csv:
+        with open(args.csv, 'w', newline='') as f:
+            writer = csv.writer(f)
+            writer.writerow(['cycle', 'address', 'access', 'pc'])
+            for sample in heatmap.samples:
+                writer.writerow([sample.cycle, sample.address, sample.access,
+                                 symbol_for(sample.pc, isa.labels) or sample.pc])
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
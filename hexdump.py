@@ -0,0 +1,76 @@
+import re
+import struct
+from dataclasses import dataclass
+from typing import List, Tuple
+
+from encoding import WORD_MASK, decode_instruction
+from isa import SimpleISA, format_instruction
+
+DEFAULT_WORDS = 16  # Words dumped when a range gives only its start
+
+@dataclass
+class DumpRow:
+    """One memory word: its address, value as a 32-bit word, what it disassembles to and its memory_kind"""
+    address: int
+    value: int
+    word: int
+    disassembly: str
+    kind: str = 'data'
+
+def parse_range(text: str, memory_words: int) -> Tuple[int, int]:
+    """(first, last) word addresses of 'A..B' (both included), 'A COUNT' or 'A'; decimal or 0x hex
+
+    The range is clipped to memory; ValueError if nothing of it is inside.
+    """
+    match = re.fullmatch(r'\s*(\w+)\s*(?:\.\.\s*(\w+)|\s+(\w+))?\s*', text)
+    if not match:
+        raise ValueError(f"'{text.strip()}' is not a range (expected A..B, A COUNT or A)")
+    try:
+        first = int(match.group(1), 0)
+        if match.group(2):
+            last = int(match.group(2), 0)
+        else:
+            last = first + (int(match.group(3), 0) if match.group(3) else DEFAULT_WORDS) - 1
+    except ValueError:
+        raise ValueError(f"'{text.strip()}' is not a range: addresses are decimal or 0x hex") from None
+    if last < first:
+        raise ValueError(f"range {text.strip()} ends before it starts")
+    if first >= memory_words or last < 0:
+        raise ValueError(f"range {text.strip()} is outside memory (0-{memory_words - 1})")
+    return max(first, 0), min(last, memory_words - 1)
+
+def _word(value) -> int:
+    """A memory value as the 32-bit word it would be stored as; floats as their IEEE 754 bits"""
+    if isinstance(value, float):
+        return struct.unpack('>I', struct.pack('>f', value))[0]
+    return value & WORD_MASK
+
+def dump_rows(isa: SimpleISA, first: int, last: int) -> List[DumpRow]:
+    """Rows for memory words first..last
+
+    A word that holds an instruction of the program (von Neumann memory)
+    shows that instruction; the value is whatever data memory has there,
+    since instructions are not stored as words. Any other word shows what
+    it decodes to in the instruction encoding (see encoding.py), if
+    anything, in parentheses.
+    """
+    data = isa.memory.data
+    rows = []
+    for address in range(first, last + 1):
+        value = data[address]
+        if isa.memory_kind(address) == 'instruction':
+            disassembly = format_instruction(isa.instructions[address - isa.text_base])
+        else:
+            try:
+                words = [_word(v) for v in data[address:address + 3]]
+                disassembly = f"({format_instruction(decode_instruction(words)[0])})"
+            except ValueError:
+                disassembly = ''
+        rows.append(DumpRow(address, value, _word(value), disassembly, isa.memory_kind(address)))
+    return rows
+
+def format_dump(rows: List[DumpRow]) -> List[str]:
+    lines = [f"{'address':<8} {'hex':<10} {'decimal':>12}  disassembly"]
+    for row in rows:
+        lines.append(f"0x{row.address:04x}   0x{row.word:08x} {row.value:>12}  {row.disassembly}".rstrip())
+    return lines
@@ -0,0 +1,100 @@
+import copy
+from typing import Any, Callable, Dict, List, Optional, Tuple
+
+from utils.logger import Logger
+
+# Undo entries kept; older ones are dropped first
+UNDO_LIMIT = 200
+
+class MachineState:
+    """A restorable copy of a set of simulator objects
+
+    Each object's attributes are deep-copied with one shared memo that maps
+    every captured object (and the shared logger, event buses and anything
+    passed as shared) to itself, so references between them - L1 to L2,
+    the pipeline to its ISA - survive as references. Restoring writes the
+    copies back into the same objects, so windows holding on to a cache or
+    memory keep seeing the live state.
+    """
+
+    def __init__(self, objects: List[Any], shared: Tuple = ()):
+        self.objects = [obj for obj in objects if obj is not None]
+        self._shared = [Logger()] + list(shared)
+        memo = self._memo()
+        self._states = [copy.deepcopy(obj.__dict__, memo) for obj in self.objects]
+
+    def _memo(self) -> Dict[int, Any]:
+        memo = {id(obj): obj for obj in self.objects + self._shared}
+        for obj in self.objects:
+            events = getattr(obj, 'events', None)
+            if events is not None:
+                memo[id(events)] = events  # Keep subscribers rather than copying them
+        return memo
+
+    def restore(self) -> None:
+        # Copy again so the same state can be restored more than once (undo, redo, undo)
+        memo = self._memo()
+        states = [copy.deepcopy(state, memo) for state in self._states]
+        for obj, state in zip(self.objects, states):
+            obj.__dict__.clear()
+            obj.__dict__.update(state)
+
+class UndoStack:
+    """Undo and redo of actions recorded as (description, state before the action)
+
+    capture() returns an opaque state and restore(state) puts it back; the
+    stack does not care whether a state is a MachineState, editor text or
+    both. Recording a new action clears the redo list.
+    """
+
+    def __init__(self, capture: Callable[[], Any], restore: Callable[[Any], None], limit: int = UNDO_LIMIT):
+        self._capture = capture
+        self._restore = restore
+        self.limit = limit
+        self._undo: List[Tuple[str, Any]] = []
+        self._redo: List[Tuple[str, Any]] = []
+
+    def record(self, description: str) -> None:
+        """Call before performing an action so it can be undone"""
+        self._undo.append((description, self._capture()))
+        if len(self._undo) > self.limit:
+            del self._undo[0]
+        self._redo.clear()
+
+    def discard_last(self) -> None:
+        """Forget the most recent record, for an action that turned out to change nothing"""
+        if self._undo:
+            self._undo.pop()
+
+    def undo(self) -> Optional[str]:
+        """Restore the state before the last action; returns its description, or None if there is nothing to undo"""
+        if not self._undo:
+            return None
+        description, state = self._undo.pop()
+        self._redo.append((description, self._capture()))
+        self._restore(state)
+        return description
+
+    def redo(self) -> Optional[str]:
+        if not self._redo:
+            return None
+        description, state = self._redo.pop()
+        self._undo.append((description, self._capture()))
+        self._restore(state)
+        return description
+
+    def can_undo(self) -> bool:
+        return bool(self._undo)
+
+    def can_redo(self) -> bool:
+        return bool(self._redo)
+
+    def undo_description(self) -> Optional[str]:
+        return self._undo[-1][0] if self._undo else None
+
+    def redo_description(self) -> Optional[str]:
+        return self._redo[-1][0] if self._redo else None
+
+    def clear(self) -> None:
+        self._undo.clear()
+        self._redo.clear()
@@ -1,15 +1,23 @@
-from typing import Dict, List, Optional, Tuple
+from typing import Callable, Dict, List, Optional, Tuple, Union
 from dataclasses import dataclass
-from enum import Enum, auto
+from enum import Enum, IntEnum, auto
 from time import time
 import logging
+import math
+import re
+import struct
 
 # Import existing utilities
 import sys
 sys.path.append('..')
 from utils.logger import Logger, LogLevel
-from memory import Memory
-from cache.cache import Cache
+from memory import Memory, DRAMModel
+from cache.cache import Cache, format_read_write, format_requesters
+from amat import amat_levels, compute_amat, formula_terms
+from cache.write_buffer import WriteBuffer
+from heap import HeapAllocator, HeapCorruptionError
+from events import EventBus, SimEvent
+from compiler.exprline import translate_line_to_instructions
 
 # Configure logging
 logging.basicConfig(level=logging.INFO, format='%(asctime)s [%(levelname)s] %(message)s')
@@ -38,6 +46,241 @@ class InstructionType(Enum):
     HALT = auto()   # Stop execution
     PRINT_CACHE = auto()  # Print cache state
     PRINT_REG = auto()    # Print register state
+    # Floating-point coprocessor (single precision)
+    MOV_S = auto()    # Move float between FP registers or from immediate
+    LOAD_S = auto()   # Load float bit pattern from memory into FP register
+    STORE_S = auto()  # Store FP register bit pattern to memory
+    ADD_S = auto()    # Float add
+    SUB_S = auto()    # Float subtract
+    MUL_S = auto()    # Float multiply
+    DIV_S = auto()    # Float divide
+    CVT_S_W = auto()  # Convert integer register to float
+    CVT_W_S = auto()  # Convert float to integer register (truncate)
+    C_EQ_S = auto()   # Set FP condition if equal
+    C_LT_S = auto()   # Set FP condition if less than
+    C_LE_S = auto()   # Set FP condition if less or equal
+    BC1T = auto()     # Branch if FP condition true
+    BC1F = auto()     # Branch if FP condition false
+    # Branch on condition flags (ARM flags mode only)
+    BEQ = auto()      # Branch if equal (Z set)
+    BNE = auto()      # Branch if not equal (Z clear)
+    BMI = auto()      # Branch if negative (N set)
+    BPL = auto()      # Branch if positive or zero (N clear)
+    BCS = auto()      # Branch if carry set (unsigned >=)
+    BCC = auto()      # Branch if carry clear (unsigned <)
+    BVS = auto()      # Branch if overflow set
+    BVC = auto()      # Branch if overflow clear
+    BHI = auto()      # Branch if unsigned higher (C set and Z clear)
+    BLS = auto()      # Branch if unsigned lower or same (C clear or Z set)
+    BGE = auto()      # Branch if signed >= (N == V)
+    BLT = auto()      # Branch if signed < (N != V)
+    BGT = auto()      # Branch if signed > (Z clear and N == V)
+    BLE = auto()      # Branch if signed <= (Z set or N != V)
+    # Privileged architecture
+    SYSCALL = auto()  # Trap into the kernel
+    ERET = auto()     # Return from trap to user mode (privileged)
+    MFC0 = auto()     # Move from control register (privileged)
+    MTC0 = auto()     # Move to control register (privileged)
+    # Sub-word memory access (byte address = word address * 4 + byte offset)
+    LB = auto()       # Load byte, sign-extended
+    LBU = auto()      # Load byte, zero-extended
+    LH = auto()       # Load halfword, sign-extended
+    LHU = auto()      # Load halfword, zero-extended
+    SB = auto()       # Store byte
+    SH = auto()       # Store halfword
+    # Cache control (no-ops without a cache or on uncacheable addresses)
+    PREF = auto()     # Prefetch a line into the cache
+    CFLUSH = auto()   # Write back and drop a line (every line with no operand)
+    CINV = auto()     # Drop a line without writing it back (every line with no operand)
+    # Subroutines
+    JAL = auto()      # Jump to label, saving the return address in the link register
+    JR = auto()       # Jump to the instruction whose index a register holds (e.g. return with JR ebp)
+    # Measurement
+    RESET_STATS = auto()  # Zero the statistics counters, so they measure only the code after it
+    # Branch delay slots
+    NOP = auto()      # Do nothing (fills a branch delay slot)
+
+# Supported ISA modes:
+#   standard - CMP/TEST write their result to a register (MIPS-style compares)
+#   arm      - CMP/TEST only set the flags register; Bcc instructions branch on flags
+ISA_MODES = ('standard', 'arm')
+
+# Byte order of the four bytes in a memory word
+ENDIANNESS = ('little', 'big')
+
+# Where instructions live:
+#   von_neumann - one memory and port for code and data; instruction i is word i
+#   harvard     - separate instruction and data memories, each with its own port
+MEMORY_ORGANIZATIONS = ('von_neumann', 'harvard')
+WORD_BYTES = 4
+
+# Datapath widths in bits: registers, ALU results and memory words are this wide
+WORD_SIZES = (8, 16, 32)
+
+# Branch delay slot modes: off; on (the instruction after a branch or jump always executes, MIPS-style);
+# fill (on, and the assembler puts a NOP after every branch and jump)
+DELAY_SLOT_MODES = ('off', 'on', 'fill')
+
+# Why a run stopped without an error (SimpleISA.halt_reason):
+#   halt          - a HALT instruction
+#   syscall       - the exit syscall
+#   end           - the PC left the loaded program (an error instead with .at_end error)
+#   instructions  - max_instructions instructions executed (.max_instructions)
+#   cycles        - max_cycles cycles elapsed: pipeline cycles with the pipeline model, else one per instruction
+#   exit_address  - a store to exit_address (.exit_address); the value stored is the exit code
+HALT_REASONS = ('halt', 'syscall', 'end', 'instructions', 'cycles', 'exit_address')
+
+# Sub-word loads: (size in bytes, sign-extend)
+SUBWORD_LOADS = {
+    InstructionType.LB: (1, True),
+    InstructionType.LBU: (1, False),
+    InstructionType.LH: (2, True),
+    InstructionType.LHU: (2, False),
+}
+SUBWORD_STORES = {InstructionType.SB: 1, InstructionType.SH: 2}
+
+# Condition each flag branch tests, as a function of the flags register
+FLAG_CONDITIONS = {
+    InstructionType.BEQ: lambda f: f['Z'] == 1,
+    InstructionType.BNE: lambda f: f['Z'] == 0,
+    InstructionType.BMI: lambda f: f['N'] == 1,
+    InstructionType.BPL: lambda f: f['N'] == 0,
+    InstructionType.BCS: lambda f: f['C'] == 1,
+    InstructionType.BCC: lambda f: f['C'] == 0,
+    InstructionType.BVS: lambda f: f['V'] == 1,
+    InstructionType.BVC: lambda f: f['V'] == 0,
+    InstructionType.BHI: lambda f: f['C'] == 1 and f['Z'] == 0,
+    InstructionType.BLS: lambda f: f['C'] == 0 or f['Z'] == 1,
+    InstructionType.BGE: lambda f: f['N'] == f['V'],
+    InstructionType.BLT: lambda f: f['N'] != f['V'],
+    InstructionType.BGT: lambda f: f['Z'] == 0 and f['N'] == f['V'],
+    InstructionType.BLE: lambda f: f['Z'] == 1 or f['N'] != f['V'],
+}
+
+# JAL writes the index of the instruction after it here; JR LINK_REGISTER returns
+LINK_REGISTER = 'ebp'
+
+class TrapCause(IntEnum):
+    """Values written to the cause control register when a trap is taken"""
+    INTERRUPT = 0   # Timer interrupt
+    PROTECTION = 4  # User-mode access to protected memory
+    SYSCALL = 8     # SYSCALL instruction
+    PRIVILEGED = 10 # Privileged instruction executed in user mode
+    SEGFAULT = 11   # Access not permitted by a memory region's permissions
+
+class Syscall(IntEnum):
+    """Services the simulator provides when no trap_handler is loaded (number in eax, argument in ebx)"""
+    PRINT_INT = 1  # Print ebx
+    SBRK = 9       # Grow the heap by ebx words; eax = old break (-1 if out of memory)
+    EXIT = 10      # Stop the program
+    MALLOC = 20    # Allocate ebx words; eax = pointer (0 on failure)
+    FREE = 21      # Free the block at ebx; eax = 1 on success, 0 otherwise
+
+# Label the CPU vectors to when a trap is taken
+TRAP_VECTOR = 'trap_handler'
+
+# ALU operator each FPU arithmetic instruction drives
+FP_OPERATORS = {InstructionType.ADD_S: '+', InstructionType.SUB_S: '-', InstructionType.MUL_S: '×',
+                InstructionType.DIV_S: '÷'}
+
+# Instructions that may only execute in kernel mode
+PRIVILEGED_INSTRUCTIONS = {InstructionType.ERET, InstructionType.MFC0, InstructionType.MTC0}
+
+# Instructions that read their first operand before writing it
+DEST_READ_INSTRUCTIONS = {
+    InstructionType.ADD, InstructionType.SUB, InstructionType.INC, InstructionType.DEC,
+    InstructionType.NOT, InstructionType.AND, InstructionType.OR, InstructionType.XOR,
+    InstructionType.SHL, InstructionType.SHR, InstructionType.CMP, InstructionType.TEST
+}
+
+# Instructions that write their first operand when it is a register
+DEST_WRITE_INSTRUCTIONS = (DEST_READ_INSTRUCTIONS - {InstructionType.CMP}) | {
+    InstructionType.MOV, InstructionType.LOAD, InstructionType.STORE,
+    InstructionType.MFC0, InstructionType.CVT_W_S
+} | set(SUBWORD_LOADS)
+
+# Instructions that write their first operand, an FPU register
+FP_WRITE_INSTRUCTIONS = {
+    InstructionType.MOV_S, InstructionType.LOAD_S, InstructionType.ADD_S, InstructionType.SUB_S,
+    InstructionType.MUL_S, InstructionType.DIV_S, InstructionType.CVT_S_W
+}
+
+# What memory_kind calls a word, by region: program text, written data, .stack, heap, .mmio devices, never written
+MEMORY_KINDS = ('instruction', 'data', 'stack', 'heap', 'mmio', 'untouched')
+
+# Instructions that assume four-byte words: byte/halfword access and single-precision loads and stores
+FULL_WORD_INSTRUCTIONS = set(SUBWORD_LOADS) | set(SUBWORD_STORES) | {InstructionType.LOAD_S, InstructionType.STORE_S}
+
+# MIPS-style register that always reads as zero while hardwired_zero is on
+ZERO_REGISTER = 'zero'
+
+class CPUTrap(Exception):
+    """Raised during execution to transfer control to the kernel"""
+    def __init__(self, cause: TrapCause, message: str = ""):
+        super().__init__(message or cause.name)
+        self.cause = cause
+
+@dataclass
+class MemoryRegion:
+    """An inclusive address range with read/write/execute permissions"""
+    start: int
+    end: int
+    permissions: str  # e.g. "r-x" for code, "rw-" for data
+    name: str = ""
+
+    def contains(self, addr: int) -> bool:
+        return self.start <= addr <= self.end
+
+    def allows(self, access: str) -> bool:
+        return access in self.permissions
+
+@dataclass
+class WriteRecord:
+    """The instruction that last wrote a register or memory word
+
+    cycle is when it completed: its cycle in the pipeline model if one is
+    attached, else its step number (one cycle per instruction).
+    """
+    pc: int
+    step: int
+    cycle: int
+
+@dataclass
+class AluOperation:
+    """What the ALU did in one step: a op b = result, written to dest (None when only the flags keep it)
+
+    op is the operator symbol: + - & | ^ ~ << >> for the integer ALU,
+    + - × ÷ for the FPU. b is None for NOT.
+    """
+    op: str
+    a: Union[int, float]
+    b: Optional[Union[int, float]]
+    result: Union[int, float]
+    dest: Optional[str] = None
+
+@dataclass
+class BranchPreview:
+    """The two ways a branch about to execute can go: fall_through (pc + 1) or target
+
+    taken is what the branch will do given the current registers and
+    flags; predicted_taken is what the fetch stage assumes before the
+    branch resolves. target is None when a jump's target is not known yet.
+    With delay_slot the fall-through instruction runs first on both paths.
+    """
+    pc: int
+    fall_through: int
+    target: Optional[int]
+    taken: bool
+    predicted_taken: bool = False
+    delay_slot: bool = False
+
+    @property
+    def next_pc(self) -> Optional[int]:
+        return self.target if self.taken else self.fall_through
+
+    @property
+    def mispredicted(self) -> bool:
+        return self.taken != self.predicted_taken
 
 @dataclass
 class Instruction:
@@ -45,9 +288,260 @@ class Instruction:
     type: InstructionType
     operands: List[str]
     line_number: int
+    source: str = ""  # Assembly text without comments
+
+def is_control_transfer(inst_type: InstructionType) -> bool:
+    """Branches and jumps: the instructions that have a delay slot when delay slots are on"""
+    return inst_type in BRANCH_INSTRUCTIONS or inst_type == InstructionType.JR
+
+def mnemonic(inst_type: InstructionType) -> str:
+    """Assembly spelling of an instruction type; FP types are written with dots (ADD_S as ADD.S)"""
+    if inst_type.name.endswith(('_S', '_W')):
+        return inst_type.name.replace('_', '.')
+    return inst_type.name
+
+def call_depth_change(instruction: Instruction) -> int:
+    """+1 for a subroutine call, -1 for a return and 0 otherwise; stepping over a call runs until the sum is 0"""
+    return {InstructionType.JAL: 1, InstructionType.JR: -1}.get(instruction.type, 0)
+
+def symbol_for(index: int, labels: Dict[str, int]) -> str:
+    """An instruction index by label: the label there, else the nearest one before it plus an offset (loop+2)
+
+    Empty before the first label. Of several labels at one index the first
+    defined is used.
+    """
+    best = None
+    for name, target in labels.items():
+        if target <= index and (best is None or target > best[1]):
+            best = (name, target)
+    if best is None:
+        return ''
+    return best[0] if best[1] == index else f"{best[0]}+{index - best[1]}"
+
+@dataclass
+class Macro:
+    """A .macro definition: its parameters (with defaults, None if required) and body lines"""
+    name: str
+    params: List[Tuple[str, Optional[str]]]
+    body: List[str]
+    line_number: int
+
+# Macros may invoke other macros, but not without end
+MACRO_DEPTH_LIMIT = 16
+MACRO_ARGUMENT = re.compile(r'\\(\w+|@)')
+
+def _macro_operands(text: str) -> List[str]:
+    """Parameters of a .macro line or arguments of an invocation: separated by commas and/or spaces"""
+    return [part for part in re.split(r'[,\s]+', text.strip()) if part]
+
+def expand_macros(program: List[str], isa_mode: str = 'standard') -> Tuple[List[Tuple[int, str]],
+                                                                          List[Tuple[int, str]]]:
+    """Replace .macro definitions and their invocations by the lines they stand for
+
+    A definition runs from '.macro name param, param=default' to '.endm'.
+    In its body \\param is replaced by the invocation's argument and \\@ by a
+    number unique to each expansion, for labels (loop\\@:). Returns
+    (source line index, text) of every resulting line, so diagnostics point
+    at the line that invoked a macro, and (line index, message) of errors;
+    lines with errors are left out.
+
+    Expression lines (eax = (ebx + 5) * ecx - edx, if eax < ebx goto done)
+    become the instructions that compute them; see compiler.exprline. Their
+    comparisons depend on the ISA mode, isa_mode until a .mode directive.
+    """
+    macros: Dict[str, Macro] = {}
+    errors: List[Tuple[int, str]] = []
+    expanded: List[Tuple[int, str]] = []
+    counter = [0]
+    mode = [isa_mode]
+
+    def new_label() -> str:
+        counter[0] += 1
+        return f"__expr{counter[0]}"
+
+    def expand(i: int, line: str, depth: int) -> None:
+        words = line.split(';')[0].split(None, 1)
+        macro = macros.get(words[0].lower()) if words else None
+        if macro is None:
+            if words and words[0].lower() == '.mode' and len(words) == 2:
+                mode[0] = words[1].strip().lower()
+            try:
+                instructions = translate_line_to_instructions(line, new_label, mode[0])
+            except ValueError as e:
+                errors.append((i, f"Expression line: {e}"))
+                return
+            expanded.extend((i, text) for text in instructions or [line])
+            return
+        if depth >= MACRO_DEPTH_LIMIT:
+            errors.append((i, f"Macro {macro.name} nests more than {MACRO_DEPTH_LIMIT} deep"))
+            return
+        args = _macro_operands(words[1]) if len(words) > 1 else []
+        if len(args) > len(macro.params):
+            count = len(macro.params)
+            errors.append((i, f"Macro {macro.name} takes {count} argument{'s' if count != 1 else ''}, "
+                              f"got {len(args)}"))
+            return
+        values = {}
+        for n, (param, default) in enumerate(macro.params):
+            if n < len(args):
+                values[param] = args[n]
+            elif default is not None:
+                values[param] = default
+            else:
+                errors.append((i, f"Macro {macro.name} is missing argument {param}"))
+                return
+        counter[0] += 1
+        values['@'] = str(counter[0])
+        unknown = [m.group(1) for body_line in macro.body for m in MACRO_ARGUMENT.finditer(body_line)
+                   if m.group(1) not in values]
+        if unknown:
+            errors.append((i, f"Macro {macro.name} has no parameter {unknown[0]}"))
+            return
+        for body_line in macro.body:
+            expand(i, MACRO_ARGUMENT.sub(lambda m: values[m.group(1)], body_line), depth + 1)
+
+    definition: Optional[Macro] = None
+    valid = False  # Whether the definition being read can be used (its body is skipped either way)
+    for i, line in enumerate(program):
+        words = line.split(';')[0].split(None, 1)
+        keyword = words[0].lower() if words else ''
+        if keyword == '.macro':
+            if definition:
+                errors.append((i, f"Macro definitions cannot be nested (inside {definition.name})"))
+                continue
+            names = _macro_operands(words[1]) if len(words) > 1 else ['']
+            params = [(p.split('=', 1)[0], p.split('=', 1)[1] if '=' in p else None) for p in names[1:]]
+            definition = Macro(names[0], params, [], i)
+            valid = False
+            if not names[0]:
+                errors.append((i, ".macro needs a name"))
+            elif names[0].upper().replace('.', '_') in InstructionType.__members__:
+                errors.append((i, f"Macro name {names[0]} is an instruction"))
+            elif names[0].lower() in macros:
+                errors.append((i, f"Macro {names[0]} is already defined"))
+            else:
+                valid = True
+        elif keyword == '.endm':
+            if definition is None:
+                errors.append((i, ".endm without .macro"))
+            else:
+                if valid:
+                    macros[definition.name.lower()] = definition
+                definition = None
+        elif definition:
+            definition.body.append(line)
+        else:
+            expand(i, line, 0)
+    if definition:
+        errors.append((definition.line_number, f"Macro {definition.name} has no .endm"))
+    return expanded, errors
+
+def expand_macro_lines(program: List[str]) -> List[str]:
+    """A program with its macros expanded; ValueError on the first macro error"""
+    expanded, errors = expand_macros(program)
+    for i, message in errors:
+        raise ValueError(f"Line {i + 1}: {message}")
+    return [line for _, line in expanded]
+
+def format_instruction(instruction: Instruction) -> str:
+    """Canonical assembly text of an instruction; parse_program reads it back as the same instruction"""
+    return ' '.join([mnemonic(instruction.type)] + list(instruction.operands))
+
+@dataclass(frozen=True)
+class Opcode:
+    """One row of the opcode table: the operand forms an instruction is written in and how it executes
+
+    Each shape lists an accepted form's operand kinds: reg, imm, mem, freg,
+    fimm, creg or label. execute runs the instruction on a SimpleISA; for
+    an instruction that jumps it returns the next pc.
+    """
+    shapes: Tuple[Tuple[str, ...], ...]
+    execute: Optional[Callable[['SimpleISA', Instruction], Optional[int]]]
+    jumps: bool = False
+
+    @property
+    def operand_counts(self) -> List[int]:
+        return sorted({len(shape) for shape in self.shapes})
+
+_REG_REG_OR_IMM = (('reg', 'reg'), ('reg', 'imm'))
+_SHIFT = (('reg', 'imm'), ('reg', 'reg'), ('mem', 'imm'))
+_NONE = ((),)
+_LABEL = (('label',),)
+
+def _no_operands(cpu: 'SimpleISA', instruction: Instruction) -> None:
+    if instruction.operands:
+        raise ValueError(f"{mnemonic(instruction.type)} takes no operands")
+
+def _syscall(cpu: 'SimpleISA', instruction: Instruction) -> None:
+    raise CPUTrap(TrapCause.SYSCALL)
+
+# Every instruction the assembler accepts, how it is written and what executes it. Adding an instruction
+# means an InstructionType member (its opcode number and reference summary) and a row here; execute_step,
+# the verifier's operand counts, the fuzzers' operand shapes, the instruction reference and --list-isa all
+# read this table.
+OPCODES: Dict[InstructionType, Opcode] = {
+    InstructionType.MOV: Opcode((('reg', 'imm'), ('reg', 'reg'), ('reg', 'mem'), ('mem', 'imm'), ('mem', 'reg')),
+                                lambda cpu, i: cpu._execute_mov(i.operands)),
+    InstructionType.LOAD: Opcode((('reg', 'mem'),), lambda cpu, i: cpu._execute_load(i.operands)),
+    InstructionType.STORE: Opcode((('mem', 'reg'),), lambda cpu, i: cpu._execute_store(i.operands)),
+    InstructionType.ADD: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_add(i.operands)),
+    InstructionType.SUB: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_sub(i.operands)),
+    InstructionType.JMP: Opcode(_LABEL, lambda cpu, i: cpu._execute_jmp(i.operands), jumps=True),
+    InstructionType.JZ: Opcode(_LABEL, lambda cpu, i: cpu._execute_jz(i.operands), jumps=True),
+    InstructionType.JNZ: Opcode(_LABEL, lambda cpu, i: cpu._execute_jnz(i.operands), jumps=True),
+    InstructionType.AND: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_and(i.operands)),
+    InstructionType.OR: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_or(i.operands)),
+    InstructionType.XOR: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_xor(i.operands)),
+    InstructionType.NOT: Opcode((('reg',),), lambda cpu, i: cpu._execute_not(i.operands)),
+    InstructionType.INC: Opcode((('reg',),), lambda cpu, i: cpu._execute_inc(i.operands)),
+    InstructionType.DEC: Opcode((('reg',),), lambda cpu, i: cpu._execute_dec(i.operands)),
+    InstructionType.SHL: Opcode(_SHIFT, lambda cpu, i: cpu._execute_shift(i.operands, True)),
+    InstructionType.SHR: Opcode(_SHIFT, lambda cpu, i: cpu._execute_shift(i.operands, False)),
+    InstructionType.CMP: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_cmp(i.operands)),
+    InstructionType.TEST: Opcode(_REG_REG_OR_IMM, lambda cpu, i: cpu._execute_test(i.operands)),
+    InstructionType.HALT: Opcode(_NONE, None),  # execute_step stops the program (or switches process) itself
+    InstructionType.PRINT_CACHE: Opcode(_NONE, lambda cpu, i: cpu._print_cache_state()),
+    InstructionType.PRINT_REG: Opcode(_NONE, lambda cpu, i: cpu._print_register_state()),
+    InstructionType.MOV_S: Opcode((('freg', 'freg'), ('freg', 'fimm')), lambda cpu, i: cpu._execute_mov_s(i.operands)),
+    InstructionType.LOAD_S: Opcode((('freg', 'mem'),), lambda cpu, i: cpu._execute_load_s(i.operands)),
+    InstructionType.STORE_S: Opcode((('mem', 'freg'),), lambda cpu, i: cpu._execute_store_s(i.operands)),
+    InstructionType.CVT_S_W: Opcode((('freg', 'reg'),), lambda cpu, i: cpu._execute_cvt_s_w(i.operands)),
+    InstructionType.CVT_W_S: Opcode((('reg', 'freg'),), lambda cpu, i: cpu._execute_cvt_w_s(i.operands)),
+    InstructionType.BC1T: Opcode(_LABEL, lambda cpu, i: cpu._execute_fp_branch(i.operands, True), jumps=True),
+    InstructionType.BC1F: Opcode(_LABEL, lambda cpu, i: cpu._execute_fp_branch(i.operands, False), jumps=True),
+    InstructionType.SYSCALL: Opcode(_NONE, _syscall),
+    InstructionType.ERET: Opcode(_NONE, lambda cpu, i: cpu._execute_eret()),
+    InstructionType.MFC0: Opcode((('reg', 'creg'),), lambda cpu, i: cpu._execute_mfc0(i.operands)),
+    InstructionType.MTC0: Opcode((('creg', 'reg'),), lambda cpu, i: cpu._execute_mtc0(i.operands)),
+    InstructionType.PREF: Opcode((('mem',),), lambda cpu, i: cpu._execute_cache_control(i.type, i.operands)),
+    InstructionType.CFLUSH: Opcode(((), ('mem',)), lambda cpu, i: cpu._execute_cache_control(i.type, i.operands)),
+    InstructionType.CINV: Opcode(((), ('mem',)), lambda cpu, i: cpu._execute_cache_control(i.type, i.operands)),
+    InstructionType.JAL: Opcode(_LABEL, lambda cpu, i: cpu._execute_jal(i.operands), jumps=True),
+    InstructionType.JR: Opcode((('reg',),), lambda cpu, i: cpu._execute_jr(i.operands), jumps=True),
+    InstructionType.RESET_STATS: Opcode(_NONE, _no_operands),  # execute_step resets once the step is counted
+    InstructionType.NOP: Opcode(_NONE, _no_operands),
+}
+for _t in FP_OPERATORS:
+    OPCODES[_t] = Opcode((('freg', 'freg'),), lambda cpu, i: cpu._execute_fp_arith(i.type, i.operands))
+for _t in (InstructionType.C_EQ_S, InstructionType.C_LT_S, InstructionType.C_LE_S):
+    OPCODES[_t] = Opcode((('freg', 'freg'),), lambda cpu, i: cpu._execute_fp_compare(i.type, i.operands))
+for _t in FLAG_CONDITIONS:
+    OPCODES[_t] = Opcode(_LABEL, lambda cpu, i: cpu._execute_flag_branch(i.type, i.operands), jumps=True)
+for _t in SUBWORD_LOADS:
+    OPCODES[_t] = Opcode((('reg', 'mem'),), lambda cpu, i: cpu._execute_subword_load(i.type, i.operands))
+for _t in SUBWORD_STORES:
+    OPCODES[_t] = Opcode((('mem', 'reg'),), lambda cpu, i: cpu._execute_subword_store(i.type, i.operands))
+_unlisted = [t.name for t in InstructionType if t not in OPCODES]
+if _unlisted:
+    raise ImportError(f"Instructions missing from the opcode table: {', '.join(_unlisted)}")
+
+# Instructions that may transfer control to a label: the jumping rows of the table written with one
+BRANCH_INSTRUCTIONS = {t for t, opcode in OPCODES.items() if opcode.jumps and opcode.shapes == _LABEL}
 
 class SimpleISA:
-    def __init__(self, memory: Optional[Memory] = None, cache: Optional[Cache] = None):
+    def __init__(self, memory: Optional[Memory] = None, cache: Optional[Cache] = None,
+                 isa_mode: str = 'standard', endianness: str = 'little',
+                 memory_organization: str = 'von_neumann'):
         # Initialize registers
         self.registers = {
             'eax': 0,
@@ -57,8 +551,36 @@ class SimpleISA:
             'esi': 0,
             'edi': 0,
             'ebp': 0,
-            'esp': 0
+            'esp': 0,
+            ZERO_REGISTER: 0
         }
+        # Discard writes to the zero register (turn off to show why the convention exists)
+        self.hardwired_zero = True
+
+        # Floating-point coprocessor registers (f0-f31) and condition flag
+        self.fp_registers = {f'f{i}': 0.0 for i in range(32)}
+        self.fp_condition = False
+
+        # Datapath width (see WORD_SIZES); status flags use the same width
+        self.word_bits = 32
+        # Branch delay slots (see DELAY_SLOT_MODES)
+        self.delay_slots = False
+        self.fill_delay_slots = False
+        self.pending_branch: Optional[Tuple[int, int]] = None  # (branch pc, target) while its delay slot is next
+        self.flags = {'Z': 0, 'N': 0, 'C': 0, 'V': 0}
+        # Settings the loaded program's directives changed, with the machine's own values to restore
+        self._directive_settings: Dict[str, object] = {}
+        self.set_isa_mode(isa_mode)
+        self.set_endianness(endianness)
+        self.set_memory_organization(memory_organization)
+        self.memory_ports = {'instruction_fetches': 0, 'data_accesses': 0, 'port_conflicts': 0}
+
+        # Privileged architecture: the CPU boots in kernel mode
+        self.kernel_region: Optional[Tuple[int, int]] = None  # Inclusive address range
+        self.memory_regions: List[MemoryRegion] = []  # Instruction i is fetched from address i
+        self.uncacheable_ranges: List[Tuple[int, int]] = []  # Inclusive ranges that bypass the cache
+        self.uncached_accesses = 0
+        self._reset_privileged_state()
 
         # Program state
         self.pc = 0  # Program counter
@@ -66,6 +588,56 @@ class SimpleISA:
         self.labels: Dict[str, int] = {}
         self.running = False
 
+        # Base-and-bounds relocation of data addresses (set per process by the scheduler, or by .data)
+        self.base_register = 0
+        self.bounds_register: Optional[int] = None
+        self.scheduler = None
+
+        # Program placement: memory word holding instruction 0 (.text), and where execution starts (.entry)
+        self.text_base = 0
+        self.entry_point = 0
+        self._entry_label: Optional[Tuple[str, int]] = None  # (label, line index) of the .entry directive
+
+        # Heap managed by the built-in malloc/free syscalls (created on first use)
+        self.heap_region: Optional[Tuple[int, int]] = None  # Start and limit, exclusive
+        self.heap: Optional[HeapAllocator] = None
+
+        # Where the program keeps its stack and its memory-mapped devices, for memory_kind
+        self.stack_region: Optional[Tuple[int, int]] = None  # Inclusive address range
+        self.mmio_ranges: List[Tuple[int, int]] = []  # Inclusive ranges, also uncacheable
+
+        # Uninitialized-read detection: locations never written by the program
+        self.initialized_registers: set = {ZERO_REGISTER}
+        self.initialized_memory: set = set()
+        self.uninitialized_reads = {'registers': 0, 'memory': 0}
+        self.memory_writers: Dict[int, WriteRecord] = {}  # Address -> the instruction that last stored to it
+        self.register_writers: Dict[str, WriteRecord] = {}  # Register -> the instruction that last wrote it
+        self._step_writes: List[WriteRecord] = []  # Records made by the current step, timed once it completes
+        self.step_warnings: List[str] = []  # Warnings raised by the last step
+        self.last_error: Optional[Exception] = None  # Error that stopped the program, if any
+        self.diagnostics: List[Tuple[int, str]] = []  # (line index, message) assembler errors of the last load
+        self.step_accesses: List[Tuple[str, int]] = []  # ('r' or 'w', address) data accesses of the last step
+        self.step_alu: Optional[AluOperation] = None  # The ALU operation of the last step, if it had one
+
+        # Register usage statistics
+        self.register_usage = {reg: {'reads': 0, 'writes': 0} for reg in self.registers}
+
+        # Loops found from taken backward branches, keyed by (first, last) instruction
+        self.loops: Dict[Tuple[int, int], Dict[str, int]] = {}
+        self.pc_exec_counts: Dict[int, int] = {}
+
+        # Optional pipeline timing model fed every executed instruction (see pipeline.py)
+        self.pipeline = None
+
+        # Optional store buffer between the L1 cache and the next level (.write_buffer)
+        self.write_buffer: Optional[WriteBuffer] = None
+
+        # Optional load/store queue model fed every executed instruction (see lsq.py)
+        self.lsq = None
+
+        # Retirements, cache accesses, memory writes, branches and exceptions for subscribers (see events.py)
+        self.events = EventBus()
+
         # Memory system
         self.memory = memory
         self.cache = cache
@@ -73,30 +645,330 @@ class SimpleISA:
         # Logging
         self.logger = Logger()
 
+        # Cycles a data access that misses in L1 (or bypasses it) waits for the levels below; 0 for none
+        self.miss_penalty = 0
+        self.step_stall = 0  # Stall cycles of the last step
+
         # Statistics
         self.instruction_count = 0
+        self.cycle = 0  # One per instruction plus memory stall cycles
+        self.memory_stall_cycles = 0
+        self.stats_reset_at = 0  # Instruction count when the statistics were last reset
         self.start_time = 0
-        self.test_mode = True  # Enable test mode by default
-        self.max_instructions = 100  # Limit execution in test mode
         self.end_time = 0
+        self._reset_halting()
+
+    def _reset_halting(self) -> None:
+        """Only HALT, the exit syscall and leaving the program stop a run; no limits or exit address"""
+        self.max_instructions = 0  # 0 for no limit
+        self.max_cycles = 0
+        self.exit_address: Optional[int] = None  # Physical address whose stores end the run
+        self.halt_at_end = True  # False makes leaving the program an error rather than a halt
+        self.halt_reason: Optional[str] = None
+        self.exit_code: Optional[Union[int, float]] = None  # A float when STORE.S wrote it
+        self._exit_value: Optional[Union[int, float]] = None  # Stored to exit_address during the current step
+
+    def set_isa_mode(self, mode: str) -> None:
+        """Select how compares are performed (see ISA_MODES)"""
+        if mode not in ISA_MODES:
+            raise ValueError(f"Unknown ISA mode: {mode} (expected one of {', '.join(ISA_MODES)})")
+        self.isa_mode = mode
+        self._directive_settings.pop('isa_mode', None)
+
+    def set_zero_register(self, hardwired: bool) -> None:
+        """Make writes to the zero register take effect or be discarded"""
+        self.hardwired_zero = hardwired
+        self._directive_settings.pop('hardwired_zero', None)
+
+    def set_word_size(self, bits: int) -> None:
+        """Select the datapath width (see WORD_SIZES) that registers, ALU results and memory words wrap to"""
+        if bits not in WORD_SIZES:
+            raise ValueError(f"Unsupported word size: {bits} bits (expected one of {', '.join(map(str, WORD_SIZES))})")
+        self.word_bits = bits
+        self._directive_settings.pop('word_bits', None)
+
+    def set_delay_slots(self, mode: str) -> None:
+        """Select a branch delay slot mode (see DELAY_SLOT_MODES); fill only affects programs assembled after"""
+        if mode not in DELAY_SLOT_MODES:
+            raise ValueError(f"Unknown delay slot mode: {mode} (expected one of {', '.join(DELAY_SLOT_MODES)})")
+        self.delay_slots = mode != 'off'
+        self.fill_delay_slots = mode == 'fill'
+        self._directive_settings.pop('delay_slots', None)
+        self._directive_settings.pop('fill_delay_slots', None)
+
+    @property
+    def delay_slot_mode(self) -> str:
+        return 'fill' if self.fill_delay_slots else 'on' if self.delay_slots else 'off'
+
+    def set_miss_penalty(self, cycles: int) -> None:
+        """Make each data access that misses in L1 stall the processor for this many cycles (0 for none)"""
+        if cycles < 0:
+            raise ValueError(f"The miss penalty cannot be negative: {cycles}")
+        self.miss_penalty = cycles
+
+    def set_endianness(self, endianness: str) -> None:
+        """Select the byte order used by sub-word loads and stores (see ENDIANNESS)"""
+        if endianness not in ENDIANNESS:
+            raise ValueError(f"Unknown endianness: {endianness} (expected one of {', '.join(ENDIANNESS)})")
+        self.endianness = endianness
+        self._directive_settings.pop('endianness', None)
+
+    def poke_register(self, reg: str, value) -> None:
+        """Set a register from outside the program (debugger or GUI); not counted as a program write"""
+        if reg in self.fp_registers:
+            self.fp_registers[reg] = float(value)
+        elif reg in self.registers:
+            self.registers[reg] = int(value)
+            self.initialized_registers.add(reg)
+            self._enforce_zero_register()
+        else:
+            raise ValueError(f"Unknown register: {reg}")
+        self.register_writers.pop(reg, None)
+
+    def poke_memory(self, addr: int, value: int) -> None:
+        """Write a memory word from outside the program, through the caches like a store"""
+        if not 0 <= addr < len(self.memory.data):
+            raise ValueError(f"Invalid memory address: {addr}")
+        self.memory_writers.pop(addr, None)
+        self._write_physical(addr, int(value))
+
+    def set_memory_organization(self, organization: str) -> None:
+        """Select unified or split instruction/data memory (see MEMORY_ORGANIZATIONS)"""
+        if organization not in MEMORY_ORGANIZATIONS:
+            raise ValueError(f"Unknown memory organization: {organization} "
+                             f"(expected one of {', '.join(MEMORY_ORGANIZATIONS)})")
+        self.memory_organization = organization
+        self._directive_settings.pop('memory_organization', None)
+
+    def enable_write_buffer(self, entries: int = 4, drain_interval: int = 2) -> None:
+        """Insert a write buffer between the L1 cache and the level below it"""
+        if not self.cache:
+            raise ValueError("A write buffer needs a cache to sit behind")
+        self.disable_write_buffer()
+        self.write_buffer = WriteBuffer(self.cache._next_level, entries, drain_interval, logger=self.logger)
+        self.cache.set_next_level(self.write_buffer)
+
+    def add_uncacheable_range(self, start: int, end: int) -> None:
+        """Send accesses to an inclusive address range straight to memory, never the cache"""
+        if start > end:
+            raise ValueError(f"Invalid uncacheable range: {start}-{end}")
+        self.uncacheable_ranges.append((start, end))
+
+    def add_mmio_range(self, start: int, end: int) -> None:
+        """Mark an inclusive address range as memory-mapped devices; device registers are never cached"""
+        self.add_uncacheable_range(*self._memory_range(start, end, '.mmio'))
+        self.mmio_ranges.append((start, end))
+
+    def _memory_range(self, start: int, end: int, directive: str) -> Tuple[int, int]:
+        if not 0 <= start <= end < len(self.memory.data):
+            raise ValueError(f"Invalid {directive} range: {start}-{end} (memory is 0-{len(self.memory.data) - 1})")
+        return start, end
+
+    def is_uncacheable(self, addr: int) -> bool:
+        return any(start <= addr <= end for start, end in self.uncacheable_ranges)
+
+    def disable_write_buffer(self) -> None:
+        """Drain and remove the write buffer, reconnecting the cache to the next level"""
+        if self.write_buffer:
+            self.write_buffer.flush()
+            self.cache.set_next_level(self.write_buffer._next_level)
+            self.write_buffer = None
+
+    def _reset_privileged_state(self) -> None:
+        self.privilege = 'kernel'
+        self.in_trap = False  # Set while handling a trap; masks interrupts
+        self.control_registers = {
+            'status': 0,  # Bit 0: interrupt enable
+            'cause': 0,   # TrapCause of the last trap
+            'epc': 0,     # Instruction to resume at after ERET
+            'timer': 0    # Timer interrupt period in instructions (0 = off)
+        }
+        self._timer_countdown = 0
+        self._saved_flags = dict(self.flags)  # Flags of the interrupted program (like ARM's SPSR)
+
+    def add_memory_region(self, start: int, end: int, permissions: str, name: str = "") -> None:
+        """Restrict an inclusive address range to the given rwx permissions"""
+        if len(permissions) != 3 or any(p not in (flag, '-') for p, flag in zip(permissions, 'rwx')):
+            raise ValueError(f"Invalid region permissions: {permissions} (expected e.g. r-x or rw-)")
+        self.memory_regions.append(MemoryRegion(start, end, permissions, name or f"region{len(self.memory_regions)}"))
 
     def load_program(self, program: List[str]) -> None:
-        """Load a program into the ISA"""
-        self.instructions = []
-        self.labels = {}
+        """Load a program into the ISA, replacing the previous one, and set the PC to its entry point"""
+        for name, value in self._directive_settings.items():  # The old program's .mode, .word_size, ... end here
+            setattr(self, name, value)
+        self._directive_settings = {}
         self.pc = 0
+        self.pending_branch = None
         self.running = True
-
-        for i, line in enumerate(program):
+        self.last_error = None
+        self.diagnostics = []
+        self.flags = {'Z': 0, 'N': 0, 'C': 0, 'V': 0}
+        self._reset_privileged_state()
+        self.kernel_region = None
+        self.memory_regions = []
+        self.uncacheable_ranges = []
+        self.stack_region = None
+        self.mmio_ranges = []
+        self.uncached_accesses = 0
+        self.initialized_registers = {ZERO_REGISTER}
+        self.initialized_memory = set()
+        self.uninitialized_reads = {'registers': 0, 'memory': 0}
+        self.memory_writers = {}
+        self.register_writers = {}
+        self.register_usage = {reg: {'reads': 0, 'writes': 0} for reg in self.registers}
+        self.loops = {}
+        self.pc_exec_counts = {}
+        self.memory_ports = {'instruction_fetches': 0, 'data_accesses': 0, 'port_conflicts': 0}
+        self.stats_reset_at = 0
+        self._reset_halting()
+        self.disable_write_buffer()
+        if self.pipeline:
+            self.pipeline.reset()
+        if self.lsq:
+            self.lsq.reset()
+        self.heap_region = None
+        self.heap = None
+        self.base_register = 0
+        self.bounds_register = None
+        self.text_base = 0
+        self.entry_point = 0
+        self._entry_label = None
+        self.instructions, self.labels = self.parse_program(program)
+
+        if self._entry_label:
+            label, line_index = self._entry_label
+            if label not in self.labels:
+                self.diagnostics.append((line_index, f"Undefined entry point: {label}"))
+                raise ValueError(f"Undefined entry point: {label}")
+            self.entry_point = self.labels[label]
+        self.pc = self.entry_point
+
+    def clear_memory(self) -> None:
+        """Zero main memory and drop every cached line, so a new program starts without the old one's data"""
+        self.disable_write_buffer()
+        if self.cache:
+            self.cache.invalidate_all()
+        self.memory.data = [0] * len(self.memory.data)
+        self.initialized_memory = set()
+        self.memory_writers = {}
+
+    def _segment_base(self, value: str, directive: str) -> int:
+        base = int(value)
+        if not 0 <= base < len(self.memory.data):
+            raise ValueError(f"{directive} base {base} is outside memory (0-{len(self.memory.data) - 1})")
+        return base
+
+    def _set_for_program(self, setter, value, *names: str) -> None:
+        """Apply a directive through its setter for this program only; the next load_program puts names back"""
+        machine = {name: self._directive_settings.get(name, getattr(self, name)) for name in names}
+        setter(value)
+        self._directive_settings.update(machine)
+
+    def inherit_directive_settings(self, other: 'SimpleISA') -> None:
+        """Take over the machine values other's directives displaced, for a machine rebuilt from other's settings
+
+        Only settings this machine still shares with other are taken; one set differently is this machine's own.
+        """
+        self._directive_settings = {name: value for name, value in other._directive_settings.items()
+                                    if getattr(self, name) == getattr(other, name)}
+
+    def _diagnose(self, line_index: int, message: str) -> None:
+        """Report a line the assembler skipped, keeping it for editors as well as the log"""
+        self.diagnostics.append((line_index, message))
+        self.logger.log(LogLevel.ERROR, f"Line {line_index + 1}: {message}", target='asm.load')
+
+    def parse_program(self, program: List[str], base: int = 0) -> Tuple[List[Instruction], Dict[str, int]]:
+        """Assemble program lines into instructions and labels
+
+        Labels are offset by base so several programs can share one instruction space.
+        Directives take effect immediately; macros are expanded first (see expand_macros).
+        """
+        instructions: List[Instruction] = []
+        labels: Dict[str, int] = {}
+
+        expanded, errors = expand_macros(program, self.isa_mode)
+        for i, message in errors:
+            self._diagnose(i, message)
+        for i, line in expanded:
             line = line.strip()
             if not line or line.startswith(';'):
                 continue
 
+            # Handle directives
+            if line.startswith('.'):
+                directive = line.split(';')[0].split()
+                try:
+                    if directive[0].lower() == '.mode' and len(directive) == 2:
+                        self._set_for_program(self.set_isa_mode, directive[1].lower(), 'isa_mode')
+                    elif directive[0].lower() == '.zero_register' and len(directive) == 2:
+                        if directive[1].lower() not in ('hardwired', 'writable'):
+                            raise ValueError(f"Invalid .zero_register setting: {directive[1]} "
+                                             "(expected hardwired or writable)")
+                        self._set_for_program(self.set_zero_register, directive[1].lower() == 'hardwired',
+                                              'hardwired_zero')
+                    elif directive[0].lower() == '.word_size' and len(directive) == 2:
+                        self._set_for_program(self.set_word_size, int(directive[1]), 'word_bits')
+                    elif directive[0].lower() == '.delay_slots' and len(directive) == 2:
+                        self._set_for_program(self.set_delay_slots, directive[1].lower(),
+                                              'delay_slots', 'fill_delay_slots')
+                    elif directive[0].lower() == '.endian' and len(directive) == 2:
+                        self._set_for_program(self.set_endianness, directive[1].lower(), 'endianness')
+                    elif directive[0].lower() == '.memory' and len(directive) == 2:
+                        self._set_for_program(self.set_memory_organization, directive[1].lower(),
+                                              'memory_organization')
+                    elif directive[0].lower() == '.dram' and len(directive) == 3:
+                        if not hasattr(self.memory, 'dram'):
+                            raise ValueError(".dram needs a main memory with a DRAM model")
+                        self.memory.dram = DRAMModel(banks=int(directive[1]), row_words=int(directive[2]))
+                    elif directive[0].lower() == '.write_buffer' and len(directive) in (2, 3):
+                        self.enable_write_buffer(int(directive[1]), int(directive[2]) if len(directive) == 3 else 2)
+                    elif directive[0].lower() == '.kernel_region' and len(directive) == 3:
+                        self.kernel_region = (int(directive[1]), int(directive[2]))
+                    elif directive[0].lower() == '.region' and len(directive) in (4, 5):
+                        self.add_memory_region(int(directive[1]), int(directive[2]), directive[3].lower(),
+                                               directive[4] if len(directive) == 5 else "")
+                    elif directive[0].lower() == '.uncacheable' and len(directive) == 3:
+                        self.add_uncacheable_range(int(directive[1]), int(directive[2]))
+                    elif directive[0].lower() == '.mmio' and len(directive) == 3:
+                        self.add_mmio_range(int(directive[1]), int(directive[2]))
+                    elif directive[0].lower() == '.stack' and len(directive) == 3:
+                        self.stack_region = self._memory_range(int(directive[1]), int(directive[2]), '.stack')
+                    elif directive[0].lower() == '.heap' and len(directive) == 3:
+                        start, limit = int(directive[1]), int(directive[2])
+                        self._memory_range(start, limit - 1, '.heap')  # The limit is exclusive
+                        self.heap_region = (start, limit)
+                    elif directive[0].lower() == '.text' and len(directive) == 2:
+                        self.text_base = self._segment_base(directive[1], '.text')
+                    elif directive[0].lower() == '.data' and len(directive) == 2:
+                        self.base_register = self._segment_base(directive[1], '.data')
+                    elif directive[0].lower() == '.entry' and len(directive) == 2:
+                        self._entry_label = (directive[1], i)
+                    elif directive[0].lower() == '.exit_address' and len(directive) == 2:
+                        self.exit_address = self._memory_range(int(directive[1]), int(directive[1]),
+                                                               '.exit_address')[0]
+                    elif directive[0].lower() == '.max_instructions' and len(directive) == 2:
+                        self.max_instructions = self._halting_limit(directive[1], '.max_instructions')
+                    elif directive[0].lower() == '.max_cycles' and len(directive) == 2:
+                        self.max_cycles = self._halting_limit(directive[1], '.max_cycles')
+                    elif directive[0].lower() == '.miss_penalty' and len(directive) == 2:
+                        self.set_miss_penalty(int(directive[1]))
+                    elif directive[0].lower() == '.at_end' and len(directive) == 2:
+                        if directive[1].lower() not in ('halt', 'error'):
+                            raise ValueError(f"Invalid .at_end setting: {directive[1]} (expected halt or error)")
+                        self.halt_at_end = directive[1].lower() == 'halt'
+                    else:
+                        self._diagnose(i, f"Unknown directive: {line}")
+                except ValueError as e:
+                    self.diagnostics.append((i, str(e)))
+                    raise
+                continue
+
             # Handle labels
             if line.endswith(':'):
                 label = line[:-1].strip()
-                self.labels[label] = len(self.instructions)
-                self.logger.log(LogLevel.DEBUG, f"Found label {label} at instruction {len(self.instructions)}")
+                labels[label] = base + len(instructions)
+                self.logger.log(LogLevel.DEBUG, f"Found label {label} at instruction {base + len(instructions)}",
+                                target='asm.load')
                 continue
 
             # Split the line and filter out comments
@@ -110,78 +982,174 @@ class SimpleISA:
             if not instruction_parts:
                 continue
 
-            # Convert instruction type
+            # Convert instruction type (FP mnemonics like ADD.S map to ADD_S)
             try:
-                inst_type = InstructionType[instruction_parts[0].upper()]
+                inst_type = InstructionType[instruction_parts[0].upper().replace('.', '_')]
                 operands = instruction_parts[1:]
-                self.instructions.append(Instruction(inst_type, operands, i))
-                self.logger.log(LogLevel.DEBUG, f"Loaded instruction: {inst_type.name} {operands}")
+                instructions.append(Instruction(inst_type, operands, i, ' '.join(instruction_parts)))
+                if self.fill_delay_slots and is_control_transfer(inst_type):
+                    instructions.append(Instruction(InstructionType.NOP, [], i, 'NOP'))
+                self.logger.log(LogLevel.DEBUG, f"Loaded instruction: {inst_type.name} {operands}", target='asm.load')
             except KeyError:
-                self.logger.log(LogLevel.ERROR, f"Unknown instruction: {instruction_parts[0]}")
+                self._diagnose(i, f"Unknown instruction: {instruction_parts[0]}")
+
+        return instructions, labels
 
     def execute_step(self) -> bool:
-        """Execute one instruction"""
-        if not self.running or self.pc >= len(self.instructions):
+        """Execute one instruction; False once the run has stopped (see halt_reason and last_error)"""
+        if not self.running:
             return False
+        if not 0 <= self.pc < len(self.instructions):
+            return self._leave_program()
 
         instruction = self.instructions[self.pc]
+        fault_pc = self.pc
         self.pc += 1
         self.instruction_count += 1
+        self.step_warnings = []
+        self.step_accesses = []
+        self.step_alu = None
+        self.step_stall = 0
+        self.cycle += 1
+        self._step_writes = []
+        self._exit_value = None
+        delayed, self.pending_branch = self.pending_branch, None  # Set when this instruction is a delay slot
+        self.pc_exec_counts[fault_pc] = self.pc_exec_counts.get(fault_pc, 0) + 1
+        self.memory_ports['instruction_fetches'] += 1
 
         try:
-            if instruction.type == InstructionType.MOV:
-                self._execute_mov(instruction.operands)
-            elif instruction.type == InstructionType.LOAD:
-                self._execute_load(instruction.operands)
-            elif instruction.type == InstructionType.STORE:
-                self._execute_store(instruction.operands)
-            elif instruction.type == InstructionType.ADD:
-                self._execute_add(instruction.operands)
-            elif instruction.type == InstructionType.SUB:
-                self._execute_sub(instruction.operands)
-            elif instruction.type == InstructionType.INC:
-                self._execute_inc(instruction.operands)
-            elif instruction.type == InstructionType.DEC:
-                self._execute_dec(instruction.operands)
-            elif instruction.type == InstructionType.NOT:
-                self._execute_not(instruction.operands)
-            elif instruction.type == InstructionType.AND:
-                self._execute_and(instruction.operands)
-            elif instruction.type == InstructionType.OR:
-                self._execute_or(instruction.operands)
-            elif instruction.type == InstructionType.XOR:
-                self._execute_xor(instruction.operands)
-            elif instruction.type == InstructionType.CMP:
-                self._execute_cmp(instruction.operands)
-            elif instruction.type == InstructionType.TEST:
-                self._execute_test(instruction.operands)
-            elif instruction.type == InstructionType.SHL:
-                self._execute_shift(instruction.operands, True)
-            elif instruction.type == InstructionType.SHR:
-                self._execute_shift(instruction.operands, False)
-            elif instruction.type == InstructionType.JMP:
-                self.pc = self._execute_jmp(instruction.operands)
-            elif instruction.type == InstructionType.JZ:
-                self.pc = self._execute_jz(instruction.operands)
-            elif instruction.type == InstructionType.JNZ:
-                self.pc = self._execute_jnz(instruction.operands)
-            elif instruction.type == InstructionType.PRINT_CACHE:
-                self._print_cache_state()
-            elif instruction.type == InstructionType.PRINT_REG:
-                self._print_register_state()
-            elif instruction.type == InstructionType.HALT:
-                self.running = False
-                return False
-            else:
+            self._check_region_access(self.text_base + fault_pc, 'x')
+            if self.privilege == 'user' and instruction.type in PRIVILEGED_INSTRUCTIONS:
+                raise CPUTrap(TrapCause.PRIVILEGED, f"{instruction.type.name} in user mode")
+            if self.word_bits < 32 and instruction.type in FULL_WORD_INSTRUCTIONS:
+                raise ValueError(f"{mnemonic(instruction.type)} needs 32-bit words "
+                                 f"(this machine has {self.word_bits}-bit words)")
+            if delayed and is_control_transfer(instruction.type):
+                raise ValueError(f"{mnemonic(instruction.type)} at pc {fault_pc} is in the delay slot of the "
+                                 f"branch at pc {delayed[0]}")
+            self._check_register_reads(instruction)
+
+            if instruction.type == InstructionType.HALT:
+                self._publish_retired(instruction, fault_pc)
+                if self.scheduler and self.scheduler.exit_current(self):
+                    return True
+                return self._halt('halt')
+            opcode = OPCODES.get(instruction.type)
+            if opcode is None:
                 raise ValueError(f"Unknown instruction: {instruction.type}")
-
-            return True
+            next_pc = opcode.execute(self, instruction)
+            if opcode.jumps:
+                self.pc = next_pc
+
+            # With delay slots a taken branch first runs the instruction after it, then goes to its target
+            branch_target = self.pc
+            if self.delay_slots and is_control_transfer(instruction.type) and self.pc != fault_pc + 1:
+                self.pending_branch = (fault_pc, self.pc)
+                self.pc = fault_pc + 1
+            elif delayed:
+                self.pc = branch_target = delayed[1]
+
+            for reg in self.register_writes(instruction):
+                self.registers[reg] = self._fit_word(self.registers[reg], reg)
+            if self.step_alu and self.step_alu.dest in self.registers:
+                self.step_alu.result = self.registers[self.step_alu.dest]  # As stored, wrapped to the word size
+            self._enforce_zero_register()
+            self._mark_register_writes(instruction, fault_pc)
+            self._track_loops(instruction, fault_pc)
+            if self.pipeline:
+                completed = self.pipeline.record(instruction, fault_pc, branch_target).complete
+                for write in self._step_writes:
+                    write.cycle = completed
+            if self.lsq:
+                self.lsq.record(instruction, fault_pc, self.step_accesses)
+            if self.write_buffer:
+                self.write_buffer.tick()
+            self._publish_retired(instruction, fault_pc)
+            self._tick_timer()
+            if instruction.type == InstructionType.RESET_STATS:
+                self.reset_statistics()  # After this step is counted, so measuring starts with the next one
+            return self._check_halting()
+
+        except CPUTrap as trap:
+            # SYSCALL resumes after the instruction, faults re-execute it; after a delay slot comes the
+            # branch target, and re-executing it means re-executing its branch (as MIPS does)
+            if delayed and trap.cause == TrapCause.SYSCALL:
+                self.pc = delayed[1]
+            resume_pc = self.pc if trap.cause == TrapCause.SYSCALL else delayed[0] if delayed else fault_pc
+            return self._take_trap(trap, resume_pc)
 
         except Exception as e:
-            print(f"Error executing instruction: {e}")
+            self.logger.log(LogLevel.ERROR, f"Error executing instruction: {e}", target='cpu.exec')
+            self.last_error = e
             self.running = False
+            self.events.publish(SimEvent.EXCEPTION, self.instruction_count, fault_pc,
+                                cause=type(e).__name__, message=str(e), fatal=True)
             return False
 
+    @property
+    def cycles(self) -> int:
+        """Cycles so far: the pipeline model's when one is attached, else one per instruction plus memory stalls"""
+        return self.pipeline.total_cycles if self.pipeline else self.cycle
+
+    def _halt(self, reason: str) -> bool:
+        self.halt_reason = reason
+        self.running = False
+        self.logger.log(LogLevel.INFO, f"Halted: {self.halt_description()}", target='cpu.exec')
+        return False
+
+    def _leave_program(self) -> bool:
+        """The PC is past the last instruction (or before the first): a halt, or with .at_end error an error"""
+        if self.halt_at_end:
+            return self._halt('end')
+        error = ValueError(f"PC {self.pc} is outside the program (instructions 0-{len(self.instructions) - 1})")
+        self.logger.log(LogLevel.ERROR, f"Error executing instruction: {error}", target='cpu.exec')
+        self.last_error = error
+        self.running = False
+        self.events.publish(SimEvent.EXCEPTION, self.instruction_count, self.pc,
+                            cause=type(error).__name__, message=str(error), fatal=True)
+        return False
+
+    def _check_halting(self) -> bool:
+        """After a step: stop on a store to the exit address or a reached limit; True to keep running"""
+        if self._exit_value is not None:
+            self.exit_code = self._exit_value
+            if self.scheduler and self.scheduler.exit_current(self):
+                return True
+            return self._halt('exit_address')
+        if self.max_instructions and self.instruction_count >= self.max_instructions:
+            return self._halt('instructions')
+        if self.max_cycles and self.cycles >= self.max_cycles:
+            return self._halt('cycles')
+        return True
+
+    def halt_description(self) -> str:
+        """Why the run stopped, in words; empty while it has not halted"""
+        return {
+            'halt': "HALT",
+            'syscall': "exit syscall",
+            'end': f"PC {self.pc} left the program",
+            'instructions': f"{self.max_instructions}-instruction limit reached",
+            'cycles': f"{self.max_cycles}-cycle limit reached",
+            'exit_address': f"exit code {self.exit_code} stored to [{self.exit_address}]",
+        }.get(self.halt_reason, "")
+
+    @staticmethod
+    def _halting_limit(text: str, directive: str) -> int:
+        limit = int(text)
+        if limit < 1:
+            raise ValueError(f"{directive} needs a limit of at least 1, not {limit}")
+        return limit
+
+    def _publish_retired(self, instruction: Instruction, pc: int) -> None:
+        """Announce a completed instruction, and for branches which way it went"""
+        if instruction.type in BRANCH_INSTRUCTIONS and self.events.wants(SimEvent.BRANCH_RESOLVED):
+            target = self.labels.get(instruction.operands[0]) if instruction.operands else None
+            taken = self.pc != pc + 1 or bool(self.pending_branch and self.pending_branch[0] == pc)
+            self.events.publish(SimEvent.BRANCH_RESOLVED, self.instruction_count, pc,
+                                instruction=instruction, target=target, taken=taken)
+        self.events.publish(SimEvent.INSTRUCTION_RETIRED, self.instruction_count, pc,
+                            instruction=instruction, next_pc=self.pc)
+
     def _execute_mov(self, operands: List[str]) -> None:
         """Execute MOV instruction"""
         if len(operands) != 2:
@@ -201,7 +1169,7 @@ class SimpleISA:
         elif src.startswith('['):
             # Memory access
             addr = self._evaluate_address(src[1:-1])
-            value = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            value = self._read_memory(addr)
             # Log register operation with enhanced visualization
             self.logger.log_register_operation('mov', {
                 'dest': dest,
@@ -221,14 +1189,11 @@ class SimpleISA:
         if dest.startswith('['):
             # Memory write
             addr = self._evaluate_address(dest[1:-1])
-            if self.cache:
-                self.cache.write(addr, value)
-                # Ensure write-through to memory
-                self.memory.write(addr, value)
-            else:
-                self.memory.write(addr, value)
-        else:
+            self._write_memory(addr, value)
+        elif dest in self.registers:
             self.registers[dest] = value
+        else:
+            raise ValueError(f"Invalid destination register: {dest}")
 
     def _execute_add(self, operands: List[str]) -> None:
         """Execute ADD instruction"""
@@ -236,6 +1201,8 @@ class SimpleISA:
             raise ValueError("ADD requires 2 operands")
 
         dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid destination register: {dest}")
 
         # Get source value
         if src.startswith('#'):
@@ -244,7 +1211,10 @@ class SimpleISA:
             value = self.registers.get(src, 0)
 
         # Add to destination
-        self.registers[dest] += value
+        old = self.registers[dest]
+        self.registers[dest] = old + value
+        self._update_flags_add(old, value, self.registers[dest])
+        self._record_alu('+', old, value, self.registers[dest], dest)
 
     def _execute_sub(self, operands: List[str]) -> None:
         """Execute SUB instruction"""
@@ -252,6 +1222,8 @@ class SimpleISA:
             raise ValueError("SUB requires 2 operands")
 
         dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid destination register: {dest}")
 
         # Get source value
         if src.startswith('#'):
@@ -260,7 +1232,10 @@ class SimpleISA:
             value = self.registers.get(src, 0)
 
         # Subtract from destination
-        self.registers[dest] -= value
+        old = self.registers[dest]
+        self.registers[dest] = old - value
+        self._update_flags_sub(old, value, self.registers[dest])
+        self._record_alu('-', old, value, self.registers[dest], dest)
 
     def _execute_inc(self, operands: List[str]) -> None:
         """Execute INC instruction - increment register by 1"""
@@ -271,7 +1246,10 @@ class SimpleISA:
         if dest not in self.registers:
             raise ValueError(f"Invalid register {dest}")
 
-        self.registers[dest] += 1
+        old = self.registers[dest]
+        self.registers[dest] = old + 1
+        self._update_flags_add(old, 1, self.registers[dest])
+        self._record_alu('+', old, 1, self.registers[dest], dest)
         self.logger.log_register_operation('inc', {
             'dest': dest,
             'value': self.registers[dest],
@@ -287,7 +1265,10 @@ class SimpleISA:
         if dest not in self.registers:
             raise ValueError(f"Invalid register {dest}")
 
-        self.registers[dest] -= 1
+        old = self.registers[dest]
+        self.registers[dest] = old - 1
+        self._update_flags_sub(old, 1, self.registers[dest])
+        self._record_alu('-', old, 1, self.registers[dest], dest)
         self.logger.log_register_operation('dec', {
             'dest': dest,
             'value': self.registers[dest],
@@ -304,7 +1285,10 @@ class SimpleISA:
             raise ValueError(f"Invalid register: {reg}")
 
         # Perform bitwise NOT operation
-        self.registers[reg] = ~self.registers[reg]
+        old = self.registers[reg]
+        self.registers[reg] = ~old
+        self._update_flags_logic(self.registers[reg])
+        self._record_alu('~', old, None, self.registers[reg], reg)
 
         # Log register operation with enhanced visualization
         self.logger.log_register_operation('not', {
@@ -327,12 +1311,15 @@ class SimpleISA:
         elif src.startswith('['):
             # Memory access
             addr = self._evaluate_address(src[1:-1])
-            value = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            value = self._read_memory(addr)
         else:
             value = self.registers.get(src, 0)
 
         # Perform bitwise AND operation
+        old = self.registers[dest]
         self.registers[dest] &= value
+        self._update_flags_logic(self.registers[dest])
+        self._record_alu('&', old, value, self.registers[dest], dest)
 
         # Log register operation with enhanced visualization
         self.logger.log_register_operation('and', {
@@ -360,9 +1347,11 @@ class SimpleISA:
 
         # Perform bitwise OR
         result = self.registers[dest] | src_value
+        self._record_alu('|', self.registers[dest], src_value, result, dest)
 
         # Update destination register
         self.registers[dest] = result
+        self._update_flags_logic(result)
 
         # Log register operation
         self.logger.log_register_operation('or', {
@@ -383,7 +1372,7 @@ class SimpleISA:
             src_val = int(src[1:])
         elif src.startswith('['):
             addr = self._evaluate_address(src[1:-1])
-            src_val = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            src_val = self._read_memory(addr)
         else:
             if src not in self.registers:
                 raise ValueError(f"Invalid source register: {src}")
@@ -393,11 +1382,11 @@ class SimpleISA:
         if dest.startswith('['):
             # Memory operation
             addr = self._evaluate_address(dest[1:-1])
-            dest_val = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            dest_val = self._read_memory(addr)
             result = dest_val ^ src_val
-            if self.cache:
-                self.cache.write(addr, result)
-            self.memory.write(addr, result)
+            self._update_flags_logic(result)
+            self._record_alu('^', dest_val, src_val, result, f"memory[{addr}]")
+            self._write_memory(addr, result)
             self.logger.log_register_operation('xor', {
                 'dest': f"Memory[{addr}]",
                 'value': result,
@@ -409,6 +1398,8 @@ class SimpleISA:
                 raise ValueError(f"Invalid destination register: {dest}")
             dest_val = self.registers[dest]
             result = dest_val ^ src_val
+            self._update_flags_logic(result)
+            self._record_alu('^', dest_val, src_val, result, dest)
             self.registers[dest] = result
             self.logger.log_register_operation('xor', {
                 'dest': dest,
@@ -428,21 +1419,25 @@ class SimpleISA:
             shift_amount = int(src[1:])
         elif src.startswith('['):
             addr = self._evaluate_address(src[1:-1])
-            shift_amount = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            shift_amount = self._read_memory(addr)
         else:
             if src not in self.registers:
                 raise ValueError(f"Invalid source register: {src}")
             shift_amount = self.registers[src]
 
+        # Registers are unbounded, so a huge shift would build a huge integer
+        if not 0 <= shift_amount <= self.word_bits:
+            raise ValueError(f"Shift amount {shift_amount} out of range (0-{self.word_bits})")
+
         # Perform shift operation
         if dest.startswith('['):
             # Memory operation
             addr = self._evaluate_address(dest[1:-1])
-            dest_val = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            dest_val = self._read_memory(addr)
             result = dest_val << shift_amount if left else dest_val >> shift_amount
-            if self.cache:
-                self.cache.write(addr, result)
-            self.memory.write(addr, result)
+            self._update_flags_shift(dest_val, shift_amount, left, result)
+            self._record_alu('<<' if left else '>>', dest_val, shift_amount, result, f"memory[{addr}]")
+            self._write_memory(addr, result)
             self.logger.log_register_operation('shift', {
                 'dest': f"Memory[{addr}]",
                 'value': result,
@@ -455,6 +1450,8 @@ class SimpleISA:
                 raise ValueError(f"Invalid destination register: {dest}")
             dest_val = self.registers[dest]
             result = dest_val << shift_amount if left else dest_val >> shift_amount
+            self._update_flags_shift(dest_val, shift_amount, left, result)
+            self._record_alu('<<' if left else '>>', dest_val, shift_amount, result, dest)
             self.registers[dest] = result
             self.logger.log_register_operation('shift', {
                 'dest': dest,
@@ -474,6 +1471,38 @@ class SimpleISA:
 
         return self.labels[label]
 
+    def _execute_jal(self, operands: List[str]) -> int:
+        """Execute JAL instruction - call the subroutine at a label"""
+        if len(operands) != 1:
+            raise ValueError("JAL requires 1 operand")
+
+        label = operands[0]
+        if label not in self.labels:
+            raise ValueError(f"Undefined label: {label}")
+
+        # With delay slots the call returns past the slot, which ran before the call
+        self.registers[LINK_REGISTER] = self.pc + 1 if self.delay_slots else self.pc
+        self.logger.log_register_operation('mov', {
+            'dest': LINK_REGISTER,
+            'value': self.registers[LINK_REGISTER],
+            'source': 'return address'
+        })
+        return self.labels[label]
+
+    def _execute_jr(self, operands: List[str]) -> int:
+        """Execute JR instruction - jump to the instruction index in a register"""
+        if len(operands) != 1:
+            raise ValueError("JR requires 1 operand")
+
+        reg = operands[0]
+        if reg not in self.registers:
+            raise ValueError(f"Invalid register: {reg}")
+
+        target = self.registers[reg]
+        if not 0 <= target <= len(self.instructions):
+            raise ValueError(f"JR target {target} in {reg} is outside the program (0-{len(self.instructions)})")
+        return target
+
     def _execute_jz(self, operands: List[str]) -> int:
         """Execute JZ instruction"""
         if len(operands) != 1:
@@ -483,9 +1512,9 @@ class SimpleISA:
         if label not in self.labels:
             raise ValueError(f"Unknown label: {label}")
 
-        if self.registers['eax'] == 0:
+        if self.branch_taken(InstructionType.JZ):
             return self.labels[label]
-        return self.pc + 1
+        return self.pc
 
     def _execute_jnz(self, operands: List[str]) -> int:
         """Execute JNZ instruction"""
@@ -496,7 +1525,7 @@ class SimpleISA:
         if label not in self.labels:
             raise ValueError(f"Unknown label: {label}")
 
-        if self.registers['eax'] != 0:
+        if self.branch_taken(InstructionType.JNZ):
             return self.labels[label]
         return self.pc
 
@@ -506,6 +1535,8 @@ class SimpleISA:
             raise ValueError("LOAD requires 2 operands")
 
         dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid destination register: {dest}")
 
         # Get memory address
         if src.startswith('['):
@@ -514,7 +1545,7 @@ class SimpleISA:
             raise ValueError("LOAD source must be a memory address")
 
         # Read from memory and store in register
-        value = self.cache.read(addr) if self.cache else self.memory.read(addr)
+        value = self._read_memory(addr)
         self.registers[dest] = value
 
         # Log register operation with enhanced visualization
@@ -534,18 +1565,18 @@ class SimpleISA:
         # Get source value
         if src.startswith('['):
             addr = self._evaluate_address(src[1:-1])
-            value = self.cache.read(addr) if self.cache else self.memory.read(addr)
+            value = self._read_memory(addr)
         else:
             value = self.registers.get(src, 0)
 
         # Store in memory
         if dest.startswith('['):
             addr = self._evaluate_address(dest[1:-1])
-            if self.cache:
-                self.cache.write(addr, value)
-            self.memory.write(addr, value)
-        else:
+            self._write_memory(addr, value)
+        elif dest in self.registers:
             self.registers[dest] = value
+        else:
+            raise ValueError(f"Invalid destination register: {dest}")
 
         # Log register operation with enhanced visualization
         self.logger.log_register_operation('store', {
@@ -554,6 +1585,131 @@ class SimpleISA:
             'source': src
         })
 
+    # Sub-word memory access
+    def _byte_shift(self, offset: int, size: int) -> int:
+        """Bit position of the `size`-byte field at byte `offset` within a word"""
+        if offset % size:
+            raise ValueError(f"Unaligned {size}-byte access at byte offset {offset}")
+        if self.endianness == 'big':
+            offset = WORD_BYTES - size - offset
+        return offset * 8
+
+    def word_to_bytes(self, value: int) -> List[int]:
+        """Split a word into its bytes in address order (+0 to +3)"""
+        return [(value >> self._byte_shift(offset, 1)) & 0xFF for offset in range(WORD_BYTES)]
+
+    def _byte_address(self, expr: str) -> Tuple[int, int]:
+        """Evaluate a byte address, returning the relocated word address and byte offset"""
+        byte_addr = self._address_value(expr)
+        return self._relocate(byte_addr // WORD_BYTES), byte_addr % WORD_BYTES
+
+    def _execute_subword_load(self, op: InstructionType, operands: List[str]) -> None:
+        """Execute LB/LBU/LH/LHU instructions"""
+        if len(operands) != 2 or not operands[1].startswith('['):
+            raise ValueError(f"{op.name} requires a register and a memory address")
+
+        dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid register: {dest}")
+        size, signed = SUBWORD_LOADS[op]
+        addr, offset = self._byte_address(src[1:-1])
+        bits = size * 8
+
+        value = (self._read_memory(addr) >> self._byte_shift(offset, size)) & ((1 << bits) - 1)
+        if signed and value & (1 << (bits - 1)):
+            value -= 1 << bits
+        self.registers[dest] = value
+
+        self.logger.log_register_operation(op.name.lower(), {
+            'dest': dest,
+            'value': value,
+            'source': f'memory[{addr}]+{offset}'
+        })
+
+    def _execute_subword_store(self, op: InstructionType, operands: List[str]) -> None:
+        """Execute SB/SH instructions - replace part of a word (read-modify-write)"""
+        if len(operands) != 2 or not operands[0].startswith('['):
+            raise ValueError(f"{op.name} requires a memory address and a source")
+
+        dest, src = operands
+        if src.startswith('#'):
+            value = int(src[1:])
+        elif src in self.registers:
+            value = self.registers[src]
+        else:
+            raise ValueError(f"Invalid source operand: {src}")
+
+        size = SUBWORD_STORES[op]
+        addr, offset = self._byte_address(dest[1:-1])
+        shift = self._byte_shift(offset, size)
+        field_mask = ((1 << (size * 8)) - 1) << shift
+
+        self._check_memory_access(addr, 'r')
+        word = self._read_physical(addr) & self._word_mask()
+        word = (word & ~field_mask) | ((value << shift) & field_mask)
+        self._write_memory(addr, word)
+
+        self.logger.log_register_operation(op.name.lower(), {
+            'dest': f'memory[{addr}]+{offset}',
+            'value': word,
+            'source': src
+        })
+
+    def _execute_cache_control(self, op: InstructionType, operands: List[str]) -> None:
+        """Execute PREF, CFLUSH or CINV"""
+        if op == InstructionType.PREF and len(operands) != 1:
+            raise ValueError("PREF requires 1 operand")
+        if len(operands) > 1:
+            raise ValueError(f"{op.name} takes at most 1 operand")
+        if operands and not operands[0].startswith('['):
+            raise ValueError(f"{op.name} operand must be a memory address")
+        if not self.cache:
+            return
+
+        if not operands:
+            if op == InstructionType.CFLUSH:
+                self.cache.flush_all()
+            else:
+                self.cache.invalidate_all()
+            return
+
+        if op == InstructionType.PREF:
+            # Prefetches are hints: an address outside the bounds is ignored rather than trapping
+            try:
+                addr = self._evaluate_address(operands[0][1:-1])
+            except CPUTrap:
+                return
+            if not self.is_uncacheable(addr):
+                self.cache.prefetch(addr)
+            return
+
+        addr = self._evaluate_address(operands[0][1:-1])
+        if op == InstructionType.CFLUSH:
+            self.cache.flush_line(addr)
+        else:
+            self.cache.invalidate_line(addr)
+
+    def get_cache_control_stats(self):
+        """Prefetches, flushes and invalidations seen by the L1 cache"""
+        if not self.cache:
+            return None
+        stats = self.cache.get_performance_stats()
+        return {key: stats[key] for key in ('prefetches', 'useful_prefetches', 'flushes',
+                                            'invalidations', 'discarded_writes')}
+
+    def cache_levels(self) -> List[Cache]:
+        """Each cache level, L1 first, skipping the write buffer"""
+        levels = []
+        level = self.cache
+        while level is not None and all(level is not seen for seen in levels):
+            levels.append(level)
+            level = getattr(level, '_next_level', None)
+        return [level for level in levels if isinstance(level, Cache)]
+
+    def get_cache_requester_stats(self) -> List[Tuple[str, Dict[str, Dict[str, int]]]]:
+        """(name, hits and misses by requester) for each cache level, L1 first, skipping the write buffer"""
+        return [(level._name, level.get_performance_stats()['by_requester']) for level in self.cache_levels()]
+
     def _execute_cmp(self, operands: List[str]) -> None:
         """Execute CMP instruction"""
         if len(operands) != 2:
@@ -570,7 +1726,10 @@ class SimpleISA:
         # Compare values but don't modify the destination register
         # Instead, store the comparison result in a flag
         dest_val = self.registers.get(dest, 0)
-        self.registers['eax'] = 1 if dest_val < value else 0
+        self._update_flags_sub(dest_val, value, dest_val - value)
+        self._record_alu('-', dest_val, value, dest_val - value)
+        if self.isa_mode == 'standard':
+            self.registers['eax'] = 1 if dest_val < value else 0
 
     def _execute_test(self, operands: List[str]) -> None:
         """Execute TEST instruction"""
@@ -578,6 +1737,8 @@ class SimpleISA:
             raise ValueError("TEST requires 2 operands")
 
         dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid destination register: {dest}")
 
         # Get source value
         if src.startswith('#'):
@@ -586,7 +1747,487 @@ class SimpleISA:
             value = self.registers.get(src, 0)
 
         # Test bits (AND without storing)
-        self.registers[dest] = 1 if self.registers[dest] & value else 0
+        result = self.registers[dest] & value
+        self._update_flags_logic(result)
+        self._record_alu('&', self.registers[dest], value, result)
+        if self.isa_mode == 'standard':
+            self.registers[dest] = 1 if result else 0
+
+    # Status flags
+    def _word_mask(self) -> int:
+        return (1 << self.word_bits) - 1
+
+    def _fit_word(self, value, location: str):
+        """An integer result as stored in a word_bits wide register or memory word
+
+        Words always hold the two's complement number with the result's low
+        word_bits bits, so one bit pattern has one value whichever way it was
+        produced. A result that fits neither as a signed nor as an unsigned
+        number overflowed, and gets a warning naming the location.
+        """
+        if not isinstance(value, int):
+            return value
+        fitted = self._to_signed(value)
+        if -(1 << (self.word_bits - 1)) <= value < (1 << self.word_bits):
+            return fitted
+        message = f"{value} does not fit in {self.word_bits} bits; {location} = {fitted}"
+        self.step_warnings.append(message)
+        self.logger.log(LogLevel.WARNING, message, target='cpu.exec')
+        return fitted
+
+    def _to_signed(self, value: int) -> int:
+        """Interpret the low word_bits of value as a two's complement number"""
+        value &= self._word_mask()
+        if value >> (self.word_bits - 1):
+            value -= 1 << self.word_bits
+        return value
+
+    def _set_zn(self, result: int) -> None:
+        signed = self._to_signed(result)
+        self.flags['Z'] = int(signed == 0)
+        self.flags['N'] = int(signed < 0)
+
+    def _record_alu(self, op: str, a, b, result, dest: Optional[str] = None) -> None:
+        self.step_alu = AluOperation(op, a, b, result, dest)
+
+    def _update_flags_add(self, a: int, b: int, result: int) -> None:
+        """Update flags for a + b: C on unsigned carry out, V on signed overflow"""
+        mask = self._word_mask()
+        self._set_zn(result)
+        self.flags['C'] = int((a & mask) + (b & mask) > mask)
+        sa, sb, sr = self._to_signed(a), self._to_signed(b), self._to_signed(result)
+        self.flags['V'] = int((sa < 0) == (sb < 0) and (sr < 0) != (sa < 0))
+
+    def _update_flags_sub(self, a: int, b: int, result: int) -> None:
+        """Update flags for a - b: C set when no borrow (ARM convention), V on signed overflow"""
+        mask = self._word_mask()
+        self._set_zn(result)
+        self.flags['C'] = int((a & mask) >= (b & mask))
+        sa, sb, sr = self._to_signed(a), self._to_signed(b), self._to_signed(result)
+        self.flags['V'] = int((sa < 0) != (sb < 0) and (sr < 0) != (sa < 0))
+
+    def _update_flags_logic(self, result: int) -> None:
+        """Logical operations only update Z and N; C and V are preserved"""
+        self._set_zn(result)
+
+    def _update_flags_shift(self, value: int, amount: int, left: bool, result: int) -> None:
+        """Shifts update Z/N and set C to the last bit shifted out"""
+        self._set_zn(result)
+        if amount > 0:
+            value &= self._word_mask()
+            bit = self.word_bits - amount if left else amount - 1
+            self.flags['C'] = (value >> bit) & 1 if 0 <= bit < self.word_bits else 0
+
+    def format_flags(self) -> str:
+        """Return the flags register as a compact string, e.g. 'Z=1 N=0 C=1 V=0'"""
+        return ' '.join(f"{name}={value}" for name, value in self.flags.items())
+
+    def _execute_flag_branch(self, op: InstructionType, operands: List[str]) -> int:
+        """Execute a branch-on-flags instruction (BEQ, BNE, BLT, ...)"""
+        if self.isa_mode != 'arm':
+            raise ValueError(f"{op.name} requires ARM flags mode (use '.mode arm')")
+        if len(operands) != 1:
+            raise ValueError(f"{op.name} requires 1 operand")
+
+        label = operands[0]
+        if label not in self.labels:
+            raise ValueError(f"Unknown label: {label}")
+
+        if self.branch_taken(op):
+            return self.labels[label]
+        return self.pc
+
+    def branch_taken(self, op: InstructionType) -> bool:
+        """Whether a branch or jump of type op would be taken with the current registers and flags"""
+        if op == InstructionType.JZ:
+            return self.registers['eax'] == 0
+        if op == InstructionType.JNZ:
+            return self.registers['eax'] != 0
+        if op in FLAG_CONDITIONS:
+            return FLAG_CONDITIONS[op](self.flags)
+        if op in (InstructionType.BC1T, InstructionType.BC1F):
+            return self.fp_condition == (op == InstructionType.BC1T)
+        return op in (InstructionType.JMP, InstructionType.JAL, InstructionType.JR)
+
+    def branch_preview(self) -> Optional[BranchPreview]:
+        """Where the branch or jump at the PC can go and will go, before it executes; None for other instructions"""
+        if not self.running or self.pc >= len(self.instructions):
+            return None
+        instruction = self.instructions[self.pc]
+        if instruction.type == InstructionType.JR:
+            target = self.registers.get(instruction.operands[0]) if instruction.operands else None
+        elif instruction.type in BRANCH_INSTRUCTIONS:
+            target = self.labels.get(instruction.operands[0]) if instruction.operands else None
+        else:
+            return None
+        predicted = self.pipeline.predict_taken(instruction, self.pc) if self.pipeline else False
+        return BranchPreview(self.pc, self.pc + 1, target, self.branch_taken(instruction.type), predicted,
+                             self.delay_slots)
+
+    # Privileged architecture
+    def _take_trap(self, trap: CPUTrap, resume_pc: int) -> bool:
+        """Switch to kernel mode and vector to the trap handler"""
+        self.events.publish(SimEvent.EXCEPTION, self.instruction_count, resume_pc, cause=trap.cause.name,
+                            message=str(trap), fatal=TRAP_VECTOR not in self.labels and trap.cause != TrapCause.SYSCALL)
+        if TRAP_VECTOR not in self.labels:
+            if trap.cause == TrapCause.SYSCALL:
+                try:
+                    return self._execute_builtin_syscall()
+                except HeapCorruptionError as e:
+                    return self._syscall_fault(CPUTrap(TrapCause.SEGFAULT, f"corrupted heap: {e}"), resume_pc)
+                except Exception as e:
+                    return self._syscall_fault(e, resume_pc)
+            self.logger.log(LogLevel.ERROR, f"Error executing instruction: unhandled trap {trap.cause.name} ({trap})",
+                            target='cpu.trap')
+            self.last_error = trap
+            self.running = False
+            return False
+
+        self.logger.log(LogLevel.INFO, f"TRAP {trap.cause.name} from {self.privilege} mode at PC {resume_pc}",
+                        target='cpu.trap')
+        self.control_registers['cause'] = int(trap.cause)
+        self.control_registers['epc'] = resume_pc
+        self._saved_flags = dict(self.flags)
+        self.privilege = 'kernel'
+        self.in_trap = True
+        self.pc = self.labels[TRAP_VECTOR]
+        return True
+
+    def _syscall_fault(self, error: Exception, pc: int) -> bool:
+        """Stop on an error raised while servicing a built-in syscall, as execute_step does for other faults"""
+        self.logger.log(LogLevel.ERROR, f"Error executing instruction: {error}", target='cpu.exec')
+        self.last_error = error
+        self.running = False
+        self.events.publish(SimEvent.EXCEPTION, self.instruction_count, pc,
+                            cause=type(error).__name__, message=str(error), fatal=True)
+        return False
+
+    def _execute_builtin_syscall(self) -> bool:
+        """Service a SYSCALL in the simulator when the program has no kernel"""
+        number = self.registers['eax']
+        arg = self.registers['ebx']
+        if number == Syscall.PRINT_INT:
+            print(arg)
+        elif number == Syscall.SBRK:
+            self.registers['eax'] = self.get_heap().sbrk(arg)
+        elif number == Syscall.EXIT:
+            if self.scheduler and self.scheduler.exit_current(self):
+                return True
+            return self._halt('syscall')
+        elif number == Syscall.MALLOC:
+            self.registers['eax'] = self.get_heap().malloc(arg)
+        elif number == Syscall.FREE:
+            self.registers['eax'] = int(self.get_heap().free(arg))
+        else:
+            self.logger.log(LogLevel.ERROR, f"Error executing instruction: unknown syscall {number}", target='cpu.exec')
+            self.last_error = ValueError(f"unknown syscall {number}")
+            self.running = False
+            self.events.publish(SimEvent.EXCEPTION, self.instruction_count, self.pc,
+                                cause='ValueError', message=str(self.last_error), fatal=True)
+            return False
+
+        self.registers['eax'] = self._fit_word(self.registers['eax'], 'eax')
+        self._record_register_write('eax', self.pc - 1)
+        self.logger.log_register_operation('syscall', {
+            'dest': 'eax',
+            'value': self.registers['eax'],
+            'source': Syscall(number).name
+        })
+        return True
+
+    def get_heap(self) -> HeapAllocator:
+        """Return the heap allocator, creating it over the heap region on first use"""
+        if self.heap is None:
+            if self.heap_region:
+                start, limit = self.heap_region
+            else:
+                # Default: the third quarter of memory, below the stack
+                size = self.memory._size
+                start, limit = size // 2, size * 3 // 4
+            self.heap = HeapAllocator(self._read_physical, self._write_physical, start, limit)
+        return self.heap
+
+    def _tick_timer(self) -> None:
+        """Count down the timer and raise an interrupt when it expires"""
+        period = self.control_registers['timer']
+        if period <= 0 or self.pending_branch:
+            return  # No interrupts between a branch and its delay slot
+
+        self._timer_countdown -= 1
+        if self._timer_countdown > 0:
+            return
+
+        self._timer_countdown = period
+        if self.scheduler:
+            self.scheduler.context_switch(self)
+        elif self.control_registers['status'] & 1 and not self.in_trap:
+            self._take_trap(CPUTrap(TrapCause.INTERRUPT, "timer"), self.pc)
+
+    def _check_memory_access(self, addr: int, access: str) -> None:
+        """Trap on user-mode accesses to the kernel region and on region permission violations"""
+        if self.privilege == 'user' and self.kernel_region:
+            start, end = self.kernel_region
+            if start <= addr <= end:
+                raise CPUTrap(TrapCause.PROTECTION, f"user access to kernel address {addr}")
+        self._check_region_access(addr, access)
+
+    def _check_region_access(self, addr: int, access: str) -> None:
+        """Raise a segmentation fault if the first region containing addr forbids the access"""
+        for region in self.memory_regions:
+            if region.contains(addr):
+                if not region.allows(access):
+                    kind = {'r': 'read from', 'w': 'write to', 'x': 'execute from'}[access]
+                    raise CPUTrap(TrapCause.SEGFAULT, f"{kind} {region.permissions} region "
+                                                      f"'{region.name}' at address {addr}")
+                return
+
+    def _execute_eret(self) -> None:
+        """Execute ERET instruction - return to user mode at epc"""
+        self.pc = self.control_registers['epc']
+        self.flags = dict(self._saved_flags)
+        self.privilege = 'user'
+        self.in_trap = False
+
+    def _execute_mfc0(self, operands: List[str]) -> None:
+        """Execute MFC0 instruction - read a control register"""
+        if len(operands) != 2:
+            raise ValueError("MFC0 requires 2 operands")
+
+        dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid register: {dest}")
+        if src not in self.control_registers:
+            raise ValueError(f"Invalid control register: {src}")
+
+        self.registers[dest] = self.control_registers[src]
+        self.logger.log_register_operation('mfc0', {
+            'dest': dest,
+            'value': self.registers[dest],
+            'source': src
+        })
+
+    def _execute_mtc0(self, operands: List[str]) -> None:
+        """Execute MTC0 instruction - write a control register"""
+        if len(operands) != 2:
+            raise ValueError("MTC0 requires 2 operands")
+
+        dest, src = operands
+        if dest not in self.control_registers:
+            raise ValueError(f"Invalid control register: {dest}")
+
+        # Source may be a register, an immediate or a label (for epc)
+        if src.startswith('#'):
+            value = int(src[1:])
+        elif src in self.registers:
+            value = self.registers[src]
+        elif src in self.labels:
+            value = self.labels[src]
+        else:
+            raise ValueError(f"Invalid source operand: {src}")
+
+        self.control_registers[dest] = value
+        if dest == 'timer':
+            self._timer_countdown = value
+
+        self.logger.log_register_operation('mtc0', {
+            'dest': dest,
+            'value': value,
+            'source': src
+        })
+
+    # Floating-point coprocessor
+    @staticmethod
+    def _to_single(value: float) -> float:
+        """Round a Python float to IEEE-754 single precision"""
+        return struct.unpack('<f', struct.pack('<f', value))[0]
+
+    @staticmethod
+    def _float_to_bits(value: float) -> int:
+        """Return the 32-bit pattern of a single-precision float"""
+        return struct.unpack('<I', struct.pack('<f', value))[0]
+
+    @staticmethod
+    def _bits_to_float(bits: int) -> float:
+        """Interpret the low 32 bits of an integer as a single-precision float"""
+        return struct.unpack('<f', struct.pack('<I', bits & 0xFFFFFFFF))[0]
+
+    def _get_fp_operand(self, src: str) -> float:
+        """Get the value of an FP source operand (FP register or #immediate)"""
+        if src.startswith('#'):
+            return self._to_single(float(src[1:]))
+        if src not in self.fp_registers:
+            raise ValueError(f"Invalid FP register: {src}")
+        return self.fp_registers[src]
+
+    def _check_fp_register(self, reg: str) -> None:
+        if reg not in self.fp_registers:
+            raise ValueError(f"Invalid FP register: {reg}")
+
+    def _execute_mov_s(self, operands: List[str]) -> None:
+        """Execute MOV.S instruction"""
+        if len(operands) != 2:
+            raise ValueError("MOV.S requires 2 operands")
+
+        dest, src = operands
+        self._check_fp_register(dest)
+        value = self._get_fp_operand(src)
+        self.fp_registers[dest] = value
+
+        self.logger.log_register_operation('mov.s', {
+            'dest': dest,
+            'value': value,
+            'source': 'immediate' if src.startswith('#') else src
+        })
+
+    def _execute_load_s(self, operands: List[str]) -> None:
+        """Execute LOAD.S instruction"""
+        if len(operands) != 2:
+            raise ValueError("LOAD.S requires 2 operands")
+
+        dest, src = operands
+        self._check_fp_register(dest)
+        if not src.startswith('['):
+            raise ValueError("LOAD.S source must be a memory address")
+
+        addr = self._evaluate_address(src[1:-1])
+        bits = self._read_memory(addr)
+        value = self._bits_to_float(bits)
+        self.fp_registers[dest] = value
+
+        self.logger.log_register_operation('load.s', {
+            'dest': dest,
+            'value': value,
+            'source': f'memory[{addr}]'
+        })
+
+    def _execute_store_s(self, operands: List[str]) -> None:
+        """Execute STORE.S instruction"""
+        if len(operands) != 2:
+            raise ValueError("STORE.S requires 2 operands")
+
+        dest, src = operands
+        self._check_fp_register(src)
+        if not dest.startswith('['):
+            raise ValueError("STORE.S destination must be a memory address")
+
+        addr = self._evaluate_address(dest[1:-1])
+        bits = self._float_to_bits(self.fp_registers[src])
+        self._write_memory(addr, bits)
+        if self._exit_value is not None:  # Stored to exit_address: the exit code is the number, not its bits
+            self._exit_value = self.fp_registers[src]
+
+        self.logger.log_register_operation('store.s', {
+            'dest': f'memory[{addr}]',
+            'value': self.fp_registers[src],
+            'source': src
+        })
+
+    def _execute_fp_arith(self, op: InstructionType, operands: List[str]) -> None:
+        """Execute ADD.S, SUB.S, MUL.S or DIV.S instruction"""
+        name = op.name.replace('_', '.')
+        if len(operands) != 2:
+            raise ValueError(f"{name} requires 2 operands")
+
+        dest, src = operands
+        self._check_fp_register(dest)
+        a = self.fp_registers[dest]
+        b = self._get_fp_operand(src)
+
+        if op == InstructionType.ADD_S:
+            result = a + b
+        elif op == InstructionType.SUB_S:
+            result = a - b
+        elif op == InstructionType.MUL_S:
+            result = a * b
+        elif b == 0.0:
+            # IEEE-754 semantics instead of Python's ZeroDivisionError
+            result = math.nan if a == 0.0 or math.isnan(a) else math.copysign(math.inf, a) * math.copysign(1.0, b)
+        else:
+            result = a / b
+
+        try:
+            result = self._to_single(result)
+        except OverflowError:
+            result = math.copysign(math.inf, result)
+        self.fp_registers[dest] = result
+        self._record_alu(FP_OPERATORS[op], a, b, result, dest)
+
+        self.logger.log_register_operation(name.lower(), {
+            'dest': dest,
+            'value': result,
+            'source': src
+        })
+
+    def _execute_cvt_s_w(self, operands: List[str]) -> None:
+        """Execute CVT.S.W instruction - integer register to float"""
+        if len(operands) != 2:
+            raise ValueError("CVT.S.W requires 2 operands")
+
+        dest, src = operands
+        self._check_fp_register(dest)
+        if src not in self.registers:
+            raise ValueError(f"Invalid register: {src}")
+
+        value = self._to_single(float(self.registers[src]))
+        self.fp_registers[dest] = value
+
+        self.logger.log_register_operation('cvt.s.w', {
+            'dest': dest,
+            'value': value,
+            'source': src
+        })
+
+    def _execute_cvt_w_s(self, operands: List[str]) -> None:
+        """Execute CVT.W.S instruction - float to integer register (truncates toward zero)"""
+        if len(operands) != 2:
+            raise ValueError("CVT.W.S requires 2 operands")
+
+        dest, src = operands
+        if dest not in self.registers:
+            raise ValueError(f"Invalid register: {dest}")
+        self._check_fp_register(src)
+
+        value = self.fp_registers[src]
+        if math.isnan(value) or math.isinf(value):
+            raise ValueError(f"Cannot convert {value} in {src} to integer")
+        self.registers[dest] = int(value)
+
+        self.logger.log_register_operation('cvt.w.s', {
+            'dest': dest,
+            'value': self.registers[dest],
+            'source': src
+        })
+
+    def _execute_fp_compare(self, op: InstructionType, operands: List[str]) -> None:
+        """Execute C.EQ.S, C.LT.S or C.LE.S - sets the FP condition flag"""
+        name = op.name.replace('_', '.')
+        if len(operands) != 2:
+            raise ValueError(f"{name} requires 2 operands")
+
+        lhs, rhs = operands
+        self._check_fp_register(lhs)
+        a = self.fp_registers[lhs]
+        b = self._get_fp_operand(rhs)
+
+        if op == InstructionType.C_EQ_S:
+            self.fp_condition = a == b
+        elif op == InstructionType.C_LT_S:
+            self.fp_condition = a < b
+        else:
+            self.fp_condition = a <= b
+
+    def _execute_fp_branch(self, operands: List[str], on_true: bool) -> int:
+        """Execute BC1T/BC1F instruction"""
+        if len(operands) != 1:
+            raise ValueError(f"{'BC1T' if on_true else 'BC1F'} requires 1 operand")
+
+        label = operands[0]
+        if label not in self.labels:
+            raise ValueError(f"Unknown label: {label}")
+
+        if self.branch_taken(InstructionType.BC1T if on_true else InstructionType.BC1F):
+            return self.labels[label]
+        return self.pc
 
     def _print_cache_state(self):
         """Print detailed cache state information"""
@@ -614,10 +2255,13 @@ class SimpleISA:
             print(f"Hits: {l1_stats['hits']}")
             print(f"Misses: {l1_stats['misses']}")
             print(f"Hit Rate: {l1_stats['hit_rate']:.2f}%")
+            print(f"Reads: {l1_stats['read_hits']} hits, {l1_stats['read_misses']} misses")
+            print(f"Writes: {l1_stats['write_hits']} hits, {l1_stats['write_misses']} misses")
 
-            # Print L2 Cache State
-            if self.cache._next_level:
-                l2_state = self.cache._next_level.get_cache_state()
+            # Print L2 Cache State (behind the write buffer, if there is one)
+            l2 = self.write_buffer._next_level if self.write_buffer else self.cache._next_level
+            if hasattr(l2, 'get_cache_state'):
+                l2_state = l2.get_cache_state()
                 print("\nL2 Cache Contents:")
                 print("Set\tWay\tTag\tData")
                 print("-" * 30)
@@ -632,11 +2276,13 @@ class SimpleISA:
                         print(f"{set_idx}\t-\t-\tEmpty")
 
                 # Print L2 Cache Stats
-                l2_stats = self.cache._next_level.get_performance_stats()
+                l2_stats = l2.get_performance_stats()
                 print(f"\nL2 Cache Stats:")
                 print(f"Hits: {l2_stats['hits']}")
                 print(f"Misses: {l2_stats['misses']}")
                 print(f"Hit Rate: {l2_stats['hit_rate']:.2f}%")
+                print(f"Reads: {l2_stats['read_hits']} hits, {l2_stats['read_misses']} misses")
+                print(f"Writes: {l2_stats['write_hits']} hits, {l2_stats['write_misses']} misses")
 
         print("\n=== END CACHE STATE ===\n")
 
@@ -645,50 +2291,390 @@ class SimpleISA:
         print("\n=== REGISTER STATE ===")
         for reg, value in self.registers.items():
             print(f"{reg}: {value}")
+        for reg, value in self.fp_registers.items():
+            if value != 0.0:
+                print(f"{reg}: {value}")
+        print(f"fcc: {int(self.fp_condition)}")
+        print(f"flags: {self.format_flags()}")
         print("=== END REGISTER STATE ===\n")
 
+    # Uninitialized-read detection
+    def register_reads(self, instruction: Instruction) -> List[str]:
+        """Registers an instruction reads, including address registers"""
+        reads = []
+        for i, operand in enumerate(instruction.operands):
+            if operand.startswith('['):
+                operand = operand[1:-1]
+            elif i == 0 and instruction.type not in DEST_READ_INSTRUCTIONS | {InstructionType.JR}:
+                continue
+            if operand in self.registers:
+                reads.append(operand)
+        if instruction.type in (InstructionType.JZ, InstructionType.JNZ, InstructionType.SYSCALL):
+            reads.append('eax')
+        return reads
+
+    def register_writes(self, instruction: Instruction) -> List[str]:
+        """Registers an instruction writes"""
+        writes = []
+        if (instruction.type in DEST_WRITE_INSTRUCTIONS and instruction.operands
+                and instruction.operands[0] in self.registers):
+            writes.append(instruction.operands[0])
+        if instruction.type == InstructionType.CMP and self.isa_mode == 'standard':
+            writes.append('eax')
+        if instruction.type == InstructionType.JAL:
+            writes.append(LINK_REGISTER)
+        return writes
+
+    def _check_register_reads(self, instruction: Instruction) -> None:
+        """Count register reads and warn about registers that were never written"""
+        for reg in self.register_reads(instruction):
+            self.register_usage[reg]['reads'] += 1
+            if reg not in self.initialized_registers:
+                self._warn_uninitialized('registers', f"register {reg}")
+
+    def _mark_register_writes(self, instruction: Instruction, pc: int) -> None:
+        for reg in self.register_writes(instruction):
+            self._record_register_write(reg, pc)
+        if (instruction.type in FP_WRITE_INSTRUCTIONS and instruction.operands
+                and instruction.operands[0] in self.fp_registers):
+            self.register_writers[instruction.operands[0]] = self._write_record(pc)
+
+    def _record_register_write(self, reg: str, pc: int) -> None:
+        self.register_usage[reg]['writes'] += 1
+        self.initialized_registers.add(reg)
+        self.register_writers[reg] = self._write_record(pc)
+
+    def _write_record(self, pc: int) -> WriteRecord:
+        write = WriteRecord(pc, self.instruction_count, self.instruction_count)
+        self._step_writes.append(write)
+        return write
+
+    def last_writer(self, location: str) -> Optional[WriteRecord]:
+        """The instruction that last wrote a register, or a memory word given as ADDR or [ADDR] (decimal or 0x hex)
+
+        None if the program has not written it; ValueError if it is
+        neither a register nor an address in memory.
+        """
+        location = location.strip()
+        if location in self.registers or location in self.fp_registers:
+            return self.register_writers.get(location)
+        try:
+            addr = int(location.strip('[]').strip(), 0)
+        except ValueError:
+            raise ValueError(f"'{location}' is neither a register nor an address") from None
+        if not 0 <= addr < len(self.memory.data):
+            raise ValueError(f"Address {addr} is outside memory (0-{len(self.memory.data) - 1})")
+        return self.memory_writers.get(addr)
+
+    def get_register_usage(self):
+        """Return per-register read/write counts with unused and dead-write summaries"""
+        counts = {reg: dict(usage) for reg, usage in self.register_usage.items()}
+        used = [reg for reg, c in counts.items() if c['reads'] + c['writes'] > 0]
+        return {
+            'counts': counts,
+            'never_used': [reg for reg in counts if reg not in used],
+            'most_used': sorted(used, key=lambda reg: counts[reg]['reads'] + counts[reg]['writes'], reverse=True)[:3],
+            'written_never_read': [reg for reg, c in counts.items() if c['writes'] > 0 and c['reads'] == 0]
+        }
+
+    # Loop detection
+    def _track_loops(self, instruction: Instruction, branch_pc: int) -> None:
+        """Record taken backward branches as loops and notice when execution leaves them"""
+        for (start, end), loop in self.loops.items():
+            if loop['active'] and not start <= self.pc <= end:
+                loop['active'] = False
+
+        # A call is not a loop, even to a subroutine placed before its caller
+        if (instruction.type in BRANCH_INSTRUCTIONS and instruction.type != InstructionType.JAL
+                and self.pc <= branch_pc):
+            loop = self.loops.setdefault((self.pc, branch_pc), {'back_edges': 0, 'entries': 0, 'active': False})
+            loop['back_edges'] += 1
+            if not loop['active']:
+                loop['entries'] += 1
+                loop['active'] = True
+
+    def get_loop_stats(self) -> List[Dict]:
+        """Per-loop iteration counts and share of executed instructions spent in the loop body
+
+        Each instruction counts as one cycle, so the share is also the share of cycles.
+        Nested loops are included in their enclosing loop's share.
+        """
+        names = {index: label for label, index in self.labels.items()}
+        stats = []
+        for (start, end), loop in sorted(self.loops.items()):
+            executed = sum(self.pc_exec_counts.get(pc, 0) for pc in range(start, end + 1))
+            stats.append({
+                'start': start,
+                'end': end,
+                'label': names.get(start, f"pc {start}"),
+                'entries': loop['entries'],
+                'iterations': loop['back_edges'] + loop['entries'],  # The final pass falls through
+                'instructions': executed,
+                'share': executed / self.measured_instructions * 100 if self.measured_instructions else 0.0
+            })
+        return stats
+
+    def _enforce_zero_register(self) -> None:
+        """Discard any value an instruction wrote to the hardwired zero register"""
+        if self.hardwired_zero and self.registers[ZERO_REGISTER] != 0:
+            self.logger.log(LogLevel.INFO, f"Write of {self.registers[ZERO_REGISTER]} to {ZERO_REGISTER} discarded",
+                            target='cpu.exec')
+            self.registers[ZERO_REGISTER] = 0
+
+    def _warn_uninitialized(self, kind: str, location: str) -> None:
+        self.uninitialized_reads[kind] += 1
+        message = f"Read of uninitialized {location}"
+        self.step_warnings.append(message)
+        self.logger.log(LogLevel.WARNING, message, target='cpu.exec')
+
+    def _read_memory(self, addr: int) -> int:
+        """Read a data word through the cache hierarchy"""
+        self._check_memory_access(addr, 'r')
+        self._count_data_access('r', addr)
+        if addr not in self.initialized_memory:
+            self._warn_uninitialized('memory', f"memory[{addr}]")
+        return self._read_physical(addr)
+
+    def _write_memory(self, addr: int, value: int) -> None:
+        """Write a data word through the cache and on to main memory"""
+        self._check_memory_access(addr, 'w')
+        self._count_data_access('w', addr)
+        if self.memory_organization == 'von_neumann' and 0 <= addr - self.text_base < len(self.instructions):
+            message = (f"Write to memory[{addr}] overwrites instruction {addr - self.text_base} "
+                       f"(code and data share memory)")
+            self.step_warnings.append(message)
+            self.logger.log(LogLevel.WARNING, message, target='cpu.exec')
+        self.memory_writers[addr] = self._write_record(self.pc - 1)
+        self._write_physical(addr, self._fit_word(value, f"memory[{addr}]"))
+
+    def _count_data_access(self, access: str, addr: int) -> None:
+        """Log a data access; with one memory port each one holds up an instruction fetch"""
+        self.step_accesses.append((access, addr))
+        self.memory_ports['data_accesses'] += 1
+        if self.memory_organization == 'von_neumann':
+            self.memory_ports['port_conflicts'] += 1
+
+    def instruction_index(self, location: str) -> int:
+        """Index of the instruction at a label or an instruction address (decimal or 0x hex, .text base included)"""
+        location = location.strip()
+        if location in self.labels:
+            return self.labels[location]
+        try:
+            index = int(location, 0) - self.text_base
+        except ValueError:
+            raise ValueError(f"'{location}' is neither a label nor an address") from None
+        if not 0 <= index < len(self.instructions):
+            raise ValueError(f"No instruction at address {location} (instructions are at {self.text_base}-"
+                             f"{self.text_base + len(self.instructions) - 1})")
+        return index
+
+    def memory_kind(self, addr: int) -> str:
+        """What a memory word holds, one of MEMORY_KINDS
+
+        The program's text, .mmio device ranges, the .stack range and the
+        heap are classified by region whether or not they were written;
+        any other word is 'data' once written and 'untouched' before.
+        """
+        if self.memory_organization == 'von_neumann' and 0 <= addr - self.text_base < len(self.instructions):
+            return 'instruction'
+        if any(start <= addr <= end for start, end in self.mmio_ranges):
+            return 'mmio'
+        if self.stack_region and self.stack_region[0] <= addr <= self.stack_region[1]:
+            return 'stack'
+        if self.heap and self.heap.start <= addr < self.heap.limit:
+            return 'heap'
+        return 'data' if addr in self.initialized_memory else 'untouched'
+
+    @property
+    def measured_instructions(self) -> int:
+        """Instructions executed since the statistics were last reset"""
+        return self.instruction_count - self.stats_reset_at
+
+    def reset_statistics(self) -> None:
+        """Zero the performance counters to start a measurement, e.g. after warm-up or setup code
+
+        Covers every cache level, the write buffer and main memory (with its
+        DRAM row counters), the pipeline and LSQ models, memory port use and
+        loop counts. What the earlier code left behind is kept: cached lines,
+        open DRAM rows and instructions still in the pipeline, so the
+        measured code runs on a warm machine. Correctness checks such as
+        uninitialized reads and register use are not statistics and are kept.
+        """
+        self.stats_reset_at = self.instruction_count
+        self.memory_ports = {'instruction_fetches': 0, 'data_accesses': 0, 'port_conflicts': 0}
+        self.pc_exec_counts = {}
+        for loop in self.loops.values():
+            loop['back_edges'] = 0
+            loop['entries'] = 1 if loop['active'] else 0  # The pass in progress still counts
+        self.uncached_accesses = 0
+        levels = []
+        level = self.cache
+        while level is not None and level not in levels:
+            levels.append(level)
+            level = getattr(level, '_next_level', None)
+        if self.memory is not None and self.memory not in levels:
+            levels.append(self.memory)
+        for level in levels:
+            level.reset_stats()
+        if self.pipeline:
+            self.pipeline.reset_stats()
+        if self.lsq:
+            self.lsq.reset_stats()
+        self.events.publish(SimEvent.STATS_RESET, self.instruction_count, self.pc)
+
+    def get_memory_port_stats(self):
+        """Fetches, data accesses and the cycles they cost under the current memory organization"""
+        stats = dict(self.memory_ports)
+        stats['organization'] = self.memory_organization
+        # One cycle per instruction, plus one per fetch that had to wait for the shared port
+        stats['cycles'] = self.measured_instructions + stats['port_conflicts']
+        return stats
+
+    def _stall_for_memory(self, addr: int, uncached: bool = False) -> None:
+        """Charge the miss penalty when an access will not be served by L1, probing before the access changes it"""
+        if self.miss_penalty and self.cache and (uncached or not self.cache.contains(addr)):
+            self.step_stall += self.miss_penalty
+            self.cycle += self.miss_penalty
+            self.memory_stall_cycles += self.miss_penalty
+
+    def _read_physical(self, addr: int) -> int:
+        if self.cache and self.is_uncacheable(addr):
+            self.uncached_accesses += 1
+            self._publish_cache_access('r', addr, uncached=True)
+            self._stall_for_memory(addr, uncached=True)
+            return self.memory.read(addr)
+        self._publish_cache_access('r', addr)
+        self._stall_for_memory(addr)
+        return self.cache.read(addr) if self.cache else self.memory.read(addr)
+
+    def _write_physical(self, addr: int, value: int) -> None:
+        self.initialized_memory.add(addr)
+        if addr == self.exit_address:
+            self._exit_value = value
+        if self.cache:
+            if self.is_uncacheable(addr):
+                self.uncached_accesses += 1
+                self._publish_cache_access('w', addr, uncached=True)
+                self._stall_for_memory(addr, uncached=True)
+            else:
+                self._publish_cache_access('w', addr)
+                self._stall_for_memory(addr)
+                self.cache.write(addr, value)
+        self.memory.write(addr, value)
+        self.events.publish(SimEvent.MEMORY_WRITE, self.instruction_count, self.pc - 1, address=addr, value=value)
+
+    def _publish_cache_access(self, access: str, addr: int, uncached: bool = False) -> None:
+        """Announce a data access to the L1 cache, probing for a hit before the access changes it"""
+        if not self.cache or not self.events.wants(SimEvent.CACHE_ACCESS):
+            return
+        self.events.publish(SimEvent.CACHE_ACCESS, self.instruction_count, self.pc - 1, access=access, address=addr,
+                            cache=None if uncached else self.cache._name,
+                            hit=False if uncached else self.cache.contains(addr))
+
     def _evaluate_address(self, expr: str) -> int:
-        """Evaluate a memory address expression"""
+        """Evaluate a memory address expression and relocate it by the base register"""
+        return self._relocate(self._address_value(expr))
+
+    def _address_value(self, expr: str) -> int:
         # Simple address evaluation - can be extended for more complex expressions
         if expr.isdigit():
             return int(expr)
         return self.registers.get(expr, 0)
 
-    def _print_state(self) -> None:
-        """Print the current state of the CPU and memory"""
-        print("\nCPU State:")
-        print(f"PC: {self.pc}")
-        print("Registers:")
-        for reg, value in self.registers.items():
-            print(f"  {reg}: {value}")
+    def _relocate(self, addr: int) -> int:
+        """Check an address against the bounds register and add the base register"""
+        if self.bounds_register is not None and not 0 <= addr < self.bounds_register:
+            raise CPUTrap(TrapCause.PROTECTION, f"address {addr} outside bounds {self.bounds_register}")
+        return self.base_register + addr
 
-        print("\nCache Performance:")
-        if self.cache:
-            try:
-                stats = self.cache.get_performance_stats()
-                print(f"  Hits: {stats.get('hits', 0)}")
-                print(f"  Misses: {stats.get('misses', 0)}")
-                print(f"  Hit Rate: {stats.get('hit_rate', 0.0):.2f}%")
-            except Exception as e:
-                print(f"  Error getting cache stats: {str(e)}")
-        else:
-            print("  No cache present")
+    def run(self, fast: bool = False) -> None:
+        """Run the loaded program
 
-    def run(self) -> None:
-        """Run the loaded program"""
+        With fast set, the logger and caches skip all message formatting below
+        ERROR and all operation recording until the program ends; results and
+        statistics are unchanged.
+        """
         self.running = True
         self.start_time = time()
         self.instruction_count = 0
+        self.cycle = 0
+        self.memory_stall_cycles = 0
+        self.stats_reset_at = 0
 
-        while self.running:
-            if not self.execute_step():
-                break
+        fast_mode = self.logger.fast_mode
+        self.logger.fast_mode = fast
+        try:
+            while self.running:
+                if not self.execute_step():
+                    break
+        finally:
+            self.logger.fast_mode = fast_mode
 
         self.end_time = time()
         exec_time = self.end_time - self.start_time
         ips = self.instruction_count / exec_time if exec_time > 0 else 0
 
-        self.logger.log(LogLevel.INFO, "\nProgram completed:")
-        self.logger.log(LogLevel.INFO, f"Instructions executed: {self.instruction_count}")
-        self.logger.log(LogLevel.INFO, f"Execution time: {exec_time:.6f}s")
-        self.logger.log(LogLevel.INFO, f"Instructions per second: {ips:.2f}")
+        self.logger.log(LogLevel.INFO, "\nProgram completed:", target='cpu.summary')
+        if self.halt_reason:
+            self.logger.log(LogLevel.INFO, f"Halted: {self.halt_description()}", target='cpu.summary')
+        self.logger.log(LogLevel.INFO, f"Instructions executed: {self.instruction_count}", target='cpu.summary')
+        if self.stats_reset_at:
+            self.logger.log(LogLevel.INFO, f"Statistics reset after {self.stats_reset_at} instructions; "
+                            f"the figures below cover the last {self.measured_instructions}", target='cpu.summary')
+        if self.miss_penalty:
+            self.logger.log(LogLevel.INFO, f"Cycles: {self.cycle}, {self.memory_stall_cycles} of them stalled waiting "
+                            f"for memory ({self.miss_penalty} per L1 miss)", target='cpu.summary')
+        self.logger.log(LogLevel.INFO, f"Execution time: {exec_time:.6f}s", target='cpu.summary')
+        self.logger.log(LogLevel.INFO, f"Instructions per second: {ips:.2f}", target='cpu.summary')
+        for loop in self.get_loop_stats():
+            self.logger.log(LogLevel.INFO, f"Loop {loop['label']} ({loop['start']}-{loop['end']}): "
+                            f"{loop['iterations']} iterations, {loop['share']:.1f}% of cycles", target='cpu.summary')
+        usage = self.get_register_usage()
+        self.logger.log(LogLevel.INFO, f"Most used registers: {', '.join(usage['most_used']) or 'none'}",
+                        target='cpu.summary')
+        self.logger.log(LogLevel.INFO, f"Registers never used: {', '.join(usage['never_used']) or 'none'}",
+                        target='cpu.summary')
+        if usage['written_never_read']:
+            self.logger.log(LogLevel.INFO, f"Registers written but never read: {', '.join(usage['written_never_read'])}",
+                            target='cpu.summary')
+        ports = self.get_memory_port_stats()
+        self.logger.log(LogLevel.INFO, f"Memory organization: {ports['organization']}, "
+                        f"port conflicts: {ports['port_conflicts']}, cycles: {ports['cycles']}", target='cpu.summary')
+        if hasattr(self.memory, 'dram'):
+            dram = self.memory.dram.get_stats()
+            self.logger.log(LogLevel.INFO, f"DRAM row hits: {dram['hits']}, misses: {dram['misses']}, "
+                            f"conflicts: {dram['conflicts']}, total latency: {dram['total_latency']}ns",
+                            target='cpu.summary')
+        if self.write_buffer:
+            stats = self.write_buffer.get_performance_stats()
+            self.logger.log(LogLevel.INFO, f"Write buffer: {stats['writes']} writes, {stats['full_stalls']} full stalls "
+                            f"({stats['stall_cycles']} cycles), peak occupancy {stats['peak_occupancy']}/{stats['capacity']}",
+                            target='cpu.summary')
+        if self.pipeline:
+            stats = self.pipeline.get_stats()
+            self.logger.log(LogLevel.INFO, f"Pipeline cycles: {stats['cycles']}, CPI: {stats['cpi']:.2f}, "
+                            f"stalls: {stats['stall_cycles']}, branch bubbles: {stats['branch_bubbles']}",
+                            target='cpu.summary')
+        control = self.get_cache_control_stats()
+        if control and any(control.values()):
+            self.logger.log(LogLevel.INFO, f"Prefetches: {control['prefetches']} ({control['useful_prefetches']} useful), "
+                            f"lines flushed: {control['flushes']}, invalidated: {control['invalidations']}",
+                            target='cpu.summary')
+        for name, requesters in self.get_cache_requester_stats():
+            if format_requesters(requesters):
+                self.logger.log(LogLevel.INFO, f"{name} hits by requester: {format_requesters(requesters)}",
+                                target='cpu.summary')
+        for level in self.cache_levels():
+            self.logger.log(LogLevel.INFO, f"{level._name}: {format_read_write(level.get_performance_stats())}",
+                            target='cpu.summary')
+        if self.cache:
+            levels = amat_levels(self.cache)
+            self.logger.log(LogLevel.INFO, f"AMAT: {formula_terms(levels)[1]} = {compute_amat(levels):.2f} ns",
+                            target='cpu.summary')
+        if any(self.uninitialized_reads.values()):
+            self.logger.log(LogLevel.WARNING, f"Uninitialized reads: {self.uninitialized_reads['registers']} register, "
+                            f"{self.uninitialized_reads['memory']} memory", target='cpu.summary')
+        if self.heap:
+            stats = self.heap.get_stats()
+            self.logger.log(LogLevel.INFO, f"Heap allocations: {stats['mallocs']}, frees: {stats['frees']}, "
+                            f"peak use: {stats['peak_words_in_use']} words", target='cpu.summary')
@@ -0,0 +1,208 @@
+import argparse
+import os
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Set
+
+from isa import BRANCH_INSTRUCTIONS, InstructionType, SimpleISA, expand_macros
+from locality import build_hierarchy
+from utils.files import check_stdin_once, display_name, read_lines
+from utils.logger import Logger, LogLevel
+
+# Directives only the linker reads; the assembler never sees them
+LINKER_DIRECTIVES = ('.global', '.extern')
+
+class LinkError(ValueError):
+    pass
+
+@dataclass
+class Module:
+    """One source file of a project: its labels, what it exports and what it needs from other files"""
+    name: str
+    lines: List[str]                                      # With macros expanded
+    labels: Dict[str, int] = field(default_factory=dict)  # Label -> instruction index within the module
+    exports: Set[str] = field(default_factory=set)        # .global names, visible to every module
+    imports: Set[str] = field(default_factory=set)        # .extern names, defined by another module
+    size: int = 0                                         # Instructions
+    base: int = 0                                         # Index of the first instruction once linked
+    line_numbers: List[int] = field(default_factory=list) # Source line index of each line
+
+def read_module(name: str, lines: List[str]) -> Module:
+    """Find a file's labels, .global and .extern names and instruction count
+
+    Every line that is not blank, a comment, a directive or a label is one
+    instruction, as the assembler counts them once macros are expanded.
+    """
+    expanded, errors = expand_macros(lines)
+    for number, message in errors:
+        raise LinkError(f"{name}:{number + 1}: {message}")
+    module = Module(name, [line for _, line in expanded], line_numbers=[number for number, _ in expanded])
+    for number, line in zip(module.line_numbers, module.lines):
+        text = line.split(';')[0].strip()
+        if not text:
+            continue
+        if text.startswith('.'):
+            directive = text.split()
+            if directive[0].lower() in LINKER_DIRECTIVES:
+                if len(directive) < 2:
+                    raise LinkError(f"{name}:{number + 1}: {directive[0]} needs a symbol name")
+                names = {n for part in directive[1:] for n in part.split(',') if n}
+                (module.exports if directive[0].lower() == '.global' else module.imports).update(names)
+        elif text.endswith(':'):
+            label = text[:-1].strip()
+            if label in module.labels:
+                raise LinkError(f"{name}:{number + 1}: label '{label}' defined twice")
+            module.labels[label] = module.size
+        else:
+            module.size += 1
+    for symbol in sorted(module.exports - set(module.labels)):
+        raise LinkError(f"{name}: .global {symbol} is not defined in this file")
+    for symbol in sorted(module.imports & set(module.labels)):
+        raise LinkError(f"{name}: .extern {symbol} is also defined in this file")
+    return module
+
+@dataclass
+class LinkedProgram:
+    """The linked assembly program and what the map file reports"""
+    lines: List[str]
+    modules: List[Module]
+    symbols: Dict[str, int]      # Linked label -> instruction index
+    origins: Dict[str, str]      # Linked label -> module that defined it
+    text_base: int = 0           # Memory word of instruction 0 (from a .text directive)
+
+def link(modules: List[Module]) -> LinkedProgram:
+    """Lay the modules out one after the other and resolve their symbols into one program
+
+    The first module comes first, so execution starts there (unless a .entry
+    directive says otherwise). .global labels keep their names and must be
+    unique; a local label another module also defines is renamed to
+    module.label. Every .extern must be some module's .global, and every
+    branch target must be a label of its module or one of its .externs.
+    """
+    defined: Dict[str, str] = {}
+    for module in modules:
+        for symbol in module.exports:
+            if symbol in defined:
+                raise LinkError(f"symbol '{symbol}' is exported by both {defined[symbol]} and {module.name}")
+            defined[symbol] = module.name
+    for module in modules:
+        for symbol in sorted(module.imports - set(defined)):
+            raise LinkError(f"{module.name}: undefined external symbol '{symbol}'")
+
+    local_counts: Dict[str, int] = {}
+    for module in modules:
+        for label in set(module.labels) - module.exports:
+            local_counts[label] = local_counts.get(label, 0) + 1
+    clashes = {label for label, count in local_counts.items() if count > 1 or label in defined}
+
+    lines: List[str] = []
+    symbols: Dict[str, int] = {}
+    origins: Dict[str, str] = {}
+    text_base = 0
+    base = 0
+    for module in modules:
+        module.base = base
+        prefix = os.path.splitext(module.name)[0]
+        renames = {label: f"{prefix}.{label}" for label in module.labels
+                   if label in clashes and label not in module.exports}
+        for label, index in module.labels.items():
+            name = renames.get(label, label)
+            symbols[name] = base + index
+            origins[name] = module.name
+        known = set(module.labels) | module.imports
+
+        lines.append(f"; ---- {module.name}: instructions {base}-{base + module.size - 1} ----")
+        for number, line in zip(module.line_numbers, module.lines):
+            text = line.split(';')[0].strip()
+            if not text:
+                lines.append(line)
+                continue
+            if text.startswith('.'):
+                directive = text.split()
+                if directive[0].lower() in LINKER_DIRECTIVES:
+                    lines.append(f"; {text}")
+                    continue
+                if directive[0].lower() == '.text' and len(directive) == 2 and directive[1].isdigit():
+                    text_base = int(directive[1])
+                lines.append(line)
+            elif text.endswith(':'):
+                label = text[:-1].strip()
+                lines.append(f"{renames.get(label, label)}:")
+            else:
+                parts = text.split()
+                try:
+                    branch = InstructionType[parts[0].upper().replace('.', '_')] in BRANCH_INSTRUCTIONS
+                except KeyError:
+                    branch = False  # The assembler reports it
+                if branch and len(parts) > 1 and parts[1] not in known:
+                    raise LinkError(f"{module.name}:{number + 1}: undefined label '{parts[1]}' "
+                                    "(declare it .extern if another file defines it)")
+                comment = line[line.index(';'):] if ';' in line else ''
+                lines.append(' '.join([parts[0]] + [renames.get(p, p) for p in parts[1:]])
+                             + (f"  {comment}" if comment else ''))
+        base += module.size
+    return LinkedProgram(lines, modules, symbols, origins, text_base)
+
+def link_files(paths: List[str]) -> LinkedProgram:
+    modules = []
+    for path in paths:
+        modules.append(read_module(os.path.basename(display_name(path)), read_lines(path)))
+    return link(modules)
+
+def format_map(program: LinkedProgram) -> List[str]:
+    """The map file: where each module and symbol ended up"""
+    base = program.text_base
+    lines = [f"Link map ({sum(m.size for m in program.modules)} instructions, text at word {base})", "",
+             "Modules:", f"  {'module':<20} {'instructions':>13} {'words':>13}"]
+    for module in program.modules:
+        end = module.base + module.size - 1
+        lines.append(f"  {module.name:<20} {f'{module.base}-{end}':>13} {f'{base + module.base}-{base + end}':>13}")
+    exports = {symbol for module in program.modules for symbol in module.exports}
+    lines += ["", "Symbols:", f"  {'index':>5} {'word':>5}  {'symbol':<20} {'module':<20} scope"]
+    for symbol, index in sorted(program.symbols.items(), key=lambda item: (item[1], item[0])):
+        lines.append(f"  {index:5d} {base + index:5d}  {symbol:<20} {program.origins[symbol]:<20} "
+                     f"{'global' if symbol in exports else 'local'}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Link several assembly files into one program, resolving "
+                                                 ".global/.extern symbols, and write a map file")
+    parser.add_argument('files', nargs='+', help="Assembly files; the first one holds the program's start, "
+                                                 "and - reads one from standard input")
+    parser.add_argument('-o', '--output', default='linked.txt', metavar='FILE',
+                        help="Linked program to write (default: linked.txt)")
+    parser.add_argument('--map', metavar='FILE', help="Map file to write (default: the output with .map)")
+    parser.add_argument('--run', action='store_true', help="Also run the linked program and print its registers")
+    args = parser.parse_args()
+    check_stdin_once(parser, args.files)
+
+    try:
+        program = link_files(args.files)
+    except (OSError, LinkError) as e:
+        print(f"Link failed: {e}")
+        return 1
+    map_path = args.map or os.path.splitext(args.output)[0] + '.map'
+    with open(args.output, 'w') as f:
+        f.write('\n'.join(program.lines) + '\n')
+    with open(map_path, 'w') as f:
+        f.write('\n'.join(format_map(program)) + '\n')
+    print(f"Linked {len(args.files)} files into {args.output} "
+          f"({sum(m.size for m in program.modules)} instructions), map in {map_path}")
+
+    if args.run:
+        memory, l1, _ = build_hierarchy()
+        isa = SimpleISA(memory=memory, cache=l1)
+        logger = Logger()
+        level = logger.log_level
+        logger.log_level = LogLevel.ERROR
+        try:
+            isa.load_program(program.lines)
+            isa.run(fast=True)
+        finally:
+            logger.log_level = level
+        print(f"Ran {isa.instruction_count} instructions: "
+              + "  ".join(f"{reg}={value}" for reg, value in isa.registers.items()))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
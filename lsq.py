@@ -0,0 +1,172 @@
+import argparse
+import sys
+from dataclasses import dataclass
+from typing import Dict, List, Optional, Tuple
+
+from isa import SimpleISA, Instruction
+from dependency import instruction_accesses
+from utils.files import read_lines
+
+@dataclass
+class MemoryOp:
+    """A load or store as it passed through the load/store queue"""
+    seq: int
+    pc: int
+    source: str
+    kind: str                 # load or store
+    address: int
+    dispatch: int             # Cycle it entered the queue (program order)
+    address_ready: int        # Cycle its address was computed
+    execute: int              # Cycle a load read memory / a store's data was ready
+    commit: int               # Cycle it retired; stores write memory here
+    outcome: str = ""         # forwarded, violation, memory or store
+    partner: Optional[int] = None  # seq of the store a load forwarded from or conflicted with
+
+class LoadStoreQueue:
+    """Load/store queue of an out-of-order core with speculative memory disambiguation
+
+    Instructions dispatch one per cycle in program order (a load or store
+    waits for a free queue entry) and execute as soon
+    as their source registers are ready (loads take LOAD_LATENCY cycles,
+    everything else one). Stores write memory when they commit, in program
+    order. A load issues as soon as its own address is known:
+      - if the youngest older uncommitted store to the same address already
+        has its address, the store's data is forwarded to the load
+      - if that store's address was still unknown when the load issued, the
+        load speculatively read stale memory: a memory-ordering violation,
+        and the load is replayed once the store resolves
+      - otherwise the load reads memory (or the cache)
+    """
+
+    LOAD_LATENCY = 3
+
+    def __init__(self, isa: SimpleISA, capacity: int = 8):
+        self.isa = isa
+        self.capacity = capacity  # Loads and stores in flight at once
+        self.reset()
+
+    def reset(self) -> None:
+        self.ops: List[MemoryOp] = []
+        self._ready: Dict[str, int] = {}  # Register -> cycle its latest value is available
+        self._seq = 0
+        self._last_dispatch = 0
+        self._last_commit = 0
+        self.measured_from = 0  # First op the statistics cover
+        self.full_stalls = 0
+
+    def reset_stats(self) -> None:
+        """Count statistics from the next load or store on; ops in flight still order later ones"""
+        self.measured_from = len(self.ops)
+        self.full_stalls = 0
+
+    def _operands_ready(self, registers) -> int:
+        return max((self._ready.get(reg, 0) for reg in registers), default=0)
+
+    def record(self, instruction: Instruction, pc: int, accesses: List[Tuple[str, int]]) -> None:
+        """Schedule an executed instruction given the data addresses it accessed"""
+        isa = self.isa
+        seq = self._seq
+        self._seq += 1
+        dispatch = self._last_dispatch + 1
+        if accesses and len(self.ops) >= self.capacity:
+            # Wait for the oldest in-flight entry to retire
+            oldest_commit = self.ops[-self.capacity].commit
+            if oldest_commit >= dispatch:
+                self.full_stalls += oldest_commit + 1 - dispatch
+                dispatch = oldest_commit + 1
+        self._last_dispatch = dispatch
+        reads, writes = instruction_accesses(isa, instruction)
+        reads = {r for r in reads if not r.startswith('mem[')}
+        writes = {w for w in writes if not w.startswith('mem[')}
+        address_registers = [op[1:-1] for op in instruction.operands
+                             if op.startswith('[') and op[1:-1] in isa.registers]
+        address_ready = max(dispatch, self._operands_ready(address_registers)) + 1
+        operands_ready = max(dispatch, self._operands_ready(reads))
+
+        loads = [addr for access, addr in accesses if access == 'r']
+        stores = [addr for access, addr in accesses if access == 'w']
+        if loads:
+            complete = self._issue_load(seq, pc, instruction, loads[0], dispatch, address_ready)
+        else:
+            complete = operands_ready + 1
+        complete = max(complete, address_ready if stores else 0)
+        commit = max(self._last_commit + 1, complete)
+        self._last_commit = commit
+        for op in self.ops:
+            if op.seq == seq:
+                op.commit = commit
+        for addr in stores:
+            self.ops.append(MemoryOp(seq, pc, instruction.source, 'store', addr, dispatch,
+                                     address_ready, operands_ready, commit, 'store'))
+
+        for reg in writes:
+            self._ready[reg] = complete
+
+    def _issue_load(self, seq: int, pc: int, instruction: Instruction, address: int,
+                    dispatch: int, address_ready: int) -> int:
+        issue = address_ready
+        older = [op for op in self.ops[-self.capacity:]
+                 if op.kind == 'store' and op.address == address and op.commit > issue]
+        op = MemoryOp(seq, pc, instruction.source, 'load', address, dispatch, address_ready, issue, 0, 'memory')
+        if older:
+            store = older[-1]
+            op.partner = store.seq
+            if store.address_ready <= issue:
+                op.outcome = 'forwarded'
+                op.execute = max(issue, store.execute)
+            else:
+                # Replay after the store's address resolves and its data is forwarded
+                op.outcome = 'violation'
+                op.execute = max(store.address_ready, store.execute) + 1
+        self.ops.append(op)
+        return op.execute + (1 if op.outcome == 'forwarded' else self.LOAD_LATENCY)
+
+    def get_stats(self):
+        ops = self.ops[self.measured_from:]
+        loads = [op for op in ops if op.kind == 'load']
+        return {
+            'loads': len(loads),
+            'stores': sum(1 for op in ops if op.kind == 'store'),
+            'forwarded': sum(1 for op in loads if op.outcome == 'forwarded'),
+            'violations': sum(1 for op in loads if op.outcome == 'violation'),
+            'from_memory': sum(1 for op in loads if op.outcome == 'memory'),
+            'full_stalls': self.full_stalls
+        }
+
+    def describe(self, op: MemoryOp) -> str:
+        if op.outcome == 'forwarded':
+            return f"forwarded from store #{op.partner}"
+        if op.outcome == 'violation':
+            return f"ordering violation with store #{op.partner}, replayed"
+        if op.outcome == 'memory':
+            return "read from memory"
+        return "writes memory at commit"
+
+def main():
+    parser = argparse.ArgumentParser(description="Show load/store queue forwarding and ordering violations")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--capacity', type=int, default=8, help="Loads and stores in flight at once")
+    args = parser.parse_args()
+
+    from memory import MainMemory
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+    isa.lsq = LoadStoreQueue(isa, args.capacity)
+    isa.run()
+
+    print(f"{'#':>3} {'instruction':<24} {'addr':>5} {'disp':>4} {'addr@':>5} {'exec':>4} {'commit':>6}  outcome")
+    for op in isa.lsq.ops:
+        print(f"{op.seq:3d} {op.source:<24} {op.address:5d} {op.dispatch:4d} {op.address_ready:5d} "
+              f"{op.execute:4d} {op.commit:6d}  {isa.lsq.describe(op)}")
+    stats = isa.lsq.get_stats()
+    print(f"Loads: {stats['loads']} ({stats['forwarded']} forwarded, {stats['violations']} violations, "
+          f"{stats['from_memory']} from memory), stores: {stats['stores']}, "
+          f"queue-full stall cycles: {stats['full_stalls']}")
+
+if __name__ == '__main__':
+    sys.exit(main())
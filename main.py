@@ -1,58 +1,37 @@
+import argparse
 import sys
 sys.path.append('..')
-from memory import Memory
-from cache.cache import Cache
 from isa import SimpleISA
-from utils.logger import Logger, LogLevel
+from settings import load_settings, build_hierarchy
+from utils.logger import Logger, LogLevel, add_logging_arguments, configure_logging
 from PyQt5.QtWidgets import QApplication
 from gui.simulator_gui import SimulatorGUI
 
 def main():
-    # Get test file from command line or use default
-    test_file = sys.argv[1] if len(sys.argv) > 1 else 'tests/test_program.txt'
+    # Get test files from command line or use default; several files run round-robin
+    parser = argparse.ArgumentParser(description="Simplified ISA simulator")
+    parser.add_argument('programs', nargs='*', default=['tests/test_program.txt'],
+                        help="Program files; several run round-robin (default: tests/test_program.txt)")
+    add_logging_arguments(parser)
+    args = parser.parse_args()
+    configure_logging(args)
+    test_files = args.programs
 
     # Initialize logger
     logger = Logger()
-    logger.log(LogLevel.INFO, f"Starting simplified ISA simulator with test file: {test_file}")
+    logger.log(LogLevel.INFO, f"Starting simplified ISA simulator with test file(s): {', '.join(test_files)}")
 
-    # Create memory hierarchy
-    main_memory = Memory("MainMemory", 1024)  # 1KB memory
-
-    # Create L2 cache (slower, larger)
-    l2_cache = Cache(
-        name="L2Cache",
-        size=64,         # 64 bytes
-        line_size=1,     # 1 byte per line for simplicity
-        associativity=4, # 4-way set associative
-        access_time=30,  # 30ns access time
-        write_policy="write-back",
-        next_level=main_memory,
-        logger=logger
-    )
-
-    # Create L1 cache (faster, smaller)
-    l1_cache = Cache(
-        name="L1Cache",
-        size=32,         # 32 bytes
-        line_size=1,     # 1 byte per line for simplicity
-        associativity=2, # 2-way set associative
-        access_time=10,  # 10ns access time
-        write_policy="write-through",
-        next_level=l2_cache,
-        logger=logger
-    )
-
-    # Connect memory hierarchy (L1 -> L2 -> Main Memory)
-    l1_cache.set_next_level(l2_cache)
-    l2_cache.set_next_level(main_memory)
+    # Create memory hierarchy (L1 -> L2 -> Main Memory) as configured in the Settings dialog
+    settings = load_settings()
+    main_memory, l1_cache, l2_cache = build_hierarchy(settings, logger)
 
     # Create ISA with L1 cache as its memory interface
-    isa = SimpleISA(memory=main_memory, cache=l1_cache)
+    isa = SimpleISA(memory=main_memory, cache=l1_cache, isa_mode=settings.isa_mode)
 
     # Create GUI with existing memory hierarchy
-    app = QApplication(sys.argv)
-    window = SimulatorGUI(main_memory=main_memory, l1_cache=l1_cache, l2_cache=l2_cache)
-    window.load_instructions(test_file)
+    app = QApplication(sys.argv[:1])
+    window = SimulatorGUI(main_memory=main_memory, l1_cache=l1_cache, l2_cache=l2_cache, settings=settings)
+    window.load_programs(test_files)
     window.show()
     sys.exit(app.exec())
 
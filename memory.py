@@ -12,6 +12,11 @@ class Memory():
         self._data = [0] * self._size
         self._logger = Logger()
         self._access_time = 100  # Default access time in ns
+        self._stack_region = (size - 512, size)  # Reserve last 512B for stack (enough for Python objects)
+        self.reset_stats()
+
+    def reset_stats(self):
+        """Zero the access counters and timings; the stored words are kept"""
         self._exec_time = 0
         self._access_count = 0
         self._total_access_time = 0
@@ -26,10 +31,12 @@ class Memory():
         }
         # Track stack operations
         self._stack_accesses = 0
-        self._stack_region = (size - 512, size)  # Reserve last 512B for stack (enough for Python objects)
         self._reads = 0
         self._writes = 0
 
+    def _log(self, level, message):
+        self._logger.log(level, message, target='memory.access')
+
     def read(self, address, output=True):
         """Read a value from memory"""
         start_time = time()
@@ -159,18 +166,18 @@ class Memory():
     def print_debug_info(self):
         """Print formatted debug information"""
         info = self.debug_info()
-        self._logger.log(LogLevel.DEBUG, f"\n=== {self._name} Debug Info ===")
-        self._logger.log(LogLevel.DEBUG, f"Size: {info['size']} bytes")
-        self._logger.log(LogLevel.DEBUG, f"Access Count: {info['access_count']}")
-        self._logger.log(LogLevel.DEBUG, f"Execution Time: {info['exec_time']:.6f}s")
-        self._logger.log(LogLevel.DEBUG, f"Bytes Transferred: {info['bytes_transferred']} bytes")
+        self._log(LogLevel.DEBUG, f"\n=== {self._name} Debug Info ===")
+        self._log(LogLevel.DEBUG, f"Size: {info['size']} bytes")
+        self._log(LogLevel.DEBUG, f"Access Count: {info['access_count']}")
+        self._log(LogLevel.DEBUG, f"Execution Time: {info['exec_time']:.6f}s")
+        self._log(LogLevel.DEBUG, f"Bytes Transferred: {info['bytes_transferred']} bytes")
 
         perf_stats = info['performance_stats']
-        self._logger.log(LogLevel.DEBUG, "\nPerformance Statistics:")
-        self._logger.log(LogLevel.DEBUG, f"  Min Access Time: {perf_stats['min_access_time']:.6f}s")
-        self._logger.log(LogLevel.DEBUG, f"  Max Access Time: {perf_stats['max_access_time']:.6f}s")
-        self._logger.log(LogLevel.DEBUG, f"  Avg Access Time: {perf_stats['avg_access_time']:.6f}s")
-        self._logger.log(LogLevel.DEBUG, f"  Bandwidth: {perf_stats['bytes_transferred'] / perf_stats['exec_time']:.2f} bytes/s")
+        self._log(LogLevel.DEBUG, "\nPerformance Statistics:")
+        self._log(LogLevel.DEBUG, f"  Min Access Time: {perf_stats['min_access_time']:.6f}s")
+        self._log(LogLevel.DEBUG, f"  Max Access Time: {perf_stats['max_access_time']:.6f}s")
+        self._log(LogLevel.DEBUG, f"  Avg Access Time: {perf_stats['avg_access_time']:.6f}s")
+        self._log(LogLevel.DEBUG, f"  Bandwidth: {perf_stats['bytes_transferred'] / perf_stats['exec_time']:.2f} bytes/s")
 
     # New memory inspection methods
     def dump_memory_region(self, start_addr, size, logger):
@@ -237,11 +244,79 @@ class Memory():
                 logger.log(LogLevel.DEBUG, f"Pattern found at address: {addr:04x}")
                 self.dump_memory_region(max(0, addr - 8), min(16, self._size - addr), logger)
 
+# DRAM timing model used by the main
+# memory in place of a flat access time
+class DRAMModel():
+    """Open-page DRAM with a row buffer per bank
+
+    Consecutive blocks of `row_words` words are interleaved across the
+    banks. Each bank keeps its last row open: an access to the open row
+    only pays the column access (a row hit), an access to a closed bank
+    first activates the row (a row miss) and an access to another row
+    must also precharge the open one (a row conflict).
+    """
+
+    def __init__(self, banks=4, row_words=16, t_cas=30, t_rcd=30, t_rp=30, t_bus=10):
+        if banks < 1 or row_words < 1:
+            raise ValueError("DRAM needs at least one bank and one word per row")
+        self.banks = banks
+        self.row_words = row_words
+        self.t_cas = t_cas  # Column access (ns)
+        self.t_rcd = t_rcd  # Row activate to column access (ns)
+        self.t_rp = t_rp    # Precharge (ns)
+        self.t_bus = t_bus  # Data transfer (ns)
+        self.reset()
+
+    def reset(self):
+        self.open_rows = [None] * self.banks
+        self.reset_stats()
+
+    def reset_stats(self):
+        """Zero the row-buffer counters; the open rows stay open"""
+        self.bank_stats = [{"hits": 0, "misses": 0, "conflicts": 0} for _ in range(self.banks)]
+        self.total_latency = 0
+
+    def locate(self, address):
+        """Return the (bank, row) an address maps to"""
+        block = address // self.row_words
+        return block % self.banks, block // self.banks
+
+    def access(self, address):
+        """Update the row buffers for an access and return its latency in ns"""
+        bank, row = self.locate(address)
+        open_row = self.open_rows[bank]
+        if open_row == row:
+            kind, latency = "hits", self.t_cas
+        elif open_row is None:
+            kind, latency = "misses", self.t_rcd + self.t_cas
+        else:
+            kind, latency = "conflicts", self.t_rp + self.t_rcd + self.t_cas
+        self.open_rows[bank] = row
+        self.bank_stats[bank][kind] += 1
+        latency += self.t_bus
+        self.total_latency += latency
+        return latency
+
+    def get_stats(self):
+        """Row-buffer outcomes summed over banks, plus the per-bank breakdown"""
+        totals = {kind: sum(bank[kind] for bank in self.bank_stats) for kind in ("hits", "misses", "conflicts")}
+        accesses = sum(totals.values())
+        totals.update({
+            "accesses": accesses,
+            "row_hit_rate": totals["hits"] / accesses * 100 if accesses else 0.0,
+            "total_latency": self.total_latency,
+            "avg_latency": self.total_latency / accesses if accesses else 0.0,
+            "open_rows": list(self.open_rows),
+            "banks": [dict(bank) for bank in self.bank_stats]
+        })
+        return totals
+
 # Memory class used for the main
 # memory data storage
 class MainMemory(Memory):
     def __init__(self, name="MainMemory", size=1024):
         super().__init__(name, size)
+        self.dram = DRAMModel()  # Row-buffer timing for every access
         self._memory_map = {}  # Track memory mapping
         self._access_patterns = []  # Track access patterns
         self._access_pattern = {
@@ -250,17 +325,23 @@ class MainMemory(Memory):
             "random": 0,
             "last_address": None
         }  # Current access pattern tracking
-        # Initialize latency tracking
+
+    def reset_stats(self):
+        super().reset_stats()
+        # Latency tracking
         self._min_latency = float('inf')
         self._max_latency = 0
         self._total_latency = 0
+        if hasattr(self, 'dram'):  # Not yet built while Memory.__init__ runs
+            self.dram.reset_stats()
 
     def get_performance_stats(self):
         """Return performance statistics about the main memory"""
         stats = super().get_performance_stats()
         stats.update({
             "memory_map": self._memory_map,
-            "access_patterns": self._access_patterns
+            "access_patterns": self._access_patterns,
+            "dram": self.dram.get_stats()
         })
         return stats
 
@@ -297,15 +378,15 @@ class MainMemory(Memory):
         value = int(self._data[address])
 
         # Update statistics
-        access_time = self._calculate_access_time()
+        access_time = self._calculate_access_time(address)
         self._exec_time += access_time
         self._update_stats(access_time)
 
         return value
 
     # Write data to main memory address
-    def write(self, address, data):
-        """Write a value to main memory"""
+    def write(self, address, data, output=True, propagate=None):
+        """Write a value to main memory (output and propagate match the cache interface)"""
         if not self._validate_address(address):
             raise ValueError(f"Invalid memory address: {address}")
 
@@ -323,20 +404,15 @@ class MainMemory(Memory):
         self._data[address] = int(data)
 
         # Update statistics
-        access_time = self._calculate_access_time()
+        access_time = self._calculate_access_time(address)
         self._exec_time += access_time
         self._update_stats(access_time)
 
         return True
 
-    def _calculate_access_time(self):
-        """Calculate memory access time based on access pattern"""
-        base_time = self._access_time
-        # Sequential access is faster
-        if self._access_pattern["last_address"] is not None:
-            if self._access_pattern["sequential"] > self._access_pattern["random"]:
-                base_time *= 0.8  # 20% faster for sequential access
-        return base_time
+    def _calculate_access_time(self, address):
+        """Calculate memory access time from the DRAM row-buffer state"""
+        return self.dram.access(address)
 
     def _update_stats(self, access_time):
         """Update latency statistics"""
@@ -364,19 +440,19 @@ class MainMemory(Memory):
         """Print formatted debug information about the main memory state"""
         info = self.debug_info()
         super().print_debug_info()
-        self._logger.log(LogLevel.DEBUG, f"\nData Size: {info['data_size']} bytes")
-        self._logger.log(LogLevel.DEBUG, "\nData Contents:")
+        self._log(LogLevel.DEBUG, f"\nData Size: {info['data_size']} bytes")
+        self._log(LogLevel.DEBUG, "\nData Contents:")
         for addr, value in enumerate(info['data']):
             if value is not None:
-                self._logger.log(LogLevel.DEBUG, f"  Address {addr}: {value}")
+                self._log(LogLevel.DEBUG, f"  Address {addr}: {value}")
 
-        self._logger.log(LogLevel.DEBUG, "\nMemory Map:")
+        self._log(LogLevel.DEBUG, "\nMemory Map:")
         for addr, region in info['memory_map'].items():
-            self._logger.log(LogLevel.DEBUG, f"  Address {addr}: {region}")
+            self._log(LogLevel.DEBUG, f"  Address {addr}: {region}")
 
-        self._logger.log(LogLevel.DEBUG, "\nAccess Pattern:")
+        self._log(LogLevel.DEBUG, "\nAccess Pattern:")
         for pattern, count in info['access_pattern'].items():
-            self._logger.log(LogLevel.DEBUG, f"  {pattern}: {count}")
+            self._log(LogLevel.DEBUG, f"  {pattern}: {count}")
 
     def validate_state(self):
         """Validate the main memory state and return any issues found"""
@@ -0,0 +1,257 @@
+import argparse
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Set
+
+from isa import (SimpleISA, Instruction, InstructionType, FLAG_CONDITIONS, LINK_REGISTER, SUBWORD_LOADS,
+                 SUBWORD_STORES)
+from utils.files import read_lines
+
+@dataclass
+class MicroInstruction:
+    """One control-ROM word: register transfers and the control signals asserted for one clock"""
+    address: int
+    routine: str
+    transfer: str                 # Register-transfer description; {dst}, {src} and {op} are filled in
+    signals: Set[str] = field(default_factory=set)
+    next: str = 'seq'             # seq, dispatch or fetch (end of the routine)
+
+    def render(self, instruction: Optional[Instruction] = None) -> str:
+        if instruction is None:
+            return self.transfer
+        operands = instruction.operands + ['', '']
+        return self.transfer.format(dst=operands[0], src=operands[1], op=instruction.type.name)
+
+# Routine name -> [(transfer, signals)]; the last word of each routine returns to fetch
+ROUTINES = {
+    'fetch': [
+        ("MAR ← PC", {'PCout', 'MARin'}),
+        ("MDR ← M[MAR]; PC ← PC + 1", {'MemRead', 'MDRin', 'PCinc'}),
+        ("IR ← MDR", {'MDRout', 'IRin'}),
+        ("μPC ← dispatch(IR.opcode, IR.mode)", {'Dispatch'}),
+    ],
+    'mov_imm': [("{dst} ← IR.imm", {'IRout', 'RegWrite'})],
+    'mov_reg': [("{dst} ← {src}", {'RegOut', 'RegWrite'})],
+    'load': [
+        ("MAR ← address({src})", {'IRout', 'MARin'}),
+        ("MDR ← M[MAR]", {'MemRead', 'MDRin'}),
+        ("{dst} ← MDR", {'MDRout', 'RegWrite'}),
+    ],
+    'store': [
+        ("MAR ← address({dst})", {'IRout', 'MARin'}),
+        ("MDR ← {src}", {'RegOut', 'MDRin'}),
+        ("M[MAR] ← MDR", {'MDRout', 'MemWrite'}),
+    ],
+    'load_sub': [
+        ("MAR ← address({src}) / 4", {'IRout', 'MARin'}),
+        ("MDR ← M[MAR]", {'MemRead', 'MDRin'}),
+        ("{dst} ← extend(bytes(MDR))", {'MDRout', 'ByteSelect', 'RegWrite'}),
+    ],
+    'store_sub': [
+        ("MAR ← address({dst}) / 4", {'IRout', 'MARin'}),
+        ("MDR ← M[MAR]", {'MemRead', 'MDRin'}),
+        ("MDR ← insert(MDR, {src})", {'RegOut', 'ByteSelect', 'MDRin'}),
+        ("M[MAR] ← MDR", {'MDRout', 'MemWrite'}),
+    ],
+    'alu2': [
+        ("A ← {dst}", {'RegOut', 'Ain'}),
+        ("B ← {src}", {'RegOut', 'Bin'}),
+        ("Z ← A {op} B; set flags", {'ALUop', 'Zin', 'FlagsIn'}),
+        ("{dst} ← Z", {'Zout', 'RegWrite'}),
+    ],
+    'alu1': [
+        ("A ← {dst}", {'RegOut', 'Ain'}),
+        ("Z ← {op} A; set flags", {'ALUop', 'Zin', 'FlagsIn'}),
+        ("{dst} ← Z", {'Zout', 'RegWrite'}),
+    ],
+    'compare': [
+        ("A ← {dst}", {'RegOut', 'Ain'}),
+        ("B ← {src}", {'RegOut', 'Bin'}),
+        ("A {op} B; set flags, discard result", {'ALUop', 'FlagsIn'}),
+    ],
+    'jump': [("PC ← {dst}", {'IRout', 'PCin'})],
+    'branch': [
+        ("cond ← {op}(flags)", {'CondTest'}),
+        ("if cond: PC ← {dst}", {'IRout', 'PCinCond'}),
+    ],
+    'call': [
+        (f"{LINK_REGISTER} ← PC", {'PCout', 'RegWrite'}),
+        ("PC ← {dst}", {'IRout', 'PCin'}),
+    ],
+    'return': [("PC ← {dst}", {'RegOut', 'PCin'})],
+    'fpu': [
+        ("FA ← {dst}; FB ← {src}", {'FRegOut', 'FPUin'}),
+        ("FZ ← {op}(FA, FB)", {'FPUop'}),
+        ("{dst} ← FZ", {'FZout', 'FRegWrite'}),
+    ],
+    'trap': [
+        ("EPC ← PC; cause ← {op}", {'PCout', 'EPCin', 'CauseIn'}),
+        ("mode ← kernel; PC ← trap_handler", {'KernelMode', 'PCin'}),
+    ],
+    'eret': [("PC ← EPC; mode ← user", {'EPCout', 'PCin', 'UserMode'})],
+    'control_reg': [("{dst} ← {src}", {'CP0Access', 'RegWrite'})],
+    'cache_op': [
+        ("MAR ← address({dst})", {'IRout', 'MARin'}),
+        ("cache.{op}(MAR)", {'CacheCtl'}),
+    ],
+    'debug': [("print {op}", {'Debug'})],
+    'halt': [("stop clock", {'Halt'})],
+}
+
+ALU_BINARY = {InstructionType.ADD, InstructionType.SUB, InstructionType.AND, InstructionType.OR,
+              InstructionType.XOR, InstructionType.SHL, InstructionType.SHR}
+ALU_UNARY = {InstructionType.INC, InstructionType.DEC, InstructionType.NOT}
+FP_INSTRUCTIONS = {InstructionType.MOV_S, InstructionType.LOAD_S, InstructionType.STORE_S, InstructionType.ADD_S,
+                   InstructionType.SUB_S, InstructionType.MUL_S, InstructionType.DIV_S, InstructionType.CVT_S_W,
+                   InstructionType.CVT_W_S, InstructionType.C_EQ_S, InstructionType.C_LT_S, InstructionType.C_LE_S}
+CONDITIONAL_JUMPS = {InstructionType.JZ, InstructionType.JNZ, InstructionType.BC1T,
+                     InstructionType.BC1F} | set(FLAG_CONDITIONS)
+
+def build_rom():
+    """Lay the routines out in a control ROM; returns (words, routine start addresses)"""
+    rom: List[MicroInstruction] = []
+    starts: Dict[str, int] = {}
+    for name, words in ROUTINES.items():
+        starts[name] = len(rom)
+        for i, (transfer, signals) in enumerate(words):
+            if name == 'fetch':
+                next_step = 'dispatch' if i == len(words) - 1 else 'seq'
+            else:
+                next_step = 'fetch' if i == len(words) - 1 else 'seq'
+            rom.append(MicroInstruction(len(rom), name, transfer, signals, next_step))
+    return rom, starts
+
+CONTROL_ROM, ROUTINE_STARTS = build_rom()
+
+def dispatch(instruction: Instruction) -> str:
+    """Routine that implements an instruction, chosen by opcode and operand mode"""
+    t = instruction.type
+    operands = instruction.operands
+    if t in (InstructionType.MOV, InstructionType.LOAD, InstructionType.STORE):
+        if operands and operands[0].startswith('['):
+            return 'store'
+        if len(operands) > 1 and operands[1].startswith('['):
+            return 'load'
+        if len(operands) > 1 and operands[1].startswith('#'):
+            return 'mov_imm'
+        return 'mov_reg'
+    if t in SUBWORD_LOADS:
+        return 'load_sub'
+    if t in SUBWORD_STORES:
+        return 'store_sub'
+    if t in ALU_BINARY:
+        return 'alu2'
+    if t in ALU_UNARY:
+        return 'alu1'
+    if t in (InstructionType.CMP, InstructionType.TEST):
+        return 'compare'
+    if t == InstructionType.JMP:
+        return 'jump'
+    if t in CONDITIONAL_JUMPS:
+        return 'branch'
+    if t == InstructionType.JAL:
+        return 'call'
+    if t == InstructionType.JR:
+        return 'return'
+    if t in FP_INSTRUCTIONS:
+        return 'fpu'
+    if t == InstructionType.SYSCALL:
+        return 'trap'
+    if t == InstructionType.ERET:
+        return 'eret'
+    if t in (InstructionType.MFC0, InstructionType.MTC0):
+        return 'control_reg'
+    if t in (InstructionType.PREF, InstructionType.CFLUSH, InstructionType.CINV):
+        return 'cache_op'
+    if t == InstructionType.HALT:
+        return 'halt'
+    return 'debug'
+
+class MicroSequencer:
+    """Steps the loaded program one microinstruction at a time
+
+    Every instruction runs the shared fetch routine, is dispatched to the
+    routine for its opcode and operand mode, and returns to fetch. The ISA
+    executes the instruction functionally on its routine's last word, so
+    registers and memory change exactly when the instruction completes.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self.reset()
+
+    def reset(self) -> None:
+        """Return to the start of fetch (e.g. after the program was stepped a whole instruction)"""
+        self.upc = ROUTINE_STARTS['fetch']
+        self.instruction: Optional[Instruction] = None
+        self.micro_cycles = 0
+        self.instructions_completed = 0
+
+    @property
+    def current(self) -> MicroInstruction:
+        return CONTROL_ROM[self.upc]
+
+    def step(self) -> bool:
+        """Execute one microinstruction; returns False once the program has stopped"""
+        isa = self.isa
+        if self.upc == ROUTINE_STARTS['fetch']:
+            if not isa.running or isa.pc >= len(isa.instructions):
+                return False
+            self.instruction = isa.instructions[isa.pc]
+
+        word = self.current
+        self.micro_cycles += 1
+        if word.next == 'dispatch':
+            self.upc = ROUTINE_STARTS[dispatch(self.instruction)]
+        elif word.next == 'fetch':
+            self.upc = ROUTINE_STARTS['fetch']
+            self.instructions_completed += 1
+            return isa.execute_step()
+        else:
+            self.upc += 1
+        return True
+
+    def get_stats(self):
+        return {
+            'micro_cycles': self.micro_cycles,
+            'instructions': self.instructions_completed,
+            'cycles_per_instruction': (self.micro_cycles / self.instructions_completed
+                                       if self.instructions_completed else 0.0)
+        }
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program on the microprogrammed control unit")
+    parser.add_argument('program', nargs='?', help="Assembly program file, or - for standard input")
+    parser.add_argument('--rom', action='store_true', help="Print the control ROM")
+    parser.add_argument('--trace', action='store_true', help="Print every microinstruction executed")
+    args = parser.parse_args()
+
+    if args.rom or not args.program:
+        for word in CONTROL_ROM:
+            print(f"{word.address:3d}  {word.routine:<12} {word.transfer:<40} {','.join(sorted(word.signals)):<30} {word.next}")
+        if not args.program:
+            return
+
+    from memory import MainMemory
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+
+    sequencer = MicroSequencer(isa)
+    running = True
+    while running:
+        word, cycles = sequencer.current, sequencer.micro_cycles
+        running = sequencer.step()
+        if args.trace and sequencer.micro_cycles > cycles:
+            print(f"  μ{word.address:3d} {word.routine:<12} {word.render(sequencer.instruction)}")
+
+    stats = sequencer.get_stats()
+    print(f"Instructions: {stats['instructions']}")
+    print(f"Micro-cycles: {stats['micro_cycles']} ({stats['cycles_per_instruction']:.2f} per instruction)")
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,220 @@
+import argparse
+import json
+import os
+import sys
+from dataclasses import asdict, dataclass, field
+from typing import Dict, List, Tuple
+
+from encoding import EXTENSION_KINDS, LABEL, NONE, decode_instruction, encode_instruction
+from isa import SimpleISA, format_instruction
+from linker import LINKER_DIRECTIVES, LinkedProgram, Module, format_map, link, read_module
+from locality import build_hierarchy
+from utils.files import check_stdin_once, display_name, read_lines, read_text
+from utils.logger import Logger, LogLevel
+
+OBJECT_FORMAT = 'cpu-sim-object'
+OBJECT_VERSION = 1
+
+@dataclass
+class Symbol:
+    name: str
+    value: int      # Instruction index within the text section; 0 for undefined symbols
+    binding: str    # 'local', 'global' (.global) or 'undefined' (.extern)
+
+@dataclass
+class Relocation:
+    """An extension word holding an instruction index, fixed up when the text section is placed"""
+    offset: int     # Word offset in the text section
+    symbol: str
+
+@dataclass
+class ObjectFile:
+    """An assembled source file, relocatable to any instruction index
+
+    The text section holds the instructions encoded as in encoding.py, with
+    branch targets and other label operands as indices from the start of the
+    section (0 for undefined symbols). Each such word has a relocation
+    naming its symbol, so a linker can place the section anywhere and
+    resolve symbols other files define. Directives are kept as text in
+    their own section and apply to the whole linked program.
+    """
+    name: str
+    text: List[int] = field(default_factory=list)
+    instructions: int = 0
+    directives: List[str] = field(default_factory=list)
+    symbols: List[Symbol] = field(default_factory=list)
+    relocations: List[Relocation] = field(default_factory=list)
+
+    def to_dict(self) -> Dict:
+        return {'format': OBJECT_FORMAT, 'version': OBJECT_VERSION, 'name': self.name,
+                'sections': {'text': self.text, 'directives': self.directives},
+                'instructions': self.instructions,
+                'symbols': [asdict(symbol) for symbol in self.symbols],
+                'relocations': [asdict(relocation) for relocation in self.relocations]}
+
+    @classmethod
+    def from_dict(cls, data: Dict) -> 'ObjectFile':
+        if data.get('format') != OBJECT_FORMAT:
+            raise ValueError("not a CPU simulator object file")
+        if data.get('version') != OBJECT_VERSION:
+            raise ValueError(f"object format version {data.get('version')} is not supported "
+                             f"(expected {OBJECT_VERSION})")
+        return cls(data['name'], list(data['sections']['text']), data['instructions'],
+                   list(data['sections']['directives']), [Symbol(**symbol) for symbol in data['symbols']],
+                   [Relocation(**relocation) for relocation in data['relocations']])
+
+def _label_operands(word: int) -> List[Tuple[int, int]]:
+    """(operand number, extension word offset from the instruction word) of each label operand of an instruction"""
+    operands = []
+    extension = 1
+    for i, shift in enumerate((16, 8)):
+        kind = (word >> (shift + 5)) & 0x7
+        if kind == NONE:
+            break
+        if kind == LABEL:
+            operands.append((i, extension))
+        if kind in EXTENSION_KINDS:
+            extension += 1
+    return operands
+
+def assemble_object(name: str, lines: List[str]) -> ObjectFile:
+    """Assemble a source file into an object file; ValueError for lines the assembler cannot use"""
+    module = read_module(name, lines)
+    # Directives go to their own section instead of changing a machine here
+    code = ['' if line.strip().startswith('.') else line for line in module.lines]
+    isa = SimpleISA()
+    instructions, labels = isa.parse_program(code)
+    for line, message in isa.diagnostics:
+        raise ValueError(f"{name}:{module.line_numbers[line] + 1}: {message}")
+
+    obj = ObjectFile(name, instructions=len(instructions),
+                     directives=[line.split(';')[0].strip() for line in module.lines
+                                 if line.strip().startswith('.')
+                                 and line.split()[0].lower() not in LINKER_DIRECTIVES])
+    obj.symbols = [Symbol(label, index, 'global' if label in module.exports else 'local')
+                   for label, index in labels.items()]
+    obj.symbols += [Symbol(symbol, 0, 'undefined') for symbol in sorted(module.imports)]
+    targets = dict(labels, **{symbol: 0 for symbol in module.imports})
+    for instruction in instructions:
+        try:
+            words = encode_instruction(instruction, targets)
+        except ValueError as e:
+            raise ValueError(f"{name}:{module.line_numbers[instruction.line_number] + 1}: {e}") from None
+        for operand, offset in _label_operands(words[0]):
+            obj.relocations.append(Relocation(len(obj.text) + offset, instruction.operands[operand]))
+        obj.text += words
+    return obj
+
+def to_module(obj: ObjectFile) -> Module:
+    """The object's program as assembly text for the linker, label operands named by their relocations"""
+    names = {relocation.offset: relocation.symbol for relocation in obj.relocations}
+    starts = {}
+    for symbol in obj.symbols:
+        if symbol.binding != 'undefined':
+            starts.setdefault(symbol.value, []).append(symbol.name)
+    lines = list(obj.directives)
+    lines += [f".global {symbol.name}" for symbol in obj.symbols if symbol.binding == 'global']
+    lines += [f".extern {symbol.name}" for symbol in obj.symbols if symbol.binding == 'undefined']
+    offset = 0
+    for index in range(obj.instructions):
+        lines += [f"{label}:" for label in starts.get(index, [])]
+        instruction, length = decode_instruction(obj.text, offset)
+        for operand, extension in _label_operands(obj.text[offset]):
+            instruction.operands[operand] = names[offset + extension]
+        lines.append(format_instruction(instruction))
+        offset += length
+    lines += [f"{label}:" for label in starts.get(obj.instructions, [])]
+    return read_module(obj.name, lines)
+
+def read_object(path: str) -> ObjectFile:
+    try:
+        return ObjectFile.from_dict(json.loads(read_text(path)))
+    except (KeyError, TypeError, json.JSONDecodeError) as e:
+        raise ValueError(f"{display_name(path)} is not a valid object file ({e})") from None
+
+def write_object(obj: ObjectFile, path: str) -> None:
+    with open(path, 'w') as f:
+        json.dump(obj.to_dict(), f, indent=1)
+        f.write('\n')
+
+def read_modules(paths: List[str]) -> List[Module]:
+    """Linker modules from object files (.obj) and assembly sources (anything else)"""
+    modules = []
+    for path in paths:
+        if path.endswith('.obj'):
+            modules.append(to_module(read_object(path)))
+        else:
+            modules.append(read_module(os.path.basename(display_name(path)), read_lines(path)))
+    return modules
+
+def load_objects(isa: SimpleISA, paths: List[str], base: int = 0) -> LinkedProgram:
+    """Link object and source files and load the program with its text at memory word base
+
+    Branch targets are instruction indices, so the same linked text runs at
+    any base; a .text directive in one of the files takes precedence.
+    """
+    program = link(read_modules(paths))
+    if not program.text_base:
+        program.text_base = base
+        program.lines.insert(0, f".text {base}")
+    isa.load_program(program.lines)
+    return program
+
+def format_object(obj: ObjectFile) -> List[str]:
+    """Sections, symbols and relocations of an object file, for inspection"""
+    lines = [f"{obj.name}: {obj.instructions} instructions, {len(obj.text)} text words", "",
+             "Directives:"] + [f"  {directive}" for directive in obj.directives or ["(none)"]]
+    lines += ["", "Text:"] + [f"  {offset:4d}  {word:08x}" for offset, word in enumerate(obj.text)]
+    lines += ["", "Symbols:"] + [f"  {symbol.value:4d}  {symbol.binding:<9} {symbol.name}" for symbol in obj.symbols]
+    lines += ["", "Relocations:"] + [f"  {r.offset:4d}  {r.symbol}" for r in obj.relocations or []]
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Assemble source files into relocatable object files, inspect "
+                                                 "them, or link and run them at a chosen base address")
+    commands = parser.add_subparsers(dest='command', required=True)
+    assemble = commands.add_parser('assemble', help="Assemble each source file into FILE.obj")
+    assemble.add_argument('files', nargs='+')
+    dump = commands.add_parser('dump', help="Show an object file's sections, symbols and relocations")
+    dump.add_argument('file', help="Object file, or - for standard input")
+    run = commands.add_parser('run', help="Link object (and source) files, load them at a base address and run")
+    run.add_argument('files', nargs='+', help="Object files, and assembly files of which - reads one from "
+                                                 "standard input")
+    run.add_argument('--base', type=int, default=0, help="Memory word of the first instruction (default: 0)")
+    run.add_argument('--map', action='store_true', help="Print the link map")
+    args = parser.parse_args()
+    if args.command == 'run':
+        check_stdin_once(parser, args.files)
+
+    try:
+        if args.command == 'assemble':
+            for path in args.files:
+                with open(path) as f:
+                    obj = assemble_object(os.path.basename(path), f.read().splitlines())
+                out = os.path.splitext(path)[0] + '.obj'
+                write_object(obj, out)
+                print(f"{path} -> {out}: {obj.instructions} instructions, {len(obj.relocations)} relocations")
+        elif args.command == 'dump':
+            print('\n'.join(format_object(read_object(args.file))))
+        else:
+            memory, l1, _ = build_hierarchy()
+            isa = SimpleISA(memory=memory, cache=l1)
+            logger = Logger()
+            level = logger.log_level
+            logger.log_level = LogLevel.ERROR
+            try:
+                program = load_objects(isa, args.files, args.base)
+                isa.run(fast=True)
+            finally:
+                logger.log_level = level
+            if args.map:
+                print('\n'.join(format_map(program)))
+            print(f"Ran {isa.instruction_count} instructions at base {args.base}: "
+                  + "  ".join(f"{reg}={value}" for reg, value in isa.registers.items()))
+    except (OSError, ValueError) as e:
+        print(f"Error: {e}")
+        return 1
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
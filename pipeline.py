@@ -0,0 +1,326 @@
+import argparse
+import csv
+import io
+import sys
+from collections import Counter
+from dataclasses import dataclass, field, fields
+from typing import Dict, List, Optional
+
+from isa import SimpleISA, Instruction, is_control_transfer
+from dependency import instruction_accesses
+from utils.files import read_lines
+
+STAGES = ('IF', 'ID', 'EX', 'MEM', 'WB')
+STRUCTURAL_CAUSES = ('ALU', 'memory port', 'write port')
+
+@dataclass
+class PipelineResources:
+    """How many of each shared unit the pipeline has; None is as many as needed
+
+    Each data read or write uses a memory port in MEM and each register
+    written, the flags included, a register-file write port in WB; an
+    instruction that needs more than there are holds its stage for another
+    cycle per round. With a von Neumann memory organization fetch shares
+    the memory ports with MEM (with memory_ports None there is one, which
+    one instruction's reads and writes never fill). An ALU operation uses
+    an ALU in EX; with a single ALU there is no separate PC adder, so
+    fetch also needs it for PC + 1 and waits while EX is using it.
+    """
+    alus: Optional[int] = None
+    memory_ports: Optional[int] = None
+    write_ports: Optional[int] = None
+
+    def describe(self) -> str:
+        """e.g. 'ALUs 1, memory ports 2' for the counts that are set"""
+        names = {'alus': "ALUs", 'memory_ports': "memory ports", 'write_ports': "write ports"}
+        parts = [f"{names[f.name]} {getattr(self, f.name)}" for f in fields(self) if getattr(self, f.name) is not None]
+        return ", ".join(parts) or "unlimited resources"
+
+def parse_resources(spec: str) -> PipelineResources:
+    """Resource counts from 'key=value,...' (e.g. 'alus=1,write_ports=1'); unset keys are unlimited"""
+    resources = PipelineResources()
+    names = [f.name for f in fields(PipelineResources)]
+    for item in filter(None, (part.strip() for part in spec.split(','))):
+        key, _, value = item.partition('=')
+        key = key.strip().replace('-', '_')
+        if key not in names:
+            raise ValueError(f"unknown resource '{key}' (use {', '.join(names)})")
+        try:
+            count = int(value.strip(), 0)
+        except ValueError:
+            raise ValueError(f"{key} must be a number, not '{value.strip()}'") from None
+        if count < 1:
+            raise ValueError(f"{key} must be at least 1")
+        setattr(resources, key, count)
+    return resources
+
+def _rounds(demand: int, units: Optional[int]) -> int:
+    """Cycles a stage takes to serve demand uses of a unit it has units of (one when unlimited or unused)"""
+    return max(1, -(-demand // units)) if units else 1
+
+@dataclass
+class PipelineRecord:
+    """Timing of one dynamic instruction through the pipeline"""
+    seq: int
+    pc: int
+    source: str
+    stages: Dict[str, int] = field(default_factory=dict)  # Cycle each stage starts
+    stalls: int = 0        # Cycles held in ID waiting for operands
+    taken_branch: bool = False
+    mispredicted: bool = False  # Fetch went the wrong way after this branch, so the instructions behind it were lost
+    squashed: Optional[int] = None  # Wrong-path records: the cycle they were discarded; seq is their branch's
+    structural: Dict[str, int] = field(default_factory=dict)  # Extra cycles waiting for a unit, by STRUCTURAL_CAUSES
+    memory_stall: int = 0  # Extra cycles held in MEM waiting for an L1 miss (the ISA's miss_penalty)
+
+    @property
+    def wrong_path(self) -> bool:
+        return self.squashed is not None
+
+    @property
+    def complete(self) -> int:
+        return self.stages['WB'] + self.structural.get('write port', 0)
+
+class PipelineModel:
+    """Timing model of a classic five-stage in-order pipeline
+
+    The functional simulator executes each instruction in full; this model
+    is fed the executed instruction stream and works out when each one would
+    occupy IF, ID, EX, MEM and WB. Each stage holds one instruction, so a
+    stalled instruction also holds up everything behind it. An instruction
+    waits in ID until its operands are ready: with forwarding ALU results feed the next EX and
+    loads feed the EX after MEM (one load-use stall); without forwarding
+    results are read after WB. Branches resolve in EX, so a taken branch
+    discards the two instructions fetched behind it; with branch delay
+    slots the first of them is the slot, which runs, so only one is lost.
+    With a branch predictor (predictor.py) fetch follows its BTB and
+    return-address stack instead, so only a branch it got wrong - taken or
+    not - costs the penalty. The instructions fetched down the wrong path
+    are kept in wrong_path, with the cycle they were squashed. With a von Neumann
+    memory organization IF and MEM share one memory port, so a fetch waits
+    while an earlier load or store is in MEM. With PipelineResources the
+    ALUs, memory ports and register write ports are counted too, and an
+    instruction that needs more of one than there are stalls for it
+    (a structural hazard); stall cycles are attributed to each cause. With
+    a miss penalty set on the ISA an instruction whose data access missed
+    in L1 holds MEM for that many more cycles, and everything behind it waits.
+    """
+
+    BRANCH_PENALTY = 2  # Instructions fetched behind a branch before it resolves in EX
+
+    def __init__(self, isa: SimpleISA, forwarding: bool = True, predictor=None,
+                 resources: Optional[PipelineResources] = None):
+        self.isa = isa
+        self.forwarding = forwarding
+        self.predictor = predictor  # A BranchPredictor, or None to always predict not taken
+        self.resources = resources or PipelineResources()
+        self.reset()
+
+    def reset(self) -> None:
+        self.records: List[PipelineRecord] = []
+        self.wrong_path: List[PipelineRecord] = []  # Fetched after a mispredicted branch and squashed
+        self._ready: Dict[str, int] = {}  # Resource -> first cycle its new value can enter EX
+        self._redirect = 0                # Earliest fetch after a taken branch
+        self._slot_redirect = None        # A taken branch's redirect, waiting until its delay slot is fetched
+        self._port_use = Counter()        # Cycle -> shared memory ports used by MEM
+        self._alu_use = Counter()         # Cycle -> ALU operations in EX
+        self.measured_from = 0            # First record the statistics cover
+        self.branch_bubbles = 0
+        self.port_stalls = 0
+        if self.predictor:
+            self.predictor.reset()
+
+    def reset_stats(self) -> None:
+        """Count statistics from the next instruction on; its timing still follows the earlier ones"""
+        self.measured_from = len(self.records)
+        self.branch_bubbles = 0
+        self.port_stalls = 0
+        if self.predictor:
+            self.predictor.reset_stats()
+
+    def record(self, instruction: Instruction, pc: int, next_pc: int) -> PipelineRecord:
+        """Schedule an executed instruction after the ones recorded so far"""
+        prev: Optional[PipelineRecord] = self.records[-1] if self.records else None
+        reads, writes = instruction_accesses(self.isa, instruction)
+        is_load = any(r.startswith('mem[') for r in reads)
+        uses_memory = is_load or any(w.startswith('mem[') for w in writes)
+        reads = {r for r in reads if not r.startswith('mem[')}
+        writes = {w for w in writes if not w.startswith('mem[')}
+
+        # Rounds each stage needs of the units it shares
+        resources = self.resources
+        ports = resources.memory_ports or 1
+        accesses = len(self.isa.step_accesses) if resources.memory_ports else int(uses_memory)
+        mem_cycles = _rounds(accesses, resources.memory_ports)
+        memory_stall = self.isa.step_stall
+        wb_cycles = _rounds(len(writes), resources.write_ports)
+
+        # A stage can only be entered once the previous instruction has moved on from it
+        stages = {'IF': max(prev.stages['ID'] if prev else 1, self._redirect)}
+        fetch_waits = Counter()
+        while True:
+            if self._port_use[stages['IF']] >= ports:
+                fetch_waits['memory port'] += 1
+            elif resources.alus == 1 and self._alu_use[stages['IF']]:
+                fetch_waits['ALU'] += 1
+            else:
+                break
+            stages['IF'] += 1
+        stages['ID'] = max(stages['IF'] + 1, prev.stages['EX'] if prev else 0)
+        operands_ready = max((self._ready.get(r, 0) for r in reads), default=0)
+        stages['EX'] = max(stages['ID'] + 1, prev.stages['MEM'] if prev else 0, operands_ready)
+        stages['MEM'] = max(stages['EX'] + 1, prev.stages['WB'] if prev else 0)
+        stages['WB'] = max(stages['MEM'] + mem_cycles + memory_stall, prev.complete + 1 if prev else 0)
+
+        if self._slot_redirect is not None:
+            # This is a delay slot, fetched before the branch resolved; its branch target follows it
+            self._redirect, self._slot_redirect = self._slot_redirect, None
+
+        if self.isa.step_alu is not None:
+            self._alu_use[stages['EX']] += 1
+        if self.isa.memory_organization == 'von_neumann':
+            for cycle in range(stages['MEM'], stages['MEM'] + mem_cycles):
+                self._port_use[cycle] += min(ports, accesses)
+                accesses -= ports
+
+        record = PipelineRecord(len(self.records), pc, instruction.source, stages,
+                                stalls=stages['EX'] - stages['ID'] - 1, memory_stall=memory_stall)
+        record.structural = {cause: cycles for cause, cycles in zip(STRUCTURAL_CAUSES, (
+            fetch_waits['ALU'], fetch_waits['memory port'] + mem_cycles - 1, wb_cycles - 1)) if cycles}
+        self.port_stalls += record.structural.get('memory port', 0)
+
+        for resource in writes:
+            if not self.forwarding:
+                self._ready[resource] = record.complete + 1
+            else:
+                self._ready[resource] = stages['MEM'] + mem_cycles + memory_stall if is_load else stages['EX'] + 1
+
+        if is_control_transfer(instruction.type):
+            record.taken_branch = next_pc != pc + 1
+            predicted = self.predictor.predict(instruction, pc) if self.predictor else pc + 1
+            if self.predictor:
+                return_address = pc + (2 if self.isa.delay_slots else 1)
+                record.mispredicted = not self.predictor.resolve(instruction, pc, next_pc, return_address)
+            else:
+                record.mispredicted = record.taken_branch
+        if record.mispredicted:
+            if self.isa.delay_slots:
+                self._slot_redirect = stages['EX'] + 1
+                self.branch_bubbles += self.BRANCH_PENALTY - 1
+                # The slot is fetched either way; only what fetch took to follow it is wrong
+                self._fetch_wrong_path(record, predicted + (predicted == pc + 1), self.BRANCH_PENALTY - 1)
+            else:
+                self._redirect = stages['EX'] + 1
+                self.branch_bubbles += self.BRANCH_PENALTY
+                self._fetch_wrong_path(record, predicted, self.BRANCH_PENALTY)
+
+        self.records.append(record)
+        return record
+
+    def _fetch_wrong_path(self, branch: PipelineRecord, pc: int, count: int) -> None:
+        """Record the count instructions from pc that fetch took behind a mispredicted branch, up to its EX
+
+        Each goes as far as it got - IF, or IF and ID - and is squashed the
+        cycle after the branch resolves, when fetch starts down the right path.
+        """
+        resolved = branch.stages['EX']
+        for i in range(count):
+            fetched = resolved + 1 - count + i
+            stages = {'IF': fetched} if fetched == resolved else {'IF': fetched, 'ID': fetched + 1}
+            source = self.isa.instructions[pc + i].source if 0 <= pc + i < len(self.isa.instructions) else "(none)"
+            self.wrong_path.append(PipelineRecord(branch.seq, pc + i, source, stages, squashed=resolved + 1))
+
+    @property
+    def squashed(self) -> int:
+        """Wrong-path instructions squashed since the last reset_stats"""
+        return sum(1 for r in self.wrong_path if r.seq >= self.measured_from)
+
+    def predict_taken(self, instruction: Instruction, pc: int) -> bool:
+        """Whether fetch follows a branch before it resolves: when the predictor says so, else never (pc + 1)"""
+        return self.predictor is not None and self.predictor.predict(instruction, pc) != pc + 1
+
+    @property
+    def total_cycles(self) -> int:
+        return self.records[-1].complete if self.records else 0
+
+    def get_stats(self):
+        """Return cycle counts, CPI and the cycles lost to stalls and branches since the last reset_stats
+
+        Cycles are counted from the completion of the last instruction before the reset.
+        """
+        records = self.records[self.measured_from:]
+        structural = {cause: sum(r.structural.get(cause, 0) for r in records) for cause in STRUCTURAL_CAUSES}
+        start = self.records[self.measured_from - 1].complete if self.measured_from else 0
+        cycles = self.total_cycles - start if records else 0
+        return {
+            'instructions': len(records),
+            'cycles': cycles,
+            'cpi': cycles / len(records) if records else 0.0,
+            'stall_cycles': sum(r.stalls for r in records),
+            'branch_bubbles': self.branch_bubbles,
+            'port_stalls': self.port_stalls,
+            'alu_stalls': structural['ALU'],
+            'write_port_stalls': structural['write port'],
+            'memory_stalls': sum(r.memory_stall for r in records),
+            'stalls_by_cause': {'data': sum(r.stalls for r in records), **structural,
+                                'memory': sum(r.memory_stall for r in records), 'branch': self.branch_bubbles},
+            'squashed': self.squashed,
+            'forwarding': self.forwarding
+        }
+
+    def to_csv(self) -> str:
+        """One row per dynamic instruction with the cycle each stage starts"""
+        out = io.StringIO()
+        writer = csv.writer(out)
+        writer.writerow(['seq', 'pc', 'instruction', *STAGES, 'stalls', 'taken_branch'])
+        for r in self.records:
+            writer.writerow([r.seq, r.pc, r.source, *(r.stages[s] for s in STAGES), r.stalls, int(r.taken_branch)])
+        return out.getvalue()
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program through the five-stage pipeline timing model")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--csv', help="Write per-instruction stage timings to this CSV file")
+    parser.add_argument('--no-forwarding', action='store_true', help="Disable operand forwarding")
+    parser.add_argument('--alus', type=int, help="ALUs; with 1, fetch shares it for PC + 1 (default: a separate adder)")
+    parser.add_argument('--memory-ports', type=int,
+                        help="Data memory ports, shared with fetch in a von Neumann machine (default one)")
+    parser.add_argument('--write-ports', type=int, help="Register-file write ports (default: as many as needed)")
+    parser.add_argument('--predictor', action='store_true',
+                        help="Predict branches with a BTB and return-address stack instead of always not taken")
+    args = parser.parse_args()
+    resources = PipelineResources(args.alus, args.memory_ports, args.write_ports)
+    if any(count is not None and count < 1 for count in (args.alus, args.memory_ports, args.write_ports)):
+        parser.error("resource counts must be at least 1")
+
+    from memory import MainMemory
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+    predictor = None
+    if args.predictor:
+        from predictor import BranchPredictor
+        predictor = BranchPredictor()
+    isa.pipeline = PipelineModel(isa, forwarding=not args.no_forwarding, predictor=predictor, resources=resources)
+    isa.run()
+
+    stats = isa.pipeline.get_stats()
+    print(f"Instructions: {stats['instructions']}")
+    print(f"Cycles: {stats['cycles']} (CPI {stats['cpi']:.2f})")
+    print(f"Stall cycles: {stats['stall_cycles']}, branch bubbles: {stats['branch_bubbles']}, "
+          f"memory port stalls: {stats['port_stalls']} ({isa.memory_organization})")
+    print(f"Wrong-path instructions squashed: {stats['squashed']}")
+    print(f"Stall cycles by cause ({resources.describe()}): " +
+          ", ".join(f"{cause} {cycles}" for cause, cycles in stats['stalls_by_cause'].items()))
+    if predictor:
+        print(f"Branch prediction: {predictor.predictions - predictor.mispredictions}/{predictor.predictions} "
+              f"correct ({predictor.accuracy:.0%})")
+    if args.csv:
+        with open(args.csv, 'w', newline='') as f:
+            f.write(isa.pipeline.to_csv())
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,235 @@
+import argparse
+import sys
+from dataclasses import dataclass
+from typing import List, Optional
+
+from isa import SimpleISA, Instruction, InstructionType, symbol_for
+from utils.files import read_lines
+
+@dataclass
+class BtbEntry:
+    """The last taken target of the branch or jump at pc"""
+    pc: int
+    target: int
+    hits: int = 0
+
+class BranchTargetBuffer:
+    """A direct-mapped cache of taken branch and jump targets, looked up by the fetch PC
+
+    Entry pc % size holds the control transfer at pc (its tag) and the
+    target it last went to, so fetch can go there the cycle after the
+    branch instead of waiting for it to resolve in EX. Only taken transfers
+    are entered, and a hit predicts taken, so a branch that was ever taken
+    is predicted taken until it is evicted: a loop branch mispredicts only
+    on the way out. Two branches whose pcs map to the same entry evict each
+    other.
+    """
+
+    def __init__(self, size: int = 16):
+        self.size = size
+        self.reset()
+
+    def reset(self) -> None:
+        self.entries: List[Optional[BtbEntry]] = [None] * self.size
+        self.reset_stats()
+
+    def reset_stats(self) -> None:
+        self.lookups = 0
+        self.hits = 0
+        self.evictions = 0
+
+    def peek(self, pc: int) -> Optional[int]:
+        """The target held for pc, without counting a lookup"""
+        entry = self.entries[pc % self.size]
+        return entry.target if entry and entry.pc == pc else None
+
+    def lookup(self, pc: int) -> Optional[int]:
+        self.lookups += 1
+        target = self.peek(pc)
+        if target is not None:
+            self.hits += 1
+            self.entries[pc % self.size].hits += 1
+        return target
+
+    def update(self, pc: int, target: int) -> None:
+        """Enter the target a transfer was taken to"""
+        index = pc % self.size
+        entry = self.entries[index]
+        if entry and entry.pc == pc:
+            entry.target = target
+        else:
+            if entry:
+                self.evictions += 1
+            self.entries[index] = BtbEntry(pc, target)
+
+    @property
+    def hit_rate(self) -> float:
+        return self.hits / self.lookups if self.lookups else 0.0
+
+class ReturnAddressStack:
+    """A small stack of return addresses: JAL pushes the address after the call, JR pops it as the prediction
+
+    When the stack is full a call overwrites the oldest entry, so returns
+    from deeper than depth nested calls find it empty or wrong; a JR that
+    finds it empty falls back to the BTB.
+    """
+
+    def __init__(self, depth: int = 8):
+        self.depth = depth
+        self.reset()
+
+    def reset(self) -> None:
+        self.stack: List[int] = []  # Bottom first
+        self.reset_stats()
+
+    def reset_stats(self) -> None:
+        self.predictions = 0
+        self.correct = 0
+        self.overflows = 0
+        self.underflows = 0
+
+    def top(self) -> Optional[int]:
+        return self.stack[-1] if self.stack else None
+
+    def push(self, address: int) -> None:
+        if len(self.stack) == self.depth:
+            self.stack.pop(0)
+            self.overflows += 1
+        self.stack.append(address)
+
+    def pop(self) -> Optional[int]:
+        if not self.stack:
+            self.underflows += 1
+            return None
+        self.predictions += 1
+        return self.stack.pop()
+
+    @property
+    def hit_rate(self) -> float:
+        """Share of the returns it predicted that went where it said"""
+        return self.correct / self.predictions if self.predictions else 0.0
+
+class BranchPredictor:
+    """Where fetch goes after a branch or jump before it resolves: a BTB for targets and a RAS for returns
+
+    A JR (a return, as the step-over command also assumes) is predicted
+    from the return-address stack, or from the BTB when the stack is
+    empty; every other transfer from the BTB, going to pc + 1 on a miss.
+    An indirect jump through a register the RAS does not cover, such as a
+    jump table, is predicted to go where it went last time.
+    """
+
+    def __init__(self, btb_size: int = 16, ras_depth: int = 8):
+        self.btb = BranchTargetBuffer(btb_size)
+        self.ras = ReturnAddressStack(ras_depth)
+        self.reset_stats()
+
+    def reset(self) -> None:
+        self.btb.reset()
+        self.ras.reset()
+        self.reset_stats()
+
+    def reset_stats(self) -> None:
+        self.btb.reset_stats()
+        self.ras.reset_stats()
+        self.predictions = 0
+        self.mispredictions = 0
+
+    def predict(self, instruction: Instruction, pc: int) -> int:
+        """The pc fetch would go to after the transfer at pc, without changing any state"""
+        if instruction.type == InstructionType.JR and self.ras.top() is not None:
+            return self.ras.top()
+        target = self.btb.peek(pc)
+        return pc + 1 if target is None else target
+
+    def resolve(self, instruction: Instruction, pc: int, next_pc: int, return_address: int) -> bool:
+        """Predict the transfer at pc, then train on where it went; True if the prediction was right
+
+        return_address is what a JAL links (pc + 1, or pc + 2 past a delay slot).
+        """
+        if instruction.type == InstructionType.JR and self.ras.top() is not None:
+            predicted = self.ras.pop()
+            self.ras.correct += predicted == next_pc
+        else:
+            if instruction.type == InstructionType.JR:
+                self.ras.pop()  # Counts the underflow
+            target = self.btb.lookup(pc)
+            predicted = pc + 1 if target is None else target
+        if instruction.type == InstructionType.JAL:
+            self.ras.push(return_address)
+        if next_pc != pc + 1:
+            self.btb.update(pc, next_pc)
+        self.predictions += 1
+        self.mispredictions += predicted != next_pc
+        return predicted == next_pc
+
+    @property
+    def accuracy(self) -> float:
+        return 1 - self.mispredictions / self.predictions if self.predictions else 0.0
+
+def format_predictor(predictor: BranchPredictor, isa: SimpleISA) -> List[str]:
+    """The BTB's valid entries and the RAS from the top down, each with its hit rate"""
+    btb, ras = predictor.btb, predictor.ras
+
+    def name(index: int) -> str:
+        label = symbol_for(index, isa.labels)
+        return f"{index} <{label}>" if label else str(index)
+
+    lines = [f"BTB: {btb.size} entries, {btb.hits}/{btb.lookups} hits ({btb.hit_rate:.0%}), "
+             f"{btb.evictions} evictions"]
+    for index, entry in enumerate(btb.entries):
+        if entry:
+            source = isa.instructions[entry.pc].source.split(';')[0].strip() if entry.pc < len(isa.instructions) else ''
+            lines.append(f"  [{index:2d}] pc {entry.pc:<4} {source:<18} -> {name(entry.target):<14} "
+                         f"{entry.hits} hits")
+    if not any(btb.entries):
+        lines.append("  empty")
+    lines.append(f"RAS: {len(ras.stack)}/{ras.depth} deep, {ras.correct}/{ras.predictions} returns predicted "
+                 f"({ras.hit_rate:.0%}), {ras.overflows} overflows, {ras.underflows} underflows")
+    for depth, address in enumerate(reversed(ras.stack)):
+        lines.append(f"  {'top' if depth == 0 else '':<4} {name(address)}")
+    if not ras.stack:
+        lines.append("  empty")
+    lines.append(f"Predicted {predictor.predictions - predictor.mispredictions}/{predictor.predictions} "
+                 f"branches and jumps ({predictor.accuracy:.0%})")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program through the pipeline model with a branch target "
+                                                 "buffer and return-address stack and show their contents and "
+                                                 "hit rates")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--btb', type=int, default=16, help="BTB entries (default 16)")
+    parser.add_argument('--ras', type=int, default=8, help="Return-address stack depth (default 8)")
+    args = parser.parse_args()
+    if args.btb < 1 or args.ras < 1:
+        parser.error("the BTB and RAS need at least one entry")
+
+    from memory import MainMemory
+    from pipeline import PipelineModel
+    from utils.logger import Logger, LogLevel
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    predictor = BranchPredictor(args.btb, args.ras)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The predictor, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.pipeline = PipelineModel(isa, predictor=predictor)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    print('\n'.join(format_predictor(predictor, isa)))
+    stats = isa.pipeline.get_stats()
+    print(f"{stats['instructions']} instructions in {stats['cycles']} cycles (CPI {stats['cpi']:.2f}), "
+          f"{stats['branch_bubbles']} branch bubbles")
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,115 @@
+import argparse
+import sys
+from typing import Dict, List, Optional
+
+from events import Event, SimEvent
+from isa import InstructionType, SimpleISA
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+
+MAIN = None  # Call stack entry of code outside any subroutine
+
+class FunctionProfiler:
+    """Call counts, instructions and cycles per function, from the ISA's retired instructions
+
+    A function is the target of a JAL; everything outside a call belongs to
+    main. A call stack follows JAL and JR, so a function's self cycles cover
+    its own instructions and its total cycles also those of the functions it
+    calls (a recursive function is counted once per instruction). The JAL
+    belongs to the caller and the JR to the function it returns from. Cycles
+    are the pipeline model's when the ISA has one and one per instruction
+    otherwise.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED])
+
+    def reset(self) -> None:
+        self.stack: List[Optional[int]] = [MAIN]
+        self.functions: Dict[Optional[int], Dict[str, int]] = {}
+        self._cycles = 0  # Pipeline cycle count at the last retired instruction
+
+    def _function(self, entry: Optional[int]) -> Dict[str, int]:
+        if entry not in self.functions:
+            self.functions[entry] = {'calls': 1 if entry is MAIN else 0, 'instructions': 0,
+                                     'self_cycles': 0, 'total_cycles': 0}
+        return self.functions[entry]
+
+    def on_event(self, event: Event) -> None:
+        pipeline = self.isa.pipeline
+        if pipeline:
+            cycles = max(pipeline.total_cycles - self._cycles, 0)
+            self._cycles = pipeline.total_cycles
+        else:
+            cycles = 1
+        current = self._function(self.stack[-1])
+        current['instructions'] += 1
+        current['self_cycles'] += cycles
+        for entry in set(self.stack):
+            self._function(entry)['total_cycles'] += cycles
+
+        kind = event.data['instruction'].type
+        if kind == InstructionType.JAL:
+            self.stack.append(event.data['next_pc'])
+            self._function(event.data['next_pc'])['calls'] += 1
+        elif kind == InstructionType.JR and len(self.stack) > 1:
+            self.stack.pop()
+
+    def profile(self) -> List[Dict]:
+        """One row per function that has run, the most expensive (total cycles) first"""
+        names = {index: label for label, index in self.isa.labels.items()}
+        cycles = sum(stats['self_cycles'] for stats in self.functions.values())
+        rows = []
+        for entry, stats in self.functions.items():
+            rows.append(dict(stats, entry=entry,
+                             name='main' if entry is MAIN else names.get(entry, f"pc {entry}"),
+                             share=stats['self_cycles'] / cycles * 100 if cycles else 0.0))
+        return sorted(rows, key=lambda row: (-row['total_cycles'], row['name']))
+
+def format_profile(rows: List[Dict]) -> List[str]:
+    """The profile as a text table"""
+    if not rows:
+        return ["No instructions executed yet"]
+    width = max(8, max(len(row['name']) for row in rows))
+    lines = [f"{'function':<{width}} {'calls':>6} {'instrs':>7} {'self cyc':>9} {'total cyc':>10} {'self %':>7}"]
+    for row in rows:
+        lines.append(f"{row['name']:<{width}} {row['calls']:>6} {row['instructions']:>7} {row['self_cycles']:>9} "
+                     f"{row['total_cycles']:>10} {row['share']:>6.1f}%")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program to halt and show how many times each function "
+                                                 "(JAL target) was called and the cycles spent in it")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--no-pipeline', action='store_true',
+                        help="Count one cycle per instruction instead of the pipeline model's cycles")
+    args = parser.parse_args()
+
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    if not args.no_pipeline:
+        isa.pipeline = PipelineModel(isa)
+    profiler = FunctionProfiler(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The profile, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    print('\n'.join(format_profile(profiler.profile())))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
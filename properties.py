@@ -0,0 +1,247 @@
+import argparse
+import io
+import random
+import sys
+from contextlib import redirect_stdout
+from typing import Dict, List, Optional, Tuple
+
+from encoding import FLOAT_IMMEDIATES, decode_instruction, encode_instruction
+from isa import SimpleISA, Instruction, InstructionType, OPCODES, format_instruction
+from utils.logger import Logger, LogLevel
+
+WORD_MASK = 0xFFFFFFFF
+REGISTERS = ['eax', 'ebx', 'ecx', 'edx', 'esi', 'edi', 'ebp', 'esp', 'zero']
+CONTROL_REGISTERS = ['status', 'cause', 'epc', 'timer']
+
+# Operand shapes each instruction is written with, from the opcode table that also executes it
+OPERAND_SHAPES = {inst_type: list(opcode.shapes) for inst_type, opcode in OPCODES.items()}
+
+# ALU instructions whose results and flags are checked against 32-bit wrapping semantics
+ALU_OPS = [InstructionType.ADD, InstructionType.SUB, InstructionType.AND, InstructionType.OR,
+           InstructionType.XOR, InstructionType.NOT, InstructionType.INC, InstructionType.DEC,
+           InstructionType.SHL, InstructionType.SHR, InstructionType.CMP, InstructionType.TEST]
+UNARY_OPS = {InstructionType.NOT, InstructionType.INC, InstructionType.DEC}
+
+# Words where carries, overflows and sign changes happen
+EDGE_WORDS = [0, 1, -1, 2, -2, 0x7FFFFFFF, -0x80000000, 0x7FFFFFFE, -0x7FFFFFFF, 0xFFFF, 0x8000, -0x8000]
+
+def random_word(rng: random.Random) -> int:
+    """A signed 32-bit word, as MOV loads it, biased towards edge cases"""
+    if rng.random() < 0.4:
+        return rng.choice(EDGE_WORDS)
+    return rng.randint(-0x80000000, 0x7FFFFFFF)
+
+def random_operand(rng: random.Random, kind: str) -> str:
+    if kind == 'reg':
+        return rng.choice(REGISTERS)
+    if kind == 'imm':
+        return f"#{random_word(rng)}"
+    if kind == 'mem':
+        return f"[{rng.choice([str(rng.randrange(1024)), rng.choice(REGISTERS[:-1])])}]"
+    if kind == 'freg':
+        return f"f{rng.randrange(32)}"
+    if kind == 'fimm':
+        return f"#{rng.choice([0.0, 1.5, -2.25, rng.uniform(-1000, 1000)])}"
+    if kind == 'creg':
+        return rng.choice(CONTROL_REGISTERS)
+    return rng.choice(['loop', 'done', 'skip_1', 'L2'])
+
+def _assemble(lines: List[str]) -> List[Instruction]:
+    with redirect_stdout(io.StringIO()):
+        instructions, _ = SimpleISA().parse_program(lines)
+    return instructions
+
+def check_round_trip(rng: random.Random, inst_type: InstructionType) -> Optional[str]:
+    """parse(format(i)) == i, also with the text in lower case and padded with spaces and a comment"""
+    operands = [random_operand(rng, kind) for kind in rng.choice(OPERAND_SHAPES[inst_type])]
+    instruction = Instruction(inst_type, operands, 0)
+    text = format_instruction(instruction)
+
+    words = text.split()
+    if rng.random() < 0.5:
+        words[0] = words[0].lower()
+    noisy = ' ' * rng.randint(0, 3) + (' ' * rng.randint(1, 3)).join(words) + rng.choice(['', '  ; comment', '\t'])
+
+    for source in (text, noisy):
+        parsed = _assemble([source])
+        if len(parsed) != 1:
+            return f"'{source}' assembled to {len(parsed)} instructions"
+        if parsed[0].type != inst_type or parsed[0].operands != operands:
+            return f"'{source}' assembled to {parsed[0].type.name} {parsed[0].operands}"
+        if format_instruction(parsed[0]) != text:
+            return f"'{source}' formats back as '{format_instruction(parsed[0])}', expected '{text}'"
+    return None
+
+def check_encoding(rng: random.Random, inst_type: InstructionType) -> Optional[str]:
+    """decode(encode(i)) == i; float immediates only need to encode to the same bits again"""
+    operands = [random_operand(rng, kind) for kind in rng.choice(OPERAND_SHAPES[inst_type])]
+    instruction = Instruction(inst_type, operands, 0)
+    labels = {name: i for i, name in enumerate(['loop', 'done', 'skip_1', 'L2'])}
+    text = format_instruction(instruction)
+
+    words = encode_instruction(instruction, labels)
+    decoded, length = decode_instruction(words + [0], 0, {i: name for name, i in labels.items()})
+    if length != len(words):
+        return f"'{text}' encoded as {len(words)} words but decodes from {length}"
+    float_immediate = inst_type in FLOAT_IMMEDIATES and any(op.startswith('#') for op in operands)
+    if not float_immediate and (decoded.type != inst_type or decoded.operands != operands):
+        return f"'{text}' decodes as '{format_instruction(decoded)}'"
+    if encode_instruction(decoded, labels) != words:
+        return f"'{text}' decodes as '{format_instruction(decoded)}', which encodes differently"
+    return None
+
+def reference_alu(op: InstructionType, a: int, b: int, flags: Dict[str, int],
+                  isa_mode: str) -> Tuple[Optional[int], Dict[str, int]]:
+    """Destination word (None if unchanged) and flags of one ALU instruction on 32-bit words
+
+    Carry and overflow come from the bits of the operands and result rather
+    than from signed comparisons, so this shares no arithmetic with isa.py.
+    """
+    ua, ub = a & WORD_MASK, b & WORD_MASK
+    flags = dict(flags)
+    write = True
+
+    if op in (InstructionType.ADD, InstructionType.INC):
+        ub = 1 if op == InstructionType.INC else ub
+        total = ua + ub
+        result = total & WORD_MASK
+        flags['C'] = total >> 32
+        flags['V'] = (((ua ^ result) & (ub ^ result)) >> 31) & 1
+    elif op in (InstructionType.SUB, InstructionType.DEC, InstructionType.CMP):
+        ub = 1 if op == InstructionType.DEC else ub
+        result = (ua - ub) & WORD_MASK
+        flags['C'] = int(ua >= ub)
+        flags['V'] = (((ua ^ ub) & (ua ^ result)) >> 31) & 1
+        write = op != InstructionType.CMP
+    elif op in (InstructionType.AND, InstructionType.TEST):
+        result = ua & ub
+        write = op != InstructionType.TEST
+    elif op == InstructionType.OR:
+        result = ua | ub
+    elif op == InstructionType.XOR:
+        result = ua ^ ub
+    elif op == InstructionType.NOT:
+        result = ~ua & WORD_MASK
+    elif op == InstructionType.SHL:
+        result = (ua << b) & WORD_MASK
+        if b:
+            flags['C'] = (ua >> (32 - b)) & 1
+    else:
+        # SHR divides by a power of two, so it shifts in copies of the sign bit
+        result = (a >> b) & WORD_MASK
+        if b:
+            flags['C'] = (ua >> (b - 1)) & 1
+
+    flags['Z'] = int(result == 0)
+    flags['N'] = result >> 31
+    if isa_mode == 'standard' and op == InstructionType.CMP:
+        return int(a < b), flags
+    if isa_mode == 'standard' and op == InstructionType.TEST:
+        return int(result != 0), flags
+    return (result if write else None), flags
+
+def check_alu(op: InstructionType, a: int, b: int, use_register: bool, preset: Tuple[int, int],
+              isa_mode: str) -> Optional[str]:
+    """Run one ALU instruction on eax (and ebx or an immediate) and compare with reference_alu
+
+    The CMP of `preset` before it leaves arbitrary flags behind, so flags the
+    instruction should preserve are checked too.
+    """
+    source = 'ebx' if use_register else f"#{b}"
+    text = f"{op.name} eax" if op in UNARY_OPS else f"{op.name} eax {source}"
+    lines = [f".mode {isa_mode}", f"MOV ecx #{preset[0]}", f"MOV edx #{preset[1]}", "CMP ecx edx",
+             f"MOV eax #{a}", f"MOV ebx #{b}", text, "HALT"]
+
+    isa = SimpleISA()
+    with redirect_stdout(io.StringIO()):
+        isa.load_program(lines)
+        isa.running = True
+        for _ in range(5):
+            isa.execute_step()
+        flags_before = dict(isa.flags)
+        isa.execute_step()
+
+    expected, expected_flags = reference_alu(op, a, b, flags_before, isa_mode)
+    actual = isa.registers['eax']
+    if expected is None:
+        expected = a & WORD_MASK
+    if actual & WORD_MASK != expected or isa.flags != expected_flags:
+        return (f"{text} with eax={a}, ebx={b} ({isa_mode} mode): eax={actual & WORD_MASK:#x} flags={isa.flags}, "
+                f"expected eax={expected:#x} flags={expected_flags}")
+    return None
+
+def shrink_alu(op: InstructionType, a: int, b: int, *rest) -> Tuple[int, int]:
+    """Replace operands with simpler words while the failure persists"""
+    candidates = [0, 1, -1, 2, 0x7FFFFFFF, -0x80000000]
+    for _ in range(4):
+        changed = False
+        for value in candidates + [a // 2]:
+            if abs(value) < abs(a) and check_alu(op, value, b, *rest):
+                a, changed = value, True
+                break
+        if op not in UNARY_OPS:
+            for value in candidates + [b // 2]:
+                if abs(value) < abs(b) and (op not in (InstructionType.SHL, InstructionType.SHR) or value >= 0) \
+                        and check_alu(op, a, value, *rest):
+                    b, changed = value, True
+                    break
+        if not changed:
+            break
+    return a, b
+
+def run_properties(seed: int, cases: int) -> List[str]:
+    """Check every property on `cases` random inputs per instruction; returns the failures"""
+    rng = random.Random(seed)
+    failures = []
+
+    missing = [t.name for t in InstructionType if t not in OPERAND_SHAPES]
+    if missing:
+        failures.append(f"no operand shapes for {', '.join(missing)} (add them to OPERAND_SHAPES)")
+
+    for inst_type in OPERAND_SHAPES:
+        for _ in range(cases):
+            problem = check_round_trip(rng, inst_type)
+            if problem:
+                failures.append(f"round trip: {problem}")
+                break
+        for _ in range(cases):
+            problem = check_encoding(rng, inst_type)
+            if problem:
+                failures.append(f"encoding: {problem}")
+                break
+
+    for op in ALU_OPS:
+        for _ in range(cases):
+            a = random_word(rng)
+            b = rng.randint(0, 32) if op in (InstructionType.SHL, InstructionType.SHR) else random_word(rng)
+            rest = (rng.random() < 0.5, (random_word(rng), random_word(rng)), rng.choice(['standard', 'arm']))
+            if check_alu(op, a, b, *rest):
+                a, b = shrink_alu(op, a, b, *rest)
+                failures.append(f"ALU: {check_alu(op, a, b, *rest)}")
+                break
+    return failures
+
+def main():
+    parser = argparse.ArgumentParser(description="Property checks: assembly text round-trips through the assembler "
+                                                 "and the binary encoding, and ALU instructions match 32-bit "
+                                                 "wrapping semantics")
+    parser.add_argument('--seed', type=int, default=0, help="Seed for the random cases")
+    parser.add_argument('--cases', type=int, default=200, help="Random cases per instruction and property")
+    args = parser.parse_args()
+
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        failures = run_properties(args.seed, args.cases)
+    finally:
+        logger.log_level = level
+
+    for failure in failures:
+        print(failure)
+    print(f"Round trip: {len(OPERAND_SHAPES)} instructions, ALU: {len(ALU_OPS)} instructions, "
+          f"{args.cases} cases each (seed {args.seed}): {'FAILED' if failures else 'all passed'}")
+    return 1 if failures else 0
+
+if __name__ == '__main__':
+    sys.exit(main())
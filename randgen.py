@@ -0,0 +1,170 @@
+import argparse
+import io
+import random
+import sys
+from contextlib import redirect_stdout
+from typing import List, Optional
+
+from isa import SimpleISA, InstructionType
+from locality import build_hierarchy
+from utils.logger import Logger, LogLevel
+
+# Registers the generator computes with; ecx is reserved as the loop counter
+DATA_REGISTERS = ['eax', 'ebx', 'edx', 'esi', 'edi', 'ebp']
+LOOP_COUNTER = 'ecx'
+
+ALU_BINARY = ['ADD', 'SUB', 'AND', 'OR', 'XOR']
+ALU_UNARY = ['INC', 'DEC', 'NOT']
+FORWARD_BRANCHES = ['BEQ', 'BNE', 'BLT', 'BGE', 'BMI', 'BPL']
+
+class ProgramGenerator:
+    """Random but valid programs for stress-testing the simulator
+
+    Programs run in ARM flags mode and always halt: forward branches skip
+    a few instructions, loops count ecx down from a small constant and are
+    never nested, and every load reads an address in the data window that
+    an earlier instruction has stored to. The same seed always produces
+    the same program.
+    """
+
+    def __init__(self, seed: int, data_start: int = 200, data_words: int = 32,
+                 max_loop_iterations: int = 4):
+        self.rng = random.Random(seed)
+        self.seed = seed
+        self.data_start = data_start
+        self.data_words = data_words
+        self.max_loop_iterations = max_loop_iterations
+        self._labels = 0
+
+    def _label(self, prefix: str) -> str:
+        self._labels += 1
+        return f"{prefix}_{self._labels}"
+
+    def _register(self) -> str:
+        return self.rng.choice(DATA_REGISTERS)
+
+    def _address(self) -> int:
+        return self.data_start + self.rng.randrange(self.data_words)
+
+    def _instruction(self, written: List[int]) -> str:
+        """One straight-line instruction"""
+        rng = self.rng
+        kind = rng.choice(['mov', 'alu', 'alu', 'unary', 'shift', 'load', 'store', 'store', 'cmp'])
+        if kind == 'load' and not written:
+            kind = 'store'
+
+        if kind == 'mov':
+            if rng.random() < 0.5:
+                return f"MOV {self._register()} #{rng.randint(-100, 100)}"
+            return f"MOV {self._register()} {self._register()}"
+        if kind == 'alu':
+            src = self._register() if rng.random() < 0.5 else f"#{rng.randint(0, 255)}"
+            return f"{rng.choice(ALU_BINARY)} {self._register()} {src}"
+        if kind == 'unary':
+            return f"{rng.choice(ALU_UNARY)} {self._register()}"
+        if kind == 'shift':
+            return f"{rng.choice(['SHL', 'SHR'])} {self._register()} #{rng.randint(1, 4)}"
+        if kind == 'load':
+            return f"LOAD {self._register()} [{rng.choice(written)}]"
+        if kind == 'store':
+            addr = self._address()
+            if addr not in written:
+                written.append(addr)
+            if rng.random() < 0.3:
+                return f"MOV [{addr}] #{rng.randint(0, 255)}"
+            return f"STORE [{addr}] {self._register()}"
+        src = self._register() if rng.random() < 0.5 else f"#{rng.randint(-50, 50)}"
+        return f"CMP {self._register()} {src}"
+
+    def _block(self, length: int, written: List[int], in_loop: bool = False) -> List[str]:
+        lines = []
+        while len(lines) < length:
+            roll = self.rng.random()
+            if roll < 0.1:
+                # Forward branch over a short run of instructions
+                skip = self._label('skip')
+                lines.append(f"CMP {self._register()} {self._register()}")
+                lines.append(f"{self.rng.choice(FORWARD_BRANCHES)} {skip}")
+                # Stores behind a branch may not run, so loads must not rely on them
+                lines.extend(self._instruction(list(written)) for _ in range(self.rng.randint(1, 3)))
+                lines.append(f"{skip}:")
+            elif roll < 0.18 and not in_loop:
+                # Counted loop; the body leaves ecx alone and DEC sets the flags BNE tests
+                top = self._label('loop')
+                lines.append(f"MOV {LOOP_COUNTER} #{self.rng.randint(1, self.max_loop_iterations)}")
+                lines.append(f"{top}:")
+                lines.extend(self._block(self.rng.randint(2, 5), written, in_loop=True))
+                lines.append(f"DEC {LOOP_COUNTER}")
+                lines.append(f"BNE {top}")
+            else:
+                lines.append(self._instruction(written))
+        return lines
+
+    def generate(self, length: int = 40) -> List[str]:
+        """Return the lines of a program with roughly `length` instructions"""
+        lines = [f"; Random program (seed {self.seed})", ".mode arm"]
+        # Start every register defined so no read is uninitialized
+        lines.extend(f"MOV {reg} #{self.rng.randint(0, 100)}" for reg in DATA_REGISTERS + [LOOP_COUNTER])
+        lines.extend(self._block(length, []))
+        lines.append("HALT")
+        return lines
+
+def check_program(lines: List[str]) -> Optional[str]:
+    """Run a program on the default hierarchy; return a description of the first problem, if any"""
+    memory, l1, l2 = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        with redirect_stdout(io.StringIO()):
+            isa.load_program(lines)
+            isa.run()
+    except Exception as e:
+        return f"crashed: {e!r}"
+    finally:
+        logger.log_level = level
+
+    if isa.last_error is not None:
+        return f"Error executing instruction: {isa.last_error}"
+    if isa.pc == 0 or isa.instructions[isa.pc - 1].type != InstructionType.HALT:
+        return f"stopped at pc {isa.pc} without reaching HALT"
+    if any(isa.uninitialized_reads.values()):
+        return f"uninitialized reads: {isa.uninitialized_reads}"
+
+    # The cache hierarchy must agree with main memory on every written word
+    for addr in sorted(isa.initialized_memory):
+        cached, stored = l1.read(addr, output=False), memory.read(addr)
+        if cached != stored:
+            return f"cache holds {cached} for address {addr} but memory holds {stored}"
+    return None
+
+def main():
+    parser = argparse.ArgumentParser(description="Generate random valid programs, or stress-test the simulator with them")
+    parser.add_argument('--seed', type=int, default=0, help="Seed of the (first) program")
+    parser.add_argument('--length', type=int, default=40, help="Approximate number of instructions")
+    parser.add_argument('-o', '--output', help="File to write the program to (default: stdout)")
+    parser.add_argument('--stress', type=int, metavar='N', help="Run N programs (seeds seed..seed+N-1) and report failures")
+    args = parser.parse_args()
+
+    if args.stress:
+        failures = 0
+        for seed in range(args.seed, args.seed + args.stress):
+            problem = check_program(ProgramGenerator(seed).generate(args.length))
+            if problem:
+                failures += 1
+                print(f"seed {seed}: {problem}")
+        print(f"{args.stress - failures}/{args.stress} programs passed")
+        if failures:
+            print(f"Reproduce with: python randgen.py --seed <seed> --length {args.length}")
+        return 1 if failures else 0
+
+    text = '\n'.join(ProgramGenerator(args.seed).generate(args.length)) + '\n'
+    if args.output:
+        with open(args.output, 'w') as f:
+            f.write(text)
+    else:
+        print(text, end='')
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,123 @@
+import argparse
+import inspect
+import re
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Tuple
+
+from isa import OPCODES, Instruction, InstructionType, mnemonic
+from microcode import CONTROL_ROM, dispatch
+from vcd import OPCODE_BITS
+
+# How each operand kind is written in a syntax line, and the operands (first, second position)
+# used to render its micro-operations
+OPERAND_SYNTAX = {'reg': ('reg', ('eax', 'ebx')), 'imm': ('#imm', ('#5', '#5')),
+                  'mem': ('[addr]', ('[100]', '[100]')), 'freg': ('fN', ('f1', 'f2')), 'fimm': ('#float', ('#1.5', '#1.5')),
+                  'creg': ('creg', ('epc', 'epc')), 'label': ('label', ('loop', 'loop'))}
+
+@dataclass
+class OperandForm:
+    """One way of writing an instruction and the control-ROM routine that runs it"""
+    syntax: str
+    routine: str
+    transfers: List[str]
+
+@dataclass
+class InstructionInfo:
+    mnemonic: str
+    opcode: int
+    group: str
+    summary: str
+    forms: List[OperandForm] = field(default_factory=list)
+
+def _enum_comments() -> Dict[str, Tuple[str, str]]:
+    """InstructionType member name -> (group heading, trailing comment), read from the enum's source"""
+    comments = {}
+    group = "Integer and control"
+    for line in inspect.getsource(InstructionType).splitlines():
+        heading = re.match(r'\s*#\s*(.+)', line)
+        member = re.match(r'\s*(\w+)\s*=\s*auto\(\)\s*(?:#\s*(.*))?', line)
+        if heading:
+            group = heading.group(1)
+        elif member:
+            comments[member.group(1)] = (group, member.group(2) or "")
+    return comments
+
+def instruction_reference() -> List[InstructionInfo]:
+    """Syntax, opcode and micro-operations of every instruction the assembler accepts, in opcode order
+
+    Built from the assembler's InstructionType table, the opcode table that
+    executes each instruction and the microcode dispatch, so it cannot drift
+    from what the simulator does.
+    """
+    comments = _enum_comments()
+    reference = []
+    for inst_type in InstructionType:
+        group, summary = comments.get(inst_type.name, ("", ""))
+        info = InstructionInfo(mnemonic(inst_type), inst_type.value, group, summary)
+        for shape in OPCODES[inst_type].shapes:
+            syntax = ' '.join([info.mnemonic] + [OPERAND_SYNTAX[kind][0] for kind in shape])
+            sample = Instruction(inst_type, [OPERAND_SYNTAX[kind][1][i] for i, kind in enumerate(shape)], 0)
+            routine = dispatch(sample)
+            transfers = [word.render(sample) for word in CONTROL_ROM if word.routine == routine]
+            info.forms.append(OperandForm(syntax, routine, transfers))
+        reference.append(info)
+    return reference
+
+def lookup(name: str) -> Optional[InstructionInfo]:
+    """Reference entry for a mnemonic in any case, with or without the FP dots"""
+    key = name.upper().replace('_', '.')
+    for info in instruction_reference():
+        if info.mnemonic.replace('_', '.') == key:
+            return info
+    return None
+
+def format_reference(info: InstructionInfo) -> List[str]:
+    lines = [f"{info.mnemonic} - {info.summary}" if info.summary else info.mnemonic,
+             f"  group:    {info.group}",
+             f"  encoding: opcode {info.opcode} (0x{info.opcode:02x}), {OPCODE_BITS}-bit field"]
+    for form in info.forms:
+        lines.append(f"  {form.syntax}")
+        lines += [f"      {transfer}" for transfer in form.transfers]
+    return lines
+
+def format_isa_list(reference: List[InstructionInfo]) -> List[str]:
+    """One line per instruction: opcode, mnemonic, operand forms and summary, under each group's heading"""
+    lines = []
+    group = None
+    for info in reference:
+        if info.group != group:
+            group = info.group
+            lines += ["", f"{group}:"] if lines else [f"{group}:"]
+        forms = " | ".join(' '.join(form.syntax.split()[1:]) or "-" for form in info.forms)
+        lines.append(f"  {info.opcode:>3}  {info.mnemonic:<12} {forms:<40} {info.summary}".rstrip())
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Print the instruction reference: syntax, opcode and the "
+                                                 "micro-operations each operand form runs")
+    parser.add_argument('mnemonics', nargs='*', help="Instructions to show (default: all)")
+    parser.add_argument('--list-isa', action='store_true',
+                        help="One line per instruction (opcode, operand forms and summary) instead")
+    args = parser.parse_args()
+
+    if args.list_isa:
+        print('\n'.join(format_isa_list(instruction_reference())))
+        return 0
+
+    if args.mnemonics:
+        entries = [lookup(name) for name in args.mnemonics]
+        unknown = [name for name, info in zip(args.mnemonics, entries) if info is None]
+        if unknown:
+            print(f"Unknown instruction: {', '.join(unknown)}")
+            return 1
+    else:
+        entries = instruction_reference()
+
+    for info in entries:
+        print('\n'.join(format_reference(info)))
+        print()
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,192 @@
+import argparse
+import base64
+import html
+import os
+import sys
+from datetime import datetime
+from typing import Dict, List, Tuple
+
+from encoding import build_listing
+from explain import _cache_below
+from isa import SimpleISA
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+
+# Series colors of the embedded charts; both read on white and dark backgrounds
+CHART_COLORS = ("#0072b2", "#e69f00", "#009e73")
+
+def report_format(filename: str) -> str:
+    """'md' for .md and .markdown files, 'html' for anything else"""
+    return 'md' if os.path.splitext(filename)[1].lower() in ('.md', '.markdown') else 'html'
+
+def svg_bar_chart(rows: List[Tuple[str, List[float]]], series: List[str], width: int = 520) -> str:
+    """Horizontal bar chart, one row per label and one bar per series, as standalone SVG"""
+    label_width, row_height = 70, 20
+    bar_height = (row_height - 4) // len(series)
+    height = 24 + row_height * len(rows)
+    top = max((max(values) for _, values in rows), default=0) or 1
+    scale = (width - label_width - 50) / top
+    parts = [f'<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" '
+             f'font-family="monospace" font-size="11">']
+    for row, (label, values) in enumerate(rows):
+        y = row * row_height
+        parts.append(f'<text x="0" y="{y + 13}">{html.escape(label)}</text>')
+        for i, value in enumerate(values):
+            bar = int(value * scale)
+            color = CHART_COLORS[i % len(CHART_COLORS)]
+            parts.append(f'<rect x="{label_width}" y="{y + i * bar_height}" width="{bar}" '
+                         f'height="{bar_height - 1}" fill="{color}"/>')
+            parts.append(f'<text x="{label_width + bar + 4}" y="{y + (i + 1) * bar_height - 1}" '
+                         f'fill="{color}">{value:g}</text>')
+    x = label_width
+    for i, name in enumerate(series):
+        parts.append(f'<text x="{x}" y="{height - 4}" fill="{CHART_COLORS[i % len(CHART_COLORS)]}">'
+                     f'{html.escape(name)}</text>')
+        x += 8 * (len(name) + 2)
+    parts.append('</svg>')
+    return ''.join(parts)
+
+def collect_report(isa: SimpleISA, name: str) -> Dict:
+    """Everything a report shows about the program the ISA ran, as tables and charts
+
+    Tables are (headers, rows) pairs of strings, charts SVG documents. Memory
+    is read from main memory, which holds every word the program wrote.
+    """
+    listing = build_listing(isa.instructions, isa.labels)
+    counts = isa.pc_exec_counts
+    program = (['line', 'pc', 'encoding', 'source', 'executed'],
+               [[str(row.line_number + 1), str(row.pc),
+                 ' '.join(f"{word:08x}" for word in row.words) if not row.error else f"error: {row.error}",
+                 row.source.strip(), str(counts.get(row.pc, 0))] for row in listing])
+
+    registers = (['register', 'value'],
+                 [[reg, str(value)] for reg, value in isa.registers.items()] +
+                 [[reg, str(value)] for reg, value in isa.fp_registers.items() if value] +
+                 [[f"flag {flag}", str(value)] for flag, value in isa.flags.items()])
+    memory = (['address', 'value', 'kind'],
+              [[str(addr), str(isa.memory.data[addr]), isa.memory_kind(addr)]
+               for addr in sorted(isa.initialized_memory) if 0 <= addr < len(isa.memory.data)])
+
+    summary = [['instructions', str(isa.instruction_count)], ['final pc', str(isa.pc)],
+               ['status', f"stopped: {isa.last_error}" if isa.last_error else
+                "halted" if not isa.running or isa.pc >= len(isa.instructions) else "not finished"]]
+    if isa.pipeline:
+        stats = isa.pipeline.get_stats()
+        summary += [['pipeline cycles', str(stats['cycles'])], ['CPI', f"{stats['cpi']:.2f}"],
+                    ['stall cycles', str(stats['stall_cycles'])], ['branch bubbles', str(stats['branch_bubbles'])]]
+    caches = []
+    cache = isa.cache
+    while cache is not None:
+        stats = cache.get_performance_stats()
+        caches.append((cache._name, stats))
+        summary += [[f"{cache._name} hits / misses", f"{stats['hits']} / {stats['misses']}"],
+                    [f"{cache._name} hit rate", f"{stats['hit_rate']:.1f}%"]]
+        cache = _cache_below(cache)
+
+    usage = isa.get_register_usage()['counts']
+    loops = isa.get_loop_stats()
+    charts = [("Register reads and writes",
+               svg_bar_chart([(reg, [c['reads'], c['writes']]) for reg, c in usage.items()], ['reads', 'writes']))]
+    if caches:
+        charts.append(("Cache hits and misses",
+                       svg_bar_chart([(name[:8], [s['hits'], s['misses']]) for name, s in caches], ['hits', 'misses'])))
+    if loops:
+        charts.append(("Share of instructions spent in each loop",
+                       svg_bar_chart([(loop['label'][:8], [round(loop['share'], 1)]) for loop in loops],
+                                     ['% of instructions'])))
+
+    return {
+        'title': f"Run report: {name}",
+        'generated': datetime.now().strftime('%Y-%m-%d %H:%M'),
+        'tables': [("Summary", (['statistic', 'value'], summary)),
+                   ("Program listing", program),
+                   ("Final registers and flags", registers),
+                   ("Memory written by the program", memory),
+                   ("Register usage", (['register', 'reads', 'writes'],
+                                       [[reg, str(c['reads']), str(c['writes'])] for reg, c in usage.items()])),
+                   ("Loops", (['loop', 'pcs', 'iterations', 'instructions', 'share'],
+                              [[loop['label'], f"{loop['start']}-{loop['end']}", str(loop['iterations']),
+                                str(loop['instructions']), f"{loop['share']:.1f}%"] for loop in loops]))],
+        'charts': charts
+    }
+
+def render_html(report: Dict) -> str:
+    """A single HTML file with the charts inlined as SVG"""
+    parts = ['<!DOCTYPE html>', '<html><head><meta charset="utf-8">',
+             f'<title>{html.escape(report["title"])}</title>',
+             '<style>body { font-family: sans-serif; margin: 2em; } '
+             'table { border-collapse: collapse; margin-bottom: 1.5em; } '
+             'th, td { border: 1px solid #999; padding: 2px 8px; text-align: left; font-family: monospace; }'
+             '</style></head><body>',
+             f'<h1>{html.escape(report["title"])}</h1>', f'<p>Generated {report["generated"]}</p>']
+    for title, (headers, rows) in report['tables']:
+        parts.append(f'<h2>{html.escape(title)}</h2>')
+        if not rows:
+            parts.append('<p>None</p>')
+            continue
+        parts.append('<table><tr>' + ''.join(f'<th>{html.escape(h)}</th>' for h in headers) + '</tr>')
+        parts += ['<tr>' + ''.join(f'<td>{html.escape(cell)}</td>' for cell in row) + '</tr>' for row in rows]
+        parts.append('</table>')
+    parts.append('<h2>Charts</h2>')
+    for title, svg in report['charts']:
+        parts += [f'<h3>{html.escape(title)}</h3>', svg]
+    parts.append('</body></html>')
+    return '\n'.join(parts) + '\n'
+
+def render_markdown(report: Dict) -> str:
+    """Markdown with the charts embedded as SVG data URIs"""
+    lines = [f"# {report['title']}", "", f"Generated {report['generated']}", ""]
+    for title, (headers, rows) in report['tables']:
+        lines += [f"## {title}", ""]
+        if not rows:
+            lines += ["None", ""]
+            continue
+        lines.append('| ' + ' | '.join(headers) + ' |')
+        lines.append('|' + '---|' * len(headers))
+        lines += ['| ' + ' | '.join(cell.replace('|', '\\|') for cell in row) + ' |' for row in rows]
+        lines.append("")
+    lines += ["## Charts", ""]
+    for title, svg in report['charts']:
+        data = base64.b64encode(svg.encode()).decode()
+        lines += [f"### {title}", "", f"![{title}](data:image/svg+xml;base64,{data})", ""]
+    return '\n'.join(lines)
+
+def write_report(isa: SimpleISA, name: str, filename: str) -> None:
+    """Save a report of the ISA's program, as Markdown for .md files and HTML otherwise"""
+    report = collect_report(isa, name)
+    with open(filename, 'w') as f:
+        f.write(render_markdown(report) if report_format(filename) == 'md' else render_html(report))
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program to halt and write a report with its listing, "
+                                                 "final registers and memory, statistics and charts")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--report', default='report.html', metavar='FILE',
+                        help="Report to write; .md files are Markdown, anything else HTML (default: report.html)")
+    args = parser.parse_args()
+
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    isa.pipeline = PipelineModel(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The report, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    write_report(isa, os.path.basename(args.program), args.report)
+    print(f"Report written to {args.report}")
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
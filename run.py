@@ -0,0 +1,203 @@
+import argparse
+import io
+import select
+import sys
+import time
+from contextlib import contextmanager, nullcontext, redirect_stdout
+from typing import Iterable, Optional
+
+from events import Event, SimEvent
+from isa import SimpleISA, symbol_for
+from locality import build_hierarchy
+from pipeline import PipelineModel
+from tracer import CompactTrace
+from utils.files import STDIN, read_lines
+from utils.logger import Logger, LogLevel
+from utils.style import add_color_arguments, configure_color
+
+ERROR_STATUS = 1   # The program stopped on an error or unhandled trap
+LIMIT_STATUS = 124  # An instruction or cycle limit stopped it, as timeout(1) reports a command it killed
+QUIT_STATUS = 130  # Quit with q before it halted, as a shell reports a command stopped by Ctrl-C
+
+def exit_status(isa: SimpleISA) -> int:
+    """The shell exit status a finished run maps to
+
+    A store to the exit address gives its value (modulo 256, as a shell
+    sees it), or ERROR_STATUS for a float that is not a whole number; an
+    error gives ERROR_STATUS and a reached limit LIMIT_STATUS; HALT, the
+    exit syscall and leaving the program give 0.
+    """
+    if isa.last_error is not None:
+        return ERROR_STATUS
+    if isa.halt_reason == 'exit_address':
+        if isinstance(isa.exit_code, float) and not isa.exit_code.is_integer():
+            return ERROR_STATUS
+        return int(isa.exit_code) & 0xFF
+    if isa.halt_reason in ('instructions', 'cycles'):
+        return LIMIT_STATUS
+    return 0
+
+@contextmanager
+def keypresses(stream):
+    """Deliver a terminal's keys one at a time, without waiting for Enter, until the block ends"""
+    try:
+        import termios
+        import tty
+        saved = termios.tcgetattr(stream) if stream.isatty() else None
+    except (ImportError, OSError, ValueError):
+        saved = None
+    if saved is None:
+        yield
+        return
+    tty.setcbreak(stream)
+    try:
+        yield
+    finally:
+        termios.tcsetattr(stream, termios.TCSADRAIN, saved)
+
+class Pacer:
+    """Holds a run back after every retired instruction: until a key is pressed (one line from a pipe), or for
+    delay_ms when advancing by itself, pausing for a key at breakpoints as well
+
+    Breakpoints are instruction indexes; the run pauses when one is next.
+    q quits, stopping the run after the instruction that just retired. At
+    the end of piped input the run goes on without pausing.
+    """
+
+    def __init__(self, isa: SimpleISA, delay_ms: Optional[int] = None, breakpoints: Iterable[int] = (),
+                 keys=sys.stdin):
+        self.isa = isa
+        self.delay_ms = delay_ms  # None waits for a key after every step
+        self.breakpoints = set(breakpoints)
+        self.keys = keys
+        self.quit = False
+        self._pausing = True
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED])
+
+    def on_event(self, event: Event) -> None:
+        if not self.isa.running or not self._pausing:
+            return
+        if self.isa.pc in self.breakpoints:
+            print(f"Breakpoint before {self._where()} - any key continues, q quits", flush=True)
+            self._handle(self._read_key())
+        elif self.delay_ms is None:
+            self._handle(self._read_key())
+        else:
+            time.sleep(self.delay_ms / 1000)
+            if self.keys.isatty() and select.select([self.keys], [], [], 0)[0]:
+                self._handle(self._read_key())
+
+    def _where(self) -> str:
+        pc, labels = self.isa.pc, self.isa.labels
+        symbol = symbol_for(pc, labels)
+        source = self.isa.instructions[pc].source if pc < len(self.isa.instructions) else "end of program"
+        return f"pc {pc}{f' <{symbol}>' if symbol else ''}: {source}"
+
+    def _read_key(self) -> Optional[str]:
+        """The key pressed, or the line typed into a pipe; None at the end of the input"""
+        key = self.keys.read(1) if self.keys.isatty() else self.keys.readline()
+        return key or None
+
+    def _handle(self, key: Optional[str]) -> None:
+        if key is None:
+            self._pausing = False
+        elif key.strip().lower() == 'q':
+            self.quit = True
+            self.isa.running = False
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program without the GUI until it halts, print why it "
+                                                 "stopped and its registers, and exit with its exit status")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--max-instructions', type=int, default=0, help="Stop after this many instructions")
+    parser.add_argument('--max-cycles', type=int, default=0,
+                        help="Stop after this many cycles (pipeline cycles unless --no-pipeline)")
+    parser.add_argument('--exit-address', type=int,
+                        help="Stop when the program stores to this address; the value is the exit status")
+    parser.add_argument('--at-end', choices=('halt', 'error'),
+                        help="Whether the PC leaving the program halts it or is an error (default: halt)")
+    parser.add_argument('--miss-penalty', type=int, default=0,
+                        help="Cycles each L1 miss stalls the processor (default: the program's .miss_penalty, or 0)")
+    parser.add_argument('--no-pipeline', action='store_true',
+                        help="Count one cycle per instruction instead of the pipeline model's cycles")
+    steps = parser.add_mutually_exclusive_group()
+    steps.add_argument('--compact', action='store_true',
+                       help="Print one line per step: cycle, pc, disassembly, changed registers and memory words, "
+                            "and its L1 hits and misses")
+    steps.add_argument('--full', action='store_true',
+                       help="Print every step's full trace: its cache lookups with their sets drawn, and a "
+                            "summary of the run")
+    pacing = parser.add_mutually_exclusive_group()
+    pacing.add_argument('--step', action='store_true',
+                        help="Wait for a key (a line when piped) after every step; q quits")
+    pacing.add_argument('--auto', action='store_true',
+                        help="Advance by itself, --delay-ms apart, pausing at breakpoints; q quits")
+    parser.add_argument('--delay-ms', type=int, default=200, help="Milliseconds between --auto steps (default 200)")
+    parser.add_argument('--break', dest='breakpoints', action='append', default=[], metavar='LABEL|ADDR',
+                        help="Pause --auto before the instruction at a label or instruction address; repeatable")
+    add_color_arguments(parser)
+    args = parser.parse_args()
+    configure_color(args)
+    if args.max_instructions < 0 or args.max_cycles < 0 or args.miss_penalty < 0 or args.delay_ms < 0:
+        parser.error("limits, the miss penalty and the delay cannot be negative")
+    if args.breakpoints and not args.auto:
+        parser.error("--break pauses --auto runs; --step pauses at every instruction already")
+    if args.program == STDIN and (args.step or args.auto):
+        parser.error("--step and --auto read keys from standard input, so the program cannot come from it")
+    args.compact = args.compact or (args.step or args.auto) and not args.full  # Paced steps are printed
+
+    memory, l1, _ = build_hierarchy()
+    if args.exit_address is not None and not 0 <= args.exit_address < len(memory.data):
+        parser.error(f"--exit-address {args.exit_address} is outside memory (0-{len(memory.data) - 1})")
+    isa = SimpleISA(memory=memory, cache=l1)
+    if not args.no_pipeline:
+        isa.pipeline = PipelineModel(isa)
+    logger = Logger()
+    level = logger.log_level
+    if not args.full:
+        logger.log_level = LogLevel.ERROR  # The outcome, not the trace, is the output
+    output = io.StringIO()
+    try:
+        # PRINT_REG and friends are kept apart from the summary, unless steps are printed and they go between them
+        program = read_lines(args.program)
+        with nullcontext() if args.compact or args.full else redirect_stdout(output):
+            isa.load_program(program)
+            if args.compact:
+                CompactTrace(isa)
+            pacer = None
+            if args.step or args.auto:
+                pacer = Pacer(isa, args.delay_ms if args.auto else None,
+                              [isa.instruction_index(location) for location in args.breakpoints])
+            # The command line overrides the program's own .max_instructions, .max_cycles, .exit_address and .at_end
+            isa.max_instructions = args.max_instructions or isa.max_instructions
+            isa.max_cycles = args.max_cycles or isa.max_cycles
+            if args.exit_address is not None:
+                isa.exit_address = args.exit_address
+            if args.at_end:
+                isa.halt_at_end = args.at_end == 'halt'
+            isa.miss_penalty = args.miss_penalty or isa.miss_penalty
+            with keypresses(sys.stdin) if pacer else nullcontext():
+                isa.run()
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return ERROR_STATUS
+    except ValueError as e:
+        print(f"Error loading {args.program}: {e}")
+        return ERROR_STATUS
+    finally:
+        logger.log_level = level
+
+    print(output.getvalue(), end='')
+    if pacer and pacer.quit:
+        print(f"Quit after {isa.instruction_count} instructions, {isa.cycles} cycles")
+    elif isa.last_error is not None:
+        print(f"Error after {isa.instruction_count} instructions: {isa.last_error}")
+    else:
+        print(f"Halted after {isa.instruction_count} instructions, {isa.cycles} cycles: {isa.halt_description()}")
+    if isa.memory_stall_cycles:
+        print(f"Stalled {isa.memory_stall_cycles} cycles waiting for memory ({isa.miss_penalty} per L1 miss)")
+    print("Registers: " + ", ".join(f"{reg}={value}" for reg, value in isa.registers.items() if value))
+    return QUIT_STATUS if pacer and pacer.quit else exit_status(isa)
+
+if __name__ == '__main__':
+    sys.exit(main())
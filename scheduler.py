@@ -0,0 +1,206 @@
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Set, Tuple
+
+from events import Event, SimEvent
+from utils.logger import Logger, LogLevel
+
+@dataclass
+class Process:
+    """A program loaded at its own code and data offsets"""
+    pid: int
+    name: str
+    code_base: int   # Index of the first instruction
+    code_size: int
+    data_base: int   # Physical address that data address 0 maps to
+    data_size: int
+    labels: Dict[str, int]
+    pc: int
+    registers: Dict[str, int] = field(default_factory=dict)
+    fp_registers: Dict[str, float] = field(default_factory=dict)
+    flags: Dict[str, int] = field(default_factory=dict)
+    initialized_registers: Set[str] = field(default_factory=set)
+    state: str = "ready"  # ready, running or done
+    instructions_executed: int = 0
+    times_scheduled: int = 0
+    cache_hits: int = 0    # L1 hits and misses while this process was running
+    cache_misses: int = 0
+
+    def owns_address(self, address: int) -> bool:
+        return self.data_base <= address < self.data_base + self.data_size
+
+class RoundRobinScheduler:
+    """Timer-driven round-robin context switcher for several programs
+
+    Each program's code is appended to the ISA's instruction list and its
+    memory accesses are relocated into a private data region with the ISA's
+    base and bounds registers. Every `quantum` instructions the timer fires
+    and the running process's registers are saved and the next ready process
+    is restored. A quantum of 0 turns the timer off, so the programs run
+    back to back, each to completion in turn. The caches are shared, and
+    each process's L1 hits and misses are counted while it runs, so what
+    one program's lines cost another shows up in its miss count.
+    """
+
+    def __init__(self, quantum: int = 8, region_size: int = 256):
+        self.quantum = quantum
+        self.region_size = region_size
+        self.processes: List[Process] = []
+        self.current: Optional[Process] = None
+        self.context_switches = 0
+        self.logger = Logger()
+        self._count_at_switch_in = 0
+
+    @property
+    def back_to_back(self) -> bool:
+        return self.quantum <= 0
+
+    def load(self, isa, programs: List[Tuple[str, List[str]]], bases: Optional[List[int]] = None) -> None:
+        """Load (name, lines) programs into the ISA and start the first one
+
+        Each program's data region starts at its entry in bases, or by
+        default right after the previous program's region.
+        """
+        if bases is None:
+            bases = [pid * self.region_size for pid in range(len(programs))]
+        if len(bases) != len(programs):
+            raise ValueError(f"{len(programs)} programs but {len(bases)} base addresses")
+        regions = sorted(bases)
+        if regions[0] < 0 or regions[-1] + self.region_size > isa.memory._size:
+            raise ValueError(f"{len(programs)} programs need {regions[-1] + self.region_size} words of memory")
+        for low, high in zip(regions, regions[1:]):
+            if high - low < self.region_size:
+                raise ValueError(f"Data regions at {low} and {high} overlap ({self.region_size} words each)")
+
+        isa.load_program([])
+        self.processes = []
+        for pid, (name, lines) in enumerate(programs):
+            code_base = len(isa.instructions)
+            instructions, labels = isa.parse_program(lines, base=code_base)
+            isa.instructions.extend(instructions)
+            self.processes.append(Process(
+                pid=pid,
+                name=name,
+                code_base=code_base,
+                code_size=len(instructions),
+                data_base=bases[pid],
+                data_size=self.region_size,
+                labels=labels,
+                pc=code_base,
+                registers={reg: 0 for reg in isa.registers},
+                initialized_registers=set(isa.initialized_registers),
+                fp_registers={reg: 0.0 for reg in isa.fp_registers},
+                flags={'Z': 0, 'N': 0, 'C': 0, 'V': 0}
+            ))
+            self.logger.log(LogLevel.INFO, f"Loaded process {pid} '{name}': code at {code_base}, "
+                            f"data at {bases[pid]}-{bases[pid] + self.region_size - 1}")
+
+        if isa.scheduler is not None:
+            isa.events.unsubscribe(isa.scheduler.on_event)
+        isa.events.subscribe(self.on_event, [SimEvent.CACHE_ACCESS, SimEvent.STATS_RESET])
+        isa.scheduler = self
+        isa.control_registers['timer'] = self.quantum
+        isa._timer_countdown = self.quantum
+        self.context_switches = 0
+        self.current = None
+        self._switch_to(isa, self.processes[0])
+
+    def _save(self, isa, process: Process) -> None:
+        process.pc = isa.pc
+        process.registers = dict(isa.registers)
+        process.fp_registers = dict(isa.fp_registers)
+        process.flags = dict(isa.flags)
+        process.initialized_registers = set(isa.initialized_registers)
+        process.instructions_executed += isa.instruction_count - self._count_at_switch_in
+
+    def _switch_to(self, isa, process: Process) -> None:
+        isa.pc = process.pc
+        isa.registers = dict(process.registers)
+        isa.fp_registers = dict(process.fp_registers)
+        isa.flags = dict(process.flags)
+        isa.initialized_registers = set(process.initialized_registers)
+        isa.labels = process.labels
+        isa.base_register = process.data_base
+        isa.bounds_register = process.data_size
+        process.state = "running"
+        process.times_scheduled += 1
+        self._count_at_switch_in = isa.instruction_count
+        self.current = process
+
+    def _next_ready(self) -> Optional[Process]:
+        """Find the next ready process after the current one, wrapping around"""
+        start = self.current.pid if self.current else -1
+        count = len(self.processes)
+        for offset in range(1, count + 1):
+            candidate = self.processes[(start + offset) % count]
+            if candidate.state == "ready":
+                return candidate
+        return None
+
+    def context_switch(self, isa) -> None:
+        """Timer interrupt: preempt the running process if another is ready"""
+        next_process = self._next_ready()
+        if next_process is None or self.current is None:
+            return
+
+        self._save(isa, self.current)
+        self.current.state = "ready"
+        self.logger.log(LogLevel.INFO, f"Context switch: {self.current.name} (pc {self.current.pc}) "
+                        f"-> {next_process.name} (pc {next_process.pc})")
+        self.context_switches += 1
+        self._switch_to(isa, next_process)
+
+    def exit_current(self, isa) -> bool:
+        """The running process halted; returns True if another process takes over"""
+        if self.current is None:
+            return False
+
+        self._save(isa, self.current)
+        self.current.state = "done"
+        self.logger.log(LogLevel.INFO, f"Process {self.current.name} finished after "
+                        f"{self.current.instructions_executed} instructions")
+
+        next_process = self._next_ready()
+        if next_process is None:
+            return False
+
+        self.context_switches += 1
+        self._switch_to(isa, next_process)
+        isa._timer_countdown = self.quantum
+        return True
+
+    def on_event(self, event: Event) -> None:
+        """Charge L1 accesses to the running process; a statistics reset zeroes them"""
+        if event.kind == SimEvent.STATS_RESET:
+            for process in self.processes:
+                process.cache_hits = process.cache_misses = 0
+        elif self.current is not None and event.data['cache'] is not None:
+            if event.data['hit']:
+                self.current.cache_hits += 1
+            else:
+                self.current.cache_misses += 1
+
+    def process_for_address(self, address: int) -> Optional[Process]:
+        """Return the process whose data region contains a physical address"""
+        for process in self.processes:
+            if process.owns_address(address):
+                return process
+        return None
+
+    def get_stats(self):
+        """Return per-process scheduling statistics"""
+        return {
+            'context_switches': self.context_switches,
+            'processes': [
+                {
+                    'pid': p.pid,
+                    'name': p.name,
+                    'state': p.state,
+                    'instructions': p.instructions_executed,
+                    'times_scheduled': p.times_scheduled,
+                    'data_base': p.data_base,
+                    'cache_hits': p.cache_hits,
+                    'cache_misses': p.cache_misses
+                }
+                for p in self.processes
+            ]
+        }
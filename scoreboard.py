@@ -0,0 +1,228 @@
+import argparse
+import sys
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional
+
+from dependency import instruction_accesses
+from events import Event, SimEvent
+from isa import Instruction, InstructionType, SimpleISA, is_control_transfer, mnemonic
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+
+# Functional units of each kind and the cycles each takes to execute (after Hennessy & Patterson's CDC 6600 example)
+UNIT_COUNTS = {'Integer': 1, 'Mult': 2, 'Add': 1, 'Divide': 1}
+LATENCIES = {'Integer': 1, 'Mult': 10, 'Add': 2, 'Divide': 40}
+STATUS_STAGES = ('issue', 'read', 'complete', 'write')
+RECENT_INSTRUCTIONS = 12  # Instruction status rows shown besides the ones in flight
+
+def unit_kind(instruction: Instruction) -> str:
+    """The functional unit kind an instruction executes on; loads, stores and branches use the integer unit"""
+    if instruction.type == InstructionType.MUL_S:
+        return 'Mult'
+    if instruction.type == InstructionType.DIV_S:
+        return 'Divide'
+    if instruction.type in (InstructionType.ADD_S, InstructionType.SUB_S, InstructionType.CVT_S_W,
+                            InstructionType.CVT_W_S, InstructionType.C_EQ_S, InstructionType.C_LT_S,
+                            InstructionType.C_LE_S):
+        return 'Add'
+    return 'Integer'
+
+def unit_names() -> List[str]:
+    """'Integer', 'Mult1', 'Mult2', 'Add', 'Divide': kinds with more than one unit are numbered"""
+    return [kind if count == 1 else f"{kind}{n}" for kind, count in UNIT_COUNTS.items() for n in range(1, count + 1)]
+
+@dataclass
+class ScoreboardEntry:
+    """One dynamic instruction: its unit, registers and the cycle it reached each scoreboard stage"""
+    seq: int
+    pc: int
+    source: str
+    op: str
+    unit: str
+    dests: List[str]
+    sources: List[str]
+    producers: List[Optional[int]]  # seq of the entry each source waits for, or None if it was ready
+    issue: int
+    read: int
+    complete: int
+    write: int
+    waits: Dict[str, int] = field(default_factory=dict)  # Stall cycles by cause
+
+class Scoreboard:
+    """CDC 6600-style scoreboarding of the ISA's retired instructions
+
+    Instructions issue in order, one per cycle, to a free functional unit
+    of their kind, unless an instruction in flight will write the same
+    register (WAW). A unit reads its operands once no earlier instruction
+    is still to write them (RAW), executes for its latency and writes its
+    result once every earlier instruction that reads that register has
+    read it (WAR); the unit is free the cycle after. Issue stops after a
+    branch until it completes, as there is no speculation. Registers are
+    the integer and FP registers and the flags; memory is not tracked.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED])
+
+    def reset(self) -> None:
+        self.entries: List[ScoreboardEntry] = []
+        self._active: List[ScoreboardEntry] = []  # Entries that may still constrain later ones
+        self._unit_free = {name: 1 for name in unit_names()}  # First cycle each unit can issue again
+        self._issue_block = 1  # First cycle after a branch completes
+
+    def on_event(self, event: Event) -> None:
+        self.record(event.data['instruction'], event.pc)
+
+    def record(self, instruction: Instruction, pc: int) -> ScoreboardEntry:
+        """Schedule an executed instruction after the ones recorded so far"""
+        reads, writes = instruction_accesses(self.isa, instruction)
+        sources = sorted(r for r in reads if not r.startswith('mem['))
+        dests = sorted(w for w in writes if not w.startswith('mem['))
+        kind = unit_kind(instruction)
+        waits: Dict[str, int] = {}
+
+        def wait(cause: str, earliest: int, cycle: int) -> int:
+            if earliest > cycle:
+                waits[cause] = waits.get(cause, 0) + earliest - cycle
+            return max(earliest, cycle)
+
+        # Issue: in order, to a free unit of the kind, with no WAW hazard
+        previous = self.entries[-1].issue + 1 if self.entries else 1
+        self._active = [e for e in self._active if e.write >= previous]
+        issue = wait('branch', self._issue_block, previous)
+        units = [name for name in self._unit_free if name.rstrip('0123456789') == kind]
+        unit = min(units, key=lambda name: self._unit_free[name])
+        issue = wait('structural', self._unit_free[unit], issue)
+        issue = wait('WAW', max((e.write + 1 for e in self._active if set(e.dests) & set(dests)), default=0), issue)
+
+        # Read operands: after the last earlier writer of each has written it
+        producers = []
+        for source in sources:
+            producer = next((e for e in reversed(self._active) if source in e.dests), None)
+            producers.append(producer.seq if producer and producer.write >= issue else None)
+        ready = max((e.write + 1 for e in self._active if e.seq in producers), default=0)
+        read = wait('RAW', ready, issue + 1)
+        complete = read + LATENCIES[kind]
+
+        # Write result: once every earlier reader of the destination has read it
+        readers = max((e.read + 1 for e in self._active if set(e.sources) & set(dests)), default=0)
+        write = wait('WAR', readers, complete + 1)
+
+        entry = ScoreboardEntry(len(self.entries), pc, instruction.source.split(';')[0].strip(),
+                                mnemonic(instruction.type), unit, dests, sources, producers,
+                                issue, read, complete, write, waits)
+        self._unit_free[unit] = write + 1
+        if is_control_transfer(instruction.type):
+            self._issue_block = complete + 1
+        self.entries.append(entry)
+        self._active.append(entry)
+        return entry
+
+    @property
+    def total_cycles(self) -> int:
+        return max((e.write for e in self.entries), default=0)
+
+    def get_stats(self) -> Dict[str, object]:
+        cycles = self.total_cycles
+        stalls: Dict[str, int] = {cause: 0 for cause in ('structural', 'WAW', 'RAW', 'WAR', 'branch')}
+        for entry in self.entries:
+            for cause, count in entry.waits.items():
+                stalls[cause] += count
+        return {'instructions': len(self.entries), 'cycles': cycles,
+                'cpi': cycles / len(self.entries) if self.entries else 0.0, 'stalls': stalls}
+
+    def unit_status(self, cycle: int) -> Dict[str, Optional[ScoreboardEntry]]:
+        """The entry each functional unit holds during cycle (from issue through write), or None"""
+        status: Dict[str, Optional[ScoreboardEntry]] = {name: None for name in self._unit_free}
+        for entry in self.entries:
+            if entry.issue <= cycle <= entry.write:
+                status[entry.unit] = entry
+        return status
+
+    def register_status(self, cycle: int) -> Dict[str, str]:
+        """Register -> the unit that will write it, for registers with a write pending during cycle"""
+        status: Dict[str, str] = {}
+        for entry in self.entries:
+            if entry.issue <= cycle <= entry.write:
+                for dest in entry.dests:
+                    status[dest] = entry.unit
+        return status
+
+def format_scoreboard(scoreboard: Scoreboard, cycle: int) -> List[str]:
+    """The three scoreboard tables as they stand at the end of cycle"""
+    entries = [e for e in scoreboard.entries if e.issue <= cycle]
+    if not entries:
+        return [f"Cycle {cycle}: nothing issued yet"]
+    by_seq = {e.seq: e for e in scoreboard.entries}
+    # Everything still in flight, and the last few issued
+    shown = [e for e in entries if e.write >= cycle or e.seq > entries[-1].seq - RECENT_INSTRUCTIONS]
+
+    lines = [f"Cycle {cycle}", "", "Instruction status",
+             f"  {'instruction':<22} {'unit':<8} {'issue':>5} {'read':>5} {'exec':>5} {'write':>5}  waited"]
+    for entry in shown:
+        stages = [str(getattr(entry, stage)) if getattr(entry, stage) <= cycle else '' for stage in STATUS_STAGES]
+        waited = ", ".join(f"{cause} {count}" for cause, count in entry.waits.items())
+        lines.append(f"  {entry.source[:22]:<22} {entry.unit:<8} {stages[0]:>5} {stages[1]:>5} {stages[2]:>5} "
+                     f"{stages[3]:>5}  {waited}".rstrip())
+
+    lines += ["", "Functional unit status",
+              f"  {'unit':<8} {'busy':<4} {'op':<7} {'Fi':<10} {'Fj':<6} {'Fk':<10} {'Qj':<8} {'Qk':<8} Rj  Rk"]
+    for name, entry in scoreboard.unit_status(cycle).items():
+        if entry is None:
+            lines.append(f"  {name:<8} no")
+            continue
+        fields, queues, ready = [], [], []
+        for source, producer in zip(entry.sources, entry.producers):
+            fields.append(source)
+            waiting = producer is not None and by_seq[producer].write >= cycle
+            queues.append(by_seq[producer].unit if waiting else '')
+            # Rj/Rk: the operand is ready and not read yet; both go back to no once the unit reads them
+            ready.append('yes' if not waiting and cycle < entry.read else 'no')
+        fj, fk = (fields + ['', ''])[0], ",".join(fields[1:])
+        qj, qk = (queues + ['', ''])[0], ",".join(q for q in queues[1:] if q)
+        rj, rk = (ready + ['', ''])[0], (ready + ['', ''])[1] if len(ready) > 1 else ''
+        lines.append(f"  {name:<8} {'yes':<4} {entry.op:<7} {','.join(entry.dests)[:10]:<10} {fj:<6} {fk[:10]:<10} "
+                     f"{qj:<8} {qk:<8} {rj:<3} {rk}".rstrip())
+
+    pending = "  ".join(f"{register}: {unit}" for register, unit in sorted(scoreboard.register_status(cycle).items()))
+    lines += ["", "Register result status", f"  {pending or 'none pending'}"]
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program through a CDC 6600-style scoreboard and show its "
+                                                 "instruction, functional unit and register result status tables")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--cycle', type=int, help="Show the tables at this cycle (default: the last)")
+    parser.add_argument('--every', action='store_true', help="Show the tables at every cycle")
+    args = parser.parse_args()
+
+    from memory import MainMemory
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    scoreboard = Scoreboard(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The scoreboard, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    cycles = range(1, scoreboard.total_cycles + 1) if args.every else [args.cycle or scoreboard.total_cycles]
+    for cycle in cycles:
+        print('\n'.join(format_scoreboard(scoreboard, cycle)))
+        print()
+    stats = scoreboard.get_stats()
+    print(f"{stats['instructions']} instructions in {stats['cycles']} cycles (CPI {stats['cpi']:.2f}); stall cycles: "
+          + ", ".join(f"{cause} {count}" for cause, count in stats['stalls'].items()))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
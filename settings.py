@@ -0,0 +1,145 @@
+import json
+import os
+import re
+from dataclasses import asdict, dataclass, field, fields
+from typing import List, Optional, Tuple
+
+from cache.cache import Cache, REPLACEMENT_POLICIES, WRITE_POLICIES
+from isa import DELAY_SLOT_MODES, ISA_MODES, MEMORY_ORGANIZATIONS, WORD_SIZES
+from memory import MainMemory
+from utils.logger import Logger, LogLevel
+
+# Where the GUI keeps its settings; CPU_SIM_CONFIG points it elsewhere (e.g. for tests)
+CONFIG_PATH = os.environ.get('CPU_SIM_CONFIG', os.path.join(os.path.expanduser('~'), '.cpu_sim.json'))
+
+MEMORY_SIZE = 1024
+MEMORY_FORMATS = ('decimal', 'hex', 'binary')
+THEMES = ('dark', 'light')
+PALETTES = ('standard', 'colorblind')  # colorblind uses the Okabe-Ito colors
+HIGHLIGHTS = ('hit_color', 'miss_color', 'pc_color')
+ENDIANNESSES = ('little', 'big')
+STEP_SPEED_RANGE = (100, 2000)  # Milliseconds between steps when running, as on the speed slider
+
+@dataclass
+class CacheSettings:
+    """Geometry and policies of one cache level (lines are always one word)"""
+    size: int
+    associativity: int
+    access_time: int
+    write_policy: str
+    replacement_policy: str = 'lru'
+
+    @property
+    def sets(self) -> int:
+        return self.size // self.associativity
+
+@dataclass
+class Settings:
+    """Everything the Settings dialog changes, with the simulator's built-in defaults"""
+    l1: CacheSettings = field(default_factory=lambda: CacheSettings(32, 2, 10, 'write-through'))
+    l2: CacheSettings = field(default_factory=lambda: CacheSettings(64, 4, 30, 'write-back'))
+    step_speed: int = 1000
+    memory_format: str = 'decimal'
+    theme: str = 'dark'
+    palette: str = 'standard'
+    # Highlight colors as #rrggbb; empty uses the palette's own
+    hit_color: str = ''
+    miss_color: str = ''
+    pc_color: str = ''
+    isa_mode: str = 'standard'  # Compare semantics for programs without a .mode directive
+    memory_organization: str = 'von_neumann'
+    endianness: str = 'little'
+    hardwired_zero: bool = True
+    word_size: int = 32  # Datapath width in bits for programs without a .word_size directive
+    delay_slots: str = 'off'  # Branch delay slot mode for programs without a .delay_slots directive
+    # Window size and dock layout when the GUI was last closed (base64 of Qt's saveGeometry/saveState)
+    window_geometry: str = ''
+    window_state: str = ''
+
+    def problems(self) -> List[str]:
+        """Why these settings cannot be used; empty when they are valid"""
+        problems = []
+        for name, cache in (('L1', self.l1), ('L2', self.l2)):
+            if cache.size <= 0 or cache.associativity <= 0 or cache.size % cache.associativity:
+                problems.append(f"{name}: size {cache.size} is not a whole number of {cache.associativity}-way sets")
+            elif cache.sets & (cache.sets - 1):
+                problems.append(f"{name}: {cache.sets} sets is not a power of two")
+            if cache.access_time < 0:
+                problems.append(f"{name}: negative access time")
+            if cache.write_policy not in WRITE_POLICIES:
+                problems.append(f"{name}: unknown write policy '{cache.write_policy}'")
+            if cache.replacement_policy not in REPLACEMENT_POLICIES:
+                problems.append(f"{name}: unknown replacement policy '{cache.replacement_policy}'")
+        if not STEP_SPEED_RANGE[0] <= self.step_speed <= STEP_SPEED_RANGE[1]:
+            problems.append(f"step speed must be {STEP_SPEED_RANGE[0]}-{STEP_SPEED_RANGE[1]} ms")
+        for name, value, allowed in (('memory format', self.memory_format, MEMORY_FORMATS),
+                                     ('theme', self.theme, THEMES),
+                                     ('palette', self.palette, PALETTES),
+                                     ('ISA mode', self.isa_mode, ISA_MODES),
+                                     ('memory organization', self.memory_organization, MEMORY_ORGANIZATIONS),
+                                     ('endianness', self.endianness, ENDIANNESSES),
+                                     ('word size', self.word_size, WORD_SIZES),
+                                     ('delay slot mode', self.delay_slots, DELAY_SLOT_MODES)):
+            if value not in allowed:
+                problems.append(f"unknown {name} '{value}' (use {', '.join(map(str, allowed))})")
+        for name in HIGHLIGHTS:
+            color = getattr(self, name)
+            if color and not re.fullmatch(r'#[0-9a-fA-F]{6}', color):
+                problems.append(f"{name.replace('_', ' ')} '{color}' is not a #rrggbb color")
+        return problems
+
+    def to_dict(self) -> dict:
+        return asdict(self)
+
+    @classmethod
+    def from_dict(cls, data: dict) -> 'Settings':
+        """Settings from a config file's contents; missing keys keep their defaults and unknown ones are ignored"""
+        settings = cls()
+        for f in fields(cls):
+            if f.name not in data:
+                continue
+            value = data[f.name]
+            if isinstance(getattr(settings, f.name), CacheSettings):
+                cache = getattr(settings, f.name)
+                value = CacheSettings(**{c.name: value.get(c.name, getattr(cache, c.name)) for c in fields(CacheSettings)})
+            setattr(settings, f.name, value)
+        return settings
+
+def load_settings(path: Optional[str] = None) -> Settings:
+    """Settings from the config file, or the defaults if it is missing or unusable"""
+    path = path or CONFIG_PATH
+    try:
+        with open(path) as f:
+            settings = Settings.from_dict(json.load(f))
+        problems = settings.problems()
+    except FileNotFoundError:
+        return Settings()
+    except (OSError, ValueError, TypeError, AttributeError) as e:
+        Logger().log(LogLevel.WARNING, f"Ignoring settings in {path}: {e}")
+        return Settings()
+    if problems:
+        Logger().log(LogLevel.WARNING, f"Ignoring settings in {path}: {'; '.join(problems)}")
+        return Settings()
+    return settings
+
+def save_settings(settings: Settings, path: Optional[str] = None) -> None:
+    with open(path or CONFIG_PATH, 'w') as f:
+        json.dump(settings.to_dict(), f, indent=2)
+        f.write('\n')
+
+def build_hierarchy(settings: Settings, logger=None) -> Tuple[MainMemory, Cache, Cache]:
+    """Main memory, L2 and L1 configured by settings"""
+    memory = MainMemory("MainMemory", MEMORY_SIZE)
+    l2 = Cache("L2Cache", settings.l2.size, 1, settings.l2.associativity, settings.l2.access_time,
+               settings.l2.write_policy, memory, logger, settings.l2.replacement_policy)
+    l1 = Cache("L1Cache", settings.l1.size, 1, settings.l1.associativity, settings.l1.access_time,
+               settings.l1.write_policy, l2, logger, settings.l1.replacement_policy)
+    return memory, l1, l2
+
+def format_word(value: int, memory_format: str) -> str:
+    """A memory word as the memory display format shows it"""
+    if memory_format == 'hex':
+        return f"0x{value & 0xFFFFFFFF:08x}"
+    if memory_format == 'binary':
+        return f"{value & 0xFFFFFFFF:032b}"
+    return str(value)
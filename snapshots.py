@@ -0,0 +1,73 @@
+from dataclasses import dataclass, field
+from time import time
+from typing import Dict, List, Optional, Tuple
+
+CURRENT = "current"  # Name that stands for live memory in diffs
+
+@dataclass
+class MemorySnapshot:
+    """A copy of every memory word, taken after a given number of instructions"""
+    name: str
+    words: List[int]
+    instruction_count: int
+    pc: int
+    timestamp: float = field(default_factory=time)
+
+def diff_words(old: List[int], new: List[int]) -> List[Tuple[int, int, int]]:
+    """(address, old value, new value) for every word that differs, in address order"""
+    changes = [(addr, a, b) for addr, (a, b) in enumerate(zip(old, new)) if a != b]
+    # Memories of different sizes: words past the shorter one count as 0 there
+    longer, old_is_longer = (old, True) if len(old) > len(new) else (new, False)
+    for addr in range(min(len(old), len(new)), len(longer)):
+        if longer[addr] != 0:
+            changes.append((addr, longer[addr], 0) if old_is_longer else (addr, 0, longer[addr]))
+    return changes
+
+class SnapshotStore:
+    """Named memory snapshots of one simulator, diffable against each other or live memory
+
+    Stores bypass the caches into main memory as well (see
+    SimpleISA._write_physical), so main memory holds every word the
+    program has written and reading it has no effect on cache state.
+    """
+
+    def __init__(self, isa=None):
+        self.isa = isa
+        self.snapshots: Dict[str, MemorySnapshot] = {}
+
+    def take(self, name: Optional[str] = None) -> MemorySnapshot:
+        """Capture memory under name (default s1, s2, ...); an existing snapshot of that name is replaced"""
+        if name is None:
+            name = f"s{len(self.snapshots) + 1}"
+            while name in self.snapshots:
+                name += "'"
+        if name == CURRENT:
+            raise ValueError(f"'{CURRENT}' is reserved for live memory")
+        snapshot = MemorySnapshot(name, list(self.isa.memory.data), self.isa.instruction_count, self.isa.pc)
+        self.snapshots[name] = snapshot
+        return snapshot
+
+    def words(self, name: str) -> List[int]:
+        if name == CURRENT:
+            return list(self.isa.memory.data)
+        if name not in self.snapshots:
+            raise ValueError(f"No snapshot named '{name}' (have: {', '.join(self.snapshots) or 'none'})")
+        return self.snapshots[name].words
+
+    def diff(self, old: str, new: str = CURRENT) -> List[Tuple[int, int, int]]:
+        return diff_words(self.words(old), self.words(new))
+
+    def describe(self, name: str) -> str:
+        if name == CURRENT:
+            return f"current (after {self.isa.instruction_count} instructions, pc {self.isa.pc})"
+        snapshot = self.snapshots[name]
+        return f"{name} (after {snapshot.instruction_count} instructions, pc {snapshot.pc})"
+
+def format_diff(changes: List[Tuple[int, int, int]]) -> List[str]:
+    """Table rows for a diff: address, old and new value, and the signed change"""
+    if not changes:
+        return ["No differences"]
+    rows = [f"{'address':>7} {'old':>11} {'new':>11} {'change':>11}"]
+    rows += [f"{addr:7d} {old:11d} {new:11d} {new - old:+11d}" for addr, old, new in changes]
+    rows.append(f"{len(changes)} word{'s' if len(changes) != 1 else ''} changed")
+    return rows
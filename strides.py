@@ -0,0 +1,158 @@
+import argparse
+import sys
+from collections import Counter
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Set
+
+from events import Event, SimEvent
+from isa import Instruction, InstructionType, SimpleISA
+from utils.files import read_lines
+
+MIN_RUN = 3  # Accesses to consecutive words that count as a sequential run
+
+@dataclass
+class LoadPattern:
+    """The addresses one load instruction read, boiled down to its strides, sequential runs and pointer chasing"""
+    pc: int
+    line_number: int
+    source: str
+    accesses: int = 0
+    strides: Counter = field(default_factory=Counter)  # Address difference between successive accesses
+    chased: int = 0  # Accesses whose address register was loaded from memory
+    runs: int = 0  # Sequential runs of at least MIN_RUN accesses
+    longest_run: int = 0
+    last_address: Optional[int] = None
+    run: int = 0  # Accesses in the sequential run in progress
+
+    def add(self, address: int, chased: bool) -> None:
+        if self.last_address is not None:
+            stride = address - self.last_address
+            self.strides[stride] += 1
+            self.run = self.run + 1 if abs(stride) == 1 else 1
+        else:
+            self.run = 1
+        if self.run == MIN_RUN:
+            self.runs += 1
+        self.longest_run = max(self.longest_run, self.run)
+        self.accesses += 1
+        self.chased += chased
+        self.last_address = address
+
+    @property
+    def top_stride(self) -> Optional[int]:
+        """The most common stride (the smallest on ties), or None before a second access"""
+        if not self.strides:
+            return None
+        return min(self.strides, key=lambda stride: (-self.strides[stride], abs(stride), stride))
+
+    @property
+    def top_share(self) -> float:
+        return self.strides[self.top_stride] / (self.accesses - 1) if self.strides else 0.0
+
+    @property
+    def pattern(self) -> str:
+        """'pointer chasing', 'sequential', 'stride N', 'same word', 'irregular' or 'single'"""
+        if self.accesses < 2:
+            return 'single'
+        if self.chased * 2 >= self.accesses:
+            return 'pointer chasing'
+        if self.top_share < 0.5:
+            return 'irregular'
+        if abs(self.top_stride) == 1:
+            return 'sequential' if self.top_stride > 0 else 'sequential (descending)'
+        return 'same word' if self.top_stride == 0 else f"stride {self.top_stride}"
+
+class StrideAnalyzer:
+    """Per load instruction, how the addresses it reads follow one another, from the ISA's retired instructions
+
+    A load is pointer chasing when its address register holds a value
+    loaded from memory (directly or through a MOV between registers), as
+    in walking a linked list; otherwise its most common stride decides
+    the pattern when it covers at least half of its accesses. Only reads
+    are counted; like the core's own counters, everything starts over
+    when the statistics are reset.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED, SimEvent.STATS_RESET])
+
+    def reset(self) -> None:
+        self.loads: Dict[int, LoadPattern] = {}
+        self._loaded: Set[str] = set()  # Registers whose value came from memory
+
+    def on_event(self, event: Event) -> None:
+        if event.kind == SimEvent.STATS_RESET:
+            self.reset()
+            return
+        self.record(event.data['instruction'], event.pc, [address for access, address in self.isa.step_accesses
+                                                          if access == 'r'])
+
+    def record(self, instruction: Instruction, pc: int, reads: List[int]) -> None:
+        """Count one executed instruction's data reads and track which registers now hold loaded values"""
+        address_registers = {operand[1:-1] for operand in instruction.operands
+                             if operand.startswith('[') and operand[1:-1] in self.isa.registers}
+        chased = bool(address_registers & self._loaded)
+        if reads:
+            pattern = self.loads.get(pc)
+            if pattern is None:
+                pattern = self.loads[pc] = LoadPattern(pc, instruction.line_number,
+                                                       instruction.source.split(';')[0].strip())
+            for address in reads:
+                pattern.add(address, chased)
+
+        for register in self.isa.register_writes(instruction):
+            copied = (instruction.type == InstructionType.MOV and len(instruction.operands) == 2
+                      and instruction.operands[1] in self._loaded)
+            if reads or copied:
+                self._loaded.add(register)
+            else:
+                self._loaded.discard(register)
+
+    def patterns(self) -> List[LoadPattern]:
+        """Every load instruction seen, in program order"""
+        return [self.loads[pc] for pc in sorted(self.loads)]
+
+def format_patterns(patterns: List[LoadPattern]) -> List[str]:
+    """A table with one row per load: its source line, access count, pattern, top stride and runs"""
+    if not patterns:
+        return ["No loads yet"]
+    lines = [f"{'pc':>4} {'line':>4}  {'instruction':<22} {'loads':>6}  {'pattern':<24} {'top stride':<14} "
+             f"{'runs':>4} {'longest':>7} {'chased':>6}"]
+    for p in patterns:
+        stride = f"{p.top_stride:+d} ({p.top_share:.0%})" if p.strides else ''
+        lines.append(f"{p.pc:>4} {p.line_number + 1:>4}  {p.source[:22]:<22} {p.accesses:>6}  {p.pattern:<24} "
+                     f"{stride:<14} {p.runs:>4} {p.longest_run:>7} {p.chased:>6}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program and classify each load instruction's addresses "
+                                                 "as sequential, strided, pointer chasing or irregular")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    args = parser.parse_args()
+
+    from locality import build_hierarchy
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    analyzer = StrideAnalyzer(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The table, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    print('\n'.join(format_patterns(analyzer.patterns())))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,169 @@
+import argparse
+import sys
+from dataclasses import dataclass, replace
+from typing import Dict, List, Optional, Tuple
+
+from heatmap import Access
+from utils.files import read_lines
+from whatif import MAX_TRACE, CacheConfig, record_trace, replay
+from workingset import DEFAULT_WINDOW, working_set
+
+SWEEP_WAYS = (1, 2, 4, 8)
+SWEEP_BLOCK_SIZES = (1, 2, 4, 8)  # Words
+SWEEP_CAPACITIES = (8, 16, 32, 64, 128, 256, 512)  # Words
+KNEE_FRACTION = 0.1  # The curve has flattened once larger caches remove less than this share of the total drop
+
+@dataclass
+class SweepPoint:
+    """One L1 shape of a sweep and the recorded trace's replay on it"""
+    value: int            # The swept parameter's setting
+    config: CacheConfig
+    result: Dict[str, object]
+
+    @property
+    def misses(self) -> int:
+        return self.result['l1']['misses']
+
+    @property
+    def traffic(self) -> int:
+        return self.result['traffic']
+
+    @property
+    def miss_rate(self) -> float:
+        accesses = self.result['l1']['hits'] + self.misses
+        return self.misses / accesses * 100 if accesses else 0.0
+
+def sweep_associativity(accesses: List[Tuple[str, int]], l1: CacheConfig, l2: CacheConfig,
+                        ways=SWEEP_WAYS) -> List[SweepPoint]:
+    """Replay a trace on L1s with each associativity and the same capacity, trading sets for ways
+
+    A shape with fewer than one set for the capacity is left out.
+    """
+    points = []
+    for count in ways:
+        sets = l1.size // (count * l1.line_size)
+        if sets >= 1:
+            config = replace(l1, sets=sets, ways=count)
+            points.append(SweepPoint(count, config, replay(accesses, config, l2)))
+    return points
+
+def sweep_block_size(accesses: List[Tuple[str, int]], l1: CacheConfig, l2: CacheConfig,
+                     sizes=SWEEP_BLOCK_SIZES) -> List[SweepPoint]:
+    """Replay a trace on L1s with each block size and the same capacity and ways, trading sets for longer blocks
+
+    Longer blocks bring in more neighbours per miss, so spatial locality
+    cuts the misses, but each miss moves more words; with fewer sets,
+    blocks also start pushing each other out. A shape with fewer than one
+    set is left out.
+    """
+    points = []
+    for size in sizes:
+        sets = l1.size // (l1.ways * size)
+        if sets >= 1:
+            config = replace(l1, sets=sets, line_size=size)
+            points.append(SweepPoint(size, config, replay(accesses, config, l2)))
+    return points
+
+def sweep_capacity(accesses: List[Tuple[str, int]], l1: CacheConfig, l2: CacheConfig,
+                   sizes=SWEEP_CAPACITIES) -> List[SweepPoint]:
+    """Replay a trace on L1s of each capacity in words, with the L1's ways and block size and as many sets as fit
+
+    A capacity too small for one set is left out.
+    """
+    points = []
+    for size in sizes:
+        sets = size // (l1.ways * l1.line_size)
+        if sets >= 1:
+            config = replace(l1, sets=sets)
+            points.append(SweepPoint(size, config, replay(accesses, config, l2)))
+    return points
+
+def knee(points: List[SweepPoint], fraction: float = KNEE_FRACTION) -> Optional[SweepPoint]:
+    """The first point past which the larger settings lower the miss rate by less than fraction of the whole
+    sweep's drop, or None if the miss rate never drops"""
+    if not points:
+        return None
+    drop = points[0].miss_rate - points[-1].miss_rate
+    if drop <= 0:
+        return None
+    return next(point for point in points if point.miss_rate - points[-1].miss_rate <= drop * fraction)
+
+def peak_working_set(accesses: List[Tuple[str, int]], window: int = DEFAULT_WINDOW) -> int:
+    """The working-set analyzer's peak for a trace, counting time in accesses: the most distinct words in one
+    window of that many consecutive loads and stores"""
+    samples = [Access(i, address, access, 0) for i, (access, address) in enumerate(accesses)]
+    return max((point.words for point in working_set(samples, window)), default=0)
+
+def describe_knee(points: List[SweepPoint], working_set_words: int, window: int = DEFAULT_WINDOW) -> str:
+    """Where a capacity sweep's miss-rate curve flattens, against the program's peak working set"""
+    point = knee(points)
+    words = f"the peak working set of {working_set_words} words in {window} accesses"
+    if point is None:
+        return f"The miss rate does not drop with capacity; {words}"
+    if point is points[-1]:
+        return f"The miss rate is still falling at {point.value} words; {words}"
+    return (f"The miss rate flattens at {point.value} words, "
+            f"{point.value / max(working_set_words, 1):.1f}x {words}")
+
+# Swept parameter -> (sweep function, default values, column heading)
+SWEEPS = {'associativity': (sweep_associativity, SWEEP_WAYS, "ways"),
+          'block-size': (sweep_block_size, SWEEP_BLOCK_SIZES, "block size"),
+          'capacity': (sweep_capacity, SWEEP_CAPACITIES, "capacity")}
+
+def returns(points: List[SweepPoint]) -> str:
+    """How many misses each step of the sweep removed and what it did to the traffic, to show whether the steps
+    still pay off"""
+    if len(points) < 2:
+        return ""
+    steps = list(zip(points, points[1:]))
+    return ("Each step removed " + ", ".join(str(previous.misses - point.misses) for previous, point in steps)
+            + " misses and changed the traffic by "
+            + ", ".join(f"{point.traffic - previous.traffic:+d}" for previous, point in steps) + " words")
+
+def format_sweep(points: List[SweepPoint]) -> List[str]:
+    """One row per setting: the L1's shape, its misses and miss rate, the change from the row before, the words
+    moved to and from the level below, and AMAT"""
+    lines = [f"{'words':>5} {'sets':>5} {'ways':>5} {'block':>5} {'misses':>7} {'miss rate':>9} {'change':>7} "
+             f"{'traffic':>8} {'AMAT':>8}"]
+    for i, point in enumerate(points):
+        change = f"{point.miss_rate - points[i - 1].miss_rate:+.1f}" if i else ""
+        config = point.config
+        lines.append(f"{config.size:>5} {config.sets:>5} {config.ways:>5} {config.line_size:>5} {point.misses:>7}"
+                     f" {point.miss_rate:>8.1f}% {change:>7} {point.traffic:>8} {point.result['amat']:>8.2f}")
+    summary = returns(points)
+    return lines + ([summary] if summary else [])
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program once, then replay its L1 loads and stores on L1s "
+                                                 "that differ in one parameter and compare their miss rates")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('parameter', nargs='?', choices=tuple(SWEEPS), default='associativity',
+                        help="What to vary: the ways or block size at the L1's capacity, or the capacity at its "
+                             "ways and block size (default associativity)")
+    parser.add_argument('--values', type=int, nargs='+',
+                        help="Settings to try (default: " + "; ".join(
+                            f"{name} {' '.join(map(str, values))}" for name, (_, values, _) in SWEEPS.items()) + ")")
+    parser.add_argument('--window', type=int, default=DEFAULT_WINDOW,
+                        help=f"Accesses per working-set window for a capacity sweep (default {DEFAULT_WINDOW})")
+    args = parser.parse_args()
+    sweep, values, _ = SWEEPS[args.parameter]
+    if args.values and any(value < 1 for value in args.values) or args.window < 1:
+        parser.error("every setting and the window must be at least 1")
+
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    trace, l1, l2 = record_trace(program)
+    recorded = CacheConfig.of(l1)
+    print(f"{len(trace.accesses)} L1 accesses recorded" +
+          (f" (the first {MAX_TRACE}; {trace.dropped} more are not replayed)" if trace.dropped else ""))
+    points = sweep(trace.accesses, recorded, CacheConfig.of(l2), args.values or values)
+    print('\n'.join(format_sweep(points)))
+    if args.parameter == 'capacity':
+        print(describe_knee(points, peak_working_set(trace.accesses, args.window), args.window))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
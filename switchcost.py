@@ -0,0 +1,113 @@
+import argparse
+import sys
+from typing import Dict, List
+
+from amat import MEMORY_ACCESS_TIME, amat_levels
+from events import Event, SimEvent
+from utils.files import read_lines
+
+class ContextSwitchFlusher:
+    """Empties the caches every `interval` retired instructions, as an operating system's context switch would
+
+    By default each switch flushes every level, writing dirty lines back,
+    as switching to a process with its own address space on a virtually
+    tagged cache must; with invalidate the lines are dropped without being
+    written back, losing their stores, which changes the program's results
+    but not which of its accesses miss. The simulator has no TLB - base
+    and bounds relocation needs no translation cache - so the caches are
+    all there is to empty.
+    """
+
+    def __init__(self, isa, interval: int, invalidate: bool = False):
+        self.isa = isa
+        self.interval = interval
+        self.invalidate = invalidate
+        self.switches = 0
+        self._retired = 0
+        isa.events.subscribe(self.on_event, [SimEvent.INSTRUCTION_RETIRED])
+
+    def on_event(self, event: Event) -> None:
+        self._retired += 1
+        if self.interval > 0 and self._retired % self.interval == 0:
+            self.switch()
+
+    def switch(self) -> None:
+        if self.isa.write_buffer:
+            self.isa.write_buffer.flush()
+        if self.invalidate:
+            self.isa.cache.invalidate_all()
+        else:
+            self.isa.cache.flush_all()
+        self.switches += 1
+
+def memory_cycles(cache, memory_time: int = MEMORY_ACCESS_TIME) -> int:
+    """Cycles spent in the memory hierarchy: each level's accesses at its hit time, and the last level's misses
+    at main memory's, counting one cycle per ns of latency"""
+    levels = amat_levels(cache)
+    cycles = sum(level.accesses * level.hit_time for level in levels)
+    return cycles + (levels[-1].misses * memory_time if levels else 0)
+
+def run(lines: List[str], interval: int = 0, invalidate: bool = False) -> Dict:
+    """Run a program on the default hierarchy with a context switch every interval instructions (0 for none)"""
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from pipeline import PipelineModel
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    isa.pipeline = PipelineModel(isa)
+    flusher = ContextSwitchFlusher(isa, interval, invalidate)
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The comparison, not the trace, is the output
+    try:
+        isa.load_program(lines)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+    return {
+        'switches': flusher.switches,
+        'misses': {level.name: level.misses for level in amat_levels(l1)},
+        'memory_cycles': memory_cycles(l1),
+        'pipeline_cycles': isa.pipeline.get_stats()['cycles']
+    }
+
+def format_cost(baseline: Dict, switched: Dict, interval: int) -> List[str]:
+    """The switched run's misses and cycles beside the baseline's, with the extra cost per switch"""
+    per_switch = max(switched['switches'], 1)
+    rows = [(f"{name} misses", baseline['misses'][name], switched['misses'][name]) for name in baseline['misses']]
+    rows += [("Memory cycles", baseline['memory_cycles'], switched['memory_cycles']),
+             ("Total cycles", baseline['pipeline_cycles'] + baseline['memory_cycles'],
+              switched['pipeline_cycles'] + switched['memory_cycles'])]
+    lines = [f"Context switch every {interval} instructions: {switched['switches']} switches",
+             f"{'':<16} {'no switches':>11} {'switching':>10} {'extra':>8} {'per switch':>10}"]
+    for label, before, after in rows:
+        extra = after - before
+        lines.append(f"{label:<16} {before:>11} {after:>10} {extra:>+8} {extra / per_switch:>10.1f}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program with the caches emptied every few instructions, "
+                                                 "as a context switch would, and count the extra misses and "
+                                                 "cycles against an undisturbed run")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--interval', type=int, nargs='+', default=[100],
+                        help="Instructions between context switches; several values compare each (default 100)")
+    parser.add_argument('--invalidate', action='store_true',
+                        help="Drop the lines without writing dirty ones back instead of flushing them")
+    args = parser.parse_args()
+    if any(interval < 1 for interval in args.interval):
+        parser.error("the interval must be at least one instruction")
+
+    try:
+        lines = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    baseline = run(lines)
+    for interval in args.interval:
+        print('\n'.join(format_cost(baseline, run(lines, interval, args.invalidate), interval)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,25 @@
+// Recursion, division and remainder
+int results[4];
+
+int factorial(int n) {
+    if (n <= 1)
+        return 1;
+    return n * factorial(n - 1);
+}
+
+int gcd(int a, int b) {
+    while (b != 0) {
+        int t = a % b;
+        a = b;
+        b = t;
+    }
+    return a;
+}
+
+int main() {
+    results[0] = factorial(6);
+    results[1] = gcd(84, 36);
+    results[2] = -17 / 5;
+    results[3] = -17 % 5;
+    return results[0] / results[1] + results[2] * results[3];
+}
@@ -0,0 +1,36 @@
+// Sieve of Eratosthenes, powers of two and calls through an array parameter
+int sieve[64];
+int powers[8];
+int count;
+
+int is_odd(int n) {
+    return n % 2 == 1;
+}
+
+void mark(int a[], int step, int limit) {
+    int i = step * 2;
+    while (i < limit) {
+        a[i] = 1;
+        i = i + step;
+    }
+}
+
+int main() {
+    for (int n = 2; n < 64; n++) {
+        if (!sieve[n]) {
+            count++;
+            mark(sieve, n, 64);
+        }
+    }
+    for (int k = 0; k < 8; k++)
+        powers[k] = (1 << k) * 4 + k * 8 - (k & 1) / 2;
+    int odd = 0;
+    for (int n = 0; n < 10 && odd < 3 || n == 0; n++) {
+        if (is_odd(n)) {
+            odd += 1;
+            continue;
+        }
+        if (n > 8) break;
+    }
+    return count * 100 + odd;
+}
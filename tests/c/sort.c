@@ -0,0 +1,27 @@
+// Sort an array with bubble sort and return a checksum of the result
+int data[8] = {29, -4, 17, 3, 42, 8, -15, 11};
+int swaps;
+
+void sort(int a[], int n) {
+    for (int i = 0; i < n - 1; i++) {
+        for (int j = 0; j < n - 1 - i; j++) {
+            if (a[j] > a[j + 1]) {
+                int t = a[j];
+                a[j] = a[j + 1];
+                a[j + 1] = t;
+                swaps += 1;
+            }
+        }
+    }
+}
+
+int main() {
+    sort(data, 8);
+    int checksum = 0;
+    int i = 0;
+    while (i < 8) {
+        checksum = checksum * 3 + data[i];
+        i++;
+    }
+    return checksum;
+}
@@ -0,0 +1,30 @@
+;===============================================
+; Test Name: Linker Test (main module)
+; Description: Link with mathlib.asm; tests the linker including:
+;   - .extern references to subroutines another file exports
+;   - A local label (loop) that mathlib.asm also defines, renamed when linked
+;
+;   python linker.py tests/link/main.asm tests/link/mathlib.asm --run
+;
+; Expected Results:
+;   - Memory[100] = 49 (square of 7)
+;   - Memory[101] = 10 (sum_to 3 + sum_to 2 + sum_to 1 = 6 + 3 + 1)
+;===============================================
+
+.extern square
+.extern sum_to
+
+MOV eax #7
+JAL square        ; eax = 49
+MOV [100] eax
+MOV edi #0
+MOV esi #3        ; Call sum_to for 3, 2 and 1
+loop:
+MOV ecx esi
+JAL sum_to        ; eax = esi + ... + 1
+ADD edi eax
+DEC esi
+MOV eax esi
+JNZ loop
+MOV [101] edi
+HALT
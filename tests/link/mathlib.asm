@@ -0,0 +1,30 @@
+;===============================================
+; Math library module for the linker test (see main.asm)
+; Subroutines are called with JAL and return with JR ebp.
+;===============================================
+
+.global square, sum_to
+
+; square: eax = eax * eax by repeated addition (uses ebx, ecx, edx)
+square:
+MOV ecx eax
+MOV edx eax
+MOV ebx #0
+loop:
+ADD ebx edx
+DEC ecx
+MOV eax ecx
+JNZ loop
+MOV eax ebx
+JR ebp
+
+; sum_to: eax = ecx + (ecx - 1) + ... + 1 (uses ebx, ecx)
+sum_to:
+MOV ebx #0
+sum_loop:
+ADD ebx ecx
+DEC ecx
+MOV eax ecx
+JNZ sum_loop
+MOV eax ebx
+JR ebp
@@ -0,0 +1,137 @@
+import argparse
+import io
+import sys
+from contextlib import redirect_stdout
+from typing import Callable, Dict, List, Optional
+
+from events import Event, EventStats, SimEvent
+from isa import SimpleISA, format_instruction, symbol_for
+from locality import build_hierarchy
+from utils.files import read_lines
+from utils.logger import Logger, LogLevel
+from utils.style import add_color_arguments, configure_color, style
+
+def _pc_name(pc: int, labels: Optional[Dict[str, int]]) -> str:
+    symbol = symbol_for(pc, labels) if labels and pc is not None else ''
+    return f"{pc} <{symbol}>" if symbol else str(pc)
+
+def _cache_result(data: Dict) -> str:
+    """Which cache level an access went to and whether it hit, colored by the outcome"""
+    if not data['cache']:
+        return "uncached"
+    return f"{data['cache']} " + (style("hit", 'hit') if data['hit'] else style("miss", 'miss'))
+
+def format_event(event: Event, labels: Optional[Dict[str, int]] = None) -> str:
+    """One line per event for terminal traces; with the program's labels, code addresses are shown symbolically"""
+    d = event.data
+    if event.kind == SimEvent.INSTRUCTION_RETIRED:
+        detail = f"{d['instruction'].source} -> pc {_pc_name(d['next_pc'], labels)}"
+    elif event.kind == SimEvent.CACHE_ACCESS:
+        where = _cache_result(d)
+        detail = f"{'read' if d['access'] == 'r' else 'write'} [{d['address']}] {where}"
+    elif event.kind == SimEvent.MEMORY_WRITE:
+        detail = f"[{d['address']}] = {d['value']}"
+    elif event.kind == SimEvent.BRANCH_RESOLVED:
+        outcome = style(f"taken to {_pc_name(d['target'], labels)}", 'taken') if d['taken'] else "not taken"
+        detail = f"{d['instruction'].source}: {outcome}"
+    elif event.kind == SimEvent.STATS_RESET:
+        detail = "statistics counters zeroed"
+    else:
+        detail = f"{d['cause']}{': ' + d['message'] if d['message'] else ''}{' (fatal)' if d['fatal'] else ''}"
+    return f"{event.step:6d} pc {event.pc:4d}  {event.kind.name:<19} {detail}"
+
+class CompactTrace:
+    """One line per retired instruction: the cycle, pc and disassembly, the registers and memory words it
+    changed, and what its loads and stores did in the L1
+
+    Registers are compared with their values when the previous line was
+    written, or when the trace started, so start it after the program is
+    loaded.
+    """
+
+    def __init__(self, isa: SimpleISA, write: Callable[[str], None] = print):
+        self.isa = isa
+        self.write = write
+        self._registers = dict(isa.registers)
+        self._writes: List[str] = []
+        self._accesses: List[str] = []
+        self._taken = False
+        isa.events.subscribe(self.on_event, [SimEvent.CACHE_ACCESS, SimEvent.MEMORY_WRITE,
+                                             SimEvent.BRANCH_RESOLVED, SimEvent.INSTRUCTION_RETIRED])
+
+    def on_event(self, event: Event) -> None:
+        d = event.data
+        if event.kind == SimEvent.CACHE_ACCESS:
+            result = _cache_result(d).replace('Cache', '')
+            self._accesses.append(f"{'read' if d['access'] == 'r' else 'write'} [{d['address']}] {result}")
+        elif event.kind == SimEvent.MEMORY_WRITE:
+            self._writes.append(f"[{d['address']}]={d['value']}")
+        elif event.kind == SimEvent.BRANCH_RESOLVED:
+            self._taken = d['taken']
+        else:
+            self.write(self.format_step(event))
+            self._writes, self._accesses, self._taken = [], [], False
+
+    def format_step(self, event: Event) -> str:
+        """The step's line; taken branches, changed registers and hits and misses are colored, after padding"""
+        registers = self.isa.registers
+        changed = [f"{reg}={value}" for reg, value in registers.items() if self._registers.get(reg) != value]
+        self._registers = dict(registers)
+        instruction = format_instruction(event.data['instruction'])
+        changes = ' '.join(changed + self._writes)
+        line = (f"{self.isa.cycles:7d} pc {_pc_name(event.pc, self.isa.labels):<14} "
+                + (style(instruction, 'taken') if self._taken else instruction) + ' ' * max(25 - len(instruction), 1)
+                + ' '.join([style(change, 'register') for change in changed] + self._writes)
+                + ' ' * max(20 - len(changes), 0))
+        return (line + "  " + ", ".join(self._accesses)).rstrip()
+
+EVENT_NAMES = {'retired': SimEvent.INSTRUCTION_RETIRED, 'cache': SimEvent.CACHE_ACCESS,
+               'write': SimEvent.MEMORY_WRITE, 'branch': SimEvent.BRANCH_RESOLVED,
+               'exception': SimEvent.EXCEPTION, 'stats': SimEvent.STATS_RESET}
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program and print the events the core publishes")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--events', default=','.join(EVENT_NAMES),
+                        help=f"Comma-separated kinds to print (default: all of {', '.join(EVENT_NAMES)})")
+    parser.add_argument('--summary', action='store_true', help="Only print the statistics, not the events")
+    add_color_arguments(parser)
+    args = parser.parse_args()
+    configure_color(args)
+
+    try:
+        kinds = [EVENT_NAMES[name.strip()] for name in args.events.split(',') if name.strip()]
+    except KeyError as e:
+        parser.error(f"unknown event kind {e} (choose from {', '.join(EVENT_NAMES)})")
+
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    stats = EventStats(isa.events)
+    lines = []
+    if not args.summary:
+        isa.events.subscribe(lambda event: lines.append(format_event(event, isa.labels)), kinds)
+
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR
+    try:
+        with redirect_stdout(io.StringIO()):
+            isa.load_program(program)
+            isa.run()
+    finally:
+        logger.log_level = level
+
+    for line in lines:
+        print(line)
+    s = stats.get_stats()
+    print(f"Retired: {s['retired']}, branches: {s['branches']} ({s['taken_rate']:.0%} taken), "
+          f"L1 accesses: {s['cache_accesses']} ({s['cache_hit_rate']:.0%} hits, {s['uncached_accesses']} uncached), "
+          f"memory writes: {s['memory_writes']} to {s['words_written']} words, exceptions: {s['exceptions']}")
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,50 @@
+from dataclasses import dataclass
+from typing import Optional
+
+SAMPLE_PROGRAM = 'tests/test_program.txt'
+
+@dataclass
+class Lesson:
+    """One page of the guided walkthrough"""
+    title: str
+    text: str
+    panel: Optional[str] = None   # Panel to point at: 'cpu', 'registers', 'cache' or 'controls'
+    action: Optional[str] = None  # What the lesson's button does: 'load' the sample program or 'step' it
+
+LESSONS = [
+    Lesson("Welcome",
+           "This walkthrough runs a small program one instruction at a time and explains what each "
+           "instruction did to the registers and the caches. Press \"Load the sample program\" to start with "
+           f"{SAMPLE_PROGRAM}, which stores a few values, loads them back and operates on them.",
+           action='load'),
+    Lesson("The CPU panel",
+           "PC is the program counter: the index of the next instruction to run. Instr shows that "
+           "instruction and Status what happened last. Flags are set by CMP and arithmetic and tested by "
+           "conditional branches.",
+           panel='cpu'),
+    Lesson("Take a step",
+           "Step runs the instruction at the PC. After every step the box below explains what it did: "
+           "which registers changed, which memory word it read or wrote and how the caches handled it.",
+           panel='controls', action='step'),
+    Lesson("Registers",
+           "The register panel shows the general purpose registers eax-edi and the zero register. LOAD "
+           "copies a memory word into a register, MOV [addr] stores one, and ALU instructions such as ADD "
+           "and SHL work on registers only.",
+           panel='registers', action='step'),
+    Lesson("Reading the cache panel",
+           "Each row of the L1 and L2 grids is a set and each box a way of that set. A box shows the tag of "
+           "the address it holds (T) and the value (V), or Empty. Lines are one word, so an address's set is "
+           "the address modulo the number of sets and its tag the rest. The box the last step used is "
+           "highlighted in the hit or miss color.",
+           panel='cache', action='step'),
+    Lesson("Hits and misses",
+           "Keep stepping. The first access to a word misses in L1 and is fetched from L2 or main memory; "
+           "the next access to the same word hits, as long as other addresses mapping to the same set have "
+           "not evicted it. The statistics line above the grids counts both.",
+           panel='cache', action='step'),
+    Lesson("Where next",
+           "Show Used Memory lists every word the program touched, Show Statistics counts register use and "
+           "loops, Edit Program changes the program and Settings... changes the cache sizes and policies, "
+           "so you can watch the same program hit and miss differently. Run plays the program at the speed "
+           "on the slider."),
+]
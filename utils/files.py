@@ -0,0 +1,31 @@
+import sys
+from typing import List
+
+STDIN = '-'  # The file name that stands for standard input, as for cat(1)
+
+def read_text(path: str) -> str:
+    """A text file's contents, or everything piped to standard input when path is '-'"""
+    if path == STDIN:
+        return sys.stdin.read()
+    with open(path) as f:
+        return f.read()
+
+def read_lines(path: str) -> List[str]:
+    """A text file's lines, or standard input's when path is '-'"""
+    return read_text(path).splitlines()
+
+def read_bytes(path: str) -> bytes:
+    """A binary file's contents, or standard input's when path is '-'"""
+    if path == STDIN:
+        return sys.stdin.buffer.read()
+    with open(path, 'rb') as f:
+        return f.read()
+
+def display_name(path: str) -> str:
+    """How to name a file in messages: '<stdin>' for standard input"""
+    return '<stdin>' if path == STDIN else path
+
+def check_stdin_once(parser, paths: List[str]) -> None:
+    """Reject a list of files naming standard input more than once, since it can only be read once"""
+    if paths.count(STDIN) > 1:
+        parser.error("standard input (-) can be given only once")
@@ -1,11 +1,12 @@
-from typing import Dict, List, Optional, Any
+import re
+from contextlib import contextmanager
+from typing import Callable, Dict, List, Optional, Any
+from collections import deque
 from dataclasses import dataclass, field
 from time import time
 from enum import Enum
-from colorama import init, Fore, Back, Style
-
-# Initialize colorama
-init()
+from colorama import Fore, Back, Style
+from utils.style import COLORS, add_color_arguments, configure_color, style  # Initializes colorama
 
 class LogLevel(Enum):
     DEBUG = 1
@@ -13,6 +14,28 @@ class LogLevel(Enum):
     WARNING = 3
     ERROR = 4
 
+# Where messages come from; levels can be set per target or per prefix ("cache" covers "cache.access")
+LOG_TARGETS = {
+    'cpu.exec': "Instruction execution, register updates and execution errors",
+    'cpu.trap': "Traps, interrupts and syscalls",
+    'cpu.summary': "End-of-run statistics",
+    'asm.load': "Assembling and loading programs",
+    'cache.access': "Cache reads, writes, address breakdowns and replacements",
+    'memory.access': "Main memory reads and writes",
+    'general': "Everything else",
+}
+
+# How many recent messages the logger keeps for collectors such as the GUI's log panel
+RECENT_RECORDS = 500
+
+@dataclass
+class LogRecord:
+    """One message that passed its target's level"""
+    level: LogLevel
+    target: str
+    message: str
+    timestamp: float = field(default_factory=time)
+
 @dataclass
 class Operation:
     """Base class for all loggable operations"""
@@ -61,12 +84,21 @@ class Logger:
         self._cache_transitions = []
         self._initialized = True
 
+        # Fast mode drops operation records and every message below ERROR (see SimpleISA.run)
+        self.fast_mode = False
+        self._muted = 0
+
+        # Per-target levels, recent messages and the collectors that receive new ones
+        self._target_levels: Dict[str, LogLevel] = {}
+        self.recent = deque(maxlen=RECENT_RECORDS)
+        self._handlers: List[Callable[[LogRecord], None]] = []
+
         # Enhanced color scheme for memory hierarchy
         self._cache_colors = {
             'L1Cache': Fore.CYAN,      # Bright cyan for L1
             'L2Cache': Fore.MAGENTA,   # Magenta for L2
             'MainMemory': Fore.GREEN,  # Green for main memory
-            'Register': Fore.YELLOW,   # Yellow for registers
+            'Register': COLORS['register'],  # Cyan for registers
             'DataFlow': Fore.WHITE     # White for data flow indicators
         }
 
@@ -90,9 +122,65 @@ class Logger:
         else:
             raise ValueError("Log level must be a LogLevel enum")
 
-    def should_log(self, level: LogLevel) -> bool:
+    def should_log(self, level: LogLevel, target: str = 'general') -> bool:
         """Check if message at given level should be logged"""
-        return level.value >= self._log_level.value
+        if self._muted or (self.fast_mode and level != LogLevel.ERROR):
+            return False
+        return level.value >= self.level_for(target).value
+
+    @contextmanager
+    def muted(self):
+        """Drop every message, errors included, inside the block (scratch work such as live syntax checks)"""
+        self._muted += 1
+        try:
+            yield
+        finally:
+            self._muted -= 1
+
+    def level_for(self, target: str) -> LogLevel:
+        """Level of the most specific target setting covering target, else the global level"""
+        while True:
+            if target in self._target_levels:
+                return self._target_levels[target]
+            if '.' not in target:
+                return self._log_level
+            target = target.rsplit('.', 1)[0]
+
+    def set_target_level(self, target: str, level: Optional[LogLevel]) -> None:
+        """Override the level of a target or target prefix; None removes the override"""
+        if level is None:
+            self._target_levels.pop(target, None)
+        else:
+            self._target_levels[target] = level
+
+    def set_verbosity(self, verbosity: int) -> None:
+        """-q/-v style verbosity: below 0 warnings only, 0 the default INFO, 1 or more DEBUG"""
+        self.log_level = LogLevel.WARNING if verbosity < 0 else LogLevel.INFO if verbosity == 0 else LogLevel.DEBUG
+
+    def add_handler(self, handler: Callable[[LogRecord], None]) -> None:
+        """Call handler with every message that passes its target's level"""
+        self._handlers.append(handler)
+
+    def remove_handler(self, handler: Callable[[LogRecord], None]) -> None:
+        if handler in self._handlers:
+            self._handlers.remove(handler)
+
+    @staticmethod
+    def _plain(message: str) -> str:
+        """A message without its terminal color codes"""
+        return re.sub(r'\x1b\[[0-9;]*m', '', message).strip()
+
+    def _publish(self, level: LogLevel, target: str, message: str) -> None:
+        """Hand a message that passed the level check to the recent buffer and collectors"""
+        record = LogRecord(level, target, message)
+        self.recent.append(record)
+        for handler in self._handlers:
+            handler(record)
+
+    def _record(self, operation: Operation):
+        """Keep an operation for the summary unless running in fast mode"""
+        if not self.fast_mode:
+            self._operations.append(operation)
 
     def _get_level_color(self, level: LogLevel) -> str:
         """Get color for log level"""
@@ -133,19 +221,20 @@ class Logger:
                 })
 
     # Core logging methods
-    def log(self, level: LogLevel, message: str, data: Dict = None):
+    def log(self, level: LogLevel, message: str, data: Dict = None, target: str = 'general'):
         """Core logging method"""
-        if self.should_log(level):
+        if self.should_log(level, target):
             color = self._get_level_color(level)
             print(f"{color}{message}{Style.RESET_ALL}")
-        self._operations.append(
+            self._publish(level, target, self._plain(message))
+        self._record(
             Operation(level.name.lower(), message, data)
         )
 
     # Cache logging methods
     def log_cache_operation(self, cache_name: str, op_type: str, hit: bool, details: Any = None):
         """Enhanced cache operation logging with cleaner visualization"""
-        if not self.should_log(LogLevel.INFO):
+        if not self.should_log(LogLevel.INFO, 'cache.access'):
             return
 
         # Get appropriate colors
@@ -159,7 +248,7 @@ class Logger:
             'through': 'PROP '
         }.get(op_type, '????')
 
-        hit_status = "HIT ✓" if hit else "MISS ✗"
+        hit_status = style("HIT ✓", 'hit') if hit else style("MISS ✗", 'miss')
 
         # Format the base message with clear spacing
         message = (
@@ -175,6 +264,7 @@ class Logger:
                 message += f" = {details['value']}"
 
         print("\n" + message)
+        self._publish(LogLevel.INFO, 'cache.access', self._plain(message))
 
         # Add cache set visualization with better spacing
         if details and isinstance(details, dict):
@@ -183,6 +273,9 @@ class Logger:
                 associativity = details['associativity']
                 current_entries = details.get('entries', 0)
 
+                if 'fields' in details:
+                    print(f"  Address {details['fields']}")
+
                 # Visual representation of cache set
                 print(f"  Cache Set {set_index}:")
                 blocks = "█" * current_entries + "░" * (associativity - current_entries)
@@ -190,7 +283,7 @@ class Logger:
 
                 # Only show capacity info if relevant
                 if current_entries >= associativity:
-                    print("  → Set full, will use LRU policy for next write")
+                    print(f"  → Set full, will use {details.get('replacement_policy', 'lru').upper()} policy for next write")
                     if details.get('dirty', False):
                         print("  → Dirty data will be written back")
 
@@ -238,7 +331,7 @@ class Logger:
     # ISA logging methods
     def log_register_operation(self, op_type: str, details: Dict[str, Any]):
         """Enhanced register operation logging with data flow visualization"""
-        if not self.should_log(LogLevel.INFO):
+        if not self.should_log(LogLevel.INFO, 'cpu.exec'):
             return
 
         dest = details.get('dest', '')
@@ -260,10 +353,11 @@ class Logger:
         )
 
         print(message)
+        self._publish(LogLevel.INFO, 'cpu.exec', self._plain(message))
 
     def log_memory_operation(self, op_type: str, details: Dict[str, Any]):
         """Enhanced memory operation logging with data flow visualization"""
-        if not self.should_log(LogLevel.INFO):
+        if not self.should_log(LogLevel.INFO, 'memory.access'):
             return
 
         cache_name = details.get('cache_name', 'MainMemory')
@@ -277,17 +371,17 @@ class Logger:
 
         # Build the operation string
         op_symbol = self._flow_symbols[op_type]
-        hit_symbol = self._flow_symbols['hit'] if hit else self._flow_symbols['miss']
+        hit_symbol = style(self._flow_symbols['hit'], 'hit') if hit else style(self._flow_symbols['miss'], 'miss')
 
         # Format the message with colors and symbols
         message = (
             f"{flow_color}{op_symbol} {cache_color}{cache_name}{Style.RESET_ALL} "
             f"at address {Fore.WHITE}[{address}]{Style.RESET_ALL} = "
-            f"{cache_color}{value}{Style.RESET_ALL} "
-            f"{flow_color}{hit_symbol}{Style.RESET_ALL}"
+            f"{cache_color}{value}{Style.RESET_ALL} {hit_symbol}"
         )
 
         print(message)
+        self._publish(LogLevel.INFO, 'memory.access', self._plain(message))
 
         # Add human-readable explanation
         if op_type == "read":
@@ -321,7 +415,7 @@ class Logger:
             if details:
                 for key, value in details.items():
                     print(f"  {key}: {value}")
-        self._operations.append(
+        self._record(
             Operation("instruction", instruction, details)
         )
 
@@ -333,7 +427,7 @@ class Logger:
             if details:
                 for key, value in details.items():
                     print(f"{key}: {value}")
-        self._operations.append(
+        self._record(
             Operation("jump", f"Jump to {target}", details)
         )
 
@@ -346,7 +440,7 @@ class Logger:
             if data:
                 for key, value in data.items():
                     print(f"  {key}: {value}")
-        self._operations.append(
+        self._record(
             Operation(
                 type=step_type,
                 description=description,
@@ -363,7 +457,7 @@ class Logger:
             for i, elem in enumerate(elements):
                 print(f"[{i}] = {elem}")
             print("----------------------------------------")
-        self._operations.append(
+        self._record(
             Operation(
                 type="array_display",
                 description=title,
@@ -379,7 +473,7 @@ class Logger:
             print(f"Position {pos1} and {pos2}")
             print(f"Values: {val1} and {val2}")
             print(f"Result: {result}")
-        self._operations.append(
+        self._record(
             Operation(
                 type="comparison",
                 description=f"Compare elements at positions {pos1} and {pos2}",
@@ -400,7 +494,7 @@ class Logger:
             print("\nSwapping Elements:")
             print(f"Position {pos1} and {pos2}")
             print(f"Values: {val1} and {val2}")
-        self._operations.append(
+        self._record(
             Operation(
                 type="swap",
                 description=f"Swap elements at positions {pos1} and {pos2}",
@@ -422,7 +516,7 @@ class Logger:
             if details:
                 for key, value in details.items():
                     print(f"  {key}: {value}")
-        self._operations.append(
+        self._record(
             Operation("error", error_type,
                      {"message": message, **details} if details else {"message": message})
         )
@@ -433,7 +527,7 @@ class Logger:
             print(f"\n{Fore.BLUE}=== Performance Metrics ==={Style.RESET_ALL}")
             for key, value in metrics.items():
                 print(f"{key}: {value}")
-        self._operations.append(
+        self._record(
             Operation("performance", "Performance metrics", metrics)
         )
 
@@ -508,7 +602,7 @@ class Logger:
                 print(f"Upward Transition Rate: {upward_rate:.2f}%")
                 print(f"Downward Transition Rate: {downward_rate:.2f}%")
 
-        self._operations.append(
+        self._record(
             Operation("cache_transitions", f"Cache transitions for {cache_name}", stats)
         )
 
@@ -519,7 +613,7 @@ class Logger:
             for issue in issues:
                 print(f"- {issue}")
 
-        self._operations.append(
+        self._record(
             Operation("cache_state_issues", f"State issues for {cache_name}", {"issues": issues})
         )
 
@@ -531,7 +625,7 @@ class Logger:
             print(f"Dirty entries: {stats['dirty_entries']}")
             print(f"Clean entries: {stats['clean_entries']}")
 
-        self._operations.append(
+        self._record(
             Operation("cache_entry_stats", f"Entry stats for {cache_name}", stats)
         )
 
@@ -544,7 +638,7 @@ class Logger:
             print(f"Random access rate: {patterns['random_rate']:.2f}%")
             print(f"Repeated access rate: {patterns['repeated_rate']:.2f}%")
 
-        self._operations.append(
+        self._record(
             Operation("cache_access_patterns", f"Access patterns for {cache_name}", patterns)
         )
 
@@ -565,7 +659,7 @@ class Logger:
         print(f"  Average: {info['latency_stats']['total'] / info['latency_stats']['count'] if info['latency_stats']['count'] > 0 else 0} ns")
         print(f"  Total Operations: {info['latency_stats']['count']}")
 
-        self._operations.append(
+        self._record(
             Operation("memory_debug", f"Debug info for {memory_name}", info)
         )
 
@@ -585,7 +679,7 @@ class Logger:
         for pattern, count in info['access_pattern'].items():
             print(f"  {pattern}: {count}")
 
-        self._operations.append(
+        self._record(
             Operation("memory_contents", f"Contents for {memory_name}", info)
         )
 
@@ -616,7 +710,7 @@ class Logger:
         print(f"  Incorrect: {info['branch_prediction']['incorrect']}")
         print(f"  Accuracy: {accuracy:.2f}%")
 
-        self._operations.append(
+        self._record(
             Operation("isa_debug", "ISA debug information", info)
         )
 
@@ -632,7 +726,7 @@ class Logger:
         for reg, value in info['registers'].items():
             print(f"{reg}: {value}")
 
-        self._operations.append(
+        self._record(
             Operation("jump_debug", "Jump operation debug", info)
         )
 
@@ -655,7 +749,7 @@ class Logger:
         print(f"\nLRU Order: {info['lru_order']}")
         print(f"Next Level: {info['next_level']}")
 
-        self._operations.append(
+        self._record(
             Operation("cache_debug", f"Debug info for {cache_name}", info)
         )
 
@@ -673,7 +767,7 @@ class Logger:
         print(f"  Access Time: {config['access_time']} ns")
         print(f"  Total Execution Time: {config['exec_time']} ns")
 
-        self._operations.append(
+        self._record(
             Operation("cache_config", f"Configuration for {cache_name}", config)
         )
 
@@ -699,7 +793,7 @@ class Logger:
                 "is_valid": val1 <= val2 if val1 is not None and val2 is not None else None
             })
 
-        self._operations.append(
+        self._record(
             Operation(
                 type="verification",
                 description="Verify array sorting",
@@ -707,3 +801,26 @@ class Logger:
                 category="algorithm"
             )
         )
+
+def add_logging_arguments(parser) -> None:
+    """Add -v/--verbose, -q/--quiet, --log TARGET=LEVEL and --no-color to an argparse parser"""
+    parser.add_argument('-v', '--verbose', action='count', default=0,
+                        help="Show debug messages (address breakdowns, cache internals)")
+    parser.add_argument('-q', '--quiet', action='store_true', help="Only show warnings and errors")
+    parser.add_argument('--log', action='append', default=[], metavar='TARGET=LEVEL',
+                        help=f"Level for one message target, e.g. cache.access=debug or cpu=warning "
+                             f"(targets: {', '.join(LOG_TARGETS)})")
+    add_color_arguments(parser)
+
+def configure_logging(args) -> Logger:
+    """Apply the options added by add_logging_arguments to the shared logger"""
+    configure_color(args)
+    logger = Logger()
+    logger.set_verbosity(-1 if args.quiet else args.verbose)
+    for spec in args.log:
+        target, _, level = spec.partition('=')
+        try:
+            logger.set_target_level(target.strip(), LogLevel[level.strip().upper()])
+        except KeyError:
+            raise SystemExit(f"Invalid --log setting '{spec}' (expected TARGET=debug|info|warning|error)")
+    return logger
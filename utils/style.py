@@ -0,0 +1,41 @@
+import os
+
+import colorama
+from colorama import Fore, Style
+
+# What each kind of terminal output is colored
+COLORS = {
+    'hit': Fore.GREEN,
+    'miss': Fore.RED,
+    'taken': Fore.YELLOW,     # Branches that went to their target
+    'register': Fore.CYAN,    # Registers an instruction changed
+}
+
+# Colors are on unless the NO_COLOR environment variable is set to anything (https://no-color.org)
+_enabled = not os.environ.get('NO_COLOR')
+
+def set_color(enabled: bool) -> None:
+    """Turn terminal colors on or off; off also strips the codes the logger writes itself"""
+    global _enabled
+    _enabled = enabled
+    colorama.deinit()
+    colorama.init(strip=None if enabled else True)  # None: colorama strips them when stdout is not a terminal
+
+def color_enabled() -> bool:
+    return _enabled
+
+def style(text: str, kind: str) -> str:
+    """text in the color for its kind, or as it is with colors off"""
+    return f"{COLORS[kind]}{text}{Style.RESET_ALL}" if _enabled else text
+
+def add_color_arguments(parser) -> None:
+    """Add --no-color to an argparse parser"""
+    parser.add_argument('--no-color', action='store_true',
+                        help="Print without colors (also turned off by setting NO_COLOR)")
+
+def configure_color(args) -> None:
+    """Apply the option added by add_color_arguments"""
+    if args.no_color:
+        set_color(False)
+
+set_color(_enabled)
@@ -0,0 +1,159 @@
+import argparse
+import sys
+from datetime import datetime
+from typing import Dict, List, Optional, Tuple
+
+from events import SimEvent
+from isa import SimpleISA, InstructionType
+from utils.files import read_lines
+
+WORD_BITS = 32
+OPCODE_BITS = 8
+
+class VCDRecorder:
+    """Samples CPU signals after every instruction for a Value Change Dump
+
+    Signals: pc, opcode (the InstructionType value of the executed
+    instruction), the register file plus a write-enable pulse, L1 cache
+    hit/miss for the instruction's memory accesses and a stall flag.
+    Without a pipeline model each instruction takes one cycle; with one the
+    sample lands on the cycle the instruction completes write-back and the
+    stall flag is set when it waited for operands.
+    """
+
+    def __init__(self, isa: SimpleISA):
+        self.isa = isa
+        self._hits = self._misses = 0
+        isa.events.subscribe(self._on_cache_access, [SimEvent.CACHE_ACCESS])
+        self.reset()
+
+    def reset(self) -> None:
+        self.samples: List[Tuple[int, Dict[str, int]]] = []  # (cycle, signal values)
+
+    def signals(self) -> List[Tuple[str, int]]:
+        """(name, width) of every recorded signal"""
+        return ([('pc', WORD_BITS), ('opcode', OPCODE_BITS), ('reg_we', 1),
+                 ('cache_hit', 1), ('cache_miss', 1), ('stall', 1)] +
+                [(reg, WORD_BITS) for reg in self.isa.registers])
+
+    def _on_cache_access(self, event) -> None:
+        if event.data['cache'] is None:
+            return
+        if event.data['hit']:
+            self._hits += 1
+        else:
+            self._misses += 1
+
+    def step(self) -> bool:
+        """Execute one instruction and sample the signals it produced"""
+        isa = self.isa
+        if not isa.running or isa.pc >= len(isa.instructions):
+            return isa.execute_step()
+
+        pc = isa.pc
+        instruction = isa.instructions[pc]
+        self._hits = self._misses = 0
+        pipeline_length = len(isa.pipeline.records) if isa.pipeline else 0
+
+        result = isa.execute_step()
+
+        values = {
+            'pc': pc,
+            'opcode': instruction.type.value,
+            'reg_we': int(result and bool(isa.register_writes(instruction))),
+            'cache_hit': int(self._hits > 0 and self._misses == 0),
+            'cache_miss': int(self._misses > 0),
+            'stall': 0
+        }
+        values.update(isa.registers)
+
+        if isa.pipeline and len(isa.pipeline.records) > pipeline_length:
+            record = isa.pipeline.records[-1]
+            cycle = record.complete
+            values['stall'] = int(record.stalls > 0)
+        else:
+            cycle = (self.samples[-1][0] + 1) if self.samples else 1
+        self.samples.append((cycle, values))
+        return result
+
+    @staticmethod
+    def _identifier(index: int) -> str:
+        """Short printable VCD identifier code for a signal"""
+        chars = [chr(33 + index % 94)]
+        index //= 94
+        while index:
+            chars.append(chr(33 + index % 94))
+            index //= 94
+        return ''.join(chars)
+
+    @staticmethod
+    def _format(value: int, width: int, identifier: str) -> str:
+        if width == 1:
+            return f"{value & 1}{identifier}"
+        return f"b{value & ((1 << width) - 1):b} {identifier}"
+
+    def to_vcd(self) -> str:
+        """Render the samples as a VCD document (one time unit per cycle)"""
+        signals = self.signals()
+        ids = {name: self._identifier(i) for i, (name, _) in enumerate(signals)}
+        opcodes = ", ".join(f"{t.value}={t.name}" for t in InstructionType)
+
+        lines = [
+            f"$date {datetime.now().strftime('%Y-%m-%d %H:%M:%S')} $end",
+            "$version CPU-Sim VCD export $end",
+            f"$comment opcode values: {opcodes} $end",
+            "$timescale 1 ns $end",
+            "$scope module cpu $end"
+        ]
+        for name, width in signals:
+            kind = 'wire' if width == 1 else 'reg'
+            lines.append(f"$var {kind} {width} {ids[name]} {name} $end")
+        lines += ["$upscope $end", "$enddefinitions $end"]
+
+        previous: Dict[str, Optional[int]] = {name: None for name, _ in signals}
+        for cycle, values in self.samples:
+            changes = []
+            for name, width in signals:
+                value = values.get(name, 0)
+                if value != previous[name]:
+                    changes.append(self._format(value, width, ids[name]))
+                    previous[name] = value
+            if changes:
+                lines.append(f"#{cycle}")
+                lines += changes
+        if self.samples:
+            lines.append(f"#{self.samples[-1][0] + 1}")
+        return '\n'.join(lines) + '\n'
+
+    def save(self, filename: str) -> None:
+        with open(filename, 'w') as f:
+            f.write(self.to_vcd())
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program and dump its signals as a VCD waveform for GTKWave")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('-o', '--output', default='trace.vcd', help="VCD file to write (default: trace.vcd)")
+    parser.add_argument('--pipeline', action='store_true',
+                        help="Time samples with the five-stage pipeline model instead of one cycle per instruction")
+    args = parser.parse_args()
+
+    from memory import MainMemory
+    isa = SimpleISA(memory=MainMemory("MainMemory", 1024))
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    isa.load_program(program)
+    if args.pipeline:
+        from pipeline import PipelineModel
+        isa.pipeline = PipelineModel(isa)
+
+    recorder = VCDRecorder(isa)
+    while recorder.step():
+        pass
+    recorder.save(args.output)
+    print(f"Wrote {len(recorder.samples)} samples to {args.output}")
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,179 @@
+from typing import Dict, List, Optional, Set, Tuple
+
+import io
+from contextlib import redirect_stdout
+
+from isa import (SimpleISA, Instruction, InstructionType, BRANCH_INSTRUCTIONS, OPCODES, SUBWORD_LOADS,
+                 SUBWORD_STORES, TRAP_VECTOR, WORD_BYTES, is_control_transfer, mnemonic)
+from memory import MainMemory
+from utils.logger import Logger
+
+# Branches that may fall through to the next instruction (after a JAL, once the subroutine returns)
+CONDITIONAL_BRANCHES = BRANCH_INSTRUCTIONS - {InstructionType.JMP}
+
+# Instructions whose last operand is a value: a register, #constant or [address]
+VALUE_SOURCES = {InstructionType.MOV, InstructionType.ADD, InstructionType.SUB, InstructionType.AND,
+                 InstructionType.OR, InstructionType.XOR, InstructionType.CMP, InstructionType.TEST,
+                 InstructionType.SHL, InstructionType.SHR}
+
+# Instructions after which execution never continues at the next instruction
+NO_FALLTHROUGH = {InstructionType.JMP, InstructionType.HALT, InstructionType.ERET, InstructionType.JR}
+
+class ProgramVerifier:
+    """Static checks run on a program after it is assembled
+
+    Flags branch targets that are undefined or fall outside the program,
+    literal data addresses outside memory, bare numbers where a value or an
+    address is expected, and instructions that no path from the entry point
+    (or the trap handler) can reach. With delay_slots the instruction after
+    every branch and jump runs, and a branch there is flagged.
+    """
+
+    def __init__(self, memory_size: Optional[int] = None, delay_slots: bool = False):
+        self.memory_size = memory_size
+        self.delay_slots = delay_slots
+
+    def verify(self, instructions: List[Instruction], labels: Dict[str, int], base: int = 0,
+               entry: Optional[int] = None) -> List[str]:
+        """Return warnings for the instructions at indices base..base+len-1, which start executing at entry (base)"""
+        warnings = []
+        for first, last, message in self.findings(instructions, labels, base, entry):
+            where = f"Line {first + 1}" if first == last else f"Lines {first + 1}-{last + 1}"
+            warnings.append(f"{where}: {message}")
+        return warnings
+
+    def findings(self, instructions: List[Instruction], labels: Dict[str, int],
+                 base: int = 0, entry: Optional[int] = None) -> List[Tuple[int, int, str]]:
+        """The warnings as (first line index, last line index, message), for editors that mark lines"""
+        findings = []
+        end = base + len(instructions)
+
+        for previous, instruction in zip([None] + instructions, instructions):
+            line = instruction.line_number
+            if self.delay_slots and previous and is_control_transfer(previous.type) and \
+                    is_control_transfer(instruction.type):
+                findings.append((line, line, f"{mnemonic(instruction.type)} is in the delay slot of the "
+                                             f"{mnemonic(previous.type)} before it, which stops the program"))
+            target = self._branch_target(instruction)
+            if target is not None:
+                if target not in labels:
+                    findings.append((line, line, f"branch target '{target}' is undefined"))
+                elif not base <= labels[target] < end:
+                    findings.append((line, line, f"branch target '{target}' is past the last instruction, "
+                                                 f"so execution stops there"))
+
+            for addr in self.literal_addresses(instruction):
+                if self.memory_size is not None and not 0 <= addr < self.memory_size:
+                    findings.append((line, line, f"address {addr} is outside memory (0-{self.memory_size - 1})"))
+            message = self._operand_problem(instruction)
+            if message:
+                findings.append((line, line, message))
+
+        findings.extend(self._unreachable_findings(instructions, labels, base, base if entry is None else entry))
+        return findings
+
+    @staticmethod
+    def _branch_target(instruction: Instruction) -> Optional[str]:
+        if instruction.type in BRANCH_INSTRUCTIONS and instruction.operands:
+            return instruction.operands[0]
+        return None
+
+    @staticmethod
+    def _operand_problem(instruction: Instruction) -> Optional[str]:
+        """A wrong number of operands, or a source operand that does not mean what it looks like: MOV eax 10
+        reads a register named 10"""
+        counts = OPCODES[instruction.type].operand_counts
+        if len(instruction.operands) not in counts:
+            takes = "no" if counts == [0] else " or ".join(map(str, counts))
+            return (f"{mnemonic(instruction.type)} takes {takes} operand{'' if counts == [1] else 's'}, "
+                    f"not {len(instruction.operands)}")
+        if len(instruction.operands) != 2:
+            return None
+        dest, src = instruction.operands
+        number = src.lstrip('#')
+        is_number = number.lstrip('-').isdigit()
+        if instruction.type == InstructionType.LOAD and not src.startswith('['):
+            if not is_number:
+                return f"LOAD reads memory: write [{src}] for the word at the address in {src}"
+            return f"LOAD reads memory: write [{number}] for memory word {number}, or LI {dest} {number} for the number"
+        if instruction.type in VALUE_SOURCES and is_number and not src.startswith('#'):
+            return f"{src} is not a register, so this reads 0: write #{src} for the number or [{src}] for memory"
+        return None
+
+    @staticmethod
+    def literal_addresses(instruction: Instruction) -> List[int]:
+        """Word addresses written as [n] in the operands"""
+        subword = instruction.type in SUBWORD_LOADS or instruction.type in SUBWORD_STORES
+        addresses = []
+        for operand in instruction.operands:
+            if operand.startswith('[') and operand[1:-1].isdigit():
+                addr = int(operand[1:-1])
+                addresses.append(addr // WORD_BYTES if subword else addr)
+        return addresses
+
+    def _unreachable_findings(self, instructions: List[Instruction], labels: Dict[str, int],
+                              base: int, entry: int) -> List[Tuple[int, int, str]]:
+        end = base + len(instructions)
+
+        # Entry points: the program's entry (.entry, or the first instruction), the trap vector
+        # and labels loaded into epc
+        worklist = [entry] if instructions else []
+        if TRAP_VECTOR in labels:
+            worklist.append(labels[TRAP_VECTOR])
+        for instruction in instructions:
+            if instruction.type == InstructionType.MTC0:
+                worklist.extend(labels[op] for op in instruction.operands if op in labels)
+
+        visited: Set[int] = set()
+        reachable: Set[int] = set()
+        while worklist:
+            pc = worklist.pop()
+            if pc in visited or not base <= pc < end:
+                continue
+            visited.add(pc)
+            reachable.add(pc)
+            instruction = instructions[pc - base]
+            target = self._branch_target(instruction)
+            if target in labels:
+                worklist.append(labels[target])
+            if self.delay_slots and is_control_transfer(instruction.type):
+                # The delay slot runs on both paths; falling through continues after it
+                if pc + 1 < end:
+                    reachable.add(pc + 1)
+                if instruction.type not in NO_FALLTHROUGH:
+                    worklist.append(pc + 2)
+            elif instruction.type not in NO_FALLTHROUGH:
+                worklist.append(pc + 1)
+
+        # Report runs of consecutive unreachable instructions once
+        findings = []
+        run: List[Instruction] = []
+        for pc in range(base, end + 1):
+            if pc < end and pc not in reachable:
+                run.append(instructions[pc - base])
+                continue
+            if run:
+                findings.append((run[0].line_number, run[-1].line_number,
+                                 f"unreachable ({len(run)} instruction{'s' if len(run) > 1 else ''})"))
+                run = []
+        return findings
+
+def check_source(lines: List[str], memory_size: int = 1024) -> List[Tuple[int, str, str]]:
+    """Assemble lines on a scratch machine and return (line index, 'error' or 'warning', message)
+
+    Errors are lines the assembler skipped or rejected; warnings come from
+    ProgramVerifier. A rejected directive stops assembly, so nothing after
+    it is checked. Nothing is logged.
+    """
+    isa = SimpleISA(memory=MainMemory("Scratch", memory_size))
+    with Logger().muted(), redirect_stdout(io.StringIO()):
+        try:
+            isa.load_program(lines)
+        except ValueError:
+            return [(line, 'error', message) for line, message in isa.diagnostics]
+    results = [(line, 'error', message) for line, message in isa.diagnostics]
+    for first, last, message in ProgramVerifier(memory_size, isa.delay_slots).findings(isa.instructions, isa.labels,
+                                                                                       entry=isa.entry_point):
+        results.extend((line, 'warning', message) for line in range(first, last + 1)
+                       if line < len(lines) and lines[line].strip() and not lines[line].strip().startswith(';'))
+    return sorted(results)
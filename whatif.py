@@ -0,0 +1,172 @@
+import argparse
+import sys
+from dataclasses import dataclass, replace
+from typing import Dict, List, Tuple
+
+from amat import amat_levels, compute_amat
+from cache.cache import Cache, REPLACEMENT_POLICIES, WRITE_POLICIES
+from events import Event, SimEvent
+from memory import MainMemory
+from utils.files import read_lines
+from utils.logger import Logger
+
+MAX_TRACE = 50000  # Accesses kept for replaying; a longer run is replayed from its start
+
+class CacheTrace:
+    """The L1's loads and stores in order, from the ISA's cache-access events, kept for replaying on other caches
+
+    Accesses to uncacheable addresses never reach the L1 and are left
+    out. PREF, CFLUSH and CINV are not cache accesses and are not replayed
+    either. Like the core's own counters, the trace starts over when the
+    statistics are reset.
+    """
+
+    def __init__(self, isa):
+        self.isa = isa
+        self.reset()
+        isa.events.subscribe(self.on_event, [SimEvent.CACHE_ACCESS, SimEvent.STATS_RESET])
+
+    def reset(self) -> None:
+        self.accesses: List[Tuple[str, int]] = []  # ('r' or 'w', address)
+        self.dropped = 0
+
+    def on_event(self, event: Event) -> None:
+        if event.kind == SimEvent.STATS_RESET:
+            self.reset()
+        elif event.data['cache'] is not None:
+            if len(self.accesses) < MAX_TRACE:
+                self.accesses.append((event.data['access'], event.data['address']))
+            else:
+                self.dropped += 1
+
+@dataclass
+class CacheConfig:
+    """One cache level's shape and policies, in the terms the what-if sliders use"""
+    sets: int
+    ways: int
+    line_size: int = 1
+    replacement_policy: str = "lru"
+    write_policy: str = "write-back"
+    access_time: int = 10
+
+    @classmethod
+    def of(cls, cache: Cache) -> 'CacheConfig':
+        return cls(cache._sets, cache._associativity, cache._line_size, cache._replacement_policy,
+                   cache._write_policy, cache._access_time)
+
+    @property
+    def size(self) -> int:
+        return self.sets * self.ways * self.line_size
+
+    def describe(self) -> str:
+        return (f"{self.sets} sets x {self.ways} ways x {self.line_size} words = {self.size} words, "
+                f"{self.replacement_policy.upper()}, {self.write_policy}")
+
+    def build(self, name: str, next_level) -> Cache:
+        return Cache(name, self.size, self.line_size, self.ways, self.access_time, self.write_policy, next_level,
+                     replacement_policy=self.replacement_policy)
+
+def l1_traffic(l1: CacheConfig, stats: Dict[str, object], write_backs: int) -> int:
+    """Words moved between the L1 and the level below: a whole block per miss and per dirty write-back, and a
+    word per store that writes through"""
+    stores = stats['write_hits'] + stats['write_misses'] if l1.write_policy == "write-through" else 0
+    return (stats['misses'] + write_backs) * l1.line_size + stores
+
+def replay(accesses: List[Tuple[str, int]], l1: CacheConfig, l2: CacheConfig) -> Dict[str, object]:
+    """Run a trace through fresh, empty caches shaped by l1 and l2 and return their statistics, AMAT and the
+    L1's traffic to the level below
+
+    Only hits and misses matter, so stores write 0 and the data is never
+    checked; a block of several words holds one value, which would be wrong
+    for a real run but does not change which accesses hit.
+    """
+    top = max((address for _, address in accesses), default=0)
+    memory = MainMemory("MainMemory", max(1024, top + 1))
+    l2_cache = l2.build("L2Cache", memory)
+    l1_cache = l1.build("L1Cache", l2_cache)
+    write_backs = []
+    l1_cache.on_evict = lambda cache, set_index, entry, reason, address, written_back: \
+        write_backs.append(written_back)
+    logger = Logger()
+    fast_mode = logger.fast_mode
+    logger.fast_mode = True  # No per-access logging: the replay has to keep up with a slider
+    try:
+        for access, address in accesses:
+            if access == 'r':
+                l1_cache.read(address, output=False)
+            else:
+                l1_cache.write(address, 0, output=False)
+    finally:
+        logger.fast_mode = fast_mode
+    l1_stats = l1_cache.get_performance_stats()
+    return {'l1': l1_stats, 'l2': l2_cache.get_performance_stats(), 'amat': compute_amat(amat_levels(l1_cache)),
+            'traffic': l1_traffic(l1, l1_stats, sum(write_backs))}
+
+def format_replay(result: Dict[str, object], l1: CacheConfig, l2: CacheConfig) -> List[str]:
+    lines = []
+    for level, config in (('l1', l1), ('l2', l2)):
+        stats = result[level]
+        lines.append(f"{level.upper()}: {config.describe()}: {stats['hits']} hits, {stats['misses']} misses "
+                     f"({stats['hit_rate']:.1f}% hit rate)")
+    lines.append(f"AMAT: {result['amat']:.2f} ns")
+    return lines
+
+def record_trace(lines: List[str]) -> Tuple[CacheTrace, Cache, Cache]:
+    """Run a program on the default hierarchy and return its L1 trace and the L1 and L2 it ran on"""
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from utils.logger import LogLevel
+    memory, l1, l2 = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    trace = CacheTrace(isa)
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The replays, not the trace, are the output
+    try:
+        isa.load_program(lines)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+    return trace, l1, l2
+
+def _power_of_two(text: str) -> int:
+    value = int(text)
+    if value < 1 or value & (value - 1):
+        raise argparse.ArgumentTypeError(f"{text} is not a power of two")
+    return value
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program once, then replay its L1 loads and stores on "
+                                                 "other cache shapes and policies without running it again")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--sets', type=_power_of_two, help="L1 sets (default: the default L1's)")
+    parser.add_argument('--ways', type=int, help="L1 associativity")
+    parser.add_argument('--line-size', type=_power_of_two, help="L1 block size in words")
+    parser.add_argument('--policy', choices=REPLACEMENT_POLICIES, help="L1 replacement policy")
+    parser.add_argument('--write-policy', choices=WRITE_POLICIES, help="L1 write policy")
+    args = parser.parse_args()
+
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    trace, l1, l2 = record_trace(program)
+    recorded, l2_config = CacheConfig.of(l1), CacheConfig.of(l2)
+    changes = {name: value for name, value in (('sets', args.sets), ('ways', args.ways),
+                                               ('line_size', args.line_size), ('replacement_policy', args.policy),
+                                               ('write_policy', args.write_policy)) if value is not None}
+    print(f"{len(trace.accesses)} L1 accesses recorded" +
+          (f" (the first {MAX_TRACE}; {trace.dropped} more are not replayed)" if trace.dropped else ""))
+    print("As run:")
+    print('\n'.join("  " + line for line in format_replay(replay(trace.accesses, recorded, l2_config),
+                                                          recorded, l2_config)))
+    if changes:
+        what_if = replace(recorded, **changes)
+        print("What if:")
+        print('\n'.join("  " + line for line in format_replay(replay(trace.accesses, what_if, l2_config),
+                                                              what_if, l2_config)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,128 @@
+import argparse
+import sys
+from collections import Counter
+from dataclasses import dataclass
+from typing import List, Tuple
+
+from cache.cache import Cache
+from heatmap import AccessHeatmap, Access
+from utils.files import read_lines
+
+DEFAULT_WINDOW = 100  # Cycles
+
+@dataclass
+class WorkingSetPoint:
+    """The distinct words accessed in the window of cycles ending at cycle"""
+    cycle: int
+    words: int
+
+def working_set(samples: List[Access], window: int = DEFAULT_WINDOW, step: int = 0) -> List[WorkingSetPoint]:
+    """Working-set size every step cycles (a quarter window by default) from the first access to the last
+
+    Each point counts the distinct words read or written in the window
+    cycles up to and including its cycle. samples must be in cycle order,
+    as the heatmap records them.
+    """
+    if not samples:
+        return []
+    step = step or max(1, window // 4)
+    start, end = samples[0].cycle, samples[-1].cycle
+    ends = list(range(start, end + 1, step))
+    if ends[-1] != end:
+        ends.append(end)
+
+    counts: Counter = Counter()
+    points = []
+    first = last = 0  # samples[first:last] are in the window
+    for cycle in ends:
+        while last < len(samples) and samples[last].cycle <= cycle:
+            counts[samples[last].address] += 1
+            last += 1
+        while first < last and samples[first].cycle <= cycle - window:
+            counts[samples[first].address] -= 1
+            if not counts[samples[first].address]:
+                del counts[samples[first].address]
+            first += 1
+        points.append(WorkingSetPoint(cycle, len(counts)))
+    return points
+
+def cache_capacities(cache) -> List[Tuple[str, int]]:
+    """(name, capacity in words) of each cache level from cache down"""
+    capacities = []
+    seen = []
+    while cache is not None and all(cache is not level for level in seen):
+        seen.append(cache)
+        if isinstance(cache, Cache):
+            capacities.append((cache._name, cache._size))
+        cache = getattr(cache, '_next_level', None)
+    return capacities
+
+def summarize(points: List[WorkingSetPoint], capacities: List[Tuple[str, int]]) -> str:
+    """Peak working set and how many windows overflowed each cache level"""
+    if not points:
+        return "No data accesses yet"
+    peak = max(points, key=lambda point: point.words)
+    parts = [f"peak {peak.words} words at cycle {peak.cycle}"]
+    for name, words in capacities:
+        over = sum(point.words > words for point in points)
+        parts.append(f"over {name}'s {words} words in {over}/{len(points)} windows")
+    return ", ".join(parts)
+
+def format_working_set(points: List[WorkingSetPoint], capacities: List[Tuple[str, int]],
+                       width: int = 50) -> List[str]:
+    """One bar per point, with a '|' in each row where a cache level's capacity falls"""
+    if not points:
+        return ["No data accesses yet"]
+    top = max([point.words for point in points] + [words for _, words in capacities]) or 1
+    marks = {round(width * words / top) - 1: name for name, words in capacities}
+    lines = [f"{'cycle':>7} {'words':>5}  " + "  ".join(f"| = {name} ({words} words)" for name, words in capacities)]
+    for point in points:
+        bar = round(width * point.words / top)
+        row = ''.join('|' if column in marks else '#' if column < bar else ' ' for column in range(width))
+        lines.append(f"{point.cycle:>7} {point.words:>5}  {row.rstrip()}")
+    return lines
+
+def main():
+    parser = argparse.ArgumentParser(description="Run a program and plot how many distinct words it touched in "
+                                                 "each sliding window of cycles against the cache capacities")
+    parser.add_argument('program', help="Assembly program file, or - for standard input")
+    parser.add_argument('--window', type=int, default=DEFAULT_WINDOW,
+                        help=f"Window length in cycles (default {DEFAULT_WINDOW})")
+    parser.add_argument('--step', type=int, default=0, help="Cycles between points (default a quarter window)")
+    parser.add_argument('--no-pipeline', action='store_true',
+                        help="Count one cycle per instruction instead of the pipeline model's cycles")
+    args = parser.parse_args()
+    if args.window < 1 or args.step < 0:
+        parser.error("the window must be at least one cycle and the step not negative")
+
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from pipeline import PipelineModel
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    if not args.no_pipeline:
+        isa.pipeline = PipelineModel(isa)
+    heatmap = AccessHeatmap(isa)
+    try:
+        program = read_lines(args.program)
+    except OSError as e:
+        print(f"Cannot read {args.program}: {e.strerror}")
+        return 1
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The plot, not the trace, is the output
+    try:
+        isa.load_program(program)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+
+    points = working_set(heatmap.samples, args.window, args.step)
+    capacities = cache_capacities(l1)
+    print('\n'.join(format_working_set(points, capacities)))
+    print(summarize(points, capacities))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())
@@ -0,0 +1,92 @@
+import argparse
+import os
+import sys
+from typing import Dict, List, Optional, Tuple
+
+from scheduler import RoundRobinScheduler
+from utils.files import check_stdin_once, display_name, read_lines
+
+def run_workload(programs: List[Tuple[str, List[str]]], quantum: int = 8, region_size: int = 256,
+                 bases: Optional[List[int]] = None) -> Dict:
+    """Run programs together on a fresh default hierarchy, sharing its caches, and return the scheduler's stats"""
+    from isa import SimpleISA
+    from locality import build_hierarchy
+    from utils.logger import Logger, LogLevel
+    memory, l1, _ = build_hierarchy()
+    isa = SimpleISA(memory=memory, cache=l1)
+    scheduler = RoundRobinScheduler(quantum=quantum, region_size=region_size)
+    logger = Logger()
+    level = logger.log_level
+    logger.log_level = LogLevel.ERROR  # The per-program table, not the trace, is the output
+    try:
+        scheduler.load(isa, programs, bases)
+        isa.run(fast=True)
+    finally:
+        logger.log_level = level
+    return scheduler.get_stats()
+
+def run_alone(programs: List[Tuple[str, List[str]]], region_size: int = 256,
+              bases: Optional[List[int]] = None) -> List[Dict]:
+    """Each program run by itself on its own fresh caches, with its data at the same base as in the workload"""
+    bases = bases or [pid * region_size for pid in range(len(programs))]
+    return [run_workload([program], 0, region_size, [base])['processes'][0]
+            for program, base in zip(programs, bases)]
+
+def format_workload(stats: Dict, alone: Optional[List[Dict]] = None) -> List[str]:
+    """One row per program: its data base, instructions and L1 hits and misses, and the misses the others cost it"""
+    lines = [f"{'program':<24} {'base':>5} {'instrs':>7} {'hits':>6} {'misses':>6} {'hit rate':>8}"
+             + (f" {'alone':>6} {'extra':>6}" if alone else "")]
+    for pid, p in enumerate(stats['processes']):
+        accesses = p['cache_hits'] + p['cache_misses']
+        rate = f"{p['cache_hits'] / accesses:.0%}" if accesses else "-"
+        row = (f"{p['name'][:24]:<24} {p['data_base']:>5} {p['instructions']:>7} {p['cache_hits']:>6} "
+               f"{p['cache_misses']:>6} {rate:>8}")
+        if alone:
+            row += f" {alone[pid]['cache_misses']:>6} {p['cache_misses'] - alone[pid]['cache_misses']:>+6}"
+        lines.append(row)
+    lines.append(f"Context switches: {stats['context_switches']}")
+    return lines
+
+def _bases(text: str) -> List[int]:
+    try:
+        return [int(base, 0) for base in text.split(',')]
+    except ValueError:
+        raise argparse.ArgumentTypeError(f"{text} is not a comma-separated list of addresses") from None
+
+def main():
+    parser = argparse.ArgumentParser(description="Run several programs against one shared cache hierarchy, "
+                                                 "interleaved or back to back, and show each program's L1 hits "
+                                                 "and misses next to a run of it alone")
+    parser.add_argument('programs', nargs='+', help="Assembly program files; - reads one from standard input")
+    parser.add_argument('--quantum', type=int, default=8,
+                        help="Instructions between context switches (default 8)")
+    parser.add_argument('--back-to-back', action='store_true',
+                        help="Run each program to completion in turn instead of interleaving them")
+    parser.add_argument('--region-size', type=int, default=256, help="Words of data per program (default 256)")
+    parser.add_argument('--bases', type=_bases,
+                        help="Comma-separated data base address of each program (default one region after another)")
+    args = parser.parse_args()
+    if args.quantum < 1 or args.region_size < 1:
+        parser.error("the quantum and the region size must be at least 1")
+    check_stdin_once(parser, args.programs)
+
+    programs = []
+    for path in args.programs:
+        try:
+            programs.append((os.path.basename(display_name(path)), read_lines(path)))
+        except OSError as e:
+            print(f"Cannot read {path}: {e.strerror}")
+            return 1
+    quantum = 0 if args.back_to_back else args.quantum
+    try:
+        stats = run_workload(programs, quantum, args.region_size, args.bases)
+        alone = run_alone(programs, args.region_size, args.bases)
+    except ValueError as e:
+        parser.error(str(e))
+
+    print("Back to back" if args.back_to_back else f"Interleaved every {args.quantum} instructions")
+    print('\n'.join(format_workload(stats, alone)))
+    return 0
+
+if __name__ == '__main__':
+    sys.exit(main())